@@ -1,8 +1,9 @@
- //! language-level attributes for futures-net.
- 
+//! language-level attributes for futures-net.
+
 extern crate proc_macro;
 
 use proc_macro::TokenStream;
+use proc_macro2::Span;
 use quote::quote;
 
 /// Marks async function  for futures_net.
@@ -15,16 +16,37 @@ use quote::quote;
 ///     Ok(())
 /// }
 /// ```
-
+///
+/// `main` may return any type implementing `std::process::Termination`.
+/// If the runtime can't be started or its reactor fails, the error is
+/// printed and the process exits with status 1.
+///
+/// The runtime can be configured with the `flavor` and `worker_threads`
+/// arguments:
+///
+/// ```ignore
+/// #[futures_net::main(flavor = "multi_thread", worker_threads = 4)]
+/// async fn main() {}
+///
+/// #[futures_net::main(flavor = "current_thread")]
+/// async fn main() {}
+/// ```
+///
+/// Any other `Runtime` implementation can be used by passing an expression
+/// creating it as `runtime`:
+///
+/// ```ignore
+/// #[futures_net::main(runtime = "my_crate::my_runtime()")]
+/// async fn main() {}
+/// ```
 #[proc_macro_attribute]
-#[cfg(not(test))] // Work around for rust-lang/rust#62127
-pub fn main(_: TokenStream, item: TokenStream) -> TokenStream {
+pub fn main(args: TokenStream, item: TokenStream) -> TokenStream {
+    let args = syn::parse_macro_input!(args as syn::AttributeArgs);
     let mut input = syn::parse_macro_input!(item as syn::ItemFn);
     let attrs = &input.attrs;
     let vis = &input.vis;
     let sig = &mut input.sig;
     let body = &input.block;
-    let name = &sig.ident;
 
     if sig.asyncness.is_none() {
         return syn::Error::new_spanned(sig.fn_token, "only async fn is supported")
@@ -32,12 +54,18 @@ pub fn main(_: TokenStream, item: TokenStream) -> TokenStream {
             .into();
     }
 
+    let config = match Config::parse(args, &["flavor", "worker_threads", "runtime"]) {
+        Ok(config) => config,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    let rt = config.runtime();
+
     sig.asyncness = None;
 
     (quote! {
         #(#attrs)*
         #vis #sig {
-            futures_net::runtime::default().exec(async move { #body })
+            futures_net::runtime::__private::block_on_main(#rt, async move { #body })
         }
     })
     .into()
@@ -47,14 +75,60 @@ pub fn main(_: TokenStream, item: TokenStream) -> TokenStream {
 ///
 /// ## Usage
 ///
-/// ```no_run
+/// ```ignore
 /// #[futures_net::test]
 /// async fn my_test() {
 ///     assert!(true);
 /// }
 /// ```
+///
+/// A `timeout` makes the test panic if it hasn't completed in time. Units
+/// are `ms`, `s` and `m`:
+///
+/// ```ignore
+/// #[futures_net::test(timeout = "30s")]
+/// async fn my_test() {
+///     assert!(true);
+/// }
+/// ```
+///
+/// Tests run on a `current_thread` runtime unless configured otherwise,
+/// with the same `flavor`, `worker_threads` and `runtime` arguments as
+/// `main`:
+///
+/// ```ignore
+/// #[futures_net::test(flavor = "multi_thread", worker_threads = 2)]
+/// async fn my_test() {
+///     let task = futures_net::spawn(async { 42 });
+///     assert_eq!(task.await.unwrap(), 42);
+/// }
+/// ```
+///
+/// `start_paused = true` runs the test with time paused, so sleeps complete
+/// as soon as nothing else can make progress. It requires the
+/// `current_thread` flavor:
+///
+/// ```ignore
+/// #[futures_net::test(start_paused = true)]
+/// async fn my_test() {
+///     futures_net::time::sleep(std::time::Duration::from_secs(3600)).await;
+/// }
+/// ```
+///
+/// `schedule_seed = <integer>` runs the spawned tasks in an order derived
+/// from the seed, so an interleaving a failure was found with can be
+/// replayed. It also requires the `current_thread` flavor:
+///
+/// ```ignore
+/// #[futures_net::test(start_paused = true, schedule_seed = 42)]
+/// async fn my_test() {
+///     let task = futures_net::spawn(async { 42 });
+///     assert_eq!(task.await.unwrap(), 42);
+/// }
+/// ```
 #[proc_macro_attribute]
-pub fn test(_: TokenStream, item: TokenStream) -> TokenStream {
+pub fn test(args: TokenStream, item: TokenStream) -> TokenStream {
+    let args = syn::parse_macro_input!(args as syn::AttributeArgs);
     let input = syn::parse_macro_input!(item as syn::ItemFn);
 
     let ret = &input.sig.output;
@@ -78,22 +152,387 @@ pub fn test(_: TokenStream, item: TokenStream) -> TokenStream {
         .into();
     }
 
-    let result = if has_test_attr {
-        quote! {
-            #(#attrs)*
-            fn #name() #ret {
-                futures_net::runtime::default().exec(async { #body })
+    let config = match Config::parse(
+        args,
+        &[
+            "flavor",
+            "worker_threads",
+            "runtime",
+            "timeout",
+            "start_paused",
+            "schedule_seed",
+        ],
+    ) {
+        Ok(config) => config,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    let rt = config.runtime();
+
+    let body = match config.timeout_ms {
+        Some(ms) => quote! {
+            futures_net::runtime::__private::with_timeout(
+                std::time::Duration::from_millis(#ms),
+                async { #body },
+            )
+        },
+        None => quote! { async { #body } },
+    };
+
+    let test_attr = if has_test_attr {
+        None
+    } else {
+        Some(quote! { #[test] })
+    };
+
+    let result = quote! {
+        #test_attr
+        #(#attrs)*
+        fn #name() #ret {
+            let mut rt = #rt.expect("failed to build the futures-net runtime");
+            futures_net::runtime::Runtime::exec(&mut rt, #body)
+        }
+    };
+
+    result.into()
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Flavor {
+    CurrentThread,
+    MultiThread,
+}
+
+/// Runtime configuration given as attribute arguments.
+#[derive(Default)]
+struct Config {
+    flavor: Option<Flavor>,
+    runtime: Option<syn::Expr>,
+    worker_threads: Option<usize>,
+    timeout_ms: Option<u64>,
+    start_paused: bool,
+    schedule_seed: Option<u64>,
+}
+
+impl Config {
+    /// Parses `args`, accepting only the argument names in `allowed`.
+    fn parse(args: syn::AttributeArgs, allowed: &[&str]) -> Result<Config, syn::Error> {
+        let mut config = Config::default();
+        let mut worker_threads_span = None;
+        let mut runtime_span = None;
+        let mut start_paused_span = None;
+        let mut schedule_seed_span = None;
+
+        for arg in args {
+            let nv = match arg {
+                syn::NestedMeta::Meta(syn::Meta::NameValue(nv)) => nv,
+                other => {
+                    return Err(syn::Error::new_spanned(
+                        other,
+                        "expected an argument of the form `name = value`",
+                    ))
+                }
+            };
+            let name = nv
+                .path
+                .get_ident()
+                .map(|ident| ident.to_string())
+                .unwrap_or_default();
+
+            if !allowed.contains(&name.as_str()) {
+                let expected = allowed
+                    .iter()
+                    .map(|name| format!("`{}`", name))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                return Err(syn::Error::new_spanned(
+                    &nv.path,
+                    format!("unknown argument, expected one of {}", expected),
+                ));
+            }
+
+            match name.as_str() {
+                "flavor" => {
+                    let flavor = match lit_str(&nv.lit)?.as_str() {
+                        "current_thread" => Flavor::CurrentThread,
+                        "multi_thread" => Flavor::MultiThread,
+                        _ => {
+                            return Err(syn::Error::new_spanned(
+                                &nv.lit,
+                                "expected `current_thread` or `multi_thread`",
+                            ))
+                        }
+                    };
+                    config.flavor = Some(flavor);
+                }
+                "worker_threads" => {
+                    let n = match &nv.lit {
+                        syn::Lit::Int(n) => n.base10_parse::<usize>()?,
+                        lit => {
+                            return Err(syn::Error::new_spanned(
+                                lit,
+                                "expected an integer",
+                            ))
+                        }
+                    };
+                    if n == 0 {
+                        return Err(syn::Error::new_spanned(
+                            &nv.lit,
+                            "`worker_threads` may not be 0",
+                        ));
+                    }
+                    config.worker_threads = Some(n);
+                    worker_threads_span = Some(nv.lit.span());
+                }
+                "runtime" => {
+                    let expr = match &nv.lit {
+                        syn::Lit::Str(s) => s.parse::<syn::Expr>()?,
+                        lit => {
+                            return Err(syn::Error::new_spanned(
+                                lit,
+                                "expected an expression in a string literal",
+                            ))
+                        }
+                    };
+                    runtime_span = Some(nv.lit.span());
+                    config.runtime = Some(expr);
+                }
+                "timeout" => {
+                    let ms = parse_duration_ms(&lit_str(&nv.lit)?).ok_or_else(|| {
+                        syn::Error::new_spanned(
+                            &nv.lit,
+                            "expected a duration such as \"500ms\", \"30s\" or \"2m\"",
+                        )
+                    })?;
+                    config.timeout_ms = Some(ms);
+                }
+                "start_paused" => {
+                    config.start_paused = match &nv.lit {
+                        syn::Lit::Bool(b) => b.value,
+                        lit => {
+                            return Err(syn::Error::new_spanned(
+                                lit,
+                                "expected a boolean",
+                            ))
+                        }
+                    };
+                    start_paused_span = Some(nv.lit.span());
+                }
+                "schedule_seed" => {
+                    let seed = match &nv.lit {
+                        syn::Lit::Int(n) => n.base10_parse::<u64>()?,
+                        lit => {
+                            return Err(syn::Error::new_spanned(
+                                lit,
+                                "expected an integer",
+                            ))
+                        }
+                    };
+                    config.schedule_seed = Some(seed);
+                    schedule_seed_span = Some(nv.lit.span());
+                }
+                _ => unreachable!(),
             }
         }
-    } else {
-        quote! {
-            #[test]
-            #(#attrs)*
-            fn #name() #ret {
-                futures_net::runtime::default().exec(async { #body })
+
+        if config.runtime.is_some()
+            && (config.flavor.is_some() || config.worker_threads.is_some())
+        {
+            return Err(syn::Error::new(
+                runtime_span.unwrap_or_else(Span::call_site),
+                "`runtime` can't be combined with `flavor` or `worker_threads`",
+            ));
+        }
+
+        if config.start_paused
+            && (config.runtime.is_some() || config.flavor == Some(Flavor::MultiThread))
+        {
+            return Err(syn::Error::new(
+                start_paused_span.unwrap_or_else(Span::call_site),
+                "`start_paused` requires `flavor = \"current_thread\"`",
+            ));
+        }
+
+        if config.schedule_seed.is_some()
+            && (config.runtime.is_some() || config.flavor == Some(Flavor::MultiThread))
+        {
+            return Err(syn::Error::new(
+                schedule_seed_span.unwrap_or_else(Span::call_site),
+                "`schedule_seed` requires `flavor = \"current_thread\"`",
+            ));
+        }
+
+        if config.worker_threads.is_some() && config.flavor != Some(Flavor::MultiThread)
+        {
+            return Err(syn::Error::new(
+                worker_threads_span.unwrap_or_else(Span::call_site),
+                "`worker_threads` requires `flavor = \"multi_thread\"`",
+            ));
+        }
+
+        Ok(config)
+    }
+
+    /// Expression building the configured runtime, as an `io::Result`.
+    fn runtime(&self) -> proc_macro2::TokenStream {
+        if let Some(expr) = &self.runtime {
+            return quote! { std::io::Result::Ok(#expr) };
+        }
+
+        match self.flavor {
+            None | Some(Flavor::CurrentThread)
+                if self.start_paused || self.schedule_seed.is_some() =>
+            {
+                let start_paused = if self.start_paused {
+                    Some(quote! { .start_paused(true) })
+                } else {
+                    None
+                };
+                let schedule_seed = self.schedule_seed.map(|seed| {
+                    quote! { .schedule_seed(#seed) }
+                });
+                quote! {
+                    futures_net::runtime::Builder::new_current_thread()
+                        #start_paused
+                        #schedule_seed
+                        .build()
+                }
+            }
+            None => quote! { futures_net::runtime::try_default() },
+            Some(Flavor::CurrentThread) => quote! {
+                futures_net::runtime::Builder::new_current_thread().build()
+            },
+            Some(Flavor::MultiThread) => {
+                let worker_threads = self.worker_threads.map(|n| {
+                    quote! { .worker_threads(#n) }
+                });
+                quote! {
+                    futures_net::runtime::Builder::new_multi_thread()
+                        #worker_threads
+                        .build()
+                }
             }
         }
+    }
+}
+
+fn lit_str(lit: &syn::Lit) -> Result<String, syn::Error> {
+    match lit {
+        syn::Lit::Str(s) => Ok(s.value()),
+        _ => Err(syn::Error::new_spanned(lit, "expected a string literal")),
+    }
+}
+
+/// Parses durations of the form `<integer><unit>` with `ms`, `s` or `m` units.
+fn parse_duration_ms(s: &str) -> Option<u64> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit())?;
+    let (n, unit) = s.split_at(split);
+    let n: u64 = n.parse().ok()?;
+    let scale = match unit.trim() {
+        "ms" => 1,
+        "s" => 1_000,
+        "m" => 60_000,
+        _ => return None,
     };
+    n.checked_mul(scale).filter(|&ms| ms > 0)
+}
 
-    result.into()
+// Tests live in a module, at the crate root `#[test]` is the attribute
+// defined above.
+#[cfg(test)]
+mod tests {
+    use super::{parse_duration_ms, Config, Flavor};
+
+    fn parse_config(args: &str) -> Result<Config, syn::Error> {
+        use syn::parse::Parser;
+        use syn::punctuated::Punctuated;
+
+        let args = Punctuated::<syn::NestedMeta, syn::Token![,]>::parse_terminated
+            .parse_str(args)
+            .unwrap();
+        Config::parse(
+            args.into_iter().collect(),
+            &[
+                "flavor",
+                "worker_threads",
+                "runtime",
+                "timeout",
+                "start_paused",
+                "schedule_seed",
+            ],
+        )
+    }
+
+    fn config_error(args: &str) -> String {
+        match parse_config(args) {
+            Ok(_) => panic!("`{}` was accepted", args),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_valid_arguments() {
+        let config =
+            parse_config(r#"flavor = "multi_thread", worker_threads = 4"#).unwrap();
+        assert!(config.flavor == Some(Flavor::MultiThread));
+        assert_eq!(config.worker_threads, Some(4));
+
+        let config = parse_config(
+            r#"flavor = "current_thread", start_paused = true, schedule_seed = 42, timeout = "2m""#,
+        )
+        .unwrap();
+        assert!(config.flavor == Some(Flavor::CurrentThread));
+        assert!(config.start_paused);
+        assert_eq!(config.schedule_seed, Some(42));
+        assert_eq!(config.timeout_ms, Some(120_000));
+
+        let config = parse_config(r#"runtime = "my_crate::my_runtime()""#).unwrap();
+        assert!(config.runtime.is_some());
+    }
+
+    #[test]
+    fn test_parse_rejects_conflicting_arguments() {
+        assert!(
+            config_error(r#"flavor = "multi_thread", schedule_seed = 1"#)
+                .contains("`schedule_seed` requires")
+        );
+        assert!(
+            config_error(r#"flavor = "multi_thread", start_paused = true"#)
+                .contains("`start_paused` requires")
+        );
+        assert!(
+            config_error(r#"flavor = "current_thread", worker_threads = 2"#)
+                .contains("`worker_threads` requires")
+        );
+        assert!(config_error("worker_threads = 2").contains("`worker_threads` requires"));
+        assert!(
+            config_error(r#"runtime = "rt()", flavor = "current_thread""#)
+                .contains("`runtime` can't be combined")
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_malformed_arguments() {
+        assert!(config_error(r#"timeout = "soon""#).contains("expected a duration"));
+        assert!(config_error(r#"timeout = "0s""#).contains("expected a duration"));
+        assert!(
+            config_error(r#"flavor = "threaded""#).contains("expected `current_thread`")
+        );
+        assert!(
+            config_error(r#"flavor = "multi_thread", worker_threads = 0"#)
+                .contains("may not be 0")
+        );
+        assert!(config_error("start_paused = 1").contains("expected a boolean"));
+        assert!(config_error("threads = 2").contains("unknown argument"));
+        assert!(config_error("flavor").contains("`name = value`"));
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration_ms("500ms"), Some(500));
+        assert_eq!(parse_duration_ms(" 30s "), Some(30_000));
+        assert_eq!(parse_duration_ms("2m"), Some(120_000));
+        assert_eq!(parse_duration_ms("2h"), None);
+        assert_eq!(parse_duration_ms("ms"), None);
+    }
 }
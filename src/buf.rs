@@ -0,0 +1,352 @@
+//! Pooled buffers for the read paths.
+//!
+//! A [`BufferPool`] hands out [`PooledBuf`]s from a few size classes and
+//! takes them back when they are dropped, so a server reading into a fresh
+//! buffer per message stops allocating once the pool is warm. A filled
+//! buffer can be [frozen] into a `Bytes`, handed to application code and
+//! sliced without copying; it goes back to the pool when the last `Bytes`
+//! referring to it is dropped.
+//!
+//! # Examples
+//!
+//! ```
+//! use futures_net::buf::{self, BufferPool};
+//! use futures_net::runtime::{self, Runtime};
+//!
+//! let pool = BufferPool::new();
+//! let mut rt = runtime::default();
+//!
+//! let mut input: &[u8] = b"GET / HTTP/1.1\r\n\r\n";
+//! let bytes = rt.exec(async {
+//!     let mut buf = pool.get(4096);
+//!     buf::read_buf(&mut input, &mut buf).await.unwrap();
+//!     buf.freeze()
+//! });
+//! assert_eq!(&bytes[..3], b"GET");
+//!
+//! drop(bytes);
+//! assert_eq!(pool.free_buffers(), 1);
+//! ```
+//!
+//! [`BufferPool`]: struct.BufferPool.html
+//! [`PooledBuf`]: struct.PooledBuf.html
+//! [frozen]: struct.PooledBuf.html#method.freeze
+
+use bytes::Bytes;
+use futures_io::AsyncRead;
+use futures_util::future::poll_fn;
+use parking_lot::Mutex;
+use std::fmt;
+use std::io;
+use std::ops::{Deref, DerefMut};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+/// The size classes of `BufferPool::new`, from 512 bytes to 64 KiB.
+const DEFAULT_CLASSES: [usize; 8] = [
+    512,
+    1024,
+    2 * 1024,
+    4 * 1024,
+    8 * 1024,
+    16 * 1024,
+    32 * 1024,
+    64 * 1024,
+];
+
+/// The free buffers `BufferPool::new` keeps per size class.
+const DEFAULT_MAX_FREE: usize = 64;
+
+/// A pool of reusable buffers in a few size classes.
+///
+/// A request is served from the smallest class holding it, with a free
+/// buffer of that class if there is one. Requests larger than the largest
+/// class are allocated on their own and never pooled. Each class keeps a
+/// bounded number of free buffers, the others are deallocated.
+///
+/// The pool is cheap to clone, clones share their buffers.
+#[derive(Clone)]
+pub struct BufferPool {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    classes: Vec<Class>,
+    max_free: usize,
+}
+
+struct Class {
+    size: usize,
+    /// Buffers of `size` initialized bytes.
+    free: Mutex<Vec<Vec<u8>>>,
+}
+
+/// A buffer borrowed from a [`BufferPool`].
+///
+/// The buffer has a fixed capacity, the size class it was taken from, and
+/// dereferences to the bytes filled so far. It is returned to its pool
+/// when dropped, or when the `Bytes` it was frozen into are.
+///
+/// [`BufferPool`]: struct.BufferPool.html
+pub struct PooledBuf {
+    /// Initialized up to its length, which is the capacity of the buffer.
+    data: Vec<u8>,
+    filled: usize,
+    /// The pool and the index of the class to return `data` to.
+    home: Option<(Arc<Shared>, usize)>,
+}
+
+impl BufferPool {
+    /// Creates a pool of buffers from 512 bytes to 64 KiB, in power of two
+    /// sizes, keeping up to 64 free buffers of each size.
+    pub fn new() -> BufferPool {
+        BufferPool::with_classes(&DEFAULT_CLASSES, DEFAULT_MAX_FREE)
+    }
+
+    /// Creates a pool of buffers of the given `sizes`, keeping up to
+    /// `max_free` free buffers of each size.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a size is zero.
+    pub fn with_classes(sizes: &[usize], max_free: usize) -> BufferPool {
+        assert!(sizes.iter().all(|&size| size > 0), "empty size class");
+        let mut sizes = sizes.to_vec();
+        sizes.sort_unstable();
+        sizes.dedup();
+
+        let classes = sizes
+            .into_iter()
+            .map(|size| Class {
+                size,
+                free: Mutex::new(Vec::new()),
+            })
+            .collect();
+        BufferPool {
+            shared: Arc::new(Shared { classes, max_free }),
+        }
+    }
+
+    /// Takes an empty buffer with room for at least `capacity` bytes.
+    pub fn get(&self, capacity: usize) -> PooledBuf {
+        let classes = &self.shared.classes;
+        let index = match classes.iter().position(|class| class.size >= capacity) {
+            Some(index) => index,
+            None => {
+                return PooledBuf {
+                    data: vec![0; capacity],
+                    filled: 0,
+                    home: None,
+                }
+            }
+        };
+
+        let class = &classes[index];
+        let data = class
+            .free
+            .lock()
+            .pop()
+            .unwrap_or_else(|| vec![0; class.size]);
+        PooledBuf {
+            data,
+            filled: 0,
+            home: Some((self.shared.clone(), index)),
+        }
+    }
+
+    /// Returns the number of free buffers held by the pool.
+    pub fn free_buffers(&self) -> usize {
+        self.shared
+            .classes
+            .iter()
+            .map(|class| class.free.lock().len())
+            .sum()
+    }
+}
+
+impl Default for BufferPool {
+    fn default() -> BufferPool {
+        BufferPool::new()
+    }
+}
+
+impl fmt::Debug for BufferPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sizes: Vec<_> = self.shared.classes.iter().map(|c| c.size).collect();
+        f.debug_struct("BufferPool")
+            .field("classes", &sizes)
+            .field("free_buffers", &self.free_buffers())
+            .finish()
+    }
+}
+
+impl PooledBuf {
+    /// Returns the number of bytes the buffer can hold.
+    pub fn capacity(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Returns the number of bytes which can still be filled.
+    pub fn remaining(&self) -> usize {
+        self.data.len() - self.filled
+    }
+
+    /// Returns the part of the buffer which is not filled yet.
+    ///
+    /// Bytes written there are added to the buffer by [`advance`].
+    ///
+    /// [`advance`]: #method.advance
+    pub fn unfilled_mut(&mut self) -> &mut [u8] {
+        &mut self.data[self.filled..]
+    }
+
+    /// Marks `n` more bytes of the unfilled part as filled.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is larger than [`remaining`].
+    ///
+    /// [`remaining`]: #method.remaining
+    pub fn advance(&mut self, n: usize) {
+        assert!(n <= self.remaining(), "advanced past the capacity");
+        self.filled += n;
+    }
+
+    /// Appends `src` to the filled bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `src` doesn't fit in the buffer.
+    pub fn extend_from_slice(&mut self, src: &[u8]) {
+        self.unfilled_mut()[..src.len()].copy_from_slice(src);
+        self.filled += src.len();
+    }
+
+    /// Shortens the filled bytes to `len`, doing nothing if there are fewer.
+    pub fn truncate(&mut self, len: usize) {
+        self.filled = self.filled.min(len);
+    }
+
+    /// Empties the buffer.
+    pub fn clear(&mut self) {
+        self.filled = 0;
+    }
+
+    /// Converts the filled bytes into `Bytes` without copying them.
+    ///
+    /// The buffer goes back to its pool once the returned `Bytes`, and all
+    /// the clones and slices made of it, are dropped.
+    pub fn freeze(self) -> Bytes {
+        Bytes::from_owner(self)
+    }
+
+    /// Attempts to read into the unfilled part of the buffer, like
+    /// `AsyncRead::poll_read`, and marks the bytes read as filled.
+    ///
+    /// Returns `Ready(Ok(0))` at the end of the stream, or right away if
+    /// the buffer is full.
+    pub fn poll_read_from<R>(
+        &mut self,
+        reader: Pin<&mut R>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<usize>>
+    where
+        R: AsyncRead + ?Sized,
+    {
+        if self.remaining() == 0 {
+            return Poll::Ready(Ok(0));
+        }
+        let poll = reader.poll_read(cx, self.unfilled_mut());
+        if let Poll::Ready(Ok(n)) = poll {
+            self.advance(n);
+        }
+        poll
+    }
+}
+
+impl Deref for PooledBuf {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.data[..self.filled]
+    }
+}
+
+impl DerefMut for PooledBuf {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.data[..self.filled]
+    }
+}
+
+impl AsRef<[u8]> for PooledBuf {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl Drop for PooledBuf {
+    fn drop(&mut self) {
+        if let Some((shared, index)) = self.home.take() {
+            let mut free = shared.classes[index].free.lock();
+            if free.len() < shared.max_free {
+                free.push(std::mem::take(&mut self.data));
+            }
+        }
+    }
+}
+
+impl fmt::Debug for PooledBuf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PooledBuf")
+            .field("len", &self.filled)
+            .field("capacity", &self.capacity())
+            .finish()
+    }
+}
+
+/// Reads from `reader` into the unfilled part of `buf`, returning the
+/// number of bytes read.
+///
+/// Reading into a buffer taken from a [`BufferPool`] rather than into a
+/// fresh `Vec` is what saves the allocation per read.
+///
+/// [`BufferPool`]: struct.BufferPool.html
+pub async fn read_buf<R>(reader: &mut R, buf: &mut PooledBuf) -> io::Result<usize>
+where
+    R: AsyncRead + Unpin + ?Sized,
+{
+    poll_fn(|cx| buf.poll_read_from(Pin::new(&mut *reader), cx)).await
+}
+
+#[test]
+fn test_buffers_are_reused_by_size_class() {
+    let pool = BufferPool::with_classes(&[1024, 256], 1);
+
+    let small = pool.get(100);
+    assert_eq!(small.capacity(), 256);
+    let ptr = small.as_ptr();
+    drop(small);
+
+    // Bytes left in a reused buffer are not visible, and a frozen buffer
+    // only comes back once every slice of it is gone.
+    let mut buf = pool.get(200);
+    assert_eq!(buf.as_ptr(), ptr);
+    assert!(buf.is_empty());
+    buf.extend_from_slice(b"hello world");
+    let bytes = buf.freeze();
+    let hello = bytes.slice(..5);
+    drop(bytes);
+    assert_eq!(pool.free_buffers(), 0);
+    assert_eq!(&hello[..], b"hello");
+    drop(hello);
+    assert_eq!(pool.free_buffers(), 1);
+
+    // Classes keep at most `max_free` buffers, and oversized requests are
+    // never pooled.
+    let (a, b) = (pool.get(256), pool.get(256));
+    drop((a, b));
+    assert_eq!(pool.free_buffers(), 1);
+    assert_eq!(pool.get(4096).capacity(), 4096);
+    assert_eq!(pool.free_buffers(), 1);
+}
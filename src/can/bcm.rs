@@ -0,0 +1,230 @@
+use futures_util::ready;
+use std::fmt;
+use std::io;
+use std::mem;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use super::CanFrame;
+use crate::driver::sys;
+use crate::driver::PollEvented;
+
+// Opcodes and flags from `linux/can/bcm.h`.
+const TX_SETUP: u32 = 1;
+const TX_DELETE: u32 = 2;
+const RX_SETUP: u32 = 5;
+const RX_DELETE: u32 = 6;
+const RX_TIMEOUT: u32 = 11;
+const RX_CHANGED: u32 = 12;
+const SETTIMER: u32 = 0x0001;
+const STARTTIMER: u32 = 0x0002;
+const RX_FILTER_ID: u32 = 0x0020;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct bcm_timeval {
+    tv_sec: libc::c_long,
+    tv_usec: libc::c_long,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct bcm_msg_head {
+    opcode: u32,
+    flags: u32,
+    count: u32,
+    ival1: bcm_timeval,
+    ival2: bcm_timeval,
+    can_id: u32,
+    nframes: u32,
+}
+
+/// A message with room for a single frame, which is all this module uses.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct BcmMsg {
+    head: bcm_msg_head,
+    frame: CanFrame,
+}
+
+/// Length of a message carrying `nframes` frames.
+fn msg_len(nframes: u32) -> usize {
+    let head = mem::size_of::<BcmMsg>() - mem::size_of::<CanFrame>();
+    head + nframes as usize * mem::size_of::<CanFrame>()
+}
+
+/// A socket to the CAN broadcast manager.
+pub struct BcmSocket {
+    io: PollEvented<sys::net::CanSocket>,
+}
+
+/// A notification of the broadcast manager.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BcmEvent {
+    /// A watched identifier was received with new content, or for the first
+    /// time.
+    Changed(CanFrame),
+    /// A watched identifier wasn't received within its timeout.
+    Timeout(u32),
+    /// Another message, identified by its opcode.
+    Other(u32),
+}
+
+impl BcmSocket {
+    /// Opens a broadcast manager socket for the interface called `name`.
+    pub fn open(name: &str) -> io::Result<BcmSocket> {
+        let ifindex = sys::net::interface_index(name)?;
+        let socket = sys::net::CanSocket::bcm(ifindex)?;
+        Ok(BcmSocket {
+            io: PollEvented::new(socket),
+        })
+    }
+
+    /// Sends `frame` every `interval`, replacing the previous periodic
+    /// frame with the same identifier.
+    pub fn send_periodic(&self, frame: &CanFrame, interval: Duration) -> io::Result<()> {
+        let mut msg = message(TX_SETUP, frame.raw_id(), SETTIMER | STARTTIMER);
+        msg.head.ival2 = timeval(interval);
+        msg.head.nframes = 1;
+        msg.frame = *frame;
+        self.send(&msg)
+    }
+
+    /// Stops sending the periodic frame with the raw identifier of `frame`.
+    pub fn stop_periodic(&self, frame: &CanFrame) -> io::Result<()> {
+        self.send(&message(TX_DELETE, frame.raw_id(), 0))
+    }
+
+    /// Watches the identifier `id`, reporting changes of its content as
+    /// [`BcmEvent::Changed`], and its absence for longer than `timeout` as
+    /// [`BcmEvent::Timeout`].
+    ///
+    /// Extended identifiers must carry the `CAN_EFF_FLAG` bit
+    /// (`0x8000_0000`).
+    ///
+    /// [`BcmEvent::Changed`]: enum.BcmEvent.html#variant.Changed
+    /// [`BcmEvent::Timeout`]: enum.BcmEvent.html#variant.Timeout
+    pub fn watch(&self, id: u32, timeout: Option<Duration>) -> io::Result<()> {
+        let mut flags = RX_FILTER_ID;
+        let mut msg = message(RX_SETUP, id, 0);
+        if let Some(timeout) = timeout {
+            flags |= SETTIMER | STARTTIMER;
+            msg.head.ival1 = timeval(timeout);
+        }
+        msg.head.flags = flags;
+        self.send(&msg)
+    }
+
+    /// Stops watching the identifier `id`.
+    pub fn unwatch(&self, id: u32) -> io::Result<()> {
+        self.send(&message(RX_DELETE, id, 0))
+    }
+
+    fn send(&self, msg: &BcmMsg) -> io::Result<()> {
+        // Safety: the message is plain old data, and the length doesn't
+        // exceed it.
+        let buf = unsafe {
+            std::slice::from_raw_parts(
+                msg as *const BcmMsg as *const u8,
+                msg_len(msg.head.nframes),
+            )
+        };
+        self.io.get_ref().send(buf).map(|_| ())
+    }
+
+    /// Receives a notification, see [`recv`].
+    ///
+    /// [`recv`]: #method.recv
+    pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<BcmEvent>> {
+        ready!(Pin::new(&mut self.io).poll_read_ready(cx)?);
+
+        let mut msg = message(0, 0, 0);
+        // Safety: any bytes are a valid message.
+        let buf = unsafe {
+            std::slice::from_raw_parts_mut(
+                &mut msg as *mut BcmMsg as *mut u8,
+                mem::size_of::<BcmMsg>(),
+            )
+        };
+        match self.io.get_ref().recv(buf) {
+            Ok(n) => Poll::Ready(decode(&msg, n)),
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                Pin::new(&mut self.io).clear_read_ready(cx)?;
+                Poll::Pending
+            }
+            Err(e) => Poll::Ready(Err(e)),
+        }
+    }
+
+    /// Receives a notification about the watched identifiers.
+    pub async fn recv(&mut self) -> io::Result<BcmEvent> {
+        futures_util::future::poll_fn(|cx| self.poll_recv(cx)).await
+    }
+}
+
+fn message(opcode: u32, can_id: u32, flags: u32) -> BcmMsg {
+    let mut msg: BcmMsg = unsafe { mem::zeroed() };
+    msg.head.opcode = opcode;
+    msg.head.flags = flags;
+    msg.head.can_id = can_id;
+    msg
+}
+
+fn timeval(dur: Duration) -> bcm_timeval {
+    bcm_timeval {
+        tv_sec: dur.as_secs() as libc::c_long,
+        tv_usec: dur.subsec_micros() as libc::c_long,
+    }
+}
+
+fn decode(msg: &BcmMsg, len: usize) -> io::Result<BcmEvent> {
+    if len < msg_len(0) || len < msg_len(msg.head.nframes.min(1)) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "truncated broadcast manager message",
+        ));
+    }
+    Ok(match msg.head.opcode {
+        RX_CHANGED if msg.head.nframes >= 1 => BcmEvent::Changed(msg.frame),
+        RX_TIMEOUT => BcmEvent::Timeout(msg.head.can_id),
+        opcode => BcmEvent::Other(opcode),
+    })
+}
+
+impl fmt::Debug for BcmSocket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.io.get_ref().fmt(f)
+    }
+}
+
+impl AsRawFd for BcmSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.io.get_ref().as_raw_fd()
+    }
+}
+
+#[test]
+fn test_message_layout() {
+    // `linux/can/bcm.h` on 64-bit targets: 56 bytes of header, then the
+    // frames.
+    if mem::size_of::<libc::c_long>() == 8 {
+        assert_eq!(msg_len(0), 56);
+    }
+    assert_eq!(mem::size_of::<CanFrame>(), 16);
+
+    let frame = CanFrame::new(0x1234_5678 & 0x1fff_ffff, &[1, 2, 3]).unwrap();
+    assert!(frame.is_extended());
+    assert_eq!(frame.data(), &[1, 2, 3]);
+    assert!(CanFrame::new(0x100, &[0; 9]).is_none());
+
+    let mut msg = message(RX_CHANGED, frame.raw_id(), 0);
+    msg.head.nframes = 1;
+    msg.frame = frame;
+    assert_eq!(decode(&msg, msg_len(1)).unwrap(), BcmEvent::Changed(frame));
+    assert!(decode(&msg, msg_len(0)).is_err());
+
+    let msg = message(RX_TIMEOUT, 0x7e8, 0);
+    assert_eq!(decode(&msg, msg_len(0)).unwrap(), BcmEvent::Timeout(0x7e8));
+}
@@ -0,0 +1,159 @@
+//! SocketCAN, the Linux interface to CAN buses.
+//!
+//! A [`CanSocket`] reads and writes raw frames on a CAN interface, such as
+//! `can0` or a virtual `vcan0`, optionally filtered by identifier in the
+//! kernel. A [`BcmSocket`] talks to the broadcast manager, which sends
+//! frames periodically and watches identifiers for content changes without
+//! waking the process for every frame.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use futures_net::can::{CanFilter, CanFrame, CanSocket};
+//!
+//! # async fn run() -> std::io::Result<()> {
+//! let mut socket = CanSocket::open("can0")?;
+//! socket.set_filters(&[CanFilter::new(0x100, 0x700)])?;
+//!
+//! let request = CanFrame::new(0x7df, &[0x02, 0x01, 0x0c]).unwrap();
+//! socket.write_frame(&request).await?;
+//! let response = socket.read_frame().await?;
+//! println!("{:03x}: {:?}", response.id(), response.data());
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`CanSocket`]: struct.CanSocket.html
+//! [`BcmSocket`]: struct.BcmSocket.html
+
+use std::fmt;
+
+mod bcm;
+mod raw;
+
+pub use self::bcm::{BcmEvent, BcmSocket};
+pub use self::raw::{CanSocket, ReadFrame, WriteFrame};
+
+const CAN_EFF_FLAG: u32 = 0x8000_0000;
+const CAN_RTR_FLAG: u32 = 0x4000_0000;
+const CAN_ERR_FLAG: u32 = 0x2000_0000;
+const CAN_SFF_MASK: u32 = 0x0000_07ff;
+const CAN_EFF_MASK: u32 = 0x1fff_ffff;
+
+/// A classic CAN frame (`struct can_frame`), with up to 8 bytes of data.
+#[repr(C, align(8))]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct CanFrame {
+    can_id: u32,
+    len: u8,
+    pad: u8,
+    res0: u8,
+    len8_dlc: u8,
+    data: [u8; 8],
+}
+
+/// An identifier filter for [`CanSocket::set_filters`] (`struct can_filter`).
+///
+/// A frame matches if `frame_id & mask == id & mask`.
+///
+/// [`CanSocket::set_filters`]: struct.CanSocket.html#method.set_filters
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CanFilter {
+    can_id: u32,
+    can_mask: u32,
+}
+
+impl CanFrame {
+    /// Creates a data frame with a standard identifier, if `id` fits in 11
+    /// bits and `data` in 8 bytes, or an extended one if `id` fits in 29.
+    pub fn new(id: u32, data: &[u8]) -> Option<CanFrame> {
+        let can_id = match id {
+            id if id <= CAN_SFF_MASK => id,
+            id if id <= CAN_EFF_MASK => id | CAN_EFF_FLAG,
+            _ => return None,
+        };
+        CanFrame::from_raw(can_id, data)
+    }
+
+    /// Creates a remote transmission request for `id`.
+    pub fn remote(id: u32) -> Option<CanFrame> {
+        let mut frame = CanFrame::new(id, &[])?;
+        frame.can_id |= CAN_RTR_FLAG;
+        Some(frame)
+    }
+
+    fn from_raw(can_id: u32, data: &[u8]) -> Option<CanFrame> {
+        if data.len() > 8 {
+            return None;
+        }
+        let mut frame = CanFrame {
+            can_id,
+            len: data.len() as u8,
+            pad: 0,
+            res0: 0,
+            len8_dlc: 0,
+            data: [0; 8],
+        };
+        frame.data[..data.len()].copy_from_slice(data);
+        Some(frame)
+    }
+
+    /// Returns the identifier, without the flags.
+    pub fn id(&self) -> u32 {
+        if self.is_extended() {
+            self.can_id & CAN_EFF_MASK
+        } else {
+            self.can_id & CAN_SFF_MASK
+        }
+    }
+
+    /// Returns `true` if the identifier is a 29-bit one.
+    pub fn is_extended(&self) -> bool {
+        self.can_id & CAN_EFF_FLAG != 0
+    }
+
+    /// Returns `true` for a remote transmission request.
+    pub fn is_remote(&self) -> bool {
+        self.can_id & CAN_RTR_FLAG != 0
+    }
+
+    /// Returns `true` for an error frame, whose identifier and data
+    /// describe the error (see `linux/can/error.h`).
+    pub fn is_error(&self) -> bool {
+        self.can_id & CAN_ERR_FLAG != 0
+    }
+
+    /// Returns the data of the frame.
+    pub fn data(&self) -> &[u8] {
+        &self.data[..(self.len as usize).min(8)]
+    }
+
+    /// Returns the identifier with its flags, as the kernel sees it.
+    pub(crate) fn raw_id(&self) -> u32 {
+        self.can_id
+    }
+}
+
+impl fmt::Debug for CanFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CanFrame")
+            .field("id", &self.id())
+            .field("extended", &self.is_extended())
+            .field("remote", &self.is_remote())
+            .field("error", &self.is_error())
+            .field("data", &self.data())
+            .finish()
+    }
+}
+
+impl CanFilter {
+    /// Matches the frames whose identifier equals `id` on the bits set in
+    /// `mask`.
+    pub fn new(id: u32, mask: u32) -> CanFilter {
+        CanFilter {
+            can_id: id,
+            can_mask: mask,
+        }
+    }
+}
@@ -0,0 +1,230 @@
+use async_ready::{AsyncReadReady, AsyncWriteReady, TakeError};
+use futures_core::Future;
+use futures_util::ready;
+use std::fmt;
+use std::io;
+use std::mem;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use super::{CanFilter, CanFrame};
+use crate::driver::sys;
+use crate::driver::PollEvented;
+
+const CAN_RAW_FILTER: libc::c_int = 1;
+const CAN_RAW_LOOPBACK: libc::c_int = 3;
+const CAN_RAW_RECV_OWN_MSGS: libc::c_int = 4;
+
+/// A raw CAN socket.
+pub struct CanSocket {
+    io: PollEvented<sys::net::CanSocket>,
+}
+
+/// The future returned by `CanSocket::read_frame`.
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct ReadFrame<'a> {
+    socket: &'a mut CanSocket,
+}
+
+/// The future returned by `CanSocket::write_frame`.
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct WriteFrame<'a, 'b> {
+    socket: &'a mut CanSocket,
+    frame: &'b CanFrame,
+}
+
+impl CanSocket {
+    /// Opens a socket on the interface called `name`.
+    pub fn open(name: &str) -> io::Result<CanSocket> {
+        CanSocket::open_index(sys::net::interface_index(name)?)
+    }
+
+    /// Opens a socket on the interface with index `ifindex`, or on every
+    /// CAN interface if it is 0.
+    pub fn open_index(ifindex: u32) -> io::Result<CanSocket> {
+        let socket = sys::net::CanSocket::raw(ifindex)?;
+        Ok(CanSocket {
+            io: PollEvented::new(socket),
+        })
+    }
+
+    /// Only receives the frames matching one of `filters`, replacing the
+    /// default filter which accepts every frame. An empty list receives
+    /// nothing.
+    pub fn set_filters(&self, filters: &[CanFilter]) -> io::Result<()> {
+        // Safety: `CanFilter` is two `u32`s without padding.
+        let bytes = unsafe {
+            std::slice::from_raw_parts(
+                filters.as_ptr() as *const u8,
+                filters.len() * mem::size_of::<CanFilter>(),
+            )
+        };
+        self.io
+            .get_ref()
+            .setsockopt_bytes(sys::net::SOL_CAN_RAW, CAN_RAW_FILTER, bytes)
+    }
+
+    /// Sets whether frames sent are looped back to the other sockets of the
+    /// interface, which is the default.
+    pub fn set_loopback(&self, on: bool) -> io::Result<()> {
+        self.io.get_ref().setsockopt(
+            sys::net::SOL_CAN_RAW,
+            CAN_RAW_LOOPBACK,
+            on as libc::c_int,
+        )
+    }
+
+    /// Sets whether the socket receives the frames it sent itself.
+    pub fn set_recv_own_msgs(&self, on: bool) -> io::Result<()> {
+        self.io.get_ref().setsockopt(
+            sys::net::SOL_CAN_RAW,
+            CAN_RAW_RECV_OWN_MSGS,
+            on as libc::c_int,
+        )
+    }
+
+    /// Reads a frame.
+    pub fn read_frame(&mut self) -> ReadFrame<'_> {
+        ReadFrame { socket: self }
+    }
+
+    /// Writes a frame.
+    ///
+    /// A full transmit queue of the interface fails with `ENOBUFS` rather
+    /// than waiting, as the kernel doesn't report when it drains.
+    pub fn write_frame<'a, 'b>(&'a mut self, frame: &'b CanFrame) -> WriteFrame<'a, 'b> {
+        WriteFrame {
+            socket: self,
+            frame,
+        }
+    }
+
+    /// Reads a frame, see [`read_frame`].
+    ///
+    /// [`read_frame`]: #method.read_frame
+    pub fn poll_read_frame(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<CanFrame>> {
+        ready!(Pin::new(&mut self.io).poll_read_ready(cx)?);
+
+        let mut frame = CanFrame::from_raw(0, &[]).unwrap();
+        // Safety: any bytes are a valid `CanFrame`.
+        let buf = unsafe {
+            std::slice::from_raw_parts_mut(
+                &mut frame as *mut CanFrame as *mut u8,
+                mem::size_of::<CanFrame>(),
+            )
+        };
+        match self.io.get_ref().recv(buf) {
+            Ok(n) if n == buf.len() => Poll::Ready(Ok(frame)),
+            Ok(_) => Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "unexpected CAN frame size",
+            ))),
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                Pin::new(&mut self.io).clear_read_ready(cx)?;
+                Poll::Pending
+            }
+            Err(e) => Poll::Ready(Err(e)),
+        }
+    }
+
+    /// Writes a frame, see [`write_frame`].
+    ///
+    /// [`write_frame`]: #method.write_frame
+    pub fn poll_write_frame(
+        &mut self,
+        cx: &mut Context<'_>,
+        frame: &CanFrame,
+    ) -> Poll<io::Result<()>> {
+        ready!(self.io.poll_write_ready(cx)?);
+
+        // Safety: `CanFrame` is plain old data.
+        let buf = unsafe {
+            std::slice::from_raw_parts(
+                frame as *const CanFrame as *const u8,
+                mem::size_of::<CanFrame>(),
+            )
+        };
+        match self.io.get_ref().send(buf) {
+            Ok(_) => Poll::Ready(Ok(())),
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                Pin::new(&mut self.io).clear_write_ready(cx)?;
+                Poll::Pending
+            }
+            Err(e) => Poll::Ready(Err(e)),
+        }
+    }
+}
+
+impl AsyncReadReady for CanSocket {
+    type Ok = sys::event::Ready;
+    type Err = io::Error;
+
+    /// Poll the socket's readiness for reading.
+    fn poll_read_ready(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Self::Ok, Self::Err>> {
+        Pin::new(&mut self.io).poll_read_ready(cx)
+    }
+}
+
+impl AsyncWriteReady for CanSocket {
+    type Ok = sys::event::Ready;
+    type Err = io::Error;
+
+    /// Poll the socket's readiness for writing.
+    fn poll_write_ready(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Self::Ok, Self::Err>> {
+        self.io.poll_write_ready(cx)
+    }
+}
+
+impl TakeError for CanSocket {
+    type Ok = io::Error;
+    type Err = io::Error;
+
+    /// Returns the value of the `SO_ERROR` option.
+    fn take_error(&self) -> Result<Option<Self::Ok>, Self::Err> {
+        self.io.get_ref().take_error()
+    }
+}
+
+impl fmt::Debug for CanSocket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.io.get_ref().fmt(f)
+    }
+}
+
+impl AsRawFd for CanSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.io.get_ref().as_raw_fd()
+    }
+}
+
+impl<'a> Future for ReadFrame<'a> {
+    type Output = io::Result<CanFrame>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.socket.poll_read_frame(cx)
+    }
+}
+
+impl<'a, 'b> Future for WriteFrame<'a, 'b> {
+    type Output = io::Result<()>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let WriteFrame {
+            ref mut socket,
+            frame,
+        } = *self;
+        socket.poll_write_frame(cx, frame)
+    }
+}
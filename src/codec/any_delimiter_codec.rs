@@ -0,0 +1,181 @@
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use std::error::Error;
+use std::{cmp, fmt, io};
+
+use super::{Decoder, Encoder};
+
+/// A codec splitting a stream into chunks ended by any of a set of
+/// delimiter bytes.
+///
+/// Decoded chunks don't include their delimiter. Encoding appends a fixed
+/// sequence to each item. Like [`LinesCodec`], a codec created with
+/// [`new_with_max_length`] fails chunks longer than its limit and skips the
+/// rest of them.
+///
+/// # Examples
+///
+/// ```
+/// use futures_net::codec::{AnyDelimiterCodec, BytesMut, Decoder, Encoder};
+///
+/// let mut codec = AnyDelimiterCodec::new(b",;".to_vec(), b";".to_vec());
+/// let mut buf = BytesMut::from(&b"a,b;c"[..]);
+///
+/// assert_eq!(codec.decode(&mut buf).unwrap().unwrap(), "a");
+/// assert_eq!(codec.decode(&mut buf).unwrap().unwrap(), "b");
+/// assert_eq!(codec.decode(&mut buf).unwrap(), None);
+/// assert_eq!(codec.decode_eof(&mut buf).unwrap().unwrap(), "c");
+///
+/// codec.encode("d", &mut buf).unwrap();
+/// assert_eq!(&buf[..], b"d;");
+/// ```
+///
+/// [`LinesCodec`]: struct.LinesCodec.html
+/// [`new_with_max_length`]: #method.new_with_max_length
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AnyDelimiterCodec {
+    /// Index in the buffer up to which no delimiter was found.
+    next_index: usize,
+    max_length: usize,
+    /// Skipping the rest of a chunk which was too long.
+    is_discarding: bool,
+    seek_delimiters: Vec<u8>,
+    sequence_writer: Vec<u8>,
+}
+
+/// Error returned by [`AnyDelimiterCodec`].
+///
+/// [`AnyDelimiterCodec`]: struct.AnyDelimiterCodec.html
+#[derive(Debug)]
+pub enum AnyDelimiterCodecError {
+    /// A chunk was longer than the maximum length of the codec.
+    MaxChunkLengthExceeded,
+    /// An I/O error.
+    Io(io::Error),
+}
+
+impl AnyDelimiterCodec {
+    /// Creates a codec splitting chunks at any byte of `seek_delimiters`,
+    /// and ending encoded items with `sequence_writer`.
+    pub fn new(seek_delimiters: Vec<u8>, sequence_writer: Vec<u8>) -> AnyDelimiterCodec {
+        AnyDelimiterCodec::new_with_max_length(
+            seek_delimiters,
+            sequence_writer,
+            usize::max_value(),
+        )
+    }
+
+    /// Creates a codec failing chunks longer than `max_length` bytes, not
+    /// counting the delimiter.
+    pub fn new_with_max_length(
+        seek_delimiters: Vec<u8>,
+        sequence_writer: Vec<u8>,
+        max_length: usize,
+    ) -> AnyDelimiterCodec {
+        AnyDelimiterCodec {
+            next_index: 0,
+            max_length,
+            is_discarding: false,
+            seek_delimiters,
+            sequence_writer,
+        }
+    }
+
+    /// Returns the maximum length of a chunk.
+    pub fn max_length(&self) -> usize {
+        self.max_length
+    }
+}
+
+impl Decoder for AnyDelimiterCodec {
+    type Item = Bytes;
+    type Error = AnyDelimiterCodecError;
+
+    fn decode(&mut self, buf: &mut BytesMut) -> Result<Option<Bytes>, Self::Error> {
+        loop {
+            let read_to = cmp::min(self.max_length.saturating_add(1), buf.len());
+            let delimiter = buf[self.next_index..read_to]
+                .iter()
+                .position(|b| self.seek_delimiters.contains(b))
+                .map(|offset| self.next_index + offset);
+
+            match (self.is_discarding, delimiter) {
+                (true, Some(index)) => {
+                    buf.advance(index + 1);
+                    self.is_discarding = false;
+                    self.next_index = 0;
+                }
+                (true, None) => {
+                    buf.advance(read_to);
+                    self.next_index = 0;
+                    if buf.is_empty() {
+                        return Ok(None);
+                    }
+                }
+                (false, Some(index)) => {
+                    self.next_index = 0;
+                    let mut chunk = buf.split_to(index + 1);
+                    chunk.truncate(index);
+                    return Ok(Some(chunk.freeze()));
+                }
+                (false, None) if buf.len() > self.max_length => {
+                    self.is_discarding = true;
+                    return Err(AnyDelimiterCodecError::MaxChunkLengthExceeded);
+                }
+                (false, None) => {
+                    self.next_index = read_to;
+                    return Ok(None);
+                }
+            }
+        }
+    }
+
+    fn decode_eof(&mut self, buf: &mut BytesMut) -> Result<Option<Bytes>, Self::Error> {
+        match self.decode(buf)? {
+            Some(chunk) => Ok(Some(chunk)),
+            // The last chunk doesn't need a delimiter.
+            None if buf.is_empty() => Ok(None),
+            None => {
+                self.next_index = 0;
+                Ok(Some(buf.split_to(buf.len()).freeze()))
+            }
+        }
+    }
+}
+
+impl<T: AsRef<[u8]>> Encoder<T> for AnyDelimiterCodec {
+    type Error = AnyDelimiterCodecError;
+
+    fn encode(&mut self, chunk: T, buf: &mut BytesMut) -> Result<(), Self::Error> {
+        let chunk = chunk.as_ref();
+        buf.reserve(chunk.len() + self.sequence_writer.len());
+        buf.put_slice(chunk);
+        buf.put_slice(&self.sequence_writer);
+        Ok(())
+    }
+}
+
+impl From<io::Error> for AnyDelimiterCodecError {
+    fn from(err: io::Error) -> AnyDelimiterCodecError {
+        AnyDelimiterCodecError::Io(err)
+    }
+}
+
+impl fmt::Display for AnyDelimiterCodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnyDelimiterCodecError::MaxChunkLengthExceeded => {
+                f.write_str("max chunk length exceeded")
+            }
+            AnyDelimiterCodecError::Io(e) => fmt::Display::fmt(e, f),
+        }
+    }
+}
+
+impl Error for AnyDelimiterCodecError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AnyDelimiterCodecError::MaxChunkLengthExceeded => None,
+            AnyDelimiterCodecError::Io(e) => Some(e),
+        }
+    }
+}
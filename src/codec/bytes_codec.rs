@@ -0,0 +1,55 @@
+use bytes::{BufMut, Bytes, BytesMut};
+use std::io;
+
+use super::{Decoder, Encoder};
+
+/// A codec passing bytes through unchanged.
+///
+/// Decoding yields whatever was read as one chunk, which is useful to turn
+/// an `AsyncRead` into a `Stream` of byte buffers and an `AsyncWrite` into a
+/// `Sink` of them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct BytesCodec {
+    _priv: (),
+}
+
+impl BytesCodec {
+    /// Creates a `BytesCodec`.
+    pub fn new() -> BytesCodec {
+        BytesCodec { _priv: () }
+    }
+}
+
+impl Decoder for BytesCodec {
+    type Item = BytesMut;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<BytesMut>> {
+        if src.is_empty() {
+            Ok(None)
+        } else {
+            let len = src.len();
+            Ok(Some(src.split_to(len)))
+        }
+    }
+}
+
+impl Encoder<Bytes> for BytesCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, data: Bytes, dst: &mut BytesMut) -> io::Result<()> {
+        dst.reserve(data.len());
+        dst.put(data);
+        Ok(())
+    }
+}
+
+impl Encoder<BytesMut> for BytesCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, data: BytesMut, dst: &mut BytesMut) -> io::Result<()> {
+        dst.reserve(data.len());
+        dst.put(data);
+        Ok(())
+    }
+}
@@ -0,0 +1,48 @@
+use bytes::BytesMut;
+use std::io;
+
+/// Decodes frames from a buffer of bytes read from an I/O object.
+///
+/// [`FramedRead`] and [`Framed`] call [`decode`] every time more bytes were
+/// read, until it returns `Ok(None)`. A decoder consumes the bytes of each
+/// frame it returns from the front of the buffer, and leaves a partial
+/// frame in place until the rest of it arrives. It can reserve room in the
+/// buffer when it knows how much is missing.
+///
+/// [`FramedRead`]: struct.FramedRead.html
+/// [`Framed`]: struct.Framed.html
+/// [`decode`]: #tymethod.decode
+pub trait Decoder {
+    /// The type of the decoded frames.
+    type Item;
+
+    /// The type of decoding errors, which includes the I/O errors of the
+    /// underlying object.
+    type Error: From<io::Error>;
+
+    /// Decodes a frame from the front of `src`, or returns `Ok(None)` if
+    /// more bytes are needed.
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error>;
+
+    /// Decodes a frame once the I/O object reached the end of its stream.
+    ///
+    /// Called until it returns `Ok(None)`, which ends the stream of frames.
+    /// The default implementation calls [`decode`], and fails if bytes
+    /// remain in the buffer once no frame can be decoded anymore.
+    ///
+    /// [`decode`]: #tymethod.decode
+    fn decode_eof(
+        &mut self,
+        buf: &mut BytesMut,
+    ) -> Result<Option<Self::Item>, Self::Error> {
+        match self.decode(buf)? {
+            Some(frame) => Ok(Some(frame)),
+            None if buf.is_empty() => Ok(None),
+            None => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "bytes remaining on stream",
+            )
+            .into()),
+        }
+    }
+}
@@ -0,0 +1,19 @@
+use bytes::BytesMut;
+use std::io;
+
+/// Encodes items into a buffer of bytes to write to an I/O object.
+///
+/// [`FramedWrite`] and [`Framed`] call [`encode`] for every item sent to
+/// them, and write the buffer when it is flushed or grows too large.
+///
+/// [`FramedWrite`]: struct.FramedWrite.html
+/// [`Framed`]: struct.Framed.html
+/// [`encode`]: #tymethod.encode
+pub trait Encoder<Item> {
+    /// The type of encoding errors, which includes the I/O errors of the
+    /// underlying object.
+    type Error: From<io::Error>;
+
+    /// Appends the encoding of `item` to `dst`.
+    fn encode(&mut self, item: Item, dst: &mut BytesMut) -> Result<(), Self::Error>;
+}
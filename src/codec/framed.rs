@@ -0,0 +1,251 @@
+use bytes::BytesMut;
+use futures_core::stream::Stream;
+use futures_io::{AsyncRead, AsyncWrite};
+use futures_util::sink::Sink;
+use std::fmt;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use super::framed_impl::{ReadFrame, WriteFrame, INITIAL_CAPACITY};
+use super::{Decoder, Encoder};
+
+/// A `Stream` and `Sink` of frames over an I/O object which is both
+/// `AsyncRead` and `AsyncWrite`, using a single codec for both directions.
+///
+/// Use `StreamExt::split` to read and write from different tasks.
+///
+/// # Examples
+///
+/// ```
+/// use futures::prelude::*;
+/// use futures_net::codec::{Bytes, BytesCodec, Framed};
+/// use futures_net::runtime::{self, Runtime};
+/// use futures_net::uds::UnixStream;
+///
+/// let mut rt = runtime::default();
+/// rt.exec(async {
+///     let (a, b) = UnixStream::pair()?;
+///     let mut a = Framed::new(a, BytesCodec::new());
+///     let mut b = Framed::new(b, BytesCodec::new());
+///
+///     a.send(Bytes::from_static(b"hello")).await?;
+///     let chunk = b.next().await.unwrap()?;
+///     assert_eq!(&chunk[..], b"hello");
+///     std::io::Result::Ok(())
+/// })
+/// .unwrap();
+/// ```
+pub struct Framed<T, U> {
+    inner: T,
+    codec: U,
+    read: ReadFrame,
+    write: WriteFrame,
+}
+
+impl<T, U> Framed<T, U> {
+    /// Frames `inner` with `codec`.
+    pub fn new(inner: T, codec: U) -> Framed<T, U> {
+        Framed::with_capacity(inner, codec, INITIAL_CAPACITY)
+    }
+
+    /// Frames `inner` with a read buffer starting with room for `capacity`
+    /// bytes.
+    pub fn with_capacity(inner: T, codec: U, capacity: usize) -> Framed<T, U> {
+        Framed::from_parts(inner, codec, BytesMut::with_capacity(capacity))
+    }
+
+    /// Frames `inner`, first decoding the bytes already in `read_buf`.
+    pub fn from_parts(inner: T, codec: U, read_buf: BytesMut) -> Framed<T, U> {
+        Framed {
+            inner,
+            codec,
+            read: ReadFrame::new(read_buf),
+            write: WriteFrame::new(BytesMut::with_capacity(INITIAL_CAPACITY)),
+        }
+    }
+
+    /// Returns a reference to the I/O object.
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the I/O object.
+    ///
+    /// Reading or writing to it directly would corrupt the frames.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Consumes the framing, returning the I/O object. Buffered bytes are
+    /// lost, see [`into_parts`].
+    ///
+    /// [`into_parts`]: #method.into_parts
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Consumes the framing, returning the I/O object, the codec, and the
+    /// bytes read but not decoded yet. Bytes not written yet are lost, flush
+    /// first to avoid it.
+    ///
+    /// This is how a connection switches protocols, e.g. after a handshake.
+    pub fn into_parts(self) -> (T, U, BytesMut) {
+        (self.inner, self.codec, self.read.buf)
+    }
+
+    /// Returns a reference to the codec.
+    pub fn codec(&self) -> &U {
+        &self.codec
+    }
+
+    /// Returns a mutable reference to the codec.
+    pub fn codec_mut(&mut self) -> &mut U {
+        &mut self.codec
+    }
+
+    /// Returns the bytes read but not decoded yet.
+    pub fn read_buffer(&self) -> &BytesMut {
+        &self.read.buf
+    }
+
+    /// Returns the bytes encoded but not written yet.
+    pub fn write_buffer(&self) -> &BytesMut {
+        &self.write.buf
+    }
+}
+
+// The fields are never pinned.
+impl<T, U> Unpin for Framed<T, U> {}
+
+impl<T, U> Stream for Framed<T, U>
+where
+    T: AsyncRead + Unpin,
+    U: Decoder,
+{
+    type Item = Result<U::Item, U::Error>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        this.read.poll_next(cx, &mut this.inner, &mut this.codec)
+    }
+}
+
+impl<T, U, I> Sink<I> for Framed<T, U>
+where
+    T: AsyncWrite + Unpin,
+    U: Encoder<I>,
+{
+    type Error = U::Error;
+
+    fn poll_ready(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), U::Error>> {
+        let this = self.get_mut();
+        this.write
+            .poll_ready(cx, &mut this.inner)
+            .map_err(Into::into)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: I) -> Result<(), U::Error> {
+        let this = self.get_mut();
+        this.write.start_send(&mut this.codec, item)
+    }
+
+    fn poll_flush(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), U::Error>> {
+        let this = self.get_mut();
+        this.write
+            .poll_flush(cx, &mut this.inner)
+            .map_err(Into::into)
+    }
+
+    fn poll_close(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), U::Error>> {
+        let this = self.get_mut();
+        this.write
+            .poll_close(cx, &mut this.inner)
+            .map_err(Into::into)
+    }
+}
+
+impl<T: fmt::Debug, U: fmt::Debug> fmt::Debug for Framed<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Framed")
+            .field("inner", &self.inner)
+            .field("codec", &self.codec)
+            .field("read_buffered", &self.read.buf.len())
+            .field("write_buffered", &self.write.buf.len())
+            .finish()
+    }
+}
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_length_prefixed_frames_across_reads() {
+    use crate::runtime::{self, Runtime};
+    use crate::uds::UnixStream;
+    use bytes::{Buf, BufMut};
+    use futures_util::io::{AsyncReadExt, AsyncWriteExt};
+    use futures_util::{SinkExt, StreamExt};
+    use std::io;
+
+    /// Frames prefixed with their length as one byte.
+    struct Prefixed;
+
+    impl Decoder for Prefixed {
+        type Item = Vec<u8>;
+        type Error = io::Error;
+
+        fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<Vec<u8>>> {
+            match src.first() {
+                Some(&len) if src.len() > len as usize => {
+                    src.advance(1);
+                    Ok(Some(src.split_to(len as usize).to_vec()))
+                }
+                _ => Ok(None),
+            }
+        }
+    }
+
+    impl Encoder<&'static [u8]> for Prefixed {
+        type Error = io::Error;
+
+        fn encode(&mut self, item: &'static [u8], dst: &mut BytesMut) -> io::Result<()> {
+            dst.put_u8(item.len() as u8);
+            dst.put_slice(item);
+            Ok(())
+        }
+    }
+
+    let mut rt = runtime::default();
+    rt.exec(async {
+        let (a, mut b) = UnixStream::pair().unwrap();
+        let mut framed = Framed::new(a, Prefixed);
+
+        // A frame split across writes, then two frames in one write.
+        b.write_all(&[5, b'h', b'e']).await.unwrap();
+        b.write_all(&[b'l', b'l', b'o', 1, b'!', 2]).await.unwrap();
+        assert_eq!(framed.next().await.unwrap().unwrap(), b"hello");
+        assert_eq!(framed.next().await.unwrap().unwrap(), b"!");
+
+        framed.send(&b"bye"[..]).await.unwrap();
+        assert!(framed.write_buffer().is_empty());
+        let mut reply = [0; 4];
+        b.read_exact(&mut reply).await.unwrap();
+        assert_eq!(&reply, b"\x03bye");
+
+        // A partial frame at the end of the stream is an error.
+        b.write_all(&[b'x']).await.unwrap();
+        drop(b);
+        let err = framed.next().await.unwrap().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    });
+}
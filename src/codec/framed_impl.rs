@@ -0,0 +1,167 @@
+//! The reading and writing halves shared by `Framed`, `FramedRead` and
+//! `FramedWrite`.
+
+use bytes::{Buf, BytesMut};
+use futures_io::{AsyncRead, AsyncWrite};
+use futures_util::ready;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use super::{Decoder, Encoder};
+
+pub(super) const INITIAL_CAPACITY: usize = 8 * 1024;
+/// Writes are flushed before accepting more items once this many bytes are
+/// buffered.
+pub(super) const BACKPRESSURE_BOUNDARY: usize = INITIAL_CAPACITY;
+
+#[derive(Debug)]
+pub(super) struct ReadFrame {
+    pub(super) buf: BytesMut,
+    /// The I/O object reached the end of its stream.
+    eof: bool,
+    /// The buffer may hold a frame which wasn't decoded yet.
+    readable: bool,
+}
+
+#[derive(Debug)]
+pub(super) struct WriteFrame {
+    pub(super) buf: BytesMut,
+}
+
+impl ReadFrame {
+    pub(super) fn new(buf: BytesMut) -> ReadFrame {
+        ReadFrame {
+            readable: !buf.is_empty(),
+            buf,
+            eof: false,
+        }
+    }
+
+    pub(super) fn poll_next<T, D>(
+        &mut self,
+        cx: &mut Context<'_>,
+        io: &mut T,
+        decoder: &mut D,
+    ) -> Poll<Option<Result<D::Item, D::Error>>>
+    where
+        T: AsyncRead + Unpin,
+        D: Decoder,
+    {
+        loop {
+            if self.readable {
+                if self.eof {
+                    let frame = decoder.decode_eof(&mut self.buf)?;
+                    if frame.is_none() {
+                        self.readable = false;
+                    }
+                    return Poll::Ready(frame.map(Ok));
+                }
+                if let Some(frame) = decoder.decode(&mut self.buf)? {
+                    return Poll::Ready(Some(Ok(frame)));
+                }
+                self.readable = false;
+            }
+
+            let n = ready!(poll_read_buf(cx, io, &mut self.buf))?;
+            if n == 0 {
+                if self.eof {
+                    return Poll::Ready(None);
+                }
+                self.eof = true;
+            } else {
+                self.eof = false;
+            }
+            self.readable = true;
+        }
+    }
+}
+
+impl WriteFrame {
+    pub(super) fn new(buf: BytesMut) -> WriteFrame {
+        WriteFrame { buf }
+    }
+
+    pub(super) fn poll_ready<T>(
+        &mut self,
+        cx: &mut Context<'_>,
+        io: &mut T,
+    ) -> Poll<io::Result<()>>
+    where
+        T: AsyncWrite + Unpin,
+    {
+        if self.buf.len() >= BACKPRESSURE_BOUNDARY {
+            self.poll_flush(cx, io)
+        } else {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    pub(super) fn start_send<E, I>(
+        &mut self,
+        encoder: &mut E,
+        item: I,
+    ) -> Result<(), E::Error>
+    where
+        E: Encoder<I>,
+    {
+        encoder.encode(item, &mut self.buf)
+    }
+
+    pub(super) fn poll_flush<T>(
+        &mut self,
+        cx: &mut Context<'_>,
+        io: &mut T,
+    ) -> Poll<io::Result<()>>
+    where
+        T: AsyncWrite + Unpin,
+    {
+        while !self.buf.is_empty() {
+            let n = ready!(Pin::new(&mut *io).poll_write(cx, &self.buf))?;
+            if n == 0 {
+                return Poll::Ready(Err(io::Error::new(
+                    io::ErrorKind::WriteZero,
+                    "failed to write frame to transport",
+                )));
+            }
+            self.buf.advance(n);
+        }
+        Pin::new(io).poll_flush(cx)
+    }
+
+    pub(super) fn poll_close<T>(
+        &mut self,
+        cx: &mut Context<'_>,
+        io: &mut T,
+    ) -> Poll<io::Result<()>>
+    where
+        T: AsyncWrite + Unpin,
+    {
+        ready!(self.poll_flush(cx, io))?;
+        Pin::new(io).poll_close(cx)
+    }
+}
+
+/// Reads into the spare capacity of `buf`, growing it if it is short.
+pub(super) fn poll_read_buf<T>(
+    cx: &mut Context<'_>,
+    io: &mut T,
+    buf: &mut BytesMut,
+) -> Poll<io::Result<usize>>
+where
+    T: AsyncRead + Unpin,
+{
+    if buf.capacity() - buf.len() < INITIAL_CAPACITY {
+        buf.reserve(INITIAL_CAPACITY);
+    }
+    let len = buf.len();
+    // `AsyncRead` needs initialized memory.
+    buf.resize(buf.capacity(), 0);
+    let res = Pin::new(io).poll_read(cx, &mut buf[len..]);
+    let n = match &res {
+        Poll::Ready(Ok(n)) => *n,
+        _ => 0,
+    };
+    buf.truncate(len + n);
+    res
+}
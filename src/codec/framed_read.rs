@@ -0,0 +1,109 @@
+use bytes::BytesMut;
+use futures_core::stream::Stream;
+use futures_io::AsyncRead;
+use std::fmt;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use super::framed_impl::{ReadFrame, INITIAL_CAPACITY};
+use super::Decoder;
+
+/// A `Stream` of the frames decoded from an `AsyncRead`.
+pub struct FramedRead<T, D> {
+    inner: T,
+    decoder: D,
+    state: ReadFrame,
+}
+
+impl<T, D> FramedRead<T, D> {
+    /// Creates a stream decoding the bytes of `inner` with `decoder`.
+    pub fn new(inner: T, decoder: D) -> FramedRead<T, D> {
+        FramedRead::with_capacity(inner, decoder, INITIAL_CAPACITY)
+    }
+
+    /// Creates a stream whose read buffer starts with room for `capacity`
+    /// bytes.
+    pub fn with_capacity(inner: T, decoder: D, capacity: usize) -> FramedRead<T, D> {
+        FramedRead::from_parts(inner, decoder, BytesMut::with_capacity(capacity))
+    }
+
+    /// Creates a stream which first decodes the bytes already in `buf`, e.g.
+    /// those left over by another codec.
+    pub fn from_parts(inner: T, decoder: D, buf: BytesMut) -> FramedRead<T, D> {
+        FramedRead {
+            inner,
+            decoder,
+            state: ReadFrame::new(buf),
+        }
+    }
+
+    /// Returns a reference to the I/O object.
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the I/O object.
+    ///
+    /// Reading from it directly would corrupt the stream of frames.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Consumes the stream, returning the I/O object. Buffered bytes are
+    /// lost, see [`into_parts`].
+    ///
+    /// [`into_parts`]: #method.into_parts
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Consumes the stream, returning the I/O object, the decoder and the
+    /// bytes read but not decoded yet.
+    pub fn into_parts(self) -> (T, D, BytesMut) {
+        (self.inner, self.decoder, self.state.buf)
+    }
+
+    /// Returns a reference to the decoder.
+    pub fn decoder(&self) -> &D {
+        &self.decoder
+    }
+
+    /// Returns a mutable reference to the decoder.
+    pub fn decoder_mut(&mut self) -> &mut D {
+        &mut self.decoder
+    }
+
+    /// Returns the bytes read but not decoded yet.
+    pub fn read_buffer(&self) -> &BytesMut {
+        &self.state.buf
+    }
+}
+
+// The fields are never pinned.
+impl<T, D> Unpin for FramedRead<T, D> {}
+
+impl<T, D> Stream for FramedRead<T, D>
+where
+    T: AsyncRead + Unpin,
+    D: Decoder,
+{
+    type Item = Result<D::Item, D::Error>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        this.state.poll_next(cx, &mut this.inner, &mut this.decoder)
+    }
+}
+
+impl<T: fmt::Debug, D: fmt::Debug> fmt::Debug for FramedRead<T, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FramedRead")
+            .field("inner", &self.inner)
+            .field("decoder", &self.decoder)
+            .field("buffered", &self.state.buf.len())
+            .finish()
+    }
+}
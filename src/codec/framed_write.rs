@@ -0,0 +1,119 @@
+use bytes::BytesMut;
+use futures_io::AsyncWrite;
+use futures_util::sink::Sink;
+use std::fmt;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use super::framed_impl::{WriteFrame, INITIAL_CAPACITY};
+use super::Encoder;
+
+/// A `Sink` of items encoded into an `AsyncWrite`.
+///
+/// Items are buffered until the sink is flushed, or until more than 8 KiB
+/// are pending, at which point `poll_ready` flushes before accepting more.
+pub struct FramedWrite<T, E> {
+    inner: T,
+    encoder: E,
+    state: WriteFrame,
+}
+
+impl<T, E> FramedWrite<T, E> {
+    /// Creates a sink encoding items with `encoder` into `inner`.
+    pub fn new(inner: T, encoder: E) -> FramedWrite<T, E> {
+        FramedWrite {
+            inner,
+            encoder,
+            state: WriteFrame::new(BytesMut::with_capacity(INITIAL_CAPACITY)),
+        }
+    }
+
+    /// Returns a reference to the I/O object.
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the I/O object.
+    ///
+    /// Writing to it directly would interleave with the buffered frames.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Consumes the sink, returning the I/O object. Unflushed items are
+    /// lost.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Returns a reference to the encoder.
+    pub fn encoder(&self) -> &E {
+        &self.encoder
+    }
+
+    /// Returns a mutable reference to the encoder.
+    pub fn encoder_mut(&mut self) -> &mut E {
+        &mut self.encoder
+    }
+
+    /// Returns the bytes encoded but not written yet.
+    pub fn write_buffer(&self) -> &BytesMut {
+        &self.state.buf
+    }
+}
+
+// The fields are never pinned.
+impl<T, E> Unpin for FramedWrite<T, E> {}
+
+impl<T, E, I> Sink<I> for FramedWrite<T, E>
+where
+    T: AsyncWrite + Unpin,
+    E: Encoder<I>,
+{
+    type Error = E::Error;
+
+    fn poll_ready(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), E::Error>> {
+        let this = self.get_mut();
+        this.state
+            .poll_ready(cx, &mut this.inner)
+            .map_err(Into::into)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: I) -> Result<(), E::Error> {
+        let this = self.get_mut();
+        this.state.start_send(&mut this.encoder, item)
+    }
+
+    fn poll_flush(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), E::Error>> {
+        let this = self.get_mut();
+        this.state
+            .poll_flush(cx, &mut this.inner)
+            .map_err(Into::into)
+    }
+
+    fn poll_close(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), E::Error>> {
+        let this = self.get_mut();
+        this.state
+            .poll_close(cx, &mut this.inner)
+            .map_err(Into::into)
+    }
+}
+
+impl<T: fmt::Debug, E: fmt::Debug> fmt::Debug for FramedWrite<T, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FramedWrite")
+            .field("inner", &self.inner)
+            .field("encoder", &self.encoder)
+            .field("buffered", &self.state.buf.len())
+            .finish()
+    }
+}
@@ -0,0 +1,142 @@
+use bytes::{Buf, BufMut, BytesMut};
+use std::io;
+
+use super::{Decoder, Encoder};
+
+const HEADER_LEN: usize = 4;
+
+/// A codec for frames prefixed with their length, as a big-endian `u32`.
+///
+/// This is the framing typed message codecs build on: each frame holds one
+/// serialized message, and the decoder knows the size of the message before
+/// it arrives. Frames longer than the maximum length, 8 MiB by default, fail
+/// with an `InvalidData` error before their body is buffered, and when
+/// encoded.
+///
+/// # Examples
+///
+/// ```
+/// use futures::prelude::*;
+/// use futures_net::codec::{Framed, LengthDelimitedCodec};
+/// use futures_net::runtime::{self, Runtime};
+/// use futures_net::uds::UnixStream;
+///
+/// let mut rt = runtime::default();
+/// rt.exec(async {
+///     let (a, b) = UnixStream::pair()?;
+///     let mut a = Framed::new(a, LengthDelimitedCodec::new());
+///     let mut b = Framed::new(b, LengthDelimitedCodec::new());
+///
+///     a.send(&b"{\"id\":1}"[..]).await?;
+///     let frame = b.next().await.unwrap()?;
+///     assert_eq!(&frame[..], b"{\"id\":1}");
+///     std::io::Result::Ok(())
+/// })
+/// .unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LengthDelimitedCodec {
+    max_frame_length: usize,
+    /// Length of the frame whose header was decoded.
+    pending: Option<usize>,
+}
+
+impl LengthDelimitedCodec {
+    /// Creates a codec accepting frames of up to 8 MiB.
+    pub fn new() -> LengthDelimitedCodec {
+        LengthDelimitedCodec::new_with_max_length(8 * 1024 * 1024)
+    }
+
+    /// Creates a codec accepting frames of up to `max_frame_length` bytes,
+    /// not counting the header.
+    pub fn new_with_max_length(max_frame_length: usize) -> LengthDelimitedCodec {
+        LengthDelimitedCodec {
+            max_frame_length,
+            pending: None,
+        }
+    }
+
+    /// Returns the maximum length of a frame.
+    pub fn max_frame_length(&self) -> usize {
+        self.max_frame_length
+    }
+
+    fn too_large(&self) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, "frame exceeds the max length")
+    }
+}
+
+impl Default for LengthDelimitedCodec {
+    fn default() -> LengthDelimitedCodec {
+        LengthDelimitedCodec::new()
+    }
+}
+
+impl Decoder for LengthDelimitedCodec {
+    type Item = BytesMut;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<BytesMut>> {
+        let len = match self.pending {
+            Some(len) => len,
+            None => {
+                if src.len() < HEADER_LEN {
+                    return Ok(None);
+                }
+                let len = src.get_u32() as usize;
+                if len > self.max_frame_length {
+                    return Err(self.too_large());
+                }
+                self.pending = Some(len);
+                len
+            }
+        };
+
+        if src.len() < len {
+            src.reserve(len - src.len());
+            return Ok(None);
+        }
+        self.pending = None;
+        src.reserve(HEADER_LEN);
+        Ok(Some(src.split_to(len)))
+    }
+}
+
+impl<T: AsRef<[u8]>> Encoder<T> for LengthDelimitedCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, frame: T, dst: &mut BytesMut) -> io::Result<()> {
+        let frame = frame.as_ref();
+        if frame.len() > self.max_frame_length || frame.len() > u32::max_value() as usize
+        {
+            return Err(self.too_large());
+        }
+        dst.reserve(HEADER_LEN + frame.len());
+        dst.put_u32(frame.len() as u32);
+        dst.put_slice(frame);
+        Ok(())
+    }
+}
+
+#[test]
+fn test_frames_over_the_limit_fail_early() {
+    let mut codec = LengthDelimitedCodec::new_with_max_length(4);
+    let mut buf = BytesMut::new();
+
+    codec.encode(&b"abcd"[..], &mut buf).unwrap();
+    assert_eq!(&buf[..], b"\0\0\0\x04abcd");
+    assert!(codec.encode(&b"abcde"[..], &mut buf).is_err());
+
+    // A frame split right after its header.
+    let mut tail = buf.split_off(4);
+    assert!(codec.decode(&mut buf).unwrap().is_none());
+    buf.unsplit(tail.split_to(2));
+    assert!(codec.decode(&mut buf).unwrap().is_none());
+    buf.unsplit(tail);
+    assert_eq!(&codec.decode(&mut buf).unwrap().unwrap()[..], b"abcd");
+
+    // Only the header of a large frame has to arrive for it to fail.
+    buf.put_u32(1 << 30);
+    let err = codec.decode(&mut buf).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
@@ -0,0 +1,226 @@
+use bytes::{Buf, BufMut, BytesMut};
+use std::error::Error;
+use std::{cmp, fmt, io, str};
+
+use super::{Decoder, Encoder};
+
+/// A codec splitting a stream into lines of text.
+///
+/// Lines end with `\n`, and a `\r` right before it is stripped as well.
+/// Encoding appends `\n` to each item. Decoded lines must be valid UTF-8.
+///
+/// A peer sending bytes without ever ending the line would make the buffer
+/// grow without bound, so a codec created with [`new_with_max_length`]
+/// fails lines longer than its limit with
+/// [`LinesCodecError::MaxLineLengthExceeded`]. The rest of that line is
+/// discarded, and decoding resumes with the next one.
+///
+/// # Examples
+///
+/// ```
+/// use futures::prelude::*;
+/// use futures_net::codec::{FramedRead, LinesCodec};
+/// use futures_net::runtime::{self, Runtime};
+/// use futures_net::uds::UnixStream;
+///
+/// let mut rt = runtime::default();
+/// rt.exec(async {
+///     let (a, mut b) = UnixStream::pair()?;
+///     b.write_all(b"HELO example.com\r\nQUIT\r\n").await?;
+///     drop(b);
+///
+///     let lines = FramedRead::new(a, LinesCodec::new_with_max_length(512));
+///     let lines: Vec<String> = lines.try_collect().await.unwrap();
+///     assert_eq!(lines, ["HELO example.com", "QUIT"]);
+///     std::io::Result::Ok(())
+/// })
+/// .unwrap();
+/// ```
+///
+/// [`new_with_max_length`]: #method.new_with_max_length
+/// [`LinesCodecError::MaxLineLengthExceeded`]: enum.LinesCodecError.html#variant.MaxLineLengthExceeded
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LinesCodec {
+    /// Index in the buffer up to which no newline was found.
+    next_index: usize,
+    max_length: usize,
+    /// Skipping the rest of a line which was too long.
+    is_discarding: bool,
+}
+
+/// Error returned by [`LinesCodec`].
+///
+/// [`LinesCodec`]: struct.LinesCodec.html
+#[derive(Debug)]
+pub enum LinesCodecError {
+    /// A line was longer than the maximum length of the codec.
+    MaxLineLengthExceeded,
+    /// An I/O error, or a line which is not valid UTF-8.
+    Io(io::Error),
+}
+
+impl LinesCodec {
+    /// Creates a codec accepting lines of any length.
+    pub fn new() -> LinesCodec {
+        LinesCodec::new_with_max_length(usize::max_value())
+    }
+
+    /// Creates a codec failing lines longer than `max_length` bytes, not
+    /// counting the line ending.
+    pub fn new_with_max_length(max_length: usize) -> LinesCodec {
+        LinesCodec {
+            next_index: 0,
+            max_length,
+            is_discarding: false,
+        }
+    }
+
+    /// Returns the maximum length of a line.
+    pub fn max_length(&self) -> usize {
+        self.max_length
+    }
+}
+
+impl Default for LinesCodec {
+    fn default() -> LinesCodec {
+        LinesCodec::new()
+    }
+}
+
+fn to_line(bytes: &[u8]) -> Result<String, LinesCodecError> {
+    let bytes = match bytes.last() {
+        Some(b'\r') => &bytes[..bytes.len() - 1],
+        _ => bytes,
+    };
+    match str::from_utf8(bytes) {
+        Ok(line) => Ok(line.to_string()),
+        Err(_) => Err(LinesCodecError::Io(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "line is not valid UTF-8",
+        ))),
+    }
+}
+
+impl Decoder for LinesCodec {
+    type Item = String;
+    type Error = LinesCodecError;
+
+    fn decode(&mut self, buf: &mut BytesMut) -> Result<Option<String>, LinesCodecError> {
+        loop {
+            // Only look one byte past the limit, the line is too long anyway
+            // if the newline isn't there.
+            let read_to = cmp::min(self.max_length.saturating_add(1), buf.len());
+            let newline = buf[self.next_index..read_to]
+                .iter()
+                .position(|&b| b == b'\n')
+                .map(|offset| self.next_index + offset);
+
+            match (self.is_discarding, newline) {
+                (true, Some(index)) => {
+                    buf.advance(index + 1);
+                    self.is_discarding = false;
+                    self.next_index = 0;
+                }
+                (true, None) => {
+                    buf.advance(read_to);
+                    self.next_index = 0;
+                    if buf.is_empty() {
+                        return Ok(None);
+                    }
+                }
+                (false, Some(index)) => {
+                    self.next_index = 0;
+                    let line = buf.split_to(index + 1);
+                    return to_line(&line[..index]).map(Some);
+                }
+                (false, None) if buf.len() > self.max_length => {
+                    self.is_discarding = true;
+                    return Err(LinesCodecError::MaxLineLengthExceeded);
+                }
+                (false, None) => {
+                    self.next_index = read_to;
+                    return Ok(None);
+                }
+            }
+        }
+    }
+
+    fn decode_eof(
+        &mut self,
+        buf: &mut BytesMut,
+    ) -> Result<Option<String>, LinesCodecError> {
+        match self.decode(buf)? {
+            Some(line) => Ok(Some(line)),
+            // The last line doesn't need a line ending.
+            None if buf.is_empty() || &buf[..] == b"\r" => Ok(None),
+            None => {
+                self.next_index = 0;
+                let line = buf.split_to(buf.len());
+                to_line(&line).map(Some)
+            }
+        }
+    }
+}
+
+impl<T: AsRef<str>> Encoder<T> for LinesCodec {
+    type Error = LinesCodecError;
+
+    fn encode(&mut self, line: T, buf: &mut BytesMut) -> Result<(), LinesCodecError> {
+        let line = line.as_ref();
+        buf.reserve(line.len() + 1);
+        buf.put_slice(line.as_bytes());
+        buf.put_u8(b'\n');
+        Ok(())
+    }
+}
+
+impl From<io::Error> for LinesCodecError {
+    fn from(err: io::Error) -> LinesCodecError {
+        LinesCodecError::Io(err)
+    }
+}
+
+impl fmt::Display for LinesCodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinesCodecError::MaxLineLengthExceeded => {
+                f.write_str("max line length exceeded")
+            }
+            LinesCodecError::Io(e) => fmt::Display::fmt(e, f),
+        }
+    }
+}
+
+impl Error for LinesCodecError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LinesCodecError::MaxLineLengthExceeded => None,
+            LinesCodecError::Io(e) => Some(e),
+        }
+    }
+}
+
+#[test]
+fn test_long_lines_are_skipped() {
+    let mut codec = LinesCodec::new_with_max_length(4);
+    let mut buf = BytesMut::from(&b"ok\r\ntoo lo"[..]);
+
+    assert_eq!(codec.decode(&mut buf).unwrap().unwrap(), "ok");
+    assert!(matches!(
+        codec.decode(&mut buf),
+        Err(LinesCodecError::MaxLineLengthExceeded)
+    ));
+    // The rest of the long line arrives later and is dropped as well.
+    assert!(codec.decode(&mut buf).unwrap().is_none());
+    buf.put_slice(b"ng\nfine\nlast");
+    assert_eq!(codec.decode(&mut buf).unwrap().unwrap(), "fine");
+    assert!(codec.decode(&mut buf).unwrap().is_none());
+    assert_eq!(codec.decode_eof(&mut buf).unwrap().unwrap(), "last");
+    assert!(codec.decode_eof(&mut buf).unwrap().is_none());
+
+    let mut buf = BytesMut::from(&b"\xff\n"[..]);
+    assert!(matches!(
+        codec.decode(&mut buf),
+        Err(LinesCodecError::Io(_))
+    ));
+}
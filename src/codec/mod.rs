@@ -0,0 +1,90 @@
+//! Framing of byte streams into streams of messages.
+//!
+//! A codec turns bytes into frames with a [`Decoder`] and frames into bytes
+//! with an [`Encoder`]. [`FramedRead`] turns any `AsyncRead`, like a
+//! `TcpStream` or a `UnixStream`, into a `Stream` of decoded frames,
+//! [`FramedWrite`] turns any `AsyncWrite` into a `Sink` of items, and
+//! [`Framed`] does both over one object.
+//!
+//! [`BytesCodec`] passes bytes through, and [`LinesCodec`] and
+//! [`AnyDelimiterCodec`] split text protocols into lines or chunks, and
+//! [`LengthDelimitedCodec`] frames binary messages with a length prefix.
+//! Other protocols implement the traits themselves. Codecs work with the buffer types of the [`bytes`] crate, re-exported
+//! here.
+//!
+//! # Examples
+//!
+//! A codec for frames prefixed with their length as a big-endian `u16`:
+//!
+//! ```
+//! use futures_net::codec::{Buf, BufMut, BytesMut, Decoder, Encoder};
+//! use std::io;
+//!
+//! struct Prefixed;
+//!
+//! impl Decoder for Prefixed {
+//!     type Item = BytesMut;
+//!     type Error = io::Error;
+//!
+//!     fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<BytesMut>> {
+//!         if src.len() < 2 {
+//!             return Ok(None);
+//!         }
+//!         let len = u16::from_be_bytes([src[0], src[1]]) as usize;
+//!         if src.len() < 2 + len {
+//!             // Make room for the rest of the frame.
+//!             src.reserve(2 + len - src.len());
+//!             return Ok(None);
+//!         }
+//!         src.advance(2);
+//!         Ok(Some(src.split_to(len)))
+//!     }
+//! }
+//!
+//! impl Encoder<&[u8]> for Prefixed {
+//!     type Error = io::Error;
+//!
+//!     fn encode(&mut self, item: &[u8], dst: &mut BytesMut) -> io::Result<()> {
+//!         if item.len() > u16::max_value() as usize {
+//!             return Err(io::Error::new(io::ErrorKind::InvalidInput, "frame too large"));
+//!         }
+//!         dst.put_u16(item.len() as u16);
+//!         dst.put_slice(item);
+//!         Ok(())
+//!     }
+//! }
+//! ```
+//!
+//! [`Decoder`]: trait.Decoder.html
+//! [`Encoder`]: trait.Encoder.html
+//! [`FramedRead`]: struct.FramedRead.html
+//! [`FramedWrite`]: struct.FramedWrite.html
+//! [`Framed`]: struct.Framed.html
+//! [`BytesCodec`]: struct.BytesCodec.html
+//! [`LinesCodec`]: struct.LinesCodec.html
+//! [`AnyDelimiterCodec`]: struct.AnyDelimiterCodec.html
+//! [`LengthDelimitedCodec`]: struct.LengthDelimitedCodec.html
+//! [`bytes`]: https://docs.rs/bytes/1
+
+mod any_delimiter_codec;
+mod bytes_codec;
+mod decoder;
+mod encoder;
+mod framed;
+mod framed_impl;
+mod framed_read;
+mod framed_write;
+mod length_delimited;
+mod lines_codec;
+
+pub use self::any_delimiter_codec::{AnyDelimiterCodec, AnyDelimiterCodecError};
+pub use self::bytes_codec::BytesCodec;
+pub use self::decoder::Decoder;
+pub use self::encoder::Encoder;
+pub use self::framed::Framed;
+pub use self::framed_read::FramedRead;
+pub use self::framed_write::FramedWrite;
+pub use self::length_delimited::LengthDelimitedCodec;
+pub use self::lines_codec::{LinesCodec, LinesCodecError};
+
+pub use bytes::{Buf, BufMut, Bytes, BytesMut};
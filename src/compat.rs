@@ -0,0 +1,113 @@
+//! Interoperability with other executors.
+//!
+//! The socket types of this crate don't depend on its runtime: they attach
+//! to the global background reactor, or to the one installed with
+//! [`driver::set_default`], whichever executor polls them. `async-std` and
+//! other executors built on the `futures` I/O traits can use them as they
+//! are.
+//!
+//! With the `compat` feature, also available as `tokio-compat`, the stream
+//! types also implement the `tokio::io::AsyncRead` and `tokio::io::AsyncWrite`
+//! traits, so they can be handed to libraries written against tokio. This
+//! covers `TcpStream` and `UnixStream`, shared references to them, and
+//! [`io::DuplexStream`].
+//!
+//! [`driver::set_default`]: ../driver/fn.set_default.html
+//! [`io::DuplexStream`]: ../io/struct.DuplexStream.html
+
+use futures_io::{AsyncRead, AsyncWrite};
+use futures_util::ready;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::ReadBuf;
+
+use crate::io::DuplexStream;
+use crate::{TcpStream, UnixStream};
+
+fn poll_read_buf<R: AsyncRead>(
+    io: Pin<&mut R>,
+    cx: &mut Context<'_>,
+    buf: &mut ReadBuf<'_>,
+) -> Poll<io::Result<()>> {
+    let n = ready!(io.poll_read(cx, buf.initialize_unfilled()))?;
+    buf.advance(n);
+    Poll::Ready(Ok(()))
+}
+
+macro_rules! tokio_io {
+    ($ty:ty $(, $lt:lifetime)?) => {
+        impl$(<$lt>)? tokio::io::AsyncRead for $ty {
+            fn poll_read(
+                self: Pin<&mut Self>,
+                cx: &mut Context<'_>,
+                buf: &mut ReadBuf<'_>,
+            ) -> Poll<io::Result<()>> {
+                poll_read_buf(self, cx, buf)
+            }
+        }
+
+        impl$(<$lt>)? tokio::io::AsyncWrite for $ty {
+            fn poll_write(
+                self: Pin<&mut Self>,
+                cx: &mut Context<'_>,
+                buf: &[u8],
+            ) -> Poll<io::Result<usize>> {
+                AsyncWrite::poll_write(self, cx, buf)
+            }
+
+            fn poll_flush(
+                self: Pin<&mut Self>,
+                cx: &mut Context<'_>,
+            ) -> Poll<io::Result<()>> {
+                AsyncWrite::poll_flush(self, cx)
+            }
+
+            /// Shuts down the write half of the stream, like tokio's own
+            /// stream types do.
+            fn poll_shutdown(
+                self: Pin<&mut Self>,
+                cx: &mut Context<'_>,
+            ) -> Poll<io::Result<()>> {
+                AsyncWrite::poll_close(self, cx)
+            }
+        }
+    };
+}
+
+tokio_io!(TcpStream);
+tokio_io!(&'a TcpStream, 'a);
+tokio_io!(UnixStream);
+tokio_io!(&'a UnixStream, 'a);
+tokio_io!(DuplexStream);
+
+#[test]
+fn test_tokio_io_traits() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    futures_executor::block_on(async {
+        let (mut a, mut b) = UnixStream::pair().unwrap();
+        a.write_all(b"tokio").await.unwrap();
+        let mut buf = [0; 5];
+        b.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"tokio");
+
+        AsyncWriteExt::shutdown(&mut a).await.unwrap();
+        assert_eq!(b.read(&mut buf).await.unwrap(), 0);
+    });
+}
+
+#[test]
+fn test_tokio_io_traits_on_shared_references() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    futures_executor::block_on(async {
+        let (a, b) = UnixStream::pair().unwrap();
+        (&a).write_all(b"shared").await.unwrap();
+        AsyncWriteExt::shutdown(&mut &a).await.unwrap();
+
+        let mut buf = Vec::new();
+        (&b).read_to_end(&mut buf).await.unwrap();
+        assert_eq!(buf, b"shared");
+    });
+}
@@ -0,0 +1,293 @@
+//! Asynchronous host name resolution.
+//!
+//! [`lookup_host`] resolves a `host:port` string into socket addresses
+//! with the system resolver, `getaddrinfo`. As `getaddrinfo` blocks, lookups
+//! run on a small pool of threads dedicated to them, so they never stall
+//! the reactor or the tasks of the runtime. Strings which already are socket
+//! addresses are returned without a lookup.
+//!
+//! The hostname paths of the sockets, [`TcpStream::connect_host`] and
+//! [`UdpSocket::send_to_host`], resolve through it as well. Their `_with`
+//! variants take any [`Resolver`] instead, so applications can plug in a
+//! caching resolver, DNS over HTTPS, or a fixed table in tests.
+//!
+//! # Examples
+//!
+//! ```
+//! use futures_net::dns::{LookupHost, Resolver};
+//! use std::net::SocketAddr;
+//!
+//! /// Sends `*.internal` to a fixed address, everything else to the system.
+//! struct Internal(SocketAddr);
+//!
+//! impl Resolver for Internal {
+//!     fn lookup_host(&self, host: &str) -> LookupHost {
+//!         match host.split(':').next() {
+//!             Some(name) if name.ends_with(".internal") => {
+//!                 LookupHost::from_addrs(vec![self.0])
+//!             }
+//!             _ => futures_net::lookup_host(host),
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! [`lookup_host`]: fn.lookup_host.html
+//! [`Resolver`]: trait.Resolver.html
+//! [`TcpStream::connect_host`]: ../tcp/struct.TcpStream.html#method.connect_host
+//! [`UdpSocket::send_to_host`]: ../udp/struct.UdpSocket.html#method.send_to_host
+
+use futures_channel::oneshot;
+use futures_core::future::BoxFuture;
+use futures_core::stream::Stream;
+use futures_executor::ThreadPool;
+use futures_util::future::{self, FutureExt};
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::vec;
+
+/// Lookups running at once; more wait for a thread.
+const POOL_SIZE: usize = 4;
+
+lazy_static::lazy_static! {
+    static ref POOL: io::Result<ThreadPool> = ThreadPool::builder()
+        .pool_size(POOL_SIZE)
+        .name_prefix("futures-net-dns-")
+        .create();
+}
+
+/// Resolves `host`, a host name or an IP address followed by a port, into
+/// the socket addresses it stands for.
+///
+/// IPv6 addresses are written in brackets, e.g. `[::1]:80`. The addresses
+/// come in the order `getaddrinfo` returns them, which is the order a
+/// client should try them in. A failed lookup yields a single error.
+///
+/// # Examples
+///
+/// ```
+/// use futures::prelude::*;
+/// use futures_net::runtime::{self, Runtime};
+///
+/// let mut rt = runtime::default();
+/// let addrs: Vec<_> = rt.exec(futures_net::lookup_host("127.0.0.1:80").collect());
+/// assert_eq!(addrs[0].as_ref().unwrap().port(), 80);
+/// ```
+pub fn lookup_host(host: &str) -> LookupHost {
+    if let Ok(addr) = host.parse::<SocketAddr>() {
+        return LookupHost::from_addrs(vec![addr]);
+    }
+
+    let pool = match &*POOL {
+        Ok(pool) => pool,
+        Err(e) => {
+            let e = io::Error::new(e.kind(), format!("failed to start resolver: {}", e));
+            return LookupHost {
+                state: State::Failed(Some(e)),
+            };
+        }
+    };
+
+    let (tx, rx) = oneshot::channel();
+    let host = host.to_owned();
+    pool.spawn_ok(future::lazy(move |_| {
+        let addrs = host.to_socket_addrs().map(Iterator::collect);
+        let _ = tx.send(addrs);
+    }));
+    LookupHost {
+        state: State::Resolving(rx),
+    }
+}
+
+/// Resolves host names for the hostname paths of the sockets.
+///
+/// `host` has the same form as for [`lookup_host`], which is what the
+/// [`SystemResolver`] uses.
+///
+/// [`lookup_host`]: fn.lookup_host.html
+/// [`SystemResolver`]: struct.SystemResolver.html
+pub trait Resolver {
+    /// Resolves `host` into the socket addresses it stands for.
+    fn lookup_host(&self, host: &str) -> LookupHost;
+}
+
+/// The resolver of the system, i.e. [`lookup_host`].
+///
+/// [`lookup_host`]: fn.lookup_host.html
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemResolver;
+
+impl Resolver for SystemResolver {
+    fn lookup_host(&self, host: &str) -> LookupHost {
+        lookup_host(host)
+    }
+}
+
+impl<R: Resolver + ?Sized> Resolver for &R {
+    fn lookup_host(&self, host: &str) -> LookupHost {
+        (**self).lookup_host(host)
+    }
+}
+
+impl<R: Resolver + ?Sized> Resolver for Box<R> {
+    fn lookup_host(&self, host: &str) -> LookupHost {
+        (**self).lookup_host(host)
+    }
+}
+
+impl<R: Resolver + ?Sized> Resolver for Arc<R> {
+    fn lookup_host(&self, host: &str) -> LookupHost {
+        (**self).lookup_host(host)
+    }
+}
+
+/// Stream of the addresses of a host, returned by [`lookup_host`] and by
+/// resolvers.
+///
+/// [`lookup_host`]: fn.lookup_host.html
+#[must_use = "streams do nothing unless polled"]
+pub struct LookupHost {
+    state: State,
+}
+
+impl LookupHost {
+    /// Creates a stream of already known addresses.
+    pub fn from_addrs(addrs: Vec<SocketAddr>) -> LookupHost {
+        LookupHost {
+            state: State::Done(addrs.into_iter()),
+        }
+    }
+
+    /// Creates a stream of the addresses `fut` resolves to.
+    pub fn from_future<F>(fut: F) -> LookupHost
+    where
+        F: Future<Output = io::Result<Vec<SocketAddr>>> + Send + 'static,
+    {
+        LookupHost {
+            state: State::Custom(fut.boxed()),
+        }
+    }
+}
+
+enum State {
+    Resolving(oneshot::Receiver<io::Result<Vec<SocketAddr>>>),
+    Custom(BoxFuture<'static, io::Result<Vec<SocketAddr>>>),
+    Done(vec::IntoIter<SocketAddr>),
+    Failed(Option<io::Error>),
+}
+
+impl Stream for LookupHost {
+    type Item = io::Result<SocketAddr>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            match &mut this.state {
+                State::Resolving(rx) => {
+                    this.state = match futures_util::ready!(Pin::new(rx).poll(cx)) {
+                        Ok(Ok(addrs)) => State::Done(addrs.into_iter()),
+                        Ok(Err(e)) => State::Failed(Some(e)),
+                        Err(_) => State::Failed(Some(io::Error::new(
+                            io::ErrorKind::Other,
+                            "resolver thread panicked",
+                        ))),
+                    };
+                }
+                State::Custom(fut) => {
+                    this.state = match futures_util::ready!(fut.as_mut().poll(cx)) {
+                        Ok(addrs) => State::Done(addrs.into_iter()),
+                        Err(e) => State::Failed(Some(e)),
+                    };
+                }
+                State::Done(addrs) => return Poll::Ready(addrs.next().map(Ok)),
+                State::Failed(e) => return Poll::Ready(e.take().map(Err)),
+            }
+        }
+    }
+}
+
+impl fmt::Debug for LookupHost {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = match self.state {
+            State::Resolving(_) | State::Custom(_) => "Resolving",
+            State::Done(_) => "Done",
+            State::Failed(_) => "Failed",
+        };
+        f.debug_struct("LookupHost").field("state", &state).finish()
+    }
+}
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_names_are_resolved_off_the_runtime() {
+    use crate::runtime::{self, Runtime};
+    use futures_util::StreamExt;
+
+    let mut rt = runtime::default();
+    rt.exec(async {
+        let addrs: Vec<_> = lookup_host("localhost:8080").collect().await;
+        assert!(!addrs.is_empty());
+        for addr in addrs {
+            let addr = addr.unwrap();
+            assert!(addr.ip().is_loopback());
+            assert_eq!(addr.port(), 8080);
+        }
+
+        // A missing port is an error of the lookup, not of the stream.
+        let mut addrs = lookup_host("localhost");
+        assert!(addrs.next().await.unwrap().is_err());
+        assert!(addrs.next().await.is_none());
+    });
+}
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_hostname_paths_use_the_given_resolver() {
+    use crate::runtime::{self, Runtime};
+    use crate::{TcpListener, TcpStream, UdpSocket};
+    use std::collections::HashMap;
+
+    struct Table(HashMap<&'static str, SocketAddr>);
+
+    impl Resolver for Table {
+        fn lookup_host(&self, host: &str) -> LookupHost {
+            let addrs = self.0.get(host).cloned().into_iter().collect();
+            LookupHost::from_future(async move { Ok(addrs) })
+        }
+    }
+
+    let any = "127.0.0.1:0".parse().unwrap();
+    let listener = TcpListener::bind(&any).unwrap();
+    let mut udp = UdpSocket::bind(&any).unwrap();
+    let mut table = HashMap::new();
+    table.insert("db.test:5432", listener.local_addr().unwrap());
+    table.insert("metrics.test:8125", udp.local_addr().unwrap());
+    let resolver = Table(table);
+
+    let mut rt = runtime::default();
+    rt.exec(async {
+        let stream = TcpStream::connect_host_with("db.test:5432", &resolver)
+            .await
+            .unwrap();
+        assert_eq!(stream.peer_addr().unwrap(), listener.local_addr().unwrap());
+
+        let n = udp
+            .send_to_host_with(b"hits:1|c", "metrics.test:8125", &resolver)
+            .await
+            .unwrap();
+        assert_eq!(n, 8);
+
+        let err = TcpStream::connect_host_with("unknown.test:1", &resolver)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    });
+}
@@ -3,14 +3,16 @@ use super::{Handle, Reactor};
 use futures_core::Future;
 use futures_executor;
 use futures_util::task::AtomicWaker;
-use log::debug;
+use log::{debug, error};
+use parking_lot::Mutex;
+use slab::Slab;
 
 use std::io;
 use std::pin::Pin;
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering::SeqCst;
 use std::sync::Arc;
-use std::task::{Context, Poll};
+use std::task::{Context, Poll, Waker};
 use std::thread;
 
 /// Handle to the reactor running on a background thread.
@@ -47,6 +49,30 @@ struct Shared {
 
     /// Task to notify when the reactor thread enters a shutdown state.
     shutdown_task: AtomicWaker,
+
+    /// Whether this is the global fallback reactor, whose failures are
+    /// reported to `FALLBACK_FAILURE`.
+    fallback: bool,
+}
+
+/// Error which stopped a reactor thread, and the tasks waiting to hear
+/// about it.
+#[derive(Debug, Default)]
+struct FailureSlot {
+    // `io::Error` isn't `Clone`, keep what is needed to rebuild one for each
+    // waiter.
+    error: Option<(io::ErrorKind, String)>,
+    waiters: Slab<Waker>,
+}
+
+lazy_static::lazy_static! {
+    static ref FALLBACK_FAILURE: Mutex<FailureSlot> = Mutex::new(FailureSlot::default());
+}
+
+/// Watches the global fallback reactor for a fatal error.
+#[derive(Debug)]
+pub(crate) struct FallbackFailure {
+    key: Option<usize>,
 }
 
 /// Notifies the reactor thread to shutdown once the reactor becomes idle.
@@ -63,6 +89,15 @@ const SHUTDOWN: usize = 3;
 impl Background {
     /// Launch a reactor in the background and return a handle to the thread.
     pub(super) fn new(reactor: Reactor) -> io::Result<Background> {
+        Background::spawn(reactor, false)
+    }
+
+    /// Launch the global fallback reactor.
+    pub(super) fn fallback(reactor: Reactor) -> io::Result<Background> {
+        Background::spawn(reactor, true)
+    }
+
+    fn spawn(reactor: Reactor, fallback: bool) -> io::Result<Background> {
         // Grab a handle to the reactor
         let handle = reactor.handle().clone();
 
@@ -71,6 +106,7 @@ impl Background {
         let shared = Arc::new(Shared {
             shutdown: AtomicUsize::new(0),
             shutdown_task: AtomicWaker::new(),
+            fallback,
         });
 
         // For the reactor thread
@@ -152,6 +188,67 @@ impl Inner {
     }
 }
 
+// ===== impl FailureSlot =====
+
+impl FailureSlot {
+    fn fail(&mut self, err: &io::Error) {
+        if self.error.is_none() {
+            self.error = Some((err.kind(), err.to_string()));
+        }
+        for (_, waker) in self.waiters.iter() {
+            waker.wake_by_ref();
+        }
+    }
+
+    fn poll_failed(
+        &mut self,
+        key: &mut Option<usize>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Error> {
+        if let Some((kind, msg)) = &self.error {
+            return Poll::Ready(io::Error::new(*kind, msg.clone()));
+        }
+
+        match *key {
+            Some(key) => {
+                if !self.waiters[key].will_wake(cx.waker()) {
+                    self.waiters[key] = cx.waker().clone();
+                }
+            }
+            None => *key = Some(self.waiters.insert(cx.waker().clone())),
+        }
+        Poll::Pending
+    }
+
+    fn cancel(&mut self, key: usize) {
+        self.waiters.remove(key);
+    }
+}
+
+// ===== impl FallbackFailure =====
+
+impl FallbackFailure {
+    pub(crate) fn new() -> FallbackFailure {
+        FallbackFailure { key: None }
+    }
+
+    /// Resolves with the error which stopped the fallback reactor.
+    ///
+    /// The reactor is gone at that point: resources registered with it have
+    /// been woken up and report errors, and new ones fail to register.
+    pub(crate) fn poll_failed(&mut self, cx: &mut Context<'_>) -> Poll<io::Error> {
+        FALLBACK_FAILURE.lock().poll_failed(&mut self.key, cx)
+    }
+}
+
+impl Drop for FallbackFailure {
+    fn drop(&mut self) {
+        if let Some(key) = self.key {
+            FALLBACK_FAILURE.lock().cancel(key);
+        }
+    }
+}
+
 // ===== impl Reactor thread =====
 
 fn run(mut reactor: Reactor, shared: Arc<Shared>) {
@@ -169,7 +266,20 @@ fn run(mut reactor: Reactor, shared: Arc<Shared>) {
             break;
         }
 
-        reactor.turn(None).unwrap();
+        match reactor.turn(None) {
+            Ok(_) => {}
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => {
+                // Nothing can be driven anymore. Shut down so that every
+                // resource is woken up and sees the reactor gone, rather than
+                // hanging forever.
+                error!("background reactor failed: {}", e);
+                if shared.fallback {
+                    FALLBACK_FAILURE.lock().fail(&e);
+                }
+                break;
+            }
+        }
     }
 
     drop(reactor);
@@ -182,3 +292,25 @@ fn run(mut reactor: Reactor, shared: Arc<Shared>) {
 
     debug!("background reactor has shutdown");
 }
+
+#[test]
+fn test_failure_slot_wakes_waiters() {
+    use futures_util::task::noop_waker;
+
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    let mut slot = FailureSlot::default();
+    let mut key = None;
+
+    assert!(slot.poll_failed(&mut key, &mut cx).is_pending());
+    assert_eq!(slot.waiters.len(), 1);
+
+    slot.fail(&io::Error::new(io::ErrorKind::Other, "epoll broke"));
+    match slot.poll_failed(&mut key, &mut cx) {
+        Poll::Ready(err) => assert_eq!(err.to_string(), "epoll broke"),
+        Poll::Pending => panic!("failure not reported"),
+    }
+
+    slot.cancel(key.unwrap());
+    assert!(slot.waiters.is_empty());
+}
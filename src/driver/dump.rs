@@ -0,0 +1,227 @@
+//! Listing of the resources registered with a reactor.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::sync::atomic::Ordering::Relaxed;
+use std::time::{Duration, Instant};
+
+#[cfg(target_os = "linux")]
+use super::sys::event::Event;
+use super::sys::event::Ready;
+use super::sys::Poll;
+use super::{Handle, HandlePriv, Inner};
+
+/// The resources registered with a reactor, returned by [`debug_dump`].
+///
+/// Its `Display` implementation prints one line per resource, meant for
+/// logs and admin endpoints.
+///
+/// [`debug_dump`]: fn.debug_dump.html
+#[derive(Debug, Clone)]
+pub struct DebugDump {
+    /// When the dump was taken.
+    pub taken: Instant,
+    /// The registered resources, in token order.
+    pub resources: Vec<RegisteredIo>,
+}
+
+/// A resource registered with a reactor.
+#[derive(Debug, Clone)]
+pub struct RegisteredIo {
+    /// The token events of the resource are delivered with.
+    pub token: usize,
+    /// The file descriptor which the system selector reports for the token.
+    ///
+    /// `None` for resources without a descriptor of their own, such as
+    /// user space registrations, or if the selector lost track of it.
+    pub fd: Option<RawFd>,
+    /// The readiness the selector watches the descriptor for.
+    pub interest: Option<Ready>,
+    /// Whether the descriptor is registered edge-triggered.
+    pub edge: bool,
+    /// Readiness received for the resource and not consumed yet.
+    pub pending: Ready,
+    /// The turn of the reactor which last delivered an event for the
+    /// resource, `None` if none did yet.
+    pub last_event: Option<Instant>,
+}
+
+/// Lists the resources registered with the reactor of the current context,
+/// or with the global fallback reactor.
+///
+/// Each resource comes with the descriptor and interest the system selector
+/// has for its token: a resource whose `fd` is `None` is one the selector
+/// will never report events for. The selector's view is read from
+/// `/proc/self/fdinfo`, so `fd` and `interest` are `None` without `/proc`.
+///
+/// # Examples
+///
+/// ```
+/// use futures_net::driver::Reactor;
+/// use futures_net::TcpListener;
+/// use std::os::unix::io::AsRawFd;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let reactor = Reactor::new()?;
+/// let handle = reactor.handle();
+///
+/// let listener = TcpListener::bind(&"127.0.0.1:0".parse().unwrap())?;
+/// listener.register_with(&handle)?;
+///
+/// let dump = handle.debug_dump()?;
+/// assert_eq!(dump.resources.len(), 1);
+/// assert_eq!(dump.resources[0].fd, Some(listener.as_raw_fd()));
+/// println!("{}", dump);
+/// # Ok(())
+/// # }
+/// ```
+pub fn debug_dump() -> io::Result<DebugDump> {
+    Handle::default().debug_dump()
+}
+
+impl Handle {
+    /// Lists the resources registered with the reactor of this handle, see
+    /// [`debug_dump`].
+    ///
+    /// [`debug_dump`]: fn.debug_dump.html
+    pub fn debug_dump(&self) -> io::Result<DebugDump> {
+        let handle = match self.as_priv() {
+            Some(handle) => handle.clone(),
+            None => HandlePriv::try_current()?,
+        };
+        match handle.inner() {
+            Some(inner) => Ok(inner.debug_dump()),
+            None => Err(io::Error::new(io::ErrorKind::Other, "reactor gone")),
+        }
+    }
+}
+
+impl Inner {
+    fn debug_dump(&self) -> DebugDump {
+        let watched = watched_fds(&self.io).unwrap_or_default();
+        let io_dispatch = self.io_dispatch.read();
+        let resources = io_dispatch
+            .iter()
+            .map(|(key, io)| {
+                let token = io.aba_guard | key;
+                let selector = watched.get(&(token as u64));
+                let events = selector.map(|&(_, events)| events);
+                let last_event = match io.last_event.load(Relaxed) {
+                    0 => None,
+                    nanos => Some(self.started + Duration::from_nanos(nanos - 1)),
+                };
+                RegisteredIo {
+                    token,
+                    fd: selector.map(|&(fd, _)| fd),
+                    interest: events.map(readiness),
+                    edge: events
+                        .map_or(false, |events| events & libc::EPOLLET as u32 != 0),
+                    pending: Ready::from_usize(io.readiness.load(Relaxed)),
+                    last_event,
+                }
+            })
+            .collect();
+        DebugDump {
+            taken: Instant::now(),
+            resources,
+        }
+    }
+}
+
+/// Reads the descriptors watched by the epoll instance of `poll`, with their
+/// event masks, by token.
+#[cfg(target_os = "linux")]
+fn watched_fds(poll: &Poll) -> io::Result<HashMap<u64, (RawFd, u32)>> {
+    let info = fs::read_to_string(format!("/proc/self/fdinfo/{}", poll.as_raw_fd()))?;
+    let mut watched = HashMap::new();
+    // Lines look like `tfd:        7 events:       19 data:           400000 ...`,
+    // the descriptor in decimal and the rest in hexadecimal.
+    for line in info.lines().filter(|line| line.starts_with("tfd:")) {
+        let fields: Vec<_> = line.split_whitespace().collect();
+        let field = |name| {
+            let at = fields.iter().position(|&field| field == name)?;
+            fields.get(at + 1).copied()
+        };
+        let parsed = (|| {
+            let fd = field("tfd:")?.parse().ok()?;
+            let events = u32::from_str_radix(field("events:")?, 16).ok()?;
+            let data = u64::from_str_radix(field("data:")?, 16).ok()?;
+            Some((data, (fd, events)))
+        })();
+        if let Some((data, fd)) = parsed {
+            watched.insert(data, fd);
+        }
+    }
+    Ok(watched)
+}
+
+/// Other backends don't expose their watch list, only the user space side of
+/// the registrations is reported.
+#[cfg(not(target_os = "linux"))]
+fn watched_fds(_poll: &Poll) -> io::Result<HashMap<u64, (RawFd, u32)>> {
+    Ok(HashMap::new())
+}
+
+#[cfg(target_os = "linux")]
+fn readiness(events: u32) -> Ready {
+    Event::from_sys(&libc::epoll_event { events, u64: 0 }).readiness()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn readiness(_events: u32) -> Ready {
+    Ready::empty()
+}
+
+impl fmt::Display for DebugDump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} registered resources", self.resources.len())?;
+        for io in &self.resources {
+            write!(f, "  token {:#x}", io.token)?;
+            match io.fd {
+                Some(fd) => write!(f, " fd {}", fd)?,
+                None => write!(f, " fd -")?,
+            }
+            if let Some(interest) = io.interest {
+                write!(f, " interest [{:?}]", interest)?;
+            }
+            if io.edge {
+                write!(f, " edge")?;
+            }
+            write!(f, " pending [{:?}]", io.pending)?;
+            match io.last_event {
+                Some(at) => {
+                    let ago = self.taken.saturating_duration_since(at);
+                    writeln!(f, " last event {:?} ago", ago)?
+                }
+                None => writeln!(f, " no event yet")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_dump_reports_events() {
+    use super::Reactor;
+    use crate::UdpSocket;
+
+    let mut reactor = Reactor::new().unwrap();
+    let handle = reactor.handle();
+    let socket = UdpSocket::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    socket.register_with(&handle).unwrap();
+
+    let io = &handle.debug_dump().unwrap().resources[0];
+    assert_eq!(io.fd, Some(socket.as_raw_fd()));
+    assert!(io.edge && io.interest.unwrap().is_readable());
+    assert!(io.last_event.is_none());
+
+    // A socket is writable right away.
+    reactor.turn(Some(Duration::from_millis(10))).unwrap();
+    let dump = handle.debug_dump().unwrap();
+    assert!(dump.resources[0].pending.is_writable());
+    assert!(dump.resources[0].last_event.unwrap() <= dump.taken);
+    assert!(dump.to_string().contains("last event"));
+}
@@ -0,0 +1,71 @@
+//! Emergency file descriptor for listeners.
+//!
+//! When `accept` fails with `EMFILE` the pending connection stays in the
+//! backlog and the listener stays readable, so a naive accept loop spins. By
+//! holding on to a spare descriptor we can free it, accept the connection,
+//! close it right away and take the spare back. The peer sees the connection
+//! closed instead of hanging, and the backlog drains.
+
+use std::fs::File;
+use std::io;
+
+#[derive(Debug)]
+pub(crate) struct FdReserve {
+    spare: Option<File>,
+}
+
+impl FdReserve {
+    pub(crate) fn new() -> io::Result<FdReserve> {
+        Ok(FdReserve {
+            spare: Some(open_spare()?),
+        })
+    }
+
+    /// Release the spare descriptor, run `accept` and drop whatever it
+    /// accepted, then reclaim the spare.
+    ///
+    /// Returns `true` if a connection was shed.
+    pub(crate) fn shed<T, F>(&mut self, accept: F) -> bool
+    where
+        F: FnOnce() -> io::Result<T>,
+    {
+        if self.spare.take().is_none() {
+            // The previous attempt to reclaim the spare failed, try again
+            // before giving up on this round.
+            self.spare = open_spare().ok();
+            return false;
+        }
+
+        let shed = match accept() {
+            Ok(conn) => {
+                drop(conn);
+                true
+            }
+            Err(_) => false,
+        };
+
+        self.spare = open_spare().ok();
+        shed
+    }
+}
+
+fn open_spare() -> io::Result<File> {
+    // std opens files with `O_CLOEXEC`.
+    File::open("/dev/null")
+}
+
+#[test]
+fn shed_closes_pending_connection() {
+    use std::io::Read;
+    use std::net::{TcpListener, TcpStream};
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+
+    let mut reserve = FdReserve::new().unwrap();
+    assert!(reserve.shed(|| listener.accept()));
+    assert!(reserve.spare.is_some());
+
+    let mut buf = [0; 1];
+    assert_eq!(client.read(&mut buf).unwrap(), 0);
+}
@@ -0,0 +1,69 @@
+//! Readiness a task waits for.
+
+use std::fmt;
+use std::ops;
+
+/// The readiness to wait for, e.g. with [`TcpStream::ready`].
+///
+/// # Examples
+///
+/// ```
+/// use futures_net::driver::Interest;
+///
+/// let interest = Interest::readable() | Interest::writable();
+///
+/// assert!(interest.is_readable());
+/// assert!(interest.is_writable());
+/// ```
+///
+/// [`TcpStream::ready`]: ../tcp/struct.TcpStream.html#method.ready
+#[derive(Copy, PartialEq, Eq, Clone)]
+pub struct Interest(u8);
+
+const READABLE: u8 = 0b01;
+const WRITABLE: u8 = 0b10;
+
+impl Interest {
+    /// Returns the interest in read readiness.
+    #[inline]
+    pub fn readable() -> Interest {
+        Interest(READABLE)
+    }
+
+    /// Returns the interest in write readiness.
+    #[inline]
+    pub fn writable() -> Interest {
+        Interest(WRITABLE)
+    }
+
+    /// Returns true if the interest includes read readiness.
+    #[inline]
+    pub fn is_readable(self) -> bool {
+        self.0 & READABLE != 0
+    }
+
+    /// Returns true if the interest includes write readiness.
+    #[inline]
+    pub fn is_writable(self) -> bool {
+        self.0 & WRITABLE != 0
+    }
+}
+
+impl ops::BitOr for Interest {
+    type Output = Interest;
+
+    #[inline]
+    fn bitor(self, other: Interest) -> Interest {
+        Interest(self.0 | other.0)
+    }
+}
+
+impl fmt::Debug for Interest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.is_readable(), self.is_writable()) {
+            (true, true) => f.write_str("Interest(readable | writable)"),
+            (true, false) => f.write_str("Interest(readable)"),
+            _ => f.write_str("Interest(writable)"),
+        }
+    }
+}
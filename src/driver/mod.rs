@@ -1,27 +1,64 @@
 //! futures reactor,  event loop.
-
-pub(crate) mod background;
+//!
+//! I/O resources register with the reactor of the current execution context
+//! and fall back to a global reactor otherwise. The global reactor is
+//! started lazily, on its own thread, the first time a resource registers,
+//! so resources make progress whichever executor polls them.
+//!
+//! If polling the OS fails, a background reactor logs the error and shuts
+//! down instead of panicking: the resources registered with it are woken up
+//! and report errors, and [`Runtime::try_exec`] returns the error so the
+//! application can exit cleanly.
+//!
+//! A [`Reactor`] can also be driven explicitly, e.g. from a thread owned by
+//! another runtime, and made the default for a thread with [`set_default`].
+//!
+//! ```
+//! use futures_net::driver::{self, Reactor};
+//! use std::time::Duration;
+//!
+//! let mut reactor = Reactor::new().unwrap();
+//! let _guard = driver::set_default(&reactor.handle());
+//!
+//! // Resources registered on this thread now use `reactor`, which has to
+//! // be turned to deliver their events.
+//! reactor.turn(Some(Duration::from_millis(10))).unwrap();
+//! ```
+//!
+//! [`Reactor`]: struct.Reactor.html
+//! [`set_default`]: fn.set_default.html
+//! [`Runtime::try_exec`]: ../runtime/trait.Runtime.html#method.try_exec
+
+mod background;
+mod dump;
+pub(crate) mod fd_reserve;
+mod interest;
 mod poll_evented;
 pub(crate) mod registration;
 mod sharded_rwlock;
 pub mod sys;
+pub(crate) mod timer;
 
+pub(crate) use self::background::FallbackFailure;
+pub use self::background::{Background, Shutdown};
+pub use self::dump::{debug_dump, DebugDump, RegisteredIo};
+pub use self::interest::Interest;
 pub use self::poll_evented::PollEvented;
 
 use futures_util::task::AtomicWaker;
 use log::{debug, log_enabled, trace, Level};
 use slab::Slab;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::io;
+use std::marker::PhantomData;
 use std::mem;
-use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering::{Relaxed, SeqCst};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize};
 use std::sync::{Arc, Weak};
 use std::task::Context;
 use std::time::{Duration, Instant};
 use std::{fmt, usize};
 
-use self::background::Background;
 use self::sharded_rwlock::RwLock;
 use self::sys::event::Evented;
 
@@ -31,13 +68,16 @@ use self::sys::event::Evented;
 /// all other I/O events and notifications happening. Each event loop can have
 /// multiple handles pointing to it, each of which can then be used to create
 /// various I/O objects to interact with the event loop in interesting ways.
-struct Reactor {
+pub struct Reactor {
     /// Reuse the `sys::event::Events` value across calls to poll.
     events: sys::event::Events,
 
     /// State shared between the reactor and the handles.
     inner: Arc<Inner>,
 
+    /// How long to spin when busy polling adaptively.
+    spin_window: SpinWindow,
+
     _wakeup_registration: sys::Registration,
 }
 
@@ -50,7 +90,7 @@ struct Reactor {
 /// By default, most components bind lazily to reactors.
 /// To get this behavior when manually passing a `Handle`, use `default()`.
 #[derive(Clone)]
-struct Handle {
+pub struct Handle {
     inner: Option<HandlePriv>,
 }
 
@@ -60,13 +100,128 @@ struct HandlePriv {
     inner: Weak<Inner>,
 }
 
-/// Return value from the `turn` method on `Reactor`.
-///
-/// Currently this value doesn't actually provide any functionality, but it may
-/// in the future give insight into what happened during `turn`.
+/// Return value from the `turn` method on `Reactor`, telling what the turn
+/// dispatched.
 #[derive(Debug)]
-struct Turn {
-    _priv: (),
+pub struct Turn {
+    events: usize,
+    timers: usize,
+}
+
+impl Turn {
+    /// Returns the number of I/O events dispatched to resources.
+    ///
+    /// Wakeups of the reactor itself are not counted.
+    pub fn events(&self) -> usize {
+        self.events
+    }
+
+    /// Returns the number of timers which fired.
+    pub fn timers(&self) -> usize {
+        self.timers
+    }
+}
+
+/// The spin window of a reactor busy polling adaptively.
+///
+/// The window starts at the configured maximum and halves each time it
+/// elapses without an event, down to nothing: an idle reactor stops
+/// spinning. It grows back to the maximum as soon as spinning pays off, or
+/// when a blocking wait returns within it, which shows that events come in
+/// faster than the window.
+#[derive(Debug, Clone, Copy, Default)]
+struct SpinWindow {
+    /// `None` until the first turn, and after each event inside it.
+    shrunk: Option<Duration>,
+}
+
+/// Spin windows shorter than this are not worth it.
+const MIN_SPIN: Duration = Duration::from_micros(1);
+
+impl SpinWindow {
+    fn get(&self, max: Duration) -> Duration {
+        self.shrunk.map_or(max, |shrunk| shrunk.min(max))
+    }
+
+    /// Adapts the window after spinning for `spin`, which is shorter than
+    /// the window if a timer was due first.
+    fn spun(&mut self, spin: Duration, max: Duration, hit: bool) {
+        if hit {
+            self.shrunk = None;
+        } else if spin >= self.get(max) {
+            let half = spin / 2;
+            self.shrunk = Some(if half < MIN_SPIN {
+                Duration::ZERO
+            } else {
+                half
+            });
+        }
+    }
+
+    /// Adapts the window after blocking for `blocked`, until `events`
+    /// events came in.
+    fn blocked(&mut self, blocked: Duration, events: usize, max: Duration) {
+        if events > 0 && blocked < max {
+            self.shrunk = None;
+        }
+    }
+}
+
+#[test]
+fn test_spin_window_adapts_to_the_event_rate() {
+    let max = Duration::from_micros(100);
+    let mut window = SpinWindow::default();
+    assert_eq!(window.get(max), max);
+
+    // Idle turns shrink the window until the reactor stops spinning.
+    while window.get(max) > Duration::ZERO {
+        let spin = window.get(max);
+        window.spun(spin, max, false);
+        assert!(window.get(max) < spin);
+    }
+    window.spun(Duration::ZERO, max, false);
+    assert_eq!(window.get(max), Duration::ZERO);
+
+    // A slow event keeps it shut, a burst opens it fully again.
+    window.blocked(Duration::from_millis(10), 1, max);
+    assert_eq!(window.get(max), Duration::ZERO);
+    window.blocked(Duration::from_micros(20), 1, max);
+    assert_eq!(window.get(max), max);
+
+    // A window cut short by a timer doesn't count as idle.
+    window.spun(Duration::from_micros(50), max, false);
+    assert_eq!(window.get(max), max);
+    window.spun(max, max, false);
+    assert_eq!(window.get(max), Duration::from_micros(50));
+    window.spun(Duration::from_micros(10), max, true);
+    assert_eq!(window.get(max), max);
+}
+
+#[test]
+fn test_reactors_are_sized_up_front() {
+    let reactor = Reactor::new().unwrap();
+    assert!(reactor.inner.io_dispatch.read().capacity() >= DEFAULT_IO_CAPACITY);
+}
+
+#[test]
+fn test_turn_counts_dispatched_events() {
+    let reactor = Reactor::new().unwrap();
+    let _guard = set_default(&reactor.handle());
+    let zero = Some(Duration::from_millis(0));
+    assert!(turn(zero).is_ok());
+
+    // Registers the socket before it becomes readable.
+    let (a, b) = sys::net::UnixDatagram::pair().unwrap();
+    let a = PollEvented::new(a);
+    let waker = futures_util::task::noop_waker();
+    let _ = a.poll_read_ready(&mut Context::from_waker(&waker));
+    b.send(b"ping").unwrap();
+
+    assert!(turn(Some(Duration::from_secs(1))).unwrap().events() >= 1);
+    assert_eq!(turn(zero).unwrap().events(), 0);
+
+    drop(_guard);
+    assert!(turn(zero).is_err());
 }
 
 #[test]
@@ -87,6 +242,12 @@ struct Inner {
 
     /// Used to wake up the reactor from a call to `turn`
     wakeup: sys::SetReadiness,
+
+    /// Pending timers, fired at the end of each turn.
+    timers: timer::Timers,
+
+    /// Origin of the `last_event` timestamps.
+    started: Instant,
 }
 
 struct ScheduledIo {
@@ -94,6 +255,15 @@ struct ScheduledIo {
     readiness: AtomicUsize,
     reader: AtomicWaker,
     writer: AtomicWaker,
+    /// Nanoseconds from `Inner::started` to the turn which last delivered
+    /// an event, plus one. Zero until the first event.
+    last_event: AtomicU64,
+}
+
+/// Returns when the system selector returned the I/O events a reactor is
+/// dispatching, if this is called from a waker which one of them woke up.
+pub(crate) fn dispatched_at() -> Option<Instant> {
+    DISPATCHING.with(Cell::get)
 }
 
 #[derive(Debug, Eq, PartialEq, Clone, Copy)]
@@ -105,9 +275,30 @@ pub(crate) enum Direction {
 /// The global fallback reactor.
 static HANDLE_FALLBACK: AtomicUsize = AtomicUsize::new(0);
 
+lazy_static::lazy_static! {
+    /// The reactor returned by `high_priority`, once started.
+    static ref HIGH_PRIORITY: parking_lot::Mutex<Option<Handle>> =
+        parking_lot::Mutex::new(None);
+}
+
+/// How long, in nanoseconds, a reactor spins on a non-blocking poll before
+/// blocking in the system selector. Zero disables spinning.
+static BUSY_POLL_NANOS: AtomicUsize = AtomicUsize::new(0);
+
+/// Whether reactors adapt how long they spin to the rate of events.
+static BUSY_POLL_ADAPTIVE: AtomicBool = AtomicBool::new(false);
+
+/// The number of I/O resources new reactors have room for up front.
+static IO_CAPACITY: AtomicUsize = AtomicUsize::new(DEFAULT_IO_CAPACITY);
+
+const DEFAULT_IO_CAPACITY: usize = 256;
+
 // Tracks the reactor for the current execution context.
 thread_local!(static CURRENT_REACTOR: RefCell<Option<HandlePriv>> = RefCell::new(None));
 
+// When the events a reactor is dispatching on this thread were received.
+thread_local!(static DISPATCHING: Cell<Option<Instant>> = Cell::new(None));
+
 const TOKEN_SHIFT: usize = 22;
 
 // Kind of arbitrary, but this reserves some token space for later usage.
@@ -125,7 +316,7 @@ fn _assert_kinds() {
 impl Reactor {
     /// Creates a new event loop, returning any error that happened during the
     /// creation.
-    fn new() -> io::Result<Reactor> {
+    pub fn new() -> io::Result<Reactor> {
         let io = sys::Poll::new()?;
         let wakeup_pair = sys::Registration::new2();
 
@@ -142,9 +333,12 @@ impl Reactor {
             inner: Arc::new(Inner {
                 io: io,
                 next_aba_guard: AtomicUsize::new(0),
-                io_dispatch: RwLock::new(Slab::with_capacity(1)),
+                io_dispatch: RwLock::new(Slab::with_capacity(io_capacity())),
                 wakeup: wakeup_pair.1,
+                timers: timer::Timers::new(),
+                started: Instant::now(),
             }),
+            spin_window: SpinWindow::default(),
         })
     }
 
@@ -154,7 +348,7 @@ impl Reactor {
     /// Handles are cloneable and clones always refer to the same event loop.
     /// This handle is typically passed into functions that create I/O objects
     /// to bind them to this event loop.
-    fn handle(&self) -> Handle {
+    pub fn handle(&self) -> Handle {
         Handle {
             inner: Some(HandlePriv {
                 inner: Arc::downgrade(&self.inner),
@@ -171,15 +365,13 @@ impl Reactor {
     ///
     /// If a `max_wait` is specified then the method should block no longer than
     /// the duration specified, but this shouldn't be used as a super-precise
-    /// timer but rather a "ballpark approximation"
+    /// timer but rather a "ballpark approximation". The reactor also stops
+    /// blocking when the earliest of its timers is due.
     ///
     /// # Return value
     ///
-    /// This function returns an instance of `Turn`
-    ///
-    /// `Turn` as of today has no extra information with it and can be safely
-    /// discarded.  In the future `Turn` may contain information about what
-    /// happened while this reactor blocked.
+    /// The returned `Turn` counts the I/O events dispatched and the timers
+    /// fired, it can be safely discarded.
     ///
     /// # Errors
     ///
@@ -187,17 +379,17 @@ impl Reactor {
     /// for readiness of I/O objects with the OS. This is quite unlikely to
     /// arise and typically mean that things have gone horribly wrong at that
     /// point.
-    fn turn(&mut self, max_wait: Option<Duration>) -> io::Result<Turn> {
-        self.poll(max_wait)?;
-        Ok(Turn { _priv: () })
+    pub fn turn(&mut self, max_wait: Option<Duration>) -> io::Result<Turn> {
+        self.inner
+            .poll(&mut self.events, &mut self.spin_window, max_wait)
     }
 
     /// Returns true if the reactor is currently idle.
     ///
     /// Idle is defined as all tasks that have been spawned have completed,
     /// either successfully or with an error.
-    fn is_idle(&self) -> bool {
-        self.inner.io_dispatch.read().is_empty()
+    pub fn is_idle(&self) -> bool {
+        self.inner.io_dispatch.read().is_empty() && self.inner.timers.is_empty()
     }
 
     /// Run this reactor on a background thread.
@@ -206,94 +398,9 @@ impl Reactor {
     /// reactor to this new thread. It then runs the reactor, driving all
     /// associated I/O resources, until the `Background` handle is dropped or
     /// explicitly shutdown.
-    fn background(self) -> io::Result<Background> {
+    pub fn background(self) -> io::Result<Background> {
         Background::new(self)
     }
-
-    fn poll(&mut self, max_wait: Option<Duration>) -> io::Result<()> {
-        // Block waiting for an event to happen, peeling out how many events
-        // happened.
-        match self.inner.io.poll(&mut self.events, max_wait) {
-            Ok(_) => {}
-            Err(e) => return Err(e),
-        }
-
-        let start = if log_enabled!(Level::Debug) {
-            Some(Instant::now())
-        } else {
-            None
-        };
-
-        // Process all the events that came in, dispatching appropriately
-        let mut events = 0;
-        for event in self.events.iter() {
-            events += 1;
-            let token = event.token();
-            trace!("event {:?} {:?}", event.readiness(), event.token());
-
-            if token == TOKEN_WAKEUP {
-                self.inner
-                    .wakeup
-                    .set_readiness(sys::event::Ready::empty())
-                    .unwrap();
-            } else {
-                self.dispatch(token, event.readiness());
-            }
-        }
-
-        if let Some(start) = start {
-            let dur = start.elapsed();
-            trace!(
-                "loop process - {} events, {}.{:03}s",
-                events,
-                dur.as_secs(),
-                dur.subsec_nanos() / 1_000_000
-            );
-        }
-
-        Ok(())
-    }
-
-    fn dispatch(&self, token: sys::Token, ready: sys::event::Ready) {
-        let aba_guard = token.0 & !MAX_SOURCES;
-        let token = token.0 & MAX_SOURCES;
-
-        let mut rd = None;
-        let mut wr = None;
-
-        // Create a scope to ensure that notifying the tasks stays out of the
-        // lock's critical section.
-        {
-            let io_dispatch = self.inner.io_dispatch.read();
-
-            let io = match io_dispatch.get(token) {
-                Some(io) => io,
-                None => return,
-            };
-
-            if aba_guard != io.aba_guard {
-                return;
-            }
-
-            io.readiness.fetch_or(ready.as_usize(), Relaxed);
-
-            if ready.is_writable() || platform::is_hup(&ready) {
-                wr = io.writer.take();
-            }
-
-            if !(ready & (!sys::event::Ready::writable())).is_empty() {
-                rd = io.reader.take();
-            }
-        }
-
-        if let Some(task) = rd {
-            task.wake();
-        }
-
-        if let Some(task) = wr {
-            task.wake();
-        }
-    }
 }
 
 impl fmt::Debug for Reactor {
@@ -333,6 +440,279 @@ impl fmt::Debug for Handle {
     }
 }
 
+/// Configures reactors to busy poll for up to `spin` before blocking.
+///
+/// When set, each reactor turn first polls the system selector without
+/// blocking, in a loop, for up to `spin`. Only if nothing became ready in
+/// that window does the reactor block in `epoll_wait`. This trades CPU for
+/// wakeup latency, and is meant to be paired with the `SO_BUSY_POLL` socket
+/// option on NICs with kernel busy polling enabled.
+///
+/// Passing `None` (the default) disables spinning.
+///
+/// # Examples
+///
+/// ```
+/// use futures_net::driver;
+/// use std::time::Duration;
+///
+/// driver::set_busy_poll(Some(Duration::from_micros(50)));
+/// assert_eq!(driver::busy_poll(), Some(Duration::from_micros(50)));
+///
+/// driver::set_busy_poll(None);
+/// assert_eq!(driver::busy_poll(), None);
+/// ```
+pub fn set_busy_poll(spin: Option<Duration>) {
+    let nanos = spin.map_or(0, |spin| {
+        spin.as_nanos().min(usize::MAX as u128).max(1) as usize
+    });
+    BUSY_POLL_NANOS.store(nanos, Relaxed);
+}
+
+/// Returns the busy poll duration configured with [`set_busy_poll`].
+///
+/// [`set_busy_poll`]: fn.set_busy_poll.html
+pub fn busy_poll() -> Option<Duration> {
+    match BUSY_POLL_NANOS.load(Relaxed) {
+        0 => None,
+        nanos => Some(Duration::from_nanos(nanos as u64)),
+    }
+}
+
+/// Makes reactors adapt how long they busy poll to the rate of events.
+///
+/// The duration set with [`set_busy_poll`] becomes the longest a reactor
+/// spins. Each reactor halves its spin window whenever it elapses without
+/// an event, until it stops spinning altogether, and restores it in full
+/// once events come in faster than the window again. Under bursty traffic
+/// the reactor spins through the burst and goes back to blocking when it
+/// ends, instead of burning CPU for every quiet turn.
+///
+/// Disabled by default. It has no effect unless busy polling is enabled.
+///
+/// # Examples
+///
+/// ```
+/// use futures_net::driver;
+/// use std::time::Duration;
+///
+/// driver::set_busy_poll(Some(Duration::from_micros(50)));
+/// driver::set_busy_poll_adaptive(true);
+/// assert!(driver::busy_poll_adaptive());
+/// ```
+///
+/// [`set_busy_poll`]: fn.set_busy_poll.html
+pub fn set_busy_poll_adaptive(adaptive: bool) {
+    BUSY_POLL_ADAPTIVE.store(adaptive, Relaxed);
+}
+
+/// Returns whether busy polling adapts to the rate of events, see
+/// [`set_busy_poll_adaptive`].
+///
+/// [`set_busy_poll_adaptive`]: fn.set_busy_poll_adaptive.html
+pub fn busy_poll_adaptive() -> bool {
+    BUSY_POLL_ADAPTIVE.load(Relaxed)
+}
+
+/// Sizes the table of I/O resources of new reactors for `capacity`
+/// resources.
+///
+/// Registering a socket with its reactor takes a slot of the table, under
+/// a lock every thread polling I/O contends on. Past its capacity the table
+/// is reallocated and copied with the lock held, so a server expecting many
+/// connections can size it up front and avoid the stalls. Slots of dropped
+/// sockets are reused without allocating. The default is 256.
+///
+/// The setting applies to reactors created after the call, including the
+/// global fallback reactor, which is created the first time a socket is
+/// polled outside of a runtime with its own reactor.
+///
+/// # Examples
+///
+/// ```
+/// use futures_net::driver;
+///
+/// driver::set_io_capacity(100_000);
+/// assert_eq!(driver::io_capacity(), 100_000);
+/// ```
+pub fn set_io_capacity(capacity: usize) {
+    IO_CAPACITY.store(capacity.min(MAX_SOURCES), Relaxed);
+}
+
+/// Returns the capacity configured with [`set_io_capacity`].
+///
+/// [`set_io_capacity`]: fn.set_io_capacity.html
+pub fn io_capacity() -> usize {
+    IO_CAPACITY.load(Relaxed)
+}
+
+/// Rounds the deadlines of new timers up to a multiple of `granularity`.
+///
+/// By default timers fire as close to their deadline as the one millisecond
+/// resolution of the timing wheel allows, so a server with a timeout per
+/// connection wakes up for nearly every millisecond in which one expires.
+/// With a coarser granularity the deadlines falling in the same bucket
+/// share a wheel slot and fire in a single wakeup, at the cost of firing up
+/// to `granularity` late. Timers never fire early.
+///
+/// The setting applies to every reactor and to timers registered after the
+/// call. `granularity` is rounded up to whole milliseconds, and `None`
+/// (the default) restores exact deadlines.
+///
+/// # Examples
+///
+/// ```
+/// use futures_net::driver;
+/// use std::time::Duration;
+///
+/// driver::set_timer_granularity(Some(Duration::from_millis(10)));
+/// assert_eq!(driver::timer_granularity(), Some(Duration::from_millis(10)));
+///
+/// driver::set_timer_granularity(None);
+/// assert_eq!(driver::timer_granularity(), None);
+/// ```
+pub fn set_timer_granularity(granularity: Option<Duration>) {
+    let ms = granularity.map_or(1, |granularity| {
+        let ms = (granularity.as_nanos() + 999_999) / 1_000_000;
+        ms.min(u128::from(timer::MAX_GRANULARITY_MS)).max(1) as u64
+    });
+    timer::GRANULARITY_MS.store(ms, Relaxed);
+}
+
+/// Returns the granularity configured with [`set_timer_granularity`].
+///
+/// [`set_timer_granularity`]: fn.set_timer_granularity.html
+pub fn timer_granularity() -> Option<Duration> {
+    match timer::GRANULARITY_MS.load(Relaxed) {
+        1 => None,
+        ms => Some(Duration::from_millis(ms)),
+    }
+}
+
+/// Returns a handle to the high priority reactor.
+///
+/// The high priority reactor runs on its own thread, started on the first
+/// call, next to the global fallback one. Registering the few sockets which
+/// must not wait, such as control connections or heartbeats, with it keeps
+/// their events from queueing behind those of every other socket when the
+/// main reactor is busy. Sockets are registered with it through their
+/// `register_with` method, e.g. [`TcpStream::register_with`], before they
+/// are first polled.
+///
+/// # Examples
+///
+/// ```no_run
+/// use futures_net::{driver, TcpStream};
+///
+/// # async fn run() -> std::io::Result<()> {
+/// let addr = "10.0.0.1:9000".parse().unwrap();
+/// let control = TcpStream::connect(&addr)
+///     .register_with(&driver::high_priority()?)
+///     .await?;
+/// # drop(control);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`TcpStream::register_with`]: ../tcp/struct.TcpStream.html#method.register_with
+pub fn high_priority() -> io::Result<Handle> {
+    let mut high_priority = HIGH_PRIORITY.lock();
+    if let Some(handle) = &*high_priority {
+        return Ok(handle.clone());
+    }
+
+    let reactor = Reactor::new()?;
+    let handle = reactor.handle();
+    reactor.background()?.forget();
+    *high_priority = Some(handle.clone());
+    Ok(handle)
+}
+
+/// Performs one iteration of the reactor set as the default of the current
+/// thread, see [`Reactor::turn`].
+///
+/// This steps a reactor installed with [`set_default`] without owning it,
+/// so tests of custom [`Evented`] types or of readiness edge cases can
+/// drive their reactor one turn at a time. The reactor should not be
+/// running in the [`background`] meanwhile, or the events get split
+/// between the two.
+///
+/// # Errors
+///
+/// Fails if no reactor is set as the default of this thread, or if it was
+/// dropped, as well as when polling the OS fails.
+///
+/// # Examples
+///
+/// ```
+/// use futures_net::driver::{self, Reactor};
+/// use std::time::Duration;
+///
+/// let reactor = Reactor::new().unwrap();
+/// let _guard = driver::set_default(&reactor.handle());
+///
+/// let turn = driver::turn(Some(Duration::from_millis(0))).unwrap();
+/// assert_eq!(turn.events(), 0);
+/// ```
+///
+/// [`Reactor::turn`]: struct.Reactor.html#method.turn
+/// [`set_default`]: fn.set_default.html
+/// [`Evented`]: sys/event/trait.Evented.html
+/// [`background`]: struct.Reactor.html#method.background
+pub fn turn(max_wait: Option<Duration>) -> io::Result<Turn> {
+    let inner = CURRENT_REACTOR
+        .with(|current| current.borrow().as_ref().map(HandlePriv::inner))
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Other,
+                "no reactor is set as the default of this thread",
+            )
+        })?
+        .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "reactor gone"))?;
+
+    let mut events = sys::event::Events::with_capacity(1024);
+    inner.poll(&mut events, &mut SpinWindow::default(), max_wait)
+}
+
+/// Makes `handle` the reactor of the current thread until the guard is
+/// dropped.
+///
+/// I/O resources first polled on this thread while the guard is alive
+/// register with this reactor instead of the global fallback one.
+pub fn set_default(handle: &Handle) -> DefaultGuard {
+    let prev = CURRENT_REACTOR.with(|current| {
+        let mut current = current.borrow_mut();
+        mem::replace(&mut *current, handle.as_priv().cloned())
+    });
+    DefaultGuard {
+        prev,
+        _not_send: PhantomData,
+    }
+}
+
+/// Guard returned by [`set_default`], restoring the previous default reactor
+/// when dropped.
+///
+/// [`set_default`]: fn.set_default.html
+#[must_use = "the default reactor is reset when the guard is dropped"]
+pub struct DefaultGuard {
+    prev: Option<HandlePriv>,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for DefaultGuard {
+    fn drop(&mut self) {
+        let prev = self.prev.take();
+        CURRENT_REACTOR.with(|current| *current.borrow_mut() = prev);
+    }
+}
+
+impl fmt::Debug for DefaultGuard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DefaultGuard").finish()
+    }
+}
+
 fn set_fallback(handle: HandlePriv) -> Result<(), ()> {
     unsafe {
         let val = handle.into_usize();
@@ -388,7 +768,7 @@ impl HandlePriv {
             if set_fallback(reactor.handle().into_priv().unwrap()).is_ok() {
                 let ret = reactor.handle().into_priv().unwrap();
 
-                match reactor.background() {
+                match Background::fallback(reactor) {
                     Ok(bg) => bg.forget(),
                     // The global handle is fubar, but y'all probably got bigger
                     // problems if a thread can't spawn.
@@ -412,7 +792,7 @@ impl HandlePriv {
 
             // This prevents `handle` from being dropped and having the ref
             // count decremented.
-            drop(handle.into_usize());
+            let _ = handle.into_usize();
 
             ret
         };
@@ -461,6 +841,170 @@ impl fmt::Debug for HandlePriv {
 // ===== impl Inner =====
 
 impl Inner {
+    /// Waits for events with `events` as the buffer, and dispatches them.
+    fn poll(
+        &self,
+        events: &mut sys::event::Events,
+        spin_window: &mut SpinWindow,
+        max_wait: Option<Duration>,
+    ) -> io::Result<Turn> {
+        // Don't sleep past the next timer.
+        let max_wait = match self.timers.next_deadline() {
+            Some(deadline) => {
+                let until = deadline.saturating_duration_since(Instant::now());
+                Some(max_wait.map_or(until, |max_wait| max_wait.min(until)))
+            }
+            None => max_wait,
+        };
+
+        // Block waiting for an event to happen, peeling out how many events
+        // happened.
+        if !self.spin(events, spin_window, max_wait)? {
+            match (busy_poll(), busy_poll_adaptive()) {
+                (Some(max), true) => {
+                    let start = Instant::now();
+                    let events = self.io.poll(events, max_wait)?;
+                    spin_window.blocked(start.elapsed(), events, max);
+                }
+                _ => {
+                    self.io.poll(events, max_wait)?;
+                }
+            }
+        }
+
+        let start = if log_enabled!(Level::Debug) {
+            Some(Instant::now())
+        } else {
+            None
+        };
+
+        // Process all the events that came in, dispatching appropriately
+        let mut turn = Turn {
+            events: 0,
+            timers: 0,
+        };
+        let received = Instant::now();
+        let now = (received - self.started).as_nanos() as u64 + 1;
+        DISPATCHING.with(|dispatching| dispatching.set(Some(received)));
+        for event in events.iter() {
+            let token = event.token();
+            trace!("event {:?} {:?}", event.readiness(), event.token());
+
+            if token == TOKEN_WAKEUP {
+                self.wakeup
+                    .set_readiness(sys::event::Ready::empty())
+                    .unwrap();
+            } else {
+                turn.events += 1;
+                self.dispatch(token, event.readiness(), now);
+            }
+        }
+        DISPATCHING.with(|dispatching| dispatching.set(None));
+
+        turn.timers = self.timers.process(Instant::now());
+
+        if let Some(start) = start {
+            let dur = start.elapsed();
+            trace!(
+                "loop process - {} events, {}.{:03}s",
+                turn.events,
+                dur.as_secs(),
+                dur.subsec_nanos() / 1_000_000
+            );
+        }
+
+        Ok(turn)
+    }
+
+    /// Spin on non-blocking polls for up to the configured busy poll
+    /// duration, bounded by `max_wait`.
+    ///
+    /// Returns `true` if events were received while spinning, in which case
+    /// the blocking poll must be skipped.
+    fn spin(
+        &self,
+        events: &mut sys::event::Events,
+        spin_window: &mut SpinWindow,
+        max_wait: Option<Duration>,
+    ) -> io::Result<bool> {
+        let max = match busy_poll() {
+            Some(max) => max,
+            None => return Ok(false),
+        };
+        let spin = if busy_poll_adaptive() {
+            spin_window.get(max)
+        } else {
+            max
+        };
+
+        let spin = match max_wait {
+            Some(max_wait) if max_wait < spin => max_wait,
+            _ => spin,
+        };
+        if spin == Duration::ZERO {
+            return Ok(false);
+        }
+
+        let start = Instant::now();
+        let zero = Some(Duration::from_millis(0));
+
+        let hit = loop {
+            if self.io.poll(events, zero)? > 0 {
+                break true;
+            }
+
+            if start.elapsed() >= spin {
+                break false;
+            }
+
+            std::hint::spin_loop();
+        };
+        spin_window.spun(spin, max, hit);
+        Ok(hit)
+    }
+
+    fn dispatch(&self, token: sys::Token, ready: sys::event::Ready, now: u64) {
+        let aba_guard = token.0 & !MAX_SOURCES;
+        let token = token.0 & MAX_SOURCES;
+
+        let mut rd = None;
+        let mut wr = None;
+
+        // Create a scope to ensure that notifying the tasks stays out of the
+        // lock's critical section.
+        {
+            let io_dispatch = self.io_dispatch.read();
+
+            let io = match io_dispatch.get(token) {
+                Some(io) => io,
+                None => return,
+            };
+
+            if aba_guard != io.aba_guard {
+                return;
+            }
+
+            io.readiness.fetch_or(ready.as_usize(), Relaxed);
+            io.last_event.store(now, Relaxed);
+
+            if ready.is_writable() || platform::is_hup(&ready) {
+                wr = io.writer.take();
+            }
+
+            if !(ready & (!sys::event::Ready::writable())).is_empty() {
+                rd = io.reader.take();
+            }
+        }
+
+        if let Some(task) = rd {
+            task.wake();
+        }
+
+        if let Some(task) = wr {
+            task.wake();
+        }
+    }
+
     /// Register an I/O resource with the reactor.
     ///
     /// The registration token is returned.
@@ -484,6 +1028,7 @@ impl Inner {
             readiness: AtomicUsize::new(0),
             reader: AtomicWaker::new(),
             writer: AtomicWaker::new(),
+            last_event: AtomicU64::new(0),
         });
 
         self.io.register(
@@ -535,6 +1080,7 @@ impl Drop for Inner {
             io.writer.wake();
             io.reader.wake();
         }
+        self.timers.fire_all();
     }
 }
 
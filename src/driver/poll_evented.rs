@@ -2,20 +2,48 @@
 //!
 //! This module exposes raw Poll APIs.
 
+#[cfg(not(feature = "runtime"))]
+use self::runtime::coop;
 use super::platform;
 use super::registration::Registration;
 use super::sys::{self, event::Evented};
+use super::Handle;
+#[cfg(feature = "runtime")]
+use crate::runtime::{self, coop};
 
 use futures_io::{AsyncRead, AsyncWrite};
 use futures_util::ready;
 
 use std::fmt;
-use std::io::{self, Read, Write};
+use std::io::{self, IoSlice, Read, Write};
 use std::pin::Pin;
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering::Relaxed;
 use std::task::{Context, Poll};
 
+/// Stands in for the runtime in `net-only` builds, where no task is
+/// budgeted or aborted.
+#[cfg(not(feature = "runtime"))]
+mod runtime {
+    pub(super) fn is_cancelled() -> bool {
+        false
+    }
+
+    pub(super) mod coop {
+        use std::task::{Context, Poll};
+
+        pub(crate) struct Restore;
+
+        impl Restore {
+            pub(crate) fn made_progress(&mut self) {}
+        }
+
+        pub(crate) fn poll_proceed(_cx: &mut Context<'_>) -> Poll<Restore> {
+            Poll::Ready(Restore)
+        }
+    }
+}
+
 /// Associates an I/O resource that implements the AsyncRead/AsyncWrite traits with the reactor that drives it.
 ///
 /// `PollEvented` uses [`Registration`] internally to take a type that
@@ -52,6 +80,12 @@ use std::task::{Context, Poll};
 /// [`clear_write_ready`]. This clears the readiness state until a new readiness
 /// event is received.
 ///
+/// Clearing readiness never makes a system call. The resource is
+/// registered once, edge-triggered and for every kind of readiness, when it
+/// is first polled, and the reactor never modifies the registration
+/// afterwards: readiness is cached in an atomic, cleared in user space, and
+/// set again by the next event of the reactor.
+///
 /// This allows the caller to implement additional functions. For example,
 /// [`TcpListener`] implements poll_accept by using [`poll_read_ready`] and
 /// [`clear_read_ready`].
@@ -73,6 +107,40 @@ use std::task::{Context, Poll};
 /// }
 /// ```
 ///
+/// ## Custom I/O resources
+///
+/// Any resource implementing [`sys::Evented`] and [`std::io::Read`] or
+/// [`std::io::Write`] gets the [`AsyncRead`] or [`AsyncWrite`]
+/// implementation, as does a shared reference to it if `&E` implements the
+/// std traits. File descriptors such as serial ports, character devices or
+/// pipes can be wrapped in a [`sys::Io`], once set to non-blocking mode:
+///
+/// ```
+/// use futures::io::{AsyncReadExt, AsyncWriteExt};
+/// use futures_net::driver::sys::Io;
+/// use futures_net::driver::PollEvented;
+/// use futures_net::runtime::{self, Runtime};
+/// use std::os::unix::io::FromRawFd;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let mut fds = [0; 2];
+/// if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_NONBLOCK | libc::O_CLOEXEC) } < 0 {
+///     return Err(std::io::Error::last_os_error());
+/// }
+/// let mut rx = PollEvented::new(unsafe { Io::from_raw_fd(fds[0]) });
+/// let mut tx = PollEvented::new(unsafe { Io::from_raw_fd(fds[1]) });
+///
+/// let mut rt = runtime::default();
+/// let mut buf = [0; 5];
+/// rt.exec(async {
+///     tx.write_all(b"hello").await?;
+///     rx.read_exact(&mut buf).await
+/// })?;
+/// assert_eq!(&buf, b"hello");
+/// # Ok(())
+/// # }
+/// ```
+///
 /// ## Platform-specific events
 ///
 /// `PollEvented` also allows receiving platform-specific `sys::event::Ready` events.
@@ -81,6 +149,8 @@ use std::task::{Context, Poll};
 ///
 /// [`std::io::Read`]: https://doc.rust-lang.org/std/io/trait.Read.html
 /// [`std::io::Write`]: https://doc.rust-lang.org/std/io/trait.Write.html
+/// [`sys::Evented`]: sys/event/trait.Evented.html
+/// [`sys::Io`]: sys/struct.Io.html
 /// [`AsyncRead`]: ../io/trait.AsyncRead.html
 /// [`AsyncWrite`]: ../io/trait.AsyncWrite.html
 /// [`Registration`]: struct.Registration.html
@@ -136,20 +206,19 @@ where
         self.io.as_mut().unwrap()
     }
 
-    // TODO: restore this once we make reactor::poll_evented public
-    // /// Consumes self, returning the inner I/O object
-    // ///
-    // /// This function will deregister the I/O resource from the reactor before
-    // /// returning. If the deregistration operation fails, an error is returned.
-    // ///
-    // /// Note that deregistering does not guarantee that the I/O resource can be
-    // /// registered with a different reactor. Some I/O resource types can only be
-    // /// associated with a single reactor instance for their lifetime.
-    // pub fn into_inner(mut self) -> io::Result<E> {
-    //     let io = self.io.take().unwrap();
-    //     self.inner.registration.deregister(&io)?;
-    //     Ok(io)
-    // }
+    /// Consumes self, returning the inner I/O object
+    ///
+    /// This function will deregister the I/O resource from the reactor before
+    /// returning. If the deregistration operation fails, an error is returned.
+    ///
+    /// Note that deregistering does not guarantee that the I/O resource can be
+    /// registered with a different reactor. Some I/O resource types can only be
+    /// associated with a single reactor instance for their lifetime.
+    pub fn into_inner(mut self) -> io::Result<E> {
+        let io = self.io.take().unwrap();
+        self.inner.registration.deregister(&io)?;
+        Ok(io)
+    }
 
     /// Check the I/O resource's read readiness state.
     ///
@@ -166,7 +235,21 @@ where
     ///
     /// [`clear_read_ready`]: #method.clear_read_ready
     pub fn poll_read_ready(
-        self: Pin<&mut Self>,
+        &self,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<sys::event::Ready>> {
+        if runtime::is_cancelled() {
+            // The task will be dropped once it yields.
+            return Poll::Pending;
+        }
+        let mut coop = ready!(coop::poll_proceed(cx));
+        let ret = ready!(self.poll_read_ready_unbudgeted(cx))?;
+        coop.made_progress();
+        Poll::Ready(Ok(ret))
+    }
+
+    fn poll_read_ready_unbudgeted(
+        &self,
         cx: &mut Context<'_>,
     ) -> Poll<io::Result<sys::event::Ready>> {
         self.register()?;
@@ -211,20 +294,20 @@ where
     /// Clears the I/O resource's read readiness state and registers the current
     /// task to be notified once a read readiness event is received.
     ///
+    /// This only updates the cached readiness and the waker of the task, the
+    /// registration with the system selector is left as it is.
+    ///
     /// After calling this function, `poll_read_ready` will return `NotReady`
     /// until a new read readiness event has been received.
     ///
     /// The `mask` argument specifies the readiness bits to clear. This may not
     /// include `writable` or `hup`.
-    pub fn clear_read_ready(
-        self: Pin<&mut Self>,
-        cx: &mut Context<'_>,
-    ) -> io::Result<()> {
+    pub fn clear_read_ready(&self, cx: &mut Context<'_>) -> io::Result<()> {
         self.inner
             .read_readiness
             .fetch_and(!sys::event::Ready::readable().as_usize(), Relaxed);
 
-        if self.poll_read_ready(cx)?.is_ready() {
+        if self.poll_read_ready_unbudgeted(cx)?.is_ready() {
             // Notify the current task
             cx.waker().wake_by_ref();
         }
@@ -255,6 +338,20 @@ where
         &self,
         cx: &mut Context<'_>,
     ) -> Poll<Result<sys::event::Ready, io::Error>> {
+        if runtime::is_cancelled() {
+            // The task will be dropped once it yields.
+            return Poll::Pending;
+        }
+        let mut coop = ready!(coop::poll_proceed(cx));
+        let ret = ready!(self.poll_write_ready_unbudgeted(cx))?;
+        coop.made_progress();
+        Poll::Ready(Ok(ret))
+    }
+
+    fn poll_write_ready_unbudgeted(
+        &self,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<sys::event::Ready>> {
         self.register()?;
 
         // Load cached & encoded readiness.
@@ -306,15 +403,12 @@ where
     /// # Panics
     ///
     /// This function will panic if called from outside of a task context.
-    pub fn clear_write_ready(
-        self: Pin<&mut Self>,
-        cx: &mut Context<'_>,
-    ) -> io::Result<()> {
+    pub fn clear_write_ready(&self, cx: &mut Context<'_>) -> io::Result<()> {
         self.inner
             .write_readiness
             .fetch_and(!sys::event::Ready::writable().as_usize(), Relaxed);
 
-        if self.poll_write_ready(cx)?.is_ready() {
+        if self.poll_write_ready_unbudgeted(cx)?.is_ready() {
             // Notify the current task
             cx.waker().wake_by_ref();
         }
@@ -322,6 +416,146 @@ where
         Ok(())
     }
 
+    /// Runs the read `f` on the I/O resource right away, outside of any task.
+    ///
+    /// If `f` fails with `WouldBlock`, the cached read readiness is cleared
+    /// so the next [`poll_read_ready`] waits for a new event, but no task is
+    /// registered for wakeup.
+    ///
+    /// [`poll_read_ready`]: #method.poll_read_ready
+    pub fn try_read_io<R>(&self, f: impl FnOnce(&E) -> io::Result<R>) -> io::Result<R> {
+        let r = f(self.get_ref());
+        if is_wouldblock(&r) {
+            self.inner
+                .read_readiness
+                .fetch_and(!sys::event::Ready::readable().as_usize(), Relaxed);
+        }
+        r
+    }
+
+    /// Runs the write `f` on the I/O resource right away, like
+    /// [`try_read_io`] for reads.
+    ///
+    /// [`try_read_io`]: #method.try_read_io
+    pub fn try_write_io<R>(&self, f: impl FnOnce(&E) -> io::Result<R>) -> io::Result<R> {
+        let r = f(self.get_ref());
+        if is_wouldblock(&r) {
+            self.inner
+                .write_readiness
+                .fetch_and(!sys::event::Ready::writable().as_usize(), Relaxed);
+        }
+        r
+    }
+
+    /// Reads through a shared reference to the I/O resource, like
+    /// `AsyncRead::poll_read`.
+    ///
+    /// Readiness is tracked per direction, not per caller: when several
+    /// tasks read at the same time, only the last one to wait is woken up.
+    pub fn poll_read_ref(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>>
+    where
+        for<'a> &'a E: Read,
+    {
+        ready!(self.poll_read_ready(cx)?);
+
+        let r = self.get_ref().read(buf);
+
+        if is_wouldblock(&r) {
+            self.clear_read_ready(cx)?;
+            Poll::Pending
+        } else {
+            Poll::Ready(r)
+        }
+    }
+
+    /// Writes through a shared reference to the I/O resource, like
+    /// `AsyncWrite::poll_write`.
+    ///
+    /// Like [`poll_read_ref`], only the last task to wait is woken up.
+    ///
+    /// [`poll_read_ref`]: #method.poll_read_ref
+    pub fn poll_write_ref(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>>
+    where
+        for<'a> &'a E: Write,
+    {
+        ready!(self.poll_write_ready(cx)?);
+
+        let r = self.get_ref().write(buf);
+
+        if is_wouldblock(&r) {
+            self.clear_write_ready(cx)?;
+            Poll::Pending
+        } else {
+            Poll::Ready(r)
+        }
+    }
+
+    /// Writes `bufs` through a shared reference to the I/O resource with a
+    /// single call to `Write::write_vectored`, like
+    /// `AsyncWrite::poll_write_vectored`.
+    pub fn poll_write_vectored_ref(
+        &self,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>>
+    where
+        for<'a> &'a E: Write,
+    {
+        ready!(self.poll_write_ready(cx)?);
+
+        let r = self.get_ref().write_vectored(bufs);
+
+        if is_wouldblock(&r) {
+            self.clear_write_ready(cx)?;
+            Poll::Pending
+        } else {
+            Poll::Ready(r)
+        }
+    }
+
+    /// Flushes through a shared reference to the I/O resource, like
+    /// `AsyncWrite::poll_flush`.
+    pub fn poll_flush_ref(&self, cx: &mut Context<'_>) -> Poll<io::Result<()>>
+    where
+        for<'a> &'a E: Write,
+    {
+        ready!(self.poll_write_ready(cx)?);
+
+        let r = self.get_ref().flush();
+
+        if is_wouldblock(&r) {
+            self.clear_write_ready(cx)?;
+            Poll::Pending
+        } else {
+            Poll::Ready(r)
+        }
+    }
+
+    /// Registers the I/O resource with the reactor of `handle`, rather than
+    /// with the reactor of the task which polls it first.
+    ///
+    /// Fails with `AlreadyExists` if the resource is registered already,
+    /// i.e. if it was polled before.
+    pub fn register_with(&self, handle: &Handle) -> io::Result<()> {
+        let io = self.io.as_ref().unwrap();
+        if self.inner.registration.register_with(io, handle)? {
+            Ok(())
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                "the I/O resource is already registered with a reactor",
+            ))
+        }
+    }
+
     /// Ensure that the I/O resource is registered with the reactor.
     fn register(&self) -> io::Result<()> {
         self.inner
@@ -376,6 +610,23 @@ where
         }
     }
 
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        ready!(self.poll_write_ready(cx)?);
+
+        let r = PollEvented::get_mut(&mut *self).write_vectored(bufs);
+
+        if is_wouldblock(&r) {
+            self.clear_write_ready(cx)?;
+            Poll::Pending
+        } else {
+            Poll::Ready(r)
+        }
+    }
+
     fn poll_flush(
         mut self: Pin<&mut Self>,
         cx: &mut Context<'_>,
@@ -397,6 +648,50 @@ where
     }
 }
 
+impl<E> AsyncRead for &PollEvented<E>
+where
+    E: Evented,
+    for<'a> &'a E: Read,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        self.poll_read_ref(cx, buf)
+    }
+}
+
+impl<E> AsyncWrite for &PollEvented<E>
+where
+    E: Evented,
+    for<'a> &'a E: Write,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.poll_write_ref(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        self.poll_write_vectored_ref(cx, bufs)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_flush_ref(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
 fn is_wouldblock<T>(r: &io::Result<T>) -> bool {
     match *r {
         Ok(_) => false,
@@ -418,3 +713,31 @@ impl<E: Evented> Drop for PollEvented<E> {
         }
     }
 }
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_shared_references_are_async_io() {
+    use crate::runtime::{self, Runtime};
+    use futures_util::future;
+    use futures_util::io::{AsyncReadExt, AsyncWriteExt};
+
+    use std::os::unix::io::FromRawFd;
+
+    let mut fds = [0; 2];
+    let flags = libc::O_NONBLOCK | libc::O_CLOEXEC;
+    assert_eq!(unsafe { libc::pipe2(fds.as_mut_ptr(), flags) }, 0);
+    let rx = PollEvented::new(unsafe { sys::Io::from_raw_fd(fds[0]) });
+    let tx = PollEvented::new(unsafe { sys::Io::from_raw_fd(fds[1]) });
+
+    let mut rt = runtime::default();
+    let mut buf = [0; 4];
+    rt.exec(async {
+        // The reader waits first, so the write has to wake it up.
+        let read = async { (&rx).read_exact(&mut buf).await };
+        let write = async { (&tx).write_all(b"ping").await };
+        let (read, write) = future::join(read, write).await;
+        read.unwrap();
+        write.unwrap();
+    });
+    assert_eq!(&buf, b"ping");
+}
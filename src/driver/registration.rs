@@ -5,7 +5,7 @@ use std::task::{Context, Poll, Waker};
 use std::{io, ptr, usize};
 
 use super::sys::{self, event::Evented};
-use super::{Direction, HandlePriv};
+use super::{Direction, Handle, HandlePriv};
 
 /// Associates an I/O resource with the reactor instance that drives it.
 ///
@@ -114,6 +114,20 @@ impl Registration {
         self.register2(io, || HandlePriv::try_current())
     }
 
+    /// Register the I/O resource with the reactor of `handle`, or with the
+    /// current one for a default handle.
+    ///
+    /// Returns like [`register`], which the I/O resource is registered with
+    /// once it was registered.
+    ///
+    /// [`register`]: #method.register
+    pub fn register_with(&self, io: &impl Evented, handle: &Handle) -> io::Result<bool> {
+        self.register2(io, || match handle.as_priv() {
+            Some(handle) => Ok(handle.clone()),
+            None => HandlePriv::try_current(),
+        })
+    }
+
     /// Deregister the I/O resource from the reactor it is associated with.
     ///
     /// This function must be called before the I/O resource associated with the
@@ -0,0 +1,141 @@
+//! The interface between the driver and an OS backend.
+//!
+//! `Poll` and `Event` only reach the system event queue through these
+//! traits. A backend lives in its own module, picked as `platform` in
+//! `sys/mod.rs` by `target_os`, and provides:
+//!
+//! * a [`Backend`] naming its selector, event buffer, raw event and
+//!   awakener types, which implement the traits of this module;
+//! * the socket shims used by `net`: `Io`, `set_nonblock`, `TcpStream`,
+//!   `TcpListener`, `UdpSocket` and `sockopt`. Backends of systems with
+//!   BSD sockets re-export the ones of `unix`.
+//!
+//! [`Backend`]: trait.Backend.html
+
+use std::fmt;
+use std::io;
+use std::os::unix::io::RawFd;
+use std::time::Duration;
+
+use super::event::{Event, Evented, PollOpt, Ready};
+use super::Token;
+
+/// The types making up a backend.
+pub trait Backend {
+    /// The system event queue.
+    type Selector: Selector<Events = Self::Events>;
+    /// The buffer `Selector::select` fills.
+    type Events: Events<Event = Self::Event>;
+    /// An event of the buffer, which `Event` wraps.
+    type Event: RawEvent;
+    /// Wakes a thread blocked in `Selector::select`.
+    type Awakener: Awakener;
+
+    /// The `UnixReady` bits the backend can report, on top of readable and
+    /// writable.
+    const READY_ALL: usize;
+}
+
+/// A system event queue.
+pub trait Selector: Sized + Send + Sync + fmt::Debug {
+    /// The buffer of events filled by `select`.
+    type Events;
+
+    /// Creates a queue with nothing registered.
+    fn new() -> io::Result<Self>;
+
+    /// Returns an id unique to this selector among the live ones.
+    fn id(&self) -> usize;
+
+    /// Waits up to `timeout`, forever if `None`, for events and stores them
+    /// in `evts`.
+    ///
+    /// The event of `awakener` is left out of `evts`, the returned flag
+    /// tells whether it was received.
+    fn select(
+        &self,
+        evts: &mut Self::Events,
+        awakener: Token,
+        timeout: Option<Duration>,
+    ) -> io::Result<bool>;
+
+    /// Starts watching `fd` for `interests`, reporting events with `token`.
+    fn register(
+        &self,
+        fd: RawFd,
+        token: Token,
+        interests: Ready,
+        opts: PollOpt,
+    ) -> io::Result<()>;
+
+    /// Changes the interests, token or options `fd` is watched with.
+    fn reregister(
+        &self,
+        fd: RawFd,
+        token: Token,
+        interests: Ready,
+        opts: PollOpt,
+    ) -> io::Result<()>;
+
+    /// Stops watching `fd`.
+    fn deregister(&self, fd: RawFd) -> io::Result<()>;
+}
+
+/// A buffer of events, filled by the selector and by the user space
+/// readiness queue.
+pub trait Events: Sized {
+    /// The raw events of the buffer.
+    type Event;
+
+    /// Creates a buffer with room for `capacity` events.
+    fn with_capacity(capacity: usize) -> Self;
+
+    /// Returns the number of events in the buffer.
+    fn len(&self) -> usize;
+
+    /// Returns the number of events the buffer has room for.
+    fn capacity(&self) -> usize;
+
+    /// Returns `true` if the buffer holds no event.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the event at `idx`.
+    fn get(&self, idx: usize) -> Option<&Event>;
+
+    /// Appends an event.
+    fn push_event(&mut self, readiness: Ready, token: Token);
+
+    /// Drops all events while keeping the allocation for the next `select`.
+    fn clear(&mut self);
+}
+
+/// The raw event of a backend, which `Event` is a transparent wrapper of.
+pub trait RawEvent: Copy {
+    /// Builds an event from a readiness set and a token.
+    ///
+    /// Every readiness bit must survive a round trip through the event.
+    fn new(readiness: Ready, token: Token) -> Self;
+
+    /// Decodes the readiness set of the event.
+    fn readiness(&self) -> Ready;
+
+    /// Decodes the token of the event.
+    fn token(&self) -> Token;
+}
+
+/// Wakes a thread blocked in `Selector::select`.
+///
+/// The awakener is registered with the `Poll` as any `Evented`, its events
+/// carry the token it was registered with.
+pub trait Awakener: Evented + Sized + Send + Sync {
+    /// Creates an awakener, which has to be registered to wake anything.
+    fn new() -> io::Result<Self>;
+
+    /// Makes the current or next `select` return.
+    fn wakeup(&self) -> io::Result<()>;
+
+    /// Resets the awakener after its event was received.
+    fn cleanup(&self);
+}
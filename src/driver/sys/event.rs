@@ -3,8 +3,9 @@
 use std::os::unix::io::RawFd;
 use std::{fmt, io, ops};
 
+use super::backend::{Backend, RawEvent, Selector as _};
 pub use super::poll::{Events, Iter};
-use super::{linux, poll};
+use super::{platform, poll, SysEvent};
 use crate::driver::sys::{Poll, Token};
 
 /// A value that may be registered with `Poll`
@@ -845,7 +846,7 @@ impl Ready {
     /// [`Poll`]: struct.Poll.html
     #[inline]
     pub fn all() -> Ready {
-        Ready(READABLE | WRITABLE | linux::READY_ALL)
+        Ready(READABLE | WRITABLE | <platform::Platform as Backend>::READY_ALL)
     }
 
     /// Returns true if `Ready` is the empty set
@@ -1184,10 +1185,10 @@ fn test_debug_ready() {
 /// [`Poll`]: ../struct.Poll.html
 /// [readiness state]: ../struct.Ready.html
 /// [`Token`]: ../struct.Token.html
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[derive(Copy, Clone)]
+#[repr(transparent)]
 pub struct Event {
-    kind: Ready,
-    token: Token,
+    inner: SysEvent,
 }
 
 impl Event {
@@ -1206,11 +1207,17 @@ impl Event {
     /// ```
     pub fn new(readiness: Ready, token: Token) -> Event {
         Event {
-            kind: readiness,
-            token,
+            inner: <SysEvent as RawEvent>::new(readiness, token),
         }
     }
 
+    /// Reinterpret a raw event from the selector buffer as an `Event`.
+    #[inline]
+    pub(crate) fn from_sys(inner: &SysEvent) -> &Event {
+        // Safety: `Event` is `repr(transparent)` over `SysEvent`.
+        unsafe { &*(inner as *const SysEvent as *const Event) }
+    }
+
     /// Returns the event's readiness.
     ///
     /// # Examples
@@ -1224,7 +1231,7 @@ impl Event {
     /// assert_eq!(event.readiness(), Ready::readable() | Ready::writable());
     /// ```
     pub fn readiness(&self) -> Ready {
-        self.kind
+        self.inner.readiness()
     }
 
     /// Returns the event's token.
@@ -1240,10 +1247,38 @@ impl Event {
     /// assert_eq!(event.token(), Token(0));
     /// ```
     pub fn token(&self) -> Token {
-        self.token
+        self.inner.token()
     }
 }
 
+impl PartialEq for Event {
+    fn eq(&self, other: &Event) -> bool {
+        self.readiness() == other.readiness() && self.token() == other.token()
+    }
+}
+
+impl Eq for Event {}
+
+impl fmt::Debug for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Event")
+            .field("kind", &self.readiness())
+            .field("token", &self.token())
+            .finish()
+    }
+}
+
+#[test]
+fn test_event_round_trip() {
+    use super::UnixReady;
+
+    let ready = Ready::readable() | UnixReady::error() | UnixReady::hup();
+    let event = Event::new(ready, Token(7));
+
+    assert_eq!(event.readiness(), ready);
+    assert_eq!(event.token(), Token(7));
+}
+
 /*
  *
  * ===== Mio internal helpers =====
@@ -1265,9 +1300,3 @@ pub fn ready_from_usize(events: usize) -> Ready {
 pub fn opt_from_usize(opt: usize) -> PollOpt {
     PollOpt(opt)
 }
-
-// Used internally to mutate an `Event` in place
-#[allow(dead_code)]
-pub fn kind_mut(event: &mut Event) -> &mut Ready {
-    &mut event.kind
-}
@@ -9,9 +9,10 @@ use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
 use std::time::Duration;
 use std::{cmp, i32};
 
+use crate::driver::sys::backend;
 use crate::driver::sys::event::{Event, PollOpt, Ready};
-use crate::driver::sys::linux::io::set_cloexec;
-use crate::driver::sys::linux::{cvt, UnixReady};
+use crate::driver::sys::unix::io::set_cloexec;
+use crate::driver::sys::unix::{cvt, UnixReady};
 use crate::driver::sys::Token;
 
 /// Each Selector has a globally unique(ish) ID associated with it. This ID
@@ -27,8 +28,10 @@ pub struct Selector {
     epfd: RawFd,
 }
 
-impl Selector {
-    pub fn new() -> io::Result<Selector> {
+impl backend::Selector for Selector {
+    type Events = Events;
+
+    fn new() -> io::Result<Selector> {
         let epfd = unsafe {
             // Emulate `epoll_create` by using `epoll_create1` if it's available
             // and otherwise falling back to `epoll_create` followed by a call to
@@ -51,12 +54,12 @@ impl Selector {
         Ok(Selector { id: id, epfd: epfd })
     }
 
-    pub fn id(&self) -> usize {
+    fn id(&self) -> usize {
         self.id
     }
 
     /// Wait for events from the OS
-    pub fn select(
+    fn select(
         &self,
         evts: &mut Events,
         awakener: Token,
@@ -67,7 +70,7 @@ impl Selector {
             .unwrap_or(-1);
 
         // Wait for epoll events for at most timeout_ms milliseconds
-        evts.clear();
+        evts.events.clear();
         unsafe {
             let cnt = cvt(libc::epoll_wait(
                 self.epfd,
@@ -78,9 +81,11 @@ impl Selector {
             let cnt = cnt as usize;
             evts.events.set_len(cnt);
 
+            // The awakener event carries no information for the caller, so
+            // swap it out rather than shifting the rest of the buffer.
             for i in 0..cnt {
                 if evts.events[i].u64 as usize == awakener.into() {
-                    evts.events.remove(i);
+                    evts.events.swap_remove(i);
                     return Ok(true);
                 }
             }
@@ -90,7 +95,7 @@ impl Selector {
     }
 
     /// Register event interests for the given IO handle with the OS
-    pub fn register(
+    fn register(
         &self,
         fd: RawFd,
         token: Token,
@@ -114,7 +119,7 @@ impl Selector {
     }
 
     /// Register event interests for the given IO handle with the OS
-    pub fn reregister(
+    fn reregister(
         &self,
         fd: RawFd,
         token: Token,
@@ -138,7 +143,7 @@ impl Selector {
     }
 
     /// Deregister event interests for the given IO handle with the OS
-    pub fn deregister(&self, fd: RawFd) -> io::Result<()> {
+    fn deregister(&self, fd: RawFd) -> io::Result<()> {
         // The &info argument should be ignored by the system,
         // but linux < 2.6.9 required it to be not null.
         // For compatibility, we provide a dummy EpollEvent.
@@ -200,76 +205,119 @@ impl Drop for Selector {
     }
 }
 
+/// Raw event type filled in by `epoll_wait`.
+///
+/// `Event` is a transparent wrapper around this type, which is what allows
+/// `Events` to hand out references straight into the kernel buffer.
+impl backend::RawEvent for libc::epoll_event {
+    /// Unlike `ioevent_to_epoll`, error and hup readiness are preserved so
+    /// that user space readiness survives a round trip through the buffer.
+    fn new(readiness: Ready, token: Token) -> libc::epoll_event {
+        let unix = UnixReady::from(readiness);
+        let mut kind = 0;
+
+        if readiness.is_readable() {
+            kind |= EPOLLIN;
+        }
+
+        if readiness.is_writable() {
+            kind |= EPOLLOUT;
+        }
+
+        if unix.is_priority() {
+            kind |= EPOLLPRI;
+        }
+
+        if unix.is_error() {
+            kind |= EPOLLERR;
+        }
+
+        if unix.is_hup() {
+            kind |= EPOLLHUP;
+        }
+
+        libc::epoll_event {
+            events: kind as u32,
+            u64: usize::from(token) as u64,
+        }
+    }
+
+    #[inline]
+    fn readiness(&self) -> Ready {
+        let epoll = self.events as c_int;
+        let mut kind = Ready::empty();
+
+        if (epoll & EPOLLIN) != 0 {
+            kind = kind | Ready::readable();
+        }
+
+        if (epoll & EPOLLPRI) != 0 {
+            kind = kind | Ready::readable() | UnixReady::priority();
+        }
+
+        if (epoll & EPOLLOUT) != 0 {
+            kind = kind | Ready::writable();
+        }
+
+        // EPOLLHUP - Usually means a socket error happened
+        if (epoll & EPOLLERR) != 0 {
+            kind = kind | UnixReady::error();
+        }
+
+        if (epoll & EPOLLHUP) != 0 {
+            kind = kind | UnixReady::hup();
+        }
+
+        kind
+    }
+
+    #[inline]
+    fn token(&self) -> Token {
+        Token(self.u64 as usize)
+    }
+}
+
 pub struct Events {
     events: Vec<libc::epoll_event>,
 }
 
-impl Events {
-    pub fn with_capacity(u: usize) -> Events {
+impl backend::Events for Events {
+    type Event = libc::epoll_event;
+
+    fn with_capacity(u: usize) -> Events {
         Events {
             events: Vec::with_capacity(u),
         }
     }
 
     #[inline]
-    pub fn len(&self) -> usize {
+    fn len(&self) -> usize {
         self.events.len()
     }
 
     #[inline]
-    pub fn capacity(&self) -> usize {
+    fn capacity(&self) -> usize {
         self.events.capacity()
     }
 
     #[inline]
-    pub fn is_empty(&self) -> bool {
+    fn is_empty(&self) -> bool {
         self.events.is_empty()
     }
 
     #[inline]
-    pub fn get(&self, idx: usize) -> Option<Event> {
-        self.events.get(idx).map(|event| {
-            let epoll = event.events as c_int;
-            let mut kind = Ready::empty();
-
-            if (epoll & EPOLLIN) != 0 {
-                kind = kind | Ready::readable();
-            }
-
-            if (epoll & EPOLLPRI) != 0 {
-                kind = kind | Ready::readable() | UnixReady::priority();
-            }
-
-            if (epoll & EPOLLOUT) != 0 {
-                kind = kind | Ready::writable();
-            }
-
-            // EPOLLHUP - Usually means a socket error happened
-            if (epoll & EPOLLERR) != 0 {
-                kind = kind | UnixReady::error();
-            }
-
-            if (epoll & EPOLLHUP) != 0 {
-                kind = kind | UnixReady::hup();
-            }
-
-            let token = self.events[idx].u64;
-
-            Event::new(kind, Token(token as usize))
-        })
+    fn get(&self, idx: usize) -> Option<&Event> {
+        self.events.get(idx).map(Event::from_sys)
     }
 
-    pub fn push_event(&mut self, event: Event) {
-        self.events.push(libc::epoll_event {
-            events: ioevent_to_epoll(event.readiness(), PollOpt::empty()),
-            u64: usize::from(event.token()) as u64,
-        });
+    fn push_event(&mut self, readiness: Ready, token: Token) {
+        self.events.push(backend::RawEvent::new(readiness, token));
     }
 
-    pub fn clear(&mut self) {
-        unsafe {
-            self.events.set_len(0);
-        }
+    /// Drop all events while keeping the allocation for the next `select`.
+    #[inline]
+    fn clear(&mut self) {
+        self.events.clear();
     }
 }
 
@@ -281,7 +329,7 @@ const MILLIS_PER_SEC: u64 = 1_000;
 ///
 /// The saturating is fine because `u64::MAX` milliseconds are still many
 /// million years.
-pub fn millis(duration: Duration) -> u64 {
+fn millis(duration: Duration) -> u64 {
     // Round up.
     let millis = (duration.subsec_nanos() + NANOS_PER_MILLI - 1) / NANOS_PER_MILLI;
     duration
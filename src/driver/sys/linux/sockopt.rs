@@ -0,0 +1,44 @@
+//! Raw socket options which are not covered by `net2`.
+
+use libc::{self, c_int};
+use std::io;
+use std::os::unix::io::RawFd;
+
+pub use crate::driver::sys::unix::sockopt::{getsockopt, setsockopt};
+
+// Not every target exports these through `libc`, the values are stable
+// across the architectures we support.
+pub const SO_BUSY_POLL: c_int = 46;
+pub const SO_PREFER_BUSY_POLL: c_int = 69;
+pub const SO_INCOMING_CPU: c_int = 49;
+
+pub fn set_busy_poll(fd: RawFd, micros: u32) -> io::Result<()> {
+    setsockopt(fd, libc::SOL_SOCKET, SO_BUSY_POLL, micros as c_int)
+}
+
+pub fn busy_poll(fd: RawFd) -> io::Result<u32> {
+    getsockopt::<c_int>(fd, libc::SOL_SOCKET, SO_BUSY_POLL).map(|v| v as u32)
+}
+
+pub fn set_prefer_busy_poll(fd: RawFd, prefer: bool) -> io::Result<()> {
+    setsockopt(fd, libc::SOL_SOCKET, SO_PREFER_BUSY_POLL, prefer as c_int)
+}
+
+pub fn prefer_busy_poll(fd: RawFd) -> io::Result<bool> {
+    getsockopt::<c_int>(fd, libc::SOL_SOCKET, SO_PREFER_BUSY_POLL).map(|v| v != 0)
+}
+
+pub fn set_incoming_cpu(fd: RawFd, cpu: usize) -> io::Result<()> {
+    setsockopt(fd, libc::SOL_SOCKET, SO_INCOMING_CPU, cpu as c_int)
+}
+
+/// `None` until the socket received a packet.
+pub fn incoming_cpu(fd: RawFd) -> io::Result<Option<usize>> {
+    getsockopt::<c_int>(fd, libc::SOL_SOCKET, SO_INCOMING_CPU).map(|v| {
+        if v < 0 {
+            None
+        } else {
+            Some(v as usize)
+        }
+    })
+}
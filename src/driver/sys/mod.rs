@@ -25,11 +25,28 @@
 
 pub mod event;
 pub mod net;
+pub mod process;
 
-mod linux;
+mod backend;
 mod poll;
 mod token;
+#[macro_use]
+mod unix;
+
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "linux")]
+use self::linux as platform;
+
+use self::backend::Backend;
+
+// What `Poll` and `Event` are built on, see `backend` for adding a platform.
+type SysSelector = <platform::Platform as Backend>::Selector;
+type SysEvents = <platform::Platform as Backend>::Events;
+type SysEvent = <platform::Platform as Backend>::Event;
+type SysAwakener = <platform::Platform as Backend>::Awakener;
 
-pub use self::linux::UnixReady;
+pub use self::platform::Io;
 pub use self::poll::{Poll, Registration, SetReadiness};
 pub use self::token::Token;
+pub use self::unix::UnixReady;
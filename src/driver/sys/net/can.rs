@@ -0,0 +1,114 @@
+use libc::{self, c_int};
+use std::io;
+use std::mem;
+use std::os::unix::prelude::*;
+
+use super::fd::SocketFd;
+use crate::driver::sys::event::{Evented, PollOpt, Ready};
+use crate::driver::sys::{Poll, Token};
+
+// From `linux/can.h`, which `libc` doesn't cover on every target.
+const AF_CAN: c_int = 29;
+const CAN_RAW: c_int = 1;
+const CAN_BCM: c_int = 2;
+pub const SOL_CAN_RAW: c_int = 101;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct sockaddr_can {
+    can_family: u16,
+    can_ifindex: c_int,
+    can_addr: [u64; 2],
+}
+
+/// An `AF_CAN` socket, either raw or talking to the broadcast manager.
+#[derive(Debug)]
+pub struct CanSocket {
+    fd: SocketFd,
+}
+
+impl CanSocket {
+    /// Opens a raw socket receiving the frames of the interface `ifindex`,
+    /// or of every interface if it is 0.
+    pub fn raw(ifindex: u32) -> io::Result<CanSocket> {
+        let fd = SocketFd::new(AF_CAN, libc::SOCK_RAW, CAN_RAW)?;
+        fd.bind(&can_addr(ifindex))?;
+        Ok(CanSocket { fd })
+    }
+
+    /// Opens a broadcast manager socket for the interface `ifindex`.
+    pub fn bcm(ifindex: u32) -> io::Result<CanSocket> {
+        let fd = SocketFd::new(AF_CAN, libc::SOCK_DGRAM, CAN_BCM)?;
+        fd.connect(&can_addr(ifindex))?;
+        Ok(CanSocket { fd })
+    }
+
+    pub fn setsockopt<T: Copy>(
+        &self,
+        level: c_int,
+        name: c_int,
+        val: T,
+    ) -> io::Result<()> {
+        self.fd.setsockopt(level, name, val)
+    }
+
+    pub fn setsockopt_bytes(
+        &self,
+        level: c_int,
+        name: c_int,
+        val: &[u8],
+    ) -> io::Result<()> {
+        self.fd.setsockopt_bytes(level, name, val)
+    }
+
+    pub fn send(&self, buf: &[u8]) -> io::Result<usize> {
+        self.fd.send(buf)
+    }
+
+    pub fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.fd.recv(buf)
+    }
+
+    pub fn take_error(&self) -> io::Result<Option<io::Error>> {
+        self.fd.take_error()
+    }
+}
+
+fn can_addr(ifindex: u32) -> sockaddr_can {
+    let mut addr: sockaddr_can = unsafe { mem::zeroed() };
+    addr.can_family = AF_CAN as u16;
+    addr.can_ifindex = ifindex as c_int;
+    addr
+}
+
+impl Evented for CanSocket {
+    fn register(
+        &self,
+        poll: &Poll,
+        token: Token,
+        events: Ready,
+        opts: PollOpt,
+    ) -> io::Result<()> {
+        self.fd.register(poll, token, events, opts)
+    }
+
+    fn reregister(
+        &self,
+        poll: &Poll,
+        token: Token,
+        events: Ready,
+        opts: PollOpt,
+    ) -> io::Result<()> {
+        self.fd.reregister(poll, token, events, opts)
+    }
+
+    fn deregister(&self, poll: &Poll) -> io::Result<()> {
+        self.fd.deregister(poll)
+    }
+}
+
+impl AsRawFd for CanSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
@@ -0,0 +1,391 @@
+use libc::{self, c_int, c_void, socklen_t};
+use std::io;
+use std::mem;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+use std::os::unix::prelude::*;
+
+use crate::driver::sys::event::{Evented, EventedFd, PollOpt, Ready};
+use crate::driver::sys::platform::sockopt;
+use crate::driver::sys::{Poll, Token};
+
+/// A nonblocking socket of any family, for the protocols `std::net` doesn't
+/// cover.
+///
+/// Addresses are passed as raw `sockaddr` structures, the wrappers around
+/// it are responsible for their layout.
+#[derive(Debug)]
+pub struct SocketFd {
+    fd: c_int,
+}
+
+impl SocketFd {
+    /// Opens a `CLOEXEC`, nonblocking socket.
+    pub fn new(domain: c_int, ty: c_int, protocol: c_int) -> io::Result<SocketFd> {
+        let ty = ty | libc::SOCK_CLOEXEC | libc::SOCK_NONBLOCK;
+        let fd = cvt(unsafe { libc::socket(domain, ty, protocol) })?;
+        Ok(SocketFd { fd })
+    }
+
+    pub fn bind<T>(&self, addr: &T) -> io::Result<()> {
+        let len = mem::size_of::<T>() as socklen_t;
+        let addr = addr as *const T as *const libc::sockaddr;
+        cvt(unsafe { libc::bind(self.fd, addr, len) }).map(|_| ())
+    }
+
+    pub fn connect<T>(&self, addr: &T) -> io::Result<()> {
+        let len = mem::size_of::<T>() as socklen_t;
+        let addr = addr as *const T as *const libc::sockaddr;
+        cvt(unsafe { libc::connect(self.fd, addr, len) }).map(|_| ())
+    }
+
+    pub fn bind_inet(&self, addr: &SocketAddr) -> io::Result<()> {
+        let (addr, len) = inet_addr(addr);
+        let addr = &addr as *const _ as *const libc::sockaddr;
+        cvt(unsafe { libc::bind(self.fd, addr, len) }).map(|_| ())
+    }
+
+    /// Starts connecting to `addr`, without waiting for the connection to
+    /// complete.
+    pub fn connect_inet(&self, addr: &SocketAddr) -> io::Result<()> {
+        let (addr, len) = inet_addr(addr);
+        let addr = &addr as *const _ as *const libc::sockaddr;
+        match cvt(unsafe { libc::connect(self.fd, addr, len) }) {
+            Err(ref e) if e.raw_os_error() == Some(libc::EINPROGRESS) => Ok(()),
+            r => r.map(|_| ()),
+        }
+    }
+
+    pub fn listen(&self, backlog: c_int) -> io::Result<()> {
+        cvt(unsafe { libc::listen(self.fd, backlog) }).map(|_| ())
+    }
+
+    /// Accepts a connection, returning a `CLOEXEC`, nonblocking socket.
+    pub fn accept(&self) -> io::Result<(SocketFd, libc::sockaddr_storage)> {
+        unsafe {
+            let mut addr: libc::sockaddr_storage = mem::zeroed();
+            let mut len = mem::size_of_val(&addr) as socklen_t;
+            let fd = cvt(libc::accept4(
+                self.fd,
+                &mut addr as *mut _ as *mut libc::sockaddr,
+                &mut len,
+                libc::SOCK_CLOEXEC | libc::SOCK_NONBLOCK,
+            ))?;
+            Ok((SocketFd { fd }, addr))
+        }
+    }
+
+    pub fn local_addr(&self) -> io::Result<libc::sockaddr_storage> {
+        unsafe {
+            let mut addr: libc::sockaddr_storage = mem::zeroed();
+            let mut len = mem::size_of_val(&addr) as socklen_t;
+            cvt(libc::getsockname(
+                self.fd,
+                &mut addr as *mut _ as *mut libc::sockaddr,
+                &mut len,
+            ))?;
+            Ok(addr)
+        }
+    }
+
+    pub fn peer_addr(&self) -> io::Result<libc::sockaddr_storage> {
+        unsafe {
+            let mut addr: libc::sockaddr_storage = mem::zeroed();
+            let mut len = mem::size_of_val(&addr) as socklen_t;
+            cvt(libc::getpeername(
+                self.fd,
+                &mut addr as *mut _ as *mut libc::sockaddr,
+                &mut len,
+            ))?;
+            Ok(addr)
+        }
+    }
+
+    pub fn shutdown(&self, how: c_int) -> io::Result<()> {
+        cvt(unsafe { libc::shutdown(self.fd, how) }).map(|_| ())
+    }
+
+    /// Receives a message, returning its length and the `MSG_*` flags the
+    /// kernel reported for it.
+    pub fn recv_msg(&self, buf: &mut [u8]) -> io::Result<(usize, c_int)> {
+        unsafe {
+            let mut iov = libc::iovec {
+                iov_base: buf.as_mut_ptr() as *mut c_void,
+                iov_len: buf.len(),
+            };
+            let mut msg: libc::msghdr = mem::zeroed();
+            msg.msg_iov = &mut iov;
+            msg.msg_iovlen = 1;
+            let n = cvt_size(libc::recvmsg(self.fd, &mut msg, 0))?;
+            Ok((n, msg.msg_flags))
+        }
+    }
+
+    pub fn send(&self, buf: &[u8]) -> io::Result<usize> {
+        let n = unsafe {
+            libc::send(
+                self.fd,
+                buf.as_ptr() as *const c_void,
+                buf.len(),
+                libc::MSG_NOSIGNAL,
+            )
+        };
+        cvt_size(n)
+    }
+
+    pub fn send_to<T>(&self, buf: &[u8], addr: &T) -> io::Result<usize> {
+        let n = unsafe {
+            libc::sendto(
+                self.fd,
+                buf.as_ptr() as *const c_void,
+                buf.len(),
+                libc::MSG_NOSIGNAL,
+                addr as *const T as *const libc::sockaddr,
+                mem::size_of::<T>() as socklen_t,
+            )
+        };
+        cvt_size(n)
+    }
+
+    pub fn send_to_inet(&self, buf: &[u8], addr: &SocketAddr) -> io::Result<usize> {
+        let (addr, len) = inet_addr(addr);
+        let n = unsafe {
+            libc::sendto(
+                self.fd,
+                buf.as_ptr() as *const c_void,
+                buf.len(),
+                libc::MSG_NOSIGNAL,
+                &addr as *const _ as *const libc::sockaddr,
+                len,
+            )
+        };
+        cvt_size(n)
+    }
+
+    pub fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = unsafe {
+            libc::recv(self.fd, buf.as_mut_ptr() as *mut c_void, buf.len(), 0)
+        };
+        cvt_size(n)
+    }
+
+    /// Receives a message and the address of its sender, which the caller
+    /// has to interpret according to the family of the socket.
+    pub fn recv_from(
+        &self,
+        buf: &mut [u8],
+    ) -> io::Result<(usize, libc::sockaddr_storage)> {
+        unsafe {
+            let mut addr: libc::sockaddr_storage = mem::zeroed();
+            let mut len = mem::size_of_val(&addr) as socklen_t;
+            let n = libc::recvfrom(
+                self.fd,
+                buf.as_mut_ptr() as *mut c_void,
+                buf.len(),
+                0,
+                &mut addr as *mut _ as *mut libc::sockaddr,
+                &mut len,
+            );
+            Ok((cvt_size(n)?, addr))
+        }
+    }
+
+    pub fn setsockopt<T: Copy>(
+        &self,
+        level: c_int,
+        name: c_int,
+        val: T,
+    ) -> io::Result<()> {
+        sockopt::setsockopt(self.fd, level, name, val)
+    }
+
+    pub fn getsockopt<T: Copy>(&self, level: c_int, name: c_int) -> io::Result<T> {
+        sockopt::getsockopt(self.fd, level, name)
+    }
+
+    /// Sets an option whose value is a buffer rather than a plain value.
+    pub fn setsockopt_bytes(
+        &self,
+        level: c_int,
+        name: c_int,
+        val: &[u8],
+    ) -> io::Result<()> {
+        let ret = unsafe {
+            libc::setsockopt(
+                self.fd,
+                level,
+                name,
+                val.as_ptr() as *const c_void,
+                val.len() as socklen_t,
+            )
+        };
+        cvt(ret).map(|_| ())
+    }
+
+    /// Returns the value of the `SO_ERROR` option.
+    pub fn take_error(&self) -> io::Result<Option<io::Error>> {
+        match self.getsockopt::<c_int>(libc::SOL_SOCKET, libc::SO_ERROR)? {
+            0 => Ok(None),
+            errno => Ok(Some(io::Error::from_raw_os_error(errno))),
+        }
+    }
+}
+
+impl Evented for SocketFd {
+    fn register(
+        &self,
+        poll: &Poll,
+        token: Token,
+        events: Ready,
+        opts: PollOpt,
+    ) -> io::Result<()> {
+        EventedFd(&self.fd).register(poll, token, events, opts)
+    }
+
+    fn reregister(
+        &self,
+        poll: &Poll,
+        token: Token,
+        events: Ready,
+        opts: PollOpt,
+    ) -> io::Result<()> {
+        EventedFd(&self.fd).reregister(poll, token, events, opts)
+    }
+
+    fn deregister(&self, poll: &Poll) -> io::Result<()> {
+        EventedFd(&self.fd).deregister(poll)
+    }
+}
+
+impl AsRawFd for SocketFd {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+impl IntoRawFd for SocketFd {
+    fn into_raw_fd(self) -> RawFd {
+        let fd = self.fd;
+        mem::forget(self);
+        fd
+    }
+}
+
+impl FromRawFd for SocketFd {
+    unsafe fn from_raw_fd(fd: RawFd) -> SocketFd {
+        SocketFd { fd }
+    }
+}
+
+impl Drop for SocketFd {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = libc::close(self.fd);
+        }
+    }
+}
+
+/// Sends `buf` on the connected socket `fd` with the `MSG_*` `flags`, on
+/// top of `MSG_NOSIGNAL`.
+pub fn send_with_flags(fd: RawFd, buf: &[u8], flags: c_int) -> io::Result<usize> {
+    let n = unsafe {
+        libc::send(
+            fd,
+            buf.as_ptr() as *const c_void,
+            buf.len(),
+            flags | libc::MSG_NOSIGNAL,
+        )
+    };
+    cvt_size(n)
+}
+
+/// Sends `buf` on `fd` to `addr` with the `MSG_*` `flags`, on top of
+/// `MSG_NOSIGNAL`.
+pub fn send_to_with_flags(
+    fd: RawFd,
+    buf: &[u8],
+    addr: &SocketAddr,
+    flags: c_int,
+) -> io::Result<usize> {
+    let (addr, len) = inet_addr(addr);
+    let n = unsafe {
+        libc::sendto(
+            fd,
+            buf.as_ptr() as *const c_void,
+            buf.len(),
+            flags | libc::MSG_NOSIGNAL,
+            &addr as *const _ as *const libc::sockaddr,
+            len,
+        )
+    };
+    cvt_size(n)
+}
+
+/// Converts `addr` to a `sockaddr_in` or `sockaddr_in6`.
+pub fn inet_addr(addr: &SocketAddr) -> (libc::sockaddr_storage, socklen_t) {
+    unsafe {
+        let mut storage: libc::sockaddr_storage = mem::zeroed();
+        let len = match addr {
+            SocketAddr::V4(addr) => {
+                let raw = &mut *(&mut storage as *mut _ as *mut libc::sockaddr_in);
+                raw.sin_family = libc::AF_INET as libc::sa_family_t;
+                raw.sin_port = addr.port().to_be();
+                raw.sin_addr.s_addr = u32::from(*addr.ip()).to_be();
+                mem::size_of::<libc::sockaddr_in>()
+            }
+            SocketAddr::V6(addr) => {
+                let raw = &mut *(&mut storage as *mut _ as *mut libc::sockaddr_in6);
+                raw.sin6_family = libc::AF_INET6 as libc::sa_family_t;
+                raw.sin6_port = addr.port().to_be();
+                raw.sin6_flowinfo = addr.flowinfo();
+                raw.sin6_addr.s6_addr = addr.ip().octets();
+                raw.sin6_scope_id = addr.scope_id();
+                mem::size_of::<libc::sockaddr_in6>()
+            }
+        };
+        (storage, len as socklen_t)
+    }
+}
+
+/// Converts an `AF_INET` or `AF_INET6` address back to a `SocketAddr`.
+pub fn to_inet_addr(storage: &libc::sockaddr_storage) -> io::Result<SocketAddr> {
+    unsafe {
+        match c_int::from(storage.ss_family) {
+            libc::AF_INET => {
+                let raw = &*(storage as *const _ as *const libc::sockaddr_in);
+                let ip = Ipv4Addr::from(u32::from_be(raw.sin_addr.s_addr));
+                Ok(SocketAddrV4::new(ip, u16::from_be(raw.sin_port)).into())
+            }
+            libc::AF_INET6 => {
+                let raw = &*(storage as *const _ as *const libc::sockaddr_in6);
+                let ip = Ipv6Addr::from(raw.sin6_addr.s6_addr);
+                Ok(SocketAddrV6::new(
+                    ip,
+                    u16::from_be(raw.sin6_port),
+                    raw.sin6_flowinfo,
+                    raw.sin6_scope_id,
+                )
+                .into())
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid argument",
+            )),
+        }
+    }
+}
+
+fn cvt(ret: c_int) -> io::Result<c_int> {
+    if ret == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(ret)
+    }
+}
+
+fn cvt_size(ret: isize) -> io::Result<usize> {
+    if ret == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(ret as usize)
+    }
+}
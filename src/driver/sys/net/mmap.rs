@@ -0,0 +1,40 @@
+use libc::{self, c_int, c_void};
+use std::io;
+use std::os::unix::prelude::*;
+use std::ptr;
+
+/// A memory mapping, unmapped on drop.
+#[derive(Debug)]
+pub struct Mmap {
+    pub ptr: *mut c_void,
+    pub len: usize,
+}
+
+impl Mmap {
+    pub fn anonymous(len: usize) -> io::Result<Mmap> {
+        let flags = libc::MAP_PRIVATE | libc::MAP_ANONYMOUS;
+        Mmap::new(len, flags, -1, 0)
+    }
+
+    pub fn new(
+        len: usize,
+        flags: c_int,
+        fd: RawFd,
+        offset: libc::off_t,
+    ) -> io::Result<Mmap> {
+        let prot = libc::PROT_READ | libc::PROT_WRITE;
+        let ptr = unsafe { libc::mmap(ptr::null_mut(), len, prot, flags, fd, offset) };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Mmap { ptr, len })
+    }
+}
+
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.ptr, self.len);
+        }
+    }
+}
@@ -3,12 +3,29 @@
 //! The types provided in this module are non-blocking by default and are
 //! designed to for Linux.
 
+mod can;
+mod fd;
+mod mmap;
+mod packet;
+mod raw;
+mod sctp;
 mod tcp;
+mod tun;
 mod udp;
+mod udp_msg;
 mod uds;
+mod xdp;
 
+pub use self::can::{CanSocket, SOL_CAN_RAW};
+pub use self::fd::SocketFd;
+pub use self::packet::{interface_index, PacketSocket};
+pub use self::raw::RawSocket;
+pub use self::sctp::{SctpSocket, MSG_NOTIFICATION};
 pub use self::tcp::{TcpListener, TcpStream};
+pub use self::tun::TunDevice;
 pub use self::udp::UdpSocket;
+pub use self::udp_msg::{EcnCodepoint, RecvMeta, Transmit, BATCH_SIZE};
 pub use self::uds::datagram::UnixDatagram;
 pub use self::uds::listener::UnixListener;
 pub use self::uds::stream::UnixStream;
+pub use self::xdp::{XdpDesc, XdpSocket, XDP_COPY, XDP_USE_NEED_WAKEUP, XDP_ZEROCOPY};
@@ -0,0 +1,271 @@
+use libc::{self, c_int, c_ushort};
+use std::ffi::CString;
+use std::io;
+use std::mem;
+use std::os::unix::prelude::*;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use super::fd::SocketFd;
+use super::mmap::Mmap;
+use crate::driver::sys::event::{Evented, PollOpt, Ready};
+use crate::driver::sys::{Poll, Token};
+
+// Not exported by `libc` for every Linux target.
+const SO_ATTACH_FILTER: c_int = 26;
+const SO_DETACH_FILTER: c_int = 27;
+const PACKET_ADD_MEMBERSHIP: c_int = 1;
+const PACKET_DROP_MEMBERSHIP: c_int = 2;
+const PACKET_MR_PROMISC: c_ushort = 1;
+const TPACKET_V3: c_int = 2;
+
+/// Offset of `hdr.bh1.block_status` in `struct tpacket_block_desc`.
+const BLOCK_STATUS_OFFSET: usize = 8;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct packet_mreq {
+    mr_ifindex: c_int,
+    mr_type: c_ushort,
+    mr_alen: c_ushort,
+    mr_address: [u8; 8],
+}
+
+/// An `AF_PACKET` socket.
+#[derive(Debug)]
+pub struct PacketSocket {
+    // Closed first, so the kernel is done with the ring when it is unmapped.
+    fd: SocketFd,
+    /// Ethernet protocol in network byte order.
+    protocol: c_ushort,
+    ring: Option<RxRing>,
+}
+
+/// The blocks of a `PACKET_RX_RING`, in `TPACKET_V3` format.
+#[derive(Debug)]
+struct RxRing {
+    map: Mmap,
+    block_size: usize,
+    block_count: usize,
+    /// The block the kernel hands over next.
+    head: usize,
+}
+
+/// The ring is only accessed through `&mut PacketSocket`, and the memory
+/// isn't tied to the thread which mapped it.
+unsafe impl Send for PacketSocket {}
+unsafe impl Sync for PacketSocket {}
+
+impl PacketSocket {
+    /// Opens a packet socket of type `SOCK_RAW` or `SOCK_DGRAM` receiving
+    /// the frames of `protocol`, an ethertype in host byte order.
+    pub fn new(ty: c_int, protocol: u16) -> io::Result<PacketSocket> {
+        let protocol = protocol.to_be();
+        let fd = SocketFd::new(libc::AF_PACKET, ty, c_int::from(protocol))?;
+        Ok(PacketSocket {
+            fd,
+            protocol,
+            ring: None,
+        })
+    }
+
+    /// Only receives the frames of the interface `ifindex`.
+    pub fn bind(&self, ifindex: u32) -> io::Result<()> {
+        let mut addr: libc::sockaddr_ll = unsafe { mem::zeroed() };
+        addr.sll_family = libc::AF_PACKET as c_ushort;
+        addr.sll_protocol = self.protocol;
+        addr.sll_ifindex = ifindex as c_int;
+        self.fd.bind(&addr)
+    }
+
+    pub fn set_promiscuous(&self, ifindex: u32, on: bool) -> io::Result<()> {
+        let mreq = packet_mreq {
+            mr_ifindex: ifindex as c_int,
+            mr_type: PACKET_MR_PROMISC,
+            mr_alen: 0,
+            mr_address: [0; 8],
+        };
+        let name = if on {
+            PACKET_ADD_MEMBERSHIP
+        } else {
+            PACKET_DROP_MEMBERSHIP
+        };
+        self.fd.setsockopt(libc::SOL_PACKET, name, mreq)
+    }
+
+    pub fn attach_filter(&self, filter: &[libc::sock_filter]) -> io::Result<()> {
+        if filter.len() > c_ushort::max_value() as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "filter has too many instructions",
+            ));
+        }
+        let prog = libc::sock_fprog {
+            len: filter.len() as c_ushort,
+            filter: filter.as_ptr() as *mut libc::sock_filter,
+        };
+        // The kernel copies the program, `filter` may go away afterwards.
+        self.fd.setsockopt(libc::SOL_SOCKET, SO_ATTACH_FILTER, prog)
+    }
+
+    pub fn detach_filter(&self) -> io::Result<()> {
+        self.fd
+            .setsockopt(libc::SOL_SOCKET, SO_DETACH_FILTER, 0 as c_int)
+    }
+
+    pub fn send(&self, buf: &[u8]) -> io::Result<usize> {
+        self.fd.send(buf)
+    }
+
+    pub fn send_to(&self, buf: &[u8], addr: &libc::sockaddr_ll) -> io::Result<usize> {
+        self.fd.send_to(buf, addr)
+    }
+
+    pub fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.fd.recv(buf)
+    }
+
+    pub fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, libc::sockaddr_ll)> {
+        let (n, storage) = self.fd.recv_from(buf)?;
+        // Safety: packet sockets only report `sockaddr_ll` addresses, which
+        // fit in a `sockaddr_storage`.
+        let addr = unsafe { *(&storage as *const _ as *const libc::sockaddr_ll) };
+        Ok((n, addr))
+    }
+
+    pub fn take_error(&self) -> io::Result<Option<io::Error>> {
+        self.fd.take_error()
+    }
+
+    /// Sets up a `TPACKET_V3` receive ring of `block_count` blocks of
+    /// `block_size` bytes, a multiple of the page size. The kernel retires
+    /// a block which isn't full after `retire_timeout_ms`.
+    pub fn set_rx_ring(
+        &mut self,
+        block_size: u32,
+        block_count: u32,
+        retire_timeout_ms: u32,
+    ) -> io::Result<()> {
+        if self.ring.is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                "the socket already has a receive ring",
+            ));
+        }
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as u32;
+        if block_size == 0 || block_size % page_size != 0 || block_count == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "ring blocks must be a non-zero multiple of the page size",
+            ));
+        }
+
+        self.fd
+            .setsockopt(libc::SOL_PACKET, libc::PACKET_VERSION, TPACKET_V3)?;
+        // Frames have no fixed size in `TPACKET_V3`, the kernel only checks
+        // that a whole number of them fits in a block.
+        let req = libc::tpacket_req3 {
+            tp_block_size: block_size,
+            tp_block_nr: block_count,
+            tp_frame_size: block_size,
+            tp_frame_nr: block_count,
+            tp_retire_blk_tov: retire_timeout_ms,
+            tp_sizeof_priv: 0,
+            tp_feature_req_word: 0,
+        };
+        self.fd
+            .setsockopt(libc::SOL_PACKET, libc::PACKET_RX_RING, req)?;
+
+        let len = block_size as usize * block_count as usize;
+        let flags = libc::MAP_SHARED | libc::MAP_POPULATE;
+        let map = Mmap::new(len, flags, self.fd.as_raw_fd(), 0)?;
+        self.ring = Some(RxRing {
+            map,
+            block_size: block_size as usize,
+            block_count: block_count as usize,
+            head: 0,
+        });
+        Ok(())
+    }
+
+    /// Returns the block at the head of the receive ring if the kernel
+    /// handed it over, or `None` if it's still being filled.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the socket has no receive ring.
+    pub fn rx_block(&self) -> Option<&[u8]> {
+        let ring = self.ring.as_ref().expect("no receive ring");
+        let block = ring.block(ring.head);
+        // Safety: the block is mapped and the status field aligned.
+        let status = unsafe { &*(block.add(BLOCK_STATUS_OFFSET) as *const AtomicU32) };
+        if status.load(Ordering::Acquire) & libc::TP_STATUS_USER == 0 {
+            return None;
+        }
+        // Safety: the kernel doesn't touch the block until it is released.
+        Some(unsafe { std::slice::from_raw_parts(block, ring.block_size) })
+    }
+
+    /// Hands the block at the head of the receive ring back to the kernel,
+    /// moving on to the next one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the socket has no receive ring.
+    pub fn release_rx_block(&mut self) {
+        let ring = self.ring.as_mut().expect("no receive ring");
+        let block = ring.block(ring.head);
+        let status = unsafe { &*(block.add(BLOCK_STATUS_OFFSET) as *const AtomicU32) };
+        status.store(libc::TP_STATUS_KERNEL, Ordering::Release);
+        ring.head = (ring.head + 1) % ring.block_count;
+    }
+}
+
+impl RxRing {
+    fn block(&self, index: usize) -> *mut u8 {
+        // Safety: `index` is smaller than the number of blocks.
+        unsafe { (self.map.ptr as *mut u8).add(index * self.block_size) }
+    }
+}
+
+/// Returns the index of the interface called `name`.
+pub fn interface_index(name: &str) -> io::Result<u32> {
+    let name = CString::new(name).map_err(|_| {
+        io::Error::new(io::ErrorKind::InvalidInput, "invalid interface name")
+    })?;
+    match unsafe { libc::if_nametoindex(name.as_ptr()) } {
+        0 => Err(io::Error::last_os_error()),
+        index => Ok(index),
+    }
+}
+
+impl Evented for PacketSocket {
+    fn register(
+        &self,
+        poll: &Poll,
+        token: Token,
+        events: Ready,
+        opts: PollOpt,
+    ) -> io::Result<()> {
+        self.fd.register(poll, token, events, opts)
+    }
+
+    fn reregister(
+        &self,
+        poll: &Poll,
+        token: Token,
+        events: Ready,
+        opts: PollOpt,
+    ) -> io::Result<()> {
+        self.fd.reregister(poll, token, events, opts)
+    }
+
+    fn deregister(&self, poll: &Poll) -> io::Result<()> {
+        self.fd.deregister(poll)
+    }
+}
+
+impl AsRawFd for PacketSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
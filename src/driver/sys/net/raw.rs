@@ -0,0 +1,109 @@
+use libc::{self, c_int};
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::os::unix::prelude::*;
+
+use super::fd::{self, SocketFd};
+use crate::driver::sys::event::{Evented, PollOpt, Ready};
+use crate::driver::sys::{Poll, Token};
+
+// Not exported by `libc` for every Linux target.
+const IPV6_HDRINCL: c_int = 36;
+
+/// A `SOCK_RAW` IPv4 or IPv6 socket.
+#[derive(Debug)]
+pub struct RawSocket {
+    fd: SocketFd,
+    domain: c_int,
+}
+
+impl RawSocket {
+    pub fn new(domain: c_int, protocol: c_int) -> io::Result<RawSocket> {
+        let fd = SocketFd::new(domain, libc::SOCK_RAW, protocol)?;
+        Ok(RawSocket { fd, domain })
+    }
+
+    pub fn bind(&self, addr: IpAddr) -> io::Result<()> {
+        self.fd.bind_inet(&SocketAddr::new(addr, 0))
+    }
+
+    pub fn connect(&self, addr: IpAddr) -> io::Result<()> {
+        // Raw sockets connect immediately.
+        self.fd.connect_inet(&SocketAddr::new(addr, 0))
+    }
+
+    pub fn set_header_included(&self, on: bool) -> io::Result<()> {
+        let (level, name) = self.hdrincl();
+        self.fd.setsockopt(level, name, on as c_int)
+    }
+
+    pub fn header_included(&self) -> io::Result<bool> {
+        let (level, name) = self.hdrincl();
+        self.fd.getsockopt::<c_int>(level, name).map(|on| on != 0)
+    }
+
+    fn hdrincl(&self) -> (c_int, c_int) {
+        if self.domain == libc::AF_INET6 {
+            (libc::IPPROTO_IPV6, IPV6_HDRINCL)
+        } else {
+            (libc::IPPROTO_IP, libc::IP_HDRINCL)
+        }
+    }
+
+    pub fn local_addr(&self) -> io::Result<IpAddr> {
+        fd::to_inet_addr(&self.fd.local_addr()?).map(|addr| addr.ip())
+    }
+
+    pub fn send(&self, buf: &[u8]) -> io::Result<usize> {
+        self.fd.send(buf)
+    }
+
+    pub fn send_to(&self, buf: &[u8], addr: IpAddr) -> io::Result<usize> {
+        self.fd.send_to_inet(buf, &SocketAddr::new(addr, 0))
+    }
+
+    pub fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.fd.recv(buf)
+    }
+
+    pub fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, IpAddr)> {
+        let (n, addr) = self.fd.recv_from(buf)?;
+        Ok((n, fd::to_inet_addr(&addr)?.ip()))
+    }
+
+    pub fn take_error(&self) -> io::Result<Option<io::Error>> {
+        self.fd.take_error()
+    }
+}
+
+impl Evented for RawSocket {
+    fn register(
+        &self,
+        poll: &Poll,
+        token: Token,
+        events: Ready,
+        opts: PollOpt,
+    ) -> io::Result<()> {
+        self.fd.register(poll, token, events, opts)
+    }
+
+    fn reregister(
+        &self,
+        poll: &Poll,
+        token: Token,
+        events: Ready,
+        opts: PollOpt,
+    ) -> io::Result<()> {
+        self.fd.reregister(poll, token, events, opts)
+    }
+
+    fn deregister(&self, poll: &Poll) -> io::Result<()> {
+        self.fd.deregister(poll)
+    }
+}
+
+impl AsRawFd for RawSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
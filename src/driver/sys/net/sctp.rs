@@ -0,0 +1,174 @@
+use libc::{self, c_int};
+use std::io::{self, Read, Write};
+use std::net::SocketAddr;
+use std::os::unix::prelude::*;
+use std::slice;
+
+use super::fd::{inet_addr, to_inet_addr, SocketFd};
+use crate::driver::sys::event::{Evented, PollOpt, Ready};
+use crate::driver::sys::{Poll, Token};
+
+// From `linux/sctp.h`, which `libc` doesn't cover.
+const SOL_SCTP: c_int = 132;
+const IPPROTO_SCTP: c_int = 132;
+const SCTP_NODELAY: c_int = 3;
+const SCTP_EVENTS: c_int = 11;
+const SCTP_SOCKOPT_BINDX_ADD: c_int = 100;
+const SCTP_SOCKOPT_CONNECTX: c_int = 110;
+pub const MSG_NOTIFICATION: c_int = 0x8000;
+
+/// A one-to-one style (`SOCK_STREAM`) SCTP socket.
+#[derive(Debug)]
+pub struct SctpSocket {
+    fd: SocketFd,
+}
+
+impl SctpSocket {
+    /// Opens a socket for the family of `addr`.
+    pub fn new(addr: &SocketAddr) -> io::Result<SctpSocket> {
+        let domain = match addr {
+            SocketAddr::V4(_) => libc::AF_INET,
+            SocketAddr::V6(_) => libc::AF_INET6,
+        };
+        let fd = SocketFd::new(domain, libc::SOCK_STREAM, IPPROTO_SCTP)?;
+        Ok(SctpSocket { fd })
+    }
+
+    /// Binds to every address of `addrs`, which must share a port.
+    pub fn bind(&self, addrs: &[SocketAddr]) -> io::Result<()> {
+        let (first, rest) = split_first(addrs)?;
+        self.fd.bind_inet(first)?;
+        if !rest.is_empty() {
+            self.fd
+                .setsockopt_bytes(SOL_SCTP, SCTP_SOCKOPT_BINDX_ADD, &pack(rest))?;
+        }
+        Ok(())
+    }
+
+    /// Starts connecting to a peer reachable at any address of `addrs`.
+    pub fn connect(&self, addrs: &[SocketAddr]) -> io::Result<()> {
+        let (first, rest) = split_first(addrs)?;
+        if rest.is_empty() {
+            return self.fd.connect_inet(first);
+        }
+        match self
+            .fd
+            .setsockopt_bytes(SOL_SCTP, SCTP_SOCKOPT_CONNECTX, &pack(addrs))
+        {
+            Err(ref e) if e.raw_os_error() == Some(libc::EINPROGRESS) => Ok(()),
+            r => r,
+        }
+    }
+
+    pub fn listen(&self, backlog: c_int) -> io::Result<()> {
+        self.fd.listen(backlog)
+    }
+
+    pub fn accept(&self) -> io::Result<(SctpSocket, SocketAddr)> {
+        let (fd, addr) = self.fd.accept()?;
+        Ok((SctpSocket { fd }, to_inet_addr(&addr)?))
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        to_inet_addr(&self.fd.local_addr()?)
+    }
+
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        to_inet_addr(&self.fd.peer_addr()?)
+    }
+
+    pub fn shutdown(&self, how: c_int) -> io::Result<()> {
+        self.fd.shutdown(how)
+    }
+
+    pub fn set_nodelay(&self, nodelay: bool) -> io::Result<()> {
+        self.fd.setsockopt(SOL_SCTP, SCTP_NODELAY, nodelay as c_int)
+    }
+
+    pub fn nodelay(&self) -> io::Result<bool> {
+        self.fd
+            .getsockopt::<c_int>(SOL_SCTP, SCTP_NODELAY)
+            .map(|v| v != 0)
+    }
+
+    /// Sets `struct sctp_event_subscribe`, one flag per byte.
+    pub fn set_events(&self, events: &[u8]) -> io::Result<()> {
+        self.fd.setsockopt_bytes(SOL_SCTP, SCTP_EVENTS, events)
+    }
+
+    pub fn recv_msg(&self, buf: &mut [u8]) -> io::Result<(usize, c_int)> {
+        self.fd.recv_msg(buf)
+    }
+
+    pub fn take_error(&self) -> io::Result<Option<io::Error>> {
+        self.fd.take_error()
+    }
+}
+
+fn split_first(addrs: &[SocketAddr]) -> io::Result<(&SocketAddr, &[SocketAddr])> {
+    addrs
+        .split_first()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no addresses given"))
+}
+
+/// Packs `addrs` back to back, the format of the `bindx` and `connectx`
+/// options.
+fn pack(addrs: &[SocketAddr]) -> Vec<u8> {
+    let mut packed = Vec::new();
+    for addr in addrs {
+        let (raw, len) = inet_addr(addr);
+        let bytes = unsafe {
+            slice::from_raw_parts(&raw as *const _ as *const u8, len as usize)
+        };
+        packed.extend_from_slice(bytes);
+    }
+    packed
+}
+
+impl Read for SctpSocket {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.fd.recv(buf)
+    }
+}
+
+impl Write for SctpSocket {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.fd.send(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Evented for SctpSocket {
+    fn register(
+        &self,
+        poll: &Poll,
+        token: Token,
+        events: Ready,
+        opts: PollOpt,
+    ) -> io::Result<()> {
+        self.fd.register(poll, token, events, opts)
+    }
+
+    fn reregister(
+        &self,
+        poll: &Poll,
+        token: Token,
+        events: Ready,
+        opts: PollOpt,
+    ) -> io::Result<()> {
+        self.fd.reregister(poll, token, events, opts)
+    }
+
+    fn deregister(&self, poll: &Poll) -> io::Result<()> {
+        self.fd.deregister(poll)
+    }
+}
+
+impl AsRawFd for SctpSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
@@ -15,10 +15,12 @@ use std::time::Duration;
 use iovec::IoVec;
 use net2::TcpBuilder;
 use std::io;
+use std::mem;
 
+use super::fd::{self, to_inet_addr};
 use crate::driver::sys::event::{Evented, PollOpt, Ready};
 use crate::driver::sys::poll::SelectorId;
-use crate::driver::sys::{linux, Poll, Token};
+use crate::driver::sys::{platform, Poll, Token};
 
 /*
  *
@@ -63,7 +65,7 @@ use crate::driver::sys::{linux, Poll, Token};
 /// # }
 /// ```
 pub struct TcpStream {
-    sys: linux::TcpStream,
+    sys: platform::TcpStream,
     selector_id: SelectorId,
 }
 
@@ -113,7 +115,7 @@ impl TcpStream {
         addr: &SocketAddr,
     ) -> io::Result<TcpStream> {
         Ok(TcpStream {
-            sys: linux::TcpStream::connect(stream, addr)?,
+            sys: platform::TcpStream::connect(stream, addr)?,
             selector_id: SelectorId::new(),
         })
     }
@@ -132,7 +134,7 @@ impl TcpStream {
         set_nonblocking(&stream)?;
 
         Ok(TcpStream {
-            sys: linux::TcpStream::from_stream(stream),
+            sys: platform::TcpStream::from_stream(stream),
             selector_id: SelectorId::new(),
         })
     }
@@ -303,6 +305,54 @@ impl TcpStream {
         self.sys.linger()
     }
 
+    /// Sets the value of the `SO_BUSY_POLL` option on this socket.
+    ///
+    /// This is the approximate time in microseconds to busy poll the device
+    /// queue on a blocking receive when no data is available. A value of `0`
+    /// disables busy polling. Raising the value above the system default
+    /// requires `CAP_NET_ADMIN`.
+    pub fn set_busy_poll(&self, micros: u32) -> io::Result<()> {
+        platform::sockopt::set_busy_poll(self.as_raw_fd(), micros)
+    }
+
+    /// Gets the value of the `SO_BUSY_POLL` option on this socket.
+    ///
+    /// For more information about this option, see [`set_busy_poll`][link].
+    ///
+    /// [link]: #method.set_busy_poll
+    pub fn busy_poll(&self) -> io::Result<u32> {
+        platform::sockopt::busy_poll(self.as_raw_fd())
+    }
+
+    /// Sets the value of the `SO_PREFER_BUSY_POLL` option on this socket.
+    ///
+    /// When enabled, the kernel prefers busy polling over softirq processing
+    /// for this socket (Linux 5.11+).
+    pub fn set_prefer_busy_poll(&self, prefer: bool) -> io::Result<()> {
+        platform::sockopt::set_prefer_busy_poll(self.as_raw_fd(), prefer)
+    }
+
+    /// Gets the value of the `SO_PREFER_BUSY_POLL` option on this socket.
+    ///
+    /// For more information about this option, see
+    /// [`set_prefer_busy_poll`][link].
+    ///
+    /// [link]: #method.set_prefer_busy_poll
+    pub fn prefer_busy_poll(&self) -> io::Result<bool> {
+        platform::sockopt::prefer_busy_poll(self.as_raw_fd())
+    }
+
+    /// Sets the value of the `SO_INCOMING_CPU` option on this socket.
+    pub fn set_incoming_cpu(&self, cpu: usize) -> io::Result<()> {
+        platform::sockopt::set_incoming_cpu(self.as_raw_fd(), cpu)
+    }
+
+    /// Gets the value of the `SO_INCOMING_CPU` option on this socket, the
+    /// CPU which last processed a packet of the socket.
+    pub fn incoming_cpu(&self) -> io::Result<Option<usize>> {
+        platform::sockopt::incoming_cpu(self.as_raw_fd())
+    }
+
     /// Get the value of the `SO_ERROR` option on this socket.
     ///
     /// This will retrieve the stored error in the underlying socket, clearing
@@ -357,6 +407,14 @@ impl TcpStream {
     pub fn write_bufs(&self, bufs: &[&IoVec]) -> io::Result<usize> {
         self.sys.writev(bufs)
     }
+
+    /// Writes `buf` with `MSG_MORE`, telling the kernel more data follows.
+    ///
+    /// The data is held back, like with `TCP_CORK` set, until a write
+    /// without the flag, up to a full segment, or for 200ms at most.
+    pub fn send_more(&self, buf: &[u8]) -> io::Result<usize> {
+        fd::send_with_flags(self.as_raw_fd(), buf, libc::MSG_MORE)
+    }
 }
 
 fn inaddr_any(other: &SocketAddr) -> SocketAddr {
@@ -378,12 +436,20 @@ impl Read for TcpStream {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         (&self.sys).read(buf)
     }
+
+    fn read_vectored(&mut self, bufs: &mut [io::IoSliceMut<'_>]) -> io::Result<usize> {
+        (&self.sys).read_vectored(bufs)
+    }
 }
 
 impl<'a> Read for &'a TcpStream {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         (&self.sys).read(buf)
     }
+
+    fn read_vectored(&mut self, bufs: &mut [io::IoSliceMut<'_>]) -> io::Result<usize> {
+        (&self.sys).read_vectored(bufs)
+    }
 }
 
 impl Write for TcpStream {
@@ -391,6 +457,10 @@ impl Write for TcpStream {
         (&self.sys).write(buf)
     }
 
+    fn write_vectored(&mut self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
+        (&self.sys).write_vectored(bufs)
+    }
+
     fn flush(&mut self) -> io::Result<()> {
         (&self.sys).flush()
     }
@@ -401,6 +471,10 @@ impl<'a> Write for &'a TcpStream {
         (&self.sys).write(buf)
     }
 
+    fn write_vectored(&mut self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
+        (&self.sys).write_vectored(bufs)
+    }
+
     fn flush(&mut self) -> io::Result<()> {
         (&self.sys).flush()
     }
@@ -477,7 +551,7 @@ impl fmt::Debug for TcpStream {
 /// # }
 /// ```
 pub struct TcpListener {
-    sys: linux::TcpListener,
+    sys: platform::TcpListener,
     selector_id: SelectorId,
 }
 
@@ -512,7 +586,7 @@ impl TcpListener {
         // listen
         let listener = sock.listen(1024)?;
         Ok(TcpListener {
-            sys: linux::TcpListener::new(listener)?,
+            sys: platform::TcpListener::new(listener)?,
             selector_id: SelectorId::new(),
         })
     }
@@ -527,7 +601,7 @@ impl TcpListener {
     ///
     /// The address provided must be the address that the listener is bound to.
     pub fn from_std(listener: net::TcpListener) -> io::Result<TcpListener> {
-        linux::TcpListener::new(listener).map(|s| TcpListener {
+        platform::TcpListener::new(listener).map(|s| TcpListener {
             sys: s,
             selector_id: SelectorId::new(),
         })
@@ -542,9 +616,33 @@ impl TcpListener {
     ///
     /// If an accepted stream is returned, the remote address of the peer is
     /// returned along with it.
+    ///
+    /// The stream is created in nonblocking mode by `accept4`, without the
+    /// extra system call `from_stream` makes.
     pub fn accept(&self) -> io::Result<(TcpStream, SocketAddr)> {
-        let (s, a) = self.accept_std()?;
-        Ok((TcpStream::from_stream(s)?, a))
+        let mut storage: libc::sockaddr_storage = unsafe { mem::zeroed() };
+        let mut len = mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+        let fd = unsafe {
+            libc::accept4(
+                self.as_raw_fd(),
+                &mut storage as *mut _ as *mut libc::sockaddr,
+                &mut len,
+                libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC,
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let stream = unsafe { net::TcpStream::from_raw_fd(fd) };
+        let addr = to_inet_addr(&storage)?;
+
+        Ok((
+            TcpStream {
+                sys: platform::TcpStream::from_stream(stream),
+                selector_id: SelectorId::new(),
+            },
+            addr,
+        ))
     }
 
     /// Accepts a new `std::net::TcpStream`.
@@ -619,6 +717,54 @@ impl TcpListener {
     pub fn take_error(&self) -> io::Result<Option<io::Error>> {
         self.sys.take_error()
     }
+
+    /// Sets the value of the `SO_BUSY_POLL` option on this socket.
+    ///
+    /// This is the approximate time in microseconds to busy poll the device
+    /// queue on a blocking receive when no data is available. A value of `0`
+    /// disables busy polling. Raising the value above the system default
+    /// requires `CAP_NET_ADMIN`.
+    pub fn set_busy_poll(&self, micros: u32) -> io::Result<()> {
+        platform::sockopt::set_busy_poll(self.as_raw_fd(), micros)
+    }
+
+    /// Gets the value of the `SO_BUSY_POLL` option on this socket.
+    ///
+    /// For more information about this option, see [`set_busy_poll`][link].
+    ///
+    /// [link]: #method.set_busy_poll
+    pub fn busy_poll(&self) -> io::Result<u32> {
+        platform::sockopt::busy_poll(self.as_raw_fd())
+    }
+
+    /// Sets the value of the `SO_PREFER_BUSY_POLL` option on this socket.
+    ///
+    /// When enabled, the kernel prefers busy polling over softirq processing
+    /// for this socket (Linux 5.11+).
+    pub fn set_prefer_busy_poll(&self, prefer: bool) -> io::Result<()> {
+        platform::sockopt::set_prefer_busy_poll(self.as_raw_fd(), prefer)
+    }
+
+    /// Gets the value of the `SO_PREFER_BUSY_POLL` option on this socket.
+    ///
+    /// For more information about this option, see
+    /// [`set_prefer_busy_poll`][link].
+    ///
+    /// [link]: #method.set_prefer_busy_poll
+    pub fn prefer_busy_poll(&self) -> io::Result<bool> {
+        platform::sockopt::prefer_busy_poll(self.as_raw_fd())
+    }
+
+    /// Sets the value of the `SO_INCOMING_CPU` option on this socket.
+    pub fn set_incoming_cpu(&self, cpu: usize) -> io::Result<()> {
+        platform::sockopt::set_incoming_cpu(self.as_raw_fd(), cpu)
+    }
+
+    /// Gets the value of the `SO_INCOMING_CPU` option on this socket, the
+    /// CPU which last processed a packet of the socket.
+    pub fn incoming_cpu(&self) -> io::Result<Option<usize>> {
+        platform::sockopt::incoming_cpu(self.as_raw_fd())
+    }
 }
 
 impl Evented for TcpListener {
@@ -0,0 +1,233 @@
+use libc::{self, c_char, c_int, c_short, c_ulong};
+use std::fs::OpenOptions;
+use std::io::{self, Read, Write};
+use std::net::Ipv4Addr;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::prelude::*;
+
+use super::fd::SocketFd;
+use crate::driver::sys::event::{Evented, PollOpt, Ready};
+use crate::driver::sys::platform::Io;
+use crate::driver::sys::{Poll, Token};
+
+// Not exported by `libc` for every Linux target.
+const TUNSETIFF: c_ulong = 0x4004_54ca;
+const IFF_TUN: c_short = 0x0001;
+const IFF_TAP: c_short = 0x0002;
+const IFF_NO_PI: c_short = 0x1000;
+const IFNAMSIZ: usize = 16;
+const ARPHRD_ETHER: u16 = 1;
+
+/// `struct ifreq`, with the union kept as bytes.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct ifreq {
+    ifr_name: [c_char; IFNAMSIZ],
+    ifr_ifru: [u8; 24],
+}
+
+/// A TUN or TAP interface (`/dev/net/tun`).
+#[derive(Debug)]
+pub struct TunDevice {
+    io: Io,
+    name: String,
+}
+
+impl TunDevice {
+    /// Creates, or attaches to, the interface called `name`. The kernel
+    /// picks a name from a pattern such as `tun%d`.
+    pub fn open(name: &str, tap: bool, packet_info: bool) -> io::Result<TunDevice> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open("/dev/net/tun")?;
+        let io = unsafe { Io::from_raw_fd(file.into_raw_fd()) };
+
+        let mut req = ifreq::new(name)?;
+        let mut flags = if tap { IFF_TAP } else { IFF_TUN };
+        if !packet_info {
+            flags |= IFF_NO_PI;
+        }
+        req.ifr_ifru[..2].copy_from_slice(&flags.to_ne_bytes());
+        ioctl(io.as_raw_fd(), TUNSETIFF, &mut req)?;
+
+        let name = req.name();
+        Ok(TunDevice { io, name })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn mtu(&self) -> io::Result<u32> {
+        let req = self.ctl(libc::SIOCGIFMTU as c_ulong, |_| ())?;
+        Ok(read_int(&req.ifr_ifru) as u32)
+    }
+
+    pub fn set_mtu(&self, mtu: u32) -> io::Result<()> {
+        self.ctl(libc::SIOCSIFMTU as c_ulong, |data| {
+            data[..4].copy_from_slice(&(mtu as c_int).to_ne_bytes())
+        })
+        .map(|_| ())
+    }
+
+    pub fn set_address(&self, addr: Ipv4Addr) -> io::Result<()> {
+        self.ctl(libc::SIOCSIFADDR as c_ulong, |data| write_inet(data, addr))
+            .map(|_| ())
+    }
+
+    pub fn set_netmask(&self, mask: Ipv4Addr) -> io::Result<()> {
+        self.ctl(libc::SIOCSIFNETMASK as c_ulong, |data| {
+            write_inet(data, mask)
+        })
+        .map(|_| ())
+    }
+
+    pub fn set_hwaddr(&self, addr: [u8; 6]) -> io::Result<()> {
+        self.ctl(libc::SIOCSIFHWADDR as c_ulong, |data| {
+            data[..2].copy_from_slice(&ARPHRD_ETHER.to_ne_bytes());
+            data[2..8].copy_from_slice(&addr);
+        })
+        .map(|_| ())
+    }
+
+    pub fn hwaddr(&self) -> io::Result<[u8; 6]> {
+        let req = self.ctl(libc::SIOCGIFHWADDR as c_ulong, |_| ())?;
+        let mut addr = [0; 6];
+        addr.copy_from_slice(&req.ifr_ifru[2..8]);
+        Ok(addr)
+    }
+
+    /// Brings the interface up or down.
+    pub fn set_up(&self, up: bool) -> io::Result<()> {
+        let req = self.ctl(libc::SIOCGIFFLAGS as c_ulong, |_| ())?;
+        let mut flags = c_short::from_ne_bytes([req.ifr_ifru[0], req.ifr_ifru[1]]);
+        if up {
+            flags |= libc::IFF_UP as c_short;
+        } else {
+            flags &= !(libc::IFF_UP as c_short);
+        }
+        self.ctl(libc::SIOCSIFFLAGS as c_ulong, |data| {
+            data[..2].copy_from_slice(&flags.to_ne_bytes())
+        })
+        .map(|_| ())
+    }
+
+    /// Runs an interface ioctl, which only works on sockets.
+    fn ctl<F>(&self, request: c_ulong, f: F) -> io::Result<ifreq>
+    where
+        F: FnOnce(&mut [u8; 24]),
+    {
+        let socket = SocketFd::new(libc::AF_INET, libc::SOCK_DGRAM, 0)?;
+        let mut req = ifreq::new(&self.name)?;
+        f(&mut req.ifr_ifru);
+        ioctl(socket.as_raw_fd(), request, &mut req)?;
+        Ok(req)
+    }
+}
+
+impl ifreq {
+    fn new(name: &str) -> io::Result<ifreq> {
+        if name.len() >= IFNAMSIZ || name.as_bytes().contains(&0) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid interface name",
+            ));
+        }
+        let mut req = ifreq {
+            ifr_name: [0; IFNAMSIZ],
+            ifr_ifru: [0; 24],
+        };
+        for (dst, &src) in req.ifr_name.iter_mut().zip(name.as_bytes()) {
+            *dst = src as c_char;
+        }
+        Ok(req)
+    }
+
+    fn name(&self) -> String {
+        let name = self.ifr_name.iter().take_while(|&&c| c != 0);
+        name.map(|&c| c as u8 as char).collect()
+    }
+}
+
+fn read_int(data: &[u8; 24]) -> c_int {
+    c_int::from_ne_bytes([data[0], data[1], data[2], data[3]])
+}
+
+/// Writes a `sockaddr_in` for `addr`.
+fn write_inet(data: &mut [u8; 24], addr: Ipv4Addr) {
+    data[..2].copy_from_slice(&(libc::AF_INET as u16).to_ne_bytes());
+    data[4..8].copy_from_slice(&addr.octets());
+}
+
+fn ioctl(fd: RawFd, request: c_ulong, req: &mut ifreq) -> io::Result<()> {
+    if unsafe { libc::ioctl(fd, request as _, req as *mut ifreq) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+impl Read for TunDevice {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        (&self.io).read(buf)
+    }
+}
+
+impl<'a> Read for &'a TunDevice {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        (&self.io).read(buf)
+    }
+}
+
+impl Write for TunDevice {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        (&self.io).write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> Write for &'a TunDevice {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        (&self.io).write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Evented for TunDevice {
+    fn register(
+        &self,
+        poll: &Poll,
+        token: Token,
+        events: Ready,
+        opts: PollOpt,
+    ) -> io::Result<()> {
+        self.io.register(poll, token, events, opts)
+    }
+
+    fn reregister(
+        &self,
+        poll: &Poll,
+        token: Token,
+        events: Ready,
+        opts: PollOpt,
+    ) -> io::Result<()> {
+        self.io.reregister(poll, token, events, opts)
+    }
+
+    fn deregister(&self, poll: &Poll) -> io::Result<()> {
+        self.io.deregister(poll)
+    }
+}
+
+impl AsRawFd for TunDevice {
+    fn as_raw_fd(&self) -> RawFd {
+        self.io.as_raw_fd()
+    }
+}
@@ -8,12 +8,15 @@
 use iovec::IoVec;
 /// [portability guidelines]: ../struct.Poll.html#portability
 use std::fmt;
-use std::io;
+use std::io::{self, IoSliceMut};
 use std::net::{self, Ipv4Addr, Ipv6Addr, SocketAddr};
 
 use crate::driver::sys::event::{Evented, PollOpt, Ready};
 use crate::driver::sys::poll::SelectorId;
-use crate::driver::sys::{linux, Poll, Token};
+use crate::driver::sys::{platform, Poll, Token};
+
+use super::fd;
+use super::udp_msg::{self, RecvMeta, Transmit};
 
 /// A User Datagram Protocol socket.
 ///
@@ -90,7 +93,7 @@ use crate::driver::sys::{linux, Poll, Token};
 /// # }
 /// ```
 pub struct UdpSocket {
-    sys: linux::UdpSocket,
+    sys: platform::UdpSocket,
     selector_id: SelectorId,
 }
 
@@ -139,7 +142,7 @@ impl UdpSocket {
     /// options like `reuse_address` or binding to multiple addresses.
     pub fn from_socket(socket: net::UdpSocket) -> io::Result<UdpSocket> {
         Ok(UdpSocket {
-            sys: linux::UdpSocket::new(socket)?,
+            sys: platform::UdpSocket::new(socket)?,
             selector_id: SelectorId::new(),
         })
     }
@@ -541,6 +544,43 @@ impl UdpSocket {
         self.sys.only_v6()
     }
 
+    /// Sets the value of the `SO_BUSY_POLL` option on this socket.
+    ///
+    /// This is the approximate time in microseconds to busy poll the device
+    /// queue on a blocking receive when no data is available. A value of `0`
+    /// disables busy polling. Raising the value above the system default
+    /// requires `CAP_NET_ADMIN`.
+    pub fn set_busy_poll(&self, micros: u32) -> io::Result<()> {
+        platform::sockopt::set_busy_poll(self.as_raw_fd(), micros)
+    }
+
+    /// Gets the value of the `SO_BUSY_POLL` option on this socket.
+    ///
+    /// For more information about this option, see [`set_busy_poll`][link].
+    ///
+    /// [link]: #method.set_busy_poll
+    pub fn busy_poll(&self) -> io::Result<u32> {
+        platform::sockopt::busy_poll(self.as_raw_fd())
+    }
+
+    /// Sets the value of the `SO_PREFER_BUSY_POLL` option on this socket.
+    ///
+    /// When enabled, the kernel prefers busy polling over softirq processing
+    /// for this socket (Linux 5.11+).
+    pub fn set_prefer_busy_poll(&self, prefer: bool) -> io::Result<()> {
+        platform::sockopt::set_prefer_busy_poll(self.as_raw_fd(), prefer)
+    }
+
+    /// Gets the value of the `SO_PREFER_BUSY_POLL` option on this socket.
+    ///
+    /// For more information about this option, see
+    /// [`set_prefer_busy_poll`][link].
+    ///
+    /// [link]: #method.set_prefer_busy_poll
+    pub fn prefer_busy_poll(&self) -> io::Result<bool> {
+        platform::sockopt::prefer_busy_poll(self.as_raw_fd())
+    }
+
     /// Get the value of the `SO_ERROR` option on this socket.
     ///
     /// This will retrieve the stored error in the underlying socket, clearing
@@ -589,6 +629,54 @@ impl UdpSocket {
     pub fn send_bufs(&self, bufs: &[&IoVec]) -> io::Result<usize> {
         self.sys.writev(bufs)
     }
+
+    /// Sends `buf` to `target` with `MSG_MORE`, appending it to the pending
+    /// datagram rather than sending it right away.
+    ///
+    /// The datagram goes out, with everything appended to it, on the next
+    /// send without the flag.
+    pub fn send_to_more(&self, buf: &[u8], target: &SocketAddr) -> io::Result<usize> {
+        fd::send_to_with_flags(self.as_raw_fd(), buf, target, libc::MSG_MORE)
+    }
+
+    /// Turns on the reporting of the metadata returned by [`recv_msgs`]: the
+    /// ECN codepoint and the destination address of each datagram, and GRO
+    /// coalescing where the kernel supports it.
+    ///
+    /// [`recv_msgs`]: #method.recv_msgs
+    pub fn enable_recv_meta(&self) -> io::Result<()> {
+        let ipv6 = self.local_addr()?.is_ipv6();
+        udp_msg::enable_recv_meta(self.as_raw_fd(), ipv6)
+    }
+
+    /// Returns the most datagrams a single [`send_msg`] can carry with
+    /// `segment_size`, 1 if the kernel doesn't support GSO.
+    ///
+    /// [`send_msg`]: #method.send_msg
+    pub fn max_gso_segments(&self) -> usize {
+        udp_msg::max_gso_segments(self.as_raw_fd())
+    }
+
+    /// Sends the datagrams of `transmit` with their metadata.
+    ///
+    /// On Unix this corresponds to the `sendmsg` syscall.
+    pub fn send_msg(&self, transmit: &Transmit<'_>) -> io::Result<usize> {
+        udp_msg::send_msg(self.as_raw_fd(), transmit)
+    }
+
+    /// Receives up to [`BATCH_SIZE`] datagrams, one per buffer, filling
+    /// `meta` with their metadata. Returns the number of datagrams received.
+    ///
+    /// On Unix this corresponds to the `recvmmsg` syscall.
+    ///
+    /// [`BATCH_SIZE`]: constant.BATCH_SIZE.html
+    pub fn recv_msgs(
+        &self,
+        bufs: &mut [IoSliceMut<'_>],
+        meta: &mut [RecvMeta],
+    ) -> io::Result<usize> {
+        udp_msg::recv_msgs(self.as_raw_fd(), bufs, meta)
+    }
 }
 
 impl Evented for UdpSocket {
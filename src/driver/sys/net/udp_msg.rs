@@ -0,0 +1,336 @@
+//! UDP datagrams with their metadata: ECN codepoints, source and
+//! destination addresses, and GSO/GRO segmentation.
+//!
+//! The control messages follow the family of the datagram's peer, which is
+//! also what a dual-stack IPv6 socket expects for IPv4 peers.
+
+use libc::{self, c_int, c_uint, c_void, socklen_t};
+use std::io::{self, IoSliceMut};
+use std::mem;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::os::unix::io::RawFd;
+use std::ptr;
+
+use super::fd::{inet_addr, to_inet_addr};
+use crate::driver::sys::platform::sockopt;
+
+// Not exported by libc for glibc targets.
+const UDP_SEGMENT: c_int = 103;
+const UDP_GRO: c_int = 104;
+
+/// The most datagrams received by a single `recv_msgs`.
+pub const BATCH_SIZE: usize = 32;
+
+/// Segments per GSO send the kernel accepts, `UDP_MAX_SEGMENTS`.
+const MAX_GSO_SEGMENTS: usize = 64;
+
+/// Room for an ECN, a packet info and a segment size message.
+const CMSG_BUF_LEN: usize = 128;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct CmsgBuffer {
+    _align: [libc::cmsghdr; 0],
+    buf: [u8; CMSG_BUF_LEN],
+}
+
+/// An explicit congestion notification codepoint, the low two bits of the
+/// traffic class of IP.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum EcnCodepoint {
+    /// ECN capable transport, `ECT(0)`.
+    Ect0 = 0b10,
+    /// ECN capable transport, `ECT(1)`.
+    Ect1 = 0b01,
+    /// Congestion experienced, `CE`.
+    Ce = 0b11,
+}
+
+impl EcnCodepoint {
+    /// Reads the codepoint of a traffic class, `None` if the transport is
+    /// not ECN capable.
+    pub fn from_bits(bits: u8) -> Option<EcnCodepoint> {
+        match bits & 0b11 {
+            0b10 => Some(EcnCodepoint::Ect0),
+            0b01 => Some(EcnCodepoint::Ect1),
+            0b11 => Some(EcnCodepoint::Ce),
+            _ => None,
+        }
+    }
+
+    /// Returns the bits of the codepoint.
+    pub fn bits(self) -> u8 {
+        self as u8
+    }
+}
+
+/// A datagram to send, or a train of them with GSO.
+#[derive(Debug, Clone, Copy)]
+pub struct Transmit<'a> {
+    /// The address to send to.
+    pub destination: SocketAddr,
+    /// The ECN codepoint to mark the datagrams with.
+    pub ecn: Option<EcnCodepoint>,
+    /// The payload: one datagram, or several of `segment_size` bytes each.
+    pub contents: &'a [u8],
+    /// Splits `contents` into datagrams of this size, the last one possibly
+    /// shorter, in the kernel (`UDP_SEGMENT`).
+    pub segment_size: Option<usize>,
+    /// The local address to send from, for sockets bound to a wildcard
+    /// address.
+    pub src_ip: Option<IpAddr>,
+}
+
+/// Metadata of a received datagram, or a train of them with GRO.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecvMeta {
+    /// The address of the sender.
+    pub addr: SocketAddr,
+    /// The number of bytes received.
+    pub len: usize,
+    /// The size of each datagram coalesced in the buffer, equal to `len`
+    /// unless the kernel coalesced several (`UDP_GRO`).
+    pub stride: usize,
+    /// The ECN codepoint the datagram was marked with.
+    pub ecn: Option<EcnCodepoint>,
+    /// The local address the datagram was sent to.
+    pub dst_ip: Option<IpAddr>,
+}
+
+impl Default for RecvMeta {
+    fn default() -> RecvMeta {
+        RecvMeta {
+            addr: SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), 0),
+            len: 0,
+            stride: 0,
+            ecn: None,
+            dst_ip: None,
+        }
+    }
+}
+
+/// Turns on the reporting of the ECN codepoint, the destination address
+/// and, where supported, GRO coalescing of received datagrams.
+pub(super) fn enable_recv_meta(fd: RawFd, ipv6: bool) -> io::Result<()> {
+    if ipv6 {
+        sockopt::setsockopt(fd, libc::IPPROTO_IPV6, libc::IPV6_RECVTCLASS, 1 as c_int)?;
+        sockopt::setsockopt(fd, libc::IPPROTO_IPV6, libc::IPV6_RECVPKTINFO, 1 as c_int)?;
+    }
+    // Also wanted by dual-stack sockets, for their IPv4 peers.
+    let v4 = sockopt::setsockopt(fd, libc::IPPROTO_IP, libc::IP_RECVTOS, 1 as c_int)
+        .and_then(|()| {
+            sockopt::setsockopt(fd, libc::IPPROTO_IP, libc::IP_PKTINFO, 1 as c_int)
+        });
+    if !ipv6 {
+        v4?;
+    }
+    // Older kernels don't coalesce, each datagram is received on its own.
+    let _ = sockopt::setsockopt(fd, libc::SOL_UDP, UDP_GRO, 1 as c_int);
+    Ok(())
+}
+
+/// Returns the most segments a single GSO send can carry, 1 if the kernel
+/// doesn't support GSO.
+pub(super) fn max_gso_segments(fd: RawFd) -> usize {
+    match sockopt::getsockopt::<c_int>(fd, libc::SOL_UDP, UDP_SEGMENT) {
+        Ok(_) => MAX_GSO_SEGMENTS,
+        Err(_) => 1,
+    }
+}
+
+pub(super) fn send_msg(fd: RawFd, transmit: &Transmit<'_>) -> io::Result<usize> {
+    let ipv4 = transmit.destination.is_ipv4();
+    let src_ip = match transmit.src_ip {
+        Some(ip) => Some(same_family(ip, ipv4)?),
+        None => None,
+    };
+
+    unsafe {
+        let (mut addr, addr_len) = inet_addr(&transmit.destination);
+        let mut iov = libc::iovec {
+            iov_base: transmit.contents.as_ptr() as *mut c_void,
+            iov_len: transmit.contents.len(),
+        };
+        let mut control: CmsgBuffer = mem::zeroed();
+        let mut msg: libc::msghdr = mem::zeroed();
+        msg.msg_name = &mut addr as *mut _ as *mut c_void;
+        msg.msg_namelen = addr_len;
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = control.buf.as_mut_ptr() as *mut c_void;
+        msg.msg_controllen = CMSG_BUF_LEN as _;
+
+        let mut encoder = Encoder {
+            msg: &msg,
+            cmsg: libc::CMSG_FIRSTHDR(&msg),
+            len: 0,
+        };
+        if let Some(ecn) = transmit.ecn {
+            let bits = c_int::from(ecn.bits());
+            if ipv4 {
+                encoder.push(libc::IPPROTO_IP, libc::IP_TOS, bits);
+            } else {
+                encoder.push(libc::IPPROTO_IPV6, libc::IPV6_TCLASS, bits);
+            }
+        }
+        match src_ip {
+            Some(IpAddr::V4(ip)) => {
+                let info = libc::in_pktinfo {
+                    ipi_ifindex: 0,
+                    ipi_spec_dst: libc::in_addr {
+                        s_addr: u32::from(ip).to_be(),
+                    },
+                    ipi_addr: libc::in_addr { s_addr: 0 },
+                };
+                encoder.push(libc::IPPROTO_IP, libc::IP_PKTINFO, info);
+            }
+            Some(IpAddr::V6(ip)) => {
+                let info = libc::in6_pktinfo {
+                    ipi6_addr: libc::in6_addr {
+                        s6_addr: ip.octets(),
+                    },
+                    ipi6_ifindex: 0,
+                };
+                encoder.push(libc::IPPROTO_IPV6, libc::IPV6_PKTINFO, info);
+            }
+            None => {}
+        }
+        if let Some(size) = transmit.segment_size {
+            encoder.push(libc::SOL_UDP, UDP_SEGMENT, size as u16);
+        }
+        let len = encoder.len;
+        if len == 0 {
+            msg.msg_control = ptr::null_mut();
+        }
+        msg.msg_controllen = len as _;
+
+        let n = libc::sendmsg(fd, &msg, 0);
+        if n < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(n as usize)
+        }
+    }
+}
+
+/// Receives up to `BATCH_SIZE` datagrams at once, one per buffer, and
+/// returns how many were received.
+pub(super) fn recv_msgs(
+    fd: RawFd,
+    bufs: &mut [IoSliceMut<'_>],
+    meta: &mut [RecvMeta],
+) -> io::Result<usize> {
+    let count = bufs.len().min(meta.len()).min(BATCH_SIZE);
+    if count == 0 {
+        return Ok(0);
+    }
+
+    unsafe {
+        let mut names: [libc::sockaddr_storage; BATCH_SIZE] = mem::zeroed();
+        let mut controls: [CmsgBuffer; BATCH_SIZE] = mem::zeroed();
+        let mut hdrs: [libc::mmsghdr; BATCH_SIZE] = mem::zeroed();
+        for i in 0..count {
+            let msg = &mut hdrs[i].msg_hdr;
+            msg.msg_name = &mut names[i] as *mut _ as *mut c_void;
+            msg.msg_namelen = mem::size_of::<libc::sockaddr_storage>() as socklen_t;
+            // `IoSliceMut` is guaranteed to be ABI compatible with `iovec`.
+            msg.msg_iov = &mut bufs[i] as *mut IoSliceMut<'_> as *mut libc::iovec;
+            msg.msg_iovlen = 1;
+            msg.msg_control = controls[i].buf.as_mut_ptr() as *mut c_void;
+            msg.msg_controllen = CMSG_BUF_LEN as _;
+        }
+
+        let n =
+            libc::recvmmsg(fd, hdrs.as_mut_ptr(), count as c_uint, 0, ptr::null_mut());
+        if n < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let n = n as usize;
+        for i in 0..n {
+            meta[i] = decode(&hdrs[i].msg_hdr, hdrs[i].msg_len as usize, &names[i])?;
+        }
+        Ok(n)
+    }
+}
+
+unsafe fn decode(
+    msg: &libc::msghdr,
+    len: usize,
+    name: &libc::sockaddr_storage,
+) -> io::Result<RecvMeta> {
+    let mut meta = RecvMeta {
+        addr: to_inet_addr(name)?,
+        len,
+        stride: len,
+        ecn: None,
+        dst_ip: None,
+    };
+
+    let mut cmsg = libc::CMSG_FIRSTHDR(msg);
+    while !cmsg.is_null() {
+        let data = libc::CMSG_DATA(cmsg);
+        match ((*cmsg).cmsg_level, (*cmsg).cmsg_type) {
+            // A single byte, unlike the option used to send it.
+            (libc::IPPROTO_IP, libc::IP_TOS) => {
+                meta.ecn = EcnCodepoint::from_bits(*data);
+            }
+            (libc::IPPROTO_IPV6, libc::IPV6_TCLASS) => {
+                let bits = ptr::read_unaligned(data as *const c_int);
+                meta.ecn = EcnCodepoint::from_bits(bits as u8);
+            }
+            (libc::IPPROTO_IP, libc::IP_PKTINFO) => {
+                let info = ptr::read_unaligned(data as *const libc::in_pktinfo);
+                let ip = Ipv4Addr::from(u32::from_be(info.ipi_addr.s_addr));
+                meta.dst_ip = Some(ip.into());
+            }
+            (libc::IPPROTO_IPV6, libc::IPV6_PKTINFO) => {
+                let info = ptr::read_unaligned(data as *const libc::in6_pktinfo);
+                meta.dst_ip = Some(Ipv6Addr::from(info.ipi6_addr.s6_addr).into());
+            }
+            (libc::SOL_UDP, UDP_GRO) => {
+                meta.stride = ptr::read_unaligned(data as *const c_int) as usize;
+            }
+            _ => {}
+        }
+        cmsg = libc::CMSG_NXTHDR(msg, cmsg);
+    }
+    Ok(meta)
+}
+
+/// Appends control messages to the buffer of a `msghdr`.
+struct Encoder<'a> {
+    msg: &'a libc::msghdr,
+    cmsg: *mut libc::cmsghdr,
+    len: usize,
+}
+
+impl Encoder<'_> {
+    unsafe fn push<T: Copy>(&mut self, level: c_int, ty: c_int, value: T) {
+        let size = mem::size_of::<T>() as c_uint;
+        let cmsg = self.cmsg;
+        assert!(!cmsg.is_null(), "control message buffer is too small");
+        (*cmsg).cmsg_level = level;
+        (*cmsg).cmsg_type = ty;
+        (*cmsg).cmsg_len = libc::CMSG_LEN(size) as _;
+        ptr::write_unaligned(libc::CMSG_DATA(cmsg) as *mut T, value);
+        self.len += libc::CMSG_SPACE(size) as usize;
+        self.cmsg = libc::CMSG_NXTHDR(self.msg, cmsg);
+    }
+}
+
+/// Converts `ip` to the family of the destination, through IPv4-mapped
+/// IPv6 addresses.
+fn same_family(ip: IpAddr, ipv4: bool) -> io::Result<IpAddr> {
+    match (ip, ipv4) {
+        (IpAddr::V4(_), true) | (IpAddr::V6(_), false) => Ok(ip),
+        (IpAddr::V4(ip), false) => Ok(ip.to_ipv6_mapped().into()),
+        (IpAddr::V6(ip), true) => match ip.to_ipv4_mapped() {
+            Some(ip) => Ok(ip.into()),
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "source address of another family than the destination",
+            )),
+        },
+    }
+}
@@ -1,15 +1,47 @@
 use libc;
 use std::io;
+use std::mem;
 use std::net::Shutdown;
 use std::os::unix::net;
 use std::os::unix::prelude::*;
 use std::path::Path;
+use std::ptr;
 
 use super::cvt;
 use super::socket::{sockaddr_un, Socket};
 use crate::driver::sys::event::{Evented, EventedFd, PollOpt, Ready};
 use crate::driver::sys::{Poll, Token};
 
+/// The most file descriptors Linux passes in a single message.
+const SCM_MAX_FD: usize = 253;
+
+/// `CMSG_SPACE` of `SCM_MAX_FD` descriptors.
+const CMSG_BUF_LEN: usize = mem::size_of::<libc::cmsghdr>()
+    + (SCM_MAX_FD * mem::size_of::<RawFd>() + mem::size_of::<usize>() - 1)
+        / mem::size_of::<usize>()
+        * mem::size_of::<usize>();
+
+/// Room for a `SCM_RIGHTS` message of `SCM_MAX_FD` descriptors, aligned
+/// for `cmsghdr`.
+#[repr(C)]
+struct CmsgBuffer {
+    _align: [libc::cmsghdr; 0],
+    buf: [u8; CMSG_BUF_LEN],
+}
+
+impl CmsgBuffer {
+    fn new() -> CmsgBuffer {
+        CmsgBuffer {
+            _align: [],
+            buf: [0; CMSG_BUF_LEN],
+        }
+    }
+
+    fn as_mut_ptr(&mut self) -> *mut libc::c_void {
+        self.buf.as_mut_ptr() as *mut libc::c_void
+    }
+}
+
 /// A Unix datagram socket.
 #[derive(Debug)]
 pub struct UnixDatagram {
@@ -125,6 +157,95 @@ impl UnixDatagram {
         self.inner.send(buf)
     }
 
+    /// Sends data on the socket to the socket's peer, along with copies of
+    /// the file descriptors `fds`.
+    ///
+    /// On success, returns the number of bytes written.
+    pub fn send_with_fds(&self, buf: &[u8], fds: &[RawFd]) -> io::Result<usize> {
+        if fds.len() > SCM_MAX_FD {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "too many file descriptors",
+            ));
+        }
+        unsafe {
+            let mut iov = libc::iovec {
+                iov_base: buf.as_ptr() as *mut libc::c_void,
+                iov_len: buf.len(),
+            };
+            let mut msg: libc::msghdr = mem::zeroed();
+            msg.msg_iov = &mut iov;
+            msg.msg_iovlen = 1;
+
+            let mut control = CmsgBuffer::new();
+            if !fds.is_empty() {
+                let len = mem::size_of_val(fds) as u32;
+                msg.msg_control = control.as_mut_ptr();
+                msg.msg_controllen = libc::CMSG_SPACE(len) as _;
+                let cmsg = libc::CMSG_FIRSTHDR(&msg);
+                (*cmsg).cmsg_level = libc::SOL_SOCKET;
+                (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+                (*cmsg).cmsg_len = libc::CMSG_LEN(len) as _;
+                let data = libc::CMSG_DATA(cmsg) as *mut RawFd;
+                ptr::copy_nonoverlapping(fds.as_ptr(), data, fds.len());
+            }
+
+            let n = libc::sendmsg(self.as_raw_fd(), &msg, libc::MSG_NOSIGNAL);
+            if n < 0 {
+                Err(io::Error::last_os_error())
+            } else {
+                Ok(n as usize)
+            }
+        }
+    }
+
+    /// Receives data from the socket, pushing the file descriptors sent
+    /// along with it to `fds`.
+    ///
+    /// On success, returns the number of bytes read and the `MSG_*` flags
+    /// the kernel reported for the message. The descriptors are received
+    /// with `CLOEXEC` set.
+    pub fn recv_with_fds(
+        &self,
+        buf: &mut [u8],
+        fds: &mut Vec<OwnedFd>,
+    ) -> io::Result<(usize, libc::c_int)> {
+        unsafe {
+            let mut iov = libc::iovec {
+                iov_base: buf.as_mut_ptr() as *mut libc::c_void,
+                iov_len: buf.len(),
+            };
+            let mut msg: libc::msghdr = mem::zeroed();
+            msg.msg_iov = &mut iov;
+            msg.msg_iovlen = 1;
+            let mut control = CmsgBuffer::new();
+            msg.msg_control = control.as_mut_ptr();
+            msg.msg_controllen = mem::size_of::<CmsgBuffer>() as _;
+
+            let n = libc::recvmsg(self.as_raw_fd(), &mut msg, libc::MSG_CMSG_CLOEXEC);
+            if n < 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+            while !cmsg.is_null() {
+                if (*cmsg).cmsg_level == libc::SOL_SOCKET
+                    && (*cmsg).cmsg_type == libc::SCM_RIGHTS
+                {
+                    let data = libc::CMSG_DATA(cmsg) as *const RawFd;
+                    let len =
+                        (*cmsg).cmsg_len as usize - (data as usize - cmsg as usize);
+                    for i in 0..len / mem::size_of::<RawFd>() {
+                        let fd = ptr::read_unaligned(data.add(i));
+                        fds.push(OwnedFd::from_raw_fd(fd));
+                    }
+                }
+                cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+            }
+            Ok((n as usize, msg.msg_flags))
+        }
+    }
+
     /// Returns the value of the `SO_ERROR` option.
     pub fn take_error(&self) -> io::Result<Option<io::Error>> {
         self.inner.take_error()
@@ -199,12 +199,20 @@ impl Read for UnixStream {
     fn read(&mut self, bytes: &mut [u8]) -> io::Result<usize> {
         self.inner.read(bytes)
     }
+
+    fn read_vectored(&mut self, bufs: &mut [io::IoSliceMut<'_>]) -> io::Result<usize> {
+        self.inner.read_vectored(bufs)
+    }
 }
 
 impl<'a> Read for &'a UnixStream {
     fn read(&mut self, bytes: &mut [u8]) -> io::Result<usize> {
         (&self.inner).read(bytes)
     }
+
+    fn read_vectored(&mut self, bufs: &mut [io::IoSliceMut<'_>]) -> io::Result<usize> {
+        (&self.inner).read_vectored(bufs)
+    }
 }
 
 impl Write for UnixStream {
@@ -212,6 +220,10 @@ impl Write for UnixStream {
         self.inner.write(bytes)
     }
 
+    fn write_vectored(&mut self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
+        self.inner.write_vectored(bufs)
+    }
+
     fn flush(&mut self) -> io::Result<()> {
         self.inner.flush()
     }
@@ -222,6 +234,10 @@ impl<'a> Write for &'a UnixStream {
         (&self.inner).write(bytes)
     }
 
+    fn write_vectored(&mut self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
+        (&self.inner).write_vectored(bufs)
+    }
+
     fn flush(&mut self) -> io::Result<()> {
         (&self.inner).flush()
     }
@@ -0,0 +1,331 @@
+use libc::{self, c_int};
+use std::io;
+use std::mem;
+use std::os::unix::prelude::*;
+use std::ptr;
+use std::slice;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use super::fd::SocketFd;
+use super::mmap::Mmap;
+use crate::driver::sys::event::{Evented, PollOpt, Ready};
+use crate::driver::sys::{Poll, Token};
+
+// From `linux/if_xdp.h`, which `libc` doesn't cover.
+const AF_XDP: c_int = 44;
+const SOL_XDP: c_int = 283;
+const XDP_MMAP_OFFSETS: c_int = 1;
+const XDP_RX_RING: c_int = 2;
+const XDP_TX_RING: c_int = 3;
+const XDP_UMEM_REG: c_int = 4;
+const XDP_UMEM_FILL_RING: c_int = 5;
+const XDP_UMEM_COMPLETION_RING: c_int = 6;
+const XDP_PGOFF_RX_RING: libc::off_t = 0;
+const XDP_PGOFF_TX_RING: libc::off_t = 0x8000_0000;
+const XDP_UMEM_PGOFF_FILL_RING: libc::off_t = 0x1_0000_0000;
+const XDP_UMEM_PGOFF_COMPLETION_RING: libc::off_t = 0x1_8000_0000;
+const XDP_RING_NEED_WAKEUP: u32 = 1;
+
+pub const XDP_COPY: u16 = 1 << 1;
+pub const XDP_ZEROCOPY: u16 = 1 << 2;
+pub const XDP_USE_NEED_WAKEUP: u16 = 1 << 3;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct xdp_umem_reg {
+    addr: u64,
+    len: u64,
+    chunk_size: u32,
+    headroom: u32,
+    flags: u32,
+    tx_metadata_len: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct xdp_ring_offset {
+    producer: u64,
+    consumer: u64,
+    desc: u64,
+    flags: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct xdp_mmap_offsets {
+    rx: xdp_ring_offset,
+    tx: xdp_ring_offset,
+    fr: xdp_ring_offset,
+    cr: xdp_ring_offset,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct sockaddr_xdp {
+    sxdp_family: u16,
+    sxdp_flags: u16,
+    sxdp_ifindex: u32,
+    sxdp_queue_id: u32,
+    sxdp_shared_umem_fd: u32,
+}
+
+/// A packet descriptor of the RX and TX rings (`struct xdp_desc`).
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct XdpDesc {
+    pub addr: u64,
+    pub len: u32,
+    pub options: u32,
+}
+
+/// An `AF_XDP` socket, with its UMEM and its four rings.
+#[derive(Debug)]
+pub struct XdpSocket {
+    // Closed first, so the kernel is done with the mappings below when they
+    // are unmapped.
+    fd: SocketFd,
+    umem: Mmap,
+    frame_size: u32,
+    fill: Ring,
+    completion: Ring,
+    rx: Ring,
+    tx: Ring,
+}
+
+/// The rings are only accessed through `&mut XdpSocket`, and the memory
+/// isn't tied to the thread which mapped it.
+unsafe impl Send for XdpSocket {}
+
+impl XdpSocket {
+    /// Opens a socket with a UMEM of `frame_count` frames of `frame_size`
+    /// bytes, and rings of `ring_size` entries, a power of two.
+    pub fn new(
+        frame_count: u32,
+        frame_size: u32,
+        ring_size: u32,
+    ) -> io::Result<XdpSocket> {
+        if !ring_size.is_power_of_two() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "ring size must be a power of two",
+            ));
+        }
+        let fd = SocketFd::new(AF_XDP, libc::SOCK_RAW, 0)?;
+
+        let len = frame_count as usize * frame_size as usize;
+        let umem = Mmap::anonymous(len)?;
+        let reg = xdp_umem_reg {
+            addr: umem.ptr as u64,
+            len: len as u64,
+            chunk_size: frame_size,
+            headroom: 0,
+            flags: 0,
+            tx_metadata_len: 0,
+        };
+        fd.setsockopt(SOL_XDP, XDP_UMEM_REG, reg)?;
+
+        for &name in &[
+            XDP_UMEM_FILL_RING,
+            XDP_UMEM_COMPLETION_RING,
+            XDP_RX_RING,
+            XDP_TX_RING,
+        ] {
+            fd.setsockopt(SOL_XDP, name, ring_size as c_int)?;
+        }
+        let off: xdp_mmap_offsets = fd.getsockopt(SOL_XDP, XDP_MMAP_OFFSETS)?;
+
+        let raw = fd.as_raw_fd();
+        let addr = mem::size_of::<u64>();
+        let desc = mem::size_of::<XdpDesc>();
+        Ok(XdpSocket {
+            fill: Ring::map(raw, &off.fr, ring_size, addr, XDP_UMEM_PGOFF_FILL_RING)?,
+            completion: Ring::map(
+                raw,
+                &off.cr,
+                ring_size,
+                addr,
+                XDP_UMEM_PGOFF_COMPLETION_RING,
+            )?,
+            rx: Ring::map(raw, &off.rx, ring_size, desc, XDP_PGOFF_RX_RING)?,
+            tx: Ring::map(raw, &off.tx, ring_size, desc, XDP_PGOFF_TX_RING)?,
+            fd,
+            umem,
+            frame_size,
+        })
+    }
+
+    pub fn bind(&self, ifindex: u32, queue_id: u32, flags: u16) -> io::Result<()> {
+        let addr = sockaddr_xdp {
+            sxdp_family: AF_XDP as u16,
+            sxdp_flags: flags,
+            sxdp_ifindex: ifindex,
+            sxdp_queue_id: queue_id,
+            sxdp_shared_umem_fd: 0,
+        };
+        self.fd.bind(&addr)
+    }
+
+    pub fn frame_size(&self) -> u32 {
+        self.frame_size
+    }
+
+    pub fn umem(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.umem.ptr as *const u8, self.umem.len) }
+    }
+
+    pub fn umem_mut(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.umem.ptr as *mut u8, self.umem.len) }
+    }
+
+    /// Hands frames to the kernel for reception, returning how many fit in
+    /// the fill ring.
+    pub fn fill(&mut self, addrs: &[u64]) -> usize {
+        let n = unsafe { self.fill.produce(addrs) };
+        if n > 0 && self.fill.needs_wakeup() {
+            // Only a hint to the driver, the frames are queued anyway.
+            let _ = self.fd.recv(&mut []);
+        }
+        n
+    }
+
+    /// Takes received descriptors off the RX ring.
+    pub fn recv(&mut self, descs: &mut [XdpDesc]) -> usize {
+        unsafe { self.rx.consume(descs) }
+    }
+
+    /// Queues descriptors on the TX ring and asks the kernel to transmit
+    /// them, returning how many fit in the ring.
+    pub fn send(&mut self, descs: &[XdpDesc]) -> io::Result<usize> {
+        let n = unsafe { self.tx.produce(descs) };
+        if n > 0 || self.tx.needs_wakeup() {
+            match self.fd.send(&[]) {
+                // The kernel is still busy with earlier frames, it picks up
+                // the new ones on its own.
+                Err(ref e)
+                    if e.kind() == io::ErrorKind::WouldBlock
+                        || e.raw_os_error() == Some(libc::EBUSY)
+                        || e.raw_os_error() == Some(libc::ENOBUFS) => {}
+                Err(e) => return Err(e),
+                Ok(_) => {}
+            }
+        }
+        Ok(n)
+    }
+
+    /// Takes the addresses of transmitted frames off the completion ring.
+    pub fn complete(&mut self, addrs: &mut [u64]) -> usize {
+        unsafe { self.completion.consume(addrs) }
+    }
+
+    pub fn take_error(&self) -> io::Result<Option<io::Error>> {
+        self.fd.take_error()
+    }
+}
+
+impl Evented for XdpSocket {
+    fn register(
+        &self,
+        poll: &Poll,
+        token: Token,
+        events: Ready,
+        opts: PollOpt,
+    ) -> io::Result<()> {
+        self.fd.register(poll, token, events, opts)
+    }
+
+    fn reregister(
+        &self,
+        poll: &Poll,
+        token: Token,
+        events: Ready,
+        opts: PollOpt,
+    ) -> io::Result<()> {
+        self.fd.reregister(poll, token, events, opts)
+    }
+
+    fn deregister(&self, poll: &Poll) -> io::Result<()> {
+        self.fd.deregister(poll)
+    }
+}
+
+impl AsRawFd for XdpSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+/// A single-producer, single-consumer ring shared with the kernel.
+#[derive(Debug)]
+struct Ring {
+    /// Never read, the pointers below point into it and it is unmapped when
+    /// the ring is dropped.
+    map: Mmap,
+    producer: *const AtomicU32,
+    consumer: *const AtomicU32,
+    flags: *const AtomicU32,
+    desc: *mut u8,
+    size: u32,
+}
+
+impl Ring {
+    fn map(
+        fd: RawFd,
+        off: &xdp_ring_offset,
+        size: u32,
+        entry: usize,
+        pgoff: libc::off_t,
+    ) -> io::Result<Ring> {
+        let len = off.desc as usize + size as usize * entry;
+        let map = Mmap::new(len, libc::MAP_SHARED | libc::MAP_POPULATE, fd, pgoff)?;
+        let base = map.ptr as *mut u8;
+        unsafe {
+            Ok(Ring {
+                producer: base.add(off.producer as usize) as *const AtomicU32,
+                consumer: base.add(off.consumer as usize) as *const AtomicU32,
+                flags: base.add(off.flags as usize) as *const AtomicU32,
+                desc: base.add(off.desc as usize),
+                size,
+                map,
+            })
+        }
+    }
+
+    fn needs_wakeup(&self) -> bool {
+        unsafe { (*self.flags).load(Ordering::Relaxed) & XDP_RING_NEED_WAKEUP != 0 }
+    }
+
+    /// Copies as many of `entries` as there is room for into the ring.
+    ///
+    /// Safety: the ring must be one the process produces into, with entries
+    /// of type `T`.
+    unsafe fn produce<T: Copy>(&mut self, entries: &[T]) -> usize {
+        let prod = (*self.producer).load(Ordering::Relaxed);
+        let cons = (*self.consumer).load(Ordering::Acquire);
+        let free = self.size - prod.wrapping_sub(cons);
+        let n = free.min(entries.len() as u32);
+        let desc = self.desc as *mut T;
+        for (i, entry) in entries[..n as usize].iter().enumerate() {
+            let idx = prod.wrapping_add(i as u32) & (self.size - 1);
+            ptr::write(desc.add(idx as usize), *entry);
+        }
+        (*self.producer).store(prod.wrapping_add(n), Ordering::Release);
+        n as usize
+    }
+
+    /// Moves as many entries as are available into `entries`.
+    ///
+    /// Safety: the ring must be one the kernel produces into, with entries
+    /// of type `T`.
+    unsafe fn consume<T: Copy>(&mut self, entries: &mut [T]) -> usize {
+        let cons = (*self.consumer).load(Ordering::Relaxed);
+        let prod = (*self.producer).load(Ordering::Acquire);
+        let n = prod.wrapping_sub(cons).min(entries.len() as u32);
+        let desc = self.desc as *const T;
+        for (i, entry) in entries[..n as usize].iter_mut().enumerate() {
+            let idx = cons.wrapping_add(i as u32) & (self.size - 1);
+            *entry = ptr::read(desc.add(idx as usize));
+        }
+        (*self.consumer).store(cons.wrapping_add(n), Ordering::Release);
+        n as usize
+    }
+}
@@ -1,5 +1,6 @@
+use super::backend::{Awakener as _, Events as _, Selector as _};
 use super::event::{self, Event, Evented, PollOpt, Ready};
-use super::{linux, Token};
+use super::{SysAwakener, SysEvents, SysSelector, Token};
 use std::cell::UnsafeCell;
 use std::os::unix::io::AsRawFd;
 use std::os::unix::io::RawFd;
@@ -12,10 +13,10 @@ use std::{fmt, io, ptr, usize};
 use std::{isize, mem, ops};
 
 // Poll is backed by two readiness queues. The first is a system readiness queue
-// represented by `linux::Selector`. The system readiness queue handles events
-// provided by the system, such as TCP and UDP. The second readiness queue is
-// implemented in user space by `ReadinessQueue`. It provides a way to implement
-// purely user space `Evented` types.
+// represented by the `Selector` of the platform backend. The system readiness
+// queue handles events provided by the system, such as TCP and UDP. The second
+// readiness queue is implemented in user space by `ReadinessQueue`. It provides
+// a way to implement purely user space `Evented` types.
 //
 // `ReadinessQueue` is backed by a MPSC queue that supports reuse of linked
 // list nodes. This significantly reduces the number of required allocations.
@@ -325,7 +326,7 @@ use std::{isize, mem, ops};
 /// [`Poll::poll`]: struct.Poll.html#method.poll
 pub struct Poll {
     // Platform specific IO selector
-    selector: linux::Selector,
+    selector: SysSelector,
 
     // Custom readiness queue
     readiness_queue: ReadinessQueue,
@@ -481,7 +482,7 @@ unsafe impl Sync for ReadinessQueue {}
 
 struct ReadinessQueueInner {
     // Used to wake up `Poll` when readiness is set in another thread.
-    awakener: linux::Awakener,
+    awakener: SysAwakener,
 
     // Head of the MPSC queue used to signal readiness to `Poll::poll`.
     head_readiness: AtomicPtr<ReadinessNode>,
@@ -652,7 +653,7 @@ impl Poll {
         is_sync::<Poll>();
 
         let poll = Poll {
-            selector: linux::Selector::new()?,
+            selector: SysSelector::new()?,
             readiness_queue: ReadinessQueue::new()?,
             lock_state: AtomicUsize::new(0),
             lock: Mutex::new(()),
@@ -1184,7 +1185,7 @@ impl Poll {
     }
 
     #[inline]
-    #[cfg_attr(feature = "cargo-clippy", allow(clippy::if_same_then_else))]
+    #[allow(clippy::if_same_then_else)]
     fn poll2(
         &self,
         events: &mut Events,
@@ -1260,6 +1261,7 @@ impl fmt::Debug for Poll {
     }
 }
 
+#[cfg(target_os = "linux")]
 impl AsRawFd for Poll {
     fn as_raw_fd(&self) -> RawFd {
         self.selector.as_raw_fd()
@@ -1307,7 +1309,7 @@ impl AsRawFd for Poll {
 /// [`Poll::poll`]: struct.Poll.html#method.poll
 /// [`Poll`]: struct.Poll.html
 pub struct Events {
-    inner: linux::Events,
+    inner: SysEvents,
 }
 
 /// [`Events`] iterator.
@@ -1400,7 +1402,7 @@ impl Events {
     /// ```
     pub fn with_capacity(capacity: usize) -> Events {
         Events {
-            inner: linux::Events::with_capacity(capacity),
+            inner: SysEvents::with_capacity(capacity),
         }
     }
 
@@ -1410,7 +1412,7 @@ impl Events {
     )]
     #[doc(hidden)]
     pub fn get(&self, idx: usize) -> Option<Event> {
-        self.inner.get(idx)
+        self.inner.get(idx).copied()
     }
 
     #[doc(hidden)]
@@ -1452,6 +1454,9 @@ impl Events {
 
     /// Returns an iterator over the `Event` values.
     ///
+    /// The iterator yields references into the buffer filled in by the
+    /// system selector, so no event is copied until the caller asks for it.
+    ///
     /// # Examples
     ///
     /// ```
@@ -1487,6 +1492,13 @@ impl Events {
 
     /// Clearing all `Event` values from container explicitly.
     ///
+    /// The capacity is retained, so a single `Events` can be reused across
+    /// any number of calls to [`Poll::poll`] without reallocating. `poll`
+    /// already clears the container before filling it, calling `clear`
+    /// is only needed to drop stale events early.
+    ///
+    /// [`Poll::poll`]: struct.Poll.html#method.poll
+    ///
     /// # Examples
     ///
     /// ```
@@ -1521,7 +1533,7 @@ impl Events {
 }
 
 impl<'a> IntoIterator for &'a Events {
-    type Item = Event;
+    type Item = &'a Event;
     type IntoIter = Iter<'a>;
 
     fn into_iter(self) -> Self::IntoIter {
@@ -1530,15 +1542,22 @@ impl<'a> IntoIterator for &'a Events {
 }
 
 impl<'a> Iterator for Iter<'a> {
-    type Item = Event;
+    type Item = &'a Event;
 
-    fn next(&mut self) -> Option<Event> {
+    fn next(&mut self) -> Option<&'a Event> {
         let ret = self.inner.inner.get(self.pos);
         self.pos += 1;
         ret
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.inner.inner.len().saturating_sub(self.pos);
+        (len, Some(len))
+    }
 }
 
+impl<'a> ExactSizeIterator for Iter<'a> {}
+
 impl IntoIterator for Events {
     type Item = Event;
     type IntoIter = IntoIter;
@@ -1555,7 +1574,7 @@ impl Iterator for IntoIter {
     type Item = Event;
 
     fn next(&mut self) -> Option<Event> {
-        let ret = self.inner.inner.get(self.pos);
+        let ret = self.inner.inner.get(self.pos).copied();
         self.pos += 1;
         ret
     }
@@ -1571,7 +1590,7 @@ impl fmt::Debug for Events {
 
 // ===== Accessors for internal usage =====
 
-pub fn selector(poll: &Poll) -> &linux::Selector {
+pub fn selector(poll: &Poll) -> &SysSelector {
     &poll.selector
 }
 
@@ -2170,7 +2189,7 @@ impl ReadinessQueue {
 
         Ok(ReadinessQueue {
             inner: Arc::new(ReadinessQueueInner {
-                awakener: linux::Awakener::new()?,
+                awakener: SysAwakener::new()?,
                 head_readiness: AtomicPtr::new(ptr),
                 tail_readiness: UnsafeCell::new(ptr),
                 end_marker,
@@ -2181,7 +2200,7 @@ impl ReadinessQueue {
     }
 
     /// Poll the queue for new events
-    fn poll(&self, dst: &mut linux::Events) {
+    fn poll(&self, dst: &mut SysEvents) {
         // `until` is set with the first node that gets re-enqueued due to being
         // set to have level-triggered notifications. This prevents an infinite
         // loop where `Poll::poll` will keep dequeuing nodes it enqueues.
@@ -2279,7 +2298,7 @@ impl ReadinessQueue {
                 let token = unsafe { token(node, next.token_read_pos()) };
 
                 // Push the event
-                dst.push_event(Event::new(readiness, token));
+                dst.push_event(readiness, token);
             }
         }
     }
@@ -2864,3 +2883,25 @@ pub fn as_raw_fd() {
     let poll = Poll::new().unwrap();
     assert!(poll.as_raw_fd() > 0);
 }
+
+#[test]
+fn events_iter_by_ref() {
+    let poll = Poll::new().unwrap();
+    let mut events = Events::with_capacity(16);
+    let (registration, set_readiness) = Registration::new2();
+
+    poll.register(&registration, Token(3), Ready::readable(), PollOpt::edge())
+        .unwrap();
+    set_readiness.set_readiness(Ready::readable()).unwrap();
+
+    poll.poll(&mut events, Some(Duration::from_millis(0)))
+        .unwrap();
+    let event: &Event = events.iter().next().unwrap();
+    assert_eq!(event.token(), Token(3));
+    assert!(event.readiness().is_readable());
+    assert_eq!(events.iter().len(), 1);
+
+    events.clear();
+    assert!(events.is_empty());
+    assert_eq!(events.capacity(), 16);
+}
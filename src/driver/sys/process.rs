@@ -0,0 +1,83 @@
+//! Process primitives
+//!
+//! Pipes to a child process and `pidfd`s, which become readable when the
+//! process they refer to exits.
+
+use libc::{self, c_int};
+use std::io;
+use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd};
+
+use crate::driver::sys::event::{Evented, EventedFd, PollOpt, Ready};
+use crate::driver::sys::platform::{set_nonblock, Io};
+use crate::driver::sys::{Poll, Token};
+
+// The same on every architecture, unlike older syscalls.
+const SYS_PIDFD_OPEN: libc::c_long = 434;
+
+/// Turns one end of a pipe to a child, as handed out by
+/// `std::process::Child`, into a nonblocking `Io`.
+pub fn pipe_end<T: IntoRawFd>(end: T) -> io::Result<Io> {
+    let fd = end.into_raw_fd();
+    // Safety: `fd` was just released by its owner.
+    let io = unsafe { Io::from_raw_fd(fd) };
+    set_nonblock(fd)?;
+    Ok(io)
+}
+
+/// A file descriptor referring to a process.
+#[derive(Debug)]
+pub struct PidFd {
+    fd: c_int,
+}
+
+impl PidFd {
+    /// Opens a `pidfd` for the process `pid`, which fails with `ENOSYS`
+    /// before Linux 5.3.
+    pub fn open(pid: u32) -> io::Result<PidFd> {
+        let fd = unsafe { libc::syscall(SYS_PIDFD_OPEN, pid as libc::pid_t, 0) };
+        if fd == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(PidFd { fd: fd as c_int })
+    }
+}
+
+impl Evented for PidFd {
+    fn register(
+        &self,
+        poll: &Poll,
+        token: Token,
+        interest: Ready,
+        opts: PollOpt,
+    ) -> io::Result<()> {
+        EventedFd(&self.fd).register(poll, token, interest, opts)
+    }
+
+    fn reregister(
+        &self,
+        poll: &Poll,
+        token: Token,
+        interest: Ready,
+        opts: PollOpt,
+    ) -> io::Result<()> {
+        EventedFd(&self.fd).reregister(poll, token, interest, opts)
+    }
+
+    fn deregister(&self, poll: &Poll) -> io::Result<()> {
+        EventedFd(&self.fd).deregister(poll)
+    }
+}
+
+impl AsRawFd for PidFd {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+impl Drop for PidFd {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.fd);
+        }
+    }
+}
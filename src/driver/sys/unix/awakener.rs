@@ -2,8 +2,9 @@ pub use self::pipe::Awakener;
 
 /// Default awakener backed by a pipe
 mod pipe {
+    use crate::driver::sys::backend;
     use crate::driver::sys::event::{Evented, PollOpt, Ready};
-    use crate::driver::sys::linux;
+    use crate::driver::sys::unix;
     use crate::driver::sys::{Poll, Token};
     use std::io::{self, Read, Write};
 
@@ -14,13 +15,19 @@ mod pipe {
      */
 
     pub struct Awakener {
-        reader: linux::Io,
-        writer: linux::Io,
+        reader: unix::Io,
+        writer: unix::Io,
     }
 
     impl Awakener {
-        pub fn new() -> io::Result<Awakener> {
-            let (rd, wr) = linux::pipe()?;
+        fn reader(&self) -> &unix::Io {
+            &self.reader
+        }
+    }
+
+    impl backend::Awakener for Awakener {
+        fn new() -> io::Result<Awakener> {
+            let (rd, wr) = unix::pipe()?;
 
             Ok(Awakener {
                 reader: rd,
@@ -28,8 +35,8 @@ mod pipe {
             })
         }
 
-        pub fn wakeup(&self) -> io::Result<()> {
-            match (&self.writer).write(&[1]) {
+        fn wakeup(&self) -> io::Result<()> {
+            match (&self.writer).write_all(&[1]) {
                 Ok(_) => Ok(()),
                 Err(e) => {
                     if e.kind() == io::ErrorKind::WouldBlock {
@@ -41,7 +48,7 @@ mod pipe {
             }
         }
 
-        pub fn cleanup(&self) {
+        fn cleanup(&self) {
             let mut buf = [0; 128];
 
             loop {
@@ -52,10 +59,6 @@ mod pipe {
                 }
             }
         }
-
-        fn reader(&self) -> &linux::Io {
-            &self.reader
-        }
     }
 
     impl Evented for Awakener {
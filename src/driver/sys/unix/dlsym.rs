@@ -6,8 +6,8 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 macro_rules! dlsym {
     (fn $name:ident($($t:ty),*) -> $ret:ty) => (
         #[allow(bad_style)]
-        static $name: crate::driver::sys::linux::dlsym::DlSym<unsafe extern fn($($t),*) -> $ret> =
-        crate::driver::sys::linux::dlsym::DlSym {
+        static $name: crate::driver::sys::unix::dlsym::DlSym<unsafe extern fn($($t),*) -> $ret> =
+        crate::driver::sys::unix::dlsym::DlSym {
                 name: concat!(stringify!($name), "\0"),
                 addr: ::std::sync::atomic::ATOMIC_USIZE_INIT,
                 _marker: ::std::marker::PhantomData,
@@ -6,7 +6,7 @@ use std::io::{self, Read, Write};
 use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd};
 
 use crate::driver::sys::event::{Evented, EventedFd, PollOpt, Ready};
-use crate::driver::sys::linux::cvt;
+use crate::driver::sys::unix::cvt;
 use crate::driver::sys::{Poll, Token};
 
 pub fn set_nonblock(fd: libc::c_int) -> io::Result<()> {
@@ -0,0 +1,43 @@
+//! Socket options shared by the BSD socket backends.
+
+use libc::{self, c_int, c_void, socklen_t};
+use std::io;
+use std::mem;
+use std::os::unix::io::RawFd;
+
+use crate::driver::sys::unix::cvt;
+
+pub fn setsockopt<T: Copy>(
+    fd: RawFd,
+    level: c_int,
+    name: c_int,
+    val: T,
+) -> io::Result<()> {
+    unsafe {
+        let payload = &val as *const T as *const c_void;
+        cvt(libc::setsockopt(
+            fd,
+            level,
+            name,
+            payload,
+            mem::size_of::<T>() as socklen_t,
+        ))?;
+    }
+    Ok(())
+}
+
+pub fn getsockopt<T: Copy>(fd: RawFd, level: c_int, name: c_int) -> io::Result<T> {
+    unsafe {
+        let mut slot: T = mem::zeroed();
+        let mut len = mem::size_of::<T>() as socklen_t;
+        cvt(libc::getsockopt(
+            fd,
+            level,
+            name,
+            &mut slot as *mut T as *mut c_void,
+            &mut len,
+        ))?;
+        assert_eq!(len as usize, mem::size_of::<T>());
+        Ok(slot)
+    }
+}
@@ -9,7 +9,7 @@ use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd};
 use std::time::Duration;
 
 use crate::driver::sys::event::{Evented, EventedFd, PollOpt, Ready};
-use crate::driver::sys::linux::io::{set_nonblock, VecIo};
+use crate::driver::sys::unix::io::{set_nonblock, VecIo};
 use crate::driver::sys::{Poll, Token};
 
 pub struct TcpStream {
@@ -130,6 +130,10 @@ impl<'a> Read for &'a TcpStream {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         (&self.inner).read(buf)
     }
+
+    fn read_vectored(&mut self, bufs: &mut [io::IoSliceMut<'_>]) -> io::Result<usize> {
+        (&self.inner).read_vectored(bufs)
+    }
 }
 
 impl<'a> Write for &'a TcpStream {
@@ -137,6 +141,10 @@ impl<'a> Write for &'a TcpStream {
         (&self.inner).write(buf)
     }
 
+    fn write_vectored(&mut self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
+        (&self.inner).write_vectored(bufs)
+    }
+
     fn flush(&mut self) -> io::Result<()> {
         (&self.inner).flush()
     }
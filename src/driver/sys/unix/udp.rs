@@ -4,7 +4,7 @@ use std::net::{self, Ipv4Addr, Ipv6Addr, SocketAddr};
 use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd};
 
 use crate::driver::sys::event::{Evented, EventedFd, PollOpt, Ready};
-use crate::driver::sys::linux::io::VecIo;
+use crate::driver::sys::unix::io::VecIo;
 use crate::driver::sys::{Poll, Token};
 
 use iovec::IoVec;
@@ -0,0 +1,155 @@
+//! Source of time for timers, which can be paused in tests.
+//!
+//! A `current_thread` runtime owns a clock and makes it current while it
+//! runs. Until the clock is paused for the first time it follows the system
+//! clock and timers are driven by the reactor. Once paused, time only moves
+//! when advanced, and timers created in the runtime are kept by the clock
+//! and fired by the runtime as it advances.
+
+use parking_lot::Mutex;
+use std::cell::RefCell;
+use std::sync::Arc;
+use std::time::Instant;
+
+use super::Timers;
+
+thread_local! {
+    static CURRENT: RefCell<Option<Arc<Clock>>> = RefCell::new(None);
+}
+
+pub(crate) struct Clock {
+    state: Mutex<State>,
+    /// Timers registered once the clock diverged from the system clock.
+    pub(super) timers: Timers,
+}
+
+struct State {
+    /// Time at the last pause or resume.
+    base: Instant,
+    /// System time at the last resume, `None` while paused.
+    unfrozen: Option<Instant>,
+    /// Set by the first pause, the clock doesn't follow the system clock
+    /// anymore.
+    diverged: bool,
+}
+
+impl Clock {
+    pub(crate) fn new(paused: bool) -> Arc<Clock> {
+        let now = Instant::now();
+        Arc::new(Clock {
+            state: Mutex::new(State {
+                base: now,
+                unfrozen: if paused { None } else { Some(now) },
+                diverged: paused,
+            }),
+            timers: Timers::new(),
+        })
+    }
+
+    pub(crate) fn now(&self) -> Instant {
+        let state = self.state.lock();
+        match state.unfrozen {
+            Some(unfrozen) => state.base + unfrozen.elapsed(),
+            None => state.base,
+        }
+    }
+
+    pub(crate) fn is_paused(&self) -> bool {
+        self.state.lock().unfrozen.is_none()
+    }
+
+    /// Returns `true` if timers must be kept by this clock rather than by
+    /// the reactor.
+    pub(super) fn is_diverged(&self) -> bool {
+        self.state.lock().diverged
+    }
+
+    pub(crate) fn pause(&self) {
+        let mut state = self.state.lock();
+        if let Some(unfrozen) = state.unfrozen.take() {
+            state.base += unfrozen.elapsed();
+            state.diverged = true;
+        }
+    }
+
+    pub(crate) fn resume(&self) {
+        let mut state = self.state.lock();
+        if state.unfrozen.is_none() {
+            state.unfrozen = Some(Instant::now());
+        }
+    }
+
+    /// Moves the paused clock forward to `t` and fires the timers which are
+    /// due.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the clock is not paused.
+    pub(crate) fn advance_to(&self, t: Instant) {
+        {
+            let mut state = self.state.lock();
+            assert!(state.unfrozen.is_none(), "time is not paused");
+            if t > state.base {
+                state.base = t;
+            }
+        }
+        self.fire();
+    }
+
+    /// Returns the deadline of the next timer kept by the clock.
+    pub(crate) fn next_deadline(&self) -> Option<Instant> {
+        self.timers.next_deadline()
+    }
+
+    /// Fires the timers which are due.
+    pub(crate) fn fire(&self) {
+        self.timers.process(self.now());
+    }
+}
+
+/// Returns the clock of the runtime running on this thread.
+pub(crate) fn current() -> Option<Arc<Clock>> {
+    CURRENT.with(|current| current.borrow().clone())
+}
+
+/// Returns the current time, as seen by the clock of the current runtime.
+pub(crate) fn now() -> Instant {
+    CURRENT.with(|current| match &*current.borrow() {
+        Some(clock) => clock.now(),
+        None => Instant::now(),
+    })
+}
+
+/// Makes `clock` current until the guard is dropped.
+pub(crate) fn enter(clock: &Arc<Clock>) -> EnterGuard {
+    let prev = CURRENT.with(|current| current.borrow_mut().replace(clock.clone()));
+    EnterGuard { prev }
+}
+
+pub(crate) struct EnterGuard {
+    prev: Option<Arc<Clock>>,
+}
+
+impl Drop for EnterGuard {
+    fn drop(&mut self) {
+        let prev = self.prev.take();
+        CURRENT.with(|current| *current.borrow_mut() = prev);
+    }
+}
+
+#[test]
+fn test_paused_clock_only_moves_when_advanced() {
+    use std::time::Duration;
+
+    let clock = Clock::new(true);
+    let start = clock.now();
+    std::thread::sleep(Duration::from_millis(2));
+    assert_eq!(clock.now(), start);
+
+    clock.advance_to(start + Duration::from_secs(3600));
+    assert_eq!(clock.now(), start + Duration::from_secs(3600));
+
+    clock.resume();
+    assert!(clock.now() >= start + Duration::from_secs(3600));
+    assert!(clock.is_diverged());
+}
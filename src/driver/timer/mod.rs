@@ -0,0 +1,396 @@
+//! Timers driven by the reactor.
+//!
+//! Each reactor keeps the pending deadlines of its timers in a timing wheel
+//! with a resolution of one millisecond. Before blocking in the system
+//! selector, the reactor caps its timeout at the next expiration of the
+//! wheel, and after each turn it fires the timers which have elapsed.
+//! Deadlines can be rounded to a coarser granularity, see
+//! [`set_timer_granularity`], so nearby timers fire together.
+//!
+//! [`set_timer_granularity`]: ../fn.set_timer_granularity.html
+
+pub(crate) mod clock;
+pub(crate) mod wheel;
+
+use futures_util::task::AtomicWaker;
+use parking_lot::Mutex;
+use std::fmt;
+use std::io;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use self::clock::Clock;
+use self::wheel::{Key, Wheel, MAX_TICKS};
+use super::HandlePriv;
+
+/// Ticks to which the deadlines of new timers are rounded up.
+pub(super) static GRANULARITY_MS: AtomicU64 = AtomicU64::new(1);
+
+/// Upper bound of the granularity, a bucket has to fit in the wheel.
+pub(super) const MAX_GRANULARITY_MS: u64 = 1 << 30;
+
+/// The timers of a reactor.
+pub(crate) struct Timers {
+    /// Instant of tick zero.
+    start: Instant,
+    wheel: Mutex<Wheel<Arc<Entry>>>,
+}
+
+/// State shared between a timer and its reactor.
+#[derive(Debug, Default)]
+struct Entry {
+    fired: AtomicBool,
+    waker: AtomicWaker,
+}
+
+/// A deadline registered with the reactor of the current execution context.
+///
+/// The registration happens on the first poll, so a timer can be created
+/// outside of the task which awaits it.
+#[derive(Debug)]
+pub(crate) struct Timer {
+    deadline: Instant,
+    registered: Option<Registered>,
+}
+
+#[derive(Debug)]
+struct Registered {
+    source: Source,
+    entry: Arc<Entry>,
+    /// `None` if the timer fired when it was registered.
+    key: Option<Key>,
+}
+
+/// What drives a registered timer.
+enum Source {
+    Reactor(HandlePriv),
+    /// A clock which diverged from the system clock.
+    Clock(Arc<Clock>),
+}
+
+// ===== impl Timers =====
+
+impl Timers {
+    pub(crate) fn new() -> Timers {
+        Timers {
+            start: Instant::now(),
+            wheel: Mutex::new(Wheel::new()),
+        }
+    }
+
+    /// Adds a timer, returning its key and whether the reactor has to wake
+    /// up earlier than it planned to.
+    ///
+    /// A timer whose deadline is already reached fires right away and gets
+    /// no key.
+    fn insert(&self, deadline: Instant, entry: Arc<Entry>) -> (Option<Key>, bool) {
+        self.reset(None, deadline, entry)
+    }
+
+    /// Moves a timer to a new deadline, inserting it again if it already
+    /// fired.
+    fn reset(
+        &self,
+        key: Option<Key>,
+        deadline: Instant,
+        entry: Arc<Entry>,
+    ) -> (Option<Key>, bool) {
+        // Round up, timers must not fire early.
+        let when = round_up(
+            self.tick_for(deadline, true),
+            GRANULARITY_MS.load(Ordering::Relaxed),
+        );
+
+        let mut wheel = self.wheel.lock();
+        if let Some(key) = key {
+            wheel.remove(key);
+        }
+        // Under the lock, so a concurrent `process` can't mark the new
+        // deadline as fired.
+        entry.fired.store(false, Ordering::Release);
+
+        let when = when.min(wheel.elapsed() + MAX_TICKS - 1);
+        let next = wheel.next_expiration();
+        match wheel.insert(when, entry) {
+            Ok(key) => (Some(key), next.map_or(true, |next| when < next)),
+            Err(entry) => {
+                entry.fired.store(true, Ordering::Release);
+                (None, false)
+            }
+        }
+    }
+
+    fn remove(&self, key: Key) {
+        self.wheel.lock().remove(key);
+    }
+
+    /// Returns the instant at which the timers next need processing.
+    pub(super) fn next_deadline(&self) -> Option<Instant> {
+        let tick = self.wheel.lock().next_expiration()?;
+        Some(self.start + Duration::from_millis(tick))
+    }
+
+    pub(super) fn is_empty(&self) -> bool {
+        self.wheel.lock().is_empty()
+    }
+
+    /// Fires the timers whose deadline is not after `now`, returning how many
+    /// fired.
+    pub(super) fn process(&self, now: Instant) -> usize {
+        let now = self.tick_for(now, false);
+        let mut expired = Vec::new();
+        self.wheel.lock().advance(now, |entry| {
+            entry.fired.store(true, Ordering::Release);
+            expired.push(entry);
+        });
+
+        // Wake the tasks outside of the lock.
+        let fired = expired.len();
+        for entry in expired {
+            entry.waker.wake();
+        }
+        fired
+    }
+
+    /// Wakes every timer, used when the reactor goes away.
+    pub(super) fn fire_all(&self) {
+        let entries = self.wheel.lock().drain();
+        for entry in entries {
+            entry.waker.wake();
+        }
+    }
+
+    fn tick_for(&self, t: Instant, round_up: bool) -> u64 {
+        tick_for(self.start, t, round_up)
+    }
+}
+
+/// Returns the number of milliseconds from `start` to `t`, the ticks of a
+/// wheel started at `start`.
+pub(crate) fn tick_for(start: Instant, t: Instant, round_up: bool) -> u64 {
+    let nanos = t.saturating_duration_since(start).as_nanos();
+    let ticks = if round_up {
+        (nanos + 999_999) / 1_000_000
+    } else {
+        nanos / 1_000_000
+    };
+    ticks.min(u128::from(u64::MAX)) as u64
+}
+
+/// Rounds `tick` up to a multiple of `granularity`.
+fn round_up(tick: u64, granularity: u64) -> u64 {
+    match tick % granularity {
+        0 => tick,
+        rem => tick.saturating_add(granularity - rem),
+    }
+}
+
+// ===== impl Timer =====
+
+impl Timer {
+    pub(crate) fn new(deadline: Instant) -> Timer {
+        Timer {
+            deadline,
+            registered: None,
+        }
+    }
+
+    pub(crate) fn deadline(&self) -> Instant {
+        self.deadline
+    }
+
+    /// Returns `true` if the deadline has been reached.
+    pub(crate) fn is_elapsed(&self) -> bool {
+        match &self.registered {
+            Some(registered) => {
+                registered.entry.fired.load(Ordering::Acquire)
+                    && registered.source.now() >= self.deadline
+            }
+            None => false,
+        }
+    }
+
+    /// Resolves once the deadline is reached.
+    ///
+    /// Fails if there is no reactor to register with, or if the reactor
+    /// goes away before the deadline.
+    pub(crate) fn poll_elapsed(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if self.registered.is_none() {
+            if clock::now() >= self.deadline {
+                return Poll::Ready(Ok(()));
+            }
+            self.register()?;
+        }
+
+        let registered = self.registered.as_ref().unwrap();
+        registered.entry.waker.register(cx.waker());
+        if registered.entry.fired.load(Ordering::Acquire) {
+            if registered.source.now() >= self.deadline {
+                return Poll::Ready(Ok(()));
+            }
+            // A deadline past the range of the wheel fired at its last tick,
+            // it goes back in for the rest of the wait.
+            self.reset(self.deadline);
+        }
+        let registered = self.registered.as_ref().unwrap();
+        if registered.source.with_timers(|_| ()).is_none() {
+            return Poll::Ready(Err(gone()));
+        }
+        Poll::Pending
+    }
+
+    /// Changes the deadline, without allocating, whether or not the previous
+    /// one was reached.
+    pub(crate) fn reset(&mut self, deadline: Instant) {
+        self.deadline = deadline;
+
+        let registered = match &mut self.registered {
+            Some(registered) => registered,
+            // Registered with the new deadline on the next poll.
+            None => return,
+        };
+        let key = registered.key.take();
+        let entry = registered.entry.clone();
+        // Without a reactor the next poll reports the error.
+        let reset = registered
+            .source
+            .with_timers(|timers| timers.reset(key, deadline, entry));
+        if let Some((key, earliest)) = reset {
+            registered.key = key;
+            if earliest {
+                registered.source.wakeup();
+            }
+        }
+    }
+
+    fn register(&mut self) -> io::Result<()> {
+        let source = match clock::current() {
+            Some(clock) if clock.is_diverged() => Source::Clock(clock),
+            _ => Source::Reactor(HandlePriv::try_current()?),
+        };
+
+        let entry = Arc::new(Entry::default());
+        let (key, earliest) = source
+            .with_timers(|timers| timers.insert(self.deadline, entry.clone()))
+            .ok_or_else(gone)?;
+        if earliest {
+            source.wakeup();
+        }
+
+        self.registered = Some(Registered { source, entry, key });
+        Ok(())
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        if let Some(Registered {
+            source,
+            key: Some(key),
+            ..
+        }) = self.registered.take()
+        {
+            source.with_timers(|timers| timers.remove(key));
+        }
+    }
+}
+
+// ===== impl Source =====
+
+impl Source {
+    /// Runs `f` with the timers of the source, unless its reactor is gone.
+    fn with_timers<R>(&self, f: impl FnOnce(&Timers) -> R) -> Option<R> {
+        match self {
+            Source::Reactor(handle) => handle.inner().map(|inner| f(&inner.timers)),
+            Source::Clock(clock) => Some(f(&clock.timers)),
+        }
+    }
+
+    /// Returns the current time of the source.
+    fn now(&self) -> Instant {
+        match self {
+            Source::Reactor(_) => Instant::now(),
+            Source::Clock(clock) => clock.now(),
+        }
+    }
+
+    /// Makes the reactor recompute its timeout. Clocks are checked by their
+    /// runtime each time it runs out of work.
+    fn wakeup(&self) {
+        if let Source::Reactor(handle) = self {
+            handle.wakeup();
+        }
+    }
+}
+
+impl fmt::Debug for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Reactor(_) => f.write_str("Reactor"),
+            Source::Clock(_) => f.write_str("Clock"),
+        }
+    }
+}
+
+fn gone() -> io::Error {
+    io::Error::new(io::ErrorKind::Other, "timer driver gone")
+}
+
+#[test]
+fn test_process_fires_in_deadline_order() {
+    let timers = Timers::new();
+    let now = timers.start;
+    let late = Arc::new(Entry::default());
+    let early = Arc::new(Entry::default());
+
+    let (_, earliest) = timers.insert(now + Duration::from_secs(10), late.clone());
+    assert!(earliest);
+    let (key, earliest) = timers.insert(now + Duration::from_millis(1), early.clone());
+    assert!(key.is_some() && earliest);
+
+    let (key, _) = timers.insert(now, Arc::new(Entry::default()));
+    assert!(key.is_none());
+
+    timers.process(now + Duration::from_millis(1));
+    assert!(early.fired.load(Ordering::Acquire));
+    assert!(!late.fired.load(Ordering::Acquire));
+    assert!(timers.next_deadline().unwrap() <= now + Duration::from_secs(10));
+}
+
+#[test]
+fn test_deadline_past_the_wheel_does_not_fire_early() {
+    use futures_util::task::noop_waker;
+
+    let clock = Clock::new(true);
+    let _enter = clock::enter(&clock);
+    let start = clock.now();
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+
+    let deadline = start + Duration::from_millis(MAX_TICKS + 1000);
+    let mut timer = Timer::new(deadline);
+    assert!(timer.poll_elapsed(&mut cx).is_pending());
+
+    clock.advance_to(start + Duration::from_millis(MAX_TICKS));
+    assert!(!timer.is_elapsed());
+    assert!(timer.poll_elapsed(&mut cx).is_pending());
+
+    while let Some(next) = clock.next_deadline() {
+        clock.advance_to(next);
+        let _ = timer.poll_elapsed(&mut cx);
+    }
+    assert!(clock.now() >= deadline);
+    assert!(timer.is_elapsed());
+    assert!(timer.poll_elapsed(&mut cx).is_ready());
+}
+
+#[test]
+fn test_round_up_to_granularity() {
+    assert_eq!(round_up(7, 1), 7);
+    assert_eq!(round_up(7, 10), 10);
+    assert_eq!(round_up(20, 10), 20);
+    assert_eq!(round_up(21, 10), 30);
+    assert_eq!(round_up(u64::MAX - 1, 10), u64::MAX);
+}
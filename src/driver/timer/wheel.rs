@@ -0,0 +1,279 @@
+//! Hashed hierarchical timing wheel.
+//!
+//! Time is counted in ticks. The wheel has `NUM_LEVELS` levels of `SLOTS`
+//! slots each: a slot of level 0 covers one tick, a slot of level 1 covers
+//! `SLOTS` ticks, and so on. A timer is stored in the level matching how far
+//! its deadline is, and moves down to lower levels as time advances, until
+//! it fires from level 0. Entries of a slot form an intrusive list over a
+//! slab, so inserting and cancelling a timer are O(1).
+
+use slab::Slab;
+
+const LEVEL_BITS: usize = 6;
+const SLOTS: usize = 1 << LEVEL_BITS;
+const SLOT_MASK: u64 = SLOTS as u64 - 1;
+const NUM_LEVELS: usize = 6;
+
+/// Deadlines further than this many ticks away are clamped.
+pub(crate) const MAX_TICKS: u64 = 1 << (LEVEL_BITS * NUM_LEVELS);
+
+pub(crate) struct Wheel<T> {
+    /// Ticks elapsed since the wheel was created.
+    elapsed: u64,
+    levels: [Level; NUM_LEVELS],
+    nodes: Slab<Node<T>>,
+    next_id: u64,
+}
+
+/// Identifies an entry of the wheel. The id guards against a slab slot
+/// being reused after the entry fired.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Key {
+    index: usize,
+    id: u64,
+}
+
+struct Level {
+    /// Bit `n` is set if slot `n` is not empty.
+    occupied: u64,
+    heads: [Option<usize>; SLOTS],
+}
+
+struct Node<T> {
+    id: u64,
+    when: u64,
+    level: usize,
+    slot: usize,
+    prev: Option<usize>,
+    next: Option<usize>,
+    value: T,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Expiration {
+    level: usize,
+    slot: usize,
+    deadline: u64,
+}
+
+impl<T> Wheel<T> {
+    pub(crate) fn new() -> Wheel<T> {
+        Wheel {
+            elapsed: 0,
+            levels: Default::default(),
+            nodes: Slab::new(),
+            next_id: 0,
+        }
+    }
+
+    pub(crate) fn elapsed(&self) -> u64 {
+        self.elapsed
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Inserts `value` to fire at tick `when`.
+    ///
+    /// Returns the value back if `when` has already elapsed.
+    pub(crate) fn insert(&mut self, when: u64, value: T) -> Result<Key, T> {
+        if when <= self.elapsed {
+            return Err(value);
+        }
+
+        let id = self.next_id;
+        self.next_id += 1;
+        let index = self.nodes.insert(Node {
+            id,
+            when,
+            level: 0,
+            slot: 0,
+            prev: None,
+            next: None,
+            value,
+        });
+        self.link(index);
+        Ok(Key { index, id })
+    }
+
+    /// Removes an entry which hasn't fired yet.
+    pub(crate) fn remove(&mut self, key: Key) -> Option<T> {
+        match self.nodes.get(key.index) {
+            Some(node) if node.id == key.id => {}
+            _ => return None,
+        }
+        self.unlink(key.index);
+        Some(self.nodes.remove(key.index).value)
+    }
+
+    /// Returns the tick at which the wheel next has work to do.
+    ///
+    /// This is a lower bound of the earliest deadline: entries of the upper
+    /// levels are cascaded at the start of their slot.
+    pub(crate) fn next_expiration(&self) -> Option<u64> {
+        self.next_slot().map(|exp| exp.deadline)
+    }
+
+    /// Advances the wheel to tick `now`, passing the values of the entries
+    /// which fired to `fire`.
+    pub(crate) fn advance(&mut self, now: u64, mut fire: impl FnMut(T)) {
+        while let Some(exp) = self.next_slot() {
+            if exp.deadline > now {
+                break;
+            }
+            self.process(exp, &mut fire);
+        }
+        if now > self.elapsed {
+            self.elapsed = now;
+        }
+    }
+
+    /// Removes every entry.
+    pub(crate) fn drain(&mut self) -> Vec<T> {
+        self.levels = Default::default();
+        self.nodes.drain().map(|node| node.value).collect()
+    }
+
+    fn next_slot(&self) -> Option<Expiration> {
+        // Lower levels always expire before upper ones.
+        (0..NUM_LEVELS).find_map(|level| self.levels[level].next(level, self.elapsed))
+    }
+
+    fn process(&mut self, exp: Expiration, fire: &mut impl FnMut(T)) {
+        debug_assert!(exp.deadline >= self.elapsed);
+        self.elapsed = exp.deadline;
+
+        let level = &mut self.levels[exp.level];
+        let mut next = level.heads[exp.slot].take();
+        level.occupied &= !(1 << exp.slot);
+
+        while let Some(index) = next {
+            let node = &mut self.nodes[index];
+            next = node.next;
+            node.prev = None;
+            node.next = None;
+
+            if node.when <= self.elapsed {
+                fire(self.nodes.remove(index).value);
+            } else {
+                // Cascade to a lower level.
+                self.link(index);
+            }
+        }
+    }
+
+    fn link(&mut self, index: usize) {
+        let when = self.nodes[index].when;
+        let level = level_for(self.elapsed, when);
+        let slot = ((when >> (level * LEVEL_BITS)) & SLOT_MASK) as usize;
+
+        let head = self.levels[level].heads[slot].replace(index);
+        self.levels[level].occupied |= 1 << slot;
+        if let Some(head) = head {
+            self.nodes[head].prev = Some(index);
+        }
+
+        let node = &mut self.nodes[index];
+        node.level = level;
+        node.slot = slot;
+        node.prev = None;
+        node.next = head;
+    }
+
+    fn unlink(&mut self, index: usize) {
+        let (level, slot, prev, next) = {
+            let node = &self.nodes[index];
+            (node.level, node.slot, node.prev, node.next)
+        };
+
+        match prev {
+            Some(prev) => self.nodes[prev].next = next,
+            None => {
+                let level = &mut self.levels[level];
+                level.heads[slot] = next;
+                if next.is_none() {
+                    level.occupied &= !(1 << slot);
+                }
+            }
+        }
+        if let Some(next) = next {
+            self.nodes[next].prev = prev;
+        }
+    }
+}
+
+impl Default for Level {
+    fn default() -> Level {
+        Level {
+            occupied: 0,
+            heads: [None; SLOTS],
+        }
+    }
+}
+
+impl Level {
+    /// Returns the next slot with entries, starting from the slot of `now`.
+    fn next(&self, level: usize, now: u64) -> Option<Expiration> {
+        if self.occupied == 0 {
+            return None;
+        }
+
+        let slot_range = 1u64 << (level * LEVEL_BITS);
+        let level_range = slot_range << LEVEL_BITS;
+
+        let now_slot = (now / slot_range) & SLOT_MASK;
+        let zeros = self.occupied.rotate_right(now_slot as u32).trailing_zeros();
+        let slot = (zeros as u64 + now_slot) & SLOT_MASK;
+
+        let level_start = now & !(level_range - 1);
+        let mut deadline = level_start + slot * slot_range;
+        if deadline < now {
+            // Only the top level wraps around, for deadlines clamped to the
+            // range of the wheel.
+            deadline += level_range;
+        }
+
+        Some(Expiration {
+            level,
+            slot: slot as usize,
+            deadline,
+        })
+    }
+}
+
+/// Returns the level storing an entry due at `when`.
+fn level_for(elapsed: u64, when: u64) -> usize {
+    let mut masked = (elapsed ^ when) | SLOT_MASK;
+    if masked >= MAX_TICKS {
+        masked = MAX_TICKS - 1;
+    }
+    let significant = 63 - masked.leading_zeros() as usize;
+    significant / LEVEL_BITS
+}
+
+#[test]
+fn test_fires_each_entry_at_its_tick() {
+    let mut wheel = Wheel::new();
+    let ticks = [1, 5, 63, 64, 65, 4095, 4096, 300_000, MAX_TICKS - 1];
+    for &when in &ticks {
+        wheel.insert(when, when).unwrap();
+    }
+    let cancelled = wheel.insert(1000, 1000).unwrap();
+    assert_eq!(wheel.remove(cancelled), Some(1000));
+    assert_eq!(wheel.remove(cancelled), None);
+
+    let mut fired = Vec::new();
+    let mut now = 0;
+    while !wheel.is_empty() {
+        now = wheel.next_expiration().unwrap();
+        wheel.advance(now, |when| {
+            assert_eq!(when, now);
+            fired.push(when);
+        });
+    }
+
+    assert_eq!(fired, ticks);
+    assert_eq!(wheel.elapsed(), MAX_TICKS - 1);
+    assert!(wheel.insert(now, 0).is_err());
+}
@@ -0,0 +1,375 @@
+//! Error classification helpers.
+//!
+//! Accept loops see a mix of errors: some only concern the connection that
+//! was being accepted, some mean the process ran out of resources and will
+//! clear up on their own, and some mean the listener itself is broken.
+//! [`AcceptErrorKind`] tells them apart so servers can keep running through
+//! the first two.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use futures::prelude::*;
+//! use futures_net::error::AcceptErrorKind;
+//! use futures_net::TcpListener;
+//!
+//! # async fn run() -> std::io::Result<()> {
+//! let addr = "127.0.0.1:8080".parse().unwrap();
+//! let mut listener = TcpListener::bind(&addr)?;
+//! listener.set_fd_reserve(true)?;
+//! let mut incoming = listener.incoming();
+//!
+//! while let Some(stream) = incoming.next().await {
+//!     match stream {
+//!         Ok(stream) => drop(stream),
+//!         Err(e) => match AcceptErrorKind::of(&e) {
+//!             AcceptErrorKind::Connection | AcceptErrorKind::Resources => continue,
+//!             AcceptErrorKind::Fatal => return Err(e),
+//!         },
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Errors only say what failed, not on which connection. Once turned on with
+//! [`set_context_enabled`], the errors returned by the connects, accepts,
+//! reads and writes of TCP and Unix streams and listeners carry a
+//! [`SocketError`] with the operation and the addresses of the socket.
+//!
+//! [`AcceptErrorKind`]: enum.AcceptErrorKind.html
+//! [`set_context_enabled`]: fn.set_context_enabled.html
+//! [`SocketError`]: struct.SocketError.html
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, Ordering::Relaxed};
+use std::task::Poll;
+
+use crate::transport::Addr;
+
+static CONTEXT: AtomicBool = AtomicBool::new(false);
+
+/// Classification of an error returned while accepting a connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AcceptErrorKind {
+    /// The pending connection failed before it could be accepted, e.g. the
+    /// peer reset it. Only that connection is affected.
+    Connection,
+
+    /// The process or the system is out of file descriptors or memory
+    /// (`EMFILE`, `ENFILE`, `ENOBUFS`, `ENOMEM`). Accepting again will fail
+    /// until resources are released.
+    Resources,
+
+    /// Any other error. The listener is probably unusable.
+    Fatal,
+}
+
+impl AcceptErrorKind {
+    /// Classifies an error returned by an accept call.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures_net::error::AcceptErrorKind;
+    /// use std::io;
+    ///
+    /// let err = io::Error::from_raw_os_error(libc::EMFILE);
+    /// assert_eq!(AcceptErrorKind::of(&err), AcceptErrorKind::Resources);
+    ///
+    /// let err = io::Error::from(io::ErrorKind::ConnectionAborted);
+    /// assert_eq!(AcceptErrorKind::of(&err), AcceptErrorKind::Connection);
+    /// ```
+    pub fn of(err: &io::Error) -> AcceptErrorKind {
+        if is_fd_exhaustion(err) {
+            return AcceptErrorKind::Resources;
+        }
+
+        match os_error(err) {
+            Some(libc::ENOBUFS) | Some(libc::ENOMEM) => {
+                return AcceptErrorKind::Resources
+            }
+            Some(libc::EPROTO)
+            | Some(libc::EPERM)
+            | Some(libc::ENETDOWN)
+            | Some(libc::ENOPROTOOPT)
+            | Some(libc::EHOSTDOWN)
+            | Some(libc::ENONET)
+            | Some(libc::EHOSTUNREACH)
+            | Some(libc::EOPNOTSUPP)
+            | Some(libc::ENETUNREACH) => return AcceptErrorKind::Connection,
+            _ => {}
+        }
+
+        match err.kind() {
+            io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::Interrupted
+            | io::ErrorKind::WouldBlock => AcceptErrorKind::Connection,
+            _ => AcceptErrorKind::Fatal,
+        }
+    }
+
+    /// Returns `true` if accepting can continue after this error.
+    pub fn is_recoverable(self) -> bool {
+        self != AcceptErrorKind::Fatal
+    }
+}
+
+/// Returns `true` if the error means the process (`EMFILE`) or the system
+/// (`ENFILE`) ran out of file descriptors.
+pub fn is_fd_exhaustion(err: &io::Error) -> bool {
+    match os_error(err) {
+        Some(libc::EMFILE) | Some(libc::ENFILE) => true,
+        _ => false,
+    }
+}
+
+/// Returns the OS error code of `err`, or of the error it adds context to.
+fn os_error(err: &io::Error) -> Option<i32> {
+    err.raw_os_error()
+        .or_else(|| SocketError::of(err).and_then(|ctx| ctx.raw_os_error()))
+}
+
+/// Turns the context of socket errors on or off, off by default.
+///
+/// With the context on, the errors of the sockets are `io::Error`s
+/// wrapping a [`SocketError`], which keep their kind but no longer return
+/// the OS error code from `raw_os_error`: it is read from the wrapped error,
+/// which [`SocketError::of`] returns. The classification of this module
+/// looks through the context.
+///
+/// # Examples
+///
+/// ```
+/// use futures_net::error::{self, Operation, SocketError};
+/// use futures_net::runtime::{self, Runtime};
+/// use futures_net::TcpStream;
+///
+/// error::set_context_enabled(true);
+///
+/// // Nothing listens on the port of a socket which was just closed.
+/// let addr = std::net::TcpListener::bind("127.0.0.1:0")?.local_addr()?;
+/// let mut rt = runtime::default();
+/// let err = rt.exec(TcpStream::connect(&addr)).unwrap_err();
+///
+/// assert_eq!(err.kind(), std::io::ErrorKind::ConnectionRefused);
+/// let ctx = SocketError::of(&err).unwrap();
+/// assert_eq!(ctx.operation(), Operation::Connect);
+/// assert_eq!(ctx.raw_os_error(), Some(libc::ECONNREFUSED));
+/// println!("{}", err);
+/// # Ok::<_, std::io::Error>(())
+/// ```
+///
+/// [`SocketError`]: struct.SocketError.html
+/// [`SocketError::of`]: struct.SocketError.html#method.of
+pub fn set_context_enabled(enabled: bool) {
+    CONTEXT.store(enabled, Relaxed);
+}
+
+/// Returns `true` if socket errors carry their context.
+pub fn is_context_enabled() -> bool {
+    CONTEXT.load(Relaxed)
+}
+
+/// The operation which failed, see [`SocketError`].
+///
+/// [`SocketError`]: struct.SocketError.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Operation {
+    /// Connecting a stream.
+    Connect,
+    /// Accepting a connection.
+    Accept,
+    /// Reading from a stream.
+    Read,
+    /// Writing to a stream.
+    Write,
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Operation::Connect => "connect",
+            Operation::Accept => "accept",
+            Operation::Read => "read",
+            Operation::Write => "write",
+        })
+    }
+}
+
+/// An I/O error of a socket, with the operation which failed and the
+/// addresses of the socket.
+///
+/// It dereferences to the underlying `io::Error`, and converts into an
+/// `io::Error` of the same kind which shows the context when printed, such
+/// as `read on 127.0.0.1:8080 from 127.0.0.1:51234: Connection reset by
+/// peer (os error 104)`.
+#[derive(Debug)]
+pub struct SocketError {
+    operation: Operation,
+    local: Option<Addr>,
+    peer: Option<Addr>,
+    source: io::Error,
+}
+
+impl SocketError {
+    /// Creates an error of `operation`, failed with `source`, on a socket
+    /// with the given addresses.
+    pub fn new(
+        operation: Operation,
+        local: Option<Addr>,
+        peer: Option<Addr>,
+        source: io::Error,
+    ) -> SocketError {
+        SocketError {
+            operation,
+            local,
+            peer,
+            source,
+        }
+    }
+
+    /// Returns the context `err` carries, if it is a converted `SocketError`.
+    pub fn of(err: &io::Error) -> Option<&SocketError> {
+        err.get_ref()?.downcast_ref()
+    }
+
+    /// Returns the operation which failed.
+    pub fn operation(&self) -> Operation {
+        self.operation
+    }
+
+    /// Returns the address of the local end of the socket, if known.
+    pub fn local_addr(&self) -> Option<&Addr> {
+        self.local.as_ref()
+    }
+
+    /// Returns the address of the peer of the socket, if known.
+    ///
+    /// Unknown for listeners, and usually for streams which were not
+    /// connected or accepted by the crate once the connection is gone.
+    pub fn peer_addr(&self) -> Option<&Addr> {
+        self.peer.as_ref()
+    }
+
+    /// Returns the underlying error.
+    pub fn into_inner(self) -> io::Error {
+        self.source
+    }
+}
+
+impl Deref for SocketError {
+    type Target = io::Error;
+
+    fn deref(&self) -> &io::Error {
+        &self.source
+    }
+}
+
+impl fmt::Display for SocketError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.operation)?;
+        if let Some(local) = &self.local {
+            write!(f, " on {}", DisplayAddr(local))?;
+        }
+        if let Some(peer) = &self.peer {
+            let dir = match self.operation {
+                Operation::Connect | Operation::Write => "to",
+                Operation::Accept | Operation::Read => "from",
+            };
+            write!(f, " {} {}", dir, DisplayAddr(peer))?;
+        }
+        write!(f, ": {}", self.source)
+    }
+}
+
+impl Error for SocketError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+impl From<SocketError> for io::Error {
+    fn from(err: SocketError) -> io::Error {
+        io::Error::new(err.source.kind(), err)
+    }
+}
+
+struct DisplayAddr<'a>(&'a Addr);
+
+impl fmt::Display for DisplayAddr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Addr::Tcp(addr) => write!(f, "{}", addr),
+            Addr::Unix(addr) => match addr.as_pathname() {
+                Some(path) => write!(f, "{}", path.display()),
+                None => f.write_str("(unnamed)"),
+            },
+        }
+    }
+}
+
+/// Adds the context of a socket to the error of `poll`, if context is on.
+///
+/// The addresses are only looked up for errors.
+pub(crate) fn annotate<T>(
+    poll: Poll<io::Result<T>>,
+    operation: Operation,
+    addrs: impl FnOnce() -> (Option<Addr>, Option<Addr>),
+) -> Poll<io::Result<T>> {
+    match poll {
+        Poll::Ready(Err(e)) => Poll::Ready(Err(annotate_err(e, operation, addrs))),
+        poll => poll,
+    }
+}
+
+/// Adds the context of a socket to `err`, if context is on.
+pub(crate) fn annotate_err(
+    err: io::Error,
+    operation: Operation,
+    addrs: impl FnOnce() -> (Option<Addr>, Option<Addr>),
+) -> io::Error {
+    if !is_context_enabled() {
+        return err;
+    }
+    with_context(err, operation, addrs)
+}
+
+/// Adds the context of a socket to `err`, unless it would block or already
+/// has one.
+fn with_context(
+    err: io::Error,
+    operation: Operation,
+    addrs: impl FnOnce() -> (Option<Addr>, Option<Addr>),
+) -> io::Error {
+    if err.kind() == io::ErrorKind::WouldBlock || SocketError::of(&err).is_some() {
+        return err;
+    }
+    let (local, peer) = addrs();
+    SocketError::new(operation, local, peer, err).into()
+}
+
+#[test]
+fn test_context_is_kept_once() {
+    let local = Some(Addr::Tcp("127.0.0.1:8080".parse().unwrap()));
+    let peer = Some(Addr::Tcp("127.0.0.1:51234".parse().unwrap()));
+    let reset = io::Error::from_raw_os_error(libc::ECONNRESET);
+    let err = with_context(reset, Operation::Read, || (local, peer));
+
+    assert_eq!(err.kind(), io::ErrorKind::ConnectionReset);
+    assert!(err
+        .to_string()
+        .starts_with("read on 127.0.0.1:8080 from 127.0.0.1:51234: "));
+    let err = with_context(err, Operation::Write, || panic!("annotated twice"));
+    assert_eq!(SocketError::of(&err).unwrap().operation(), Operation::Read);
+
+    // Classification looks through the context.
+    let emfile = io::Error::from_raw_os_error(libc::EMFILE);
+    let err = with_context(emfile, Operation::Accept, || (None, None));
+    assert!(err.raw_os_error().is_none());
+    assert_eq!(AcceptErrorKind::of(&err), AcceptErrorKind::Resources);
+}
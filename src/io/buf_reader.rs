@@ -0,0 +1,328 @@
+use async_ready::{AsyncReadReady, AsyncWriteReady};
+use futures_io::{AsyncBufRead, AsyncRead, AsyncWrite};
+use futures_util::ready;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::{cmp, fmt, io};
+
+use super::DEFAULT_BUF_SIZE;
+use crate::driver::sys::event::Ready;
+
+/// Adds buffering to the reading half of an I/O object.
+///
+/// Each read from the object fills as much of the buffer as the socket has
+/// available, and the buffer is only refilled once it was consumed, so a
+/// read never waits for the socket while buffered bytes remain. Reads
+/// larger than the buffer skip it when it is empty.
+///
+/// `poll_read_ready` reports the reader readable while the buffer holds
+/// bytes, and only defers to the object once it is empty.
+///
+/// # Examples
+///
+/// ```
+/// use futures::prelude::*;
+/// use futures_net::io::BufReader;
+/// use futures_net::runtime::{self, Runtime};
+/// use futures_net::uds::UnixStream;
+///
+/// let mut rt = runtime::default();
+/// rt.exec(async {
+///     let (a, mut b) = UnixStream::pair()?;
+///     b.write_all(b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n").await?;
+///
+///     let mut reader = BufReader::new(a);
+///     let mut line = String::new();
+///     reader.read_line(&mut line).await?;
+///     assert_eq!(line, "GET / HTTP/1.1\r\n");
+///     std::io::Result::Ok(())
+/// })
+/// .unwrap();
+/// ```
+pub struct BufReader<R> {
+    inner: R,
+    buf: ReadBuffer,
+}
+
+/// The buffer of a `BufReader`, also used by the streams which buffer
+/// reads themselves.
+#[derive(Debug)]
+pub(crate) struct ReadBuffer {
+    buf: Box<[u8]>,
+    pos: usize,
+    cap: usize,
+}
+
+impl ReadBuffer {
+    pub(crate) fn new(capacity: usize) -> ReadBuffer {
+        ReadBuffer {
+            buf: vec![0; capacity].into_boxed_slice(),
+            pos: 0,
+            cap: 0,
+        }
+    }
+
+    pub(crate) fn capacity(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Returns the bytes read but not consumed yet.
+    pub(crate) fn buffer(&self) -> &[u8] {
+        &self.buf[self.pos..self.cap]
+    }
+
+    pub(crate) fn consume(&mut self, amt: usize) {
+        self.pos = cmp::min(self.pos + amt, self.cap);
+    }
+
+    /// Refills the buffer with `read` if it was consumed.
+    pub(crate) fn poll_fill<F>(&mut self, read: F) -> Poll<io::Result<&[u8]>>
+    where
+        F: FnOnce(&mut [u8]) -> Poll<io::Result<usize>>,
+    {
+        if self.pos >= self.cap {
+            self.cap = ready!(read(&mut self.buf))?;
+            self.pos = 0;
+        }
+        Poll::Ready(Ok(self.buffer()))
+    }
+
+    /// Reads into `buf` from the buffer, refilling it with `read` if it was
+    /// consumed. Reads larger than the buffer skip it when it is empty.
+    pub(crate) fn poll_read<F>(
+        &mut self,
+        buf: &mut [u8],
+        read: F,
+    ) -> Poll<io::Result<usize>>
+    where
+        F: FnOnce(&mut [u8]) -> Poll<io::Result<usize>>,
+    {
+        if self.pos == self.cap && buf.len() >= self.buf.len() {
+            let res = ready!(read(buf));
+            self.pos = 0;
+            self.cap = 0;
+            return Poll::Ready(res);
+        }
+        let available = ready!(self.poll_fill(read))?;
+        let n = cmp::min(available.len(), buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Poll::Ready(Ok(n))
+    }
+}
+
+impl<R> BufReader<R> {
+    /// Creates a reader with an 8 KiB buffer.
+    pub fn new(inner: R) -> BufReader<R> {
+        BufReader::with_capacity(DEFAULT_BUF_SIZE, inner)
+    }
+
+    /// Creates a reader with a buffer of `capacity` bytes.
+    pub fn with_capacity(capacity: usize, inner: R) -> BufReader<R> {
+        BufReader {
+            inner,
+            buf: ReadBuffer::new(capacity),
+        }
+    }
+
+    /// Returns a reference to the I/O object.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the I/O object.
+    ///
+    /// Reading from it directly would skip the buffered bytes.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Consumes the reader, returning the I/O object. Buffered bytes are
+    /// lost.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Returns the bytes read but not consumed yet.
+    pub fn buffer(&self) -> &[u8] {
+        self.buf.buffer()
+    }
+
+    /// Returns the size of the buffer.
+    pub fn capacity(&self) -> usize {
+        self.buf.capacity()
+    }
+
+    /// Reads bytes into `buf` until `byte` or the end of the stream,
+    /// returning how many were read. The delimiter is included.
+    ///
+    /// Bytes read before the future is dropped are consumed from the
+    /// reader and appended to `buf`.
+    pub async fn read_until(&mut self, byte: u8, buf: &mut Vec<u8>) -> io::Result<usize>
+    where
+        R: AsyncRead + Unpin,
+    {
+        super::read_until(self, byte, buf).await
+    }
+
+    /// Reads a line into `buf`, including its `\n`, returning how many bytes
+    /// were read. Returns `0` at the end of the stream.
+    ///
+    /// If the line is not valid UTF-8 an `InvalidData` error is returned and
+    /// `buf` is left unchanged. Bytes read before the future is dropped are
+    /// lost.
+    pub async fn read_line(&mut self, buf: &mut String) -> io::Result<usize>
+    where
+        R: AsyncRead + Unpin,
+    {
+        super::read_line(self, buf).await
+    }
+}
+
+// The fields are never pinned.
+impl<R> Unpin for BufReader<R> {}
+
+impl<R: AsyncRead + Unpin> AsyncRead for BufReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let inner = &mut this.inner;
+        this.buf
+            .poll_read(buf, |buf| Pin::new(inner).poll_read(cx, buf))
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncBufRead for BufReader<R> {
+    fn poll_fill_buf(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<&[u8]>> {
+        let this = self.get_mut();
+        let inner = &mut this.inner;
+        this.buf.poll_fill(|buf| Pin::new(inner).poll_read(cx, buf))
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        self.get_mut().buf.consume(amt);
+    }
+}
+
+impl<R: AsyncWrite + Unpin> AsyncWrite for BufReader<R> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).poll_write_vectored(cx, bufs)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_close(cx)
+    }
+}
+
+impl<R> AsyncReadReady for BufReader<R>
+where
+    R: AsyncReadReady<Ok = Ready> + Unpin,
+{
+    type Ok = Ready;
+    type Err = R::Err;
+
+    /// Reports the reader readable while bytes are buffered, and polls the
+    /// readiness of the I/O object otherwise.
+    fn poll_read_ready(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Ready, R::Err>> {
+        let this = self.get_mut();
+        if !this.buf.buffer().is_empty() {
+            Poll::Ready(Ok(Ready::readable()))
+        } else {
+            Pin::new(&mut this.inner).poll_read_ready(cx)
+        }
+    }
+}
+
+impl<R: AsyncWriteReady + Unpin> AsyncWriteReady for BufReader<R> {
+    type Ok = R::Ok;
+    type Err = R::Err;
+
+    fn poll_write_ready(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<R::Ok, R::Err>> {
+        Pin::new(&mut self.get_mut().inner).poll_write_ready(cx)
+    }
+}
+
+impl<R: fmt::Debug> fmt::Debug for BufReader<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BufReader")
+            .field("inner", &self.inner)
+            .field("buffered", &self.buf.buffer().len())
+            .field("capacity", &self.buf.capacity())
+            .finish()
+    }
+}
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_buffered_bytes_stay_ready() {
+    use crate::runtime::{self, Runtime};
+    use crate::uds::UnixStream;
+    use futures_util::io::{AsyncReadExt, AsyncWriteExt};
+
+    let mut rt = runtime::default();
+    rt.exec(async {
+        let (a, mut b) = UnixStream::pair().unwrap();
+        let mut reader = BufReader::with_capacity(16, a);
+
+        // Both lines arrive in one read, the second must not wait on the
+        // socket, which has nothing left to report.
+        b.write_all(b"one\ntwo\nthr").await.unwrap();
+        let mut line = String::new();
+        reader.read_line(&mut line).await.unwrap();
+        assert_eq!(line, "one\n");
+        assert_eq!(reader.buffer(), b"two\nthr");
+
+        let waker = futures_util::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let ready = Pin::new(&mut reader).poll_read_ready(&mut cx);
+        assert!(matches!(ready, Poll::Ready(Ok(r)) if r.is_readable()));
+
+        let mut buf = Vec::new();
+        reader.read_until(b'\n', &mut buf).await.unwrap();
+        assert_eq!(buf, b"two\n");
+
+        // A line split across reads.
+        b.write_all(b"ee\n").await.unwrap();
+        buf.clear();
+        reader.read_until(b'\n', &mut buf).await.unwrap();
+        assert_eq!(buf, b"three\n");
+
+        // Reads larger than the buffer bypass it.
+        b.write_all(&[7; 32]).await.unwrap();
+        drop(b);
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest).await.unwrap();
+        assert_eq!(rest, [7; 32]);
+
+        line.clear();
+        assert_eq!(reader.read_line(&mut line).await.unwrap(), 0);
+    });
+}
@@ -0,0 +1,208 @@
+use async_ready::{AsyncReadReady, AsyncWriteReady};
+use futures_io::{AsyncBufRead, AsyncRead, AsyncWrite};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::{fmt, io};
+
+use super::{BufReader, BufWriter, DEFAULT_BUF_SIZE};
+use crate::driver::sys::event::Ready;
+
+/// Adds buffering to both halves of an I/O object, like a [`BufReader`]
+/// over a [`BufWriter`].
+///
+/// # Examples
+///
+/// ```
+/// use futures::prelude::*;
+/// use futures_net::io::BufStream;
+/// use futures_net::runtime::{self, Runtime};
+/// use futures_net::uds::UnixStream;
+///
+/// let mut rt = runtime::default();
+/// rt.exec(async {
+///     let (a, b) = UnixStream::pair()?;
+///     let mut a = BufStream::new(a);
+///     let mut b = BufStream::new(b);
+///
+///     a.write_all(b"PING\r\n").await?;
+///     a.flush().await?;
+///
+///     let mut line = String::new();
+///     b.read_line(&mut line).await?;
+///     assert_eq!(line, "PING\r\n");
+///     std::io::Result::Ok(())
+/// })
+/// .unwrap();
+/// ```
+///
+/// [`BufReader`]: struct.BufReader.html
+/// [`BufWriter`]: struct.BufWriter.html
+pub struct BufStream<S> {
+    inner: BufReader<BufWriter<S>>,
+}
+
+impl<S> BufStream<S> {
+    /// Creates a stream with 8 KiB buffers.
+    pub fn new(inner: S) -> BufStream<S> {
+        BufStream::with_capacity(DEFAULT_BUF_SIZE, DEFAULT_BUF_SIZE, inner)
+    }
+
+    /// Creates a stream with buffers of `reader_capacity` and
+    /// `writer_capacity` bytes.
+    pub fn with_capacity(
+        reader_capacity: usize,
+        writer_capacity: usize,
+        inner: S,
+    ) -> BufStream<S> {
+        BufStream {
+            inner: BufReader::with_capacity(
+                reader_capacity,
+                BufWriter::with_capacity(writer_capacity, inner),
+            ),
+        }
+    }
+
+    /// Returns a reference to the I/O object.
+    pub fn get_ref(&self) -> &S {
+        self.inner.get_ref().get_ref()
+    }
+
+    /// Returns a mutable reference to the I/O object.
+    ///
+    /// Using it directly would bypass the buffered bytes.
+    pub fn get_mut(&mut self) -> &mut S {
+        self.inner.get_mut().get_mut()
+    }
+
+    /// Consumes the stream, returning the I/O object. Buffered bytes are
+    /// lost.
+    pub fn into_inner(self) -> S {
+        self.inner.into_inner().into_inner()
+    }
+
+    /// Returns the bytes read but not consumed yet.
+    pub fn read_buffer(&self) -> &[u8] {
+        self.inner.buffer()
+    }
+
+    /// Returns the bytes written but not flushed yet.
+    pub fn write_buffer(&self) -> &[u8] {
+        self.inner.get_ref().buffer()
+    }
+
+    /// Reads bytes into `buf` until `byte` or the end of the stream, like
+    /// [`BufReader::read_until`].
+    ///
+    /// [`BufReader::read_until`]: struct.BufReader.html#method.read_until
+    pub async fn read_until(&mut self, byte: u8, buf: &mut Vec<u8>) -> io::Result<usize>
+    where
+        S: AsyncRead + Unpin,
+    {
+        self.inner.read_until(byte, buf).await
+    }
+
+    /// Reads a line into `buf`, like [`BufReader::read_line`].
+    ///
+    /// [`BufReader::read_line`]: struct.BufReader.html#method.read_line
+    pub async fn read_line(&mut self, buf: &mut String) -> io::Result<usize>
+    where
+        S: AsyncRead + Unpin,
+    {
+        self.inner.read_line(buf).await
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for BufStream<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncBufRead for BufStream<S> {
+    fn poll_fill_buf(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<&[u8]>> {
+        Pin::new(&mut self.get_mut().inner).poll_fill_buf(cx)
+    }
+
+    fn consume(mut self: Pin<&mut Self>, amt: usize) {
+        Pin::new(&mut self.inner).consume(amt)
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for BufStream<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write_vectored(cx, bufs)
+    }
+
+    fn poll_flush(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_close(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_close(cx)
+    }
+}
+
+impl<S> AsyncReadReady for BufStream<S>
+where
+    S: AsyncReadReady<Ok = Ready> + Unpin,
+{
+    type Ok = Ready;
+    type Err = S::Err;
+
+    fn poll_read_ready(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Ready, S::Err>> {
+        Pin::new(&mut self.inner).poll_read_ready(cx)
+    }
+}
+
+impl<S> AsyncWriteReady for BufStream<S>
+where
+    S: AsyncWriteReady<Ok = Ready> + Unpin,
+{
+    type Ok = Ready;
+    type Err = S::Err;
+
+    fn poll_write_ready(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Ready, S::Err>> {
+        Pin::new(&mut self.inner).poll_write_ready(cx)
+    }
+}
+
+impl<S: fmt::Debug> fmt::Debug for BufStream<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BufStream")
+            .field("inner", self.get_ref())
+            .field("read_buffered", &self.read_buffer().len())
+            .field("write_buffered", &self.write_buffer().len())
+            .finish()
+    }
+}
@@ -0,0 +1,241 @@
+use async_ready::{AsyncReadReady, AsyncWriteReady};
+use futures_io::{AsyncBufRead, AsyncRead, AsyncWrite};
+use futures_util::ready;
+use std::io::IoSlice;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::{fmt, io};
+
+use super::DEFAULT_BUF_SIZE;
+use crate::driver::sys::event::Ready;
+
+/// Adds buffering to the writing half of an I/O object.
+///
+/// Small writes are collected in the buffer and written out when it fills
+/// up, or on `flush` or `close`. Writes at least as large as the buffer go
+/// to the object directly once the buffer was written out. Bytes still
+/// buffered when the writer is dropped are lost, so flush it first.
+///
+/// `poll_write_ready` reports the writer writable while the buffer has
+/// room, and only defers to the object once it is full.
+///
+/// # Examples
+///
+/// ```
+/// use futures::prelude::*;
+/// use futures_net::io::BufWriter;
+/// use futures_net::runtime::{self, Runtime};
+/// use futures_net::uds::UnixStream;
+///
+/// let mut rt = runtime::default();
+/// rt.exec(async {
+///     let (a, mut b) = UnixStream::pair()?;
+///
+///     let mut writer = BufWriter::new(a);
+///     for i in 0..3 {
+///         writer.write_all(format!("{}\n", i).as_bytes()).await?;
+///     }
+///     assert_eq!(writer.buffer(), b"0\n1\n2\n");
+///     writer.flush().await?;
+///
+///     let mut buf = [0; 6];
+///     b.read_exact(&mut buf).await?;
+///     assert_eq!(&buf, b"0\n1\n2\n");
+///     std::io::Result::Ok(())
+/// })
+/// .unwrap();
+/// ```
+pub struct BufWriter<W> {
+    inner: W,
+    buf: Vec<u8>,
+    /// Bytes at the front of `buf` which were already written.
+    written: usize,
+}
+
+impl<W> BufWriter<W> {
+    /// Creates a writer with an 8 KiB buffer.
+    pub fn new(inner: W) -> BufWriter<W> {
+        BufWriter::with_capacity(DEFAULT_BUF_SIZE, inner)
+    }
+
+    /// Creates a writer with a buffer of `capacity` bytes.
+    pub fn with_capacity(capacity: usize, inner: W) -> BufWriter<W> {
+        BufWriter {
+            inner,
+            buf: Vec::with_capacity(capacity),
+            written: 0,
+        }
+    }
+
+    /// Returns a reference to the I/O object.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the I/O object.
+    ///
+    /// Writing to it directly would jump ahead of the buffered bytes.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Consumes the writer, returning the I/O object. Buffered bytes are
+    /// lost.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    /// Returns the bytes written to the buffer but not to the object yet.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buf[self.written..]
+    }
+
+    /// Returns the size of the buffer.
+    pub fn capacity(&self) -> usize {
+        self.buf.capacity()
+    }
+
+    fn poll_flush_buf(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        while self.written < self.buf.len() {
+            let res =
+                Pin::new(&mut self.inner).poll_write(cx, &self.buf[self.written..]);
+            match ready!(res) {
+                Ok(0) => {
+                    return Poll::Ready(Err(io::Error::new(
+                        io::ErrorKind::WriteZero,
+                        "failed to write the buffered data",
+                    )))
+                }
+                Ok(n) => self.written += n,
+                Err(e) => return Poll::Ready(Err(e)),
+            }
+        }
+        self.buf.clear();
+        self.written = 0;
+        Poll::Ready(Ok(()))
+    }
+}
+
+// The fields are never pinned.
+impl<W> Unpin for BufWriter<W> {}
+
+impl<W: AsyncWrite + Unpin> AsyncWrite for BufWriter<W> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if this.buf.len() + buf.len() > this.buf.capacity() {
+            ready!(this.poll_flush_buf(cx))?;
+        }
+        if buf.len() >= this.buf.capacity() {
+            Pin::new(&mut this.inner).poll_write(cx, buf)
+        } else {
+            this.buf.extend_from_slice(buf);
+            Poll::Ready(Ok(buf.len()))
+        }
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let len: usize = bufs.iter().map(|buf| buf.len()).sum();
+        if this.buf.len() + len > this.buf.capacity() {
+            ready!(this.poll_flush_buf(cx))?;
+        }
+        if len >= this.buf.capacity() {
+            Pin::new(&mut this.inner).poll_write_vectored(cx, bufs)
+        } else {
+            for buf in bufs {
+                this.buf.extend_from_slice(buf);
+            }
+            Poll::Ready(Ok(len))
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_flush_buf(cx))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_flush_buf(cx))?;
+        Pin::new(&mut this.inner).poll_close(cx)
+    }
+}
+
+impl<W: AsyncRead + Unpin> AsyncRead for BufWriter<W> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).poll_read(cx, buf)
+    }
+}
+
+impl<W: AsyncBufRead + Unpin> AsyncBufRead for BufWriter<W> {
+    fn poll_fill_buf(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<&[u8]>> {
+        Pin::new(&mut self.get_mut().inner).poll_fill_buf(cx)
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        Pin::new(&mut self.get_mut().inner).consume(amt)
+    }
+}
+
+impl<W: AsyncReadReady + Unpin> AsyncReadReady for BufWriter<W> {
+    type Ok = W::Ok;
+    type Err = W::Err;
+
+    fn poll_read_ready(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<W::Ok, W::Err>> {
+        Pin::new(&mut self.get_mut().inner).poll_read_ready(cx)
+    }
+}
+
+impl<W> AsyncWriteReady for BufWriter<W>
+where
+    W: AsyncWriteReady<Ok = Ready> + Unpin,
+{
+    type Ok = Ready;
+    type Err = W::Err;
+
+    /// Reports the writer writable while the buffer has room, and polls the
+    /// readiness of the I/O object otherwise.
+    fn poll_write_ready(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Ready, W::Err>> {
+        let this = self.get_mut();
+        if this.buf.len() < this.buf.capacity() {
+            Poll::Ready(Ok(Ready::writable()))
+        } else {
+            Pin::new(&mut this.inner).poll_write_ready(cx)
+        }
+    }
+}
+
+impl<W: fmt::Debug> fmt::Debug for BufWriter<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BufWriter")
+            .field("inner", &self.inner)
+            .field("buffered", &(self.buf.len() - self.written))
+            .field("capacity", &self.buf.capacity())
+            .finish()
+    }
+}
@@ -0,0 +1,426 @@
+use futures_io::{AsyncRead, AsyncWrite};
+use futures_util::ready;
+use std::io::{self, Write};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{cmp, fmt};
+
+/// `LINKTYPE_RAW`: packets start with their IPv4 or IPv6 header.
+const LINKTYPE_RAW: u32 = 101;
+const SNAPLEN: u32 = 65535;
+/// Leaves room for the headers in the 16-bit lengths of IP.
+const MAX_SEGMENT: usize = 65000;
+
+const FIN: u8 = 0x01;
+const SYN: u8 = 0x02;
+const PSH: u8 = 0x08;
+const ACK: u8 = 0x10;
+
+/// Records the bytes going through an I/O object into a pcap file.
+///
+/// The traffic is written as a synthetic TCP connection between two
+/// addresses: the capture starts with a handshake, each chunk written
+/// becomes a segment from the local address, each chunk read a segment
+/// from the peer, and closing either side ends with a `FIN`. Tools like
+/// Wireshark can then follow and decode the stream as if it had been
+/// captured on the wire, e.g. for streams which are encrypted on the wire
+/// or aren't TCP at all.
+///
+/// Records are written synchronously to `sink`, so it should be buffered,
+/// e.g. a `std::io::BufWriter<File>`. If writing to it fails, capturing
+/// stops and the error is kept for [`take_capture_error`], while the
+/// traffic itself goes on.
+///
+/// # Examples
+///
+/// ```no_run
+/// use futures::prelude::*;
+/// use futures_net::io::Capture;
+/// use futures_net::TcpStream;
+/// use std::fs::File;
+///
+/// # async fn run() -> std::io::Result<()> {
+/// let addr = "127.0.0.1:8080".parse().unwrap();
+/// let stream = TcpStream::connect(&addr).await?;
+/// let (local, peer) = (stream.local_addr()?, stream.peer_addr()?);
+///
+/// let sink = std::io::BufWriter::new(File::create("conn.pcap")?);
+/// let mut stream = Capture::with_addrs(stream, sink, local, peer)?;
+/// stream.write_all(b"GET / HTTP/1.0\r\n\r\n").await?;
+/// stream.close().await?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`take_capture_error`]: #method.take_capture_error
+pub struct Capture<T, W: Write> {
+    inner: T,
+    pcap: Option<Pcap<W>>,
+    error: Option<io::Error>,
+}
+
+struct Pcap<W> {
+    sink: W,
+    local: SocketAddr,
+    peer: SocketAddr,
+    local_seq: u32,
+    peer_seq: u32,
+    local_fin: bool,
+    peer_fin: bool,
+}
+
+#[derive(Clone, Copy)]
+enum Direction {
+    /// From the local address to the peer, i.e. written.
+    Outbound,
+    /// From the peer to the local address, i.e. read.
+    Inbound,
+}
+
+impl<T, W: Write> Capture<T, W> {
+    /// Records the traffic of `inner` into `sink`, as a connection from
+    /// `10.0.0.1:49152` to `10.0.0.2:80`.
+    ///
+    /// Fails if the header of the capture can't be written.
+    pub fn new(inner: T, sink: W) -> io::Result<Capture<T, W>> {
+        let local = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), 49152);
+        let peer = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)), 80);
+        Capture::with_addrs(inner, sink, local, peer)
+    }
+
+    /// Records the traffic of `inner` into `sink`, as a connection from
+    /// `local` to `peer`.
+    ///
+    /// Fails if the header of the capture can't be written, or with an
+    /// `InvalidInput` error if the addresses are of different families.
+    pub fn with_addrs(
+        inner: T,
+        sink: W,
+        local: SocketAddr,
+        peer: SocketAddr,
+    ) -> io::Result<Capture<T, W>> {
+        if local.is_ipv4() != peer.is_ipv4() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "addresses of different families",
+            ));
+        }
+        let mut pcap = Pcap {
+            sink,
+            local,
+            peer,
+            local_seq: 0,
+            peer_seq: 0,
+            local_fin: false,
+            peer_fin: false,
+        };
+        pcap.start()?;
+        Ok(Capture {
+            inner,
+            pcap: Some(pcap),
+            error: None,
+        })
+    }
+
+    /// Returns a reference to the I/O object.
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the I/O object.
+    ///
+    /// Bytes going through it directly are not recorded.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Consumes the wrapper, returning the I/O object and the sink, unless
+    /// capturing stopped on an error.
+    pub fn into_parts(self) -> (T, Option<W>) {
+        (self.inner, self.pcap.map(|pcap| pcap.sink))
+    }
+
+    /// Returns `true` while the traffic is being recorded.
+    pub fn is_capturing(&self) -> bool {
+        self.pcap.is_some()
+    }
+
+    /// Returns the error which stopped capturing, if any.
+    pub fn take_capture_error(&mut self) -> Option<io::Error> {
+        self.error.take()
+    }
+
+    fn record<F>(&mut self, f: F)
+    where
+        F: FnOnce(&mut Pcap<W>) -> io::Result<()>,
+    {
+        if let Some(pcap) = &mut self.pcap {
+            if let Err(e) = f(pcap) {
+                self.pcap = None;
+                self.error = Some(e);
+            }
+        }
+    }
+}
+
+impl<W: Write> Pcap<W> {
+    fn start(&mut self) -> io::Result<()> {
+        let mut header = Vec::with_capacity(24);
+        header.extend_from_slice(&0xa1b2_c3d4u32.to_le_bytes());
+        header.extend_from_slice(&2u16.to_le_bytes());
+        header.extend_from_slice(&4u16.to_le_bytes());
+        header.extend_from_slice(&0i32.to_le_bytes());
+        header.extend_from_slice(&0u32.to_le_bytes());
+        header.extend_from_slice(&SNAPLEN.to_le_bytes());
+        header.extend_from_slice(&LINKTYPE_RAW.to_le_bytes());
+        self.sink.write_all(&header)?;
+
+        self.segment(Direction::Outbound, SYN, &[])?;
+        self.segment(Direction::Inbound, SYN | ACK, &[])?;
+        self.segment(Direction::Outbound, ACK, &[])
+    }
+
+    fn data(&mut self, dir: Direction, mut data: &[u8]) -> io::Result<()> {
+        while !data.is_empty() {
+            let n = cmp::min(data.len(), MAX_SEGMENT);
+            self.segment(dir, PSH | ACK, &data[..n])?;
+            data = &data[n..];
+        }
+        Ok(())
+    }
+
+    fn fin(&mut self, dir: Direction) -> io::Result<()> {
+        let sent = match dir {
+            Direction::Outbound => &mut self.local_fin,
+            Direction::Inbound => &mut self.peer_fin,
+        };
+        if *sent {
+            return Ok(());
+        }
+        *sent = true;
+        self.segment(dir, FIN | ACK, &[])?;
+        self.sink.flush()
+    }
+
+    fn segment(&mut self, dir: Direction, flags: u8, payload: &[u8]) -> io::Result<()> {
+        let (src, dst, seq, ack) = match dir {
+            Direction::Outbound => {
+                (self.local, self.peer, self.local_seq, self.peer_seq)
+            }
+            Direction::Inbound => (self.peer, self.local, self.peer_seq, self.local_seq),
+        };
+        // The flags which take a sequence number.
+        let len = payload.len() as u32 + u32::from(flags & (SYN | FIN) != 0);
+        match dir {
+            Direction::Outbound => self.local_seq = seq.wrapping_add(len),
+            Direction::Inbound => self.peer_seq = seq.wrapping_add(len),
+        }
+        let ack = if flags & ACK != 0 { ack } else { 0 };
+
+        let mut tcp = Vec::with_capacity(20 + payload.len());
+        tcp.extend_from_slice(&src.port().to_be_bytes());
+        tcp.extend_from_slice(&dst.port().to_be_bytes());
+        tcp.extend_from_slice(&seq.to_be_bytes());
+        tcp.extend_from_slice(&ack.to_be_bytes());
+        tcp.extend_from_slice(&[5 << 4, flags]);
+        tcp.extend_from_slice(&0xffffu16.to_be_bytes());
+        tcp.extend_from_slice(&[0; 4]);
+        tcp.extend_from_slice(payload);
+
+        let packet = match (src.ip(), dst.ip()) {
+            (IpAddr::V4(src), IpAddr::V4(dst)) => {
+                let mut pseudo = Vec::with_capacity(12);
+                pseudo.extend_from_slice(&src.octets());
+                pseudo.extend_from_slice(&dst.octets());
+                pseudo.extend_from_slice(&[0, 6]);
+                pseudo.extend_from_slice(&(tcp.len() as u16).to_be_bytes());
+                let sum = checksum(&[&pseudo, &tcp]);
+                tcp[16..18].copy_from_slice(&sum.to_be_bytes());
+
+                let mut ip = Vec::with_capacity(20 + tcp.len());
+                ip.extend_from_slice(&[0x45, 0]);
+                ip.extend_from_slice(&((20 + tcp.len()) as u16).to_be_bytes());
+                ip.extend_from_slice(&[0, 0, 0x40, 0, 64, 6, 0, 0]);
+                ip.extend_from_slice(&src.octets());
+                ip.extend_from_slice(&dst.octets());
+                let sum = checksum(&[&ip]);
+                ip[10..12].copy_from_slice(&sum.to_be_bytes());
+                ip.extend_from_slice(&tcp);
+                ip
+            }
+            (IpAddr::V6(src), IpAddr::V6(dst)) => {
+                let mut pseudo = Vec::with_capacity(40);
+                pseudo.extend_from_slice(&src.octets());
+                pseudo.extend_from_slice(&dst.octets());
+                pseudo.extend_from_slice(&(tcp.len() as u32).to_be_bytes());
+                pseudo.extend_from_slice(&[0, 0, 0, 6]);
+                let sum = checksum(&[&pseudo, &tcp]);
+                tcp[16..18].copy_from_slice(&sum.to_be_bytes());
+
+                let mut ip = Vec::with_capacity(40 + tcp.len());
+                ip.extend_from_slice(&[0x60, 0, 0, 0]);
+                ip.extend_from_slice(&(tcp.len() as u16).to_be_bytes());
+                ip.extend_from_slice(&[6, 64]);
+                ip.extend_from_slice(&src.octets());
+                ip.extend_from_slice(&dst.octets());
+                ip.extend_from_slice(&tcp);
+                ip
+            }
+            _ => unreachable!("checked by `with_addrs`"),
+        };
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let mut record = Vec::with_capacity(16);
+        record.extend_from_slice(&(now.as_secs() as u32).to_le_bytes());
+        record.extend_from_slice(&now.subsec_micros().to_le_bytes());
+        record.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+        record.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+        self.sink.write_all(&record)?;
+        self.sink.write_all(&packet)
+    }
+}
+
+/// The internet checksum of the concatenation of `parts`, each of an even
+/// length but the last.
+fn checksum(parts: &[&[u8]]) -> u16 {
+    let mut sum = 0u32;
+    for part in parts {
+        let mut chunks = part.chunks_exact(2);
+        for word in &mut chunks {
+            sum += u32::from(u16::from_be_bytes([word[0], word[1]]));
+        }
+        if let [last] = chunks.remainder() {
+            sum += u32::from(*last) << 8;
+        }
+    }
+    while sum >> 16 != 0 {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+// The fields are never pinned.
+impl<T, W: Write> Unpin for Capture<T, W> {}
+
+impl<T: AsyncRead + Unpin, W: Write> AsyncRead for Capture<T, W> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let n = ready!(Pin::new(&mut this.inner).poll_read(cx, buf))?;
+        if n > 0 {
+            this.record(|pcap| pcap.data(Direction::Inbound, &buf[..n]));
+        } else if !buf.is_empty() {
+            this.record(|pcap| pcap.fin(Direction::Inbound));
+        }
+        Poll::Ready(Ok(n))
+    }
+}
+
+impl<T: AsyncWrite + Unpin, W: Write> AsyncWrite for Capture<T, W> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let n = ready!(Pin::new(&mut this.inner).poll_write(cx, buf))?;
+        this.record(|pcap| pcap.data(Direction::Outbound, &buf[..n]));
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(Pin::new(&mut this.inner).poll_flush(cx))?;
+        this.record(|pcap| pcap.sink.flush());
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(Pin::new(&mut this.inner).poll_close(cx))?;
+        this.record(|pcap| pcap.fin(Direction::Outbound));
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T: fmt::Debug, W: Write> fmt::Debug for Capture<T, W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Capture")
+            .field("inner", &self.inner)
+            .field("capturing", &self.is_capturing())
+            .finish()
+    }
+}
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_traffic_is_recorded_as_a_tcp_flow() {
+    use super::duplex;
+    use crate::runtime::{self, Runtime};
+    use futures_util::io::{AsyncReadExt, AsyncWriteExt};
+
+    let (a, mut b) = duplex(64);
+    let mut a = Capture::new(a, Vec::new()).unwrap();
+
+    let mut rt = runtime::default();
+    rt.exec(async {
+        a.write_all(b"ping").await.unwrap();
+        b.write_all(b"pong!").await.unwrap();
+        b.close().await.unwrap();
+        let mut buf = Vec::new();
+        a.read_to_end(&mut buf).await.unwrap();
+        a.close().await.unwrap();
+    });
+
+    let (_, pcap) = a.into_parts();
+    let pcap = pcap.unwrap();
+    assert_eq!(&pcap[..4], &0xa1b2_c3d4u32.to_le_bytes());
+    assert_eq!(&pcap[20..24], &LINKTYPE_RAW.to_le_bytes());
+
+    // SYN, SYN-ACK, ACK, the data each way, then both FINs.
+    let mut packets = Vec::new();
+    let mut rest = &pcap[24..];
+    while !rest.is_empty() {
+        let len = u32::from_le_bytes([rest[8], rest[9], rest[10], rest[11]]) as usize;
+        packets.push(&rest[16..16 + len]);
+        rest = &rest[16 + len..];
+    }
+    let flags: Vec<u8> = packets.iter().map(|p| p[33]).collect();
+    assert_eq!(
+        flags,
+        [
+            SYN,
+            SYN | ACK,
+            ACK,
+            PSH | ACK,
+            PSH | ACK,
+            FIN | ACK,
+            FIN | ACK
+        ]
+    );
+    assert_eq!(&packets[3][40..], b"ping");
+    assert_eq!(&packets[4][40..], b"pong!");
+    for packet in &packets {
+        // Both checksums verify to zero.
+        assert_eq!(checksum(&[&packet[..20]]), 0);
+        let mut pseudo = packet[12..20].to_vec();
+        pseudo.extend_from_slice(&[0, 6]);
+        pseudo.extend_from_slice(&((packet.len() - 20) as u16).to_be_bytes());
+        assert_eq!(checksum(&[&pseudo, &packet[20..]]), 0);
+    }
+    // The peer acknowledges the data written.
+    let ack = u32::from_be_bytes([
+        packets[4][28],
+        packets[4][29],
+        packets[4][30],
+        packets[4][31],
+    ]);
+    assert_eq!(ack, 5);
+}
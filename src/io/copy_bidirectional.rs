@@ -0,0 +1,219 @@
+use futures_core::future::Future;
+use futures_io::{AsyncRead, AsyncWrite};
+use futures_util::ready;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::{fmt, io};
+
+use super::DEFAULT_BUF_SIZE;
+
+/// Copies bytes from `a` to `b` and from `b` to `a` at the same time, until
+/// both reach the end of their streams.
+///
+/// When one side reaches the end of its stream, the bytes read from it are
+/// flushed and the other side is closed, which shuts down its write half
+/// for the sockets of this crate, so the end of the stream propagates while
+/// the other direction keeps running. The future resolves once both
+/// directions are done, with the number of bytes copied from `a` to `b` and
+/// from `b` to `a`.
+///
+/// An error in either direction stops both, and the counts are lost.
+///
+/// # Examples
+///
+/// ```
+/// use futures::prelude::*;
+/// use futures_net::io::copy_bidirectional;
+/// use futures_net::runtime::{self, Runtime};
+/// use futures_net::uds::UnixStream;
+///
+/// let mut rt = runtime::default();
+/// rt.exec(async {
+///     // client <-> (a, proxy, b) <-> server
+///     let (mut client, mut a) = UnixStream::pair()?;
+///     let (mut b, mut server) = UnixStream::pair()?;
+///     let proxy = copy_bidirectional(&mut a, &mut b);
+///
+///     let peers = async {
+///         client.write_all(b"request").await?;
+///         client.close().await?;
+///         let mut request = Vec::new();
+///         server.read_to_end(&mut request).await?;
+///
+///         server.write_all(b"response").await?;
+///         server.close().await?;
+///         let mut response = Vec::new();
+///         client.read_to_end(&mut response).await?;
+///         assert_eq!((request, response), (b"request".to_vec(), b"response".to_vec()));
+///         Ok(())
+///     };
+///
+///     let (counts, ()) = future::try_join(proxy, peers).await?;
+///     assert_eq!(counts, (7, 8));
+///     std::io::Result::Ok(())
+/// })
+/// .unwrap();
+/// ```
+pub fn copy_bidirectional<'a, A, B>(
+    a: &'a mut A,
+    b: &'a mut B,
+) -> CopyBidirectional<'a, A, B>
+where
+    A: AsyncRead + AsyncWrite + Unpin + ?Sized,
+    B: AsyncRead + AsyncWrite + Unpin + ?Sized,
+{
+    CopyBidirectional {
+        a,
+        b,
+        a_to_b: TransferState::Running(CopyBuffer::new()),
+        b_to_a: TransferState::Running(CopyBuffer::new()),
+    }
+}
+
+/// Future returned by [`copy_bidirectional`].
+///
+/// [`copy_bidirectional`]: fn.copy_bidirectional.html
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct CopyBidirectional<'a, A: ?Sized, B: ?Sized> {
+    a: &'a mut A,
+    b: &'a mut B,
+    a_to_b: TransferState,
+    b_to_a: TransferState,
+}
+
+enum TransferState {
+    Running(CopyBuffer),
+    /// Closing the writer after the reader reached its end.
+    ShuttingDown(u64),
+    Done(u64),
+}
+
+struct CopyBuffer {
+    buf: Box<[u8]>,
+    pos: usize,
+    cap: usize,
+    amt: u64,
+    read_done: bool,
+    /// Bytes were written since the last flush.
+    need_flush: bool,
+}
+
+impl CopyBuffer {
+    fn new() -> CopyBuffer {
+        CopyBuffer {
+            buf: vec![0; DEFAULT_BUF_SIZE].into_boxed_slice(),
+            pos: 0,
+            cap: 0,
+            amt: 0,
+            read_done: false,
+            need_flush: false,
+        }
+    }
+
+    fn poll_copy<R, W>(
+        &mut self,
+        cx: &mut Context<'_>,
+        reader: &mut R,
+        writer: &mut W,
+    ) -> Poll<io::Result<u64>>
+    where
+        R: AsyncRead + Unpin + ?Sized,
+        W: AsyncWrite + Unpin + ?Sized,
+    {
+        loop {
+            if self.pos == self.cap && !self.read_done {
+                match Pin::new(&mut *reader).poll_read(cx, &mut self.buf) {
+                    Poll::Ready(res) => {
+                        let n = res?;
+                        if n == 0 {
+                            self.read_done = true;
+                        } else {
+                            self.pos = 0;
+                            self.cap = n;
+                        }
+                    }
+                    Poll::Pending => {
+                        // Don't sit on written bytes while the reader is idle.
+                        if self.need_flush {
+                            ready!(Pin::new(&mut *writer).poll_flush(cx))?;
+                            self.need_flush = false;
+                        }
+                        return Poll::Pending;
+                    }
+                }
+            }
+
+            while self.pos < self.cap {
+                let n =
+                    ready!(Pin::new(&mut *writer)
+                        .poll_write(cx, &self.buf[self.pos..self.cap]))?;
+                if n == 0 {
+                    return Poll::Ready(Err(io::Error::new(
+                        io::ErrorKind::WriteZero,
+                        "write zero byte into writer",
+                    )));
+                }
+                self.pos += n;
+                self.amt += n as u64;
+                self.need_flush = true;
+            }
+
+            if self.pos == self.cap && self.read_done {
+                ready!(Pin::new(&mut *writer).poll_flush(cx))?;
+                return Poll::Ready(Ok(self.amt));
+            }
+        }
+    }
+}
+
+fn transfer_one_direction<R, W>(
+    cx: &mut Context<'_>,
+    state: &mut TransferState,
+    reader: &mut R,
+    writer: &mut W,
+) -> Poll<io::Result<u64>>
+where
+    R: AsyncRead + Unpin + ?Sized,
+    W: AsyncWrite + Unpin + ?Sized,
+{
+    loop {
+        match state {
+            TransferState::Running(buf) => {
+                let count = ready!(buf.poll_copy(cx, reader, writer))?;
+                *state = TransferState::ShuttingDown(count);
+            }
+            TransferState::ShuttingDown(count) => {
+                ready!(Pin::new(&mut *writer).poll_close(cx))?;
+                *state = TransferState::Done(*count);
+            }
+            TransferState::Done(count) => return Poll::Ready(Ok(*count)),
+        }
+    }
+}
+
+impl<A, B> Future for CopyBidirectional<'_, A, B>
+where
+    A: AsyncRead + AsyncWrite + Unpin + ?Sized,
+    B: AsyncRead + AsyncWrite + Unpin + ?Sized,
+{
+    type Output = io::Result<(u64, u64)>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<(u64, u64)>> {
+        let this = self.get_mut();
+        // Poll both directions before returning, so each registers its own
+        // interest.
+        let a_to_b =
+            transfer_one_direction(cx, &mut this.a_to_b, &mut *this.a, &mut *this.b)?;
+        let b_to_a =
+            transfer_one_direction(cx, &mut this.b_to_a, &mut *this.b, &mut *this.a)?;
+        let a_to_b = ready!(a_to_b);
+        let b_to_a = ready!(b_to_a);
+        Poll::Ready(Ok((a_to_b, b_to_a)))
+    }
+}
+
+impl<A: ?Sized, B: ?Sized> fmt::Debug for CopyBidirectional<'_, A, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CopyBidirectional").finish()
+    }
+}
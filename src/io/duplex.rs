@@ -0,0 +1,223 @@
+use futures_io::{AsyncRead, AsyncWrite};
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Waker};
+use std::{cmp, fmt, io};
+
+/// Creates a pair of connected in-memory streams.
+///
+/// Bytes written to one stream are read from the other. Each direction
+/// buffers up to `max_buf_size` bytes, after which writes wait for the
+/// other side to read, like a socket whose peer is slow.
+///
+/// Closing a stream ends the stream of the other side once it read the
+/// buffered bytes. Dropping a stream closes it as well, and makes writes
+/// from the other side fail with `BrokenPipe`.
+///
+/// # Panics
+///
+/// Panics if `max_buf_size` is zero.
+///
+/// # Examples
+///
+/// ```
+/// use futures::prelude::*;
+/// use futures_net::io;
+/// use futures_net::runtime::{self, Runtime};
+///
+/// let mut rt = runtime::default();
+/// rt.exec(async {
+///     let (mut client, mut server) = io::duplex(64);
+///
+///     client.write_all(b"ping").await?;
+///     let mut buf = [0; 4];
+///     server.read_exact(&mut buf).await?;
+///     assert_eq!(&buf, b"ping");
+///
+///     server.close().await?;
+///     assert_eq!(client.read(&mut buf).await?, 0);
+///     std::io::Result::Ok(())
+/// })
+/// .unwrap();
+/// ```
+pub fn duplex(max_buf_size: usize) -> (DuplexStream, DuplexStream) {
+    assert!(max_buf_size > 0, "buffer size must be positive");
+    let one = Arc::new(Mutex::new(Pipe::new(max_buf_size)));
+    let two = Arc::new(Mutex::new(Pipe::new(max_buf_size)));
+    (
+        DuplexStream {
+            read: one.clone(),
+            write: two.clone(),
+        },
+        DuplexStream {
+            read: two,
+            write: one,
+        },
+    )
+}
+
+/// One end of an in-memory stream created by [`duplex`].
+///
+/// [`duplex`]: fn.duplex.html
+pub struct DuplexStream {
+    read: Arc<Mutex<Pipe>>,
+    write: Arc<Mutex<Pipe>>,
+}
+
+/// The bytes going in one direction.
+struct Pipe {
+    buf: VecDeque<u8>,
+    max_buf_size: usize,
+    /// The writing side was closed.
+    closed: bool,
+    /// The reading side was dropped.
+    dropped: bool,
+    read_waker: Option<Waker>,
+    write_waker: Option<Waker>,
+}
+
+impl Pipe {
+    fn new(max_buf_size: usize) -> Pipe {
+        Pipe {
+            buf: VecDeque::new(),
+            max_buf_size,
+            closed: false,
+            dropped: false,
+            read_waker: None,
+            write_waker: None,
+        }
+    }
+
+    fn close(&mut self) {
+        self.closed = true;
+        if let Some(waker) = self.read_waker.take() {
+            waker.wake();
+        }
+    }
+
+    fn drop_reader(&mut self) {
+        self.dropped = true;
+        self.buf.clear();
+        if let Some(waker) = self.write_waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl AsyncRead for DuplexStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut pipe = self.read.lock();
+        if pipe.buf.is_empty() && !buf.is_empty() {
+            if pipe.closed {
+                return Poll::Ready(Ok(0));
+            }
+            pipe.read_waker = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+
+        let n = cmp::min(buf.len(), pipe.buf.len());
+        for (dst, src) in buf.iter_mut().zip(pipe.buf.drain(..n)) {
+            *dst = src;
+        }
+        if let Some(waker) = pipe.write_waker.take() {
+            waker.wake();
+        }
+        Poll::Ready(Ok(n))
+    }
+}
+
+impl AsyncWrite for DuplexStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut pipe = self.write.lock();
+        if pipe.dropped {
+            return Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()));
+        }
+        if pipe.closed {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "write after close",
+            )));
+        }
+        let room = pipe.max_buf_size - pipe.buf.len();
+        if room == 0 && !buf.is_empty() {
+            pipe.write_waker = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+
+        let n = cmp::min(buf.len(), room);
+        pipe.buf.extend(&buf[..n]);
+        if let Some(waker) = pipe.read_waker.take() {
+            waker.wake();
+        }
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.write.lock().close();
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Drop for DuplexStream {
+    fn drop(&mut self) {
+        self.write.lock().close();
+        self.read.lock().drop_reader();
+    }
+}
+
+impl fmt::Debug for DuplexStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DuplexStream")
+            .field("readable", &self.read.lock().buf.len())
+            .field("writable", &{
+                let pipe = self.write.lock();
+                pipe.max_buf_size - pipe.buf.len()
+            })
+            .finish()
+    }
+}
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_writes_wait_for_room() {
+    use crate::runtime::{self, Runtime};
+    use futures_util::future;
+    use futures_util::io::{AsyncReadExt, AsyncWriteExt};
+
+    let mut rt = runtime::default();
+    rt.exec(async {
+        let (mut a, mut b) = duplex(4);
+
+        // The writer can only go ahead as fast as the reader makes room.
+        let write = async {
+            a.write_all(b"0123456789").await.unwrap();
+            a
+        };
+        let read = async {
+            let mut buf = [0; 10];
+            b.read_exact(&mut buf).await.unwrap();
+            assert_eq!(&buf, b"0123456789");
+            b
+        };
+        let (a, mut b) = future::join(write, read).await;
+
+        drop(a);
+        assert_eq!(b.read(&mut [0; 1]).await.unwrap(), 0);
+        let err = b.write(b"x").await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::BrokenPipe);
+    });
+}
@@ -0,0 +1,201 @@
+use futures_io::{AsyncRead, AsyncWrite};
+use futures_util::ready;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::{fmt, io};
+
+type Callback = Box<dyn FnMut(&[u8]) + Send>;
+
+/// Calls back with the bytes going through an I/O object, without changing
+/// them.
+///
+/// The read callback sees every chunk returned by a read, and the write
+/// callback every chunk accepted by a write, in order, so they can log or
+/// checksum the traffic of an existing connection. The close callback runs
+/// once closing the object succeeded.
+///
+/// # Examples
+///
+/// ```
+/// use futures::prelude::*;
+/// use futures_net::io::Inspect;
+/// use futures_net::runtime::{self, Runtime};
+/// use futures_net::uds::UnixStream;
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use std::sync::Arc;
+///
+/// let mut rt = runtime::default();
+/// rt.exec(async {
+///     let (a, _b) = UnixStream::pair()?;
+///     let written = Arc::new(AtomicUsize::new(0));
+///
+///     let counter = written.clone();
+///     let mut a = Inspect::new(a).on_write(move |chunk| {
+///         counter.fetch_add(chunk.len(), Ordering::Relaxed);
+///     });
+///     a.write_all(b"hello").await?;
+///     assert_eq!(written.load(Ordering::Relaxed), 5);
+///     std::io::Result::Ok(())
+/// })
+/// .unwrap();
+/// ```
+pub struct Inspect<T> {
+    inner: T,
+    on_read: Option<Callback>,
+    on_write: Option<Callback>,
+    on_close: Option<Box<dyn FnMut() + Send>>,
+    closed: bool,
+}
+
+impl<T> Inspect<T> {
+    /// Wraps `inner`, without any callback yet.
+    pub fn new(inner: T) -> Inspect<T> {
+        Inspect {
+            inner,
+            on_read: None,
+            on_write: None,
+            on_close: None,
+            closed: false,
+        }
+    }
+
+    /// Calls `f` with each chunk read.
+    pub fn on_read<F>(mut self, f: F) -> Inspect<T>
+    where
+        F: FnMut(&[u8]) + Send + 'static,
+    {
+        self.on_read = Some(Box::new(f));
+        self
+    }
+
+    /// Calls `f` with each chunk written.
+    pub fn on_write<F>(mut self, f: F) -> Inspect<T>
+    where
+        F: FnMut(&[u8]) + Send + 'static,
+    {
+        self.on_write = Some(Box::new(f));
+        self
+    }
+
+    /// Calls `f` once the object was closed.
+    pub fn on_close<F>(mut self, f: F) -> Inspect<T>
+    where
+        F: FnMut() + Send + 'static,
+    {
+        self.on_close = Some(Box::new(f));
+        self
+    }
+
+    /// Returns a reference to the I/O object.
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the I/O object.
+    ///
+    /// Bytes going through it directly are not inspected.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Consumes the wrapper, returning the I/O object.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+// The fields are never pinned.
+impl<T> Unpin for Inspect<T> {}
+
+impl<T: AsyncRead + Unpin> AsyncRead for Inspect<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let n = ready!(Pin::new(&mut this.inner).poll_read(cx, buf))?;
+        if let Some(f) = &mut this.on_read {
+            if n > 0 {
+                f(&buf[..n]);
+            }
+        }
+        Poll::Ready(Ok(n))
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for Inspect<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let n = ready!(Pin::new(&mut this.inner).poll_write(cx, buf))?;
+        if let Some(f) = &mut this.on_write {
+            if n > 0 {
+                f(&buf[..n]);
+            }
+        }
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(Pin::new(&mut this.inner).poll_close(cx))?;
+        if !this.closed {
+            this.closed = true;
+            if let Some(f) = &mut this.on_close {
+                f();
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Inspect<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Inspect")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_callbacks_see_the_traffic() {
+    use super::duplex;
+    use crate::runtime::{self, Runtime};
+    use futures_util::io::{AsyncReadExt, AsyncWriteExt};
+    use std::sync::{Arc, Mutex};
+
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let (a, mut b) = duplex(64);
+    let (read_log, write_log, close_log) = (log.clone(), log.clone(), log.clone());
+    let mut a = Inspect::new(a)
+        .on_read(move |chunk| read_log.lock().unwrap().push(format!("< {:?}", chunk)))
+        .on_write(move |chunk| write_log.lock().unwrap().push(format!("> {:?}", chunk)))
+        .on_close(move || close_log.lock().unwrap().push("close".to_string()));
+
+    let mut rt = runtime::default();
+    rt.exec(async {
+        a.write_all(b"hi").await.unwrap();
+        b.write_all(b"yo").await.unwrap();
+        let mut buf = [0; 2];
+        a.read_exact(&mut buf).await.unwrap();
+        a.close().await.unwrap();
+        a.close().await.unwrap();
+
+        let mut echoed = Vec::new();
+        b.read_to_end(&mut echoed).await.unwrap();
+        assert_eq!(echoed, b"hi");
+    });
+    assert_eq!(
+        *log.lock().unwrap(),
+        ["> [104, 105]", "< [121, 111]", "close"]
+    );
+}
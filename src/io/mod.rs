@@ -0,0 +1,196 @@
+//! Helpers for the `AsyncRead` and `AsyncWrite` types of this crate.
+//!
+//! [`BufReader`], [`BufWriter`] and [`BufStream`] buffer any I/O object,
+//! and keep reporting it readable while bytes remain buffered, so code
+//! waiting on [`AsyncReadReady`] doesn't stall on data the socket already
+//! delivered.
+//!
+//! [`copy_bidirectional`] is the core of a proxy, shuttling bytes between
+//! two streams and propagating the end of each. [`Throttled`] caps the
+//! bandwidth of any stream. [`Inspect`] taps its traffic for debugging, and
+//! [`Capture`] records it into a pcap file. [`duplex`] creates a pair of connected in-memory streams, to
+//! test protocol code without sockets. [`write_all_vectored`] writes
+//! scatter-gather buffers with as few system calls as the object allows.
+//!
+//! [`BufReader`]: struct.BufReader.html
+//! [`BufWriter`]: struct.BufWriter.html
+//! [`BufStream`]: struct.BufStream.html
+//! [`copy_bidirectional`]: fn.copy_bidirectional.html
+//! [`Throttled`]: struct.Throttled.html
+//! [`duplex`]: fn.duplex.html
+//! [`Inspect`]: struct.Inspect.html
+//! [`Capture`]: struct.Capture.html
+//! [`write_all_vectored`]: fn.write_all_vectored.html
+//! [`AsyncReadReady`]: https://docs.rs/async-ready/3/async_ready/trait.AsyncReadReady.html
+
+mod buf_reader;
+mod buf_stream;
+mod buf_writer;
+mod capture;
+mod copy_bidirectional;
+mod duplex;
+mod inspect;
+mod throttled;
+
+pub use self::buf_reader::BufReader;
+pub(crate) use self::buf_reader::ReadBuffer;
+pub use self::buf_stream::BufStream;
+pub use self::buf_writer::BufWriter;
+pub use self::capture::Capture;
+pub use self::copy_bidirectional::{copy_bidirectional, CopyBidirectional};
+pub use self::duplex::{duplex, DuplexStream};
+pub use self::inspect::Inspect;
+pub use self::throttled::Throttled;
+pub(crate) use self::throttled::{TokenBucket, TokenWait};
+
+use futures_io::{AsyncBufRead, AsyncWrite};
+use futures_util::ready;
+use std::io::IoSlice;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::{io, str};
+
+pub(crate) const DEFAULT_BUF_SIZE: usize = 8 * 1024;
+
+/// Turns `WouldBlock` into `Pending`, so a `try_` method can go through the
+/// same counting and annotating as the poll methods.
+pub(crate) fn pending_on_wouldblock<T>(res: io::Result<T>) -> Poll<io::Result<T>> {
+    match res {
+        Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => Poll::Pending,
+        res => Poll::Ready(res),
+    }
+}
+
+/// Turns `Pending` back into `WouldBlock`.
+pub(crate) fn wouldblock_on_pending<T>(poll: Poll<io::Result<T>>) -> io::Result<T> {
+    match poll {
+        Poll::Ready(res) => res,
+        Poll::Pending => Err(io::ErrorKind::WouldBlock.into()),
+    }
+}
+
+/// Appends bytes to `buf` until `byte` or the end of the stream, counting
+/// them in `read` so the call can be resumed.
+pub(crate) fn poll_read_until<R>(
+    mut reader: Pin<&mut R>,
+    cx: &mut Context<'_>,
+    byte: u8,
+    buf: &mut Vec<u8>,
+    read: &mut usize,
+) -> Poll<io::Result<usize>>
+where
+    R: AsyncBufRead + ?Sized,
+{
+    loop {
+        let (done, used) = {
+            let available = ready!(reader.as_mut().poll_fill_buf(cx))?;
+            match available.iter().position(|&b| b == byte) {
+                Some(i) => {
+                    buf.extend_from_slice(&available[..=i]);
+                    (true, i + 1)
+                }
+                None => {
+                    buf.extend_from_slice(available);
+                    (false, available.len())
+                }
+            }
+        };
+        reader.as_mut().consume(used);
+        *read += used;
+        if done || used == 0 {
+            return Poll::Ready(Ok(*read));
+        }
+    }
+}
+
+/// Writes all of `bufs` to `writer`, in order.
+///
+/// Each write hands every remaining buffer to `poll_write_vectored`, which
+/// the streams of this crate submit as a single `writev`. After a partial
+/// write the buffers are advanced past the bytes written, which is why they
+/// are taken mutably; their contents are left as they were.
+///
+/// # Errors
+///
+/// Fails with `WriteZero` if the writer stops accepting bytes.
+///
+/// # Examples
+///
+/// ```
+/// use futures::prelude::*;
+/// use futures_net::io::write_all_vectored;
+/// use futures_net::runtime::{self, Runtime};
+/// use futures_net::uds::UnixStream;
+/// use std::io::IoSlice;
+///
+/// let mut rt = runtime::default();
+/// rt.exec(async {
+///     let (mut a, mut b) = UnixStream::pair()?;
+///     let head = b"HTTP/1.1 200 OK\r\ncontent-length: 5\r\n\r\n";
+///     let mut bufs = [IoSlice::new(head), IoSlice::new(b"hello")];
+///     write_all_vectored(&mut a, &mut bufs).await?;
+///     drop(a);
+///
+///     let mut response = Vec::new();
+///     b.read_to_end(&mut response).await?;
+///     assert!(response.ends_with(b"\r\n\r\nhello"));
+///     std::io::Result::Ok(())
+/// })
+/// .unwrap();
+/// ```
+pub async fn write_all_vectored<W>(
+    writer: &mut W,
+    mut bufs: &mut [IoSlice<'_>],
+) -> io::Result<()>
+where
+    W: AsyncWrite + Unpin + ?Sized,
+{
+    IoSlice::advance_slices(&mut bufs, 0);
+    while !bufs.is_empty() {
+        let n = futures_util::future::poll_fn(|cx| {
+            Pin::new(&mut *writer).poll_write_vectored(cx, bufs)
+        })
+        .await?;
+        if n == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::WriteZero,
+                "failed to write whole buffers",
+            ));
+        }
+        IoSlice::advance_slices(&mut bufs, n);
+    }
+    Ok(())
+}
+
+pub(crate) async fn read_until<R>(
+    reader: &mut R,
+    byte: u8,
+    buf: &mut Vec<u8>,
+) -> io::Result<usize>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut read = 0;
+    futures_util::future::poll_fn(|cx| {
+        poll_read_until(Pin::new(&mut *reader), cx, byte, buf, &mut read)
+    })
+    .await
+}
+
+pub(crate) async fn read_line<R>(reader: &mut R, buf: &mut String) -> io::Result<usize>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut line = Vec::new();
+    let n = read_until(reader, b'\n', &mut line).await?;
+    match str::from_utf8(&line) {
+        Ok(line) => {
+            buf.push_str(line);
+            Ok(n)
+        }
+        Err(_) => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "stream did not contain valid UTF-8",
+        )),
+    }
+}
@@ -0,0 +1,338 @@
+use futures_core::future::Future;
+use futures_io::{AsyncRead, AsyncWrite};
+use futures_util::ready;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use std::{cmp, fmt, io};
+
+use crate::time::{self, Sleep};
+
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+
+/// Limits the byte rate of an I/O object.
+///
+/// Each direction has its own token bucket: a byte is read or written for
+/// each token, and tokens come back at the configured rate, up to a tenth
+/// of a second worth of bytes. Once the bucket is empty, reads and writes
+/// wait on a timer until it refilled, instead of touching the object.
+///
+/// A direction without a rate is not limited. Time follows the clock of
+/// the runtime, so [`time::pause`] applies to the limits as well.
+///
+/// # Examples
+///
+/// ```
+/// use futures::prelude::*;
+/// use futures_net::io::Throttled;
+/// use futures_net::runtime::{self, Runtime};
+/// use futures_net::uds::UnixStream;
+/// use std::time::{Duration, Instant};
+///
+/// let mut rt = runtime::default();
+/// rt.exec(async {
+///     let (a, _b) = UnixStream::pair()?;
+///     // 10 KiB/s for writes, reads stay unlimited.
+///     let mut a = Throttled::new(a);
+///     a.set_write_rate(Some(10 * 1024));
+///
+///     let start = Instant::now();
+///     a.write_all(&[0; 2048]).await?;
+///     assert!(start.elapsed() >= Duration::from_millis(90));
+///     std::io::Result::Ok(())
+/// })
+/// .unwrap();
+/// ```
+///
+/// [`time::pause`]: ../time/fn.pause.html
+pub struct Throttled<T> {
+    inner: T,
+    read: Option<Bucket>,
+    write: Option<Bucket>,
+}
+
+struct Bucket {
+    tokens: TokenBucket,
+    wait: TokenWait,
+}
+
+impl Bucket {
+    fn new(rate: u64) -> Bucket {
+        Bucket {
+            tokens: TokenBucket::new(rate),
+            wait: TokenWait::new(),
+        }
+    }
+
+    fn poll_acquire(&mut self, cx: &mut Context<'_>, want: usize) -> Poll<usize> {
+        let tokens = &mut self.tokens;
+        self.wait.poll(cx, || tokens.acquire(time::now(), want))
+    }
+}
+
+/// A token bucket holding up to a tenth of a second worth of its rate.
+#[derive(Debug)]
+pub(crate) struct TokenBucket {
+    /// Tokens per second.
+    rate: u64,
+    capacity: u64,
+    tokens: u64,
+    /// Time up to which tokens were added.
+    refilled: Instant,
+}
+
+impl TokenBucket {
+    /// Creates a full bucket.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rate` is zero.
+    pub(crate) fn new(rate: u64) -> TokenBucket {
+        assert!(rate > 0, "rate must be positive");
+        let capacity = cmp::max(rate / 10, 1);
+        TokenBucket {
+            rate,
+            capacity,
+            tokens: capacity,
+            refilled: time::now(),
+        }
+    }
+
+    pub(crate) fn rate(&self) -> u64 {
+        self.rate
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.refilled);
+        let new = elapsed.as_nanos() * u128::from(self.rate) / NANOS_PER_SEC;
+        if self.tokens + (new.min(u128::from(self.capacity)) as u64) >= self.capacity {
+            self.tokens = self.capacity;
+            self.refilled = now;
+        } else if new > 0 {
+            self.tokens += new as u64;
+            // Only account for the time of whole tokens, the rest carries
+            // over to the next refill.
+            let nanos = new * NANOS_PER_SEC / u128::from(self.rate);
+            self.refilled += Duration::from_nanos(nanos as u64);
+        }
+    }
+
+    /// Returns how many tokens out of `want` are available, without taking
+    /// them, or the instant to retry at if there are none.
+    pub(crate) fn acquire(
+        &mut self,
+        now: Instant,
+        want: usize,
+    ) -> Result<usize, Instant> {
+        let want = cmp::min(want as u64, self.capacity);
+        self.refill(now);
+        if self.tokens > 0 {
+            return Ok(cmp::min(self.tokens, want) as usize);
+        }
+        // Wait for all of `want` to avoid a trickle of tiny transfers.
+        let nanos = (u128::from(want) * NANOS_PER_SEC).div_ceil(u128::from(self.rate));
+        Err(self.refilled + Duration::from_nanos(nanos as u64))
+    }
+
+    /// Takes `n` tokens, after they were acquired.
+    pub(crate) fn consume(&mut self, n: usize) {
+        self.tokens = self.tokens.saturating_sub(n as u64);
+    }
+}
+
+/// The timer of a task waiting for a `TokenBucket`.
+#[derive(Debug)]
+pub(crate) struct TokenWait {
+    sleep: Option<Sleep>,
+}
+
+impl TokenWait {
+    pub(crate) fn new() -> TokenWait {
+        TokenWait { sleep: None }
+    }
+
+    /// Calls `acquire` until it returns tokens, sleeping until the instant
+    /// it returns otherwise.
+    pub(crate) fn poll<F>(&mut self, cx: &mut Context<'_>, mut acquire: F) -> Poll<usize>
+    where
+        F: FnMut() -> Result<usize, Instant>,
+    {
+        loop {
+            let deadline = match acquire() {
+                Ok(n) => {
+                    self.sleep = None;
+                    return Poll::Ready(n);
+                }
+                Err(deadline) => deadline,
+            };
+            let sleep = match &mut self.sleep {
+                Some(sleep) => {
+                    if sleep.deadline() != deadline {
+                        sleep.reset(deadline);
+                    }
+                    sleep
+                }
+                None => self.sleep.get_or_insert(time::sleep_until(deadline)),
+            };
+            ready!(Pin::new(sleep).poll(cx));
+        }
+    }
+}
+
+impl<T> Throttled<T> {
+    /// Wraps `inner` without any limit, see [`set_read_rate`] and
+    /// [`set_write_rate`].
+    ///
+    /// [`set_read_rate`]: #method.set_read_rate
+    /// [`set_write_rate`]: #method.set_write_rate
+    pub fn new(inner: T) -> Throttled<T> {
+        Throttled {
+            inner,
+            read: None,
+            write: None,
+        }
+    }
+
+    /// Wraps `inner`, limiting both directions to `rate` bytes per second
+    /// each.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rate` is zero.
+    pub fn with_rate(inner: T, rate: u64) -> Throttled<T> {
+        let mut throttled = Throttled::new(inner);
+        throttled.set_read_rate(Some(rate));
+        throttled.set_write_rate(Some(rate));
+        throttled
+    }
+
+    /// Returns the limit of reads, in bytes per second.
+    pub fn read_rate(&self) -> Option<u64> {
+        self.read.as_ref().map(|bucket| bucket.tokens.rate())
+    }
+
+    /// Limits reads to `rate` bytes per second, or lifts the limit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rate` is zero.
+    pub fn set_read_rate(&mut self, rate: Option<u64>) {
+        self.read = rate.map(Bucket::new);
+    }
+
+    /// Returns the limit of writes, in bytes per second.
+    pub fn write_rate(&self) -> Option<u64> {
+        self.write.as_ref().map(|bucket| bucket.tokens.rate())
+    }
+
+    /// Limits writes to `rate` bytes per second, or lifts the limit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rate` is zero.
+    pub fn set_write_rate(&mut self, rate: Option<u64>) {
+        self.write = rate.map(Bucket::new);
+    }
+
+    /// Returns a reference to the I/O object.
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the I/O object.
+    ///
+    /// Bytes transferred through it directly are not limited.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Consumes the wrapper, returning the I/O object.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+// The fields are never pinned.
+impl<T> Unpin for Throttled<T> {}
+
+impl<T: AsyncRead + Unpin> AsyncRead for Throttled<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let bucket = match &mut this.read {
+            Some(bucket) if !buf.is_empty() => bucket,
+            _ => return Pin::new(&mut this.inner).poll_read(cx, buf),
+        };
+        let n = ready!(bucket.poll_acquire(cx, buf.len()));
+        let n = ready!(Pin::new(&mut this.inner).poll_read(cx, &mut buf[..n]))?;
+        bucket.tokens.consume(n);
+        Poll::Ready(Ok(n))
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for Throttled<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let bucket = match &mut this.write {
+            Some(bucket) if !buf.is_empty() => bucket,
+            _ => return Pin::new(&mut this.inner).poll_write(cx, buf),
+        };
+        let n = ready!(bucket.poll_acquire(cx, buf.len()));
+        let n = ready!(Pin::new(&mut this.inner).poll_write(cx, &buf[..n]))?;
+        bucket.tokens.consume(n);
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_close(cx)
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Throttled<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Throttled")
+            .field("inner", &self.inner)
+            .field("read_rate", &self.read_rate())
+            .field("write_rate", &self.write_rate())
+            .finish()
+    }
+}
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_reads_follow_the_rate() {
+    use crate::runtime::{self, Runtime};
+    use crate::uds::UnixStream;
+    use futures_util::io::{AsyncReadExt, AsyncWriteExt};
+
+    let mut rt = runtime::default();
+    rt.exec(async {
+        time::pause();
+        let (a, mut b) = UnixStream::pair().unwrap();
+        b.write_all(&[1; 1000]).await.unwrap();
+        drop(b);
+
+        let mut a = Throttled::new(a);
+        a.set_read_rate(Some(1000));
+        let start = time::now();
+        let mut buf = Vec::new();
+        a.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(buf.len(), 1000);
+
+        // The first 100 bytes come from the full bucket, the rest at the rate.
+        let elapsed = time::now() - start;
+        assert!(elapsed >= Duration::from_millis(900), "{:?}", elapsed);
+        assert!(elapsed <= Duration::from_millis(1000), "{:?}", elapsed);
+    });
+}
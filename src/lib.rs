@@ -43,6 +43,56 @@
 //!     Ok(())
 //! }
 //! ```
+//!
+//! # Using another executor
+//!
+//! The socket types don't need the runtime of this crate. The first time
+//! one is polled it attaches to a reactor which runs on a background thread,
+//! so they work from any executor, e.g. `futures::executor::block_on`:
+//!
+//! ```rust
+//! use futures::prelude::*;
+//! use futures_net::{TcpListener, TcpStream};
+//!
+//! futures::executor::block_on(async {
+//!     let addr = "127.0.0.1:0".parse().unwrap();
+//!     let mut listener = TcpListener::bind(&addr).unwrap();
+//!     let addr = listener.local_addr().unwrap();
+//!
+//!     let client = async {
+//!         let mut stream = TcpStream::connect(&addr).await.unwrap();
+//!         stream.write_all(b"ping").await.unwrap();
+//!     };
+//!     let server = async {
+//!         let mut stream = listener.incoming().next().await.unwrap().unwrap();
+//!         let mut buf = [0; 4];
+//!         stream.read_exact(&mut buf).await.unwrap();
+//!         buf
+//!     };
+//!
+//!     let ((), buf) = future::join(client, server).await;
+//!     assert_eq!(&buf, b"ping");
+//! });
+//! ```
+//!
+//! # Feature flags
+//!
+//! Everything is built by default. Without the default features the crate
+//! can be cut down to:
+//!
+//! * `poll-only`: the poller of [`driver::sys`] alone, without any
+//!   futures or executor dependency.
+//! * `net-only`: the reactor, the socket types and their utilities, without
+//!   the runtime and the `main`/`test` macros.
+//! * `runtime`: `net-only` and the [`runtime`] with its task APIs.
+//! * `macro` (default): `runtime` and the `main`/`test` macros.
+//!
+//! ```toml
+//! futures-net = { version = "0.6", default-features = false, features = ["net-only"] }
+//! ```
+//!
+//! [`driver::sys`]: driver/sys/index.html
+//! [`runtime`]: runtime/index.html
 
 #![warn(
     rust_2018_idioms,
@@ -66,15 +116,73 @@
 #[doc(inline)]
 pub use futures_net_macro::{main, test};
 
+#[cfg(feature = "net-only")]
+pub mod buf;
+#[cfg(feature = "net-only")]
+pub mod can;
+#[cfg(feature = "net-only")]
+pub mod codec;
+#[cfg(feature = "compat")]
+pub mod compat;
+#[cfg(feature = "net-only")]
+pub mod dns;
+#[cfg(feature = "net-only")]
 pub mod driver;
+#[cfg(not(feature = "net-only"))]
+pub mod driver {
+    //! The poller alone, as built with the `poll-only` feature.
+
+    pub mod sys;
+}
+#[cfg(feature = "net-only")]
+pub mod error;
+#[cfg(feature = "net-only")]
+pub mod io;
+#[cfg(feature = "net-only")]
+pub mod metrics;
+#[cfg(feature = "net-only")]
+pub mod packet;
+#[cfg(feature = "net-only")]
+pub mod process;
+#[cfg(feature = "net-only")]
+pub mod raw;
+#[cfg(feature = "runtime")]
 pub mod runtime;
+#[cfg(feature = "net-only")]
+pub mod sctp;
+#[cfg(feature = "sim")]
+pub mod sim;
+#[cfg(feature = "net-only")]
+pub mod task;
+#[cfg(feature = "net-only")]
 pub mod tcp;
+#[cfg(feature = "test-util")]
+pub mod test_util;
+#[cfg(feature = "net-only")]
+pub mod time;
+#[cfg(feature = "net-only")]
+pub mod transport;
+#[cfg(feature = "net-only")]
+pub mod tun;
+#[cfg(feature = "net-only")]
 pub mod udp;
+#[cfg(feature = "net-only")]
 pub mod uds;
+#[cfg(feature = "net-only")]
+pub mod xdp;
 
+#[cfg(feature = "net-only")]
+#[doc(inline)]
+pub use crate::dns::lookup_host;
+#[cfg(feature = "runtime")]
+#[doc(inline)]
+pub use crate::runtime::spawn;
+#[cfg(feature = "net-only")]
 #[doc(inline)]
 pub use crate::tcp::{TcpListener, TcpStream};
+#[cfg(feature = "net-only")]
 #[doc(inline)]
 pub use crate::udp::UdpSocket;
+#[cfg(feature = "net-only")]
 #[doc(inline)]
 pub use crate::uds::{UnixDatagram, UnixListener, UnixStream};
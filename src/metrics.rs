@@ -0,0 +1,273 @@
+//! I/O counters of the sockets of the crate.
+//!
+//! Counting is off by default, and costs a branch per operation while it
+//! is. Once turned on with [`set_enabled`], the TCP and
+//! Unix streams, listeners and UDP sockets opened from then on count the
+//! bytes they read and write and the errors they return, both on their own
+//! and into process wide totals read with [`snapshot`]. Sockets opened
+//! while counting was off are never counted.
+//!
+//! Bytes are counted as they go through the socket: reads served from the
+//! buffer of a [buffered] stream were counted when the buffer was filled.
+//!
+//! # Examples
+//!
+//! ```
+//! use futures::io::{AsyncReadExt, AsyncWriteExt};
+//! use futures::StreamExt;
+//! use futures_net::runtime::{self, Runtime};
+//! use futures_net::{metrics, TcpListener, TcpStream};
+//!
+//! metrics::set_enabled(true);
+//! let before = metrics::snapshot();
+//!
+//! let mut rt = runtime::default();
+//! let client = rt.exec(async {
+//!     let mut listener = TcpListener::bind(&"127.0.0.1:0".parse().unwrap())?;
+//!     let mut client = TcpStream::connect(&listener.local_addr()?).await?;
+//!     let mut server = listener.incoming().next().await.unwrap()?;
+//!     client.write_all(b"ping").await?;
+//!     server.read_exact(&mut [0; 4]).await?;
+//!     Ok::<_, std::io::Error>(client)
+//! })?;
+//!
+//! assert_eq!(client.io_stats().bytes_written, 4);
+//! let after = metrics::snapshot();
+//! assert!(after.accepted > before.accepted);
+//! assert!(after.bytes_read >= before.bytes_read + 4);
+//! # Ok::<_, std::io::Error>(())
+//! ```
+//!
+//! [`set_enabled`]: fn.set_enabled.html
+//! [`snapshot`]: fn.snapshot.html
+//! [buffered]: ../tcp/struct.TcpStream.html#method.buffered
+
+use std::io;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering::Relaxed};
+use std::task::Poll;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+static TOTALS: Totals = Totals {
+    bytes_read: AtomicU64::new(0),
+    bytes_written: AtomicU64::new(0),
+    accepted: AtomicU64::new(0),
+    closed: AtomicU64::new(0),
+    errors: AtomicU64::new(0),
+    open: AtomicU64::new(0),
+};
+
+struct Totals {
+    bytes_read: AtomicU64,
+    bytes_written: AtomicU64,
+    accepted: AtomicU64,
+    closed: AtomicU64,
+    errors: AtomicU64,
+    open: AtomicU64,
+}
+
+/// Turns the counting on or off, for the sockets opened from then on.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Relaxed);
+}
+
+/// Returns `true` if sockets opened now are counted.
+pub fn is_enabled() -> bool {
+    ENABLED.load(Relaxed)
+}
+
+/// Returns the totals of every counted socket so far.
+///
+/// The counters are read one by one while sockets keep updating them, so
+/// they may be off by the operations running meanwhile.
+pub fn snapshot() -> Snapshot {
+    Snapshot {
+        bytes_read: TOTALS.bytes_read.load(Relaxed),
+        bytes_written: TOTALS.bytes_written.load(Relaxed),
+        accepted: TOTALS.accepted.load(Relaxed),
+        closed: TOTALS.closed.load(Relaxed),
+        errors: TOTALS.errors.load(Relaxed),
+        open_sockets: TOTALS.open.load(Relaxed),
+    }
+}
+
+/// The totals returned by [`snapshot`].
+///
+/// [`snapshot`]: fn.snapshot.html
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Snapshot {
+    /// Bytes read from streams and received by UDP sockets.
+    pub bytes_read: u64,
+    /// Bytes written to streams and sent by UDP sockets.
+    pub bytes_written: u64,
+    /// Connections accepted by listeners.
+    pub accepted: u64,
+    /// Streams closed, connected or accepted ones alike.
+    pub closed: u64,
+    /// Errors returned by reads, writes and accepts.
+    pub errors: u64,
+    /// Streams, listeners and UDP sockets currently open.
+    pub open_sockets: u64,
+}
+
+/// The counters of a single socket.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SocketStats {
+    /// Bytes read or received.
+    pub bytes_read: u64,
+    /// Bytes written or sent.
+    pub bytes_written: u64,
+    /// Errors returned by reads, writes and accepts.
+    pub errors: u64,
+}
+
+/// The counters kept by a socket, which also updates the totals.
+pub(crate) struct SocketMetrics {
+    /// Unset if counting was off when the socket was opened.
+    counted: bool,
+    /// Set for streams, whose dropping counts as a closed connection.
+    stream: bool,
+    bytes_read: AtomicU64,
+    bytes_written: AtomicU64,
+    errors: AtomicU64,
+}
+
+impl SocketMetrics {
+    /// Counts a stream.
+    pub(crate) fn stream() -> SocketMetrics {
+        SocketMetrics::new(true)
+    }
+
+    /// Counts a listener or a datagram socket.
+    pub(crate) fn socket() -> SocketMetrics {
+        SocketMetrics::new(false)
+    }
+
+    fn new(stream: bool) -> SocketMetrics {
+        let counted = is_enabled();
+        if counted {
+            TOTALS.open.fetch_add(1, Relaxed);
+        }
+        SocketMetrics {
+            counted,
+            stream,
+            bytes_read: AtomicU64::new(0),
+            bytes_written: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+        }
+    }
+
+    /// Counts the outcome of a read, passing it on.
+    pub(crate) fn read<T>(&self, poll: Poll<io::Result<T>>) -> Poll<io::Result<T>>
+    where
+        T: Transferred,
+    {
+        self.record(poll, &self.bytes_read, &TOTALS.bytes_read)
+    }
+
+    /// Counts the outcome of a write, passing it on.
+    pub(crate) fn written<T>(&self, poll: Poll<io::Result<T>>) -> Poll<io::Result<T>>
+    where
+        T: Transferred,
+    {
+        self.record(poll, &self.bytes_written, &TOTALS.bytes_written)
+    }
+
+    /// Counts the outcome of an accept, passing it on.
+    pub(crate) fn accepted<T>(&self, poll: Poll<io::Result<T>>) -> Poll<io::Result<T>> {
+        if self.counted {
+            match &poll {
+                Poll::Ready(Ok(_)) => {
+                    TOTALS.accepted.fetch_add(1, Relaxed);
+                }
+                Poll::Ready(Err(_)) => self.error(),
+                Poll::Pending => {}
+            }
+        }
+        poll
+    }
+
+    fn record<T: Transferred>(
+        &self,
+        poll: Poll<io::Result<T>>,
+        local: &AtomicU64,
+        total: &AtomicU64,
+    ) -> Poll<io::Result<T>> {
+        if self.counted {
+            match &poll {
+                Poll::Ready(Ok(t)) => {
+                    let n = t.bytes() as u64;
+                    local.fetch_add(n, Relaxed);
+                    total.fetch_add(n, Relaxed);
+                }
+                Poll::Ready(Err(_)) => self.error(),
+                Poll::Pending => {}
+            }
+        }
+        poll
+    }
+
+    fn error(&self) {
+        self.errors.fetch_add(1, Relaxed);
+        TOTALS.errors.fetch_add(1, Relaxed);
+    }
+
+    pub(crate) fn stats(&self) -> SocketStats {
+        SocketStats {
+            bytes_read: self.bytes_read.load(Relaxed),
+            bytes_written: self.bytes_written.load(Relaxed),
+            errors: self.errors.load(Relaxed),
+        }
+    }
+}
+
+impl Drop for SocketMetrics {
+    fn drop(&mut self) {
+        if self.counted {
+            TOTALS.open.fetch_sub(1, Relaxed);
+            if self.stream {
+                TOTALS.closed.fetch_add(1, Relaxed);
+            }
+        }
+    }
+}
+
+/// The outcome of an operation moving bytes.
+pub(crate) trait Transferred {
+    fn bytes(&self) -> usize;
+}
+
+impl Transferred for usize {
+    fn bytes(&self) -> usize {
+        *self
+    }
+}
+
+/// A datagram received with its sender.
+impl<A> Transferred for (usize, A) {
+    fn bytes(&self) -> usize {
+        self.0
+    }
+}
+
+#[test]
+fn test_sockets_are_counted_once_enabled() {
+    set_enabled(false);
+    let uncounted = SocketMetrics::stream();
+    set_enabled(true);
+    let socket = SocketMetrics::stream();
+    let open = snapshot().open_sockets;
+    assert!(open >= 1);
+
+    let _ = uncounted.written(Poll::Ready(Ok(10)));
+    assert_eq!(uncounted.stats(), SocketStats::default());
+
+    let _ = socket.read(Poll::Ready(Ok((3, ()))));
+    let _ = socket.written::<usize>(Poll::Pending);
+    let _ = socket.written::<usize>(Poll::Ready(Err(io::ErrorKind::Other.into())));
+    let stats = socket.stats();
+    assert_eq!(
+        (stats.bytes_read, stats.bytes_written, stats.errors),
+        (3, 0, 1)
+    );
+}
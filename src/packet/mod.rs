@@ -0,0 +1,733 @@
+//! Link-layer packet sockets.
+//!
+//! A [`PacketSocket`] sends and receives whole frames (`AF_PACKET`) on one
+//! or all network interfaces, which is how capture tools and network
+//! probes see traffic below the IP layer. Opening one requires the
+//! `CAP_NET_RAW` capability.
+//!
+//! # Examples
+//!
+//! Capturing the IPv4 frames of the loopback interface:
+//!
+//! ```no_run
+//! use futures_net::packet::{PacketSocket, PacketType};
+//!
+//! # async fn run() -> std::io::Result<()> {
+//! const ETH_P_IP: u16 = 0x0800;
+//!
+//! let mut socket = PacketSocket::new(PacketType::Raw, ETH_P_IP)?;
+//! socket.bind_interface("lo")?;
+//!
+//! let mut frame = vec![0; 65536];
+//! loop {
+//!     let (n, addr) = socket.recv_from(&mut frame).await?;
+//!     println!("{} bytes on interface {}", n, addr.ifindex());
+//! }
+//! # }
+//! ```
+//!
+//! # Receive ring
+//!
+//! Capture workloads can skip the copy of every frame into a buffer with a
+//! ring shared with the kernel (`PACKET_RX_RING`, `TPACKET_V3`), set up
+//! with [`set_rx_ring`]. The kernel fills blocks of the ring with frames
+//! and hands them over as they are full or time out; each block is read in
+//! place with [`next_block`] and given back when the [`PacketBlock`] is
+//! dropped.
+//!
+//! ```no_run
+//! use futures_net::packet::{PacketSocket, PacketType, ETH_P_ALL};
+//! use std::time::Duration;
+//!
+//! # async fn run() -> std::io::Result<()> {
+//! let mut socket = PacketSocket::new(PacketType::Raw, ETH_P_ALL)?;
+//! socket.set_rx_ring(1 << 20, 64, Duration::from_millis(10))?;
+//! socket.bind_interface("eth0")?;
+//!
+//! loop {
+//!     let block = socket.next_block().await?;
+//!     for frame in block.frames() {
+//!         println!("{} bytes at {:?}", frame.len(), frame.timestamp());
+//!     }
+//! }
+//! # }
+//! ```
+//!
+//! [`PacketSocket`]: struct.PacketSocket.html
+//! [`set_rx_ring`]: struct.PacketSocket.html#method.set_rx_ring
+//! [`next_block`]: struct.PacketSocket.html#method.next_block
+//! [`PacketBlock`]: struct.PacketBlock.html
+
+use async_datagram::AsyncDatagram;
+use async_ready::{AsyncReadReady, AsyncWriteReady, TakeError};
+use futures_core::Future;
+use futures_util::ready;
+use std::convert::TryInto;
+use std::fmt;
+use std::io;
+use std::mem;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::pin::Pin;
+use std::ptr;
+use std::task::{Context, Poll};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::driver::sys;
+use crate::driver::PollEvented;
+
+/// Receives the frames of every protocol.
+pub const ETH_P_ALL: u16 = libc::ETH_P_ALL as u16;
+
+/// Whether a [`PacketSocket`] sees the link-layer header of the frames.
+///
+/// [`PacketSocket`]: struct.PacketSocket.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PacketType {
+    /// Frames are read and written with their link-layer header
+    /// (`SOCK_RAW`).
+    Raw,
+    /// The kernel strips the link-layer header of received frames and
+    /// builds it for sent ones from the address (`SOCK_DGRAM`).
+    Dgram,
+}
+
+/// An `AF_PACKET` socket.
+pub struct PacketSocket {
+    io: PollEvented<sys::net::PacketSocket>,
+}
+
+/// A block of the receive ring of a [`PacketSocket`], handed over by the
+/// kernel.
+///
+/// The frames of the block are read in place, and the block is given back
+/// to the kernel when dropped.
+///
+/// [`PacketSocket`]: struct.PacketSocket.html
+pub struct PacketBlock<'a> {
+    socket: &'a mut PacketSocket,
+}
+
+/// An iterator over the frames of a [`PacketBlock`].
+///
+/// [`PacketBlock`]: struct.PacketBlock.html
+#[derive(Debug)]
+pub struct Frames<'a> {
+    block: &'a [u8],
+    offset: usize,
+    remaining: u32,
+}
+
+/// A frame of a [`PacketBlock`].
+///
+/// [`PacketBlock`]: struct.PacketBlock.html
+#[derive(Debug, Clone, Copy)]
+pub struct Frame<'a> {
+    data: &'a [u8],
+    len: u32,
+    timestamp: Duration,
+    addr: PacketAddr,
+}
+
+/// The link-level address of a frame (`sockaddr_ll`).
+#[derive(Clone, Copy)]
+pub struct PacketAddr {
+    raw: libc::sockaddr_ll,
+}
+
+/// A classic BPF instruction, as attached with
+/// [`PacketSocket::attach_filter`].
+///
+/// Filters are usually generated, e.g. with `tcpdump -dd <expression>`,
+/// whose output lines map to `SockFilter::new(code, jt, jf, k)`.
+///
+/// [`PacketSocket::attach_filter`]: struct.PacketSocket.html#method.attach_filter
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SockFilter {
+    code: u16,
+    jt: u8,
+    jf: u8,
+    k: u32,
+}
+
+impl PacketSocket {
+    /// Opens a packet socket receiving the frames of `protocol`, an
+    /// ethertype such as `0x0800` for IPv4, or [`ETH_P_ALL`].
+    ///
+    /// The socket receives frames from every interface until it is bound
+    /// to one with [`bind_interface`].
+    ///
+    /// [`ETH_P_ALL`]: constant.ETH_P_ALL.html
+    /// [`bind_interface`]: #method.bind_interface
+    pub fn new(ty: PacketType, protocol: u16) -> io::Result<PacketSocket> {
+        let ty = match ty {
+            PacketType::Raw => libc::SOCK_RAW,
+            PacketType::Dgram => libc::SOCK_DGRAM,
+        };
+        let socket = sys::net::PacketSocket::new(ty, protocol)?;
+        Ok(PacketSocket {
+            io: PollEvented::new(socket),
+        })
+    }
+
+    /// Only receives the frames of the interface called `name`.
+    ///
+    /// Once bound, frames can be sent with [`send`] without building an
+    /// address.
+    ///
+    /// [`send`]: #method.send
+    pub fn bind_interface(&self, name: &str) -> io::Result<()> {
+        self.bind_index(sys::net::interface_index(name)?)
+    }
+
+    /// Only receives the frames of the interface with index `ifindex`.
+    pub fn bind_index(&self, ifindex: u32) -> io::Result<()> {
+        self.io.get_ref().bind(ifindex)
+    }
+
+    /// Puts the interface called `name` in promiscuous mode for as long as
+    /// the socket is open, or until disabled again.
+    pub fn set_promiscuous(&self, name: &str, on: bool) -> io::Result<()> {
+        let ifindex = sys::net::interface_index(name)?;
+        self.io.get_ref().set_promiscuous(ifindex, on)
+    }
+
+    /// Attaches a BPF program which decides in the kernel which frames the
+    /// socket receives, replacing any previous one.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use futures_net::packet::{PacketSocket, PacketType, SockFilter, ETH_P_ALL};
+    ///
+    /// # fn run() -> std::io::Result<()> {
+    /// let socket = PacketSocket::new(PacketType::Raw, ETH_P_ALL)?;
+    /// // `tcpdump -dd arp`
+    /// socket.attach_filter(&[
+    ///     SockFilter::new(0x28, 0, 0, 0x0000000c),
+    ///     SockFilter::new(0x15, 0, 1, 0x00000806),
+    ///     SockFilter::new(0x06, 0, 0, 0x00040000),
+    ///     SockFilter::new(0x06, 0, 0, 0x00000000),
+    /// ])?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn attach_filter(&self, filter: &[SockFilter]) -> io::Result<()> {
+        // Safety: `SockFilter` has the layout of `sock_filter`.
+        let filter = unsafe {
+            std::slice::from_raw_parts(
+                filter.as_ptr() as *const libc::sock_filter,
+                filter.len(),
+            )
+        };
+        self.io.get_ref().attach_filter(filter)
+    }
+
+    /// Removes the BPF program attached with [`attach_filter`].
+    ///
+    /// [`attach_filter`]: #method.attach_filter
+    pub fn detach_filter(&self) -> io::Result<()> {
+        self.io.get_ref().detach_filter()
+    }
+
+    /// Sends a frame to `target`.
+    pub fn send_to<'a, 'b>(
+        &'a mut self,
+        buf: &'b [u8],
+        target: &'b PacketAddr,
+    ) -> SendTo<'a, 'b> {
+        SendTo {
+            socket: self,
+            buf,
+            target,
+        }
+    }
+
+    /// Receives a frame, returning its length and where it came from.
+    pub fn recv_from<'a, 'b>(&'a mut self, buf: &'b mut [u8]) -> RecvFrom<'a, 'b> {
+        RecvFrom { socket: self, buf }
+    }
+
+    /// Sends a frame on the interface the socket is bound to.
+    pub fn poll_send(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        ready!(self.io.poll_write_ready(cx)?);
+
+        match self.io.get_ref().send(buf) {
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                Pin::new(&mut self.io).clear_write_ready(cx)?;
+                Poll::Pending
+            }
+            r => Poll::Ready(r),
+        }
+    }
+
+    /// Sends a frame on the interface the socket is bound to.
+    pub async fn send(&mut self, buf: &[u8]) -> io::Result<usize> {
+        futures_util::future::poll_fn(|cx| self.poll_send(cx, buf)).await
+    }
+
+    /// Sets up a receive ring of `block_count` blocks of `block_size`
+    /// bytes, which must be a multiple of the page size.
+    ///
+    /// Received frames go to the ring from then on, and are read with
+    /// [`next_block`] instead of [`recv_from`]. The kernel hands a block
+    /// over when it's full, or `retire_timeout` after it received its first
+    /// frame. A frame larger than a block is truncated.
+    ///
+    /// [`next_block`]: #method.next_block
+    /// [`recv_from`]: #method.recv_from
+    pub fn set_rx_ring(
+        &mut self,
+        block_size: u32,
+        block_count: u32,
+        retire_timeout: Duration,
+    ) -> io::Result<()> {
+        let timeout = retire_timeout.as_millis().clamp(1, u32::MAX as u128) as u32;
+        self.io
+            .get_mut()
+            .set_rx_ring(block_size, block_count, timeout)
+    }
+
+    /// Waits until the kernel hands over the next block of the receive
+    /// ring.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the socket has no receive ring, see [`set_rx_ring`].
+    ///
+    /// [`set_rx_ring`]: #method.set_rx_ring
+    pub fn poll_rx_block(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(Pin::new(&mut self.io).poll_read_ready(cx)?);
+        if self.io.get_ref().rx_block().is_some() {
+            return Poll::Ready(Ok(()));
+        }
+
+        Pin::new(&mut self.io).clear_read_ready(cx)?;
+        // The block may have been handed over since it was checked, and its
+        // readiness event cleared with the stale one.
+        if self.io.get_ref().rx_block().is_some() {
+            return Poll::Ready(Ok(()));
+        }
+        Poll::Pending
+    }
+
+    /// Waits for the next block of the receive ring.
+    ///
+    /// The socket can't be used while the block is borrowed, the block goes
+    /// back to the kernel once dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the socket has no receive ring, see [`set_rx_ring`].
+    ///
+    /// [`set_rx_ring`]: #method.set_rx_ring
+    pub async fn next_block(&mut self) -> io::Result<PacketBlock<'_>> {
+        futures_util::future::poll_fn(|cx| self.poll_rx_block(cx)).await?;
+        Ok(PacketBlock { socket: self })
+    }
+}
+
+impl PacketBlock<'_> {
+    fn raw(&self) -> &[u8] {
+        self.socket.io.get_ref().rx_block().unwrap()
+    }
+
+    /// Returns the number of frames in the block.
+    pub fn len(&self) -> usize {
+        read_u32(self.raw(), 12) as usize
+    }
+
+    /// Returns `true` if the block has no frames.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the sequence number of the block, which increases by one for
+    /// every block handed over.
+    pub fn seq_num(&self) -> u64 {
+        let raw = self.raw();
+        u64::from_ne_bytes(raw[24..32].try_into().unwrap())
+    }
+
+    /// Returns an iterator over the frames of the block.
+    pub fn frames(&self) -> Frames<'_> {
+        let raw = self.raw();
+        Frames {
+            block: raw,
+            offset: read_u32(raw, 16) as usize,
+            remaining: read_u32(raw, 12),
+        }
+    }
+}
+
+impl Drop for PacketBlock<'_> {
+    fn drop(&mut self) {
+        self.socket.io.get_mut().release_rx_block();
+    }
+}
+
+impl fmt::Debug for PacketBlock<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PacketBlock")
+            .field("seq_num", &self.seq_num())
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+impl<'a> Iterator for Frames<'a> {
+    type Item = Frame<'a>;
+
+    fn next(&mut self) -> Option<Frame<'a>> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+
+        // A `struct tpacket3_hdr`, followed by the address of the frame.
+        let hdr = &self.block[self.offset..];
+        let next_offset = read_u32(hdr, 0) as usize;
+        let timestamp = Duration::new(read_u32(hdr, 4).into(), read_u32(hdr, 8));
+        let snaplen = read_u32(hdr, 12) as usize;
+        let len = read_u32(hdr, 16);
+        let mac = u16::from_ne_bytes([hdr[24], hdr[25]]) as usize;
+        let addr = &hdr
+            [TPACKET3_HDR_LEN..TPACKET3_HDR_LEN + mem::size_of::<libc::sockaddr_ll>()];
+        // Safety: the kernel writes a `sockaddr_ll` there.
+        let raw =
+            unsafe { ptr::read_unaligned(addr.as_ptr() as *const libc::sockaddr_ll) };
+
+        self.offset += next_offset;
+        Some(Frame {
+            data: &hdr[mac..mac + snaplen],
+            len,
+            timestamp,
+            addr: PacketAddr { raw },
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining as usize, Some(self.remaining as usize))
+    }
+}
+
+impl ExactSizeIterator for Frames<'_> {}
+
+impl<'a> Frame<'a> {
+    /// Returns the bytes of the frame that were captured.
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Returns the length of the frame on the wire, which is larger than
+    /// the captured bytes if it was truncated.
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    /// Returns `true` if the frame is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns when the frame was received.
+    pub fn timestamp(&self) -> SystemTime {
+        UNIX_EPOCH + self.timestamp
+    }
+
+    /// Returns where the frame came from.
+    pub fn addr(&self) -> &PacketAddr {
+        &self.addr
+    }
+}
+
+/// Length of a `struct tpacket3_hdr` with its alignment padding.
+const TPACKET3_HDR_LEN: usize = 48;
+
+fn read_u32(buf: &[u8], offset: usize) -> u32 {
+    u32::from_ne_bytes(buf[offset..offset + 4].try_into().unwrap())
+}
+
+impl AsyncDatagram for PacketSocket {
+    type Sender = PacketAddr;
+    type Receiver = PacketAddr;
+    type Err = io::Error;
+
+    fn poll_send_to(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+        receiver: &Self::Receiver,
+    ) -> Poll<io::Result<usize>> {
+        ready!(self.io.poll_write_ready(cx)?);
+
+        match self.io.get_ref().send_to(buf, &receiver.raw) {
+            Ok(n) => Poll::Ready(Ok(n)),
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                Pin::new(&mut self.io).clear_write_ready(cx)?;
+                Poll::Pending
+            }
+            Err(e) => Poll::Ready(Err(e)),
+        }
+    }
+
+    fn poll_recv_from(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<(usize, Self::Sender)>> {
+        ready!(Pin::new(&mut self.io).poll_read_ready(cx)?);
+
+        match self.io.get_ref().recv_from(buf) {
+            Ok((n, raw)) => Poll::Ready(Ok((n, PacketAddr { raw }))),
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                Pin::new(&mut self.io).clear_read_ready(cx)?;
+                Poll::Pending
+            }
+            Err(e) => Poll::Ready(Err(e)),
+        }
+    }
+}
+
+impl AsyncReadReady for PacketSocket {
+    type Ok = sys::event::Ready;
+    type Err = io::Error;
+
+    /// Check the socket's read readiness state.
+    fn poll_read_ready(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Self::Ok, Self::Err>> {
+        Pin::new(&mut self.io).poll_read_ready(cx)
+    }
+}
+
+impl AsyncWriteReady for PacketSocket {
+    type Ok = sys::event::Ready;
+    type Err = io::Error;
+
+    /// Check the socket's write readiness state.
+    fn poll_write_ready(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Self::Ok, Self::Err>> {
+        self.io.poll_write_ready(cx)
+    }
+}
+
+impl TakeError for PacketSocket {
+    type Ok = io::Error;
+    type Err = io::Error;
+
+    /// Returns the value of the `SO_ERROR` option.
+    fn take_error(&self) -> Result<Option<Self::Ok>, Self::Err> {
+        self.io.get_ref().take_error()
+    }
+}
+
+impl fmt::Debug for PacketSocket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.io.get_ref().fmt(f)
+    }
+}
+
+impl AsRawFd for PacketSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.io.get_ref().as_raw_fd()
+    }
+}
+
+impl PacketAddr {
+    /// Returns the address used to send frames of `protocol` to the
+    /// hardware address `addr` on the interface with index `ifindex`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `addr` is longer than 8 bytes.
+    pub fn new(ifindex: u32, protocol: u16, addr: &[u8]) -> PacketAddr {
+        assert!(addr.len() <= 8, "hardware address longer than 8 bytes");
+        let mut raw: libc::sockaddr_ll = unsafe { mem::zeroed() };
+        raw.sll_family = libc::AF_PACKET as u16;
+        raw.sll_protocol = protocol.to_be();
+        raw.sll_ifindex = ifindex as i32;
+        raw.sll_halen = addr.len() as u8;
+        raw.sll_addr[..addr.len()].copy_from_slice(addr);
+        PacketAddr { raw }
+    }
+
+    /// Returns the index of the interface.
+    pub fn ifindex(&self) -> u32 {
+        self.raw.sll_ifindex as u32
+    }
+
+    /// Returns the ethertype of the frame, in host byte order.
+    pub fn protocol(&self) -> u16 {
+        u16::from_be(self.raw.sll_protocol)
+    }
+
+    /// Returns the ARP hardware type of the interface, e.g. `1` for
+    /// Ethernet.
+    pub fn hatype(&self) -> u16 {
+        self.raw.sll_hatype
+    }
+
+    /// Returns the type of the frame: `PACKET_HOST`, `PACKET_BROADCAST`,
+    /// `PACKET_OUTGOING`...
+    pub fn pkttype(&self) -> u8 {
+        self.raw.sll_pkttype
+    }
+
+    /// Returns the hardware address of the sender, or of the receiver for
+    /// outgoing frames.
+    pub fn addr(&self) -> &[u8] {
+        let len = (self.raw.sll_halen as usize).min(self.raw.sll_addr.len());
+        &self.raw.sll_addr[..len]
+    }
+}
+
+impl fmt::Debug for PacketAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PacketAddr")
+            .field("ifindex", &self.ifindex())
+            .field("protocol", &self.protocol())
+            .field("hatype", &self.hatype())
+            .field("pkttype", &self.pkttype())
+            .field("addr", &self.addr())
+            .finish()
+    }
+}
+
+impl SockFilter {
+    /// Creates an instruction from its opcode, jump offsets and operand.
+    pub const fn new(code: u16, jt: u8, jf: u8, k: u32) -> SockFilter {
+        SockFilter { code, jt, jf, k }
+    }
+}
+
+/// The future returned by `PacketSocket::send_to`.
+#[derive(Debug)]
+pub struct SendTo<'a, 'b> {
+    socket: &'a mut PacketSocket,
+    buf: &'b [u8],
+    target: &'b PacketAddr,
+}
+
+impl<'a, 'b> Future for SendTo<'a, 'b> {
+    type Output = io::Result<usize>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let SendTo {
+            socket,
+            buf,
+            target,
+        } = &mut *self;
+        Pin::new(&mut **socket).poll_send_to(cx, buf, target)
+    }
+}
+
+/// The future returned by `PacketSocket::recv_from`.
+#[derive(Debug)]
+pub struct RecvFrom<'a, 'b> {
+    socket: &'a mut PacketSocket,
+    buf: &'b mut [u8],
+}
+
+impl<'a, 'b> Future for RecvFrom<'a, 'b> {
+    type Output = io::Result<(usize, PacketAddr)>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let RecvFrom { socket, buf } = &mut *self;
+        Pin::new(&mut **socket).poll_recv_from(cx, buf)
+    }
+}
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_filtered_capture_on_loopback() {
+    use crate::runtime::{self, Runtime};
+    use std::net::UdpSocket;
+
+    const ETH_P_IP: u16 = 0x0800;
+
+    let mut socket = match PacketSocket::new(PacketType::Dgram, ETH_P_IP) {
+        Ok(socket) => socket,
+        // Needs CAP_NET_RAW.
+        Err(ref e) if e.kind() == io::ErrorKind::PermissionDenied => return,
+        Err(e) => panic!("{}", e),
+    };
+    socket.bind_interface("lo").unwrap();
+    // Only accept UDP: `ldb [9]` loads the protocol byte of the IPv4 header,
+    // which starts the frames of a `SOCK_DGRAM` socket.
+    socket
+        .attach_filter(&[
+            SockFilter::new(0x30, 0, 0, 9),
+            SockFilter::new(0x15, 0, 1, 17),
+            SockFilter::new(0x06, 0, 0, 0xffff),
+            SockFilter::new(0x06, 0, 0, 0),
+        ])
+        .unwrap();
+
+    let rx = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let tx = UdpSocket::bind("127.0.0.1:0").unwrap();
+    tx.send_to(b"probe", rx.local_addr().unwrap()).unwrap();
+
+    let mut rt = runtime::default();
+    let mut frame = [0; 2048];
+    let (n, addr) = rt.exec(socket.recv_from(&mut frame)).unwrap();
+    assert_eq!(addr.protocol(), ETH_P_IP);
+    assert_eq!(frame[9], 17);
+    assert!(frame[..n].ends_with(b"probe"));
+}
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_rx_ring_hands_over_blocks() {
+    use crate::runtime::{self, Runtime};
+    use std::net::UdpSocket;
+
+    const ETH_P_IP: u16 = 0x0800;
+
+    let mut socket = match PacketSocket::new(PacketType::Dgram, ETH_P_IP) {
+        Ok(socket) => socket,
+        // Needs CAP_NET_RAW.
+        Err(ref e) if e.kind() == io::ErrorKind::PermissionDenied => return,
+        Err(e) => panic!("{}", e),
+    };
+    socket
+        .set_rx_ring(1 << 16, 4, Duration::from_millis(5))
+        .unwrap();
+    socket.bind_interface("lo").unwrap();
+
+    let rx = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let tx = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let port = rx.local_addr().unwrap().port().to_be_bytes();
+
+    // Other traffic on the loopback interface may come first: go through
+    // the blocks until one holds the datagram, which checks that released
+    // blocks are handed over again.
+    let mut rt = runtime::default();
+    rt.exec(async {
+        for _ in 0..16 {
+            tx.send_to(b"probe", rx.local_addr().unwrap()).unwrap();
+            let block = socket.next_block().await.unwrap();
+            assert_eq!(block.frames().len(), block.len());
+            let found = block.frames().any(|frame| {
+                let data = frame.data();
+                data.len() >= 28
+                    && data[9] == 17
+                    && data[22..24] == port
+                    && data.ends_with(b"probe")
+                    && frame.len() == data.len()
+                    && frame.addr().protocol() == ETH_P_IP
+            });
+            if found {
+                return;
+            }
+        }
+        panic!("the datagram never showed up in the ring");
+    });
+}
@@ -0,0 +1,301 @@
+use futures_core::Future;
+use futures_io::{AsyncRead, AsyncWrite};
+use futures_util::io::AsyncReadExt;
+use futures_util::{future, ready};
+use std::fmt;
+use std::io;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::pin::Pin;
+use std::process::{self, ExitStatus, Output};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use crate::driver::sys;
+use crate::driver::PollEvented;
+use crate::time::{sleep, Sleep};
+
+/// How often the exit of a child is checked without a `pidfd`.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// A spawned child process.
+///
+/// The pipes of the child are taken out of the public fields, like with
+/// `std::process::Child`.
+pub struct Child {
+    /// The standard input of the child, if piped. Dropping it closes the
+    /// pipe.
+    pub stdin: Option<ChildStdin>,
+    /// The standard output of the child, if piped.
+    pub stdout: Option<ChildStdout>,
+    /// The standard error of the child, if piped.
+    pub stderr: Option<ChildStderr>,
+    inner: process::Child,
+    exit: Exit,
+    status: Option<ExitStatus>,
+    kill_on_drop: bool,
+}
+
+enum Exit {
+    PidFd(PollEvented<sys::process::PidFd>),
+    Poll(Option<Sleep>),
+}
+
+macro_rules! pipe {
+    ($(#[$attr:meta])* $name:ident) => {
+        $(#[$attr])*
+        pub struct $name {
+            io: PollEvented<sys::Io>,
+        }
+
+        impl $name {
+            fn new<T: std::os::unix::io::IntoRawFd>(end: T) -> io::Result<$name> {
+                Ok($name {
+                    io: PollEvented::new(sys::process::pipe_end(end)?),
+                })
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.io.get_ref().fmt(f)
+            }
+        }
+
+        impl AsRawFd for $name {
+            fn as_raw_fd(&self) -> RawFd {
+                self.io.get_ref().as_raw_fd()
+            }
+        }
+    };
+}
+
+pipe!(
+    /// The standard input of a child.
+    ChildStdin
+);
+pipe!(
+    /// The standard output of a child.
+    ChildStdout
+);
+pipe!(
+    /// The standard error of a child.
+    ChildStderr
+);
+
+impl Child {
+    pub(super) fn new(
+        mut inner: process::Child,
+        kill_on_drop: bool,
+    ) -> io::Result<Child> {
+        let stdin = inner.stdin.take().map(ChildStdin::new).transpose()?;
+        let stdout = inner.stdout.take().map(ChildStdout::new).transpose()?;
+        let stderr = inner.stderr.take().map(ChildStderr::new).transpose()?;
+        let exit = match sys::process::PidFd::open(inner.id()) {
+            Ok(fd) => Exit::PidFd(PollEvented::new(fd)),
+            Err(ref e) if e.raw_os_error() == Some(libc::ENOSYS) => Exit::Poll(None),
+            Err(e) => return Err(e),
+        };
+        Ok(Child {
+            stdin,
+            stdout,
+            stderr,
+            inner,
+            exit,
+            status: None,
+            kill_on_drop,
+        })
+    }
+
+    /// Returns the process id of the child.
+    pub fn id(&self) -> u32 {
+        self.inner.id()
+    }
+
+    /// Kills the child with `SIGKILL`. Waiting is still needed to reap it.
+    pub fn kill(&mut self) -> io::Result<()> {
+        if self.status.is_some() {
+            return Ok(());
+        }
+        self.inner.kill()
+    }
+
+    /// Returns the exit status of the child if it exited, without waiting.
+    pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        if self.status.is_none() {
+            self.status = self.inner.try_wait()?;
+        }
+        Ok(self.status)
+    }
+
+    /// Waits for the child to exit, see [`wait`].
+    ///
+    /// [`wait`]: #method.wait
+    pub fn poll_wait(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<ExitStatus>> {
+        loop {
+            if let Some(status) = self.try_wait()? {
+                return Poll::Ready(Ok(status));
+            }
+            match &mut self.exit {
+                Exit::PidFd(fd) => {
+                    // The pidfd stays readable once the child exited, so
+                    // readiness only has to be cleared before it did.
+                    ready!(Pin::new(&mut *fd).poll_read_ready(cx)?);
+                    if let Some(status) = self.try_wait()? {
+                        return Poll::Ready(Ok(status));
+                    }
+                    if let Exit::PidFd(fd) = &mut self.exit {
+                        Pin::new(fd).clear_read_ready(cx)?;
+                    }
+                    return Poll::Pending;
+                }
+                Exit::Poll(timer) => {
+                    let timer = timer.get_or_insert_with(|| sleep(POLL_INTERVAL));
+                    ready!(Pin::new(timer).poll(cx));
+                    self.exit = Exit::Poll(None);
+                }
+            }
+        }
+    }
+
+    /// Waits for the child to exit, returning its exit status.
+    ///
+    /// The standard input of the child is closed first, so a child reading
+    /// it until the end doesn't wait forever.
+    pub async fn wait(&mut self) -> io::Result<ExitStatus> {
+        drop(self.stdin.take());
+        future::poll_fn(|cx| self.poll_wait(cx)).await
+    }
+
+    /// Waits for the child to exit, collecting the rest of its standard
+    /// output and error if they are piped.
+    pub async fn wait_with_output(mut self) -> io::Result<Output> {
+        drop(self.stdin.take());
+        let mut stdout = self.stdout.take();
+        let mut stderr = self.stderr.take();
+
+        async fn read_all<R: AsyncRead + Unpin>(
+            pipe: Option<&mut R>,
+        ) -> io::Result<Vec<u8>> {
+            let mut buf = Vec::new();
+            if let Some(pipe) = pipe {
+                pipe.read_to_end(&mut buf).await?;
+            }
+            Ok(buf)
+        }
+
+        let (stdout, stderr, status) = future::try_join3(
+            read_all(stdout.as_mut()),
+            read_all(stderr.as_mut()),
+            self.wait(),
+        )
+        .await?;
+        Ok(Output {
+            status,
+            stdout,
+            stderr,
+        })
+    }
+}
+
+impl Drop for Child {
+    fn drop(&mut self) {
+        if self.kill_on_drop && self.status.is_none() {
+            let _ = self.inner.kill();
+            // Reap it if it is already gone, it stays a zombie otherwise.
+            let _ = self.inner.try_wait();
+        }
+    }
+}
+
+impl fmt::Debug for Child {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Child")
+            .field("id", &self.id())
+            .field("stdin", &self.stdin)
+            .field("stdout", &self.stdout)
+            .field("stderr", &self.stderr)
+            .field("status", &self.status)
+            .finish()
+    }
+}
+
+impl AsyncWrite for ChildStdin {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.io).poll_write(cx, buf)
+    }
+
+    fn poll_flush(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_flush(cx)
+    }
+
+    fn poll_close(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_close(cx)
+    }
+}
+
+impl AsyncRead for ChildStdout {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.io).poll_read(cx, buf)
+    }
+}
+
+impl AsyncRead for ChildStderr {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.io).poll_read(cx, buf)
+    }
+}
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_piped_child() {
+    use crate::runtime::{self, Runtime};
+    use futures_util::io::AsyncWriteExt;
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::Stdio;
+
+    let mut rt = runtime::default();
+    rt.exec(async {
+        let mut child = super::Command::new("sh")
+            .args(&["-c", "read line; echo \"got $line\"; echo oops >&2; exit 3"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        child
+            .stdin
+            .as_mut()
+            .unwrap()
+            .write_all(b"ping\n")
+            .await
+            .unwrap();
+        let output = child.wait_with_output().await.unwrap();
+        assert_eq!(output.status.code(), Some(3));
+        assert_eq!(output.stdout, b"got ping\n");
+        assert_eq!(output.stderr, b"oops\n");
+
+        let mut child = super::Command::new("sleep").arg("10").spawn().unwrap();
+        child.kill().unwrap();
+        let status = child.wait().await.unwrap();
+        assert_eq!(status.signal(), Some(libc::SIGKILL));
+    });
+}
@@ -0,0 +1,173 @@
+//! Async child processes.
+//!
+//! [`Command`] mirrors `std::process::Command`, except that the pipes of
+//! the spawned [`Child`] implement `AsyncRead` and `AsyncWrite`, and that
+//! waiting for it to exit is a future. Exits are observed through a
+//! `pidfd` registered with the reactor, on Linux 5.3 and later, and by
+//! checking periodically on older kernels.
+//!
+//! # Examples
+//!
+//! Streaming the output of a child line by line:
+//!
+//! ```no_run
+//! use futures::prelude::*;
+//! use futures_net::process::Command;
+//! use std::process::Stdio;
+//!
+//! # async fn run() -> std::io::Result<()> {
+//! let mut child = Command::new("journalctl")
+//!     .arg("--follow")
+//!     .stdout(Stdio::piped())
+//!     .spawn()?;
+//!
+//! let stdout = child.stdout.take().unwrap();
+//! let mut lines = futures::io::BufReader::new(stdout).lines();
+//! while let Some(line) = lines.next().await {
+//!     println!("{}", line?);
+//! }
+//! let status = child.wait().await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`Command`]: struct.Command.html
+//! [`Child`]: struct.Child.html
+
+use std::ffi::OsStr;
+use std::io;
+use std::path::Path;
+use std::process::{self, ExitStatus, Output, Stdio};
+
+mod child;
+
+pub use self::child::{Child, ChildStderr, ChildStdin, ChildStdout};
+
+/// A process builder, see `std::process::Command`.
+#[derive(Debug)]
+pub struct Command {
+    inner: process::Command,
+    kill_on_drop: bool,
+}
+
+impl Command {
+    /// Creates a builder for running the program at `program`.
+    pub fn new<S: AsRef<OsStr>>(program: S) -> Command {
+        Command::from(process::Command::new(program))
+    }
+
+    /// Adds an argument.
+    pub fn arg<S: AsRef<OsStr>>(&mut self, arg: S) -> &mut Command {
+        self.inner.arg(arg);
+        self
+    }
+
+    /// Adds several arguments.
+    pub fn args<I, S>(&mut self, args: I) -> &mut Command
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        self.inner.args(args);
+        self
+    }
+
+    /// Sets an environment variable.
+    pub fn env<K, V>(&mut self, key: K, val: V) -> &mut Command
+    where
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        self.inner.env(key, val);
+        self
+    }
+
+    /// Sets several environment variables.
+    pub fn envs<I, K, V>(&mut self, vars: I) -> &mut Command
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        self.inner.envs(vars);
+        self
+    }
+
+    /// Removes an environment variable.
+    pub fn env_remove<K: AsRef<OsStr>>(&mut self, key: K) -> &mut Command {
+        self.inner.env_remove(key);
+        self
+    }
+
+    /// Clears the environment of the child.
+    pub fn env_clear(&mut self) -> &mut Command {
+        self.inner.env_clear();
+        self
+    }
+
+    /// Sets the working directory of the child.
+    pub fn current_dir<P: AsRef<Path>>(&mut self, dir: P) -> &mut Command {
+        self.inner.current_dir(dir);
+        self
+    }
+
+    /// Configures the standard input of the child.
+    pub fn stdin<T: Into<Stdio>>(&mut self, cfg: T) -> &mut Command {
+        self.inner.stdin(cfg);
+        self
+    }
+
+    /// Configures the standard output of the child.
+    pub fn stdout<T: Into<Stdio>>(&mut self, cfg: T) -> &mut Command {
+        self.inner.stdout(cfg);
+        self
+    }
+
+    /// Configures the standard error of the child.
+    pub fn stderr<T: Into<Stdio>>(&mut self, cfg: T) -> &mut Command {
+        self.inner.stderr(cfg);
+        self
+    }
+
+    /// Kills the child when its [`Child`] handle is dropped before it
+    /// exited. Off by default, like in `std`.
+    ///
+    /// [`Child`]: struct.Child.html
+    pub fn kill_on_drop(&mut self, kill: bool) -> &mut Command {
+        self.kill_on_drop = kill;
+        self
+    }
+
+    /// Returns the underlying `std` builder, for the options this type
+    /// doesn't forward, such as those of `std::os::unix::process::CommandExt`.
+    pub fn as_std_mut(&mut self) -> &mut process::Command {
+        &mut self.inner
+    }
+
+    /// Spawns the child.
+    pub fn spawn(&mut self) -> io::Result<Child> {
+        let child = self.inner.spawn()?;
+        Child::new(child, self.kill_on_drop)
+    }
+
+    /// Runs the child to completion, returning its exit status.
+    pub async fn status(&mut self) -> io::Result<ExitStatus> {
+        self.spawn()?.wait().await
+    }
+
+    /// Runs the child to completion, capturing its standard output and
+    /// error.
+    pub async fn output(&mut self) -> io::Result<Output> {
+        self.stdout(Stdio::piped()).stderr(Stdio::piped());
+        self.spawn()?.wait_with_output().await
+    }
+}
+
+impl From<process::Command> for Command {
+    fn from(inner: process::Command) -> Command {
+        Command {
+            inner,
+            kill_on_drop: false,
+        }
+    }
+}
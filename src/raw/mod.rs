@@ -0,0 +1,340 @@
+//! Raw IP sockets.
+//!
+//! A [`RawSocket`] sends and receives the packets of a single IP protocol,
+//! such as OSPF (89) or a custom tunneling protocol, bypassing the
+//! transport layer. Opening one requires the `CAP_NET_RAW` capability.
+//!
+//! Received IPv4 packets always start with their IP header, IPv6 ones
+//! never do. Sent packets get a header from the kernel, unless
+//! [`set_header_included`] is enabled, in which case the caller builds it.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use futures_net::raw::{Domain, RawSocket};
+//!
+//! # async fn run() -> std::io::Result<()> {
+//! const IPPROTO_OSPF: i32 = 89;
+//!
+//! let mut socket = RawSocket::new(Domain::Ipv4, IPPROTO_OSPF)?;
+//! let mut packet = vec![0; 65536];
+//! loop {
+//!     let (n, from) = socket.recv_from(&mut packet).await?;
+//!     let header_len = usize::from(packet[0] & 0x0f) * 4;
+//!     println!("{} bytes of OSPF from {}", n - header_len, from);
+//! }
+//! # }
+//! ```
+//!
+//! [`RawSocket`]: struct.RawSocket.html
+//! [`set_header_included`]: struct.RawSocket.html#method.set_header_included
+
+use async_datagram::AsyncDatagram;
+use async_ready::{AsyncReadReady, AsyncWriteReady, TakeError};
+use futures_core::Future;
+use futures_util::ready;
+use std::fmt;
+use std::io;
+use std::net::IpAddr;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use crate::driver::sys;
+use crate::driver::PollEvented;
+
+/// The IP version of a [`RawSocket`].
+///
+/// [`RawSocket`]: struct.RawSocket.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Domain {
+    /// IPv4 (`AF_INET`).
+    Ipv4,
+    /// IPv6 (`AF_INET6`).
+    Ipv6,
+}
+
+/// A raw IP socket.
+pub struct RawSocket {
+    io: PollEvented<sys::net::RawSocket>,
+}
+
+impl RawSocket {
+    /// Opens a socket sending and receiving the packets of the IP protocol
+    /// number `protocol`.
+    pub fn new(domain: Domain, protocol: i32) -> io::Result<RawSocket> {
+        let domain = match domain {
+            Domain::Ipv4 => libc::AF_INET,
+            Domain::Ipv6 => libc::AF_INET6,
+        };
+        let socket = sys::net::RawSocket::new(domain, protocol)?;
+        Ok(RawSocket {
+            io: PollEvented::new(socket),
+        })
+    }
+
+    /// Only receives the packets sent to `addr`, and uses it as the source
+    /// of the packets sent.
+    pub fn bind(&self, addr: IpAddr) -> io::Result<()> {
+        self.io.get_ref().bind(addr)
+    }
+
+    /// Only receives the packets from `addr`, and sends to it by default
+    /// with [`send`].
+    ///
+    /// [`send`]: #method.send
+    pub fn connect(&self, addr: IpAddr) -> io::Result<()> {
+        self.io.get_ref().connect(addr)
+    }
+
+    /// Returns the local address the socket is bound to.
+    pub fn local_addr(&self) -> io::Result<IpAddr> {
+        self.io.get_ref().local_addr()
+    }
+
+    /// Gets the value of the `IP_HDRINCL` option, or `IPV6_HDRINCL` for an
+    /// IPv6 socket.
+    pub fn header_included(&self) -> io::Result<bool> {
+        self.io.get_ref().header_included()
+    }
+
+    /// Sets the value of the `IP_HDRINCL` option, or `IPV6_HDRINCL` for an
+    /// IPv6 socket.
+    ///
+    /// When enabled, sent packets start with an IP header built by the
+    /// caller. For IPv4 the kernel still fills in the checksum, the total
+    /// length, and the identification if left at 0.
+    pub fn set_header_included(&self, on: bool) -> io::Result<()> {
+        self.io.get_ref().set_header_included(on)
+    }
+
+    /// Sends a packet to `target`.
+    pub fn send_to<'a, 'b>(
+        &'a mut self,
+        buf: &'b [u8],
+        target: &'b IpAddr,
+    ) -> SendTo<'a, 'b> {
+        SendTo {
+            socket: self,
+            buf,
+            target,
+        }
+    }
+
+    /// Receives a packet, returning its length and where it came from.
+    pub fn recv_from<'a, 'b>(&'a mut self, buf: &'b mut [u8]) -> RecvFrom<'a, 'b> {
+        RecvFrom { socket: self, buf }
+    }
+
+    /// Sends a packet to the address the socket is connected to.
+    pub fn poll_send(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        ready!(self.io.poll_write_ready(cx)?);
+
+        match self.io.get_ref().send(buf) {
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                Pin::new(&mut self.io).clear_write_ready(cx)?;
+                Poll::Pending
+            }
+            r => Poll::Ready(r),
+        }
+    }
+
+    /// Sends a packet to the address the socket is connected to.
+    pub async fn send(&mut self, buf: &[u8]) -> io::Result<usize> {
+        futures_util::future::poll_fn(|cx| self.poll_send(cx, buf)).await
+    }
+
+    /// Receives a packet from the address the socket is connected to.
+    pub fn poll_recv(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        ready!(Pin::new(&mut self.io).poll_read_ready(cx)?);
+
+        match self.io.get_ref().recv(buf) {
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                Pin::new(&mut self.io).clear_read_ready(cx)?;
+                Poll::Pending
+            }
+            r => Poll::Ready(r),
+        }
+    }
+
+    /// Receives a packet from the address the socket is connected to.
+    pub async fn recv(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        futures_util::future::poll_fn(|cx| self.poll_recv(cx, buf)).await
+    }
+}
+
+impl AsyncDatagram for RawSocket {
+    type Sender = IpAddr;
+    type Receiver = IpAddr;
+    type Err = io::Error;
+
+    fn poll_send_to(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+        receiver: &Self::Receiver,
+    ) -> Poll<io::Result<usize>> {
+        ready!(self.io.poll_write_ready(cx)?);
+
+        match self.io.get_ref().send_to(buf, *receiver) {
+            Ok(n) => Poll::Ready(Ok(n)),
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                Pin::new(&mut self.io).clear_write_ready(cx)?;
+                Poll::Pending
+            }
+            Err(e) => Poll::Ready(Err(e)),
+        }
+    }
+
+    fn poll_recv_from(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<(usize, Self::Sender)>> {
+        ready!(Pin::new(&mut self.io).poll_read_ready(cx)?);
+
+        match self.io.get_ref().recv_from(buf) {
+            Ok(r) => Poll::Ready(Ok(r)),
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                Pin::new(&mut self.io).clear_read_ready(cx)?;
+                Poll::Pending
+            }
+            Err(e) => Poll::Ready(Err(e)),
+        }
+    }
+}
+
+impl AsyncReadReady for RawSocket {
+    type Ok = sys::event::Ready;
+    type Err = io::Error;
+
+    /// Check the socket's read readiness state.
+    fn poll_read_ready(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Self::Ok, Self::Err>> {
+        Pin::new(&mut self.io).poll_read_ready(cx)
+    }
+}
+
+impl AsyncWriteReady for RawSocket {
+    type Ok = sys::event::Ready;
+    type Err = io::Error;
+
+    /// Check the socket's write readiness state.
+    fn poll_write_ready(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Self::Ok, Self::Err>> {
+        self.io.poll_write_ready(cx)
+    }
+}
+
+impl TakeError for RawSocket {
+    type Ok = io::Error;
+    type Err = io::Error;
+
+    /// Returns the value of the `SO_ERROR` option.
+    fn take_error(&self) -> Result<Option<Self::Ok>, Self::Err> {
+        self.io.get_ref().take_error()
+    }
+}
+
+impl fmt::Debug for RawSocket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.io.get_ref().fmt(f)
+    }
+}
+
+impl AsRawFd for RawSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.io.get_ref().as_raw_fd()
+    }
+}
+
+/// The future returned by `RawSocket::send_to`.
+#[derive(Debug)]
+pub struct SendTo<'a, 'b> {
+    socket: &'a mut RawSocket,
+    buf: &'b [u8],
+    target: &'b IpAddr,
+}
+
+impl<'a, 'b> Future for SendTo<'a, 'b> {
+    type Output = io::Result<usize>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let SendTo {
+            socket,
+            buf,
+            target,
+        } = &mut *self;
+        Pin::new(&mut **socket).poll_send_to(cx, buf, target)
+    }
+}
+
+/// The future returned by `RawSocket::recv_from`.
+#[derive(Debug)]
+pub struct RecvFrom<'a, 'b> {
+    socket: &'a mut RawSocket,
+    buf: &'b mut [u8],
+}
+
+impl<'a, 'b> Future for RecvFrom<'a, 'b> {
+    type Output = io::Result<(usize, IpAddr)>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let RecvFrom { socket, buf } = &mut *self;
+        Pin::new(&mut **socket).poll_recv_from(cx, buf)
+    }
+}
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_custom_protocol_with_header_included() {
+    use crate::runtime::{self, Runtime};
+
+    // Reserved for experimentation by RFC 3692.
+    const PROTOCOL: i32 = 253;
+
+    let mut socket = match RawSocket::new(Domain::Ipv4, PROTOCOL) {
+        Ok(socket) => socket,
+        // Needs CAP_NET_RAW.
+        Err(ref e) if e.kind() == io::ErrorKind::PermissionDenied => return,
+        Err(e) => panic!("{}", e),
+    };
+    let lo: IpAddr = "127.0.0.1".parse().unwrap();
+    socket.bind(lo).unwrap();
+
+    let mut rt = runtime::default();
+    rt.exec(async {
+        let mut buf = [0; 1500];
+
+        socket.send_to(b"kernel header", &lo).await.unwrap();
+        let (n, from) = socket.recv_from(&mut buf).await.unwrap();
+        assert_eq!(from, lo);
+        assert_eq!(buf[9] as i32, PROTOCOL);
+        assert_eq!(&buf[20..n], b"kernel header");
+
+        socket.set_header_included(true).unwrap();
+        assert!(socket.header_included().unwrap());
+        // The kernel fills in the total length, identification and
+        // checksum left at 0.
+        let mut packet = vec![0x45, 0, 0, 0, 0, 0, 0, 0];
+        packet.extend_from_slice(&[64, PROTOCOL as u8, 0, 0]);
+        packet.extend_from_slice(&[127, 0, 0, 1, 127, 0, 0, 1]);
+        packet.extend_from_slice(b"own header");
+        socket.send_to(&packet, &lo).await.unwrap();
+        let (n, _) = socket.recv_from(&mut buf).await.unwrap();
+        assert_eq!(buf[8], 64);
+        assert_eq!(&buf[20..n], b"own header");
+    });
+}
@@ -0,0 +1,69 @@
+//! CPU affinity of the runtime threads.
+
+use std::io;
+use std::mem;
+
+/// Returns the CPUs the process is allowed to run on, in increasing order.
+///
+/// This is the set to split between worker threads, or between one
+/// runtime per CPU each serving its own listener, see
+/// [`TcpListener::set_incoming_cpu`].
+///
+/// # Examples
+///
+/// ```
+/// use futures_net::runtime;
+///
+/// let cpus = runtime::available_cpus().unwrap();
+/// assert!(!cpus.is_empty());
+/// ```
+///
+/// [`TcpListener::set_incoming_cpu`]: ../struct.TcpListener.html#method.set_incoming_cpu
+pub fn available_cpus() -> io::Result<Vec<usize>> {
+    let mut set: libc::cpu_set_t = unsafe { mem::zeroed() };
+    let ret = unsafe { libc::sched_getaffinity(0, mem::size_of_val(&set), &mut set) };
+    if ret != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok((0..libc::CPU_SETSIZE as usize)
+        .filter(|&cpu| unsafe { libc::CPU_ISSET(cpu, &set) })
+        .collect())
+}
+
+/// Restricts the current thread to run on `cpu` only.
+///
+/// Worker threads are pinned with [`Builder::worker_cpus`], this is for
+/// threads driving their own runtime.
+///
+/// [`Builder::worker_cpus`]: struct.Builder.html#method.worker_cpus
+pub fn pin_current_thread(cpu: usize) -> io::Result<()> {
+    if cpu >= libc::CPU_SETSIZE as usize {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "CPU index out of range",
+        ));
+    }
+    let mut set: libc::cpu_set_t = unsafe { mem::zeroed() };
+    unsafe { libc::CPU_SET(cpu, &mut set) };
+    let ret = unsafe { libc::sched_setaffinity(0, mem::size_of_val(&set), &set) };
+    if ret != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[test]
+fn test_threads_are_pinned() {
+    let cpus = available_cpus().unwrap();
+    let cpu = *cpus.last().unwrap();
+
+    std::thread::spawn(move || {
+        pin_current_thread(cpu).unwrap();
+        assert_eq!(available_cpus().unwrap(), [cpu]);
+        assert_eq!(unsafe { libc::sched_getcpu() }, cpu as i32);
+    })
+    .join()
+    .unwrap();
+
+    assert!(pin_current_thread(libc::CPU_SETSIZE as usize).is_err());
+}
@@ -0,0 +1,264 @@
+//! Recycled storage for spawned futures.
+//!
+//! Every spawned future needs a heap allocation of its own, since the
+//! executors only know it as a `FutureObj`. Rather than a `Box` per spawn,
+//! the storage is taken from a few size classes and handed back when the
+//! task is dropped, so a server spawning a task per connection stops
+//! hitting the allocator for them once it is warm.
+//!
+//! Freed blocks go to a cache of the thread dropping the task, usually the
+//! worker which ran it. A thread whose cache overflows passes a magazine of
+//! blocks to a shared depot, and a thread whose cache runs dry takes one
+//! from there, so tasks spawned on one thread and completed on others keep
+//! being recycled. Futures larger than the largest class, or aligned more
+//! strictly than the blocks, are allocated on their own.
+
+use futures_core::future::Future;
+use futures_util::task::{FutureObj, LocalFutureObj, UnsafeFutureObj};
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use std::alloc::{self, Layout};
+use std::cell::RefCell;
+use std::marker::PhantomData;
+use std::mem;
+use std::ptr::{self, NonNull};
+
+/// The block sizes, from 64 bytes to 16 KiB.
+const CLASSES: [usize; 9] = [
+    64,
+    128,
+    256,
+    512,
+    1024,
+    2 * 1024,
+    4 * 1024,
+    8 * 1024,
+    16 * 1024,
+];
+
+/// Alignment of every block.
+const ALIGN: usize = 16;
+
+/// Blocks moved at once between a thread and the depot.
+const MAGAZINE: usize = 32;
+
+/// Bytes the depot keeps per class, the blocks beyond are deallocated.
+const MAX_DEPOT_BYTES: usize = 4 * 1024 * 1024;
+
+thread_local! {
+    static CACHE: RefCell<Cache> = RefCell::new(Cache::new());
+}
+
+lazy_static! {
+    static ref DEPOT: Vec<Mutex<Vec<Magazine>>> =
+        CLASSES.iter().map(|_| Mutex::new(Vec::new())).collect();
+}
+
+/// Free blocks of a thread, up to two magazines per class.
+struct Cache {
+    classes: Vec<Vec<NonNull<u8>>>,
+}
+
+/// Free blocks of one class, moved between threads.
+struct Magazine(Vec<NonNull<u8>>);
+
+// Safety: the blocks are unused memory owned by the magazine.
+unsafe impl Send for Magazine {}
+
+/// A future stored in a block, as handed to the executors.
+struct Slot<F> {
+    ptr: NonNull<F>,
+    _marker: PhantomData<F>,
+}
+
+// Safety: the slot owns its future.
+unsafe impl<F: Send> Send for Slot<F> {}
+
+/// Stores `fut` in a recycled block.
+pub(crate) fn future_obj<F>(fut: F) -> FutureObj<'static, ()>
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    FutureObj::new(Slot::new(fut))
+}
+
+/// Stores the `!Send` future `fut` in a recycled block.
+pub(crate) fn local_future_obj<F>(fut: F) -> LocalFutureObj<'static, ()>
+where
+    F: Future<Output = ()> + 'static,
+{
+    LocalFutureObj::new(Slot::new(fut))
+}
+
+impl<F> Slot<F> {
+    fn new(fut: F) -> Slot<F> {
+        let ptr = allocate(Layout::new::<F>()).cast::<F>();
+        // Safety: the block is large enough and aligned for `F`.
+        unsafe { ptr::write(ptr.as_ptr(), fut) };
+        Slot {
+            ptr,
+            _marker: PhantomData,
+        }
+    }
+}
+
+unsafe impl<F> UnsafeFutureObj<'static, ()> for Slot<F>
+where
+    F: Future<Output = ()> + 'static,
+{
+    fn into_raw(self) -> *mut (dyn Future<Output = ()> + 'static) {
+        let ptr = self.ptr.as_ptr();
+        mem::forget(self);
+        ptr
+    }
+
+    unsafe fn drop(ptr: *mut (dyn Future<Output = ()> + 'static)) {
+        let layout = Layout::for_value(&*ptr);
+        ptr::drop_in_place(ptr);
+        deallocate(NonNull::new_unchecked(ptr as *mut u8), layout);
+    }
+}
+
+impl<F> Drop for Slot<F> {
+    fn drop(&mut self) {
+        // Only reached if the slot never made it into a `FutureObj`.
+        unsafe {
+            ptr::drop_in_place(self.ptr.as_ptr());
+            deallocate(self.ptr.cast(), Layout::new::<F>());
+        }
+    }
+}
+
+/// Returns the index of the class serving `layout`.
+fn class_of(layout: Layout) -> Option<usize> {
+    if layout.align() > ALIGN {
+        return None;
+    }
+    CLASSES.iter().position(|&size| size >= layout.size())
+}
+
+fn block_layout(class: usize) -> Layout {
+    Layout::from_size_align(CLASSES[class], ALIGN).unwrap()
+}
+
+/// The layout of a block allocated on its own.
+fn own_layout(layout: Layout) -> Layout {
+    Layout::from_size_align(layout.size().max(1), layout.align()).unwrap()
+}
+
+fn allocate(layout: Layout) -> NonNull<u8> {
+    let (layout, class) = match class_of(layout) {
+        Some(class) => (block_layout(class), Some(class)),
+        None => (own_layout(layout), None),
+    };
+    let recycled = class.and_then(|class| {
+        CACHE
+            .try_with(|cache| cache.borrow_mut().pop(class))
+            .ok()
+            .flatten()
+    });
+    recycled.unwrap_or_else(|| {
+        // Safety: `layout` is never zero sized.
+        let ptr = unsafe { alloc::alloc(layout) };
+        NonNull::new(ptr).unwrap_or_else(|| alloc::handle_alloc_error(layout))
+    })
+}
+
+/// Gives back a block returned by `allocate(layout)`.
+///
+/// # Safety
+///
+/// The block must not be used afterwards.
+unsafe fn deallocate(ptr: NonNull<u8>, layout: Layout) {
+    let class = match class_of(layout) {
+        Some(class) => class,
+        None => return alloc::dealloc(ptr.as_ptr(), own_layout(layout)),
+    };
+    // The cache is gone when a task is dropped while its thread exits.
+    if CACHE
+        .try_with(|cache| cache.borrow_mut().push(class, ptr))
+        .is_err()
+    {
+        release(class, vec![ptr]);
+    }
+}
+
+/// Hands a magazine of blocks to the depot, or deallocates it if the depot
+/// is full.
+fn release(class: usize, blocks: Vec<NonNull<u8>>) {
+    let mut depot = DEPOT[class].lock();
+    if depot.len() < MAX_DEPOT_BYTES / (CLASSES[class] * MAGAZINE) {
+        depot.push(Magazine(blocks));
+        return;
+    }
+    drop(depot);
+    for ptr in blocks {
+        // Safety: the blocks of a class all have its layout.
+        unsafe { alloc::dealloc(ptr.as_ptr(), block_layout(class)) };
+    }
+}
+
+impl Cache {
+    fn new() -> Cache {
+        Cache {
+            classes: CLASSES.iter().map(|_| Vec::new()).collect(),
+        }
+    }
+
+    fn pop(&mut self, class: usize) -> Option<NonNull<u8>> {
+        let free = &mut self.classes[class];
+        if free.is_empty() {
+            if let Some(Magazine(blocks)) = DEPOT[class].lock().pop() {
+                *free = blocks;
+            }
+        }
+        free.pop()
+    }
+
+    fn push(&mut self, class: usize, ptr: NonNull<u8>) {
+        let free = &mut self.classes[class];
+        if free.len() >= 2 * MAGAZINE {
+            release(class, free.split_off(MAGAZINE));
+        }
+        free.push(ptr);
+    }
+}
+
+impl Drop for Cache {
+    fn drop(&mut self) {
+        for (class, blocks) in self.classes.drain(..).enumerate() {
+            if !blocks.is_empty() {
+                release(class, blocks);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_blocks_are_recycled() {
+    use std::rc::Rc;
+    use std::task::Context;
+
+    let layout = Layout::new::<[u64; 20]>();
+    let ptr = allocate(layout);
+    unsafe { deallocate(ptr, layout) };
+    // A smaller future of the same class gets the same block back.
+    let again = allocate(Layout::new::<[u64; 17]>());
+    assert_eq!(again, ptr);
+    unsafe { deallocate(again, Layout::new::<[u64; 17]>()) };
+
+    // The futures themselves are dropped with their task, pooled or not.
+    let alive = Rc::new(());
+    let (a, b) = (alive.clone(), alive.clone());
+    let mut small = local_future_obj(async move { drop(a) });
+    let buf = [1u8; 64 * 1024];
+    let large = local_future_obj(async move { drop((b, buf)) });
+    assert_eq!(Rc::strong_count(&alive), 3);
+
+    let waker = futures_util::task::noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    assert!(std::pin::Pin::new(&mut small).poll(&mut cx).is_ready());
+    assert_eq!(Rc::strong_count(&alive), 2);
+    drop((small, large));
+    assert_eq!(Rc::strong_count(&alive), 1);
+}
@@ -0,0 +1,468 @@
+//! Runtime configuration.
+
+use futures_executor::ThreadPool;
+use log::warn;
+use std::fmt;
+use std::io;
+use std::time::Duration;
+
+use super::affinity;
+use super::metrics::{self, Metrics};
+use super::DefaultRuntime;
+use std::sync::Arc;
+
+use crate::driver;
+
+/// Builds a [`DefaultRuntime`] with custom configuration values.
+///
+/// # Examples
+///
+/// ```
+/// use futures_net::runtime::{Builder, Runtime};
+///
+/// let mut rt = Builder::new_multi_thread()
+///     .worker_threads(2)
+///     .build()
+///     .unwrap();
+///
+/// let task = rt.handle().spawn(async { 40 + 2 });
+/// assert_eq!(rt.exec(task).unwrap(), 42);
+/// ```
+///
+/// The `main` attribute accepts the same settings:
+///
+/// ```
+/// #[futures_net::main(flavor = "multi_thread", worker_threads = 2)]
+/// async fn main() {
+///     let task = futures_net::spawn(async { std::thread::current().name().map(String::from) });
+///     let name = task.await.unwrap().unwrap();
+///     assert!(name.starts_with("futures-net-worker"));
+/// }
+/// ```
+///
+/// [`DefaultRuntime`]: struct.DefaultRuntime.html
+pub struct Builder {
+    flavor: Flavor,
+    worker_threads: Option<usize>,
+    worker_cpus: Option<Arc<[usize]>>,
+    thread_name: String,
+    on_thread_start: Option<Callback>,
+    on_thread_stop: Option<Callback>,
+    slow_poll_threshold: Option<Duration>,
+    start_paused: bool,
+    schedule_seed: Option<u64>,
+    /// The busy poll duration, and whether it adapts to the event rate.
+    busy_poll: Option<(Duration, bool)>,
+}
+
+type Callback = Arc<dyn Fn() + Send + Sync + 'static>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Flavor {
+    CurrentThread,
+    MultiThread,
+}
+
+impl Builder {
+    /// Returns a new builder for a runtime running every task on the thread
+    /// calling `exec`.
+    pub fn new_current_thread() -> Builder {
+        Builder::new(Flavor::CurrentThread)
+    }
+
+    /// Returns a new builder for a runtime running spawned tasks on a pool of
+    /// worker threads.
+    pub fn new_multi_thread() -> Builder {
+        Builder::new(Flavor::MultiThread)
+    }
+
+    fn new(flavor: Flavor) -> Builder {
+        Builder {
+            flavor,
+            worker_threads: None,
+            worker_cpus: None,
+            thread_name: "futures-net-worker".to_string(),
+            on_thread_start: None,
+            on_thread_stop: None,
+            slow_poll_threshold: None,
+            start_paused: false,
+            schedule_seed: None,
+            busy_poll: None,
+        }
+    }
+
+    /// Sets the number of worker threads of a `multi_thread` runtime.
+    ///
+    /// Defaults to the number of [`worker_cpus`] if set, to the number of
+    /// CPUs otherwise. Ignored by `current_thread` runtimes.
+    ///
+    /// [`worker_cpus`]: #method.worker_cpus
+    ///
+    /// # Panics
+    ///
+    /// Panics if `val` is zero.
+    pub fn worker_threads(&mut self, val: usize) -> &mut Self {
+        assert!(val > 0, "worker threads cannot be set to 0");
+        self.worker_threads = Some(val);
+        self
+    }
+
+    /// Pins the worker threads of a `multi_thread` runtime to `cpus`.
+    ///
+    /// Worker `i` only runs on the CPU `cpus[i % cpus.len()]`, so a worker
+    /// per CPU of a NUMA node, for instance, keeps the memory of its tasks
+    /// local. Building the runtime fails if the process isn't allowed to
+    /// run on one of the CPUs, see [`available_cpus`]. The reactor runs on
+    /// its own thread and is not pinned. Ignored by `current_thread`
+    /// runtimes, whose thread can be pinned with [`pin_current_thread`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures_net::runtime::{self, Builder, Runtime};
+    ///
+    /// let cpu = runtime::available_cpus().unwrap()[0];
+    /// let mut rt = Builder::new_multi_thread()
+    ///     .worker_cpus(vec![cpu])
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let task = rt.handle().spawn(async { runtime::available_cpus().unwrap() });
+    /// assert_eq!(rt.exec(task).unwrap(), [cpu]);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cpus` is empty.
+    ///
+    /// [`available_cpus`]: fn.available_cpus.html
+    /// [`pin_current_thread`]: fn.pin_current_thread.html
+    pub fn worker_cpus(&mut self, cpus: impl IntoIterator<Item = usize>) -> &mut Self {
+        let cpus: Arc<[usize]> = cpus.into_iter().collect();
+        assert!(!cpus.is_empty(), "worker CPUs cannot be empty");
+        self.worker_cpus = Some(cpus);
+        self
+    }
+
+    /// Sets the name prefix of the worker threads.
+    pub fn thread_name(&mut self, val: impl Into<String>) -> &mut Self {
+        self.thread_name = val.into();
+        self
+    }
+
+    /// Runs `f` on each worker thread when it starts, before it polls any
+    /// task.
+    ///
+    /// Only `multi_thread` runtimes own threads, the hook is not called by
+    /// `current_thread` runtimes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures_net::runtime::{Builder, Runtime};
+    /// use std::cell::Cell;
+    ///
+    /// thread_local!(static READY: Cell<bool> = Cell::new(false));
+    ///
+    /// let mut rt = Builder::new_multi_thread()
+    ///     .worker_threads(1)
+    ///     .on_thread_start(|| READY.with(|ready| ready.set(true)))
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let task = rt.handle().spawn(async { READY.with(Cell::get) });
+    /// assert!(rt.exec(task).unwrap());
+    /// ```
+    pub fn on_thread_start<F>(&mut self, f: F) -> &mut Self
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        self.on_thread_start = Some(Arc::new(f));
+        self
+    }
+
+    /// Runs `f` on each worker thread right before it exits.
+    ///
+    /// Like [`on_thread_start`], this only applies to `multi_thread`
+    /// runtimes.
+    ///
+    /// [`on_thread_start`]: #method.on_thread_start
+    pub fn on_thread_stop<F>(&mut self, f: F) -> &mut Self
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        self.on_thread_stop = Some(Arc::new(f));
+        self
+    }
+
+    /// Reports task polls which take longer than `threshold`.
+    ///
+    /// A task blocking its thread, with synchronous DNS resolution or file
+    /// I/O for instance, stalls every other task scheduled on that thread.
+    /// Once set, each poll reaching the threshold is logged as a warning
+    /// with the id and name of the task, and counted in
+    /// [`RuntimeMetrics::slow_poll_count`]. When backtraces are enabled
+    /// through `RUST_BACKTRACE`, the first warning of a task also shows
+    /// where it was spawned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures_net::runtime::{Builder, Runtime};
+    /// use std::thread;
+    /// use std::time::Duration;
+    ///
+    /// let mut rt = Builder::new_current_thread()
+    ///     .slow_poll_threshold(Duration::from_millis(10))
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let task = rt.handle().spawn(async {
+    ///     thread::sleep(Duration::from_millis(20));
+    /// });
+    /// rt.exec(task).unwrap();
+    /// assert_eq!(rt.metrics().slow_poll_count(), 1);
+    /// ```
+    ///
+    /// [`RuntimeMetrics::slow_poll_count`]: struct.RuntimeMetrics.html#method.slow_poll_count
+    pub fn slow_poll_threshold(&mut self, threshold: Duration) -> &mut Self {
+        self.slow_poll_threshold = Some(threshold);
+        self
+    }
+
+    /// Starts the runtime with time paused, see [`time::pause`].
+    ///
+    /// Only `current_thread` runtimes can pause time, building a
+    /// `multi_thread` one with this set fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures_net::runtime::{Builder, Runtime};
+    /// use futures_net::time;
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let mut rt = Builder::new_current_thread()
+    ///     .start_paused(true)
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let start = Instant::now();
+    /// rt.exec(time::sleep(Duration::from_secs(24 * 3600)));
+    /// assert!(start.elapsed() < Duration::from_secs(60));
+    /// ```
+    ///
+    /// [`time::pause`]: ../time/fn.pause.html
+    pub fn start_paused(&mut self, start_paused: bool) -> &mut Self {
+        self.start_paused = start_paused;
+        self
+    }
+
+    /// Schedules the local tasks in an order derived from `seed`.
+    ///
+    /// Instead of polling the woken tasks in the order they were woken, the
+    /// runtime picks the next one at random, with a generator started from
+    /// `seed`. Bugs depending on how tasks interleave show up with some
+    /// seeds and not others, and as long as the wakeups are the same, a run
+    /// with the seed a failure was found with replays the same
+    /// interleaving. Wakeups of I/O resources and of other threads come in
+    /// whenever they happen, so pairing this with [`start_paused`] and the
+    /// simulated network of the `sim` feature makes a run fully repeatable.
+    ///
+    /// Only `current_thread` runtimes can be seeded, building a
+    /// `multi_thread` one with this set fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::channel::mpsc;
+    /// use futures::prelude::*;
+    /// use futures_net::runtime::{Builder, Runtime, Spawner};
+    ///
+    /// fn run(seed: u64) -> Vec<u32> {
+    ///     let mut rt = Builder::new_current_thread()
+    ///         .schedule_seed(seed)
+    ///         .build()
+    ///         .unwrap();
+    ///     let (tx, rx) = mpsc::unbounded();
+    ///     for i in 0..8 {
+    ///         let tx = tx.clone();
+    ///         rt.spawner()
+    ///             .spawn_local_future(async move { tx.unbounded_send(i).unwrap() })
+    ///             .unwrap();
+    ///     }
+    ///     drop(tx);
+    ///     rt.exec(rx.collect())
+    /// }
+    ///
+    /// assert_eq!(run(7), run(7));
+    /// ```
+    ///
+    /// [`start_paused`]: #method.start_paused
+    pub fn schedule_seed(&mut self, seed: u64) -> &mut Self {
+        self.schedule_seed = Some(seed);
+        self
+    }
+
+    /// Makes the reactor spin for up to `spin` before blocking, see
+    /// [`driver::set_busy_poll`].
+    ///
+    /// Runtimes share the global reactor, so this is a process wide
+    /// setting: it is applied once the runtime is built successfully, and
+    /// stays in effect after the runtime is dropped.
+    ///
+    /// [`driver::set_busy_poll`]: ../driver/fn.set_busy_poll.html
+    pub fn busy_poll(&mut self, spin: Duration) -> &mut Self {
+        self.busy_poll = Some((spin, false));
+        self
+    }
+
+    /// Makes the reactor spin for up to `max` before blocking, for less or
+    /// not at all when events are rare, see
+    /// [`driver::set_busy_poll_adaptive`].
+    ///
+    /// Like [`busy_poll`], this is a process wide setting, applied once the
+    /// runtime is built successfully.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures_net::driver;
+    /// use futures_net::runtime::Builder;
+    /// use std::time::Duration;
+    ///
+    /// let rt = Builder::new_multi_thread()
+    ///     .adaptive_busy_poll(Duration::from_micros(50))
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(driver::busy_poll(), Some(Duration::from_micros(50)));
+    /// assert!(driver::busy_poll_adaptive());
+    /// # drop(rt);
+    /// ```
+    ///
+    /// [`driver::set_busy_poll_adaptive`]: ../driver/fn.set_busy_poll_adaptive.html
+    /// [`busy_poll`]: #method.busy_poll
+    pub fn adaptive_busy_poll(&mut self, max: Duration) -> &mut Self {
+        self.busy_poll = Some((max, true));
+        self
+    }
+
+    /// Creates the configured runtime.
+    pub fn build(&mut self) -> io::Result<DefaultRuntime> {
+        let rt = self.build_runtime()?;
+        if let Some((spin, adaptive)) = self.busy_poll {
+            driver::set_busy_poll(Some(spin));
+            driver::set_busy_poll_adaptive(adaptive);
+        }
+        Ok(rt)
+    }
+
+    fn build_runtime(&self) -> io::Result<DefaultRuntime> {
+        match self.flavor {
+            Flavor::CurrentThread => {
+                let metrics = Metrics::new(1, self.slow_poll_threshold);
+                Ok(DefaultRuntime::new(
+                    None,
+                    metrics,
+                    self.start_paused,
+                    self.schedule_seed,
+                ))
+            }
+            Flavor::MultiThread => {
+                if self.start_paused {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "time can only be paused in a `current_thread` runtime",
+                    ));
+                }
+                if self.schedule_seed.is_some() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "only a `current_thread` runtime can be seeded",
+                    ));
+                }
+                let size = self
+                    .worker_threads
+                    .or_else(|| self.worker_cpus.as_ref().map(|cpus| cpus.len()))
+                    .unwrap_or_else(num_cpus::get);
+                let metrics = Metrics::new(size, self.slow_poll_threshold);
+                let workers = self.build_workers(size, &metrics)?;
+                Ok(DefaultRuntime::new(Some(workers), metrics, false, None))
+            }
+        }
+    }
+
+    fn build_workers(
+        &self,
+        size: usize,
+        metrics: &Arc<Metrics>,
+    ) -> io::Result<ThreadPool> {
+        if let Some(cpus) = &self.worker_cpus {
+            let available = affinity::available_cpus()?;
+            if let Some(cpu) = cpus.iter().find(|cpu| !available.contains(cpu)) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("CPU {} is not available to the process", cpu),
+                ));
+            }
+        }
+
+        let metrics = metrics.clone();
+        let cpus = self.worker_cpus.clone();
+        let on_start = self.on_thread_start.clone();
+        let mut builder = ThreadPool::builder();
+        builder
+            .pool_size(size)
+            .name_prefix(format!("{}-", self.thread_name))
+            .after_start(move |index| {
+                if let Some(cpus) = &cpus {
+                    let cpu = cpus[index % cpus.len()];
+                    if let Err(e) = affinity::pin_current_thread(cpu) {
+                        warn!("failed to pin worker {} to CPU {}: {}", index, cpu, e);
+                    }
+                }
+                metrics::set_worker(&metrics, index);
+                if let Some(f) = &on_start {
+                    f();
+                }
+            });
+        if let Some(f) = self.on_thread_stop.clone() {
+            builder.before_stop(move |_| f());
+        }
+        builder.create()
+    }
+}
+
+impl fmt::Debug for Builder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Builder")
+            .field("flavor", &self.flavor)
+            .field("worker_threads", &self.worker_threads)
+            .field("worker_cpus", &self.worker_cpus)
+            .field("thread_name", &self.thread_name)
+            .field("start_paused", &self.start_paused)
+            .field("schedule_seed", &self.schedule_seed)
+            .field("busy_poll", &self.busy_poll)
+            .field(
+                "on_thread_start",
+                &self.on_thread_start.as_ref().map(|_| ".."),
+            )
+            .field(
+                "on_thread_stop",
+                &self.on_thread_stop.as_ref().map(|_| ".."),
+            )
+            .finish()
+    }
+}
+
+#[test]
+fn test_failed_build_leaves_busy_poll_alone() {
+    let before = (driver::busy_poll(), driver::busy_poll_adaptive());
+    let res = Builder::new_multi_thread()
+        .start_paused(true)
+        .adaptive_busy_poll(Duration::from_millis(3))
+        .build();
+    assert!(res.is_err());
+    assert_eq!((driver::busy_poll(), driver::busy_poll_adaptive()), before);
+}
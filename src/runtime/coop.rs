@@ -0,0 +1,119 @@
+//! Cooperative scheduling budget.
+//!
+//! Each time the runtime polls a task it hands the task a budget of I/O
+//! readiness checks. Every check reporting readiness consumes one unit, and
+//! once the budget is spent checks report `Pending` and reschedule the task,
+//! so a socket that is always ready can't keep its task from yielding.
+//!
+//! Futures polled outside of the runtime, e.g. by a foreign executor, are not
+//! budgeted.
+
+use futures_core::future::Future;
+use std::cell::Cell;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// Readiness checks a task may make per poll.
+const BUDGET: u8 = 128;
+
+thread_local! {
+    static CURRENT: Cell<Option<u8>> = Cell::new(None);
+}
+
+/// Runs `f` with a fresh budget, restoring the previous one afterwards.
+pub(crate) fn budget<R>(f: impl FnOnce() -> R) -> R {
+    struct Reset(Option<u8>);
+
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            CURRENT.with(|cell| cell.set(self.0));
+        }
+    }
+
+    let _reset = Reset(CURRENT.with(|cell| cell.replace(Some(BUDGET))));
+    f()
+}
+
+/// Consumes one unit of the budget.
+///
+/// Returns `Pending`, after scheduling the task to be polled again, if the
+/// budget is spent. Call [`Restore::made_progress`] once the operation turns
+/// out ready, otherwise the unit is given back.
+pub(crate) fn poll_proceed(cx: &mut Context<'_>) -> Poll<Restore> {
+    CURRENT.with(|cell| match cell.get() {
+        None => Poll::Ready(Restore(None)),
+        Some(0) => {
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+        Some(n) => {
+            cell.set(Some(n - 1));
+            Poll::Ready(Restore(Some(n)))
+        }
+    })
+}
+
+/// Gives a unit back to the budget unless progress was made.
+pub(crate) struct Restore(Option<u8>);
+
+impl Restore {
+    pub(crate) fn made_progress(&mut self) {
+        self.0 = None;
+    }
+}
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        if let Some(n) = self.0 {
+            CURRENT.with(|cell| cell.set(Some(n)));
+        }
+    }
+}
+
+/// Polls the wrapped future with a fresh budget each time.
+pub(crate) struct Budgeted<F> {
+    fut: F,
+}
+
+pub(crate) fn budgeted<F: Future>(fut: F) -> Budgeted<F> {
+    Budgeted { fut }
+}
+
+impl<F: Future> Future for Budgeted<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        // Safety: `fut` is never moved out of `self`.
+        let fut = unsafe { self.map_unchecked_mut(|this| &mut this.fut) };
+        budget(|| fut.poll(cx))
+    }
+}
+
+#[test]
+fn test_budget_exhaustion() {
+    use futures_util::task::noop_waker;
+
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+
+    assert!(poll_proceed(&mut cx).is_ready());
+
+    budget(|| {
+        for _ in 0..BUDGET {
+            match poll_proceed(&mut cx) {
+                Poll::Ready(mut restore) => restore.made_progress(),
+                Poll::Pending => panic!("budget spent too early"),
+            }
+        }
+        assert!(poll_proceed(&mut cx).is_pending());
+
+        // Checks that didn't make progress are free.
+        budget(|| {
+            for _ in 0..u32::from(BUDGET) * 2 {
+                drop(poll_proceed(&mut cx));
+            }
+            assert!(poll_proceed(&mut cx).is_ready());
+        });
+        assert!(poll_proceed(&mut cx).is_pending());
+    });
+}
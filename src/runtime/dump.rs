@@ -0,0 +1,173 @@
+//! Listing of the live tasks of a runtime.
+
+use std::fmt;
+use std::panic::Location;
+use std::time::Instant;
+
+use super::Handle;
+
+/// The live tasks of a runtime, returned by [`Handle::dump_tasks`].
+///
+/// Its `Display` implementation prints one line per task, meant for logs
+/// and admin endpoints.
+///
+/// [`Handle::dump_tasks`]: struct.Handle.html#method.dump_tasks
+#[derive(Debug, Clone)]
+pub struct TaskDump {
+    /// When the dump was taken.
+    pub taken: Instant,
+    /// The tasks which have been spawned and haven't completed or been
+    /// dropped yet, in spawn order.
+    pub tasks: Vec<TaskInfo>,
+}
+
+/// A live task of a runtime.
+#[derive(Debug, Clone)]
+pub struct TaskInfo {
+    /// The id of the task, unique within the process.
+    pub id: u64,
+    /// The name the task was spawned with, if any.
+    pub name: Option<String>,
+    /// What the task is doing.
+    pub state: TaskState,
+    /// The number of times the task has been polled.
+    pub polls: u64,
+    /// When the last poll of the task started, `None` if it was never
+    /// polled.
+    pub last_poll: Option<Instant>,
+    /// The call to `spawn` which spawned the task.
+    ///
+    /// Only recorded with the `tracing` feature, and only for tasks spawned
+    /// through the functions of the crate rather than through the `Spawn`
+    /// traits.
+    pub spawned_at: Option<&'static Location<'static>>,
+}
+
+/// What a task is doing, see [`TaskInfo`].
+///
+/// [`TaskInfo`]: struct.TaskInfo.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskState {
+    /// The task waits for a wake-up.
+    Idle,
+    /// The task has been woken up and waits to be polled.
+    Scheduled,
+    /// The task is being polled.
+    ///
+    /// A task staying in this state blocks its worker thread.
+    Running,
+}
+
+impl Handle {
+    /// Lists the live tasks of the runtime.
+    ///
+    /// A stuck service usually shows as tasks which are idle since long, or
+    /// running since long. The states are read one task at a time while the
+    /// runtime keeps going, so the dump is a close view rather than an exact
+    /// one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures_net::runtime::{self, Runtime, TaskState};
+    ///
+    /// let mut rt = runtime::default();
+    /// let handle = rt.handle().clone();
+    ///
+    /// let (tx, rx) = futures::channel::oneshot::channel::<()>();
+    /// let task = handle.spawn_named("waiter", rx);
+    /// rt.exec(futures_net::task::yield_now());
+    ///
+    /// let dump = handle.dump_tasks();
+    /// let waiter = &dump.tasks[0];
+    /// assert_eq!(waiter.name.as_deref(), Some("waiter"));
+    /// assert_eq!(waiter.state, TaskState::Idle);
+    /// println!("{}", dump);
+    ///
+    /// tx.send(()).unwrap();
+    /// rt.exec(task).unwrap().unwrap();
+    /// assert!(handle.dump_tasks().tasks.is_empty());
+    /// ```
+    pub fn dump_tasks(&self) -> TaskDump {
+        let mut tasks: Vec<_> = self
+            .raw_metrics()
+            .tasks()
+            .iter()
+            .map(|task| task.info())
+            .collect();
+        tasks.sort_by_key(|task| task.id);
+        TaskDump {
+            taken: Instant::now(),
+            tasks,
+        }
+    }
+}
+
+impl fmt::Display for TaskDump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} live tasks", self.tasks.len())?;
+        for task in &self.tasks {
+            write!(f, "  task #{}", task.id)?;
+            if let Some(name) = &task.name {
+                write!(f, " ({})", name)?;
+            }
+            let state = match task.state {
+                TaskState::Idle => "idle",
+                TaskState::Scheduled => "scheduled",
+                TaskState::Running => "running",
+            };
+            write!(f, " {}, {} polls", state, task.polls)?;
+            if let Some(at) = task.last_poll {
+                let ago = self.taken.saturating_duration_since(at);
+                write!(f, ", last polled {:?} ago", ago)?;
+            }
+            if let Some(location) = task.spawned_at {
+                write!(f, ", spawned at {}", location)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_dump_tracks_task_states() {
+    use super::{Builder, Runtime};
+    use futures_util::future::poll_fn;
+    use std::task::Poll;
+
+    let mut rt = Builder::new_current_thread().build().unwrap();
+    let handle = rt.handle().clone();
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let dumped = handle.clone();
+    let task = handle.spawn(async move {
+        // Dumping from within a task sees it running.
+        tx.send(dumped.dump_tasks()).unwrap();
+        let mut first = true;
+        poll_fn(|cx| {
+            if std::mem::replace(&mut first, false) {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            Poll::Ready(())
+        })
+        .await
+    });
+    let dump = handle.dump_tasks();
+    assert_eq!(dump.tasks.len(), 1);
+    assert_eq!(dump.tasks[0].state, TaskState::Scheduled);
+    assert!(dump.tasks[0].last_poll.is_none());
+
+    rt.exec(task).unwrap();
+    let running = rx.recv().unwrap();
+    assert_eq!(running.tasks[0].state, TaskState::Running);
+    assert!(running.to_string().contains("running, 1 polls"));
+    #[cfg(feature = "tracing")]
+    assert!(running.tasks[0]
+        .spawned_at
+        .unwrap()
+        .file()
+        .ends_with("dump.rs"));
+    assert!(handle.dump_tasks().tasks.is_empty());
+}
@@ -0,0 +1,28 @@
+//! Entry point used by `#[futures_net::main]`.
+
+use futures_core::future::Future;
+use std::io;
+use std::process;
+
+use super::Runtime;
+
+/// Runs the body of `main` on `rt`.
+///
+/// The output of `fut` is handed back to `main`, so whatever it returns is
+/// reported by its `Termination` impl, e.g. an `Err` is printed and the
+/// process exits with a failure code. Errors of the runtime itself are
+/// reported the same way instead of panicking.
+pub fn block_on_main<R, F>(rt: io::Result<R>, fut: F) -> F::Output
+where
+    R: Runtime,
+    F: Future,
+{
+    let mut rt = rt.unwrap_or_else(|e| exit("failed to start the runtime", &e));
+    rt.try_exec(fut)
+        .unwrap_or_else(|e| exit("the runtime failed", &e))
+}
+
+fn exit(context: &str, err: &io::Error) -> ! {
+    eprintln!("Error: {}: {}", context, err);
+    process::exit(1)
+}
@@ -0,0 +1,358 @@
+//! Handle to a running runtime.
+
+use futures_channel::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use futures_core::future::Future;
+use futures_executor::ThreadPool;
+use futures_util::stream::StreamExt;
+use futures_util::task::{FutureObj, Spawn, SpawnError};
+use std::cell::RefCell;
+use std::fmt;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use super::arena;
+use super::coop;
+use super::join::{self, JoinHandle};
+use super::metrics::{Metrics, RuntimeMetrics};
+use super::task::Task;
+
+thread_local! {
+    static CURRENT: RefCell<Option<Handle>> = RefCell::new(None);
+}
+
+/// Handle to the runtime.
+///
+/// A `Handle` can be cloned and sent to other threads, and spawns tasks onto
+/// the runtime it was obtained from. Tasks spawned from a foreign thread are
+/// queued and run the next time the runtime is driving a future.
+///
+/// # Examples
+///
+/// ```
+/// use futures_net::runtime::{self, Handle, Runtime};
+///
+/// let mut rt = runtime::default();
+///
+/// rt.exec(async {
+///     let handle = Handle::current();
+///
+///     let task = std::thread::spawn(move || handle.spawn(async { 1 + 1 }))
+///         .join()
+///         .unwrap();
+///
+///     assert_eq!(task.await.unwrap(), 2);
+/// });
+/// ```
+#[derive(Clone)]
+pub struct Handle {
+    inner: Inner,
+    metrics: Arc<Metrics>,
+}
+
+#[derive(Clone)]
+enum Inner {
+    /// Tasks are queued for the thread driving the runtime.
+    Queue(UnboundedSender<FutureObj<'static, ()>>),
+    /// Tasks are spawned straight onto the worker threads.
+    Pool(ThreadPool),
+}
+
+impl Handle {
+    /// Creates a handle together with the queue of tasks spawned through it.
+    pub(crate) fn queue(
+        metrics: Arc<Metrics>,
+    ) -> (Handle, UnboundedReceiver<FutureObj<'static, ()>>) {
+        let (tx, rx) = mpsc::unbounded();
+        let inner = Inner::Queue(tx);
+        (Handle { inner, metrics }, rx)
+    }
+
+    /// Creates a handle spawning onto `pool`.
+    pub(crate) fn pool(pool: ThreadPool, metrics: Arc<Metrics>) -> Handle {
+        Handle {
+            inner: Inner::Pool(pool),
+            metrics,
+        }
+    }
+
+    /// Returns a handle to the runtime driving the current thread.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a runtime, see [`try_current`].
+    ///
+    /// [`try_current`]: #method.try_current
+    pub fn current() -> Handle {
+        Handle::try_current()
+            .expect("there is no futures-net runtime running on this thread")
+    }
+
+    /// Returns a handle to the runtime driving the current thread, or `None`
+    /// if called outside of a runtime.
+    pub fn try_current() -> Option<Handle> {
+        CURRENT.with(|current| current.borrow().clone())
+    }
+
+    /// Spawns a task onto the runtime.
+    ///
+    /// If the runtime has been dropped the task is dropped as well, and
+    /// awaiting the returned handle yields a cancelled [`JoinError`].
+    ///
+    /// [`JoinError`]: struct.JoinError.html
+    #[cfg_attr(feature = "tracing", track_caller)]
+    pub fn spawn<Fut>(&self, fut: Fut) -> JoinHandle<Fut::Output>
+    where
+        Fut: Future + Send + 'static,
+        Fut::Output: Send + 'static,
+    {
+        let (task, handle) = join::pair(fut);
+        let _ = self.spawn_task(None, task);
+        handle
+    }
+
+    /// Spawns a task with a name onto the runtime.
+    ///
+    /// The name shows up in logs, in the `tracing` spans of the task when the
+    /// `tracing` feature is enabled, and through [`task::name`].
+    ///
+    /// [`task::name`]: ../task/fn.name.html
+    #[cfg_attr(feature = "tracing", track_caller)]
+    pub fn spawn_named<Fut>(
+        &self,
+        name: impl Into<String>,
+        fut: Fut,
+    ) -> JoinHandle<Fut::Output>
+    where
+        Fut: Future + Send + 'static,
+        Fut::Output: Send + 'static,
+    {
+        let (task, handle) = join::pair(fut);
+        let _ = self.spawn_task(Some(name.into()), task);
+        handle
+    }
+
+    #[cfg_attr(feature = "tracing", track_caller)]
+    pub(crate) fn spawn_task<Fut>(
+        &self,
+        name: Option<String>,
+        fut: Fut,
+    ) -> Result<(), SpawnError>
+    where
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let task = Task::new(name, Some(self.metrics.clone()), fut);
+        match &self.inner {
+            Inner::Queue(tx) => tx
+                .unbounded_send(arena::future_obj(task))
+                .map_err(|_| SpawnError::shutdown()),
+            Inner::Pool(pool) => {
+                let task = Entered {
+                    handle: self.clone(),
+                    task,
+                };
+                pool.spawn_obj(arena::future_obj(task))
+            }
+        }
+    }
+
+    /// Returns the counters of the runtime.
+    pub fn metrics(&self) -> RuntimeMetrics {
+        RuntimeMetrics::new(self.metrics.clone())
+    }
+
+    pub(crate) fn raw_metrics(&self) -> &Arc<Metrics> {
+        &self.metrics
+    }
+
+    pub(crate) fn is_multi_thread(&self) -> bool {
+        match self.inner {
+            Inner::Queue(_) => false,
+            Inner::Pool(_) => true,
+        }
+    }
+
+    /// Enters the runtime context on the current thread.
+    ///
+    /// Until the returned guard is dropped, [`Handle::current`] and
+    /// [`spawn`] refer to this runtime, even on threads the runtime knows
+    /// nothing about, such as threads of a C library delivering callbacks.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures_net::runtime::{self, Handle, Runtime};
+    ///
+    /// let mut rt = runtime::default();
+    /// let handle = rt.handle().clone();
+    ///
+    /// let task = std::thread::spawn(move || {
+    ///     let _guard = handle.enter();
+    ///     futures_net::spawn(async { "from a foreign thread" })
+    /// })
+    /// .join()
+    /// .unwrap();
+    ///
+    /// assert_eq!(rt.exec(task).unwrap(), "from a foreign thread");
+    /// ```
+    ///
+    /// [`Handle::current`]: #method.current
+    /// [`spawn`]: fn.spawn.html
+    pub fn enter(&self) -> EnterGuard {
+        let prev = CURRENT.with(|current| current.borrow_mut().replace(self.clone()));
+        EnterGuard {
+            prev,
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Runs a future to completion on the current thread, within the
+    /// runtime context.
+    ///
+    /// This is meant for threads which are not driven by the runtime. Tasks
+    /// spawned by `fut` run on the runtime as usual: on its workers for a
+    /// `multi_thread` runtime, and on the thread calling `exec` for a
+    /// `current_thread` one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from within an executor, e.g. inside a task.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures_net::runtime::Builder;
+    ///
+    /// let rt = Builder::new_multi_thread().worker_threads(1).build().unwrap();
+    /// let handle = rt.handle().clone();
+    ///
+    /// let sum = std::thread::spawn(move || {
+    ///     handle.block_on(async {
+    ///         let task = futures_net::spawn(async { 1 + 1 });
+    ///         task.await.unwrap() + 1
+    ///     })
+    /// })
+    /// .join()
+    /// .unwrap();
+    ///
+    /// assert_eq!(sum, 3);
+    /// ```
+    pub fn block_on<Fut: Future>(&self, fut: Fut) -> Fut::Output {
+        let _enter = self.enter();
+        futures_executor::block_on(coop::budgeted(fut))
+    }
+}
+
+impl fmt::Debug for Handle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let flavor = match self.inner {
+            Inner::Queue(_) => "current_thread",
+            Inner::Pool(_) => "multi_thread",
+        };
+        f.debug_struct("Handle").field("flavor", &flavor).finish()
+    }
+}
+
+/// Guard returned by [`Handle::enter`], restoring the previous runtime
+/// context when dropped.
+///
+/// [`Handle::enter`]: struct.Handle.html#method.enter
+#[must_use = "the runtime context is left when the guard is dropped"]
+pub struct EnterGuard {
+    prev: Option<Handle>,
+    // The guard manipulates thread-local state.
+    _not_send: PhantomData<*const ()>,
+}
+
+impl fmt::Debug for EnterGuard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EnterGuard").finish()
+    }
+}
+
+impl Drop for EnterGuard {
+    fn drop(&mut self) {
+        let prev = self.prev.take();
+        CURRENT.with(|current| *current.borrow_mut() = prev);
+    }
+}
+
+/// Makes the runtime current while a task on a worker thread is polled.
+struct Entered<F> {
+    handle: Handle,
+    task: Task<F>,
+}
+
+impl<F: Future<Output = ()>> Future for Entered<F> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        // Safety: `task` is never moved out of `self`.
+        let this = unsafe { self.get_unchecked_mut() };
+        let _enter = this.handle.enter();
+        unsafe { Pin::new_unchecked(&mut this.task) }.poll(cx)
+    }
+}
+
+impl Spawn for Handle {
+    fn spawn_obj(&self, fut: FutureObj<'static, ()>) -> Result<(), SpawnError> {
+        self.spawn_task(None, fut)
+    }
+}
+
+/// Forwards tasks spawned through a `Handle` to `spawn`.
+pub(crate) async fn forward<F>(
+    mut rx: UnboundedReceiver<FutureObj<'static, ()>>,
+    mut spawn: F,
+) where
+    F: FnMut(FutureObj<'static, ()>),
+{
+    while let Some(task) = rx.next().await {
+        spawn(task);
+    }
+}
+
+/// Spawns a task onto the current runtime.
+///
+/// This is a shorthand for `Handle::current().spawn(fut)`.
+///
+/// # Panics
+///
+/// Panics if called outside of a runtime.
+///
+/// # Examples
+///
+/// ```
+/// use futures_net::runtime::Runtime;
+///
+/// #[futures_net::main]
+/// async fn main() {
+///     let task = futures_net::spawn(async { "hello" });
+///     assert_eq!(task.await.unwrap(), "hello");
+/// }
+/// ```
+#[cfg_attr(feature = "tracing", track_caller)]
+pub fn spawn<Fut>(fut: Fut) -> JoinHandle<Fut::Output>
+where
+    Fut: Future + Send + 'static,
+    Fut::Output: Send + 'static,
+{
+    Handle::current().spawn(fut)
+}
+
+/// Spawns a named task onto the current runtime.
+///
+/// This is a shorthand for `Handle::current().spawn_named(name, fut)`.
+///
+/// # Panics
+///
+/// Panics if called outside of a runtime.
+#[cfg_attr(feature = "tracing", track_caller)]
+pub fn spawn_named<Fut>(name: impl Into<String>, fut: Fut) -> JoinHandle<Fut::Output>
+where
+    Fut: Future + Send + 'static,
+    Fut::Output: Send + 'static,
+{
+    Handle::current().spawn_named(name, fut)
+}
@@ -0,0 +1,335 @@
+//! Handles to the output of spawned tasks.
+
+use futures_core::future::Future;
+use futures_util::future::{CatchUnwind, FutureExt};
+use futures_util::ready;
+use futures_util::task::AtomicWaker;
+use parking_lot::Mutex;
+use std::any::Any;
+use std::cell::Cell;
+use std::error::Error;
+use std::fmt;
+use std::panic::AssertUnwindSafe;
+use std::pin::Pin;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+/// An owned permission to await the output of a spawned task.
+///
+/// Awaiting a `JoinHandle` yields the output of the task, or a
+/// [`JoinError`] if the task panicked, was aborted or was dropped by its
+/// executor before it could finish. Dropping the handle detaches the task:
+/// it keeps running and its output is discarded. Use [`abort`] to tear it
+/// down instead.
+///
+/// Created by [`Spawner::spawn_with_handle`] and
+/// [`Spawner::spawn_local_with_handle`].
+///
+/// [`JoinError`]: struct.JoinError.html
+/// [`abort`]: #method.abort
+/// [`Spawner::spawn_with_handle`]: trait.Spawner.html#method.spawn_with_handle
+/// [`Spawner::spawn_local_with_handle`]: trait.Spawner.html#method.spawn_local_with_handle
+pub struct JoinHandle<T> {
+    inner: Arc<Inner<T>>,
+}
+
+/// Task failed to run to completion.
+pub struct JoinError {
+    repr: Repr,
+}
+
+enum Repr {
+    // The mutex only makes the payload `Sync`, so `JoinError` can be used
+    // with error types like `anyhow::Error`.
+    Panic(Mutex<Box<dyn Any + Send + 'static>>),
+    Cancelled,
+}
+
+struct Inner<T> {
+    state: Mutex<State<T>>,
+    /// Waker of the task awaiting the `JoinHandle`.
+    waker: AtomicWaker,
+    /// Waker of the spawned task itself, used to abort it.
+    task_waker: AtomicWaker,
+    aborted: AtomicBool,
+}
+
+enum State<T> {
+    Running,
+    Done(Result<T, JoinError>),
+    Taken,
+}
+
+/// Completes the join state when the task is dropped, whether or not its
+/// future ran to completion.
+struct Completion<T> {
+    inner: Arc<Inner<T>>,
+    done: bool,
+}
+
+impl<T> Completion<T> {
+    fn complete(&mut self, res: Result<T, JoinError>) {
+        self.done = true;
+        *self.inner.state.lock() = State::Done(res);
+        self.inner.waker.wake();
+    }
+}
+
+impl<T> Drop for Completion<T> {
+    fn drop(&mut self) {
+        if !self.done {
+            self.complete(Err(JoinError::cancelled()));
+        }
+    }
+}
+
+/// The spawned half of a `JoinHandle`.
+pub(crate) struct Joinable<F: Future> {
+    // Declared first so the future is dropped before the handle learns
+    // about the cancellation.
+    fut: Option<CatchUnwind<AssertUnwindSafe<F>>>,
+    completion: Completion<F::Output>,
+}
+
+/// Wraps `fut` so that its output, or its panic, is delivered to the
+/// returned `JoinHandle`.
+pub(crate) fn pair<Fut>(fut: Fut) -> (Joinable<Fut>, JoinHandle<Fut::Output>)
+where
+    Fut: Future,
+{
+    let inner = Arc::new(Inner {
+        state: Mutex::new(State::Running),
+        waker: AtomicWaker::new(),
+        task_waker: AtomicWaker::new(),
+        aborted: AtomicBool::new(false),
+    });
+    let task = Joinable {
+        fut: Some(AssertUnwindSafe(fut).catch_unwind()),
+        completion: Completion {
+            inner: inner.clone(),
+            done: false,
+        },
+    };
+
+    (task, JoinHandle { inner })
+}
+
+impl<F: Future> Future for Joinable<F> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        // Safety: `fut` is never moved out of `self`, only dropped in place.
+        let this = unsafe { self.get_unchecked_mut() };
+        let inner = &this.completion.inner;
+
+        inner.task_waker.register(cx.waker());
+        if inner.aborted.load(Ordering::Acquire) {
+            this.fut = None;
+            this.completion.complete(Err(JoinError::cancelled()));
+            return Poll::Ready(());
+        }
+
+        let fut = match this.fut.as_mut() {
+            Some(fut) => unsafe { Pin::new_unchecked(fut) },
+            None => return Poll::Ready(()),
+        };
+        let res = {
+            let _enter = enter_abort(&inner.aborted);
+            ready!(fut.poll(cx))
+        };
+        this.fut = None;
+        this.completion.complete(res.map_err(JoinError::panic));
+        Poll::Ready(())
+    }
+}
+
+thread_local! {
+    static ABORTED: Cell<*const AtomicBool> = Cell::new(ptr::null());
+}
+
+struct AbortGuard {
+    prev: *const AtomicBool,
+}
+
+fn enter_abort(flag: &AtomicBool) -> AbortGuard {
+    let prev = ABORTED.with(|cell| cell.replace(flag));
+    AbortGuard { prev }
+}
+
+impl Drop for AbortGuard {
+    fn drop(&mut self) {
+        ABORTED.with(|cell| cell.set(self.prev));
+    }
+}
+
+/// Returns `true` if the task being polled has been aborted through its
+/// [`JoinHandle`].
+///
+/// An aborted task is dropped the next time it yields. Long-running loops
+/// which don't yield can check this to stop early. The I/O types of this
+/// crate check it as well and stop reporting readiness, so a task stuck on
+/// an always-ready socket still yields and gets dropped.
+///
+/// [`JoinHandle`]: struct.JoinHandle.html
+pub fn is_cancelled() -> bool {
+    ABORTED.with(|cell| {
+        let flag = cell.get();
+        // Safety: the pointer is only set while the owning task is polled.
+        !flag.is_null() && unsafe { (*flag).load(Ordering::Acquire) }
+    })
+}
+
+impl<T> JoinHandle<T> {
+    /// Aborts the task.
+    ///
+    /// The task is dropped the next time it is scheduled instead of being
+    /// polled, and awaiting the handle yields a cancelled [`JoinError`]. A
+    /// task which already completed is not affected.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::future;
+    /// use futures_net::runtime::{self, Runtime};
+    ///
+    /// let mut rt = runtime::default();
+    /// let task = rt.handle().spawn(future::pending::<()>());
+    ///
+    /// task.abort();
+    /// assert!(rt.exec(task).unwrap_err().is_cancelled());
+    /// ```
+    ///
+    /// [`JoinError`]: struct.JoinError.html
+    pub fn abort(&self) {
+        self.inner.aborted.store(true, Ordering::Release);
+        self.inner.task_waker.wake();
+    }
+
+    /// Returns `true` if the task has finished, successfully or not.
+    pub fn is_finished(&self) -> bool {
+        match *self.inner.state.lock() {
+            State::Running => false,
+            _ => true,
+        }
+    }
+}
+
+impl<T> Unpin for JoinHandle<T> {}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = Result<T, JoinError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // Register first so a completion racing with this poll is not missed.
+        self.inner.waker.register(cx.waker());
+
+        let mut state = self.inner.state.lock();
+        match std::mem::replace(&mut *state, State::Taken) {
+            State::Running => {
+                *state = State::Running;
+                Poll::Pending
+            }
+            State::Done(res) => Poll::Ready(res),
+            State::Taken => panic!("`JoinHandle` polled after completion"),
+        }
+    }
+}
+
+impl<T> fmt::Debug for JoinHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JoinHandle")
+            .field("finished", &self.is_finished())
+            .finish()
+    }
+}
+
+impl JoinError {
+    fn panic(payload: Box<dyn Any + Send + 'static>) -> JoinError {
+        JoinError {
+            repr: Repr::Panic(Mutex::new(payload)),
+        }
+    }
+
+    fn cancelled() -> JoinError {
+        JoinError {
+            repr: Repr::Cancelled,
+        }
+    }
+
+    /// Returns `true` if the task panicked.
+    pub fn is_panic(&self) -> bool {
+        match self.repr {
+            Repr::Panic(_) => true,
+            Repr::Cancelled => false,
+        }
+    }
+
+    /// Returns `true` if the task was aborted or dropped before it completed.
+    pub fn is_cancelled(&self) -> bool {
+        match self.repr {
+            Repr::Panic(_) => false,
+            Repr::Cancelled => true,
+        }
+    }
+
+    /// Consumes the error, returning the panic payload.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the task didn't panic. Use [`try_into_panic`] to check.
+    ///
+    /// [`try_into_panic`]: #method.try_into_panic
+    pub fn into_panic(self) -> Box<dyn Any + Send + 'static> {
+        self.try_into_panic()
+            .expect("`JoinError` reason is not a panic")
+    }
+
+    /// Consumes the error, returning the panic payload if the task panicked.
+    pub fn try_into_panic(self) -> Result<Box<dyn Any + Send + 'static>, JoinError> {
+        match self.repr {
+            Repr::Panic(payload) => Ok(payload.into_inner()),
+            _ => Err(self),
+        }
+    }
+
+    fn panic_message(&self) -> Option<String> {
+        match &self.repr {
+            Repr::Panic(payload) => {
+                let payload = payload.lock();
+                payload
+                    .downcast_ref::<&'static str>()
+                    .map(|s| s.to_string())
+                    .or_else(|| payload.downcast_ref::<String>().cloned())
+            }
+            Repr::Cancelled => None,
+        }
+    }
+}
+
+impl fmt::Display for JoinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.repr {
+            Repr::Panic(_) => match self.panic_message() {
+                Some(msg) => write!(f, "task panicked: {}", msg),
+                None => f.write_str("task panicked"),
+            },
+            Repr::Cancelled => f.write_str("task was cancelled"),
+        }
+    }
+}
+
+impl fmt::Debug for JoinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.repr {
+            Repr::Panic(_) => match self.panic_message() {
+                Some(msg) => write!(f, "JoinError::Panic({:?})", msg),
+                None => f.write_str("JoinError::Panic(..)"),
+            },
+            Repr::Cancelled => f.write_str("JoinError::Cancelled"),
+        }
+    }
+}
+
+impl Error for JoinError {}
@@ -0,0 +1,247 @@
+//! Running `!Send` tasks on the current thread.
+
+use futures_core::future::Future;
+use futures_core::stream::Stream;
+use futures_util::stream::FuturesUnordered;
+use futures_util::task::LocalFutureObj;
+use std::cell::RefCell;
+use std::fmt;
+use std::mem;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+
+use super::arena;
+use super::join::{self, JoinHandle};
+use super::task::Task;
+use super::Handle;
+
+thread_local! {
+    static CURRENT: RefCell<Option<Rc<Shared>>> = RefCell::new(None);
+}
+
+/// A set of `!Send` tasks driven on the thread polling it.
+///
+/// Tasks spawned onto a `LocalSet` only make progress while the set is being
+/// polled, either through [`run_until`] or by awaiting the set itself. This
+/// lets local tasks live next to `Send` tasks of a `multi_thread` runtime.
+///
+/// # Examples
+///
+/// ```
+/// use futures_net::runtime::{self, Builder, LocalSet, Runtime};
+/// use std::rc::Rc;
+///
+/// let mut rt = Builder::new_multi_thread().build().unwrap();
+/// let local = LocalSet::new();
+///
+/// let shared = Rc::new(21);
+/// let value = shared.clone();
+/// let task = local.spawn_local(async move { *value * 2 });
+///
+/// rt.exec(local.run_until(async move {
+///     let doubled = runtime::spawn_local(async move { *shared }).await.unwrap();
+///     assert_eq!(task.await.unwrap(), doubled * 2);
+/// }));
+/// ```
+///
+/// [`run_until`]: #method.run_until
+pub struct LocalSet {
+    shared: Rc<Shared>,
+}
+
+struct Shared {
+    tasks: RefCell<FuturesUnordered<LocalFutureObj<'static, ()>>>,
+    // Tasks spawned while `tasks` is borrowed for polling.
+    queue: RefCell<Vec<LocalFutureObj<'static, ()>>>,
+    waker: RefCell<Option<Waker>>,
+}
+
+impl LocalSet {
+    /// Creates an empty set.
+    pub fn new() -> LocalSet {
+        LocalSet {
+            shared: Rc::new(Shared {
+                tasks: RefCell::new(FuturesUnordered::new()),
+                queue: RefCell::new(Vec::new()),
+                waker: RefCell::new(None),
+            }),
+        }
+    }
+
+    /// Spawns a `!Send` task onto the set.
+    #[cfg_attr(feature = "tracing", track_caller)]
+    pub fn spawn_local<Fut>(&self, fut: Fut) -> JoinHandle<Fut::Output>
+    where
+        Fut: Future + 'static,
+        Fut::Output: 'static,
+    {
+        self.shared.spawn(None, fut)
+    }
+
+    /// Spawns a named `!Send` task onto the set.
+    #[cfg_attr(feature = "tracing", track_caller)]
+    pub fn spawn_local_named<Fut>(
+        &self,
+        name: impl Into<String>,
+        fut: Fut,
+    ) -> JoinHandle<Fut::Output>
+    where
+        Fut: Future + 'static,
+        Fut::Output: 'static,
+    {
+        self.shared.spawn(Some(name.into()), fut)
+    }
+
+    /// Drives `fut` together with the tasks of the set, until `fut`
+    /// completes.
+    ///
+    /// [`spawn_local`] called from within `fut` or the tasks spawns onto this
+    /// set.
+    ///
+    /// [`spawn_local`]: fn.spawn_local.html
+    pub async fn run_until<Fut: Future>(&self, fut: Fut) -> Fut::Output {
+        RunUntil {
+            shared: &self.shared,
+            fut: Box::pin(fut),
+        }
+        .await
+    }
+
+    /// Returns the number of tasks which haven't completed yet.
+    pub fn len(&self) -> usize {
+        let tasks = self.shared.tasks.try_borrow().map(|t| t.len()).unwrap_or(0);
+        tasks + self.shared.queue.borrow().len()
+    }
+
+    /// Returns `true` if every task of the set has completed.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Shared {
+    #[cfg_attr(feature = "tracing", track_caller)]
+    fn spawn<Fut>(&self, name: Option<String>, fut: Fut) -> JoinHandle<Fut::Output>
+    where
+        Fut: Future + 'static,
+        Fut::Output: 'static,
+    {
+        let (task, handle) = join::pair(fut);
+        let metrics = Handle::try_current().map(|handle| handle.raw_metrics().clone());
+        self.queue
+            .borrow_mut()
+            .push(arena::local_future_obj(Task::new(name, metrics, task)));
+        if let Some(waker) = self.waker.borrow_mut().take() {
+            waker.wake();
+        }
+        handle
+    }
+
+    /// Polls the tasks until none of them can make progress.
+    ///
+    /// Returns `true` if the set is empty.
+    fn poll_tasks(self: &Rc<Self>, cx: &mut Context<'_>) -> bool {
+        *self.waker.borrow_mut() = Some(cx.waker().clone());
+        let _enter = enter(self);
+
+        let mut tasks = self.tasks.borrow_mut();
+        loop {
+            tasks.extend(mem::replace(&mut *self.queue.borrow_mut(), Vec::new()));
+
+            loop {
+                match Pin::new(&mut *tasks).poll_next(cx) {
+                    Poll::Ready(Some(())) => {}
+                    Poll::Ready(None) | Poll::Pending => break,
+                }
+            }
+
+            if self.queue.borrow().is_empty() {
+                return tasks.is_empty();
+            }
+        }
+    }
+}
+
+struct RunUntil<'a, Fut> {
+    shared: &'a Rc<Shared>,
+    fut: Pin<Box<Fut>>,
+}
+
+impl<Fut: Future> Future for RunUntil<'_, Fut> {
+    type Output = Fut::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Fut::Output> {
+        let shared = self.shared;
+        let res = {
+            let _enter = enter(shared);
+            self.fut.as_mut().poll(cx)
+        };
+        if res.is_pending() {
+            shared.poll_tasks(cx);
+        }
+        res
+    }
+}
+
+impl Future for LocalSet {
+    type Output = ();
+
+    /// Runs the tasks of the set until all of them have completed.
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.shared.poll_tasks(cx) {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl Default for LocalSet {
+    fn default() -> LocalSet {
+        LocalSet::new()
+    }
+}
+
+impl fmt::Debug for LocalSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LocalSet")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+struct EnterGuard {
+    prev: Option<Rc<Shared>>,
+}
+
+fn enter(shared: &Rc<Shared>) -> EnterGuard {
+    let prev = CURRENT.with(|current| current.borrow_mut().replace(shared.clone()));
+    EnterGuard { prev }
+}
+
+impl Drop for EnterGuard {
+    fn drop(&mut self) {
+        let prev = self.prev.take();
+        CURRENT.with(|current| *current.borrow_mut() = prev);
+    }
+}
+
+/// Spawns a `!Send` task onto the current [`LocalSet`].
+///
+/// # Panics
+///
+/// Panics if called outside of a `LocalSet`.
+///
+/// [`LocalSet`]: struct.LocalSet.html
+#[cfg_attr(feature = "tracing", track_caller)]
+pub fn spawn_local<Fut>(fut: Fut) -> JoinHandle<Fut::Output>
+where
+    Fut: Future + 'static,
+    Fut::Output: 'static,
+{
+    let shared = CURRENT
+        .with(|current| current.borrow().clone())
+        .expect("`spawn_local` called outside of a `LocalSet`");
+    shared.spawn(None, fut)
+}
@@ -0,0 +1,476 @@
+//! Runtime counters.
+
+use parking_lot::Mutex;
+use slab::Slab;
+use std::cell::RefCell;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering::Relaxed};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+
+use super::task::Schedule;
+
+thread_local! {
+    static WORKER: RefCell<Option<(Arc<Metrics>, usize)>> = RefCell::new(None);
+}
+
+/// Counters shared by a runtime, its handles and its tasks.
+pub(crate) struct Metrics {
+    started: Instant,
+    spawned: AtomicU64,
+    completed: AtomicU64,
+    scheduled: AtomicUsize,
+    polls: AtomicU64,
+    slow_polls: AtomicU64,
+    workers: Box<[Worker]>,
+    /// Polls taking at least this long are reported, see
+    /// `Builder::slow_poll_threshold`.
+    slow_poll_threshold: Option<Duration>,
+    /// The live tasks, listed by `Handle::dump_tasks`.
+    tasks: Mutex<Slab<Weak<Schedule>>>,
+    /// Delays from I/O events to the polls of the tasks they woke up.
+    io_latency: [AtomicU64; LATENCY_BUCKETS],
+}
+
+/// The number of buckets of a latency histogram: the first counts delays
+/// below 1µs, bucket `i` delays from 2^(i - 1) to 2^i µs, and the last one
+/// delays from about a second on.
+const LATENCY_BUCKETS: usize = 22;
+
+#[derive(Default)]
+struct Worker {
+    polls: AtomicU64,
+    busy_nanos: AtomicU64,
+}
+
+impl Metrics {
+    pub(crate) fn new(
+        workers: usize,
+        slow_poll_threshold: Option<Duration>,
+    ) -> Arc<Metrics> {
+        Arc::new(Metrics {
+            started: Instant::now(),
+            spawned: AtomicU64::new(0),
+            completed: AtomicU64::new(0),
+            scheduled: AtomicUsize::new(0),
+            polls: AtomicU64::new(0),
+            slow_polls: AtomicU64::new(0),
+            workers: (0..workers).map(|_| Worker::default()).collect(),
+            slow_poll_threshold,
+            tasks: Mutex::new(Slab::new()),
+            io_latency: Default::default(),
+        })
+    }
+
+    pub(crate) fn started(&self) -> Instant {
+        self.started
+    }
+
+    /// Adds the task built by `new` from its key to the live tasks.
+    pub(crate) fn register_task(
+        &self,
+        new: impl FnOnce(usize) -> Schedule,
+    ) -> Arc<Schedule> {
+        let mut tasks = self.tasks.lock();
+        let entry = tasks.vacant_entry();
+        let sched = Arc::new(new(entry.key()));
+        entry.insert(Arc::downgrade(&sched));
+        sched
+    }
+
+    pub(crate) fn deregister_task(&self, key: usize) {
+        self.tasks.lock().remove(key);
+    }
+
+    /// Counts the delay from an I/O event to the poll of the task it woke.
+    pub(crate) fn record_io_latency(&self, delay: Duration) {
+        self.io_latency[latency_bucket(delay)].fetch_add(1, Relaxed);
+    }
+
+    /// Returns the live tasks, in no particular order.
+    pub(crate) fn tasks(&self) -> Vec<Arc<Schedule>> {
+        let tasks = self.tasks.lock();
+        tasks
+            .iter()
+            .filter_map(|(_, task)| task.upgrade())
+            .collect()
+    }
+
+    pub(crate) fn task_spawned(&self) {
+        self.spawned.fetch_add(1, Relaxed);
+    }
+
+    /// Counts completed tasks as well as tasks dropped before completion.
+    pub(crate) fn task_finished(&self) {
+        self.completed.fetch_add(1, Relaxed);
+    }
+
+    pub(crate) fn task_scheduled(&self) {
+        self.scheduled.fetch_add(1, Relaxed);
+    }
+
+    pub(crate) fn task_unscheduled(&self) {
+        self.scheduled.fetch_sub(1, Relaxed);
+    }
+
+    pub(crate) fn slow_poll_threshold(&self) -> Option<Duration> {
+        self.slow_poll_threshold
+    }
+
+    /// Returns `true` if a poll which took `elapsed` is slow, counting it.
+    pub(crate) fn check_slow_poll(&self, elapsed: Duration) -> bool {
+        match self.slow_poll_threshold {
+            Some(threshold) if elapsed >= threshold => {
+                self.slow_polls.fetch_add(1, Relaxed);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+fn latency_bucket(delay: Duration) -> usize {
+    let micros = delay.as_micros() as u64;
+    let bucket = 64 - micros.leading_zeros() as usize;
+    bucket.min(LATENCY_BUCKETS - 1)
+}
+
+/// Marks the current thread as worker `index` for the rest of its life.
+pub(crate) fn set_worker(metrics: &Arc<Metrics>, index: usize) {
+    WORKER.with(|worker| *worker.borrow_mut() = Some((metrics.clone(), index)));
+}
+
+/// Marks the current thread as worker `index` until the guard is dropped.
+pub(crate) fn enter_worker(metrics: &Arc<Metrics>, index: usize) -> WorkerGuard {
+    let prev =
+        WORKER.with(|worker| worker.borrow_mut().replace((metrics.clone(), index)));
+    WorkerGuard { prev }
+}
+
+pub(crate) struct WorkerGuard {
+    prev: Option<(Arc<Metrics>, usize)>,
+}
+
+impl Drop for WorkerGuard {
+    fn drop(&mut self) {
+        let prev = self.prev.take();
+        WORKER.with(|worker| *worker.borrow_mut() = prev);
+    }
+}
+
+/// Runs a poll of a task, accounting it to the worker of this thread.
+///
+/// Returns the output of `f` and how long it ran.
+pub(crate) fn poll<R>(f: impl FnOnce() -> R) -> (R, Duration) {
+    let start = Instant::now();
+    let res = f();
+    let elapsed = start.elapsed();
+
+    WORKER.with(|worker| {
+        if let Some((metrics, index)) = &*worker.borrow() {
+            metrics.polls.fetch_add(1, Relaxed);
+            let worker = &metrics.workers[*index];
+            worker.polls.fetch_add(1, Relaxed);
+            worker
+                .busy_nanos
+                .fetch_add(elapsed.as_nanos() as u64, Relaxed);
+        }
+    });
+    (res, elapsed)
+}
+
+/// Handle to the counters of a runtime.
+///
+/// Counters are read live, so a rate such as polls per second is obtained by
+/// sampling a counter twice.
+///
+/// # Examples
+///
+/// ```
+/// use futures_net::runtime::{self, Runtime};
+///
+/// let mut rt = runtime::Builder::new_current_thread().build().unwrap();
+/// let metrics = rt.metrics();
+///
+/// let task = rt.handle().spawn(async {});
+/// rt.exec(task).unwrap();
+///
+/// assert_eq!(metrics.num_workers(), 1);
+/// assert_eq!(metrics.spawned_tasks(), 1);
+/// assert_eq!(metrics.alive_tasks(), 0);
+/// assert!(metrics.poll_count() >= 1);
+/// ```
+#[derive(Clone)]
+pub struct RuntimeMetrics {
+    inner: Arc<Metrics>,
+}
+
+impl RuntimeMetrics {
+    pub(crate) fn new(inner: Arc<Metrics>) -> RuntimeMetrics {
+        RuntimeMetrics { inner }
+    }
+
+    /// Returns the number of worker threads.
+    ///
+    /// The thread calling `exec` on a `current_thread` runtime is its only
+    /// worker.
+    pub fn num_workers(&self) -> usize {
+        self.inner.workers.len()
+    }
+
+    /// Returns the time elapsed since the runtime was created.
+    pub fn uptime(&self) -> Duration {
+        self.inner.started.elapsed()
+    }
+
+    /// Returns the number of tasks spawned since the runtime was created.
+    pub fn spawned_tasks(&self) -> u64 {
+        self.inner.spawned.load(Relaxed)
+    }
+
+    /// Returns the number of tasks which have been spawned and haven't
+    /// completed or been dropped yet.
+    pub fn alive_tasks(&self) -> u64 {
+        // Load `completed` first so the difference never goes negative.
+        let completed = self.inner.completed.load(Relaxed);
+        self.inner.spawned.load(Relaxed).saturating_sub(completed)
+    }
+
+    /// Returns the number of tasks which have been woken up and are waiting
+    /// to be polled.
+    pub fn queue_depth(&self) -> usize {
+        self.inner.scheduled.load(Relaxed)
+    }
+
+    /// Returns the number of task polls made by the workers.
+    pub fn poll_count(&self) -> u64 {
+        self.inner.polls.load(Relaxed)
+    }
+
+    /// Returns the number of polls which exceeded the slow poll threshold of
+    /// the runtime.
+    ///
+    /// Always zero unless [`Builder::slow_poll_threshold`] is set.
+    ///
+    /// [`Builder::slow_poll_threshold`]: struct.Builder.html#method.slow_poll_threshold
+    pub fn slow_poll_count(&self) -> u64 {
+        self.inner.slow_polls.load(Relaxed)
+    }
+
+    /// Returns the number of task polls made by the worker `worker`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `worker` is not less than [`num_workers`].
+    ///
+    /// [`num_workers`]: #method.num_workers
+    pub fn worker_poll_count(&self, worker: usize) -> u64 {
+        self.inner.workers[worker].polls.load(Relaxed)
+    }
+
+    /// Returns the time the worker `worker` spent polling tasks.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `worker` is not less than [`num_workers`].
+    ///
+    /// [`num_workers`]: #method.num_workers
+    pub fn worker_busy_duration(&self, worker: usize) -> Duration {
+        Duration::from_nanos(self.inner.workers[worker].busy_nanos.load(Relaxed))
+    }
+
+    /// Returns the time the worker `worker` didn't spend polling tasks since
+    /// the runtime was created.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `worker` is not less than [`num_workers`].
+    ///
+    /// [`num_workers`]: #method.num_workers
+    pub fn worker_idle_duration(&self, worker: usize) -> Duration {
+        self.uptime()
+            .checked_sub(self.worker_busy_duration(worker))
+            .unwrap_or_default()
+    }
+
+    /// Returns the histogram of the delays between the reactor receiving an
+    /// I/O event and the poll of the task it woke up.
+    ///
+    /// The delay is how long ready tasks wait for a worker: steady growth
+    /// of the upper quantiles is the sign of an overloaded runtime, long
+    /// before timeouts show it. Only wake-ups of tasks spawned on the
+    /// runtime by I/O events are measured, timers and channels are not.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures_net::runtime::{self, Runtime};
+    /// use futures_net::UdpSocket;
+    ///
+    /// let mut rt = runtime::Builder::new_current_thread().build().unwrap();
+    /// let metrics = rt.metrics();
+    ///
+    /// let addr = "127.0.0.1:0".parse().unwrap();
+    /// let (mut a, mut b) = (UdpSocket::bind(&addr)?, UdpSocket::bind(&addr)?);
+    /// let to = b.local_addr()?;
+    /// let task = rt.handle().spawn(async move {
+    ///     let mut buf = [0; 8];
+    ///     b.recv_from(&mut buf).await
+    /// });
+    /// rt.exec(async move {
+    ///     futures_net::task::yield_now().await;
+    ///     a.send_to(b"ping", &to).await?;
+    ///     task.await.unwrap()
+    /// })?;
+    ///
+    /// let latency = metrics.io_latency_histogram();
+    /// assert!(latency.count() >= 1);
+    /// println!("p99 wake-up delay: {:?}", latency.quantile(0.99));
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn io_latency_histogram(&self) -> LatencyHistogram {
+        LatencyHistogram {
+            counts: self
+                .inner
+                .io_latency
+                .iter()
+                .map(|n| n.load(Relaxed))
+                .collect(),
+        }
+    }
+}
+
+/// The counts of a latency histogram of a runtime, read at once.
+///
+/// Buckets grow in powers of two, from below a microsecond to a last one
+/// counting the delays from about a second on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LatencyHistogram {
+    counts: Vec<u64>,
+}
+
+/// A bucket of a [`LatencyHistogram`].
+///
+/// [`LatencyHistogram`]: struct.LatencyHistogram.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatencyBucket {
+    /// The shortest delay counted in the bucket.
+    pub start: Duration,
+    /// The delay the bucket counts up to, excluded, `None` for the last one.
+    pub end: Option<Duration>,
+    /// The number of delays counted in the bucket.
+    pub count: u64,
+}
+
+impl LatencyHistogram {
+    /// Returns the number of delays counted.
+    pub fn count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Returns the buckets, shortest delays first.
+    pub fn buckets(&self) -> impl Iterator<Item = LatencyBucket> + '_ {
+        let bound = |i: usize| Duration::from_micros(1 << i);
+        self.counts
+            .iter()
+            .enumerate()
+            .map(move |(i, &count)| LatencyBucket {
+                start: if i == 0 { Duration::ZERO } else { bound(i - 1) },
+                end: Some(bound(i)).filter(|_| i + 1 < self.counts.len()),
+                count,
+            })
+    }
+
+    /// Returns a bound for the `q`-quantile of the delays, with `q` from 0
+    /// to 1: the end of the bucket holding it, or the start of the last
+    /// bucket if it falls there. Returns `None` if no delay was counted.
+    pub fn quantile(&self, q: f64) -> Option<Duration> {
+        let count = self.count();
+        if count == 0 {
+            return None;
+        }
+        let rank = ((q.max(0.0).min(1.0) * count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        self.buckets().find_map(|bucket| {
+            seen += bucket.count;
+            if seen >= rank {
+                Some(bucket.end.unwrap_or(bucket.start))
+            } else {
+                None
+            }
+        })
+    }
+}
+
+impl fmt::Debug for RuntimeMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RuntimeMetrics")
+            .field("num_workers", &self.num_workers())
+            .field("alive_tasks", &self.alive_tasks())
+            .field("queue_depth", &self.queue_depth())
+            .field("poll_count", &self.poll_count())
+            .finish()
+    }
+}
+
+#[test]
+fn test_latency_histogram_buckets() {
+    let metrics = Metrics::new(1, None);
+    for &micros in &[0, 1, 3, 5, 7, 10_000_000] {
+        metrics.record_io_latency(Duration::from_micros(micros));
+    }
+    let latency = RuntimeMetrics::new(metrics).io_latency_histogram();
+    let counts: Vec<_> = latency.buckets().map(|b| b.count).take(4).collect();
+    assert_eq!(counts, [1, 1, 1, 2]);
+    assert_eq!(latency.count(), 6);
+
+    let last = latency.buckets().last().unwrap();
+    assert_eq!((last.end, last.count), (None, 1));
+    assert_eq!(latency.quantile(0.5), Some(Duration::from_micros(4)));
+    assert_eq!(latency.quantile(0.8), Some(Duration::from_micros(8)));
+    assert_eq!(latency.quantile(1.0), Some(last.start));
+}
+
+#[test]
+fn test_multi_thread_worker_metrics() {
+    use super::{Builder, Runtime};
+
+    let mut rt = Builder::new_multi_thread()
+        .worker_threads(2)
+        .build()
+        .unwrap();
+    let metrics = rt.metrics();
+
+    let tasks: Vec<_> = (0..4)
+        .map(|_| {
+            rt.handle().spawn(async {
+                std::thread::sleep(Duration::from_millis(5));
+            })
+        })
+        .collect();
+    rt.exec(async {
+        for task in tasks {
+            task.await.unwrap();
+        }
+    });
+
+    assert_eq!(metrics.num_workers(), 2);
+    assert_eq!(metrics.spawned_tasks(), 4);
+    assert_eq!(metrics.queue_depth(), 0);
+
+    // Polls are recorded once they return, which can be after the output
+    // of the task reached its `JoinHandle`.
+    let busy = || {
+        (0..2)
+            .map(|i| metrics.worker_busy_duration(i))
+            .sum::<Duration>()
+    };
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while (metrics.poll_count() < 4 || busy() < Duration::from_millis(20))
+        && Instant::now() < deadline
+    {
+        std::thread::yield_now();
+    }
+    assert!(metrics.poll_count() >= 4);
+    assert!(busy() >= Duration::from_millis(20));
+}
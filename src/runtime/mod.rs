@@ -13,10 +13,87 @@
 //! }
 //!
 //! ```
+//!
+//! `main` may return anything implementing [`Termination`], errors are
+//! printed and the process exits with a failure code:
+//!
+//! ```no_run
+//! use futures_net::TcpListener;
+//!
+//! #[futures_net::main]
+//! async fn main() -> std::io::Result<()> {
+//!     let addr = "127.0.0.1:8080".parse().unwrap();
+//!     let _listener = TcpListener::bind(&addr)?;
+//!     Ok(())
+//! }
+//! ```
+//!
+//! The attributes build a [`DefaultRuntime`] unless given an expression
+//! creating another [`Runtime`]:
+//!
+//! ```
+//! use futures_net::runtime::{Builder, DefaultRuntime};
+//!
+//! fn runtime() -> DefaultRuntime {
+//!     Builder::new_multi_thread().worker_threads(2).build().unwrap()
+//! }
+//!
+//! #[futures_net::main(runtime = "runtime()")]
+//! async fn main() {
+//!     assert_eq!(futures_net::spawn(async { 42 }).await.unwrap(), 42);
+//! }
+//! ```
+//!
+//! [`Termination`]: https://doc.rust-lang.org/std/process/trait.Termination.html
+//! [`DefaultRuntime`]: struct.DefaultRuntime.html
+//! [`Runtime`]: trait.Runtime.html
+
+mod affinity;
+mod arena;
+mod builder;
+pub(crate) mod coop;
+mod dump;
+mod entry;
+mod handle;
+mod join;
+mod local;
+mod metrics;
+mod seeded;
+pub(crate) mod task;
+mod test_timeout;
+
+pub use self::affinity::{available_cpus, pin_current_thread};
+pub use self::builder::Builder;
+pub use self::dump::{TaskDump, TaskInfo, TaskState};
+pub use self::handle::{spawn, spawn_named, EnterGuard, Handle};
+pub use self::join::{is_cancelled, JoinError, JoinHandle};
+pub use self::local::{spawn_local, LocalSet};
+pub use self::metrics::{LatencyBucket, LatencyHistogram, RuntimeMetrics};
+
+#[doc(hidden)]
+pub mod __private {
+    pub use super::entry::block_on_main;
+    pub use super::test_timeout::with_timeout;
+}
 
 use futures_core::future::{BoxFuture, Future, LocalBoxFuture};
-use futures_executor::{LocalPool, LocalSpawner};
-use futures_util::task::{LocalSpawn as _, Spawn as _};
+use futures_executor::ThreadPool;
+use futures_util::future;
+use futures_util::pin_mut;
+use futures_util::task::{
+    waker, ArcWake, FutureObj, LocalFutureObj, LocalSpawn, Spawn, SpawnError,
+};
+use std::fmt;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::thread;
+
+use crate::driver::timer::clock::{self, Clock};
+use crate::driver::FallbackFailure;
+
+use self::seeded::{Pool, PoolSpawner};
 
 /// The Runtime for driving the  application.
 pub trait Runtime {
@@ -30,6 +107,28 @@ pub trait Runtime {
     fn exec<Fut>(&mut self, fut: Fut) -> Fut::Output
     where
         Fut: Future;
+
+    /// Run a future and wait for its result, or for the runtime to fail.
+    ///
+    /// Unlike [`exec`], which keeps running the future when the runtime's
+    /// reactor dies and leaves I/O resources to report errors, this returns
+    /// as soon as such a failure is detected. The default implementation
+    /// forwards to `exec` and never fails.
+    ///
+    /// [`exec`]: #tymethod.exec
+    fn try_exec<Fut>(&mut self, fut: Fut) -> io::Result<Fut::Output>
+    where
+        Fut: Future,
+    {
+        Ok(self.exec(fut))
+    }
+
+    /// Run the tasks spawned onto the current thread until none of them can
+    /// make progress, without blocking.
+    ///
+    /// This lets tests step the executor deterministically. The default
+    /// implementation does nothing.
+    fn run_until_stalled(&mut self) {}
 }
 
 impl<T: ?Sized> Runtime for &mut T
@@ -50,6 +149,19 @@ where
     {
         (**self).exec(fut)
     }
+
+    #[inline]
+    fn try_exec<Fut>(&mut self, fut: Fut) -> io::Result<Fut::Output>
+    where
+        Fut: Future,
+    {
+        (**self).try_exec(fut)
+    }
+
+    #[inline]
+    fn run_until_stalled(&mut self) {
+        (**self).run_until_stalled()
+    }
 }
 
 impl<T: ?Sized> Runtime for Box<T>
@@ -70,6 +182,19 @@ where
     {
         (**self).exec(fut)
     }
+
+    #[inline]
+    fn try_exec<Fut>(&mut self, fut: Fut) -> io::Result<Fut::Output>
+    where
+        Fut: Future,
+    {
+        (**self).try_exec(fut)
+    }
+
+    #[inline]
+    fn run_until_stalled(&mut self) {
+        (**self).run_until_stalled()
+    }
 }
 
 /// The value for spawning  cases.
@@ -82,6 +207,90 @@ pub trait Spawner {
 
     /// Spawn a task to execute a  case which may block the running thread.
     fn block(&mut self, f: Box<dyn FnOnce() + Send + 'static>) -> anyhow::Result<()>;
+
+    /// Spawn a task without requiring the caller to box it.
+    ///
+    /// The default implementation boxes `fut` and forwards it to [`spawn`];
+    /// spawners that can store the future directly should override it.
+    ///
+    /// [`spawn`]: #tymethod.spawn
+    fn spawn_future<Fut>(&mut self, fut: Fut) -> anyhow::Result<()>
+    where
+        Self: Sized,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.spawn(Box::pin(fut))
+    }
+
+    /// Spawn a task onto the current thread without requiring the caller to
+    /// box it.
+    ///
+    /// The default implementation boxes `fut` and forwards it to
+    /// [`spawn_local`].
+    ///
+    /// [`spawn_local`]: #tymethod.spawn_local
+    fn spawn_local_future<Fut>(&mut self, fut: Fut) -> anyhow::Result<()>
+    where
+        Self: Sized,
+        Fut: Future<Output = ()> + 'static,
+    {
+        self.spawn_local(Box::pin(fut))
+    }
+
+    /// Spawn a task and return a handle to await its output.
+    ///
+    /// A panic inside the task is caught and reported through the handle as
+    /// a [`JoinError`] instead of tearing down the executor.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures_net::runtime::{self, Runtime, Spawner};
+    ///
+    /// let mut rt = runtime::default();
+    /// let mut spawner = rt.spawner();
+    ///
+    /// let answer = spawner.spawn_with_handle(async { 6 * 7 }).unwrap();
+    /// let failed = spawner
+    ///     .spawn_with_handle(async { panic!("boom") })
+    ///     .unwrap();
+    ///
+    /// rt.exec(async move {
+    ///     assert_eq!(answer.await.unwrap(), 42);
+    ///     assert!(failed.await.unwrap_err().is_panic());
+    /// });
+    /// ```
+    ///
+    /// [`JoinError`]: struct.JoinError.html
+    fn spawn_with_handle<Fut>(
+        &mut self,
+        fut: Fut,
+    ) -> anyhow::Result<JoinHandle<Fut::Output>>
+    where
+        Self: Sized,
+        Fut: Future + Send + 'static,
+        Fut::Output: Send + 'static,
+    {
+        let (task, handle) = join::pair(fut);
+        self.spawn_future(task)?;
+        Ok(handle)
+    }
+
+    /// Spawn a task onto the current thread and return a handle to await its
+    /// output.
+    fn spawn_local_with_handle<Fut>(
+        &mut self,
+        fut: Fut,
+    ) -> anyhow::Result<JoinHandle<Fut::Output>>
+    where
+        Self: Sized,
+        Fut: Future + 'static,
+        Fut::Output: 'static,
+    {
+        let (task, handle) = join::pair(fut);
+        self.spawn_local_future(task)?;
+        Ok(handle)
+    }
 }
 
 impl<T: ?Sized> Spawner for &mut T
@@ -125,18 +334,188 @@ where
 }
 
 /// Create an instance of `Runtime` used by the default  harness.
-pub fn default() -> impl Runtime {
-    DefaultRuntime {
-        pool: LocalPool::new(),
-    }
+///
+/// This is a single-threaded runtime, see [`Builder`] to configure another
+/// one.
+///
+/// # Panics
+///
+/// Panics if the runtime can't be created, see [`try_default`] to handle
+/// the error instead.
+///
+/// [`Builder`]: struct.Builder.html
+/// [`try_default`]: fn.try_default.html
+pub fn default() -> DefaultRuntime {
+    try_default().expect("failed to create the default runtime")
+}
+
+/// Create an instance of the default `Runtime`, returning an error if it
+/// can't be created.
+///
+/// # Examples
+///
+/// ```
+/// use futures_net::runtime::{self, Runtime};
+///
+/// fn main() -> std::io::Result<()> {
+///     let mut rt = runtime::try_default()?;
+///     let answer = rt.try_exec(async { 6 * 7 })?;
+///     assert_eq!(answer, 42);
+///     Ok(())
+/// }
+/// ```
+pub fn try_default() -> io::Result<DefaultRuntime> {
+    Builder::new_current_thread().build()
+}
+
+/// The runtime created by [`Builder`].
+///
+/// The future passed to [`exec`] and the tasks spawned with `spawn_local`
+/// always run on the thread calling `exec`. With the `multi_thread` flavor,
+/// tasks spawned with `spawn` run on a pool of worker threads instead.
+///
+/// [`Builder`]: struct.Builder.html
+/// [`exec`]: trait.Runtime.html#tymethod.exec
+pub struct DefaultRuntime {
+    pool: Pool,
+    workers: Option<ThreadPool>,
+    handle: Handle,
+    /// Clock of a `current_thread` runtime, which can be paused.
+    clock: Option<Arc<Clock>>,
 }
 
-struct DefaultRuntime {
-    pool: LocalPool,
+/// The spawner of a [`DefaultRuntime`].
+///
+/// [`DefaultRuntime`]: struct.DefaultRuntime.html
+#[derive(Clone)]
+pub struct DefaultSpawner {
+    spawner: PoolSpawner,
+    handle: Handle,
 }
 
-struct DefaultSpawner {
-    spawner: LocalSpawner,
+impl DefaultRuntime {
+    pub(crate) fn new(
+        workers: Option<ThreadPool>,
+        metrics: Arc<metrics::Metrics>,
+        start_paused: bool,
+        schedule_seed: Option<u64>,
+    ) -> DefaultRuntime {
+        let pool = Pool::new(schedule_seed);
+
+        let handle = match &workers {
+            Some(workers) => Handle::pool(workers.clone(), metrics),
+            None => {
+                let (handle, rx) = Handle::queue(metrics);
+                let spawner = pool.spawner();
+                let forward = handle::forward(rx, move |task| {
+                    let _ = spawner.spawn_obj(task.into());
+                });
+                pool.spawner()
+                    .spawn_local_obj(Box::pin(forward).into())
+                    .expect("spawning onto a new pool");
+                handle
+            }
+        };
+
+        let clock = match workers {
+            Some(_) => None,
+            None => Some(Clock::new(start_paused)),
+        };
+
+        DefaultRuntime {
+            pool,
+            workers,
+            handle,
+            clock,
+        }
+    }
+
+    /// Returns a handle to this runtime.
+    pub fn handle(&self) -> &Handle {
+        &self.handle
+    }
+
+    /// Returns the counters of this runtime.
+    pub fn metrics(&self) -> RuntimeMetrics {
+        self.handle.metrics()
+    }
+
+    /// Returns the seed the local tasks are scheduled from, see
+    /// [`Builder::schedule_seed`].
+    ///
+    /// [`Builder::schedule_seed`]: struct.Builder.html#method.schedule_seed
+    pub fn schedule_seed(&self) -> Option<u64> {
+        self.pool.seed()
+    }
+
+    /// Make this runtime current while it runs tasks on this thread.
+    fn enter(
+        &self,
+    ) -> (
+        EnterGuard,
+        Option<metrics::WorkerGuard>,
+        Option<clock::EnterGuard>,
+    ) {
+        let enter = self.handle.enter();
+        // The thread driving the pool is the only worker of a
+        // `current_thread` runtime.
+        let worker = match self.workers {
+            Some(_) => None,
+            None => Some(metrics::enter_worker(self.handle.raw_metrics(), 0)),
+        };
+        let clock = self.clock.as_ref().map(clock::enter);
+        (enter, worker, clock)
+    }
+
+    /// Runs `fut` and the local tasks, moving paused time forward whenever
+    /// they all wait.
+    fn run_with_clock<Fut: Future>(&mut self, clock: &Clock, fut: Fut) -> Fut::Output {
+        struct Notify {
+            woken: AtomicBool,
+            thread: thread::Thread,
+        }
+
+        impl ArcWake for Notify {
+            fn wake_by_ref(arc_self: &Arc<Self>) {
+                arc_self.woken.store(true, Ordering::Release);
+                arc_self.thread.unpark();
+            }
+        }
+
+        let notify = Arc::new(Notify {
+            woken: AtomicBool::new(true),
+            thread: thread::current(),
+        });
+        let waker = waker(notify.clone());
+        let mut cx = Context::from_waker(&waker);
+        pin_mut!(fut);
+
+        loop {
+            if notify.woken.swap(false, Ordering::AcqRel) {
+                if let Poll::Ready(out) = fut.as_mut().poll(&mut cx) {
+                    return out;
+                }
+            }
+            // Wakeups of the local tasks unpark the thread as well, so none
+            // is missed between this and parking.
+            self.pool.run_until_stalled();
+            if notify.woken.load(Ordering::Acquire) {
+                continue;
+            }
+
+            match clock.next_deadline() {
+                Some(deadline) if clock.is_paused() => clock.advance_to(deadline),
+                Some(deadline) => {
+                    let now = clock.now();
+                    if deadline > now {
+                        thread::park_timeout(deadline - now);
+                    }
+                    clock.fire();
+                }
+                None => thread::park(),
+            }
+        }
+    }
 }
 
 impl Runtime for DefaultRuntime {
@@ -146,6 +525,7 @@ impl Runtime for DefaultRuntime {
     fn spawner(&self) -> Self::Spawner {
         DefaultSpawner {
             spawner: self.pool.spawner(),
+            handle: self.handle.clone(),
         }
     }
 
@@ -154,20 +534,159 @@ impl Runtime for DefaultRuntime {
     where
         Fut: Future,
     {
-        self.pool.run_until(fut)
+        let _enter = self.enter();
+        match self.clock.clone() {
+            Some(clock) => self.run_with_clock(&clock, coop::budgeted(fut)),
+            None => self.pool.run_until(coop::budgeted(fut)),
+        }
+    }
+
+    /// Run a future until it completes or the global reactor fails.
+    ///
+    /// When the reactor thread hits an I/O error it shuts down: the I/O
+    /// resources registered with it are woken up and report errors, and
+    /// this returns the reactor error without waiting for `fut`. Tasks
+    /// spawned on the runtime are not cancelled, they are dropped with it.
+    fn try_exec<Fut>(&mut self, fut: Fut) -> io::Result<Fut::Output>
+    where
+        Fut: Future,
+    {
+        let mut failure = FallbackFailure::new();
+        pin_mut!(fut);
+        self.exec(future::poll_fn(|cx| {
+            if let Poll::Ready(err) = failure.poll_failed(cx) {
+                return Poll::Ready(Err(err));
+            }
+            fut.as_mut().poll(cx).map(Ok)
+        }))
+    }
+
+    /// Run the local tasks until they all wait on something.
+    ///
+    /// Tasks spawned with `spawn` on a `multi_thread` runtime run on the
+    /// worker threads and are not affected.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::channel::oneshot;
+    /// use futures_net::runtime::{self, Runtime, Spawner};
+    ///
+    /// let mut rt = runtime::default();
+    /// let (tx, rx) = oneshot::channel();
+    /// let task = rt.spawner().spawn_local_with_handle(rx).unwrap();
+    ///
+    /// rt.run_until_stalled();
+    /// assert!(!task.is_finished());
+    ///
+    /// tx.send(42).unwrap();
+    /// rt.run_until_stalled();
+    /// assert!(task.is_finished());
+    /// ```
+    fn run_until_stalled(&mut self) {
+        let _enter = self.enter();
+        self.pool.run_until_stalled()
+    }
+}
+
+impl DefaultSpawner {
+    fn spawn_task<Fut>(&self, fut: Fut) -> Result<(), SpawnError>
+    where
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        if self.handle.is_multi_thread() {
+            self.handle.spawn_task(None, fut)
+        } else {
+            let metrics = self.handle.raw_metrics().clone();
+            let fut = task::Task::new(None, Some(metrics), fut);
+            self.spawner.spawn_obj(arena::future_obj(fut))
+        }
+    }
+
+    fn spawn_local_task<Fut>(&self, fut: Fut) -> Result<(), SpawnError>
+    where
+        Fut: Future<Output = ()> + 'static,
+    {
+        let metrics = self.handle.raw_metrics().clone();
+        let fut = task::Task::new(None, Some(metrics), fut);
+        self.spawner.spawn_local_obj(arena::local_future_obj(fut))
     }
 }
 
 impl Spawner for DefaultSpawner {
     fn spawn(&mut self, fut: BoxFuture<'static, ()>) -> anyhow::Result<()> {
-        self.spawner.spawn_obj(fut.into()).map_err(Into::into)
+        self.spawn_task(fut).map_err(Into::into)
     }
 
     fn spawn_local(&mut self, fut: LocalBoxFuture<'static, ()>) -> anyhow::Result<()> {
-        self.spawner.spawn_local_obj(fut.into()).map_err(Into::into)
+        self.spawn_local_task(fut).map_err(Into::into)
     }
 
     fn block(&mut self, f: Box<dyn FnOnce() + Send + 'static>) -> anyhow::Result<()> {
-        self.spawn_local(Box::pin(async move { f() }))
+        if self.handle.is_multi_thread() {
+            self.spawn_future(async move { f() })
+        } else {
+            self.spawn_local_future(async move { f() })
+        }
+    }
+
+    fn spawn_future<Fut>(&mut self, fut: Fut) -> anyhow::Result<()>
+    where
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.spawn_task(fut).map_err(Into::into)
+    }
+
+    fn spawn_local_future<Fut>(&mut self, fut: Fut) -> anyhow::Result<()>
+    where
+        Fut: Future<Output = ()> + 'static,
+    {
+        self.spawn_local_task(fut).map_err(Into::into)
+    }
+}
+
+/// Makes the runtime usable by libraries generic over the `futures` spawn
+/// traits.
+///
+/// # Examples
+///
+/// ```
+/// use futures::task::SpawnExt;
+/// use futures_net::runtime::{self, Runtime};
+///
+/// let mut rt = runtime::default();
+/// let spawner = rt.spawner();
+///
+/// let answer = spawner.spawn_with_handle(async { 42 }).unwrap();
+/// assert_eq!(rt.exec(answer), 42);
+/// ```
+impl Spawn for DefaultSpawner {
+    fn spawn_obj(&self, fut: FutureObj<'static, ()>) -> Result<(), SpawnError> {
+        self.spawn_task(fut)
+    }
+}
+
+impl LocalSpawn for DefaultSpawner {
+    fn spawn_local_obj(
+        &self,
+        fut: LocalFutureObj<'static, ()>,
+    ) -> Result<(), SpawnError> {
+        self.spawn_local_task(fut)
+    }
+}
+
+impl fmt::Debug for DefaultRuntime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DefaultRuntime")
+            .field("handle", &self.handle)
+            .finish()
+    }
+}
+
+impl fmt::Debug for DefaultSpawner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DefaultSpawner")
+            .field("handle", &self.handle)
+            .finish()
     }
 }
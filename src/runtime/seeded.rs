@@ -0,0 +1,270 @@
+//! Pool of the thread driving a `current_thread` runtime.
+//!
+//! By default the tasks are run by a `LocalPool`, which polls them in the
+//! order they were woken. A runtime built with a schedule seed runs them on
+//! a `SeededPool` instead, which picks the next task to poll among the
+//! woken ones with a pseudo-random generator started from the seed. The
+//! same seed and the same sequence of wakeups give the same interleaving,
+//! so an ordering bug found with one seed can be replayed with it.
+
+use futures_core::future::Future;
+use futures_executor::{LocalPool, LocalSpawner};
+use futures_util::task::{
+    waker, ArcWake, FutureObj, LocalFutureObj, LocalSpawn, Spawn, SpawnError,
+};
+use parking_lot::Mutex;
+use slab::Slab;
+use std::cell::{Cell, RefCell};
+use std::pin::Pin;
+use std::rc::{Rc, Weak};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::Context;
+use std::thread;
+
+/// The local tasks of a runtime.
+pub(crate) enum Pool {
+    Fifo(LocalPool),
+    Seeded(SeededPool),
+}
+
+/// Spawns onto a [`Pool`].
+#[derive(Clone)]
+pub(crate) enum PoolSpawner {
+    Fifo(LocalSpawner),
+    Seeded(SeededSpawner),
+}
+
+impl Pool {
+    pub(crate) fn new(seed: Option<u64>) -> Pool {
+        match seed {
+            Some(seed) => Pool::Seeded(SeededPool::new(seed)),
+            None => Pool::Fifo(LocalPool::new()),
+        }
+    }
+
+    pub(crate) fn spawner(&self) -> PoolSpawner {
+        match self {
+            Pool::Fifo(pool) => PoolSpawner::Fifo(pool.spawner()),
+            Pool::Seeded(pool) => PoolSpawner::Seeded(pool.spawner()),
+        }
+    }
+
+    /// Returns the seed of the schedule, `None` for a FIFO pool.
+    pub(crate) fn seed(&self) -> Option<u64> {
+        match self {
+            Pool::Fifo(_) => None,
+            Pool::Seeded(pool) => Some(pool.shared.seed),
+        }
+    }
+
+    pub(crate) fn run_until_stalled(&mut self) {
+        match self {
+            Pool::Fifo(pool) => pool.run_until_stalled(),
+            Pool::Seeded(pool) => pool.run_until_stalled(),
+        }
+    }
+
+    pub(crate) fn run_until<Fut: Future>(&mut self, fut: Fut) -> Fut::Output {
+        match self {
+            Pool::Fifo(pool) => pool.run_until(fut),
+            // Only `current_thread` runtimes are seeded, and they run their
+            // futures along with their clock.
+            Pool::Seeded(_) => unreachable!("seeded pools are driven by the clock"),
+        }
+    }
+}
+
+impl Spawn for PoolSpawner {
+    fn spawn_obj(&self, fut: FutureObj<'static, ()>) -> Result<(), SpawnError> {
+        match self {
+            PoolSpawner::Fifo(spawner) => spawner.spawn_obj(fut),
+            PoolSpawner::Seeded(spawner) => spawner.spawn_local_obj(fut.into()),
+        }
+    }
+}
+
+impl LocalSpawn for PoolSpawner {
+    fn spawn_local_obj(
+        &self,
+        fut: LocalFutureObj<'static, ()>,
+    ) -> Result<(), SpawnError> {
+        match self {
+            PoolSpawner::Fifo(spawner) => spawner.spawn_local_obj(fut),
+            PoolSpawner::Seeded(spawner) => spawner.spawn_local_obj(fut),
+        }
+    }
+}
+
+/// Runs the woken tasks in an order derived from a seed.
+pub(crate) struct SeededPool {
+    shared: Rc<Shared>,
+}
+
+#[derive(Clone)]
+pub(crate) struct SeededSpawner {
+    shared: Weak<Shared>,
+}
+
+struct Shared {
+    seed: u64,
+    rng: Cell<u64>,
+    /// The tasks, taken out of their slot while they are polled.
+    tasks: RefCell<Slab<Slot>>,
+    ready: Arc<Ready>,
+}
+
+struct Slot {
+    fut: Option<LocalFutureObj<'static, ()>>,
+    waker: Arc<TaskWaker>,
+}
+
+/// Ids of the woken tasks, in the order they were woken.
+struct Ready {
+    ids: Mutex<Vec<usize>>,
+    /// The thread running the pool, unparked by wakeups.
+    thread: Mutex<Option<thread::Thread>>,
+}
+
+struct TaskWaker {
+    id: usize,
+    queued: AtomicBool,
+    ready: Arc<Ready>,
+}
+
+impl ArcWake for TaskWaker {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        if arc_self.queued.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        arc_self.ready.ids.lock().push(arc_self.id);
+        if let Some(thread) = &*arc_self.ready.thread.lock() {
+            thread.unpark();
+        }
+    }
+}
+
+impl SeededPool {
+    fn new(seed: u64) -> SeededPool {
+        SeededPool {
+            shared: Rc::new(Shared {
+                seed,
+                rng: Cell::new(seed),
+                tasks: RefCell::new(Slab::new()),
+                ready: Arc::new(Ready {
+                    ids: Mutex::new(Vec::new()),
+                    thread: Mutex::new(None),
+                }),
+            }),
+        }
+    }
+
+    fn spawner(&self) -> SeededSpawner {
+        SeededSpawner {
+            shared: Rc::downgrade(&self.shared),
+        }
+    }
+
+    /// Polls woken tasks, picked at random, until none is left.
+    fn run_until_stalled(&mut self) {
+        let shared = &self.shared;
+        *shared.ready.thread.lock() = Some(thread::current());
+
+        loop {
+            let id = {
+                let mut ids = shared.ready.ids.lock();
+                if ids.is_empty() {
+                    return;
+                }
+                let i = (shared.next_u64() % ids.len() as u64) as usize;
+                ids.swap_remove(i)
+            };
+
+            // A stale wakeup of a completed task may name a vacant slot.
+            let (mut fut, task_waker) = {
+                let mut tasks = shared.tasks.borrow_mut();
+                match tasks.get_mut(id) {
+                    Some(slot) => match slot.fut.take() {
+                        Some(fut) => (fut, slot.waker.clone()),
+                        None => continue,
+                    },
+                    None => continue,
+                }
+            };
+
+            task_waker.queued.store(false, Ordering::Release);
+            let waker = waker(task_waker);
+            let mut cx = Context::from_waker(&waker);
+            if Pin::new(&mut fut).poll(&mut cx).is_ready() {
+                // Drop the slot once the tasks are released, the future may
+                // spawn from its destructor.
+                let slot = shared.tasks.borrow_mut().remove(id);
+                drop(slot);
+            } else {
+                shared.tasks.borrow_mut()[id].fut = Some(fut);
+            }
+        }
+    }
+}
+
+impl Shared {
+    /// Steps the splitmix64 generator of the schedule.
+    fn next_u64(&self) -> u64 {
+        let state = self.rng.get().wrapping_add(0x9e37_79b9_7f4a_7c15);
+        self.rng.set(state);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+impl LocalSpawn for SeededSpawner {
+    fn spawn_local_obj(
+        &self,
+        fut: LocalFutureObj<'static, ()>,
+    ) -> Result<(), SpawnError> {
+        let shared = self.shared.upgrade().ok_or_else(SpawnError::shutdown)?;
+        let mut tasks = shared.tasks.borrow_mut();
+        let entry = tasks.vacant_entry();
+        let waker = Arc::new(TaskWaker {
+            id: entry.key(),
+            queued: AtomicBool::new(false),
+            ready: shared.ready.clone(),
+        });
+        entry.insert(Slot {
+            fut: Some(fut),
+            waker: waker.clone(),
+        });
+        // New tasks are ready to be polled.
+        waker.wake();
+        Ok(())
+    }
+}
+
+#[test]
+fn test_seeded_schedule_is_replayed() {
+    use super::{Builder, Runtime, Spawner};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    fn run(seed: u64) -> Vec<u32> {
+        let mut rt = Builder::new_current_thread()
+            .schedule_seed(seed)
+            .build()
+            .unwrap();
+        let order = Rc::new(RefCell::new(Vec::new()));
+        for i in 0..16 {
+            let order = order.clone();
+            rt.spawner()
+                .spawn_local_future(async move { order.borrow_mut().push(i) })
+                .unwrap();
+        }
+        rt.run_until_stalled();
+        let order = order.borrow().clone();
+        order
+    }
+
+    assert_eq!(run(1), run(1));
+    assert!((2..10).any(|seed| run(seed) != run(1)));
+}
@@ -0,0 +1,378 @@
+//! Bookkeeping shared by every task spawned on the runtime.
+//!
+//! Spawned futures are wrapped in a [`Task`] which assigns them an id,
+//! carries their optional name, resets their cooperative budget on each poll,
+//! reports polls which block for too long and, with the `tracing` feature,
+//! instruments their lifecycle. Tasks spawned on a runtime are also listed
+//! in its registry of live tasks, see `Handle::dump_tasks`.
+
+use futures_core::future::Future;
+use futures_util::task::{waker, ArcWake, AtomicWaker};
+use log::{trace, warn};
+use std::backtrace::{Backtrace, BacktraceStatus};
+use std::cell::RefCell;
+use std::fmt;
+use std::panic::Location;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+
+use super::coop;
+use super::dump::{TaskInfo, TaskState};
+use super::metrics::{self, Metrics};
+use crate::driver;
+
+thread_local! {
+    static CURRENT: RefCell<Option<Arc<Header>>> = RefCell::new(None);
+}
+
+/// Identity of a spawned task.
+pub(crate) struct Header {
+    id: u64,
+    name: Option<Box<str>>,
+}
+
+impl Header {
+    pub(crate) fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+}
+
+impl fmt::Display for Header {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "#{} ({})", self.id, name),
+            None => write!(f, "#{}", self.id),
+        }
+    }
+}
+
+/// A spawned future.
+pub(crate) struct Task<F> {
+    header: Arc<Header>,
+    fut: F,
+    done: bool,
+    sched: Option<(Arc<Schedule>, Waker)>,
+    /// Where the task was spawned, captured when slow polls are reported.
+    spawned_at: Option<Backtrace>,
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+}
+
+/// Tracks whether the task is waiting in the run queue of the executor, by
+/// intercepting its wake-ups, and what the task dump reports about it.
+pub(crate) struct Schedule {
+    scheduled: AtomicBool,
+    finished: AtomicBool,
+    waker: AtomicWaker,
+    metrics: Arc<Metrics>,
+    header: Arc<Header>,
+    /// The key of the task in the registry of the runtime.
+    key: usize,
+    running: AtomicBool,
+    polls: AtomicU64,
+    /// Nanoseconds from the start of the runtime to the start of the last
+    /// poll, plus one; zero until the task is first polled.
+    last_poll: AtomicU64,
+    /// Nanoseconds from the start of the runtime to the I/O event which
+    /// woke the task up, plus one; zero unless an I/O event did.
+    io_woken: AtomicU64,
+    /// Where the task was spawned, with the `tracing` feature.
+    location: Option<&'static Location<'static>>,
+}
+
+impl ArcWake for Schedule {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        if !arc_self.finished.load(Ordering::Acquire)
+            && !arc_self.scheduled.swap(true, Ordering::AcqRel)
+        {
+            arc_self.metrics.task_scheduled();
+        }
+        if let Some(at) = driver::dispatched_at() {
+            let nanos = at.saturating_duration_since(arc_self.metrics.started());
+            // Keep the earliest event until the task is polled.
+            let _ = arc_self.io_woken.compare_exchange(
+                0,
+                nanos.as_nanos() as u64 + 1,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            );
+        }
+        arc_self.waker.wake();
+    }
+}
+
+impl Schedule {
+    /// Describes the task for a task dump.
+    pub(crate) fn info(&self) -> TaskInfo {
+        let state = if self.running.load(Ordering::Acquire) {
+            TaskState::Running
+        } else if self.scheduled.load(Ordering::Acquire) {
+            TaskState::Scheduled
+        } else {
+            TaskState::Idle
+        };
+        let last_poll = match self.last_poll.load(Ordering::Relaxed) {
+            0 => None,
+            nanos => Some(self.metrics.started() + Duration::from_nanos(nanos - 1)),
+        };
+        TaskInfo {
+            id: self.header.id,
+            name: self.header.name().map(String::from),
+            state,
+            polls: self.polls.load(Ordering::Relaxed),
+            last_poll,
+            spawned_at: self.location,
+        }
+    }
+
+    /// Called when a poll of the task starts.
+    fn enter_poll(&self) {
+        let nanos = self.metrics.started().elapsed().as_nanos() as u64;
+        self.last_poll.store(nanos + 1, Ordering::Relaxed);
+        match self.io_woken.swap(0, Ordering::Relaxed) {
+            0 => {}
+            woken => {
+                let delay = nanos.saturating_sub(woken - 1);
+                self.metrics.record_io_latency(Duration::from_nanos(delay));
+            }
+        }
+        self.polls.fetch_add(1, Ordering::Relaxed);
+        self.running.store(true, Ordering::Release);
+    }
+}
+
+impl Schedule {
+    /// Called when the task is about to be polled.
+    fn unschedule(&self) {
+        if self.scheduled.swap(false, Ordering::AcqRel) {
+            self.metrics.task_unscheduled();
+        }
+    }
+}
+
+impl<F: Future<Output = ()>> Task<F> {
+    #[cfg_attr(feature = "tracing", track_caller)]
+    pub(crate) fn new(
+        name: Option<String>,
+        metrics: Option<Arc<Metrics>>,
+        fut: F,
+    ) -> Task<F> {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+        let header = Arc::new(Header {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            name: name.map(String::into_boxed_str),
+        });
+
+        #[cfg(feature = "tracing")]
+        let span = {
+            let span = tracing::trace_span!(
+                "task",
+                task.id = header.id,
+                task.name = header.name().unwrap_or(""),
+            );
+            span.in_scope(|| tracing::trace!("spawn"));
+            span
+        };
+        trace!("spawn task {}", header);
+
+        let spawned_at = metrics
+            .as_ref()
+            .and_then(|metrics| metrics.slow_poll_threshold())
+            .map(|_| Backtrace::capture());
+
+        #[cfg(feature = "tracing")]
+        let location = Some(Location::caller());
+        #[cfg(not(feature = "tracing"))]
+        let location = None;
+
+        let sched = metrics.map(|metrics| {
+            metrics.task_spawned();
+            metrics.task_scheduled();
+            let sched = metrics.clone().register_task(|key| Schedule {
+                scheduled: AtomicBool::new(true),
+                finished: AtomicBool::new(false),
+                waker: AtomicWaker::new(),
+                metrics,
+                header: header.clone(),
+                key,
+                running: AtomicBool::new(false),
+                polls: AtomicU64::new(0),
+                last_poll: AtomicU64::new(0),
+                io_woken: AtomicU64::new(0),
+                location,
+            });
+            let waker = waker(sched.clone());
+            (sched, waker)
+        });
+
+        Task {
+            header,
+            fut,
+            done: false,
+            sched,
+            spawned_at,
+            #[cfg(feature = "tracing")]
+            span,
+        }
+    }
+}
+
+impl<F: Future<Output = ()>> Future for Task<F> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        // Safety: `fut` is never moved out of `self`.
+        let this = unsafe { self.get_unchecked_mut() };
+        let fut = unsafe { Pin::new_unchecked(&mut this.fut) };
+
+        #[cfg(feature = "tracing")]
+        let _span = this.span.enter();
+        let _enter = enter(&this.header);
+
+        let poll =
+            |cx: &mut Context<'_>| metrics::poll(|| coop::budget(|| fut.poll(cx)));
+        let (res, elapsed) = match &this.sched {
+            Some((sched, waker)) => {
+                sched.waker.register(cx.waker());
+                sched.unschedule();
+                sched.enter_poll();
+                let res = poll(&mut Context::from_waker(waker));
+                sched.running.store(false, Ordering::Release);
+                res
+            }
+            None => poll(cx),
+        };
+        if let Some((sched, _)) = &this.sched {
+            if sched.metrics.check_slow_poll(elapsed) {
+                report_slow_poll(&this.header, this.spawned_at.take(), elapsed);
+            }
+        }
+        if res.is_ready() {
+            this.done = true;
+            #[cfg(feature = "tracing")]
+            tracing::trace!("complete");
+            trace!("task {} complete", this.header);
+        }
+        res
+    }
+}
+
+fn report_slow_poll(header: &Header, spawned_at: Option<Backtrace>, elapsed: Duration) {
+    #[cfg(feature = "tracing")]
+    tracing::warn!(?elapsed, "slow poll");
+
+    match spawned_at {
+        Some(bt) if bt.status() == BacktraceStatus::Captured => warn!(
+            "task {} blocked its thread for {:?} in a single poll, it was spawned at:\n{}",
+            header, elapsed, bt
+        ),
+        _ => warn!(
+            "task {} blocked its thread for {:?} in a single poll",
+            header, elapsed
+        ),
+    }
+}
+
+impl<F> Drop for Task<F> {
+    fn drop(&mut self) {
+        if let Some((sched, _)) = &self.sched {
+            sched.finished.store(true, Ordering::Release);
+            sched.unschedule();
+            sched.metrics.task_finished();
+            sched.metrics.deregister_task(sched.key);
+        }
+        if !self.done {
+            #[cfg(feature = "tracing")]
+            self.span.in_scope(|| tracing::trace!("dropped"));
+            trace!("task {} dropped before completion", self.header);
+        }
+    }
+}
+
+struct EnterGuard {
+    prev: Option<Arc<Header>>,
+}
+
+fn enter(header: &Arc<Header>) -> EnterGuard {
+    let prev = CURRENT.with(|current| current.borrow_mut().replace(header.clone()));
+    EnterGuard { prev }
+}
+
+impl Drop for EnterGuard {
+    fn drop(&mut self) {
+        let prev = self.prev.take();
+        CURRENT.with(|current| *current.borrow_mut() = prev);
+    }
+}
+
+/// Returns the header of the task being polled on this thread.
+pub(crate) fn current() -> Option<Arc<Header>> {
+    CURRENT.with(|current| current.borrow().clone())
+}
+
+/// Returns the name of the task being polled, if it was spawned with one.
+///
+/// # Examples
+///
+/// ```
+/// use futures_net::runtime::Runtime;
+///
+/// #[futures_net::main]
+/// async fn main() {
+///     let task = futures_net::task::spawn_named("worker", async {
+///         futures_net::task::name()
+///     });
+///     assert_eq!(task.await.unwrap().as_deref(), Some("worker"));
+/// }
+/// ```
+pub fn name() -> Option<String> {
+    current().and_then(|header| header.name().map(String::from))
+}
+
+#[test]
+fn test_slow_polls_are_counted_and_reported_once_with_the_spawn_site() {
+    use super::metrics::RuntimeMetrics;
+    use futures_util::task::noop_waker;
+    use std::thread;
+
+    let metrics = Metrics::new(1, Some(Duration::from_millis(10)));
+    let mut polls = 0;
+    let fut = futures_util::future::poll_fn(move |_| {
+        polls += 1;
+        if polls < 3 {
+            thread::sleep(Duration::from_millis(20));
+            Poll::Pending
+        } else {
+            Poll::Ready(())
+        }
+    });
+    let mut task = Box::pin(Task::new(Some("slow".into()), Some(metrics.clone()), fut));
+    assert!(task.spawned_at.is_some());
+
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    assert!(task.as_mut().poll(&mut cx).is_pending());
+    let metrics = RuntimeMetrics::new(metrics);
+    assert_eq!(metrics.slow_poll_count(), 1);
+    // The spawn site is only shown in the first report.
+    assert!(task.spawned_at.is_none());
+
+    assert!(task.as_mut().poll(&mut cx).is_pending());
+    assert!(task.as_mut().poll(&mut cx).is_ready());
+    assert_eq!(metrics.slow_poll_count(), 2);
+
+    // Without a threshold, nothing is captured or counted.
+    let metrics = Metrics::new(1, None);
+    let fut = futures_util::future::poll_fn(|_| {
+        thread::sleep(Duration::from_millis(20));
+        Poll::Ready(())
+    });
+    let mut task = Box::pin(Task::new(None, Some(metrics.clone()), fut));
+    assert!(task.spawned_at.is_none());
+    assert!(task.as_mut().poll(&mut cx).is_ready());
+    assert_eq!(RuntimeMetrics::new(metrics).slow_poll_count(), 0);
+}
@@ -0,0 +1,85 @@
+//! Deadline used by `#[futures_net::test(timeout = "..")]`.
+
+use futures_core::future::Future;
+use futures_util::future::{self, Either};
+use futures_util::task::AtomicWaker;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::thread;
+use std::time::Duration;
+
+/// Drives `fut`, panicking if it doesn't complete within `dur`.
+///
+/// A test blocking its thread can't be interrupted, but one waiting on I/O
+/// that never arrives fails with a clear message instead of hanging.
+pub async fn with_timeout<F: Future>(dur: Duration, fut: F) -> F::Output {
+    let fut = Box::pin(fut);
+    match future::select(fut, Deadline::new(dur)).await {
+        Either::Left((out, _)) => out,
+        Either::Right(..) => panic!("test timed out after {:?}", dur),
+    }
+}
+
+struct Deadline {
+    shared: Arc<Shared>,
+    // Dropping the sender stops the timer thread.
+    _cancel: mpsc::Sender<()>,
+}
+
+struct Shared {
+    fired: AtomicBool,
+    waker: AtomicWaker,
+}
+
+impl Deadline {
+    fn new(dur: Duration) -> Deadline {
+        let shared = Arc::new(Shared {
+            fired: AtomicBool::new(false),
+            waker: AtomicWaker::new(),
+        });
+        let (tx, rx) = mpsc::channel::<()>();
+
+        let timer = shared.clone();
+        thread::Builder::new()
+            .name("futures-net-test-timeout".to_string())
+            .spawn(move || {
+                if let Err(mpsc::RecvTimeoutError::Timeout) = rx.recv_timeout(dur) {
+                    timer.fired.store(true, Ordering::SeqCst);
+                    timer.waker.wake();
+                }
+            })
+            .expect("failed to spawn the test timeout thread");
+
+        Deadline {
+            shared,
+            _cancel: tx,
+        }
+    }
+}
+
+impl Future for Deadline {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        self.shared.waker.register(cx.waker());
+        if self.shared.fired.load(Ordering::SeqCst) {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+#[test]
+#[should_panic(expected = "test timed out after 10ms")]
+fn test_timeout_fires() {
+    use super::Runtime;
+
+    super::default().exec(with_timeout(
+        Duration::from_millis(10),
+        future::pending::<()>(),
+    ));
+}
@@ -0,0 +1,102 @@
+use futures_core::stream::Stream;
+use futures_util::ready;
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use super::SctpStream;
+use crate::driver::sys;
+use crate::driver::PollEvented;
+
+/// An SCTP socket server, listening for associations.
+pub struct SctpListener {
+    io: PollEvented<sys::net::SctpSocket>,
+}
+
+impl SctpListener {
+    /// Creates a listener bound to `addr`.
+    pub fn bind(addr: &SocketAddr) -> io::Result<SctpListener> {
+        SctpListener::bind_multi(std::slice::from_ref(addr))
+    }
+
+    /// Creates a listener bound to every address of `addrs`, which must
+    /// share the same port, and advertised to peers as alternate paths.
+    pub fn bind_multi(addrs: &[SocketAddr]) -> io::Result<SctpListener> {
+        let first = addrs.first().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "no addresses given")
+        })?;
+        let socket = sys::net::SctpSocket::new(first)?;
+        socket.bind(addrs)?;
+        socket.listen(1024)?;
+        Ok(SctpListener {
+            io: PollEvented::new(socket),
+        })
+    }
+
+    /// Returns the primary local address of the listener.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.io.get_ref().local_addr()
+    }
+
+    /// Accepts a new association.
+    pub fn poll_accept(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<(SctpStream, SocketAddr)>> {
+        ready!(Pin::new(&mut self.io).poll_read_ready(cx)?);
+
+        match self.io.get_ref().accept() {
+            Ok((socket, addr)) => Poll::Ready(Ok((SctpStream::new(socket), addr))),
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                Pin::new(&mut self.io).clear_read_ready(cx)?;
+                Poll::Pending
+            }
+            Err(e) => Poll::Ready(Err(e)),
+        }
+    }
+
+    /// Accepts a new association, returning the stream and the primary
+    /// address of the peer.
+    pub async fn accept(&mut self) -> io::Result<(SctpStream, SocketAddr)> {
+        futures_util::future::poll_fn(|cx| self.poll_accept(cx)).await
+    }
+
+    /// Returns a stream of the accepted associations.
+    pub fn incoming(&mut self) -> Incoming<'_> {
+        Incoming { inner: self }
+    }
+}
+
+impl fmt::Debug for SctpListener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.io.get_ref().fmt(f)
+    }
+}
+
+impl AsRawFd for SctpListener {
+    fn as_raw_fd(&self) -> RawFd {
+        self.io.get_ref().as_raw_fd()
+    }
+}
+
+/// Stream returned by `SctpListener::incoming`.
+#[must_use = "streams do nothing unless polled"]
+#[derive(Debug)]
+pub struct Incoming<'a> {
+    inner: &'a mut SctpListener,
+}
+
+impl<'a> Stream for Incoming<'a> {
+    type Item = io::Result<SctpStream>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let (socket, _) = ready!(self.inner.poll_accept(cx)?);
+        Poll::Ready(Some(Ok(socket)))
+    }
+}
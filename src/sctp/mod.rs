@@ -0,0 +1,49 @@
+//! Async SCTP, with one-to-one style sockets.
+//!
+//! SCTP carries messages over associations which can span several
+//! addresses per endpoint (multi-homing), surviving the loss of a path. A
+//! [`SctpListener`] can be bound to several local addresses with
+//! [`bind_multi`] and a [`SctpStream`] can reach a peer through any of its
+//! addresses with [`connect_multi`]. Streams implement `AsyncRead` and
+//! `AsyncWrite`, and [`SctpStream::recv_msg`] additionally reports message
+//! boundaries and the [`Notification`]s subscribed to with
+//! [`SctpStream::subscribe`].
+//!
+//! SCTP has to be enabled in the kernel, opening a socket fails with
+//! `EPROTONOSUPPORT` otherwise.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use futures::prelude::*;
+//! use futures_net::sctp::{SctpListener, SctpStream};
+//!
+//! # async fn run() -> std::io::Result<()> {
+//! let addrs = ["10.0.0.1:3868".parse().unwrap(), "10.0.1.1:3868".parse().unwrap()];
+//! let mut listener = SctpListener::bind_multi(&addrs)?;
+//!
+//! let mut client = SctpStream::connect(&addrs[0]).await?;
+//! let (mut server, _) = listener.accept().await?;
+//!
+//! client.write_all(b"CER").await?;
+//! let mut buf = [0; 3];
+//! server.read_exact(&mut buf).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`SctpListener`]: struct.SctpListener.html
+//! [`bind_multi`]: struct.SctpListener.html#method.bind_multi
+//! [`SctpStream`]: struct.SctpStream.html
+//! [`connect_multi`]: struct.SctpStream.html#method.connect_multi
+//! [`SctpStream::recv_msg`]: struct.SctpStream.html#method.recv_msg
+//! [`SctpStream::subscribe`]: struct.SctpStream.html#method.subscribe
+//! [`Notification`]: enum.Notification.html
+
+mod listener;
+mod notification;
+mod stream;
+
+pub use self::listener::{Incoming, SctpListener};
+pub use self::notification::{AssocState, Notification, SctpEvents};
+pub use self::stream::{ConnectFuture, RecvMsg, SctpStream};
@@ -0,0 +1,185 @@
+/// The notifications to subscribe to with `SctpStream::subscribe`.
+///
+/// Every notification is off by default. `data_io` is not a notification:
+/// it asks for the per-message `sctp_sndrcvinfo` ancillary data, which this
+/// crate doesn't decode.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SctpEvents {
+    data_io: bool,
+    association: bool,
+    address: bool,
+    send_failure: bool,
+    peer_error: bool,
+    shutdown: bool,
+    partial_delivery: bool,
+    adaptation_layer: bool,
+    authentication: bool,
+    sender_dry: bool,
+}
+
+macro_rules! event {
+    ($(#[$attr:meta])* $name:ident) => {
+        $(#[$attr])*
+        pub fn $name(mut self, on: bool) -> SctpEvents {
+            self.$name = on;
+            self
+        }
+    };
+}
+
+impl SctpEvents {
+    /// Creates a subscription to no notification.
+    pub fn new() -> SctpEvents {
+        SctpEvents::default()
+    }
+
+    event!(
+        /// Requests the `SCTP_SNDRCV` ancillary data.
+        data_io
+    );
+    event!(
+        /// Subscribes to association changes, see [`Notification::AssocChange`].
+        ///
+        /// [`Notification::AssocChange`]: enum.Notification.html#variant.AssocChange
+        association
+    );
+    event!(
+        /// Subscribes to changes of the peer addresses, see
+        /// [`Notification::PeerAddrChange`].
+        ///
+        /// [`Notification::PeerAddrChange`]: enum.Notification.html#variant.PeerAddrChange
+        address
+    );
+    event!(
+        /// Subscribes to messages which couldn't be delivered.
+        send_failure
+    );
+    event!(
+        /// Subscribes to operation errors reported by the peer.
+        peer_error
+    );
+    event!(
+        /// Subscribes to the peer shutting down the association.
+        shutdown
+    );
+    event!(
+        /// Subscribes to aborted partial deliveries.
+        partial_delivery
+    );
+    event!(
+        /// Subscribes to the adaptation layer indication of the peer.
+        adaptation_layer
+    );
+    event!(
+        /// Subscribes to authentication key events.
+        authentication
+    );
+    event!(
+        /// Subscribes to the send queue becoming empty.
+        sender_dry
+    );
+
+    /// Returns the `struct sctp_event_subscribe` for these events.
+    pub(super) fn to_bytes(self) -> [u8; 10] {
+        [
+            self.data_io as u8,
+            self.association as u8,
+            self.address as u8,
+            self.send_failure as u8,
+            self.peer_error as u8,
+            self.shutdown as u8,
+            self.partial_delivery as u8,
+            self.adaptation_layer as u8,
+            self.authentication as u8,
+            self.sender_dry as u8,
+        ]
+    }
+}
+
+/// State reported by an association change notification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AssocState {
+    /// The association is up.
+    CommUp,
+    /// The association failed.
+    CommLost,
+    /// The peer restarted.
+    Restart,
+    /// The association was shut down gracefully.
+    ShutdownComplete,
+    /// The association couldn't be established.
+    CantStartAssoc,
+    /// A state this crate doesn't know about.
+    Other(u16),
+}
+
+/// A notification received with `SctpStream::recv_msg`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Notification {
+    /// The association changed state (`SCTP_ASSOC_CHANGE`).
+    AssocChange(AssocState),
+    /// A peer address changed state (`SCTP_PEER_ADDR_CHANGE`).
+    PeerAddrChange,
+    /// A message couldn't be delivered (`SCTP_SEND_FAILED`).
+    SendFailed,
+    /// The peer reported an error (`SCTP_REMOTE_ERROR`).
+    RemoteError,
+    /// The peer started a shutdown (`SCTP_SHUTDOWN_EVENT`).
+    Shutdown,
+    /// Another notification, identified by its type.
+    Other(u16),
+}
+
+impl Notification {
+    /// Parses the notification received in `buf`, returning `None` if it is
+    /// truncated.
+    pub fn parse(buf: &[u8]) -> Option<Notification> {
+        let ty = read_u16(buf, 0)?;
+        Some(match ty {
+            0x8001 => {
+                // struct sctp_assoc_change: type, flags, length, state
+                let state = match read_u16(buf, 8)? {
+                    0 => AssocState::CommUp,
+                    1 => AssocState::CommLost,
+                    2 => AssocState::Restart,
+                    3 => AssocState::ShutdownComplete,
+                    4 => AssocState::CantStartAssoc,
+                    state => AssocState::Other(state),
+                };
+                Notification::AssocChange(state)
+            }
+            0x8002 => Notification::PeerAddrChange,
+            0x8003 => Notification::SendFailed,
+            0x8004 => Notification::RemoteError,
+            0x8005 => Notification::Shutdown,
+            ty => Notification::Other(ty),
+        })
+    }
+}
+
+fn read_u16(buf: &[u8], at: usize) -> Option<u16> {
+    let bytes = buf.get(at..at + 2)?;
+    Some(u16::from_ne_bytes([bytes[0], bytes[1]]))
+}
+
+#[test]
+fn test_parse_notifications() {
+    let mut assoc = [0; 20];
+    assoc[..2].copy_from_slice(&0x8001u16.to_ne_bytes());
+    assoc[8..10].copy_from_slice(&1u16.to_ne_bytes());
+    assert_eq!(
+        Notification::parse(&assoc),
+        Some(Notification::AssocChange(AssocState::CommLost))
+    );
+    assert_eq!(Notification::parse(&assoc[..6]), None);
+
+    let shutdown = 0x8005u16.to_ne_bytes();
+    assert_eq!(Notification::parse(&shutdown), Some(Notification::Shutdown));
+    assert_eq!(
+        Notification::parse(&0x8009u16.to_ne_bytes()),
+        Some(Notification::Other(0x8009))
+    );
+
+    let events = SctpEvents::new().association(true).sender_dry(true);
+    assert_eq!(events.to_bytes(), [0, 1, 0, 0, 0, 0, 0, 0, 0, 1]);
+}
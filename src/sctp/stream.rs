@@ -0,0 +1,320 @@
+use async_ready::{AsyncReadReady, AsyncWriteReady};
+use futures_core::Future;
+use futures_io::{AsyncRead, AsyncWrite};
+use futures_util::ready;
+use std::fmt;
+use std::io;
+use std::mem;
+use std::net::{Shutdown, SocketAddr};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use super::SctpEvents;
+use crate::driver::sys;
+use crate::driver::PollEvented;
+
+/// An SCTP association between a local and a remote socket.
+pub struct SctpStream {
+    io: PollEvented<sys::net::SctpSocket>,
+}
+
+/// The future returned by `SctpStream::connect`, which resolves once the
+/// association is established.
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct ConnectFuture {
+    inner: ConnectFutureState,
+}
+
+#[derive(Debug)]
+enum ConnectFutureState {
+    Waiting(SctpStream),
+    Error(io::Error),
+    Empty,
+}
+
+/// What `SctpStream::recv_msg` received.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecvMsg {
+    len: usize,
+    flags: libc::c_int,
+}
+
+impl SctpStream {
+    /// Opens an association to `addr`.
+    pub fn connect(addr: &SocketAddr) -> ConnectFuture {
+        SctpStream::connect_multi(std::slice::from_ref(addr))
+    }
+
+    /// Opens an association to a peer reachable at any address of `addrs`,
+    /// which must share the same port. The kernel tries them in turn and
+    /// uses the others as alternate paths.
+    pub fn connect_multi(addrs: &[SocketAddr]) -> ConnectFuture {
+        let connect = || {
+            let first = addrs.first().ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidInput, "no addresses given")
+            })?;
+            let socket = sys::net::SctpSocket::new(first)?;
+            socket.connect(addrs)?;
+            Ok(SctpStream::new(socket))
+        };
+
+        let inner = match connect() {
+            Ok(stream) => ConnectFutureState::Waiting(stream),
+            Err(e) => ConnectFutureState::Error(e),
+        };
+        ConnectFuture { inner }
+    }
+
+    pub(super) fn new(socket: sys::net::SctpSocket) -> SctpStream {
+        SctpStream {
+            io: PollEvented::new(socket),
+        }
+    }
+
+    /// Returns the primary local address of the association.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.io.get_ref().local_addr()
+    }
+
+    /// Returns the primary address of the peer.
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.io.get_ref().peer_addr()
+    }
+
+    /// Shuts down the read, write, or both halves of the association.
+    pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        let how = match how {
+            Shutdown::Read => libc::SHUT_RD,
+            Shutdown::Write => libc::SHUT_WR,
+            Shutdown::Both => libc::SHUT_RDWR,
+        };
+        self.io.get_ref().shutdown(how)
+    }
+
+    /// Gets the value of the `SCTP_NODELAY` option on this socket.
+    pub fn nodelay(&self) -> io::Result<bool> {
+        self.io.get_ref().nodelay()
+    }
+
+    /// Sets the value of the `SCTP_NODELAY` option on this socket, which
+    /// disables the bundling of small messages.
+    pub fn set_nodelay(&self, nodelay: bool) -> io::Result<()> {
+        self.io.get_ref().set_nodelay(nodelay)
+    }
+
+    /// Selects the notifications delivered by [`recv_msg`] (`SCTP_EVENTS`).
+    ///
+    /// [`recv_msg`]: #method.recv_msg
+    pub fn subscribe(&self, events: &SctpEvents) -> io::Result<()> {
+        self.io.get_ref().set_events(&events.to_bytes())
+    }
+
+    /// Receives data or a notification, see [`recv_msg`].
+    ///
+    /// [`recv_msg`]: #method.recv_msg
+    pub fn poll_recv_msg(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<RecvMsg>> {
+        ready!(Pin::new(&mut self.io).poll_read_ready(cx)?);
+
+        match self.io.get_ref().recv_msg(buf) {
+            Ok((len, flags)) => Poll::Ready(Ok(RecvMsg { len, flags })),
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                Pin::new(&mut self.io).clear_read_ready(cx)?;
+                Poll::Pending
+            }
+            Err(e) => Poll::Ready(Err(e)),
+        }
+    }
+
+    /// Receives data or a notification into `buf`.
+    ///
+    /// Unlike reads, this tells whether the data completes a message, and
+    /// whether it is a notification, to decode with [`Notification::parse`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use futures_net::sctp::{Notification, SctpEvents, SctpStream};
+    ///
+    /// # async fn run(mut stream: SctpStream) -> std::io::Result<()> {
+    /// stream.subscribe(&SctpEvents::new().association(true).shutdown(true))?;
+    ///
+    /// let mut buf = vec![0; 4096];
+    /// loop {
+    ///     let msg = stream.recv_msg(&mut buf).await?;
+    ///     if msg.is_notification() {
+    ///         if let Some(Notification::Shutdown) = Notification::parse(&buf[..msg.len()]) {
+    ///             break;
+    ///         }
+    ///     } else if msg.len() == 0 {
+    ///         break;
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`Notification::parse`]: enum.Notification.html#method.parse
+    pub async fn recv_msg(&mut self, buf: &mut [u8]) -> io::Result<RecvMsg> {
+        futures_util::future::poll_fn(|cx| self.poll_recv_msg(cx, buf)).await
+    }
+}
+
+impl RecvMsg {
+    /// Returns the number of bytes received.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the bytes are a notification rather than data.
+    pub fn is_notification(&self) -> bool {
+        self.flags & sys::net::MSG_NOTIFICATION != 0
+    }
+
+    /// Returns `true` if the bytes complete a message (`MSG_EOR`). A message
+    /// larger than the buffer is received in several parts.
+    pub fn is_eor(&self) -> bool {
+        self.flags & libc::MSG_EOR != 0
+    }
+}
+
+impl AsyncRead for SctpStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.io).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for SctpStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.io).poll_write(cx, buf)
+    }
+
+    fn poll_flush(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_flush(cx)
+    }
+
+    fn poll_close(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_close(cx)
+    }
+}
+
+impl AsyncReadReady for SctpStream {
+    type Ok = sys::event::Ready;
+    type Err = io::Error;
+
+    /// Poll the stream's readiness for reading.
+    fn poll_read_ready(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Self::Ok, Self::Err>> {
+        Pin::new(&mut self.io).poll_read_ready(cx)
+    }
+}
+
+impl AsyncWriteReady for SctpStream {
+    type Ok = sys::event::Ready;
+    type Err = io::Error;
+
+    /// Poll the stream's readiness for writing.
+    fn poll_write_ready(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Self::Ok, Self::Err>> {
+        self.io.poll_write_ready(cx)
+    }
+}
+
+impl fmt::Debug for SctpStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.io.get_ref().fmt(f)
+    }
+}
+
+impl AsRawFd for SctpStream {
+    fn as_raw_fd(&self) -> RawFd {
+        self.io.get_ref().as_raw_fd()
+    }
+}
+
+impl Future for ConnectFuture {
+    type Output = io::Result<SctpStream>;
+
+    fn poll(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<SctpStream>> {
+        match mem::replace(&mut self.inner, ConnectFutureState::Empty) {
+            ConnectFutureState::Waiting(stream) => {
+                // The association is up, or failed, once the socket is
+                // writable.
+                if let Poll::Pending = stream.io.poll_write_ready(cx)? {
+                    self.inner = ConnectFutureState::Waiting(stream);
+                    return Poll::Pending;
+                }
+                if let Some(e) = stream.io.get_ref().take_error()? {
+                    return Poll::Ready(Err(e));
+                }
+                Poll::Ready(Ok(stream))
+            }
+            ConnectFutureState::Error(e) => Poll::Ready(Err(e)),
+            ConnectFutureState::Empty => panic!("can't poll SCTP stream twice"),
+        }
+    }
+}
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_echo_over_loopback() {
+    use crate::runtime::{self, Runtime};
+    use futures_util::io::{AsyncReadExt, AsyncWriteExt};
+
+    let addr = "127.0.0.1:0".parse().unwrap();
+    let mut listener = match super::SctpListener::bind(&addr) {
+        Ok(listener) => listener,
+        // The kernel may be built without SCTP.
+        Err(ref e) if e.raw_os_error() == Some(libc::EPROTONOSUPPORT) => return,
+        Err(e) => panic!("{}", e),
+    };
+    let addr = listener.local_addr().unwrap();
+
+    let mut rt = runtime::default();
+    rt.exec(async {
+        let mut client = SctpStream::connect(&addr).await.unwrap();
+        let (mut server, _) = listener.accept().await.unwrap();
+        server.subscribe(&SctpEvents::new().shutdown(true)).unwrap();
+
+        client.write_all(b"ping").await.unwrap();
+        let mut buf = [0; 64];
+        let msg = server.recv_msg(&mut buf).await.unwrap();
+        assert!(!msg.is_notification() && msg.is_eor());
+        assert_eq!(&buf[..msg.len()], b"ping");
+
+        client.shutdown(Shutdown::Write).unwrap();
+        let msg = server.recv_msg(&mut buf).await.unwrap();
+        assert!(msg.is_notification());
+        assert_eq!(
+            super::Notification::parse(&buf[..msg.len()]),
+            Some(super::Notification::Shutdown)
+        );
+        let _ = client.read(&mut buf).await;
+    });
+}
@@ -0,0 +1,329 @@
+//! An in-memory network for deterministic tests, with the `sim` feature.
+//!
+//! A [`Network`] links simulated hosts, each known by its IP address. The
+//! [`TcpListener`], [`TcpStream`] and [`UdpSocket`] of this module are
+//! opened on a [`Host`] and exchange data through the network only, with
+//! the latency, bandwidth and partitions configured on it. The TCP types
+//! implement [`Transport`] and [`Listener`], so a server written against
+//! those traits runs unchanged on real and simulated sockets.
+//!
+//! Delays are measured with [`time::now`] and waited for with timers.
+//! Once time is [paused], a test goes through its delays instantly and the
+//! same way on every run. Timers fire on the millisecond ticks of the
+//! timer, so a delay after the pause can end up to a millisecond late.
+//!
+//! # Model
+//!
+//! Each direction of a connection, and each UDP socket, sends over a link
+//! of the configured bandwidth: a message starts once the ones before it
+//! are out, and arrives the latency after it is fully sent. Connecting
+//! takes a round trip, and fails with `ConnectionRefused` after one if
+//! nothing listens on the address.
+//!
+//! While two hosts are partitioned, connects between them wait, the bytes
+//! streams send are held back and delivered once the partition heals, as
+//! retransmissions would, and datagrams are lost.
+//!
+//! # Examples
+//!
+//! ```
+//! use futures::prelude::*;
+//! use futures_net::runtime::{self, Runtime};
+//! use futures_net::sim::{Network, TcpListener, TcpStream};
+//! use futures_net::time;
+//! use std::time::Duration;
+//!
+//! let net = Network::new();
+//! net.set_latency(Duration::from_millis(10));
+//! let server = net.host("10.0.0.1".parse().unwrap());
+//! let client = net.host("10.0.0.2".parse().unwrap());
+//!
+//! let mut rt = runtime::default();
+//! rt.exec(async {
+//!     time::pause();
+//!     let start = time::now();
+//!
+//!     let mut listener = TcpListener::bind(&server, 80)?;
+//!     let mut stream = TcpStream::connect(&client, listener.local_addr()).await?;
+//!     let (mut accepted, _) = listener.accept().await?;
+//!     assert!(time::now() - start >= Duration::from_millis(20));
+//!
+//!     stream.write_all(b"ping").await?;
+//!     let mut buf = [0; 4];
+//!     accepted.read_exact(&mut buf).await?;
+//!     assert!(time::now() - start >= Duration::from_millis(30));
+//!     Ok::<_, std::io::Error>(())
+//! })?;
+//! # Ok::<_, std::io::Error>(())
+//! ```
+//!
+//! [`Network`]: struct.Network.html
+//! [`Host`]: struct.Host.html
+//! [`TcpListener`]: struct.TcpListener.html
+//! [`TcpStream`]: struct.TcpStream.html
+//! [`UdpSocket`]: struct.UdpSocket.html
+//! [`Transport`]: ../transport/trait.Transport.html
+//! [`Listener`]: ../transport/trait.Listener.html
+//! [`time::now`]: ../time/fn.now.html
+//! [paused]: ../time/fn.pause.html
+
+mod tcp;
+mod udp;
+
+pub use self::tcp::{Incoming, TcpListener, TcpStream};
+pub use self::udp::UdpSocket;
+
+use parking_lot::{Mutex, MutexGuard};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
+use std::sync::{Arc, Weak};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+use self::tcp::{Backlog, Pipe};
+use self::udp::Inbox;
+use crate::time::{self, Sleep};
+
+/// The first port handed out to sockets bound to port 0.
+const EPHEMERAL_PORTS: u16 = 49152;
+
+/// A simulated network, see the [module documentation](index.html).
+///
+/// The network is cheap to clone, clones refer to the same network.
+#[derive(Clone)]
+pub struct Network {
+    shared: Arc<Mutex<State>>,
+}
+
+struct State {
+    latency: Duration,
+    bandwidth: Option<u64>,
+    /// Pairs of partitioned hosts, the lower address first.
+    partitions: HashSet<(IpAddr, IpAddr)>,
+    /// Ports in use, by listeners, UDP sockets and connected streams.
+    ports: HashSet<SocketAddr>,
+    next_port: u16,
+    listeners: HashMap<SocketAddr, Arc<Mutex<Backlog>>>,
+    udp: HashMap<SocketAddr, Arc<Mutex<Inbox>>>,
+    /// The directions of the open connections, to deliver what they held
+    /// back once a partition heals.
+    pipes: Vec<Weak<Mutex<Pipe>>>,
+    /// Connects waiting for a partition to heal.
+    healed: Vec<Waker>,
+}
+
+/// A host of a [`Network`], which sockets are opened on.
+///
+/// [`Network`]: struct.Network.html
+#[derive(Clone)]
+pub struct Host {
+    net: Network,
+    ip: IpAddr,
+}
+
+impl Network {
+    /// Creates a network without latency or bandwidth limit.
+    pub fn new() -> Network {
+        Network {
+            shared: Arc::new(Mutex::new(State {
+                latency: Duration::ZERO,
+                bandwidth: None,
+                partitions: HashSet::new(),
+                ports: HashSet::new(),
+                next_port: EPHEMERAL_PORTS,
+                listeners: HashMap::new(),
+                udp: HashMap::new(),
+                pipes: Vec::new(),
+                healed: Vec::new(),
+            })),
+        }
+    }
+
+    /// Returns the host of the network with the address `ip`.
+    ///
+    /// Hosts don't need to be declared: any address can be used, and every
+    /// host reaches every other one, itself included, unless partitioned.
+    pub fn host(&self, ip: IpAddr) -> Host {
+        Host {
+            net: self.clone(),
+            ip,
+        }
+    }
+
+    /// Returns the one way latency between hosts.
+    pub fn latency(&self) -> Duration {
+        self.lock().latency
+    }
+
+    /// Sets the one way latency between hosts, for the messages sent from
+    /// then on.
+    pub fn set_latency(&self, latency: Duration) {
+        self.lock().latency = latency;
+    }
+
+    /// Returns the bandwidth of each link, in bytes per second.
+    pub fn bandwidth(&self) -> Option<u64> {
+        self.lock().bandwidth
+    }
+
+    /// Limits the bandwidth of each direction of each connection, and of
+    /// each UDP socket, to `bytes_per_second`, or lifts the limit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bytes_per_second` is zero.
+    pub fn set_bandwidth(&self, bytes_per_second: Option<u64>) {
+        assert!(bytes_per_second != Some(0), "bandwidth cannot be 0");
+        self.lock().bandwidth = bytes_per_second;
+    }
+
+    /// Cuts the hosts `a` and `b` off from each other, in both directions.
+    pub fn partition(&self, a: IpAddr, b: IpAddr) {
+        self.lock().partitions.insert(link(a, b));
+    }
+
+    /// Heals the partition between the hosts `a` and `b`.
+    ///
+    /// The bytes held back by the streams between them get on their way,
+    /// and arrive after the latency of the network.
+    pub fn repair(&self, a: IpAddr, b: IpAddr) {
+        let mut state = self.lock();
+        if !state.partitions.remove(&link(a, b)) {
+            return;
+        }
+        let at = time::now() + state.latency;
+        state.pipes.retain(|pipe| match pipe.upgrade() {
+            Some(pipe) => {
+                let mut pipe = pipe.lock();
+                if link(pipe.src, pipe.dst) == link(a, b) {
+                    pipe.release(at);
+                }
+                true
+            }
+            None => false,
+        });
+        for waker in state.healed.drain(..) {
+            waker.wake();
+        }
+    }
+
+    /// Returns `true` if the hosts `a` and `b` are partitioned.
+    pub fn is_partitioned(&self, a: IpAddr, b: IpAddr) -> bool {
+        self.lock().is_partitioned(a, b)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.shared.lock()
+    }
+
+    /// Waits until the hosts `a` and `b` are not partitioned.
+    fn poll_reachable(&self, cx: &mut Context<'_>, a: IpAddr, b: IpAddr) -> Poll<()> {
+        let mut state = self.lock();
+        if state.is_partitioned(a, b) {
+            state.healed.push(cx.waker().clone());
+            return Poll::Pending;
+        }
+        Poll::Ready(())
+    }
+}
+
+impl State {
+    fn is_partitioned(&self, a: IpAddr, b: IpAddr) -> bool {
+        self.partitions.contains(&link(a, b))
+    }
+
+    /// Takes the port `port` of `ip`, or a free one if `port` is zero.
+    fn bind(&mut self, ip: IpAddr, port: u16) -> io::Result<SocketAddr> {
+        if port != 0 {
+            let addr = SocketAddr::new(ip, port);
+            if !self.ports.insert(addr) {
+                return Err(io::ErrorKind::AddrInUse.into());
+            }
+            return Ok(addr);
+        }
+        for _ in EPHEMERAL_PORTS..=u16::MAX {
+            let addr = SocketAddr::new(ip, self.next_port);
+            self.next_port = self.next_port.checked_add(1).unwrap_or(EPHEMERAL_PORTS);
+            if self.ports.insert(addr) {
+                return Ok(addr);
+            }
+        }
+        Err(io::ErrorKind::AddrNotAvailable.into())
+    }
+
+    /// Returns when a message of `len` bytes sent now over a link whose
+    /// previous messages are out at `link_free` arrives.
+    fn arrival(&self, link_free: &mut Instant, len: usize) -> Instant {
+        let start = (*link_free).max(time::now());
+        let sending = match self.bandwidth {
+            Some(rate) => {
+                Duration::from_nanos((len as u128 * 1_000_000_000 / rate as u128) as u64)
+            }
+            None => Duration::ZERO,
+        };
+        *link_free = start + sending;
+        *link_free + self.latency
+    }
+}
+
+/// The key of the link between two hosts, the same both ways.
+fn link(a: IpAddr, b: IpAddr) -> (IpAddr, IpAddr) {
+    if a <= b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+/// Waits until `at`, with `sleep` kept from one call to the next.
+fn poll_until(sleep: &mut Option<Sleep>, cx: &mut Context<'_>, at: Instant) -> Poll<()> {
+    let sleep = match sleep {
+        Some(sleep) => {
+            if sleep.deadline() != at {
+                sleep.reset(at);
+            }
+            sleep
+        }
+        None => sleep.get_or_insert_with(|| time::sleep_until(at)),
+    };
+    Pin::new(sleep).poll(cx)
+}
+
+impl Default for Network {
+    fn default() -> Network {
+        Network::new()
+    }
+}
+
+impl fmt::Debug for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.lock();
+        f.debug_struct("Network")
+            .field("latency", &state.latency)
+            .field("bandwidth", &state.bandwidth)
+            .field("partitions", &state.partitions)
+            .finish()
+    }
+}
+
+impl Host {
+    /// Returns the address of the host.
+    pub fn ip(&self) -> IpAddr {
+        self.ip
+    }
+
+    /// Returns the network of the host.
+    pub fn network(&self) -> &Network {
+        &self.net
+    }
+}
+
+impl fmt::Debug for Host {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Host").field("ip", &self.ip).finish()
+    }
+}
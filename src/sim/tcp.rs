@@ -0,0 +1,478 @@
+//! Simulated TCP connections.
+
+use futures_core::stream::Stream;
+use futures_io::{AsyncRead, AsyncWrite};
+use futures_util::future::poll_fn;
+use futures_util::ready;
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Waker};
+use std::time::Instant;
+
+use super::{poll_until, Host, Network};
+use crate::time::{self, Sleep};
+use crate::transport::{self, Addr};
+
+/// The bytes a direction of a connection holds, sent and not read yet,
+/// before writes wait.
+const MAX_BUFFERED: usize = 256 * 1024;
+
+/// A simulated TCP listener, see the [module documentation](index.html).
+pub struct TcpListener {
+    net: Network,
+    local: SocketAddr,
+    backlog: Arc<Mutex<Backlog>>,
+}
+
+/// The connections waiting to be accepted by a listener.
+#[derive(Default)]
+pub(super) struct Backlog {
+    queue: VecDeque<(TcpStream, SocketAddr)>,
+    waker: Option<Waker>,
+}
+
+/// A simulated TCP stream, see the [module documentation](index.html).
+///
+/// Dropping the stream closes it. Reads of the peer see the end of the
+/// stream once the bytes sent before arrived, and its writes fail with
+/// `BrokenPipe`.
+pub struct TcpStream {
+    net: Network,
+    local: SocketAddr,
+    peer: SocketAddr,
+    /// Set for connected streams, which own their port.
+    owns_port: bool,
+    read: Arc<Mutex<Pipe>>,
+    write: Arc<Mutex<Pipe>>,
+    sleep: Option<Sleep>,
+}
+
+/// One direction of a connection.
+pub(super) struct Pipe {
+    pub(super) src: std::net::IpAddr,
+    pub(super) dst: std::net::IpAddr,
+    segments: VecDeque<Segment>,
+    /// Bytes sent and not read yet.
+    buffered: usize,
+    /// When the link is done sending the segments.
+    link_free: Instant,
+    /// The writer shut the direction down.
+    closed: bool,
+    /// The reader was dropped.
+    dropped: bool,
+    reader: Option<Waker>,
+    writer: Option<Waker>,
+}
+
+struct Segment {
+    /// When the segment arrives, `None` while held back by a partition.
+    at: Option<Instant>,
+    /// The bytes not read yet, none for the end of the stream.
+    data: Vec<u8>,
+    read: usize,
+}
+
+impl TcpListener {
+    /// Listens on `port` of `host`, or on a free port if `port` is zero.
+    pub fn bind(host: &Host, port: u16) -> io::Result<TcpListener> {
+        let mut state = host.net.lock();
+        let local = state.bind(host.ip, port)?;
+        let backlog = Arc::new(Mutex::new(Backlog::default()));
+        state.listeners.insert(local, backlog.clone());
+        Ok(TcpListener {
+            net: host.net.clone(),
+            local,
+            backlog,
+        })
+    }
+
+    /// Returns the address the listener is bound to.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local
+    }
+
+    /// Polls for the next connection, along with the address of its peer.
+    pub fn poll_accept(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<(TcpStream, SocketAddr)>> {
+        let mut backlog = self.backlog.lock();
+        match backlog.queue.pop_front() {
+            Some(conn) => Poll::Ready(Ok(conn)),
+            None => {
+                backlog.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+
+    /// Accepts the next connection, along with the address of its peer.
+    pub async fn accept(&mut self) -> io::Result<(TcpStream, SocketAddr)> {
+        poll_fn(|cx| self.poll_accept(cx)).await
+    }
+
+    /// Returns a stream of the accepted connections.
+    pub fn incoming(&mut self) -> Incoming<'_> {
+        Incoming { inner: self }
+    }
+}
+
+impl Drop for TcpListener {
+    fn drop(&mut self) {
+        let mut state = self.net.lock();
+        state.ports.remove(&self.local);
+        state.listeners.remove(&self.local);
+        // The queued streams refer to the network, drop them outside of its
+        // lock.
+        let queued = std::mem::take(&mut self.backlog.lock().queue);
+        drop(state);
+        drop(queued);
+    }
+}
+
+impl fmt::Debug for TcpListener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TcpListener")
+            .field("local", &self.local)
+            .finish()
+    }
+}
+
+/// Stream returned by `TcpListener::incoming`.
+#[must_use = "streams do nothing unless polled"]
+#[derive(Debug)]
+pub struct Incoming<'a> {
+    inner: &'a mut TcpListener,
+}
+
+impl Stream for Incoming<'_> {
+    type Item = io::Result<TcpStream>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let (stream, _) = ready!(self.inner.poll_accept(cx))?;
+        Poll::Ready(Some(Ok(stream)))
+    }
+}
+
+impl TcpStream {
+    /// Connects from `host` to `addr`.
+    ///
+    /// Waits for a partition between the hosts to heal, then takes a round
+    /// trip. Fails with `ConnectionRefused` if no listener is bound to
+    /// `addr` when the connection reaches it.
+    pub async fn connect(host: &Host, addr: SocketAddr) -> io::Result<TcpStream> {
+        let net = &host.net;
+        poll_fn(|cx| net.poll_reachable(cx, host.ip, addr.ip())).await;
+        time::sleep(net.latency()).await;
+
+        let mut state = net.lock();
+        let backlog = match state.listeners.get(&addr) {
+            Some(backlog) => backlog.clone(),
+            None => {
+                let latency = state.latency;
+                drop(state);
+                time::sleep(latency).await;
+                return Err(io::ErrorKind::ConnectionRefused.into());
+            }
+        };
+        let local = state.bind(host.ip, 0)?;
+        let now = time::now();
+        let up = Arc::new(Mutex::new(Pipe::new(host.ip, addr.ip(), now)));
+        let down = Arc::new(Mutex::new(Pipe::new(addr.ip(), host.ip, now)));
+        state.pipes.push(Arc::downgrade(&up));
+        state.pipes.push(Arc::downgrade(&down));
+        let latency = state.latency;
+        drop(state);
+
+        let accepted = TcpStream {
+            net: net.clone(),
+            local: addr,
+            peer: local,
+            owns_port: false,
+            read: up.clone(),
+            write: down.clone(),
+            sleep: None,
+        };
+        let mut backlog = backlog.lock();
+        backlog.queue.push_back((accepted, local));
+        if let Some(waker) = backlog.waker.take() {
+            waker.wake();
+        }
+        drop(backlog);
+
+        let stream = TcpStream {
+            net: net.clone(),
+            local,
+            peer: addr,
+            owns_port: true,
+            read: down,
+            write: up,
+            sleep: None,
+        };
+        time::sleep(latency).await;
+        Ok(stream)
+    }
+
+    /// Returns the address of the local end of the stream.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local
+    }
+
+    /// Returns the address of the peer of the stream.
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.peer
+    }
+
+    /// Shuts the write half of the stream down, so the peer reads the end
+    /// of the stream once the bytes sent before arrived.
+    pub fn shutdown_write(&self) {
+        let state = self.net.lock();
+        let mut pipe = self.write.lock();
+        if !pipe.closed {
+            pipe.closed = true;
+            let at = if state.is_partitioned(pipe.src, pipe.dst) {
+                None
+            } else {
+                Some(state.arrival(&mut pipe.link_free, 0))
+            };
+            pipe.push(at, Vec::new());
+        }
+    }
+}
+
+impl Pipe {
+    fn new(src: std::net::IpAddr, dst: std::net::IpAddr, now: Instant) -> Pipe {
+        Pipe {
+            src,
+            dst,
+            segments: VecDeque::new(),
+            buffered: 0,
+            link_free: now,
+            closed: false,
+            dropped: false,
+            reader: None,
+            writer: None,
+        }
+    }
+
+    fn push(&mut self, at: Option<Instant>, data: Vec<u8>) {
+        self.buffered += data.len();
+        self.segments.push_back(Segment { at, data, read: 0 });
+        if let Some(waker) = self.reader.take() {
+            waker.wake();
+        }
+    }
+
+    /// Sends the segments held back by a partition, to arrive at `at`.
+    pub(super) fn release(&mut self, at: Instant) {
+        let mut released = false;
+        for segment in self.segments.iter_mut().filter(|s| s.at.is_none()) {
+            segment.at = Some(at);
+            released = true;
+        }
+        if released {
+            if let Some(waker) = self.reader.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+impl AsyncRead for TcpStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        loop {
+            let mut pipe = this.read.lock();
+            let at = match pipe.segments.front() {
+                Some(segment) => segment.at,
+                None => None,
+            };
+            match at {
+                Some(at) if at <= time::now() => {}
+                Some(at) => {
+                    pipe.reader = Some(cx.waker().clone());
+                    drop(pipe);
+                    ready!(poll_until(&mut this.sleep, cx, at));
+                    continue;
+                }
+                None => {
+                    pipe.reader = Some(cx.waker().clone());
+                    return Poll::Pending;
+                }
+            }
+
+            let segment = pipe.segments.front_mut().unwrap();
+            // The end of the stream stays at the front.
+            if segment.data.is_empty() {
+                return Poll::Ready(Ok(0));
+            }
+            let unread = &segment.data[segment.read..];
+            let n = unread.len().min(buf.len());
+            buf[..n].copy_from_slice(&unread[..n]);
+            segment.read += n;
+            if segment.read == segment.data.len() {
+                pipe.segments.pop_front();
+            }
+            pipe.buffered -= n;
+            if let Some(waker) = pipe.writer.take() {
+                waker.wake();
+            }
+            return Poll::Ready(Ok(n));
+        }
+    }
+}
+
+impl AsyncWrite for TcpStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let state = self.net.lock();
+        let mut pipe = self.write.lock();
+        if pipe.dropped || pipe.closed {
+            return Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()));
+        }
+        let n = buf.len().min(MAX_BUFFERED - pipe.buffered);
+        if n == 0 && !buf.is_empty() {
+            pipe.writer = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+        let at = if state.is_partitioned(pipe.src, pipe.dst) {
+            None
+        } else {
+            Some(state.arrival(&mut pipe.link_free, n))
+        };
+        pipe.push(at, buf[..n].to_vec());
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.shutdown_write();
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Drop for TcpStream {
+    fn drop(&mut self) {
+        self.shutdown_write();
+        let mut read = self.read.lock();
+        read.dropped = true;
+        if let Some(waker) = read.writer.take() {
+            waker.wake();
+        }
+        drop(read);
+        if self.owns_port {
+            self.net.lock().ports.remove(&self.local);
+        }
+    }
+}
+
+impl fmt::Debug for TcpStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TcpStream")
+            .field("local", &self.local)
+            .field("peer", &self.peer)
+            .finish()
+    }
+}
+
+impl transport::Transport for TcpStream {
+    fn local_addr(&self) -> io::Result<Addr> {
+        Ok(Addr::Tcp(self.local))
+    }
+
+    fn peer_addr(&self) -> io::Result<Addr> {
+        Ok(Addr::Tcp(self.peer))
+    }
+}
+
+impl transport::Listener for TcpListener {
+    type Io = TcpStream;
+
+    fn poll_accept(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<(TcpStream, Addr)>> {
+        let (stream, peer) = ready!(self.get_mut().poll_accept(cx))?;
+        Poll::Ready(Ok((stream, Addr::Tcp(peer))))
+    }
+
+    fn local_addr(&self) -> io::Result<Addr> {
+        Ok(Addr::Tcp(self.local))
+    }
+}
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_bandwidth_and_partitions_delay_bytes() {
+    use crate::runtime::{self, Runtime};
+    use futures_util::future::{select, Either};
+    use futures_util::io::{AsyncReadExt, AsyncWriteExt};
+    use std::time::Duration;
+
+    let net = Network::new();
+    net.set_latency(Duration::from_millis(5));
+    net.set_bandwidth(Some(1000));
+    let (a, b) = (
+        net.host([10, 0, 0, 1].into()),
+        net.host([10, 0, 0, 2].into()),
+    );
+
+    let mut rt = runtime::default();
+    rt.exec(async {
+        time::pause();
+        let mut listener = TcpListener::bind(&b, 0).unwrap();
+        let refused = TcpStream::connect(&a, "10.0.0.2:1".parse().unwrap()).await;
+        assert_eq!(
+            refused.unwrap_err().kind(),
+            io::ErrorKind::ConnectionRefused
+        );
+
+        let mut client = TcpStream::connect(&a, listener.local_addr()).await.unwrap();
+        let (mut server, peer) = listener.accept().await.unwrap();
+        assert_eq!(peer, client.local_addr());
+
+        // 100 bytes take 100ms to send, then the latency to arrive.
+        let start = time::now();
+        client.write_all(&[7; 100]).await.unwrap();
+        let mut buf = [0; 100];
+        server.read_exact(&mut buf).await.unwrap();
+        assert_eq!(time::now() - start, Duration::from_millis(105));
+
+        // Bytes sent during a partition wait for it to heal.
+        net.partition(a.ip(), b.ip());
+        client.write_all(b"x").await.unwrap();
+        let read = server.read(&mut buf);
+        let wait = time::sleep(Duration::from_secs(10));
+        let read = match select(read, wait).await {
+            Either::Left(_) => panic!("read across a partition"),
+            Either::Right((_, read)) => read,
+        };
+        net.repair(a.ip(), b.ip());
+        assert_eq!(read.await.unwrap(), 1);
+
+        drop(client);
+        assert_eq!(server.read(&mut buf).await.unwrap(), 0);
+        assert_eq!(
+            server.write(b"y").await.unwrap_err().kind(),
+            io::ErrorKind::BrokenPipe
+        );
+    });
+}
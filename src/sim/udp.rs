@@ -0,0 +1,189 @@
+//! Simulated UDP sockets.
+
+use futures_util::future::poll_fn;
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::task::{Context, Poll, Waker};
+use std::time::Instant;
+
+use super::{poll_until, Host, Network};
+use crate::time::{self, Sleep};
+
+/// The datagrams a socket holds, received and not read yet, before the next
+/// ones are dropped.
+const MAX_QUEUED: usize = 1024;
+
+/// A simulated UDP socket, see the [module documentation](index.html).
+///
+/// Datagrams sent to an address nothing is bound to, across a partition or
+/// to a socket with too many unread datagrams are lost.
+pub struct UdpSocket {
+    net: Network,
+    local: SocketAddr,
+    inbox: Arc<Mutex<Inbox>>,
+    /// When the link of the socket is done sending the datagrams.
+    link_free: Instant,
+    sleep: Option<Sleep>,
+}
+
+/// The datagrams sent to a socket, in arrival order.
+#[derive(Default)]
+pub(super) struct Inbox {
+    datagrams: VecDeque<(Instant, SocketAddr, Vec<u8>)>,
+    waker: Option<Waker>,
+}
+
+impl UdpSocket {
+    /// Binds to `port` of `host`, or to a free port if `port` is zero.
+    pub fn bind(host: &Host, port: u16) -> io::Result<UdpSocket> {
+        let mut state = host.net.lock();
+        let local = state.bind(host.ip, port)?;
+        let inbox = Arc::new(Mutex::new(Inbox::default()));
+        state.udp.insert(local, inbox.clone());
+        Ok(UdpSocket {
+            net: host.net.clone(),
+            local,
+            inbox,
+            link_free: time::now(),
+            sleep: None,
+        })
+    }
+
+    /// Returns the address the socket is bound to.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local
+    }
+
+    /// Sends `buf` to `target`, returning the number of bytes sent.
+    ///
+    /// A datagram is sent as a whole, whether it arrives or not.
+    pub async fn send_to(
+        &mut self,
+        buf: &[u8],
+        target: SocketAddr,
+    ) -> io::Result<usize> {
+        let state = self.net.lock();
+        if state.is_partitioned(self.local.ip(), target.ip()) {
+            return Ok(buf.len());
+        }
+        let at = state.arrival(&mut self.link_free, buf.len());
+        if let Some(inbox) = state.udp.get(&target) {
+            let mut inbox = inbox.lock();
+            if inbox.datagrams.len() < MAX_QUEUED {
+                let i = inbox.datagrams.partition_point(|&(due, _, _)| due <= at);
+                inbox.datagrams.insert(i, (at, self.local, buf.to_vec()));
+                if let Some(waker) = inbox.waker.take() {
+                    waker.wake();
+                }
+            }
+        }
+        Ok(buf.len())
+    }
+
+    /// Polls for the next datagram, copied into `buf` along with its
+    /// sender.
+    ///
+    /// The part of a datagram which doesn't fit in `buf` is discarded.
+    pub fn poll_recv_from(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<(usize, SocketAddr)>> {
+        loop {
+            let mut inbox = self.inbox.lock();
+            let at = match inbox.datagrams.front() {
+                Some(&(at, _, _)) => at,
+                None => {
+                    inbox.waker = Some(cx.waker().clone());
+                    return Poll::Pending;
+                }
+            };
+            if at > time::now() {
+                // An earlier datagram may still come in.
+                inbox.waker = Some(cx.waker().clone());
+                drop(inbox);
+                futures_util::ready!(poll_until(&mut self.sleep, cx, at));
+                continue;
+            }
+            let (_, from, data) = inbox.datagrams.pop_front().unwrap();
+            let n = data.len().min(buf.len());
+            buf[..n].copy_from_slice(&data[..n]);
+            return Poll::Ready(Ok((n, from)));
+        }
+    }
+
+    /// Receives the next datagram into `buf`, returning its length and its
+    /// sender.
+    pub async fn recv_from(
+        &mut self,
+        buf: &mut [u8],
+    ) -> io::Result<(usize, SocketAddr)> {
+        poll_fn(|cx| self.poll_recv_from(cx, buf)).await
+    }
+}
+
+impl Drop for UdpSocket {
+    fn drop(&mut self) {
+        let mut state = self.net.lock();
+        state.ports.remove(&self.local);
+        state.udp.remove(&self.local);
+    }
+}
+
+impl fmt::Debug for UdpSocket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UdpSocket")
+            .field("local", &self.local)
+            .finish()
+    }
+}
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_datagrams_are_lost_across_partitions() {
+    use crate::runtime::{self, Runtime};
+    use std::time::Duration;
+
+    let net = Network::new();
+    net.set_latency(Duration::from_millis(3));
+    let (a, b) = (
+        net.host([10, 0, 0, 1].into()),
+        net.host([10, 0, 0, 2].into()),
+    );
+
+    let mut rt = runtime::default();
+    rt.exec(async {
+        time::pause();
+        let mut sender = UdpSocket::bind(&a, 0).unwrap();
+        let mut receiver = UdpSocket::bind(&b, 53).unwrap();
+        assert_eq!(
+            UdpSocket::bind(&b, 53).unwrap_err().kind(),
+            io::ErrorKind::AddrInUse
+        );
+
+        net.partition(a.ip(), b.ip());
+        sender
+            .send_to(b"lost", receiver.local_addr())
+            .await
+            .unwrap();
+        net.repair(a.ip(), b.ip());
+
+        let start = time::now();
+        sender
+            .send_to(b"found", receiver.local_addr())
+            .await
+            .unwrap();
+        let mut buf = [0; 16];
+        let (n, from) = receiver.recv_from(&mut buf).await.unwrap();
+        assert_eq!((&buf[..n], from), (&b"found"[..], sender.local_addr()));
+        // The first timer after the pause is rounded up to a tick.
+        let elapsed = time::now() - start;
+        assert!(
+            elapsed >= Duration::from_millis(3) && elapsed < Duration::from_millis(4)
+        );
+    });
+}
@@ -0,0 +1,38 @@
+//! Asynchronous tasks.
+//!
+//! Helpers for code running inside a task, and re-exports of the spawning
+//! functions of the [`runtime`] module.
+//!
+//! # Cooperative scheduling
+//!
+//! Tasks spawned on the runtime get a budget of I/O readiness checks each
+//! time they are polled. When a task keeps finding its sockets ready and
+//! spends the budget, further checks return `Pending` and the task is
+//! rescheduled behind the other tasks, so a chatty peer can't monopolize
+//! the executor. Use [`yield_now`] for the same effect in compute loops.
+//!
+//! # Task-local storage
+//!
+//! Values declared with [`task_local!`] follow a future across `.await`
+//! points, which lets per-request context such as a request id reach
+//! handler code without being passed around.
+//!
+//! [`runtime`]: ../runtime/index.html
+//! [`task_local!`]: macro.task_local.html
+//! [`yield_now`]: fn.yield_now.html
+
+mod task_local;
+mod yield_now;
+
+pub use self::task_local::{AccessError, LocalKey, TaskLocalFuture};
+pub use self::yield_now::{yield_now, YieldNow};
+#[cfg(feature = "runtime")]
+#[doc(inline)]
+pub use crate::runtime::task::name;
+#[cfg(feature = "runtime")]
+#[doc(inline)]
+pub use crate::runtime::{
+    is_cancelled, spawn, spawn_local, spawn_named, JoinError, JoinHandle,
+};
+#[doc(inline)]
+pub use crate::task_local;
@@ -0,0 +1,261 @@
+use futures_core::future::Future;
+use std::cell::RefCell;
+use std::error::Error;
+use std::fmt;
+use std::mem;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::thread;
+
+/// Declares new task-local keys of type [`LocalKey`].
+///
+/// The syntax mirrors `thread_local!`, without an initializer: a task-local
+/// only has a value inside a [`LocalKey::scope`].
+///
+/// # Examples
+///
+/// ```
+/// use futures_net::task_local;
+///
+/// task_local! {
+///     static REQUEST_ID: u64;
+///     pub static USER: String;
+/// }
+/// ```
+///
+/// [`LocalKey`]: task/struct.LocalKey.html
+/// [`LocalKey::scope`]: task/struct.LocalKey.html#method.scope
+#[macro_export]
+macro_rules! task_local {
+    () => {};
+    ($(#[$attr:meta])* $vis:vis static $name:ident: $t:ty; $($rest:tt)*) => {
+        $crate::__task_local_inner!($(#[$attr])* $vis $name, $t);
+        $crate::task_local!($($rest)*);
+    };
+    ($(#[$attr:meta])* $vis:vis static $name:ident: $t:ty) => {
+        $crate::__task_local_inner!($(#[$attr])* $vis $name, $t);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __task_local_inner {
+    ($(#[$attr:meta])* $vis:vis $name:ident, $t:ty) => {
+        $(#[$attr])*
+        $vis static $name: $crate::task::LocalKey<$t> = {
+            std::thread_local! {
+                static __KEY: std::cell::RefCell<Option<$t>> =
+                    std::cell::RefCell::new(None);
+            }
+
+            $crate::task::LocalKey { inner: &__KEY }
+        };
+    };
+}
+
+/// A key for task-local data, declared with [`task_local!`].
+///
+/// A value is bound to the key for the duration of a future with
+/// [`scope`], and can be accessed from any code that future runs, across
+/// `.await` points. Tasks spawned from within the scope don't inherit the
+/// value.
+///
+/// [`task_local!`]: ../macro.task_local.html
+/// [`scope`]: #method.scope
+pub struct LocalKey<T: 'static> {
+    // The value lives in a thread-local while the future owning it is
+    // polled, and back in the future in between polls.
+    #[doc(hidden)]
+    pub inner: &'static thread::LocalKey<RefCell<Option<T>>>,
+}
+
+/// Error returned by [`LocalKey::try_with`] outside of a scope.
+///
+/// [`LocalKey::try_with`]: struct.LocalKey.html#method.try_with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccessError {
+    _priv: (),
+}
+
+impl<T: 'static> LocalKey<T> {
+    /// Binds `value` to the key while `fut` runs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures_net::runtime::{self, Runtime};
+    /// use futures_net::task::{self, task_local};
+    ///
+    /// task_local! {
+    ///     static REQUEST_ID: u64;
+    /// }
+    ///
+    /// async fn handle() -> u64 {
+    ///     task::yield_now().await;
+    ///     REQUEST_ID.get()
+    /// }
+    ///
+    /// let mut rt = runtime::default();
+    /// let id = rt.exec(REQUEST_ID.scope(7, handle()));
+    /// assert_eq!(id, 7);
+    /// ```
+    pub fn scope<F>(&'static self, value: T, fut: F) -> TaskLocalFuture<T, F>
+    where
+        F: Future,
+    {
+        TaskLocalFuture {
+            key: self,
+            slot: Some(value),
+            fut,
+        }
+    }
+
+    /// Binds `value` to the key while the closure `f` runs.
+    pub fn sync_scope<F, R>(&'static self, value: T, f: F) -> R
+    where
+        F: FnOnce() -> R,
+    {
+        let mut slot = Some(value);
+        self.enter(&mut slot, f)
+    }
+
+    /// Accesses the value bound to the key.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a [`scope`].
+    ///
+    /// [`scope`]: #method.scope
+    pub fn with<F, R>(&'static self, f: F) -> R
+    where
+        F: FnOnce(&T) -> R,
+    {
+        self.try_with(f)
+            .expect("cannot access a task-local outside of its scope")
+    }
+
+    /// Accesses the value bound to the key, returning an error if called
+    /// outside of a [`scope`].
+    ///
+    /// [`scope`]: #method.scope
+    pub fn try_with<F, R>(&'static self, f: F) -> Result<R, AccessError>
+    where
+        F: FnOnce(&T) -> R,
+    {
+        self.inner.with(|cell| match cell.borrow().as_ref() {
+            Some(value) => Ok(f(value)),
+            None => Err(AccessError { _priv: () }),
+        })
+    }
+
+    /// Returns a copy of the value bound to the key.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a [`scope`].
+    ///
+    /// [`scope`]: #method.scope
+    pub fn get(&'static self) -> T
+    where
+        T: Clone,
+    {
+        self.with(T::clone)
+    }
+
+    fn enter<F, R>(&'static self, slot: &mut Option<T>, f: F) -> R
+    where
+        F: FnOnce() -> R,
+    {
+        struct Guard<'a, T: 'static> {
+            key: &'static LocalKey<T>,
+            slot: &'a mut Option<T>,
+        }
+
+        impl<T: 'static> Drop for Guard<'_, T> {
+            fn drop(&mut self) {
+                let slot = &mut *self.slot;
+                self.key
+                    .inner
+                    .with(|cell| mem::swap(slot, &mut *cell.borrow_mut()));
+            }
+        }
+
+        // Swapping back and forth keeps an outer value of the same key
+        // around while a nested scope runs.
+        self.inner
+            .with(|cell| mem::swap(slot, &mut *cell.borrow_mut()));
+        let _guard = Guard { key: self, slot };
+        f()
+    }
+}
+
+impl<T: 'static> fmt::Debug for LocalKey<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad("LocalKey { .. }")
+    }
+}
+
+/// Future returned by [`LocalKey::scope`].
+///
+/// [`LocalKey::scope`]: struct.LocalKey.html#method.scope
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct TaskLocalFuture<T: 'static, F> {
+    key: &'static LocalKey<T>,
+    slot: Option<T>,
+    fut: F,
+}
+
+impl<T: 'static, F: Future> Future for TaskLocalFuture<T, F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        // Safety: `fut` is never moved out of `self`, only `slot` is, and it
+        // isn't pinned.
+        let this = unsafe { self.get_unchecked_mut() };
+        let fut = unsafe { Pin::new_unchecked(&mut this.fut) };
+        this.key.enter(&mut this.slot, || fut.poll(cx))
+    }
+}
+
+impl<T: 'static, F> fmt::Debug for TaskLocalFuture<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TaskLocalFuture").finish()
+    }
+}
+
+impl fmt::Display for AccessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("task-local value not set")
+    }
+}
+
+impl Error for AccessError {}
+
+#[test]
+fn test_scopes_nest_and_restore() {
+    crate::task_local! {
+        static KEY: u32;
+    }
+
+    assert!(KEY.try_with(|_| ()).is_err());
+
+    let mut outer = Box::pin(KEY.scope(1, async {
+        assert_eq!(KEY.get(), 1);
+        KEY.scope(2, async {
+            crate::task::yield_now().await;
+            assert_eq!(KEY.get(), 2);
+        })
+        .await;
+        assert_eq!(KEY.get(), 1);
+        KEY.sync_scope(3, || assert_eq!(KEY.get(), 3));
+        KEY.get()
+    }));
+
+    // Between polls the value is stored back in the scope, so other code
+    // running on the thread doesn't see it.
+    let waker = futures_util::task::noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    assert!(outer.as_mut().poll(&mut cx).is_pending());
+    assert!(KEY.try_with(|_| ()).is_err());
+    assert_eq!(outer.as_mut().poll(&mut cx), Poll::Ready(1));
+}
@@ -0,0 +1,76 @@
+use futures_core::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// Yields execution back to the executor.
+///
+/// The current task is rescheduled at the back of the run queue, giving the
+/// other tasks a chance to run. Long computations should call this now and
+/// then so they don't starve the connections sharing their thread.
+///
+/// # Examples
+///
+/// ```
+/// use futures_net::runtime::Runtime;
+/// use futures_net::task;
+///
+/// #[futures_net::main]
+/// async fn main() {
+///     let mut sum = 0u64;
+///     for i in 0..10_000u64 {
+///         sum += i;
+///         if i % 1_000 == 0 {
+///             task::yield_now().await;
+///         }
+///     }
+///     assert_eq!(sum, 49_995_000);
+/// }
+/// ```
+pub fn yield_now() -> YieldNow {
+    YieldNow { yielded: false }
+}
+
+/// Future returned by [`yield_now`].
+///
+/// [`yield_now`]: fn.yield_now.html
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+#[derive(Debug)]
+pub struct YieldNow {
+    yielded: bool,
+}
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.yielded {
+            return Poll::Ready(());
+        }
+
+        self.yielded = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+#[cfg(feature = "runtime")]
+#[test]
+fn yield_now_lets_other_tasks_run() {
+    use crate::runtime::{self, Runtime, Spawner};
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    let mut rt = runtime::default();
+    let ran = Rc::new(Cell::new(false));
+
+    let flag = ran.clone();
+    rt.spawner()
+        .spawn_local(Box::pin(async move { flag.set(true) }))
+        .unwrap();
+
+    rt.exec(async {
+        assert!(!ran.get());
+        yield_now().await;
+        assert!(ran.get());
+    });
+}
@@ -0,0 +1,107 @@
+//! Small-write coalescing of a `TcpStream`.
+
+use futures_util::ready;
+use std::io::{self, IoSlice};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use crate::driver::timer::{clock, Timer};
+
+/// Small writes held back by `TcpStream::coalesce_writes`.
+#[derive(Debug)]
+pub(super) struct Coalescer {
+    buf: Vec<u8>,
+    /// Writes of this many bytes or more are not held back, and no more
+    /// than this many bytes are.
+    threshold: usize,
+    delay: Duration,
+    /// Fires `delay` after the oldest write held back.
+    timer: Option<Timer>,
+}
+
+impl Coalescer {
+    pub(super) fn new(threshold: usize, delay: Duration) -> Coalescer {
+        Coalescer {
+            buf: Vec::with_capacity(threshold),
+            threshold,
+            delay,
+            timer: None,
+        }
+    }
+
+    /// Whether no bytes are held back.
+    pub(super) fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    /// Holds back the bytes of `bufs` if they are few enough, returning
+    /// `None` if the caller has to write them itself, which it can once the
+    /// bytes held back before were written with `write`.
+    pub(super) fn poll_write<W>(
+        &mut self,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+        mut write: W,
+    ) -> Poll<io::Result<Option<usize>>>
+    where
+        W: FnMut(&mut Context<'_>, &[u8]) -> Poll<io::Result<usize>>,
+    {
+        let len = bufs.iter().map(|buf| buf.len()).sum::<usize>();
+        if len >= self.threshold {
+            ready!(self.poll_drain(cx, &mut write))?;
+            return Poll::Ready(Ok(None));
+        }
+        if self.buf.len() + len > self.threshold {
+            ready!(self.poll_drain(cx, &mut write))?;
+        }
+
+        if self.buf.is_empty() {
+            let deadline = clock::now() + self.delay;
+            match &mut self.timer {
+                Some(timer) => timer.reset(deadline),
+                None => self.timer = Some(Timer::new(deadline)),
+            }
+        }
+        for buf in bufs {
+            self.buf.extend_from_slice(buf);
+        }
+        Poll::Ready(Ok(Some(len)))
+    }
+
+    /// Writes the bytes held back once the delay of the oldest one elapsed,
+    /// waking up the task when it does.
+    pub(super) fn poll_due<W>(
+        &mut self,
+        cx: &mut Context<'_>,
+        write: W,
+    ) -> Poll<io::Result<()>>
+    where
+        W: FnMut(&mut Context<'_>, &[u8]) -> Poll<io::Result<usize>>,
+    {
+        if self.buf.is_empty() {
+            return Poll::Ready(Ok(()));
+        }
+        ready!(self.timer.as_mut().unwrap().poll_elapsed(cx))?;
+        self.poll_drain(cx, write)
+    }
+
+    /// Writes every byte held back.
+    pub(super) fn poll_drain<W>(
+        &mut self,
+        cx: &mut Context<'_>,
+        mut write: W,
+    ) -> Poll<io::Result<()>>
+    where
+        W: FnMut(&mut Context<'_>, &[u8]) -> Poll<io::Result<usize>>,
+    {
+        while !self.buf.is_empty() {
+            match ready!(write(cx, &self.buf))? {
+                0 => return Poll::Ready(Err(io::ErrorKind::WriteZero.into())),
+                n => {
+                    self.buf.drain(..n);
+                }
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+}
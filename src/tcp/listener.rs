@@ -1,21 +1,86 @@
 use async_ready::AsyncReady;
 use futures_core::stream::Stream;
 use futures_util::ready;
+use std::collections::VecDeque;
 use std::fmt;
 use std::io;
-use std::net::{self, SocketAddr};
+use std::net::{IpAddr, SocketAddr};
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::{Context, Poll};
 
+use super::shaping::Shaping;
 use super::TcpStream;
+use crate::driver::fd_reserve::FdReserve;
 use crate::driver::sys;
-use crate::driver::PollEvented;
+use crate::driver::{Handle, PollEvented};
+use crate::error::{self, Operation};
+use crate::metrics::SocketMetrics;
+use crate::transport::{Addr, CloseHook, ConnectionObserver};
 
 /// A TCP socket server, listening for connections.
+///
+/// # Running out of file descriptors
+///
+/// If the process hits its descriptor limit, `accept` fails with `EMFILE`
+/// while the connection stays queued in the backlog, so every following
+/// accept fails the same way. Either enable [`set_fd_reserve`], which lets
+/// the listener close such connections to drain the backlog, or classify
+/// errors with [`AcceptErrorKind`] and back off before accepting again.
+///
+/// # Batching
+///
+/// Once the listener is readable it accepts up to [`accept_batch`]
+/// connections in a row, and yields the ones it queued on the following
+/// polls without another system call or trip through the reactor.
+///
+/// # Shaping
+///
+/// A listener can cap how fast it accepts connections with
+/// [`set_max_accept_rate`], how many connections each source address keeps
+/// open with [`set_max_connections_per_ip`], and the bandwidth shared by
+/// all the streams it accepted with [`set_bandwidth_limit`].
+///
+/// # Examples
+///
+/// ```no_run
+/// use futures::prelude::*;
+/// use futures_net::TcpListener;
+///
+/// # async fn run() -> std::io::Result<()> {
+/// let addr = "0.0.0.0:8080".parse().unwrap();
+/// let mut listener = TcpListener::bind(&addr)?;
+/// listener.set_max_accept_rate(Some(100));
+/// listener.set_max_connections_per_ip(Some(8));
+/// listener.set_bandwidth_limit(Some(10 * 1024 * 1024));
+///
+/// let mut incoming = listener.incoming();
+/// while let Some(stream) = incoming.next().await {
+///     drop(stream?);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`accept_batch`]: #method.accept_batch
+/// [`set_fd_reserve`]: #method.set_fd_reserve
+/// [`AcceptErrorKind`]: ../error/enum.AcceptErrorKind.html
+/// [`set_max_accept_rate`]: #method.set_max_accept_rate
+/// [`set_max_connections_per_ip`]: #method.set_max_connections_per_ip
+/// [`set_bandwidth_limit`]: #method.set_bandwidth_limit
 pub struct TcpListener {
     io: PollEvented<sys::net::TcpListener>,
+    /// Accepted by the last batch and not yielded yet.
+    accepted: VecDeque<(sys::net::TcpStream, SocketAddr)>,
+    accept_batch: usize,
+    reserve: Option<FdReserve>,
+    shaping: Shaping,
+    metrics: SocketMetrics,
+    observer: Option<Arc<dyn ConnectionObserver>>,
 }
 
+const DEFAULT_ACCEPT_BATCH: usize = 16;
+
 impl TcpListener {
     pub fn bind(addr: &SocketAddr) -> io::Result<TcpListener> {
         let l = sys::net::TcpListener::bind(addr)?;
@@ -24,7 +89,44 @@ impl TcpListener {
 
     fn new(listener: sys::net::TcpListener) -> TcpListener {
         let io = PollEvented::new(listener);
-        TcpListener { io }
+        TcpListener {
+            io,
+            accepted: VecDeque::with_capacity(DEFAULT_ACCEPT_BATCH),
+            accept_batch: DEFAULT_ACCEPT_BATCH,
+            reserve: None,
+            shaping: Shaping::new(),
+            metrics: SocketMetrics::socket(),
+            observer: None,
+        }
+    }
+
+    /// Creates a `TcpListener` from a listening `std::net::TcpListener`,
+    /// moving it into nonblocking mode.
+    ///
+    /// Like [`TcpStream::from_std`], this takes listeners configured
+    /// elsewhere, e.g. a `socket2::Socket` with `SO_REUSEPORT` set.
+    ///
+    /// [`TcpStream::from_std`]: struct.TcpStream.html#method.from_std
+    pub fn from_std(listener: std::net::TcpListener) -> io::Result<TcpListener> {
+        sys::net::TcpListener::from_std(listener).map(TcpListener::new)
+    }
+
+    /// Deregisters the listener from its reactor and returns it as a
+    /// `std::net::TcpListener`, still in nonblocking mode.
+    pub fn into_std(self) -> io::Result<std::net::TcpListener> {
+        let io = self.io.into_inner()?;
+        Ok(unsafe { std::net::TcpListener::from_raw_fd(io.into_raw_fd()) })
+    }
+
+    /// Registers the listener with the reactor of `handle`, e.g. the
+    /// [`high_priority`] one, instead of the reactor of the task which
+    /// polls it first.
+    ///
+    /// Fails with `AlreadyExists` if the listener was polled already.
+    ///
+    /// [`high_priority`]: ../driver/fn.high_priority.html
+    pub fn register_with(&self, handle: &Handle) -> io::Result<()> {
+        self.io.register_with(handle)
     }
 
     pub fn local_addr(&self) -> io::Result<SocketAddr> {
@@ -43,20 +145,262 @@ impl TcpListener {
         self.io.get_ref().set_ttl(ttl)
     }
 
-    fn poll_accept_std(
-        mut self: Pin<&mut Self>,
+    /// Gets the value of the `SO_BUSY_POLL` option on this socket.
+    pub fn busy_poll(&self) -> io::Result<u32> {
+        self.io.get_ref().busy_poll()
+    }
+
+    /// Sets the value of the `SO_BUSY_POLL` option on this socket, in
+    /// microseconds.
+    pub fn set_busy_poll(&self, micros: u32) -> io::Result<()> {
+        self.io.get_ref().set_busy_poll(micros)
+    }
+
+    /// Gets the value of the `SO_PREFER_BUSY_POLL` option on this socket.
+    pub fn prefer_busy_poll(&self) -> io::Result<bool> {
+        self.io.get_ref().prefer_busy_poll()
+    }
+
+    /// Sets the value of the `SO_PREFER_BUSY_POLL` option on this socket.
+    pub fn set_prefer_busy_poll(&self, prefer: bool) -> io::Result<()> {
+        self.io.get_ref().set_prefer_busy_poll(prefer)
+    }
+
+    /// Gets the value of the `SO_INCOMING_CPU` option on this socket.
+    ///
+    /// For more information about this option, see [`set_incoming_cpu`].
+    ///
+    /// [`set_incoming_cpu`]: #method.set_incoming_cpu
+    pub fn incoming_cpu(&self) -> io::Result<Option<usize>> {
+        self.io.get_ref().incoming_cpu()
+    }
+
+    /// Sets the value of the `SO_INCOMING_CPU` option on this socket.
+    ///
+    /// With one listener per CPU bound to the same address with
+    /// `SO_REUSEPORT`, each set to its own CPU, the kernel hands a new
+    /// connection to the listener of the CPU which received its first
+    /// packet (Linux 6.3+, or with a matching number of receive queues
+    /// before). Serving each listener from a thread pinned to its CPU then
+    /// keeps the connection data in that CPU's cache, from the interrupt to
+    /// the application.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use futures::prelude::*;
+    /// use futures_net::runtime::{self, Builder, Runtime};
+    /// use futures_net::TcpListener;
+    /// use net2::unix::UnixTcpBuilderExt;
+    /// use std::thread;
+    ///
+    /// let threads: Vec<_> = runtime::available_cpus()
+    ///     .unwrap()
+    ///     .into_iter()
+    ///     .map(|cpu| {
+    ///         thread::spawn(move || -> std::io::Result<()> {
+    ///             runtime::pin_current_thread(cpu)?;
+    ///             let listener = net2::TcpBuilder::new_v4()?
+    ///                 .reuse_port(true)?
+    ///                 .bind("0.0.0.0:8080")?
+    ///                 .listen(1024)?;
+    ///             let mut listener = TcpListener::from_std(listener)?;
+    ///             listener.set_incoming_cpu(cpu)?;
+    ///
+    ///             let mut rt = Builder::new_current_thread().build()?;
+    ///             rt.exec(async {
+    ///                 let mut incoming = listener.incoming();
+    ///                 while let Some(stream) = incoming.next().await {
+    ///                     stream?.write_all(b"hello").await?;
+    ///                 }
+    ///                 Ok(())
+    ///             })
+    ///         })
+    ///     })
+    ///     .collect();
+    /// # drop(threads);
+    /// ```
+    pub fn set_incoming_cpu(&self, cpu: usize) -> io::Result<()> {
+        self.io.get_ref().set_incoming_cpu(cpu)
+    }
+
+    /// Returns the most connections accepted in a row once the listener is
+    /// readable.
+    pub fn accept_batch(&self) -> usize {
+        self.accept_batch
+    }
+
+    /// Accepts up to `max` connections in a row once the listener is
+    /// readable, 16 by default.
+    ///
+    /// Larger batches drain connection floods with fewer wakeups, at the
+    /// cost of holding accepted connections in the listener until they are
+    /// yielded. They are closed if the listener is dropped first. Listeners
+    /// with a [maximum accept rate] accept one connection at a time, so the
+    /// others wait in the backlog.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max` is zero.
+    ///
+    /// [maximum accept rate]: #method.set_max_accept_rate
+    pub fn set_accept_batch(&mut self, max: usize) {
+        assert!(max > 0, "empty accept batch");
+        self.accept_batch = max;
+        self.accepted.reserve(max);
+    }
+
+    /// Keeps a spare file descriptor around to recover from descriptor
+    /// exhaustion.
+    ///
+    /// When accepting fails with `EMFILE` or `ENFILE` the listener releases
+    /// the spare descriptor, accepts the pending connection, closes it at
+    /// once and reclaims the spare. The error is still returned, but the
+    /// refused peer sees its connection closed and the backlog keeps
+    /// draining, so the accept loop doesn't spin on the same connection.
+    ///
+    /// Disabled by default.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use futures_net::TcpListener;
+    ///
+    /// let addr = "127.0.0.1:0".parse().unwrap();
+    /// let mut listener = TcpListener::bind(&addr).unwrap();
+    /// listener.set_fd_reserve(true).unwrap();
+    /// ```
+    pub fn set_fd_reserve(&mut self, reserve: bool) -> io::Result<()> {
+        match (reserve, self.reserve.is_some()) {
+            (true, false) => self.reserve = Some(FdReserve::new()?),
+            (false, true) => self.reserve = None,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Returns whether a spare file descriptor is kept for this listener.
+    pub fn fd_reserve(&self) -> bool {
+        self.reserve.is_some()
+    }
+
+    /// Returns the maximum number of connections accepted per second.
+    pub fn max_accept_rate(&self) -> Option<u32> {
+        self.shaping.accept_rate()
+    }
+
+    /// Accepts at most `per_second` connections per second, or lifts the
+    /// limit.
+    ///
+    /// Connections over the rate wait in the backlog of the socket until
+    /// the listener accepts them, so a burst of connections is spread out
+    /// instead of refused. Up to a tenth of a second worth of connections
+    /// is accepted at once.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `per_second` is zero.
+    pub fn set_max_accept_rate(&mut self, per_second: Option<u32>) {
+        self.shaping.set_accept_rate(per_second);
+    }
+
+    /// Returns the maximum number of open connections per source address.
+    pub fn max_connections_per_ip(&self) -> Option<usize> {
+        self.shaping.max_per_ip()
+    }
+
+    /// Limits how many of the accepted streams may be open at once for each
+    /// source address, or lifts the limit.
+    ///
+    /// A connection over the limit is closed as soon as it is accepted, and
+    /// the listener goes on with the next one without yielding it. A stream
+    /// counts against the limit until it is dropped. Only streams accepted
+    /// while a limit is set are counted.
+    pub fn set_max_connections_per_ip(&mut self, max: Option<usize>) {
+        self.shaping.set_max_per_ip(max);
+    }
+
+    /// Returns the bandwidth limit of the accepted streams, in bytes per
+    /// second.
+    pub fn bandwidth_limit(&self) -> Option<u64> {
+        self.shaping.bandwidth()
+    }
+
+    /// Limits the bandwidth of all the accepted streams together to
+    /// `bytes_per_second`, in each direction, or lifts the limit.
+    ///
+    /// The streams draw from a shared token bucket, like [`Throttled`] does
+    /// for a single one, and wait for the bucket to refill once it is empty.
+    /// Changing the limit applies to the streams already accepted, but
+    /// streams accepted while neither this nor a per-address limit was set
+    /// are never limited.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bytes_per_second` is zero.
+    ///
+    /// [`Throttled`]: ../io/struct.Throttled.html
+    pub fn set_bandwidth_limit(&mut self, bytes_per_second: Option<u64>) {
+        self.shaping.set_bandwidth(bytes_per_second);
+    }
+
+    /// Returns the number of accepted streams from `ip` which are still open
+    /// and count against the limit of [`set_max_connections_per_ip`].
+    ///
+    /// [`set_max_connections_per_ip`]: #method.set_max_connections_per_ip
+    pub fn connections_from(&self, ip: IpAddr) -> usize {
+        self.shaping.connections(ip)
+    }
+
+    /// Reports the connections the listener yields, and the closing of
+    /// their streams, to `observer`, or stops reporting them.
+    ///
+    /// See [`ConnectionObserver`]. Only connections accepted while an
+    /// observer is set are reported.
+    ///
+    /// [`ConnectionObserver`]: ../transport/trait.ConnectionObserver.html
+    pub fn set_observer(&mut self, observer: Option<Arc<dyn ConnectionObserver>>) {
+        self.observer = observer;
+    }
+
+    fn poll_accept_sys(
+        &mut self,
         cx: &mut Context<'_>,
-    ) -> Poll<io::Result<(net::TcpStream, SocketAddr)>> {
-        ready!(Pin::new(&mut self.io).poll_read_ready(cx)?);
-
-        match Pin::new(&mut self.io).get_ref().accept_std() {
-            Ok(pair) => Poll::Ready(Ok(pair)),
-            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
-                Pin::new(&mut self.io).clear_read_ready(cx)?;
-                Poll::Pending
+    ) -> Poll<io::Result<(sys::net::TcpStream, SocketAddr)>> {
+        if let Some(pair) = self.accepted.pop_front() {
+            return Poll::Ready(Ok(pair));
+        }
+        ready!(self.io.poll_read_ready(cx)?);
+
+        let batch = match self.shaping.accept_rate() {
+            Some(_) => 1,
+            None => self.accept_batch,
+        };
+        while self.accepted.len() < batch {
+            match self.io.get_ref().accept() {
+                Ok(pair) => self.accepted.push_back(pair),
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    self.io.clear_read_ready(cx)?;
+                    if self.accepted.is_empty() {
+                        return Poll::Pending;
+                    }
+                    break;
+                }
+                // Yield what was accepted, the error comes back on the next
+                // accept.
+                Err(_) if !self.accepted.is_empty() => break,
+                Err(e) => {
+                    if crate::error::is_fd_exhaustion(&e) {
+                        if let Some(reserve) = self.reserve.as_mut() {
+                            let io = self.io.get_ref();
+                            reserve.shed(|| io.accept_std());
+                        }
+                    }
+                    return Poll::Ready(Err(e));
+                }
             }
-            Err(e) => Poll::Ready(Err(e)),
         }
+        Poll::Ready(Ok(self.accepted.pop_front().unwrap()))
     }
 }
 
@@ -69,10 +413,38 @@ impl AsyncReady for TcpListener {
         self: Pin<&mut Self>,
         cx: &mut Context<'_>,
     ) -> Poll<Result<Self::Ok, Self::Err>> {
-        let (io, addr) = ready!(self.poll_accept_std(cx)?);
-        let io = sys::net::TcpStream::from_stream(io)?;
-        let io = TcpStream::new(io);
-        Poll::Ready(Ok((io, addr)))
+        let this = self.get_mut();
+        loop {
+            ready!(this.shaping.poll_accept(cx));
+            let accepted = this.poll_accept_sys(cx);
+            let accepted = this.metrics.accepted(accepted);
+            let (io, addr) =
+                ready!(error::annotate(accepted, Operation::Accept, || {
+                    (this.local_addr().ok().map(Addr::Tcp), None)
+                }))?;
+            let lease = match this.shaping.admit(addr.ip()) {
+                Ok(lease) => lease,
+                // Over the limit of its address, close it.
+                Err(()) => continue,
+            };
+            let mut io = TcpStream::new(io);
+            io.set_peer(addr);
+            if let Some(lease) = lease {
+                io.set_lease(lease);
+            }
+            if let Some(observer) = &this.observer {
+                io.set_close_hook(CloseHook::accepted(observer, Addr::Tcp(addr)));
+            }
+            return Poll::Ready(Ok((io, addr)));
+        }
+    }
+}
+
+impl std::convert::TryFrom<std::net::TcpListener> for TcpListener {
+    type Error = io::Error;
+
+    fn try_from(listener: std::net::TcpListener) -> Result<Self, Self::Error> {
+        TcpListener::from_std(listener)
     }
 }
 
@@ -109,3 +481,79 @@ impl AsRawFd for TcpListener {
         self.io.get_ref().as_raw_fd()
     }
 }
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_connections_over_the_per_ip_limit_are_closed() {
+    use crate::runtime::{self, Runtime};
+    use futures_util::io::AsyncReadExt;
+    use futures_util::StreamExt;
+
+    let mut rt = runtime::default();
+    rt.exec(async {
+        let mut listener = TcpListener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+        listener.set_max_connections_per_ip(Some(1));
+
+        let _first = TcpStream::connect(&addr).await.unwrap();
+        let mut second = TcpStream::connect(&addr).await.unwrap();
+        let accepted = listener.incoming().next().await.unwrap().unwrap();
+        assert_eq!(listener.connections_from(addr.ip()), 1);
+
+        // Accepting the second connection closes it and keeps waiting.
+        let mut incoming = listener.incoming();
+        let refused = futures_util::future::select(
+            incoming.next(),
+            Box::pin(async { second.read(&mut [0; 1]).await }),
+        )
+        .await;
+        match refused {
+            futures_util::future::Either::Right((res, _)) => assert_eq!(res.unwrap(), 0),
+            _ => panic!("connection over the limit was accepted"),
+        }
+
+        drop(accepted);
+        assert_eq!(listener.connections_from(addr.ip()), 0);
+        let _third = TcpStream::connect(&addr).await.unwrap();
+        let _accepted = listener.incoming().next().await.unwrap().unwrap();
+        assert_eq!(listener.connections_from(addr.ip()), 1);
+    });
+}
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_connections_are_accepted_in_batches() {
+    use crate::runtime::{self, Runtime};
+    use futures_util::StreamExt;
+
+    let mut rt = runtime::default();
+    rt.exec(async {
+        let mut listener = TcpListener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+        listener.set_accept_batch(2);
+
+        let mut clients = Vec::new();
+        for _ in 0..3 {
+            clients.push(TcpStream::connect(&addr).await.unwrap());
+        }
+
+        // The first batch takes two of the three queued connections.
+        let first = listener.incoming().next().await.unwrap().unwrap();
+        assert_eq!(listener.accepted.len(), 1);
+        let second = listener.incoming().next().await.unwrap().unwrap();
+        assert_eq!(listener.accepted.len(), 0);
+        let third = listener.incoming().next().await.unwrap().unwrap();
+
+        let mut peers = vec![first, second, third]
+            .iter()
+            .map(|s| s.peer_addr().unwrap())
+            .collect::<Vec<_>>();
+        let mut locals = clients
+            .iter()
+            .map(|s| s.local_addr().unwrap())
+            .collect::<Vec<_>>();
+        peers.sort();
+        locals.sort();
+        assert_eq!(peers, locals);
+    });
+}
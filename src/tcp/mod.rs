@@ -7,6 +7,8 @@
 //! - To connect to an address via TCP, use [`TcpStream::connect`].
 //! - To listen for TCP connection, use [`TcpListener::bind`] and then
 //!   [`TcpListener::incoming`].
+//! - To set options which have to be set before connecting or listening,
+//!   create a [`TcpSocket`] first.
 //! - Once you have a [`TcpStream`], you can use methods from `AsyncRead`,
 //!   `AsyncWrite`, and their extension traits (`AsyncReadExt`, `AsyncWriteExt`)
 //!   to send and receive data.
@@ -15,6 +17,7 @@
 //! [`TcpStream::connect`]: struct.TcpStream.html#method.connect
 //! [`TcpListener::bind`]: struct.TcpListener.html#method.bind
 //! [`TcpListener::incoming`]: struct.TcpListener.html#method.incoming
+//! [`TcpSocket`]: struct.TcpSocket.html
 //!
 //! # Example
 //!
@@ -39,8 +42,16 @@
 //! }
 //! ```
 
+mod coalesce;
 mod listener;
+mod shaping;
+mod socket;
+mod split;
 mod stream;
 
 pub use self::listener::{Incoming, TcpListener};
+pub use self::socket::TcpSocket;
+pub use self::split::{
+    OwnedReadHalf, OwnedWriteHalf, ReadHalf, ReuniteError, WriteHalf,
+};
 pub use self::stream::{ConnectFuture, TcpStream};
@@ -0,0 +1,190 @@
+//! Connection and bandwidth policies of a `TcpListener`.
+
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use crate::io::{TokenBucket, TokenWait};
+use crate::time;
+
+/// The policies of a listener, and the state shared with the streams it
+/// accepted.
+#[derive(Debug)]
+pub(crate) struct Shaping {
+    accept: Option<TokenBucket>,
+    accept_wait: TokenWait,
+    shared: Arc<Mutex<Shared>>,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    max_per_ip: Option<usize>,
+    connections: HashMap<IpAddr, usize>,
+    read: Option<TokenBucket>,
+    write: Option<TokenBucket>,
+}
+
+/// A stream accepted under the policies of a listener.
+///
+/// Holds a slot of the limit of its source address until dropped, and
+/// draws from the bandwidth of the listener.
+#[derive(Debug)]
+pub(crate) struct Lease {
+    shared: Arc<Mutex<Shared>>,
+    ip: IpAddr,
+    read_wait: Mutex<TokenWait>,
+    write_wait: Mutex<TokenWait>,
+}
+
+impl Shaping {
+    pub(crate) fn new() -> Shaping {
+        Shaping {
+            accept: None,
+            accept_wait: TokenWait::new(),
+            shared: Arc::new(Mutex::new(Shared::default())),
+        }
+    }
+
+    pub(crate) fn accept_rate(&self) -> Option<u32> {
+        self.accept.as_ref().map(|bucket| bucket.rate() as u32)
+    }
+
+    pub(crate) fn set_accept_rate(&mut self, rate: Option<u32>) {
+        self.accept = rate.map(|rate| TokenBucket::new(u64::from(rate)));
+    }
+
+    pub(crate) fn max_per_ip(&self) -> Option<usize> {
+        self.shared.lock().max_per_ip
+    }
+
+    pub(crate) fn set_max_per_ip(&mut self, max: Option<usize>) {
+        self.shared.lock().max_per_ip = max;
+    }
+
+    pub(crate) fn bandwidth(&self) -> Option<u64> {
+        self.shared.lock().read.as_ref().map(TokenBucket::rate)
+    }
+
+    pub(crate) fn set_bandwidth(&mut self, rate: Option<u64>) {
+        let mut shared = self.shared.lock();
+        shared.read = rate.map(TokenBucket::new);
+        shared.write = rate.map(TokenBucket::new);
+    }
+
+    pub(crate) fn connections(&self, ip: IpAddr) -> usize {
+        self.shared
+            .lock()
+            .connections
+            .get(&ip)
+            .cloned()
+            .unwrap_or(0)
+    }
+
+    /// Waits until the accept rate allows another connection.
+    pub(crate) fn poll_accept(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        if let Some(bucket) = &mut self.accept {
+            futures_util::ready!(self
+                .accept_wait
+                .poll(cx, || bucket.acquire(time::now(), 1)));
+        }
+        Poll::Ready(())
+    }
+
+    /// Accounts for a connection accepted from `ip`.
+    ///
+    /// Returns `Err` if `ip` is over its limit and the connection must be
+    /// refused, or the lease of the stream otherwise, if it needs one.
+    pub(crate) fn admit(&mut self, ip: IpAddr) -> Result<Option<Lease>, ()> {
+        if let Some(bucket) = &mut self.accept {
+            bucket.consume(1);
+        }
+
+        let mut shared = self.shared.lock();
+        if shared.max_per_ip.is_none() && shared.read.is_none() {
+            return Ok(None);
+        }
+        let max = shared.max_per_ip.unwrap_or(usize::max_value());
+        let count = shared.connections.entry(ip).or_insert(0);
+        if *count >= max {
+            return Err(());
+        }
+        *count += 1;
+
+        Ok(Some(Lease {
+            shared: self.shared.clone(),
+            ip,
+            read_wait: Mutex::new(TokenWait::new()),
+            write_wait: Mutex::new(TokenWait::new()),
+        }))
+    }
+}
+
+impl Lease {
+    /// Waits for bandwidth, returning how many bytes out of `want` may be
+    /// read.
+    pub(crate) fn poll_read(&self, cx: &mut Context<'_>, want: usize) -> Poll<usize> {
+        let shared = &self.shared;
+        self.read_wait
+            .lock()
+            .poll(cx, || match &mut shared.lock().read {
+                Some(bucket) => bucket.acquire(time::now(), want),
+                None => Ok(want),
+            })
+    }
+
+    /// Returns how many bytes out of `want` may be read right away, without
+    /// touching the timer a task waiting in `poll_read` sleeps on.
+    pub(crate) fn try_read(&self, want: usize) -> Option<usize> {
+        match &mut self.shared.lock().read {
+            Some(bucket) => bucket.acquire(time::now(), want).ok(),
+            None => Some(want),
+        }
+    }
+
+    pub(crate) fn consume_read(&self, n: usize) {
+        if let Some(bucket) = &mut self.shared.lock().read {
+            bucket.consume(n);
+        }
+    }
+
+    /// Waits for bandwidth, returning how many bytes out of `want` may be
+    /// written.
+    pub(crate) fn poll_write(&self, cx: &mut Context<'_>, want: usize) -> Poll<usize> {
+        let shared = &self.shared;
+        self.write_wait
+            .lock()
+            .poll(cx, || match &mut shared.lock().write {
+                Some(bucket) => bucket.acquire(time::now(), want),
+                None => Ok(want),
+            })
+    }
+
+    /// Returns how many bytes out of `want` may be written right away, see
+    /// `try_read`.
+    pub(crate) fn try_write(&self, want: usize) -> Option<usize> {
+        match &mut self.shared.lock().write {
+            Some(bucket) => bucket.acquire(time::now(), want).ok(),
+            None => Some(want),
+        }
+    }
+
+    pub(crate) fn consume_write(&self, n: usize) {
+        if let Some(bucket) = &mut self.shared.lock().write {
+            bucket.consume(n);
+        }
+    }
+}
+
+impl Drop for Lease {
+    fn drop(&mut self) {
+        let mut shared = self.shared.lock();
+        if let Some(count) = shared.connections.get_mut(&self.ip) {
+            *count -= 1;
+            if *count == 0 {
+                shared.connections.remove(&self.ip);
+            }
+        }
+    }
+}
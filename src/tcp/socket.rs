@@ -0,0 +1,184 @@
+//! A TCP socket which is not connected or listening yet.
+
+use std::fmt;
+use std::io;
+use std::mem::ManuallyDrop;
+use std::net::SocketAddr;
+use std::os::unix::prelude::*;
+
+use net2::unix::UnixTcpBuilderExt;
+use net2::{TcpBuilder, TcpStreamExt};
+
+use super::{TcpListener, TcpStream};
+
+/// A TCP socket which is not connected or listening yet.
+///
+/// Some options only take effect if they are set before the socket is bound
+/// or connected: `SO_REUSEADDR` and `SO_REUSEPORT` on a listening socket,
+/// the buffer sizes which the TCP window scale is derived from. A
+/// `TcpSocket` sets them and then turns into a [`TcpStream`] with
+/// [`connect`] or into a [`TcpListener`] with [`listen`].
+///
+/// # Examples
+///
+/// ```no_run
+/// use futures_net::tcp::TcpSocket;
+///
+/// # async fn run() -> Result<(), Box<dyn std::error::Error + 'static>> {
+/// let addr = "127.0.0.1:8080".parse()?;
+/// let socket = TcpSocket::new_v4()?;
+/// socket.set_recv_buffer_size(1 << 20)?;
+/// let stream = socket.connect(addr).await?;
+/// # Ok(())}
+/// ```
+///
+/// [`TcpStream`]: struct.TcpStream.html
+/// [`TcpListener`]: struct.TcpListener.html
+/// [`connect`]: #method.connect
+/// [`listen`]: #method.listen
+pub struct TcpSocket {
+    inner: TcpBuilder,
+}
+
+impl TcpSocket {
+    /// Creates an IPv4 socket.
+    pub fn new_v4() -> io::Result<TcpSocket> {
+        TcpBuilder::new_v4().map(|inner| TcpSocket { inner })
+    }
+
+    /// Creates an IPv6 socket.
+    pub fn new_v6() -> io::Result<TcpSocket> {
+        TcpBuilder::new_v6().map(|inner| TcpSocket { inner })
+    }
+
+    /// Creates a socket of the family of `addr`.
+    pub fn new_for_addr(addr: SocketAddr) -> io::Result<TcpSocket> {
+        match addr {
+            SocketAddr::V4(..) => TcpSocket::new_v4(),
+            SocketAddr::V6(..) => TcpSocket::new_v6(),
+        }
+    }
+
+    /// Sets the `SO_REUSEADDR` option, which lets a listener bind to the
+    /// address of connections left in `TIME_WAIT`.
+    pub fn set_reuseaddr(&self, reuseaddr: bool) -> io::Result<()> {
+        self.inner.reuse_address(reuseaddr).map(drop)
+    }
+
+    /// Gets the value of the `SO_REUSEADDR` option.
+    pub fn reuseaddr(&self) -> io::Result<bool> {
+        self.inner.get_reuse_address()
+    }
+
+    /// Sets the `SO_REUSEPORT` option, which lets several sockets bind to
+    /// the same address, e.g. a listener per thread.
+    pub fn set_reuseport(&self, reuseport: bool) -> io::Result<()> {
+        self.inner.reuse_port(reuseport).map(drop)
+    }
+
+    /// Gets the value of the `SO_REUSEPORT` option.
+    pub fn reuseport(&self) -> io::Result<bool> {
+        self.inner.get_reuse_port()
+    }
+
+    /// Sets the size of the send buffer, `SO_SNDBUF`.
+    pub fn set_send_buffer_size(&self, size: usize) -> io::Result<()> {
+        self.as_std().set_send_buffer_size(size)
+    }
+
+    /// Gets the size of the send buffer.
+    pub fn send_buffer_size(&self) -> io::Result<usize> {
+        self.as_std().send_buffer_size()
+    }
+
+    /// Sets the size of the receive buffer, `SO_RCVBUF`.
+    ///
+    /// The window scale of a connection is picked from it when the
+    /// connection is established, so it has to be set beforehand to get
+    /// windows larger than 64 KiB.
+    pub fn set_recv_buffer_size(&self, size: usize) -> io::Result<()> {
+        self.as_std().set_recv_buffer_size(size)
+    }
+
+    /// Gets the size of the receive buffer.
+    pub fn recv_buffer_size(&self) -> io::Result<usize> {
+        self.as_std().recv_buffer_size()
+    }
+
+    /// Binds the socket to `addr`, e.g. to pick the source address of a
+    /// connection.
+    pub fn bind(&self, addr: SocketAddr) -> io::Result<()> {
+        self.inner.bind(addr).map(drop)
+    }
+
+    /// Returns the address the socket is bound to.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.local_addr()
+    }
+
+    /// Connects the socket to `addr`.
+    pub async fn connect(self, addr: SocketAddr) -> io::Result<TcpStream> {
+        let stream = self.inner.to_tcp_stream()?;
+        TcpStream::connect_std(stream, &addr).await
+    }
+
+    /// Starts listening for connections, with at most `backlog` of them
+    /// waiting to be accepted.
+    ///
+    /// The socket must be bound, see [`bind`].
+    ///
+    /// [`bind`]: #method.bind
+    pub fn listen(self, backlog: u32) -> io::Result<TcpListener> {
+        let backlog = backlog.min(i32::MAX as u32) as i32;
+        let listener = self.inner.listen(backlog)?;
+        TcpListener::from_std(listener)
+    }
+
+    /// Views the socket as a `std::net::TcpStream`, for the options `net2`
+    /// only provides on streams.
+    fn as_std(&self) -> ManuallyDrop<std::net::TcpStream> {
+        ManuallyDrop::new(unsafe { std::net::TcpStream::from_raw_fd(self.as_raw_fd()) })
+    }
+}
+
+impl AsRawFd for TcpSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+}
+
+impl fmt::Debug for TcpSocket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TcpSocket")
+            .field("fd", &self.as_raw_fd())
+            .finish()
+    }
+}
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_options_are_set_before_connecting() {
+    use crate::runtime::{self, Runtime};
+    use futures_util::StreamExt;
+
+    let mut rt = runtime::default();
+    rt.exec(async {
+        let socket = TcpSocket::new_v4().unwrap();
+        socket.set_reuseaddr(true).unwrap();
+        socket.set_reuseport(true).unwrap();
+        assert!(socket.reuseaddr().unwrap());
+        assert!(socket.reuseport().unwrap());
+        socket.bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = socket.local_addr().unwrap();
+        let mut listener = socket.listen(16).unwrap();
+        assert_eq!(listener.local_addr().unwrap(), addr);
+
+        let socket = TcpSocket::new_for_addr(addr).unwrap();
+        socket.set_recv_buffer_size(1 << 16).unwrap();
+        assert!(socket.recv_buffer_size().unwrap() >= 1 << 16);
+        let stream = socket.connect(addr).await.unwrap();
+        let accepted = listener.incoming().next().await.unwrap().unwrap();
+        assert_eq!(stream.local_addr().unwrap(), accepted.peer_addr().unwrap());
+        assert_eq!(stream.peer_addr().unwrap(), addr);
+    });
+}
@@ -0,0 +1,288 @@
+use std::error::Error;
+use std::fmt;
+use std::io::{self, IoSlice};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use futures_io::{AsyncRead, AsyncWrite};
+
+use super::TcpStream;
+
+/// The read half of a [`TcpStream`], returned by [`TcpStream::split`].
+///
+/// [`TcpStream`]: struct.TcpStream.html
+/// [`TcpStream::split`]: struct.TcpStream.html#method.split
+#[derive(Debug)]
+pub struct ReadHalf<'a>(&'a TcpStream);
+
+/// The write half of a [`TcpStream`], returned by [`TcpStream::split`].
+///
+/// Closing the half shuts down the write side of the stream.
+///
+/// [`TcpStream`]: struct.TcpStream.html
+/// [`TcpStream::split`]: struct.TcpStream.html#method.split
+#[derive(Debug)]
+pub struct WriteHalf<'a>(&'a TcpStream);
+
+pub(crate) fn split(stream: &mut TcpStream) -> (ReadHalf<'_>, WriteHalf<'_>) {
+    (ReadHalf(&*stream), WriteHalf(&*stream))
+}
+
+impl AsRef<TcpStream> for ReadHalf<'_> {
+    fn as_ref(&self) -> &TcpStream {
+        self.0
+    }
+}
+
+impl AsRef<TcpStream> for WriteHalf<'_> {
+    fn as_ref(&self) -> &TcpStream {
+        self.0
+    }
+}
+
+// The halves go through the `&TcpStream` impls: readiness is tracked per
+// direction, so the reading task and the writing task each get their own
+// wakeups.
+
+impl AsyncRead for ReadHalf<'_> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.0).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for WriteHalf<'_> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.0).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.0).poll_write_vectored(cx, bufs)
+    }
+
+    fn poll_flush(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_flush(cx)
+    }
+
+    fn poll_close(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_close(cx)
+    }
+}
+
+/// The read half of a [`TcpStream`], returned by [`TcpStream::into_split`].
+///
+/// [`TcpStream`]: struct.TcpStream.html
+/// [`TcpStream::into_split`]: struct.TcpStream.html#method.into_split
+#[derive(Debug)]
+pub struct OwnedReadHalf {
+    inner: Arc<TcpStream>,
+}
+
+/// The write half of a [`TcpStream`], returned by [`TcpStream::into_split`].
+///
+/// Closing the half shuts down the write side of the stream. The stream
+/// itself is closed once both halves are dropped.
+///
+/// [`TcpStream`]: struct.TcpStream.html
+/// [`TcpStream::into_split`]: struct.TcpStream.html#method.into_split
+#[derive(Debug)]
+pub struct OwnedWriteHalf {
+    inner: Arc<TcpStream>,
+}
+
+/// Error returned by [`OwnedReadHalf::reunite`] for halves of different
+/// streams, which it hands back.
+///
+/// [`OwnedReadHalf::reunite`]: struct.OwnedReadHalf.html#method.reunite
+#[derive(Debug)]
+pub struct ReuniteError(pub OwnedReadHalf, pub OwnedWriteHalf);
+
+pub(crate) fn into_split(stream: TcpStream) -> (OwnedReadHalf, OwnedWriteHalf) {
+    let inner = Arc::new(stream);
+    let read = OwnedReadHalf {
+        inner: inner.clone(),
+    };
+    (read, OwnedWriteHalf { inner })
+}
+
+impl OwnedReadHalf {
+    /// Puts the stream back together.
+    ///
+    /// Fails if the halves weren't split from the same stream.
+    pub fn reunite(self, other: OwnedWriteHalf) -> Result<TcpStream, ReuniteError> {
+        if !Arc::ptr_eq(&self.inner, &other.inner) {
+            return Err(ReuniteError(self, other));
+        }
+        drop(other);
+        Ok(Arc::try_unwrap(self.inner)
+            .unwrap_or_else(|_| unreachable!("a stream is split in two halves")))
+    }
+}
+
+impl OwnedWriteHalf {
+    /// Puts the stream back together, see [`OwnedReadHalf::reunite`].
+    ///
+    /// [`OwnedReadHalf::reunite`]: struct.OwnedReadHalf.html#method.reunite
+    pub fn reunite(self, other: OwnedReadHalf) -> Result<TcpStream, ReuniteError> {
+        other.reunite(self)
+    }
+}
+
+impl AsRef<TcpStream> for OwnedReadHalf {
+    fn as_ref(&self) -> &TcpStream {
+        &self.inner
+    }
+}
+
+impl AsRef<TcpStream> for OwnedWriteHalf {
+    fn as_ref(&self) -> &TcpStream {
+        &self.inner
+    }
+}
+
+impl AsyncRead for OwnedReadHalf {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut &*self.inner).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for OwnedWriteHalf {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut &*self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut &*self.inner).poll_write_vectored(cx, bufs)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut &*self.inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut &*self.inner).poll_close(cx)
+    }
+}
+
+impl fmt::Display for ReuniteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("tried to reunite halves of different streams")
+    }
+}
+
+impl Error for ReuniteError {}
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_halves_are_driven_by_separate_tasks() {
+    use crate::runtime::{self, Runtime};
+    use crate::TcpListener;
+    use futures_util::future;
+    use futures_util::io::{AsyncReadExt, AsyncWriteExt};
+    use futures_util::StreamExt;
+
+    let mut rt = runtime::default();
+    rt.exec(async {
+        let mut listener = TcpListener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut peer = TcpStream::connect(&addr).await.unwrap();
+        let mut stream = listener.incoming().next().await.unwrap().unwrap();
+        let (mut rd, mut wr) = stream.split();
+
+        // The reader waits for bytes the writer only sends once the peer
+        // echoed them back, so both halves are pending at the same time.
+        let read = async {
+            let mut buf = [0; 4];
+            rd.read_exact(&mut buf).await.unwrap();
+            buf
+        };
+        let write = async {
+            wr.write_all(b"ping").await.unwrap();
+            wr.close().await.unwrap();
+        };
+        let echo = async {
+            let mut buf = Vec::new();
+            peer.read_to_end(&mut buf).await.unwrap();
+            peer.write_all(&buf).await.unwrap();
+        };
+        let (buf, (), ()) = future::join3(read, write, echo).await;
+        assert_eq!(&buf, b"ping");
+    });
+}
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_owned_halves_move_to_tasks_and_reunite() {
+    use crate::runtime::{self, Runtime};
+    use crate::TcpListener;
+    use futures_util::io::{AsyncReadExt, AsyncWriteExt};
+    use futures_util::StreamExt;
+
+    fn assert_send<T: Send + 'static>(_: &T) {}
+
+    let mut rt = runtime::default();
+    rt.exec(async {
+        let mut listener = TcpListener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut peer = TcpStream::connect(&addr).await.unwrap();
+        let stream = listener.incoming().next().await.unwrap().unwrap();
+        let other = TcpStream::connect(&addr).await.unwrap();
+
+        let (mut rd, mut wr) = stream.into_split();
+        assert_send(&rd);
+        assert_send(&wr);
+        let write = runtime::spawn(async move {
+            wr.write_all(b"ping").await.unwrap();
+            wr
+        });
+        let read = runtime::spawn(async move {
+            let mut buf = [0; 4];
+            rd.read_exact(&mut buf).await.unwrap();
+            (rd, buf)
+        });
+
+        let mut buf = [0; 4];
+        peer.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+        peer.write_all(b"pong").await.unwrap();
+        let wr = write.await.unwrap();
+        let (rd, buf) = read.await.unwrap();
+        assert_eq!(&buf, b"pong");
+
+        // Halves of different streams are handed back.
+        let (_, other_wr) = other.into_split();
+        let ReuniteError(rd, _) = rd.reunite(other_wr).unwrap_err();
+        let stream = rd.reunite(wr).unwrap();
+        assert_eq!(stream.peer_addr().unwrap(), peer.local_addr().unwrap());
+    });
+}
@@ -1,7 +1,7 @@
 //! A TCP stream between a local and a remote socket.
 
 use std::fmt;
-use std::io;
+use std::io::{self, IoSlice, IoSliceMut, Read, Write};
 use std::mem;
 use std::net::{Shutdown, SocketAddr};
 use std::pin::Pin;
@@ -10,10 +10,23 @@ use std::time::Duration;
 
 use async_ready::{AsyncReadReady, AsyncWriteReady};
 use futures_core::Future;
-use futures_io::{AsyncRead, AsyncWrite};
+use futures_io::{AsyncBufRead, AsyncRead, AsyncWrite};
+use futures_util::ready;
+use futures_util::StreamExt;
+use parking_lot::Mutex;
 
+use super::coalesce::Coalescer;
+use super::shaping::Lease;
+use super::split::{self, OwnedReadHalf, OwnedWriteHalf, ReadHalf, WriteHalf};
+use crate::dns::{Resolver, SystemResolver};
 use crate::driver::sys;
-use crate::driver::PollEvented;
+use crate::driver::{Handle, Interest, PollEvented};
+use crate::error::{self, Operation};
+use crate::io::{
+    pending_on_wouldblock, wouldblock_on_pending, ReadBuffer, DEFAULT_BUF_SIZE,
+};
+use crate::metrics::{SocketMetrics, SocketStats};
+use crate::transport::{Addr, CloseHook};
 
 /// A TCP stream between a local and a remote socket.
 ///
@@ -31,6 +44,19 @@ use crate::driver::PollEvented;
 /// [listener]: struct.TcpListener.html
 pub struct TcpStream {
     io: PollEvented<sys::net::TcpStream>,
+    /// Set for streams accepted by a listener with connection or bandwidth
+    /// limits.
+    lease: Option<Lease>,
+    /// Set by `buffered`. Locked by reads through `&TcpStream`.
+    read_buf: Option<Mutex<ReadBuffer>>,
+    /// Set by `coalesce_writes`.
+    write_buf: Option<Mutex<Coalescer>>,
+    metrics: SocketMetrics,
+    /// Set for streams accepted by a listener with an observer.
+    close_hook: Option<CloseHook>,
+    /// Set for streams connected or accepted by the crate, for the context
+    /// of errors once the connection is gone.
+    peer: Option<SocketAddr>,
 }
 
 /// The future returned by `TcpStream::connect`, which will resolve to a `TcpStream`
@@ -71,19 +97,247 @@ impl TcpStream {
     /// # }
     /// ```
     pub fn connect(addr: &SocketAddr) -> ConnectFuture {
+        TcpStream::connecting(sys::net::TcpStream::connect(addr), addr)
+    }
+
+    /// Connects `socket`, configured beforehand, to `addr`.
+    pub(crate) fn connect_std(
+        socket: std::net::TcpStream,
+        addr: &SocketAddr,
+    ) -> ConnectFuture {
+        TcpStream::connecting(sys::net::TcpStream::connect_stream(socket, addr), addr)
+    }
+
+    fn connecting(
+        connect: io::Result<sys::net::TcpStream>,
+        addr: &SocketAddr,
+    ) -> ConnectFuture {
         use self::ConnectFutureState::*;
 
-        let inner = match sys::net::TcpStream::connect(addr) {
-            Ok(tcp) => Waiting(TcpStream::new(tcp)),
-            Err(e) => Error(e),
+        let inner = match connect {
+            Ok(tcp) => {
+                let mut stream = TcpStream::new(tcp);
+                stream.set_peer(*addr);
+                Waiting(stream)
+            }
+            Err(e) => Error(error::annotate_err(e, Operation::Connect, || {
+                (None, Some(Addr::Tcp(*addr)))
+            })),
         };
 
         ConnectFuture { inner }
     }
 
+    /// Connects to `host`, a host name or an IP address followed by a port.
+    ///
+    /// The host is resolved with [`lookup_host`], then each of its addresses
+    /// is tried in turn until a connection is established. Fails with the
+    /// error of the lookup, or of the last address tried.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::io;
+    /// use futures_net::tcp::TcpStream;
+    ///
+    /// # async fn connect_localhost() -> io::Result<TcpStream> {
+    /// TcpStream::connect_host("localhost:8080").await
+    /// # }
+    /// ```
+    ///
+    /// [`lookup_host`]: ../fn.lookup_host.html
+    pub async fn connect_host(host: &str) -> io::Result<TcpStream> {
+        TcpStream::connect_host_with(host, &SystemResolver).await
+    }
+
+    /// Connects to `host` like [`connect_host`], resolving it with
+    /// `resolver`.
+    ///
+    /// [`connect_host`]: #method.connect_host
+    pub async fn connect_host_with<R>(host: &str, resolver: &R) -> io::Result<TcpStream>
+    where
+        R: Resolver + ?Sized,
+    {
+        let mut addrs = resolver.lookup_host(host);
+        let mut last_err = None;
+        while let Some(addr) = addrs.next().await {
+            match TcpStream::connect(&addr?).await {
+                Ok(stream) => return Ok(stream),
+                Err(e) => last_err = Some(e),
+            }
+        }
+        Err(last_err.unwrap_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "host has no addresses")
+        }))
+    }
+
     pub(crate) fn new(connected: sys::net::TcpStream) -> TcpStream {
         let io = PollEvented::new(connected);
-        TcpStream { io }
+        TcpStream {
+            io,
+            lease: None,
+            read_buf: None,
+            write_buf: None,
+            metrics: SocketMetrics::stream(),
+            close_hook: None,
+            peer: None,
+        }
+    }
+
+    /// Creates a `TcpStream` from a connected `std::net::TcpStream`, moving
+    /// it into nonblocking mode.
+    ///
+    /// This hands over sockets set up elsewhere, e.g. with options this
+    /// crate has no setter for: a `socket2::Socket` converts into the
+    /// standard library types with `into()`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use futures_net::TcpStream;
+    ///
+    /// # fn run() -> std::io::Result<()> {
+    /// let std_stream = std::net::TcpStream::connect("127.0.0.1:8080")?;
+    /// let stream = TcpStream::from_std(std_stream)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_std(stream: std::net::TcpStream) -> io::Result<TcpStream> {
+        sys::net::TcpStream::from_stream(stream).map(TcpStream::new)
+    }
+
+    /// Deregisters the stream from its reactor and returns it as a
+    /// `std::net::TcpStream`, still in nonblocking mode.
+    ///
+    /// Bytes held in the read buffer of a [`buffered`] stream are lost.
+    ///
+    /// [`buffered`]: #method.buffered
+    pub fn into_std(self) -> io::Result<std::net::TcpStream> {
+        let io = self.io.into_inner()?;
+        Ok(unsafe { std::net::TcpStream::from_raw_fd(io.into_raw_fd()) })
+    }
+
+    /// Gives the stream an internal read buffer of `capacity` bytes.
+    ///
+    /// Reads are then served from the buffer, which is refilled with a
+    /// single read of as many bytes as the socket has once it was consumed,
+    /// so protocols parsing their input with many small reads make few
+    /// system calls. Reads larger than the buffer skip it when it is empty.
+    /// The stream keeps reporting read readiness while bytes are buffered.
+    ///
+    /// The stream implements `AsyncBufRead` either way, and allocates an
+    /// 8 KiB buffer the first time it is used that way if it had none.
+    /// Bytes still buffered are lost if the buffer is replaced by another
+    /// call.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use futures::prelude::*;
+    /// use futures_net::TcpStream;
+    ///
+    /// # async fn run() -> std::io::Result<()> {
+    /// let addr = "127.0.0.1:6379".parse().unwrap();
+    /// let mut stream = TcpStream::connect(&addr).await?.buffered(4096);
+    ///
+    /// stream.write_all(b"PING\r\n").await?;
+    /// let mut reply = String::new();
+    /// stream.read_line(&mut reply).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn buffered(mut self, capacity: usize) -> TcpStream {
+        self.read_buf = Some(Mutex::new(ReadBuffer::new(capacity)));
+        self
+    }
+
+    /// Holds back writes smaller than `threshold` bytes for up to `delay`,
+    /// to send them together.
+    ///
+    /// Small writes are gathered in a buffer of `threshold` bytes and sent
+    /// in a single segment once it is full, once `delay` has passed since
+    /// the oldest of them, or when the stream is flushed. Larger writes go
+    /// out right away, after the bytes held back. Unlike Nagle's algorithm,
+    /// which holds back small segments until the data in flight is
+    /// acknowledged, the delay is bounded and set by the application; the
+    /// stream usually has `TCP_NODELAY` set as well.
+    ///
+    /// The delay is only measured while the stream is in use: bytes held
+    /// back are sent when it is due and the stream is written to or read
+    /// from, e.g. by a client awaiting the response to its request, and are
+    /// lost if the stream is dropped before being flushed or closed.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use futures::prelude::*;
+    /// use futures_net::TcpStream;
+    /// use std::time::Duration;
+    ///
+    /// # async fn run() -> std::io::Result<()> {
+    /// let addr = "127.0.0.1:7000".parse().unwrap();
+    /// let mut stream = TcpStream::connect(&addr)
+    ///     .await?
+    ///     .coalesce_writes(1024, Duration::from_micros(200));
+    /// stream.set_nodelay(true)?;
+    ///
+    /// // Sent in one segment, 200µs after the first write at the latest.
+    /// for id in 0..10u32 {
+    ///     stream.write_all(&id.to_be_bytes()).await?;
+    /// }
+    /// let mut reply = [0; 4];
+    /// stream.read_exact(&mut reply).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `threshold` is zero.
+    pub fn coalesce_writes(mut self, threshold: usize, delay: Duration) -> TcpStream {
+        assert!(threshold > 0, "coalescing threshold cannot be 0");
+        self.write_buf = Some(Mutex::new(Coalescer::new(threshold, delay)));
+        self
+    }
+
+    pub(super) fn set_lease(&mut self, lease: Lease) {
+        self.lease = Some(lease);
+    }
+
+    pub(crate) fn set_close_hook(&mut self, hook: CloseHook) {
+        self.close_hook = Some(hook);
+    }
+
+    pub(super) fn set_peer(&mut self, peer: SocketAddr) {
+        self.peer = Some(peer);
+    }
+
+    /// Registers the stream with the reactor of `handle`, e.g. the
+    /// [`high_priority`] one, instead of the reactor of the task which
+    /// polls it first.
+    ///
+    /// Fails with `AlreadyExists` if the stream was polled already.
+    ///
+    /// [`high_priority`]: ../driver/fn.high_priority.html
+    pub fn register_with(&self, handle: &Handle) -> io::Result<()> {
+        self.io.register_with(handle)
+    }
+
+    /// Returns the bytes read and written by the stream and the errors it
+    /// returned, if it was opened while [`metrics`] were enabled.
+    ///
+    /// [`metrics`]: ../metrics/index.html
+    pub fn io_stats(&self) -> SocketStats {
+        self.metrics.stats()
+    }
+
+    /// Returns the id the stream was reported with to the
+    /// [`ConnectionObserver`] of its listener, if it was accepted by a
+    /// listener with one.
+    ///
+    /// [`ConnectionObserver`]: ../transport/trait.ConnectionObserver.html
+    pub fn connection_id(&self) -> Option<u64> {
+        self.close_hook.as_ref().map(CloseHook::id)
     }
 
     /// Returns the local address that this stream is bound to.
@@ -132,23 +386,442 @@ impl TcpStream {
     /// portions to return immediately with an appropriate value (see the
     /// documentation of `Shutdown`).
     ///
+    /// Shutting down the write half sends the peer the end of the stream,
+    /// while the stream can still read the response. It fails while writes
+    /// are held back by [`coalesce_writes`], which the peer would never
+    /// get: `flush` the stream first, or `close` it, which sends them before
+    /// shutting down the write half.
+    ///
     /// # Examples
     ///
     /// ```rust
+    /// use futures::prelude::*;
     /// use futures_net::tcp::TcpStream;
     /// use std::net::Shutdown;
     ///
     /// # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
     /// let addr = "127.0.0.1:8080".parse()?;
-    /// let stream = TcpStream::connect(&addr).await?;
+    /// let mut stream = TcpStream::connect(&addr).await?;
+    ///
+    /// stream.write_all(b"request").await?;
+    /// stream.shutdown(Shutdown::Write)?;
     ///
-    /// stream.shutdown(Shutdown::Both)?;
+    /// let mut response = Vec::new();
+    /// stream.read_to_end(&mut response).await?;
     /// # Ok(())}
     /// ```
+    ///
+    /// [`coalesce_writes`]: #method.coalesce_writes
     pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        if let (Some(write_buf), Shutdown::Write) | (Some(write_buf), Shutdown::Both) =
+            (&self.write_buf, how)
+        {
+            if !write_buf.lock().is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    "writes are held back, flush the stream before shutting it down",
+                ));
+            }
+        }
         self.io.get_ref().shutdown(how)
     }
 
+    /// Splits the stream into a read half and a write half, which can be
+    /// used from two tasks at the same time.
+    ///
+    /// Readiness is tracked per direction, so a task waiting to read and a
+    /// task waiting to write are woken up independently. The halves borrow
+    /// the stream and don't lock anything, except the buffers of a
+    /// [`buffered`] or [`coalesce_writes`] stream.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use futures::prelude::*;
+    /// use futures_net::TcpStream;
+    ///
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let addr = "127.0.0.1:8080".parse()?;
+    /// let mut stream = TcpStream::connect(&addr).await?;
+    /// let (mut rd, mut wr) = stream.split();
+    ///
+    /// // Echo everything the peer sends.
+    /// futures::io::copy(&mut rd, &mut wr).await?;
+    /// # Ok(())}
+    /// ```
+    ///
+    /// [`buffered`]: #method.buffered
+    /// [`coalesce_writes`]: #method.coalesce_writes
+    pub fn split(&mut self) -> (ReadHalf<'_>, WriteHalf<'_>) {
+        split::split(self)
+    }
+
+    /// Splits the stream into a read half and a write half which own it,
+    /// so each can be moved to its own task.
+    ///
+    /// The halves behave as the ones of [`split`]. [`reunite`] puts the
+    /// stream back together.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use futures::prelude::*;
+    /// use futures_net::TcpStream;
+    ///
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let addr = "127.0.0.1:8080".parse()?;
+    /// let stream = TcpStream::connect(&addr).await?;
+    /// let (mut rd, mut wr) = stream.into_split();
+    ///
+    /// let writer = futures_net::spawn(async move {
+    ///     wr.write_all(b"hello").await?;
+    ///     Ok::<_, std::io::Error>(wr)
+    /// });
+    /// let mut buf = [0; 5];
+    /// rd.read_exact(&mut buf).await?;
+    ///
+    /// let wr = writer.await??;
+    /// let stream = rd.reunite(wr)?;
+    /// # Ok(())}
+    /// ```
+    ///
+    /// [`split`]: #method.split
+    /// [`reunite`]: struct.OwnedReadHalf.html#method.reunite
+    pub fn into_split(self) -> (OwnedReadHalf, OwnedWriteHalf) {
+        split::into_split(self)
+    }
+
+    /// Attempts to peek at the incoming bytes, see [`peek`].
+    ///
+    /// [`peek`]: #method.peek
+    pub fn poll_peek(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        if let Some(read_buf) = &self.read_buf {
+            let read_buf = read_buf.lock();
+            let buffered = read_buf.buffer();
+            if !buffered.is_empty() {
+                let n = buffered.len().min(buf.len());
+                buf[..n].copy_from_slice(&buffered[..n]);
+                return Poll::Ready(Ok(n));
+            }
+        }
+
+        ready!(self.io.poll_read_ready(cx)?);
+        let poll = match self.io.get_ref().peek(buf) {
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                self.io.clear_read_ready(cx)?;
+                Poll::Pending
+            }
+            r => Poll::Ready(r),
+        };
+        annotate(&self.io, self.peer, Operation::Read, poll)
+    }
+
+    /// Receives bytes into `buf` without removing them from the stream, so
+    /// the next read returns them again. On success, returns the number of
+    /// bytes peeked, `0` once the peer closed the stream.
+    ///
+    /// Waits until there is something to peek at. The bytes a [`buffered`]
+    /// stream already read are peeked first.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use futures_net::tcp::TcpStream;
+    ///
+    /// # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let addr = "127.0.0.1:8443".parse()?;
+    /// let mut stream = TcpStream::connect(&addr).await?;
+    ///
+    /// // A TLS connection starts with a handshake record.
+    /// let mut first = [0; 1];
+    /// let is_tls = stream.peek(&mut first).await? == 1 && first[0] == 0x16;
+    /// # Ok(())}
+    /// ```
+    ///
+    /// [`buffered`]: #method.buffered
+    pub async fn peek(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let this = &*self;
+        futures_util::future::poll_fn(|cx| this.poll_peek(cx, buf)).await
+    }
+
+    /// Tries to read into `buf` right away, without waiting.
+    ///
+    /// Fails with `WouldBlock` if there is nothing to read. No task is
+    /// registered for wakeup then, so this doesn't steal the wakeups of a
+    /// task reading the stream; wait with [`poll_read_ready`] to retry. The
+    /// bytes a [`buffered`] stream already read are returned first.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use futures_net::tcp::TcpStream;
+    /// use std::io;
+    ///
+    /// # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let addr = "127.0.0.1:8080".parse()?;
+    /// let stream = TcpStream::connect(&addr).await?;
+    ///
+    /// let mut buf = [0; 1024];
+    /// match stream.try_read(&mut buf) {
+    ///     Ok(n) => println!("read {} bytes", n),
+    ///     Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {}
+    ///     Err(e) => return Err(e.into()),
+    /// }
+    /// # Ok(())}
+    /// ```
+    ///
+    /// [`poll_read_ready`]: #method.poll_read_ready
+    /// [`buffered`]: #method.buffered
+    pub fn try_read(&self, buf: &mut [u8]) -> io::Result<usize> {
+        wouldblock_on_pending(match &self.read_buf {
+            Some(read_buf) => read_buf
+                .lock()
+                .poll_read(buf, |buf| self.try_read_counted(buf)),
+            None => self.try_read_counted(buf),
+        })
+    }
+
+    /// Tries to read into `bufs` right away, like [`try_read`].
+    ///
+    /// [`try_read`]: #method.try_read
+    pub fn try_read_vectored(&self, bufs: &mut [IoSliceMut<'_>]) -> io::Result<usize> {
+        if let Some(read_buf) = &self.read_buf {
+            let mut read_buf = read_buf.lock();
+            if !read_buf.buffer().is_empty() {
+                let mut n = 0;
+                for buf in bufs.iter_mut() {
+                    let buffered = read_buf.buffer();
+                    let m = buffered.len().min(buf.len());
+                    buf[..m].copy_from_slice(&buffered[..m]);
+                    read_buf.consume(m);
+                    n += m;
+                }
+                return Ok(n);
+            }
+        }
+        if self.lease.is_some() {
+            // The bandwidth is granted for one buffer at a time.
+            return match bufs.iter_mut().find(|buf| !buf.is_empty()) {
+                Some(buf) => wouldblock_on_pending(self.try_read_counted(buf)),
+                None => Ok(0),
+            };
+        }
+        let res = self.io.try_read_io(|io| (&*io).read_vectored(bufs));
+        let poll = self.metrics.read(pending_on_wouldblock(res));
+        wouldblock_on_pending(annotate(&self.io, self.peer, Operation::Read, poll))
+    }
+
+    /// Tries to write `buf` right away, without waiting.
+    ///
+    /// Fails with `WouldBlock` if the socket's send buffer is full, without
+    /// registering a task for wakeup, see [`try_read`]. On a stream which
+    /// [coalesces writes], it also fails while writes are held back, as
+    /// `buf` would overtake them; [`flush`] the stream first.
+    ///
+    /// [`try_read`]: #method.try_read
+    /// [coalesces writes]: #method.coalesce_writes
+    /// [`flush`]: https://docs.rs/futures/0.3/futures/io/trait.AsyncWriteExt.html#method.flush
+    pub fn try_write(&self, buf: &[u8]) -> io::Result<usize> {
+        if let Some(write_buf) = &self.write_buf {
+            if !write_buf.lock().is_empty() {
+                return Err(io::ErrorKind::WouldBlock.into());
+            }
+        }
+        let n = match &self.lease {
+            Some(lease) if !buf.is_empty() => match lease.try_write(buf.len()) {
+                Some(n) => n,
+                None => return Err(io::ErrorKind::WouldBlock.into()),
+            },
+            _ => buf.len(),
+        };
+        let res = self.io.try_write_io(|io| (&*io).write(&buf[..n]));
+        let poll = self.metrics.written(pending_on_wouldblock(res));
+        let n = wouldblock_on_pending(annotate(
+            &self.io,
+            self.peer,
+            Operation::Write,
+            poll,
+        ))?;
+        if let Some(lease) = &self.lease {
+            lease.consume_write(n);
+        }
+        Ok(n)
+    }
+
+    /// Waits until the stream is ready for any of `interest`, returning the
+    /// readiness observed.
+    ///
+    /// This drives system calls the stream has no method for, e.g. a
+    /// `sendmsg` with ancillary data on the [raw fd]. Run them with
+    /// [`try_io`], so the readiness is cleared once they would block;
+    /// otherwise `ready` keeps returning right away.
+    ///
+    /// A [`buffered`] stream is readable while bytes remain in its buffer.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use futures_net::driver::Interest;
+    /// use futures_net::tcp::TcpStream;
+    /// use std::io;
+    /// use std::os::unix::io::AsRawFd;
+    ///
+    /// # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let addr = "127.0.0.1:8080".parse()?;
+    /// let stream = TcpStream::connect(&addr).await?;
+    ///
+    /// loop {
+    ///     stream.ready(Interest::writable()).await?;
+    ///     let sent = stream.try_io(Interest::writable(), || {
+    ///         let n = unsafe {
+    ///             libc::send(stream.as_raw_fd(), b"ping".as_ptr() as *const _, 4, 0)
+    ///         };
+    ///         if n < 0 {
+    ///             return Err(io::Error::last_os_error());
+    ///         }
+    ///         Ok(n as usize)
+    ///     });
+    ///     match sent {
+    ///         Ok(n) => break println!("sent {} bytes", n),
+    ///         Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => continue,
+    ///         Err(e) => return Err(e.into()),
+    ///     }
+    /// }
+    /// # Ok(())}
+    /// ```
+    ///
+    /// [raw fd]: #method.as_raw_fd
+    /// [`try_io`]: #method.try_io
+    /// [`buffered`]: #method.buffered
+    pub async fn ready(&self, interest: Interest) -> io::Result<sys::event::Ready> {
+        futures_util::future::poll_fn(|cx| self.poll_ready(cx, interest)).await
+    }
+
+    /// Waits until the stream is readable, see [`ready`].
+    ///
+    /// [`ready`]: #method.ready
+    pub async fn readable(&self) -> io::Result<()> {
+        self.ready(Interest::readable()).await.map(drop)
+    }
+
+    /// Waits until the stream is writable, see [`ready`].
+    ///
+    /// [`ready`]: #method.ready
+    pub async fn writable(&self) -> io::Result<()> {
+        self.ready(Interest::writable()).await.map(drop)
+    }
+
+    /// Runs the system call `f` on the stream right away.
+    ///
+    /// If `f` fails with `WouldBlock`, the readiness for `interest` is
+    /// cleared, so the next [`ready`] waits for the socket to become ready
+    /// again. No task is registered for wakeup, see [`try_read`].
+    ///
+    /// [`ready`]: #method.ready
+    /// [`try_read`]: #method.try_read
+    pub fn try_io<R>(
+        &self,
+        interest: Interest,
+        f: impl FnOnce() -> io::Result<R>,
+    ) -> io::Result<R> {
+        match (interest.is_readable(), interest.is_writable()) {
+            (true, true) => self.io.try_read_io(|_| self.io.try_write_io(|_| f())),
+            (true, false) => self.io.try_read_io(|_| f()),
+            _ => self.io.try_write_io(|_| f()),
+        }
+    }
+
+    fn poll_ready(
+        &self,
+        cx: &mut Context<'_>,
+        interest: Interest,
+    ) -> Poll<io::Result<sys::event::Ready>> {
+        let mut ready = sys::event::Ready::empty();
+        if interest.is_readable() {
+            let buffered = match &self.read_buf {
+                Some(read_buf) => !read_buf.lock().buffer().is_empty(),
+                None => false,
+            };
+            if buffered {
+                ready |= sys::event::Ready::readable();
+            } else if let Poll::Ready(r) = self.io.poll_read_ready(cx) {
+                ready |= r?;
+            }
+        }
+        if interest.is_writable() {
+            if let Poll::Ready(r) = self.io.poll_write_ready(cx) {
+                ready |= r?;
+            }
+        }
+        if ready.is_empty() {
+            Poll::Pending
+        } else {
+            Poll::Ready(Ok(ready))
+        }
+    }
+
+    /// Writes all of `bufs` to the stream, with one `writev` per write the
+    /// socket accepts rather than one write per buffer.
+    ///
+    /// See [`io::write_all_vectored`] for how partial writes are handled.
+    ///
+    /// [`io::write_all_vectored`]: ../io/fn.write_all_vectored.html
+    pub async fn write_all_vectored(
+        &mut self,
+        bufs: &mut [IoSlice<'_>],
+    ) -> io::Result<()> {
+        crate::io::write_all_vectored(self, bufs).await
+    }
+
+    /// Attempts to write `buf` with `MSG_MORE`, see [`send_more`].
+    ///
+    /// [`send_more`]: #method.send_more
+    pub fn poll_send_more(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        if let Some(write_buf) = &self.write_buf {
+            ready!(write_buf
+                .lock()
+                .poll_drain(cx, |cx, buf| self.poll_write_priv(cx, buf)))?;
+        }
+        self.poll_send_priv(cx, buf, true)
+    }
+
+    /// Writes `buf`, telling the kernel that more data follows so it
+    /// doesn't send a partial segment for it. On success, returns the
+    /// number of bytes written.
+    ///
+    /// The bytes are held back until the next write without the hint, a
+    /// full segment, or at most 200ms, like with `TCP_CORK` but without
+    /// toggling the option around each message. This lets an encoder send
+    /// a header and a body with separate writes and have them leave in the
+    /// same segment.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use futures::io::AsyncWriteExt;
+    /// use futures_net::tcp::TcpStream;
+    ///
+    /// # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let addr = "127.0.0.1:8080".parse()?;
+    /// let mut stream = TcpStream::connect(&addr).await?;
+    ///
+    /// let body = b"hello";
+    /// stream.send_more(&(body.len() as u32).to_be_bytes()).await?;
+    /// stream.write_all(body).await?;
+    /// # Ok(())}
+    /// ```
+    pub async fn send_more(&self, buf: &[u8]) -> io::Result<usize> {
+        futures_util::future::poll_fn(|cx| self.poll_send_more(cx, buf)).await
+    }
+
     /// Gets the value of the `TCP_NODELAY` option on this socket.
     ///
     /// For more information about this option, see [`set_nodelay`].
@@ -436,39 +1109,391 @@ impl TcpStream {
     pub fn set_linger(&self, dur: Option<Duration>) -> io::Result<()> {
         self.io.get_ref().set_linger(dur)
     }
+
+    /// Gets the value of the `SO_BUSY_POLL` option on this socket, in
+    /// microseconds.
+    ///
+    /// For more information about this option, see [`set_busy_poll`].
+    ///
+    /// [`set_busy_poll`]: #method.set_busy_poll
+    pub fn busy_poll(&self) -> io::Result<u32> {
+        self.io.get_ref().busy_poll()
+    }
+
+    /// Sets the value of the `SO_BUSY_POLL` option on this socket.
+    ///
+    /// On NICs with kernel busy polling enabled, a receive on this socket
+    /// will spin on the device queue for up to `micros` microseconds before
+    /// sleeping. Pair it with [`driver::set_busy_poll`] so the reactor also
+    /// spins instead of blocking in `epoll_wait`.
+    ///
+    /// [`driver::set_busy_poll`]: ../driver/fn.set_busy_poll.html
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use futures_net::tcp::TcpStream;
+    ///
+    /// # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let addr = "127.0.0.1:8080".parse()?;
+    /// let stream = TcpStream::connect(&addr).await?;
+    ///
+    /// stream.set_busy_poll(50)?;
+    /// # Ok(())}
+    /// ```
+    pub fn set_busy_poll(&self, micros: u32) -> io::Result<()> {
+        self.io.get_ref().set_busy_poll(micros)
+    }
+
+    /// Gets the value of the `SO_PREFER_BUSY_POLL` option on this socket.
+    ///
+    /// For more information about this option, see [`set_prefer_busy_poll`].
+    ///
+    /// [`set_prefer_busy_poll`]: #method.set_prefer_busy_poll
+    pub fn prefer_busy_poll(&self) -> io::Result<bool> {
+        self.io.get_ref().prefer_busy_poll()
+    }
+
+    /// Sets the value of the `SO_PREFER_BUSY_POLL` option on this socket.
+    ///
+    /// When set, the kernel favours busy polling over interrupt driven
+    /// processing for this socket. Requires Linux 5.11 or newer.
+    pub fn set_prefer_busy_poll(&self, prefer: bool) -> io::Result<()> {
+        self.io.get_ref().set_prefer_busy_poll(prefer)
+    }
+
+    /// Gets the value of the `SO_INCOMING_CPU` option on this socket, the
+    /// CPU which processed the last packet received by the stream, or
+    /// `None` if it didn't receive any yet.
+    ///
+    /// A server can use it to hand a connection to the worker running on
+    /// that CPU, see [`TcpListener::set_incoming_cpu`].
+    ///
+    /// [`TcpListener::set_incoming_cpu`]: struct.TcpListener.html#method.set_incoming_cpu
+    pub fn incoming_cpu(&self) -> io::Result<Option<usize>> {
+        self.io.get_ref().incoming_cpu()
+    }
+
+    /// Sets the value of the `SO_INCOMING_CPU` option on this socket.
+    pub fn set_incoming_cpu(&self, cpu: usize) -> io::Result<()> {
+        self.io.get_ref().set_incoming_cpu(cpu)
+    }
+}
+
+impl TcpStream {
+    fn poll_read_priv(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        self.send_due_writes(cx)?;
+        match &self.read_buf {
+            Some(read_buf) => read_buf
+                .lock()
+                .poll_read(buf, |buf| self.poll_read_counted(cx, buf)),
+            None => self.poll_read_counted(cx, buf),
+        }
+    }
+
+    fn poll_read_counted(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let poll = self
+            .metrics
+            .read(poll_read_leased(&self.io, &self.lease, cx, buf));
+        annotate(&self.io, self.peer, Operation::Read, poll)
+    }
+
+    /// Reads like `poll_read_counted`, but returns `Pending` instead of
+    /// waiting for readiness or bandwidth.
+    fn try_read_counted(&self, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        let n = match &self.lease {
+            Some(lease) if !buf.is_empty() => match lease.try_read(buf.len()) {
+                Some(n) => n,
+                None => return Poll::Pending,
+            },
+            _ => buf.len(),
+        };
+        let res = self.io.try_read_io(|io| (&*io).read(&mut buf[..n]));
+        let poll = self.metrics.read(pending_on_wouldblock(res));
+        if let (Some(lease), Poll::Ready(Ok(n))) = (&self.lease, &poll) {
+            lease.consume_read(*n);
+        }
+        annotate(&self.io, self.peer, Operation::Read, poll)
+    }
+
+    fn poll_write_priv(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.poll_send_priv(cx, buf, false)
+    }
+
+    /// Writes `buf`, with `MSG_MORE` if `more` is set.
+    fn poll_send_priv(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+        more: bool,
+    ) -> Poll<io::Result<usize>> {
+        let lease = match &self.lease {
+            Some(lease) if !buf.is_empty() => lease,
+            _ => return self.poll_send_unleased(cx, buf, more),
+        };
+        let n = ready!(lease.poll_write(cx, buf.len()));
+        let n = ready!(self.poll_send_unleased(cx, &buf[..n], more))?;
+        lease.consume_write(n);
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_send_unleased(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+        more: bool,
+    ) -> Poll<io::Result<usize>> {
+        let poll = if more {
+            self.poll_send_more_sys(cx, buf)
+        } else {
+            self.io.poll_write_ref(cx, buf)
+        };
+        let poll = self.metrics.written(poll);
+        annotate(&self.io, self.peer, Operation::Write, poll)
+    }
+
+    fn poll_send_more_sys(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        ready!(self.io.poll_write_ready(cx)?);
+
+        match self.io.get_ref().send_more(buf) {
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                self.io.clear_write_ready(cx)?;
+                Poll::Pending
+            }
+            r => Poll::Ready(r),
+        }
+    }
+
+    fn poll_write_vectored_priv(
+        &self,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        if self.lease.is_none() {
+            let poll = self
+                .metrics
+                .written(self.io.poll_write_vectored_ref(cx, bufs));
+            return annotate(&self.io, self.peer, Operation::Write, poll);
+        }
+        // Leased bandwidth is granted for a single buffer.
+        match bufs.iter().find(|buf| !buf.is_empty()) {
+            Some(buf) => self.poll_write_priv(cx, buf),
+            None => Poll::Ready(Ok(0)),
+        }
+    }
+
+    /// Sends the writes held back by `coalesce_writes` if they are due, or
+    /// wakes up the task when they are: the peer may well be waiting for
+    /// them before it sends anything to read.
+    fn send_due_writes(&self, cx: &mut Context<'_>) -> io::Result<()> {
+        if let Some(write_buf) = &self.write_buf {
+            let due = write_buf
+                .lock()
+                .poll_due(cx, |cx, buf| self.poll_write_priv(cx, buf));
+            if let Poll::Ready(Err(e)) = due {
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    fn poll_write_coalesced(
+        &self,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        let write_buf = match &self.write_buf {
+            Some(write_buf) => write_buf,
+            None => return self.poll_write_vectored_priv(cx, bufs),
+        };
+        let held = ready!(write_buf
+            .lock()
+            .poll_write(cx, bufs, |cx, buf| self.poll_write_priv(cx, buf)))?;
+        match held {
+            Some(n) => Poll::Ready(Ok(n)),
+            None => self.poll_write_vectored_priv(cx, bufs),
+        }
+    }
+
+    fn poll_flush_priv(&self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if let Some(write_buf) = &self.write_buf {
+            ready!(write_buf
+                .lock()
+                .poll_drain(cx, |cx, buf| self.poll_write_priv(cx, buf)))?;
+        }
+        self.io.poll_flush_ref(cx)
+    }
+
+    /// Shuts down the write half of the stream, so the peer reads the end
+    /// of the stream. A peer which already went away is not an error.
+    fn poll_close_priv(&self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.poll_flush_priv(cx))?;
+        match self.shutdown(Shutdown::Write) {
+            Err(ref e) if e.kind() == io::ErrorKind::NotConnected => Poll::Ready(Ok(())),
+            res => Poll::Ready(res),
+        }
+    }
+}
+
+/// Adds the context of the stream to the error of `poll`, see
+/// `error::set_context_enabled`.
+fn annotate<T>(
+    io: &PollEvented<sys::net::TcpStream>,
+    peer: Option<SocketAddr>,
+    operation: Operation,
+    poll: Poll<io::Result<T>>,
+) -> Poll<io::Result<T>> {
+    error::annotate(poll, operation, || {
+        let local = io.get_ref().local_addr().ok();
+        let peer = peer.or_else(|| io.get_ref().peer_addr().ok());
+        (local.map(Addr::Tcp), peer.map(Addr::Tcp))
+    })
+}
+
+/// Reads from the socket, within the bandwidth of its listener.
+fn poll_read_leased(
+    io: &PollEvented<sys::net::TcpStream>,
+    lease: &Option<Lease>,
+    cx: &mut Context<'_>,
+    buf: &mut [u8],
+) -> Poll<io::Result<usize>> {
+    let lease = match lease {
+        Some(lease) if !buf.is_empty() => lease,
+        _ => return io.poll_read_ref(cx, buf),
+    };
+    let n = ready!(lease.poll_read(cx, buf.len()));
+    let n = ready!(io.poll_read_ref(cx, &mut buf[..n]))?;
+    lease.consume_read(n);
+    Poll::Ready(Ok(n))
 }
 
 impl AsyncRead for TcpStream {
     fn poll_read(
-        mut self: Pin<&mut Self>,
+        self: Pin<&mut Self>,
         cx: &mut Context<'_>,
         buf: &mut [u8],
     ) -> Poll<io::Result<usize>> {
-        Pin::new(&mut self.io).poll_read(cx, buf)
+        self.poll_read_priv(cx, buf)
+    }
+}
+
+impl AsyncBufRead for TcpStream {
+    fn poll_fill_buf(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<&[u8]>> {
+        let this = self.get_mut();
+        this.send_due_writes(cx)?;
+        let read_buf = this
+            .read_buf
+            .get_or_insert_with(|| Mutex::new(ReadBuffer::new(DEFAULT_BUF_SIZE)))
+            .get_mut();
+        let (io, lease, metrics, peer) =
+            (&this.io, &this.lease, &this.metrics, this.peer);
+        read_buf.poll_fill(|buf| {
+            let poll = metrics.read(poll_read_leased(io, lease, cx, buf));
+            annotate(io, peer, Operation::Read, poll)
+        })
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        if let Some(read_buf) = &mut self.get_mut().read_buf {
+            read_buf.get_mut().consume(amt);
+        }
     }
 }
 
 impl AsyncWrite for TcpStream {
     fn poll_write(
-        mut self: Pin<&mut Self>,
+        self: Pin<&mut Self>,
         cx: &mut Context<'_>,
         buf: &[u8],
     ) -> Poll<io::Result<usize>> {
-        Pin::new(&mut self.io).poll_write(cx, buf)
+        match &self.write_buf {
+            Some(_) => self.poll_write_coalesced(cx, &[IoSlice::new(buf)]),
+            None => self.poll_write_priv(cx, buf),
+        }
     }
 
-    fn poll_flush(
-        mut self: Pin<&mut Self>,
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
         cx: &mut Context<'_>,
-    ) -> Poll<io::Result<()>> {
-        Pin::new(&mut self.io).poll_flush(cx)
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        self.poll_write_coalesced(cx, bufs)
     }
 
-    fn poll_close(
-        mut self: Pin<&mut Self>,
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_flush_priv(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_close_priv(cx)
+    }
+}
+
+/// Reads through a shared reference, like `Read` for `&std::net::TcpStream`.
+///
+/// One task can read while another writes, e.g. with the stream behind an
+/// `Arc`. Readiness is tracked per direction, so when several tasks read at
+/// the same time, only the last one to wait is woken up.
+impl AsyncRead for &TcpStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
         cx: &mut Context<'_>,
-    ) -> Poll<io::Result<()>> {
-        Pin::new(&mut self.io).poll_close(cx)
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        self.poll_read_priv(cx, buf)
+    }
+}
+
+/// Writes through a shared reference, like `Write` for
+/// `&std::net::TcpStream`.
+///
+/// When several tasks write at the same time, only the last one to wait is
+/// woken up.
+impl AsyncWrite for &TcpStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match &self.write_buf {
+            Some(_) => self.poll_write_coalesced(cx, &[IoSlice::new(buf)]),
+            None => self.poll_write_priv(cx, buf),
+        }
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        self.poll_write_coalesced(cx, bufs)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_flush_priv(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_close_priv(cx)
     }
 }
 
@@ -483,11 +1508,19 @@ impl AsyncReadReady for TcpStream {
     ///
     /// Once the stream is ready for reading, it will remain so until all available
     /// bytes have been extracted (via `futures::io::AsyncRead` and related traits).
+    /// A [`buffered`] stream is ready while bytes remain in its buffer.
+    ///
+    /// [`buffered`]: #method.buffered
     fn poll_read_ready(
         mut self: Pin<&mut Self>,
         cx: &mut Context<'_>,
     ) -> Poll<Result<Self::Ok, Self::Err>> {
-        Pin::new(&mut self.io).poll_read_ready(cx)
+        if let Some(read_buf) = &mut self.read_buf {
+            if !read_buf.get_mut().buffer().is_empty() {
+                return Poll::Ready(Ok(sys::event::Ready::readable()));
+            }
+        }
+        self.io.poll_read_ready(cx)
     }
 }
 
@@ -523,6 +1556,22 @@ impl fmt::Debug for TcpStream {
     }
 }
 
+impl ConnectFuture {
+    /// Registers the stream with the reactor of `handle`, e.g. the
+    /// [`high_priority`] one, instead of the reactor of the task which
+    /// polls the future.
+    ///
+    /// [`high_priority`]: ../driver/fn.high_priority.html
+    pub fn register_with(mut self, handle: &Handle) -> ConnectFuture {
+        if let ConnectFutureState::Waiting(stream) = &self.inner {
+            if let Err(e) = stream.register_with(handle) {
+                self.inner = ConnectFutureState::Error(e);
+            }
+        }
+        self
+    }
+}
+
 impl Future for ConnectFuture {
     type Output = io::Result<TcpStream>;
 
@@ -538,16 +1587,22 @@ impl Future for ConnectFuture {
                 // actually hit an error or not.
                 //
                 // If all that succeeded then we ship everything on up.
-                if let Poll::Pending = stream.io.poll_write_ready(cx)? {
-                    self.inner = ConnectFutureState::Waiting(stream);
-                    return Poll::Pending;
+                let connected = match stream.io.poll_write_ready(cx) {
+                    Poll::Pending => {
+                        self.inner = ConnectFutureState::Waiting(stream);
+                        return Poll::Pending;
+                    }
+                    Poll::Ready(ready) => {
+                        ready.and_then(|_| stream.io.get_ref().take_error())
+                    }
+                };
+                match connected {
+                    Ok(None) => Poll::Ready(Ok(stream)),
+                    Ok(Some(e)) | Err(e) => {
+                        let poll = Poll::Ready(Err(e));
+                        annotate(&stream.io, stream.peer, Operation::Connect, poll)
+                    }
                 }
-
-                if let Some(e) = stream.io.get_ref().take_error()? {
-                    return Poll::Ready(Err(e));
-                }
-
-                Poll::Ready(Ok(stream))
             }
             ConnectFutureState::Error(e) => Poll::Ready(Err(e)),
             ConnectFutureState::Empty => panic!("can't poll TCP stream twice"),
@@ -559,8 +1614,7 @@ impl std::convert::TryFrom<std::net::TcpStream> for TcpStream {
     type Error = io::Error;
 
     fn try_from(stream: std::net::TcpStream) -> Result<Self, Self::Error> {
-        let tcp = sys::net::TcpStream::from_stream(stream)?;
-        Ok(TcpStream::new(tcp))
+        TcpStream::from_std(stream)
     }
 }
 
@@ -580,3 +1634,497 @@ impl AsRawFd for TcpStream {
         self.io.get_ref().as_raw_fd()
     }
 }
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_buffered_reads_come_from_the_buffer() {
+    use crate::runtime::{self, Runtime};
+    use crate::TcpListener;
+    use futures_util::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt};
+    use futures_util::StreamExt;
+
+    let mut rt = runtime::default();
+    rt.exec(async {
+        let mut listener = TcpListener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut peer = TcpStream::connect(&addr).await.unwrap();
+        let stream = listener.incoming().next().await.unwrap().unwrap();
+        let mut stream = stream.buffered(64);
+
+        peer.write_all(b"one\ntwo\nthree").await.unwrap();
+        let mut line = String::new();
+        stream.read_line(&mut line).await.unwrap();
+        assert_eq!(line, "one\n");
+
+        // The rest of the first read is still buffered.
+        let waker = futures_util::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let ready = Pin::new(&mut stream).poll_read_ready(&mut cx);
+        assert!(matches!(ready, Poll::Ready(Ok(r)) if r.is_readable()));
+
+        // Reads through a shared reference see the buffered bytes first.
+        let mut buf = [0; 4];
+        (&stream).read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"two\n");
+
+        drop(peer);
+        let mut rest = String::new();
+        stream.read_to_string(&mut rest).await.unwrap();
+        assert_eq!(rest, "three");
+    });
+}
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_connect_host_resolves_names() {
+    use crate::runtime::{self, Runtime};
+    use crate::TcpListener;
+
+    let addr = "127.0.0.1:0".parse().unwrap();
+    let mut listener = TcpListener::bind(&addr).unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let mut rt = runtime::default();
+    rt.exec(async {
+        let stream = TcpStream::connect_host(&format!("localhost:{}", port))
+            .await
+            .unwrap();
+        let accepted = listener.incoming().next().await.unwrap().unwrap();
+        assert_eq!(stream.local_addr().unwrap(), accepted.peer_addr().unwrap());
+    });
+}
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_std_streams_round_trip() {
+    use crate::runtime::{self, Runtime};
+    use futures_util::io::{AsyncReadExt, AsyncWriteExt};
+    use std::convert::TryFrom;
+    use std::io::Read;
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let std_stream =
+        std::net::TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (mut peer, _) = listener.accept().unwrap();
+
+    let mut stream = TcpStream::try_from(std_stream).unwrap();
+    let mut rt = runtime::default();
+    rt.exec(async {
+        stream.write_all(b"async").await.unwrap();
+        std::io::Write::write_all(&mut peer, b"back").unwrap();
+        let mut buf = [0; 4];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"back");
+    });
+
+    // Handed back, the socket works without a reactor.
+    let mut std_stream = stream.into_std().unwrap();
+    std_stream.set_nonblocking(false).unwrap();
+    std::io::Write::write_all(&mut peer, b"sync").unwrap();
+    let mut buf = [0; 4];
+    std_stream.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"sync");
+    peer.read_exact(&mut [0; 5]).unwrap();
+}
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_incoming_cpu_is_reported() {
+    use crate::runtime::{self, Runtime};
+    use crate::TcpListener;
+    use futures_util::io::{AsyncReadExt, AsyncWriteExt};
+    use futures_util::StreamExt;
+
+    let mut rt = runtime::default();
+    rt.exec(async {
+        let mut listener = TcpListener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+        listener.set_incoming_cpu(0).unwrap();
+        assert_eq!(listener.incoming_cpu().unwrap(), Some(0));
+
+        let addr = listener.local_addr().unwrap();
+        let mut client = TcpStream::connect(&addr).await.unwrap();
+        let mut server = listener.incoming().next().await.unwrap().unwrap();
+        client.write_all(b"x").await.unwrap();
+        server.read_exact(&mut [0; 1]).await.unwrap();
+        assert!(server.incoming_cpu().unwrap().is_some());
+    });
+}
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_write_all_vectored_survives_partial_writes() {
+    use crate::runtime::{self, Runtime};
+    use crate::TcpListener;
+    use futures_util::io::AsyncReadExt;
+    use futures_util::StreamExt;
+
+    let mut rt = runtime::default();
+    rt.exec(async {
+        let mut listener = TcpListener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut client = TcpStream::connect(&addr).await.unwrap();
+        let mut server = listener.incoming().next().await.unwrap().unwrap();
+        client.set_send_buffer_size(4096).unwrap();
+
+        // Far more than the socket buffers hold, so most writes are partial
+        // and some end in the middle of a buffer.
+        let parts: Vec<Vec<u8>> = (0..8u8).map(|i| vec![i; 100_003]).collect();
+        let mut bufs: Vec<_> = parts.iter().map(|p| IoSlice::new(p)).collect();
+        let write = async {
+            client.write_all_vectored(&mut bufs).await.unwrap();
+            drop(client);
+        };
+        let mut received = Vec::new();
+        let read = server.read_to_end(&mut received);
+        let (_, read) = futures_util::future::join(write, read).await;
+        read.unwrap();
+        assert_eq!(received, parts.concat());
+    });
+}
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_streams_register_with_the_high_priority_reactor() {
+    use crate::driver;
+    use crate::runtime::{self, Runtime};
+    use crate::TcpListener;
+    use futures_util::io::{AsyncReadExt, AsyncWriteExt};
+    use futures_util::StreamExt;
+
+    let high_priority = driver::high_priority().unwrap();
+    let mut rt = runtime::default();
+    rt.exec(async {
+        let mut listener = TcpListener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut client = TcpStream::connect(&addr)
+            .register_with(&high_priority)
+            .await
+            .unwrap();
+        let mut server = listener.incoming().next().await.unwrap().unwrap();
+        server.register_with(&high_priority).unwrap();
+
+        client.write_all(b"ping").await.unwrap();
+        let mut buf = [0; 4];
+        server.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+
+        // Streams stay with the reactor they were first registered with.
+        let err = client.register_with(&high_priority).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+    });
+}
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_small_writes_are_coalesced() {
+    use crate::runtime::{self, Runtime};
+    use crate::TcpListener;
+    use futures_util::future::{self, Either};
+    use futures_util::io::{AsyncReadExt, AsyncWriteExt};
+    use futures_util::StreamExt;
+
+    let mut rt = runtime::default();
+    rt.exec(async {
+        let mut listener = TcpListener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = TcpStream::connect(&addr).await.unwrap();
+        let mut client = client.coalesce_writes(64, Duration::from_millis(20));
+        let mut server = listener.incoming().next().await.unwrap().unwrap();
+
+        // Held back until the delay elapses while the client waits for the
+        // reply.
+        for i in 0..10u32 {
+            client.write_all(&i.to_be_bytes()).await.unwrap();
+        }
+        let mut buf = [0; 40];
+        let early = future::select(
+            server.read_exact(&mut buf),
+            crate::time::sleep(Duration::from_millis(5)),
+        );
+        assert!(matches!(early.await, Either::Right(_)));
+        let reply = async {
+            server.read_exact(&mut buf).await.unwrap();
+            server.write_all(b"!").await.unwrap();
+        };
+        let (_, read) = future::join(reply, client.read_exact(&mut [0; 1])).await;
+        read.unwrap();
+        assert_eq!(buf[36..], 9u32.to_be_bytes());
+
+        // Large writes go out right away, after the bytes held back, and
+        // flushing sends what is held back.
+        client.write_all(b"ab").await.unwrap();
+        client.write_all(&[7; 100]).await.unwrap();
+        let mut buf = [0; 102];
+        server.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf[..3], b"ab\x07");
+        client.write_all(b"cd").await.unwrap();
+        client.flush().await.unwrap();
+        server.read_exact(&mut buf[..2]).await.unwrap();
+        assert_eq!(&buf[..2], b"cd");
+    });
+}
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_send_more_holds_back_the_segment() {
+    use crate::runtime::{self, Runtime};
+    use crate::TcpListener;
+    use futures_util::future::{self, Either};
+    use futures_util::io::{AsyncReadExt, AsyncWriteExt};
+
+    let mut rt = runtime::default();
+    rt.exec(async {
+        let mut listener = TcpListener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut client = TcpStream::connect(&addr).await.unwrap();
+        client.set_nodelay(true).unwrap();
+        let mut server = listener.incoming().next().await.unwrap().unwrap();
+
+        // Nothing is sent until a write without the hint, even with
+        // `TCP_NODELAY`.
+        assert_eq!(client.send_more(b"hello ").await.unwrap(), 6);
+        let mut buf = [0; 11];
+        let early = future::select(
+            server.read(&mut buf),
+            crate::time::sleep(Duration::from_millis(50)),
+        );
+        assert!(matches!(early.await, Either::Right(_)));
+        client.write_all(b"world").await.unwrap();
+        assert_eq!(server.read(&mut buf).await.unwrap(), 11);
+        assert_eq!(&buf, b"hello world");
+    });
+}
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_peek_leaves_the_bytes_to_read() {
+    use crate::runtime::{self, Runtime};
+    use crate::TcpListener;
+    use futures_util::io::{AsyncReadExt, AsyncWriteExt};
+
+    let mut rt = runtime::default();
+    rt.exec(async {
+        let mut listener = TcpListener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut client = TcpStream::connect(&addr).await.unwrap();
+        let mut server = listener.incoming().next().await.unwrap().unwrap();
+
+        client.write_all(b"\x16\x03\x01").await.unwrap();
+        let mut first = [0; 1];
+        assert_eq!(server.peek(&mut first).await.unwrap(), 1);
+        assert_eq!(first[0], 0x16);
+
+        let mut buf = [0; 3];
+        server.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"\x16\x03\x01");
+
+        // A buffered stream peeks at what it already read.
+        let mut server = server.buffered(64);
+        client.write_all(b"ab").await.unwrap();
+        server.read_exact(&mut first).await.unwrap();
+        assert_eq!(server.peek(&mut buf).await.unwrap(), 1);
+        assert_eq!(buf[0], b'b');
+    });
+}
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_try_read_and_write_do_not_wait() {
+    use crate::runtime::{self, Runtime};
+    use futures_util::io::AsyncReadExt;
+
+    let mut rt = runtime::default();
+    rt.exec(async {
+        let mut listener =
+            super::TcpListener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let peer = TcpStream::connect(&addr).await.unwrap();
+        let stream = listener.incoming().next().await.unwrap().unwrap();
+        let mut stream = stream.buffered(64);
+
+        let mut buf = [0; 4];
+        let err = stream.try_read(&mut buf).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+
+        assert_eq!(peer.try_write(b"pingpong").unwrap(), 8);
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+
+        // The rest of the first read is still buffered.
+        let (mut x, mut y) = ([0; 2], [0; 2]);
+        let mut bufs = [IoSliceMut::new(&mut x), IoSliceMut::new(&mut y)];
+        assert_eq!(stream.try_read_vectored(&mut bufs).unwrap(), 4);
+        assert_eq!((&x, &y), (b"po", b"ng"));
+        let err = stream.try_read(&mut buf).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+        assert_eq!(stream.io_stats().errors, 0);
+    });
+}
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_ready_drives_custom_syscalls() {
+    use crate::runtime::{self, Runtime};
+    use futures_util::future::{self, Either};
+    use futures_util::io::AsyncWriteExt;
+    use std::os::unix::io::AsRawFd;
+
+    let mut rt = runtime::default();
+    rt.exec(async {
+        let mut listener =
+            super::TcpListener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut peer = TcpStream::connect(&addr).await.unwrap();
+        let stream = listener.incoming().next().await.unwrap().unwrap();
+
+        stream.writable().await.unwrap();
+        let ready = stream
+            .ready(Interest::readable() | Interest::writable())
+            .await
+            .unwrap();
+        assert!(ready.is_writable());
+        assert!(!ready.is_readable());
+
+        let recv = || {
+            let mut buf = [0; 4];
+            let n = unsafe {
+                libc::recv(stream.as_raw_fd(), buf.as_mut_ptr() as *mut _, buf.len(), 0)
+            };
+            if n < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(buf[..n as usize].to_vec())
+        };
+        let err = stream.try_io(Interest::readable(), recv).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+
+        // The readiness was cleared, so this waits for the peer.
+        let readable = stream.readable();
+        futures_util::pin_mut!(readable);
+        let write = Box::pin(peer.write_all(b"ping"));
+        match future::select(readable.as_mut(), write).await {
+            Either::Left(..) => panic!("readable before the peer wrote"),
+            Either::Right((res, _)) => res.unwrap(),
+        }
+        readable.await.unwrap();
+        let buf = stream.try_io(Interest::readable(), recv).unwrap();
+        assert_eq!(&buf, b"ping");
+    });
+}
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_shutdown_write_still_reads_the_response() {
+    use crate::runtime::{self, Runtime};
+    use futures_util::io::{AsyncReadExt, AsyncWriteExt};
+
+    let mut rt = runtime::default();
+    rt.exec(async {
+        let mut listener =
+            super::TcpListener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut client = TcpStream::connect(&addr).await.unwrap();
+        let mut server = listener.incoming().next().await.unwrap().unwrap();
+
+        client.write_all(b"request").await.unwrap();
+        client.shutdown(Shutdown::Write).unwrap();
+
+        // The server reads up to the end of the stream, then answers.
+        let mut request = Vec::new();
+        server.read_to_end(&mut request).await.unwrap();
+        assert_eq!(&request, b"request");
+        server.write_all(b"response").await.unwrap();
+        drop(server);
+
+        let mut response = Vec::new();
+        client.read_to_end(&mut response).await.unwrap();
+        assert_eq!(&response, b"response");
+    });
+}
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_try_write_keeps_the_bandwidth_waiter_awake() {
+    use crate::runtime::{self, Runtime};
+    use crate::time;
+    use futures_util::future;
+    use futures_util::io::AsyncWriteExt;
+
+    let mut rt = runtime::default();
+    rt.exec(async {
+        let mut listener =
+            super::TcpListener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+        listener.set_bandwidth_limit(Some(10));
+        let addr = listener.local_addr().unwrap();
+        let _peer = TcpStream::connect(&addr).await.unwrap();
+        let stream = listener.incoming().next().await.unwrap().unwrap();
+        stream.writable().await.unwrap();
+
+        // The first byte drains the bucket, so the writer then sleeps until
+        // it refills, while `try_write` finds it empty.
+        let waiting = async { (&stream).write_all(&[0; 3]).await.unwrap() };
+        let trying = async {
+            let err = stream.try_write(&[0; 2]).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+        };
+        let both = future::join(waiting, trying);
+        time::timeout(Duration::from_secs(5), both).await.unwrap();
+    });
+}
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_close_shuts_down_the_write_half() {
+    use crate::runtime::{self, Runtime};
+    use futures_util::io::{AsyncReadExt, AsyncWriteExt};
+
+    let mut rt = runtime::default();
+    rt.exec(async {
+        let mut listener =
+            super::TcpListener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut client = TcpStream::connect(&addr).await.unwrap();
+        let mut server = listener.incoming().next().await.unwrap().unwrap();
+
+        client.write_all(b"ping").await.unwrap();
+        client.close().await.unwrap();
+
+        // The peer reads the end of the stream.
+        let mut buf = Vec::new();
+        server.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+
+        // The closed side can still read.
+        server.write_all(b"pong").await.unwrap();
+        let mut buf = [0; 4];
+        client.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"pong");
+    });
+}
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_shutdown_does_not_drop_coalesced_writes() {
+    use crate::runtime::{self, Runtime};
+    use futures_util::io::{AsyncReadExt, AsyncWriteExt};
+
+    let mut rt = runtime::default();
+    rt.exec(async {
+        let mut listener =
+            super::TcpListener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = TcpStream::connect(&addr).await.unwrap();
+        let mut client = client.coalesce_writes(64, Duration::from_secs(10));
+        let mut server = listener.incoming().next().await.unwrap().unwrap();
+
+        client.write_all(b"hi").await.unwrap();
+        assert!(client.shutdown(Shutdown::Write).is_err());
+        client.shutdown(Shutdown::Read).unwrap();
+
+        client.flush().await.unwrap();
+        client.shutdown(Shutdown::Write).unwrap();
+        let mut buf = Vec::new();
+        server.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hi");
+    });
+}
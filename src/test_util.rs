@@ -0,0 +1,441 @@
+//! Connected socket pairs and a scripted listener for tests, with the
+//! `test-util` feature.
+//!
+//! Each `*_pair` function binds a socket to an ephemeral port of the
+//! loopback interface, or to a fresh path in the temporary directory,
+//! connects a second one to it and returns both ends along with their
+//! addresses. A [`TestListener`] accepts connections from a script rather
+//! than from the network, to test accept loops against failures.
+//!
+//! # Examples
+//!
+//! ```
+//! use futures::prelude::*;
+//! use futures_net::runtime::{self, Runtime};
+//! use futures_net::test_util;
+//!
+//! let mut rt = runtime::default();
+//! rt.exec(async {
+//!     let mut pair = test_util::tcp_pair().await?;
+//!     assert_eq!(pair.client.peer_addr()?, pair.server_addr);
+//!
+//!     pair.client.write_all(b"ping").await?;
+//!     let mut buf = [0; 4];
+//!     pair.server.read_exact(&mut buf).await?;
+//!     assert_eq!(&buf, b"ping");
+//!     Ok::<_, std::io::Error>(())
+//! })?;
+//! # Ok::<_, std::io::Error>(())
+//! ```
+//!
+//! [`TestListener`]: struct.TestListener.html
+
+use futures_core::stream::Stream;
+use futures_io::{AsyncRead, AsyncWrite};
+use futures_util::future;
+use futures_util::task::noop_waker;
+use std::collections::VecDeque;
+use std::fs;
+use std::io;
+use std::net::{self, SocketAddr};
+use std::os::unix::net::SocketAddr as UnixSocketAddr;
+use std::pin::Pin;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::task::{Context, Poll};
+
+use crate::io::{duplex, DuplexStream};
+use crate::transport::{Addr, Listener, Transport};
+use crate::{TcpListener, TcpStream, UdpSocket, UnixListener, UnixStream};
+
+/// Two connected sockets and their addresses.
+///
+/// For UDP the two ends are alike, `client` is simply the socket bound
+/// last.
+#[derive(Debug)]
+pub struct Pair<S, A> {
+    /// The connecting end.
+    pub client: S,
+    /// The accepting end.
+    pub server: S,
+    /// The address of `client`.
+    pub client_addr: A,
+    /// The address of `server`.
+    pub server_addr: A,
+}
+
+/// Returns a TCP stream connected to another one over the loopback
+/// interface.
+pub async fn tcp_pair() -> io::Result<Pair<TcpStream, SocketAddr>> {
+    let mut listener = TcpListener::bind(&loopback())?;
+    let server_addr = listener.local_addr()?;
+
+    let (client, accepted) =
+        future::join(TcpStream::connect(&server_addr), listener.accept()).await;
+    let client = client?;
+    let (server, _) = accepted?;
+    let client_addr = client.local_addr()?;
+
+    Ok(Pair {
+        client,
+        server,
+        client_addr,
+        server_addr,
+    })
+}
+
+/// Returns two UDP sockets bound to the loopback interface, each connected
+/// to the other so they only receive the datagrams of their peer.
+pub fn udp_pair() -> io::Result<Pair<UdpSocket, SocketAddr>> {
+    let server = net::UdpSocket::bind(loopback())?;
+    let client = net::UdpSocket::bind(loopback())?;
+    let server_addr = server.local_addr()?;
+    let client_addr = client.local_addr()?;
+    server.connect(client_addr)?;
+    client.connect(server_addr)?;
+
+    Ok(Pair {
+        client: UdpSocket::from_std(client)?,
+        server: UdpSocket::from_std(server)?,
+        client_addr,
+        server_addr,
+    })
+}
+
+/// Returns a Unix stream connected to another one through a socket file in
+/// the temporary directory.
+///
+/// The file is removed once the streams are connected, the address of the
+/// server still names it.
+pub async fn uds_pair() -> io::Result<Pair<UnixStream, UnixSocketAddr>> {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+    let path = std::env::temp_dir().join(format!(
+        "futures-net-{}-{}.sock",
+        process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    ));
+    // A file left by a process which had the same id.
+    let _ = fs::remove_file(&path);
+    let mut listener = UnixListener::bind(&path)?;
+
+    let connected = future::join(UnixStream::connect(&path), listener.accept()).await;
+    let _ = fs::remove_file(&path);
+    let (client, accepted) = connected;
+    let client = client?;
+    let (server, _) = accepted?;
+
+    Ok(Pair {
+        client_addr: client.local_addr()?,
+        server_addr: server.local_addr()?,
+        client,
+        server,
+    })
+}
+
+/// A listener accepting scripted connections and errors.
+///
+/// The script is a queue of steps set up before the listener is used: each
+/// call to `accept` takes the next one, which is either an in-memory
+/// connection or an error. This lets a server's accept loop be tested
+/// against sequences such as running out of file descriptors and then
+/// recovering, without opening any socket.
+///
+/// Once the script is exhausted, [`incoming`] ends and [`Listener::accept`]
+/// waits forever, like a real listener nobody connects to.
+///
+/// # Examples
+///
+/// ```
+/// use futures::prelude::*;
+/// use futures_net::runtime::{self, Runtime};
+/// use futures_net::test_util::TestListener;
+/// use std::io;
+///
+/// let mut listener = TestListener::new("127.0.0.1:80".parse().unwrap());
+/// listener.push_error(io::Error::from_raw_os_error(libc::EMFILE));
+/// listener.push_data(b"hello".to_vec());
+///
+/// let mut rt = runtime::default();
+/// rt.exec(async {
+///     let mut incoming = listener.incoming();
+///     let err = incoming.next().await.unwrap().unwrap_err();
+///     assert_eq!(err.raw_os_error(), Some(libc::EMFILE));
+///
+///     let mut stream = incoming.next().await.unwrap()?;
+///     let mut buf = String::new();
+///     stream.read_to_string(&mut buf).await?;
+///     assert_eq!(buf, "hello");
+///
+///     assert!(incoming.next().await.is_none());
+///     Ok::<_, io::Error>(())
+/// })?;
+/// # Ok::<_, io::Error>(())
+/// ```
+///
+/// [`incoming`]: #method.incoming
+/// [`Listener::accept`]: ../transport/trait.Listener.html#method.accept
+#[derive(Debug)]
+pub struct TestListener {
+    local: SocketAddr,
+    script: VecDeque<io::Result<TestStream>>,
+    /// Port of the peer of the next scripted connection.
+    next_port: u16,
+}
+
+/// A connection accepted by a [`TestListener`].
+///
+/// [`TestListener`]: struct.TestListener.html
+#[derive(Debug)]
+pub struct TestStream {
+    io: DuplexStream,
+    local: SocketAddr,
+    peer: SocketAddr,
+}
+
+/// Bytes buffered in each direction of a scripted connection.
+const TEST_STREAM_BUF: usize = 64 * 1024;
+
+impl TestListener {
+    /// Creates a listener with an empty script, reporting `local` as the
+    /// address it is bound to.
+    pub fn new(local: SocketAddr) -> TestListener {
+        TestListener {
+            local,
+            script: VecDeque::new(),
+            next_port: 49152,
+        }
+    }
+
+    /// Returns the address the listener reports being bound to.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local
+    }
+
+    /// Queues a connection, returning the end of its peer.
+    ///
+    /// Bytes written to the returned stream are read from the accepted one,
+    /// and the other way around.
+    pub fn push_stream(&mut self) -> DuplexStream {
+        let (client, server) = duplex(TEST_STREAM_BUF);
+        self.push(server);
+        client
+    }
+
+    /// Queues a connection whose peer sends `data` and goes away.
+    ///
+    /// The accepted stream reads `data` and then the end of the stream,
+    /// writing to it fails with `BrokenPipe`.
+    pub fn push_data(&mut self, data: impl Into<Vec<u8>>) {
+        let data = data.into();
+        let (mut client, server) = duplex(data.len().max(1));
+
+        // The pipe has room for all of it, so the writes complete at once.
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        match Pin::new(&mut client).poll_write(&mut cx, &data) {
+            Poll::Ready(Ok(n)) => debug_assert_eq!(n, data.len()),
+            _ => unreachable!("writing to a fresh pipe with room for the data"),
+        }
+        self.push(server);
+    }
+
+    /// Queues an error, returned by the `accept` taking it.
+    pub fn push_error(&mut self, err: io::Error) {
+        self.script.push_back(Err(err));
+    }
+
+    /// Returns the number of steps left in the script.
+    pub fn remaining(&self) -> usize {
+        self.script.len()
+    }
+
+    /// Returns a stream of the scripted connections, which ends with the
+    /// script.
+    pub fn incoming(&mut self) -> TestIncoming<'_> {
+        TestIncoming { inner: self }
+    }
+
+    fn push(&mut self, io: DuplexStream) {
+        let peer = SocketAddr::new(self.local.ip(), self.next_port);
+        self.next_port = self.next_port.checked_add(1).unwrap_or(49152);
+        self.script.push_back(Ok(TestStream {
+            io,
+            local: self.local,
+            peer,
+        }));
+    }
+}
+
+impl TestStream {
+    /// Returns the address of the listener which accepted the stream.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local
+    }
+
+    /// Returns the made up address of the peer.
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.peer
+    }
+}
+
+impl AsyncRead for TestStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.io).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for TestStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.io).poll_write(cx, buf)
+    }
+
+    fn poll_flush(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_flush(cx)
+    }
+
+    fn poll_close(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_close(cx)
+    }
+}
+
+impl Transport for TestStream {
+    fn local_addr(&self) -> io::Result<Addr> {
+        Ok(Addr::Tcp(self.local))
+    }
+
+    fn peer_addr(&self) -> io::Result<Addr> {
+        Ok(Addr::Tcp(self.peer))
+    }
+}
+
+impl Listener for TestListener {
+    type Io = TestStream;
+
+    fn poll_accept(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<io::Result<(TestStream, Addr)>> {
+        match self.get_mut().script.pop_front() {
+            Some(Ok(stream)) => {
+                let peer = stream.peer;
+                Poll::Ready(Ok((stream, Addr::Tcp(peer))))
+            }
+            Some(Err(err)) => Poll::Ready(Err(err)),
+            // Nothing will ever be pushed, there is no waker to keep.
+            None => Poll::Pending,
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<Addr> {
+        Ok(Addr::Tcp(self.local))
+    }
+}
+
+/// Stream returned by `TestListener::incoming`.
+#[must_use = "streams do nothing unless polled"]
+#[derive(Debug)]
+pub struct TestIncoming<'a> {
+    inner: &'a mut TestListener,
+}
+
+impl Stream for TestIncoming<'_> {
+    type Item = io::Result<TestStream>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        Poll::Ready(self.inner.script.pop_front())
+    }
+}
+
+fn loopback() -> SocketAddr {
+    SocketAddr::from(([127, 0, 0, 1], 0))
+}
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_pairs_are_connected() {
+    use crate::runtime::{self, Runtime};
+    use futures_util::io::{AsyncReadExt, AsyncWriteExt};
+
+    let mut rt = runtime::default();
+    rt.exec(async {
+        let mut pair = tcp_pair().await.unwrap();
+        assert_eq!(pair.client.peer_addr().unwrap(), pair.server_addr);
+        assert_eq!(pair.server.peer_addr().unwrap(), pair.client_addr);
+        pair.client.write_all(b"ping").await.unwrap();
+        let mut buf = [0; 4];
+        pair.server.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+        pair.server.write_all(b"pong").await.unwrap();
+        pair.client.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"pong");
+
+        let mut pair = uds_pair().await.unwrap();
+        assert_eq!(
+            pair.client.peer_addr().unwrap().as_pathname(),
+            pair.server_addr.as_pathname()
+        );
+        pair.server.write_all(b"pong").await.unwrap();
+        pair.client.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"pong");
+
+        let mut pair = udp_pair().unwrap();
+        pair.client
+            .send_to(b"ping", &pair.server_addr)
+            .await
+            .unwrap();
+        let (n, from) = pair.server.recv_from(&mut buf).await.unwrap();
+        assert_eq!((&buf[..n], from), (&b"ping"[..], pair.client_addr));
+    });
+}
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_listener_follows_its_script() {
+    use crate::runtime::{self, Runtime};
+    use futures_util::io::{AsyncReadExt, AsyncWriteExt};
+
+    let mut listener = TestListener::new(loopback());
+    let mut client = listener.push_stream();
+    listener.push_error(io::ErrorKind::ConnectionAborted.into());
+    listener.push_data(&b"bye"[..]);
+    assert_eq!(listener.remaining(), 3);
+
+    let mut rt = runtime::default();
+    rt.exec(async {
+        let (mut stream, peer) = listener.accept().await.unwrap();
+        assert!(matches!(peer, Addr::Tcp(addr) if addr == stream.peer_addr()));
+        client.write_all(b"ping").await.unwrap();
+        let mut buf = [0; 4];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+
+        let err = listener.accept().await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::ConnectionAborted);
+
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut buf = Vec::new();
+        stream.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(buf, b"bye");
+        let err = stream.write_all(b"x").await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+        assert_eq!(listener.remaining(), 0);
+    });
+}
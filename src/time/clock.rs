@@ -0,0 +1,121 @@
+use std::time::{Duration, Instant};
+
+use crate::driver::timer::clock::{self, Clock};
+use crate::task;
+
+/// Returns the current time, as seen by the timers of the current runtime.
+///
+/// This is `Instant::now()` unless the clock of the runtime was paused with
+/// [`pause`], in which case it only moves when time is advanced.
+///
+/// [`pause`]: fn.pause.html
+pub fn now() -> Instant {
+    clock::now()
+}
+
+/// Pauses time in the current runtime.
+///
+/// Once paused, [`now`] stops moving and timers created in the runtime are
+/// driven by its clock instead of the reactor: whenever every task waits
+/// on something, the runtime moves time forward to the next timer and
+/// fires it, so a test sleeping for an hour completes instantly. Use
+/// [`advance`] to move time by hand. Timers created before the pause keep
+/// running on the system clock.
+///
+/// Time is advanced even when tasks wait on I/O, which is driven by the
+/// system clock: a test pausing time shouldn't expect a timeout to lose
+/// the race against a socket.
+///
+/// # Panics
+///
+/// Panics if not called from a `current_thread` [`DefaultRuntime`].
+///
+/// # Examples
+///
+/// ```
+/// use futures_net::runtime::{self, Runtime};
+/// use futures_net::time;
+/// use std::time::{Duration, Instant};
+///
+/// let mut rt = runtime::default();
+/// let start = Instant::now();
+///
+/// rt.exec(async {
+///     time::pause();
+///     let paused = time::now();
+///     time::sleep(Duration::from_secs(3600)).await;
+///     assert!(time::now() - paused >= Duration::from_secs(3600));
+/// });
+/// assert!(start.elapsed() < Duration::from_secs(60));
+/// ```
+///
+/// [`now`]: fn.now.html
+/// [`advance`]: fn.advance.html
+/// [`DefaultRuntime`]: ../runtime/struct.DefaultRuntime.html
+pub fn pause() {
+    current().pause()
+}
+
+/// Resumes time in the current runtime, from where it was paused.
+///
+/// Timers created while time was paused, and after, stay driven by the
+/// runtime clock, which now follows the system clock again.
+///
+/// # Panics
+///
+/// Panics if not called from a `current_thread` [`DefaultRuntime`].
+///
+/// [`DefaultRuntime`]: ../runtime/struct.DefaultRuntime.html
+pub fn resume() {
+    current().resume()
+}
+
+/// Moves paused time forward by `duration`.
+///
+/// The timers due in the meantime fire in order of their deadlines, and
+/// the tasks they wake get to run before the next one fires, as if the time
+/// had actually passed.
+///
+/// # Panics
+///
+/// Panics if time is not paused.
+///
+/// # Examples
+///
+/// ```
+/// use futures::future::{self, Either};
+/// use futures_net::runtime::{Builder, Runtime};
+/// use futures_net::time;
+/// use std::time::Duration;
+///
+/// let mut rt = Builder::new_current_thread()
+///     .start_paused(true)
+///     .build()
+///     .unwrap();
+///
+/// rt.exec(async {
+///     let sleep = time::sleep(Duration::from_secs(10));
+///     let advance = time::advance(Duration::from_secs(5));
+///     futures::pin_mut!(sleep, advance);
+///
+///     // Half way there, the sleep is still pending.
+///     assert!(matches!(future::select(sleep, advance).await, Either::Right(..)));
+/// });
+/// ```
+pub async fn advance(duration: Duration) {
+    let clock = current();
+    let target = clock.now() + duration;
+    while let Some(deadline) = clock.next_deadline() {
+        if deadline > target {
+            break;
+        }
+        clock.advance_to(deadline);
+        task::yield_now().await;
+    }
+    clock.advance_to(target);
+    task::yield_now().await;
+}
+
+fn current() -> std::sync::Arc<Clock> {
+    clock::current().expect("time can only be paused in a `current_thread` runtime")
+}
@@ -0,0 +1,187 @@
+use futures_core::future::Future;
+use std::cell::Cell;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use super::{clock, sleep_until, Elapsed, Sleep};
+
+thread_local! {
+    /// Deadline of the innermost `Deadline` being polled on this thread.
+    static CURRENT: Cell<Option<Instant>> = Cell::new(None);
+}
+
+/// Adds [`with_deadline`] to every future.
+///
+/// [`with_deadline`]: #method.with_deadline
+pub trait DeadlineExt: Future + Sized {
+    /// Fails the I/O operation `self` with a `TimedOut` error if it isn't
+    /// complete by `deadline`.
+    ///
+    /// Unlike [`timeout`], the deadline is visible to the code the wrapped
+    /// future runs, through [`deadline`] and [`remaining`], so it can be
+    /// passed on to sub-operations, or to a remote peer. Nested deadlines
+    /// can only shorten the outer one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures_net::runtime::{self, Runtime};
+    /// use futures_net::time::{self, DeadlineExt};
+    /// use std::io;
+    /// use std::time::{Duration, Instant};
+    ///
+    /// async fn query() -> io::Result<Duration> {
+    ///     // A real client would send this along with the request.
+    ///     let budget = time::remaining().unwrap();
+    ///     time::sleep(Duration::from_millis(1)).await;
+    ///     Ok(budget)
+    /// }
+    ///
+    /// let mut rt = runtime::default();
+    /// let deadline = Instant::now() + Duration::from_secs(5);
+    /// let budget = rt.exec(query().with_deadline(deadline)).unwrap();
+    /// assert!(budget <= Duration::from_secs(5));
+    ///
+    /// let slow = time::sleep(Duration::from_secs(5));
+    /// let err = rt
+    ///     .exec(async { io::Result::Ok(slow.await) }.with_deadline(Instant::now()))
+    ///     .unwrap_err();
+    /// assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    /// ```
+    ///
+    /// [`timeout`]: fn.timeout.html
+    /// [`deadline`]: fn.deadline.html
+    /// [`remaining`]: fn.remaining.html
+    fn with_deadline(self, deadline: Instant) -> Deadline<Self> {
+        Deadline {
+            fut: self,
+            sleep: sleep_until(deadline),
+        }
+    }
+}
+
+impl<F: Future> DeadlineExt for F {}
+
+/// Future returned by [`DeadlineExt::with_deadline`].
+///
+/// [`DeadlineExt::with_deadline`]: trait.DeadlineExt.html#method.with_deadline
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+#[derive(Debug)]
+pub struct Deadline<F> {
+    fut: F,
+    sleep: Sleep,
+}
+
+impl<F> Deadline<F> {
+    /// Returns the deadline of the wrapped future.
+    pub fn deadline(&self) -> Instant {
+        self.sleep.deadline()
+    }
+
+    /// Returns a reference to the wrapped future.
+    pub fn get_ref(&self) -> &F {
+        &self.fut
+    }
+
+    /// Returns a mutable reference to the wrapped future.
+    pub fn get_mut(&mut self) -> &mut F {
+        &mut self.fut
+    }
+
+    /// Consumes `self`, returning the wrapped future.
+    pub fn into_inner(self) -> F {
+        self.fut
+    }
+}
+
+impl<F, T> Future for Deadline<F>
+where
+    F: Future<Output = io::Result<T>>,
+{
+    type Output = io::Result<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<T>> {
+        // Safety: `fut` is never moved out of `self`, and `Sleep` is `Unpin`.
+        let this = unsafe { self.get_unchecked_mut() };
+        let fut = unsafe { Pin::new_unchecked(&mut this.fut) };
+
+        let deadline = this.sleep.deadline();
+        let res = {
+            let _enter = enter(deadline);
+            fut.poll(cx)
+        };
+        if res.is_ready() {
+            return res;
+        }
+        match Pin::new(&mut this.sleep).poll(cx) {
+            Poll::Ready(()) => Poll::Ready(Err(Elapsed::new().into())),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// Returns the deadline of the [`Deadline`] future being polled, if any.
+///
+/// With nested deadlines, this is the earliest one.
+///
+/// [`Deadline`]: struct.Deadline.html
+pub fn deadline() -> Option<Instant> {
+    CURRENT.with(Cell::get)
+}
+
+/// Returns the time left until [`deadline`], zero once it passed.
+///
+/// [`deadline`]: fn.deadline.html
+pub fn remaining() -> Option<Duration> {
+    deadline().map(|deadline| deadline.saturating_duration_since(clock::now()))
+}
+
+struct EnterGuard {
+    prev: Option<Instant>,
+}
+
+fn enter(deadline: Instant) -> EnterGuard {
+    let prev = CURRENT.with(|current| {
+        let prev = current.get();
+        current.set(Some(prev.map_or(deadline, |prev| prev.min(deadline))));
+        prev
+    });
+    EnterGuard { prev }
+}
+
+impl Drop for EnterGuard {
+    fn drop(&mut self) {
+        CURRENT.with(|current| current.set(self.prev));
+    }
+}
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_nested_deadlines_only_shorten() {
+    use crate::runtime::{self, Runtime};
+
+    let mut rt = runtime::default();
+    let now = Instant::now();
+    let outer = now + Duration::from_secs(10);
+
+    let seen = rt.exec(
+        async {
+            let later = async { io::Result::Ok(deadline()) }
+                .with_deadline(now + Duration::from_secs(20))
+                .await?;
+            let sooner = async { io::Result::Ok(deadline()) }
+                .with_deadline(now + Duration::from_secs(5))
+                .await?;
+            io::Result::Ok((later, sooner, deadline()))
+        }
+        .with_deadline(outer),
+    );
+
+    let (later, sooner, after) = seen.unwrap();
+    assert_eq!(later, Some(outer));
+    assert_eq!(sooner, Some(now + Duration::from_secs(5)));
+    assert_eq!(after, Some(outer));
+    assert_eq!(deadline(), None);
+}
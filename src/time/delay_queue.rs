@@ -0,0 +1,373 @@
+//! A queue of values yielded once their deadline is reached.
+
+use futures_core::stream::Stream;
+use slab::Slab;
+use std::collections::VecDeque;
+use std::fmt;
+use std::pin::Pin;
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+use crate::driver::timer::wheel::{self, Wheel, MAX_TICKS};
+use crate::driver::timer::{clock, tick_for, Timer};
+
+/// A queue of values which are yielded once their deadline is reached.
+///
+/// Values are inserted with a deadline and come out of the queue, as a
+/// [`Stream`], once that deadline is reached. Each insertion returns a
+/// [`Key`] which can be used to remove the value or to change its deadline
+/// before it expires. The queue keeps its own timing wheel and registers a
+/// single timer with the reactor, for its next expiration, so it holds
+/// large numbers of entries cheaply.
+///
+/// The stream ends when the queue is empty. It can be polled again after
+/// more values are inserted.
+///
+/// # Examples
+///
+/// ```
+/// use futures::StreamExt;
+/// use futures_net::runtime::{self, Runtime};
+/// use futures_net::time::DelayQueue;
+/// use std::time::Duration;
+///
+/// let mut rt = runtime::default();
+/// let mut sessions = DelayQueue::new();
+///
+/// sessions.insert("bob", Duration::from_millis(20));
+/// let alice = sessions.insert("alice", Duration::from_millis(10));
+/// sessions.insert("carol", Duration::from_millis(30));
+/// sessions.remove(&alice);
+///
+/// let expired: Vec<_> = rt.exec(sessions.map(|e| e.into_inner()).collect());
+/// assert_eq!(expired, ["bob", "carol"]);
+/// ```
+///
+/// [`Stream`]: https://docs.rs/futures/0.3/futures/stream/trait.Stream.html
+/// [`Key`]: struct.Key.html
+pub struct DelayQueue<T> {
+    /// Instant of tick zero of the wheel.
+    start: Instant,
+    wheel: Wheel<usize>,
+    entries: Slab<Data<T>>,
+    /// Entries which expired and haven't been yielded yet.
+    expired: VecDeque<usize>,
+    /// Fires at the next expiration of the wheel.
+    timer: Option<Timer>,
+    /// Task polling the queue, woken when an insertion moves the next
+    /// expiration earlier.
+    waker: Option<Waker>,
+}
+
+/// Identifies a value of a [`DelayQueue`].
+///
+/// A key stays valid until its value is removed or yielded by the queue.
+///
+/// [`DelayQueue`]: struct.DelayQueue.html
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Key {
+    index: usize,
+}
+
+/// A value yielded by a [`DelayQueue`] once its deadline was reached.
+///
+/// [`DelayQueue`]: struct.DelayQueue.html
+#[derive(Debug)]
+pub struct Expired<T> {
+    data: T,
+    deadline: Instant,
+    key: Key,
+}
+
+struct Data<T> {
+    value: T,
+    deadline: Instant,
+    /// `None` once the entry expired.
+    wheel_key: Option<wheel::Key>,
+}
+
+impl<T> DelayQueue<T> {
+    /// Creates an empty queue.
+    pub fn new() -> DelayQueue<T> {
+        DelayQueue::with_capacity(0)
+    }
+
+    /// Creates an empty queue with room for `capacity` values.
+    pub fn with_capacity(capacity: usize) -> DelayQueue<T> {
+        DelayQueue {
+            start: clock::now(),
+            wheel: Wheel::new(),
+            entries: Slab::with_capacity(capacity),
+            expired: VecDeque::new(),
+            timer: None,
+            waker: None,
+        }
+    }
+
+    /// Inserts `value`, to be yielded once `timeout` has elapsed.
+    pub fn insert(&mut self, value: T, timeout: Duration) -> Key {
+        self.insert_at(value, clock::now() + timeout)
+    }
+
+    /// Inserts `value`, to be yielded once `deadline` is reached.
+    pub fn insert_at(&mut self, value: T, deadline: Instant) -> Key {
+        let index = self.entries.insert(Data {
+            value,
+            deadline,
+            wheel_key: None,
+        });
+        self.schedule(index);
+        Key { index }
+    }
+
+    /// Removes the value identified by `key`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` is not in the queue.
+    pub fn remove(&mut self, key: &Key) -> Expired<T> {
+        self.try_remove(key).expect("invalid key")
+    }
+
+    /// Removes the value identified by `key`, if it is still in the queue.
+    pub fn try_remove(&mut self, key: &Key) -> Option<Expired<T>> {
+        self.unschedule(key.index)?;
+        let data = self.entries.remove(key.index);
+        Some(Expired {
+            data: data.value,
+            deadline: data.deadline,
+            key: key.clone(),
+        })
+    }
+
+    /// Moves the deadline of the value identified by `key` to `timeout`
+    /// from now.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` is not in the queue.
+    pub fn reset(&mut self, key: &Key, timeout: Duration) {
+        self.reset_at(key, clock::now() + timeout);
+    }
+
+    /// Moves the deadline of the value identified by `key` to `deadline`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` is not in the queue.
+    pub fn reset_at(&mut self, key: &Key, deadline: Instant) {
+        self.unschedule(key.index).expect("invalid key");
+        self.entries[key.index].deadline = deadline;
+        self.schedule(key.index);
+    }
+
+    /// Returns the deadline of the value identified by `key`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` is not in the queue.
+    pub fn deadline(&self, key: &Key) -> Instant {
+        self.entries.get(key.index).expect("invalid key").deadline
+    }
+
+    /// Returns the number of values in the queue.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if the queue is empty.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Removes every value.
+    pub fn clear(&mut self) {
+        self.wheel.drain();
+        self.entries.clear();
+        self.expired.clear();
+    }
+
+    /// Polls for the next value whose deadline was reached.
+    ///
+    /// Returns `Ready(None)` if the queue is empty.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no reactor can drive the timer of the queue, like
+    /// [`Sleep`].
+    ///
+    /// [`Sleep`]: struct.Sleep.html
+    pub fn poll_expired(&mut self, cx: &mut Context<'_>) -> Poll<Option<Expired<T>>> {
+        self.waker = Some(cx.waker().clone());
+
+        loop {
+            if let Some(index) = self.expired.pop_front() {
+                let data = self.entries.remove(index);
+                return Poll::Ready(Some(Expired {
+                    data: data.value,
+                    deadline: data.deadline,
+                    key: Key { index },
+                }));
+            }
+            if self.wheel.is_empty() {
+                return Poll::Ready(None);
+            }
+
+            let now = tick_for(self.start, clock::now(), false);
+            self.advance(now);
+            if !self.expired.is_empty() {
+                continue;
+            }
+
+            let next = self.wheel.next_expiration().unwrap();
+            let deadline = self.start + Duration::from_millis(next);
+            let timer = match &mut self.timer {
+                Some(timer) => {
+                    if timer.deadline() != deadline {
+                        timer.reset(deadline);
+                    }
+                    timer
+                }
+                None => self.timer.get_or_insert(Timer::new(deadline)),
+            };
+            match timer.poll_elapsed(cx) {
+                Poll::Ready(Ok(())) => continue,
+                Poll::Ready(Err(e)) => panic!("timer error: {}", e),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+
+    fn advance(&mut self, now: u64) {
+        let entries = &mut self.entries;
+        let expired = &mut self.expired;
+        self.wheel.advance(now, |index| {
+            entries[index].wheel_key = None;
+            expired.push_back(index);
+        });
+    }
+
+    /// Puts the entry at `index` in the wheel, or in the expired list if its
+    /// deadline was reached.
+    fn schedule(&mut self, index: usize) {
+        let when = tick_for(self.start, self.entries[index].deadline, true);
+        let when = when.min(self.wheel.elapsed() + MAX_TICKS - 1);
+        let next = self.wheel.next_expiration();
+
+        match self.wheel.insert(when, index) {
+            Ok(key) => {
+                self.entries[index].wheel_key = Some(key);
+                if next.map_or(true, |next| when < next) {
+                    self.wake();
+                }
+            }
+            Err(_) => {
+                self.expired.push_back(index);
+                self.wake();
+            }
+        }
+    }
+
+    /// Takes the entry at `index` out of the wheel or of the expired list.
+    fn unschedule(&mut self, index: usize) -> Option<()> {
+        let data = self.entries.get_mut(index)?;
+        match data.wheel_key.take() {
+            Some(key) => {
+                self.wheel.remove(key);
+            }
+            None => {
+                let pos = self.expired.iter().position(|&i| i == index)?;
+                self.expired.remove(pos);
+            }
+        }
+        Some(())
+    }
+
+    fn wake(&mut self) {
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> Default for DelayQueue<T> {
+    fn default() -> DelayQueue<T> {
+        DelayQueue::new()
+    }
+}
+
+// Values are never pinned.
+impl<T> Unpin for DelayQueue<T> {}
+
+impl<T> Stream for DelayQueue<T> {
+    type Item = Expired<T>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Expired<T>>> {
+        self.get_mut().poll_expired(cx)
+    }
+}
+
+impl<T> fmt::Debug for DelayQueue<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DelayQueue")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+impl<T> Expired<T> {
+    /// Returns a reference to the value.
+    pub fn get_ref(&self) -> &T {
+        &self.data
+    }
+
+    /// Returns a mutable reference to the value.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.data
+    }
+
+    /// Consumes `self`, returning the value.
+    pub fn into_inner(self) -> T {
+        self.data
+    }
+
+    /// Returns the deadline the value was inserted with.
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+
+    /// Returns the key the value was inserted with.
+    pub fn key(&self) -> Key {
+        self.key.clone()
+    }
+}
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_reset_and_insert_while_waiting() {
+    use crate::runtime::{self, Runtime};
+    use futures_util::future::{self, Either};
+    use futures_util::StreamExt;
+
+    let mut rt = runtime::default();
+    let mut queue = DelayQueue::new();
+    let late = queue.insert("late", Duration::from_secs(60));
+
+    rt.exec(async {
+        // Wait on the late entry, then insert an earlier one; the queue must
+        // pick it up without waiting for the first deadline.
+        let pending =
+            future::select(queue.next(), crate::time::sleep(Duration::from_millis(5)));
+        assert!(matches!(pending.await, Either::Right(_)));
+        queue.insert("early", Duration::from_millis(5));
+        assert_eq!(queue.next().await.unwrap().into_inner(), "early");
+
+        queue.reset(&late, Duration::from_millis(1));
+        let expired = queue.next().await.unwrap();
+        assert_eq!(expired.key(), late);
+        assert!(queue.next().await.is_none());
+    });
+}
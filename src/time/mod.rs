@@ -0,0 +1,59 @@
+//! Utilities for tracking time.
+//!
+//! Timers are driven by the same reactor as the I/O resources: the reactor
+//! wakes up when the earliest timer is due, so waiting for time doesn't
+//! need a thread of its own.
+//!
+//! [`timeout`] bounds the time any future may take, while
+//! [`DeadlineExt::with_deadline`] fails an I/O operation once its deadline
+//! passes and lets the code it runs look the deadline up with [`remaining`].
+//!
+//! Tests can [`pause`] time, after which sleeps complete as soon as there
+//! is nothing else to do, without waiting for the system clock.
+//!
+//! # Examples
+//!
+//! Retrying an operation with an exponential backoff:
+//!
+//! ```
+//! use futures_net::runtime::Runtime;
+//! use futures_net::time;
+//! use std::time::Duration;
+//!
+//! # fn attempt(n: u32) -> Result<u32, ()> { if n < 2 { Err(()) } else { Ok(n) } }
+//! #[futures_net::main]
+//! async fn main() {
+//!     let mut backoff = Duration::from_millis(1);
+//!     for n in 0.. {
+//!         match attempt(n) {
+//!             Ok(_) => break,
+//!             Err(_) => {
+//!                 time::sleep(backoff).await;
+//!                 backoff *= 2;
+//!             }
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! [`timeout`]: fn.timeout.html
+//! [`DeadlineExt::with_deadline`]: trait.DeadlineExt.html#method.with_deadline
+//! [`remaining`]: fn.remaining.html
+//! [`pause`]: fn.pause.html
+
+mod clock;
+mod deadline;
+pub mod delay_queue;
+mod sleep;
+mod stream;
+mod timeout;
+
+pub use self::clock::{advance, now, pause, resume};
+pub use self::deadline::{deadline, remaining, Deadline, DeadlineExt};
+#[doc(inline)]
+pub use self::delay_queue::DelayQueue;
+pub use self::sleep::{sleep, sleep_until, Sleep};
+pub use self::stream::{
+    chunks_timeout, debounce, throttle, ChunksTimeout, Debounce, Throttle,
+};
+pub use self::timeout::{timeout, timeout_at, Elapsed, Timeout};
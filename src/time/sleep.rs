@@ -0,0 +1,172 @@
+use futures_core::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use crate::driver::timer::{clock, Timer};
+
+/// Waits until `duration` has elapsed.
+///
+/// The timer is registered with the reactor of the current execution
+/// context the first time the returned future is polled, and has a
+/// resolution of about a millisecond.
+///
+/// # Examples
+///
+/// ```
+/// use futures_net::runtime::{self, Runtime};
+/// use futures_net::time;
+/// use std::time::{Duration, Instant};
+///
+/// let mut rt = runtime::default();
+/// let start = Instant::now();
+///
+/// rt.exec(time::sleep(Duration::from_millis(10)));
+/// assert!(start.elapsed() >= Duration::from_millis(10));
+/// ```
+pub fn sleep(duration: Duration) -> Sleep {
+    sleep_until(clock::now() + duration)
+}
+
+/// Waits until `deadline` is reached.
+///
+/// # Examples
+///
+/// ```
+/// use futures_net::runtime::{self, Runtime};
+/// use futures_net::time;
+/// use std::time::{Duration, Instant};
+///
+/// let mut rt = runtime::default();
+/// let deadline = Instant::now() + Duration::from_millis(10);
+///
+/// rt.exec(time::sleep_until(deadline));
+/// assert!(Instant::now() >= deadline);
+/// ```
+pub fn sleep_until(deadline: Instant) -> Sleep {
+    Sleep {
+        timer: Timer::new(deadline),
+    }
+}
+
+/// Future returned by [`sleep`] and [`sleep_until`].
+///
+/// # Panics
+///
+/// Polling a `Sleep` panics if no reactor can be created to drive it, or
+/// if its reactor is shut down before the deadline.
+///
+/// [`sleep`]: fn.sleep.html
+/// [`sleep_until`]: fn.sleep_until.html
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+#[derive(Debug)]
+pub struct Sleep {
+    timer: Timer,
+}
+
+impl Sleep {
+    /// Returns the instant at which the future completes.
+    pub fn deadline(&self) -> Instant {
+        self.timer.deadline()
+    }
+
+    /// Returns `true` if the deadline has been reached.
+    pub fn is_elapsed(&self) -> bool {
+        self.timer.is_elapsed() || clock::now() >= self.deadline()
+    }
+
+    /// Moves the deadline to `deadline`.
+    ///
+    /// The future can be reset whether or not it completed, and completes
+    /// again once the new deadline is reached. This keeps the timer of an
+    /// idle timeout allocated while rearming it on each activity.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::channel::mpsc;
+    /// use futures::future::{self, Either};
+    /// use futures::StreamExt;
+    /// use futures_net::runtime::{self, Runtime};
+    /// use futures_net::time;
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let mut rt = runtime::default();
+    /// let (tx, mut activity) = mpsc::unbounded();
+    /// tx.unbounded_send(()).unwrap();
+    ///
+    /// let idle = Duration::from_millis(20);
+    /// let seen = rt.exec(async {
+    ///     let mut timeout = time::sleep(idle);
+    ///     let mut seen = 0;
+    ///     // Gives up once no activity happened for `idle`.
+    ///     while let Either::Left(_) = future::select(activity.next(), &mut timeout).await {
+    ///         seen += 1;
+    ///         timeout.reset(Instant::now() + idle);
+    ///     }
+    ///     seen
+    /// });
+    /// assert_eq!(seen, 1);
+    /// ```
+    pub fn reset(&mut self, deadline: Instant) {
+        self.timer.reset(deadline);
+    }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        match self.timer.poll_elapsed(cx) {
+            Poll::Ready(Ok(())) => Poll::Ready(()),
+            Poll::Ready(Err(e)) => panic!("timer error: {}", e),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_sleeps_wake_in_order() {
+    use crate::runtime::{self, Runtime};
+    use futures_util::future;
+
+    let mut rt = runtime::default();
+    let order = std::cell::RefCell::new(Vec::new());
+    let start = Instant::now();
+
+    rt.exec(future::join3(
+        async {
+            sleep(Duration::from_millis(30)).await;
+            order.borrow_mut().push(30);
+        },
+        async {
+            sleep(Duration::from_millis(10)).await;
+            order.borrow_mut().push(10);
+        },
+        async {
+            sleep(Duration::from_millis(20)).await;
+            order.borrow_mut().push(20);
+        },
+    ));
+
+    assert_eq!(*order.borrow(), [10, 20, 30]);
+    assert!(start.elapsed() >= Duration::from_millis(30));
+}
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_reset_after_completion() {
+    use crate::runtime::{self, Runtime};
+
+    let mut rt = runtime::default();
+    let mut sleep = sleep(Duration::from_millis(1));
+    rt.exec(&mut sleep);
+    assert!(sleep.is_elapsed());
+
+    let deadline = Instant::now() + Duration::from_millis(10);
+    sleep.reset(deadline);
+    assert!(!sleep.is_elapsed());
+    rt.exec(&mut sleep);
+    assert!(Instant::now() >= deadline);
+}
@@ -0,0 +1,316 @@
+use futures_core::future::Future;
+use futures_core::stream::Stream;
+use futures_util::ready;
+use std::fmt;
+use std::mem;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use super::{now, sleep_until, Sleep};
+
+/// Limits `stream` to one item per `duration`.
+///
+/// The first item is yielded right away, and each following one no sooner
+/// than `duration` after the previous one. Items are delayed, not dropped:
+/// `stream` isn't polled while waiting.
+///
+/// # Examples
+///
+/// ```
+/// use futures::stream::{self, StreamExt};
+/// use futures_net::runtime::{Builder, Runtime};
+/// use futures_net::time;
+/// use std::time::Duration;
+///
+/// let mut rt = Builder::new_current_thread().start_paused(true).build().unwrap();
+///
+/// rt.exec(async {
+///     let start = time::now();
+///     let items: Vec<_> = time::throttle(Duration::from_secs(1), stream::iter(1..=3))
+///         .collect()
+///         .await;
+///     assert_eq!(items, [1, 2, 3]);
+///     assert!(time::now() - start >= Duration::from_secs(2));
+/// });
+/// ```
+pub fn throttle<S: Stream>(duration: Duration, stream: S) -> Throttle<S> {
+    Throttle {
+        stream,
+        duration,
+        sleep: sleep_until(now()),
+        waiting: false,
+    }
+}
+
+/// Yields the last item of each burst of `stream`, once it has been quiet
+/// for `duration`.
+///
+/// Each item replaces the one waiting to be yielded and restarts the wait.
+/// When `stream` ends, the waiting item is yielded right away.
+///
+/// # Examples
+///
+/// ```
+/// use futures::channel::mpsc;
+/// use futures::StreamExt;
+/// use futures_net::runtime::{Builder, Runtime};
+/// use futures_net::time;
+/// use std::time::Duration;
+///
+/// let mut rt = Builder::new_current_thread().start_paused(true).build().unwrap();
+/// let (tx, rx) = mpsc::unbounded();
+///
+/// rt.exec(async {
+///     let mut changes = time::debounce(Duration::from_millis(100), rx);
+///     for n in 0..3 {
+///         tx.unbounded_send(n).unwrap();
+///     }
+///     assert_eq!(changes.next().await, Some(2));
+///
+///     tx.unbounded_send(3).unwrap();
+///     drop(tx);
+///     assert_eq!(changes.next().await, Some(3));
+///     assert_eq!(changes.next().await, None);
+/// });
+/// ```
+pub fn debounce<S: Stream>(duration: Duration, stream: S) -> Debounce<S> {
+    Debounce {
+        stream,
+        duration,
+        sleep: sleep_until(now()),
+        pending: None,
+        done: false,
+    }
+}
+
+/// Batches the items of `stream` into vectors of up to `capacity` items.
+///
+/// A batch is yielded once it is full, or once `duration` has elapsed since
+/// its first item was received, whichever comes first. This bounds both the
+/// number of items a writer has to buffer and how long an item waits to be
+/// flushed. The last batch is yielded when `stream` ends.
+///
+/// # Panics
+///
+/// Panics if `capacity` is zero.
+///
+/// # Examples
+///
+/// ```
+/// use futures::channel::mpsc;
+/// use futures::StreamExt;
+/// use futures_net::runtime::{Builder, Runtime};
+/// use futures_net::time;
+/// use std::time::Duration;
+///
+/// let mut rt = Builder::new_current_thread().start_paused(true).build().unwrap();
+/// let (tx, rx) = mpsc::unbounded();
+///
+/// rt.exec(async {
+///     let mut batches = time::chunks_timeout(3, Duration::from_millis(10), rx);
+///     for n in 0..4 {
+///         tx.unbounded_send(n).unwrap();
+///     }
+///     // The first batch is full, the second one is flushed by the timeout.
+///     assert_eq!(batches.next().await, Some(vec![0, 1, 2]));
+///     assert_eq!(batches.next().await, Some(vec![3]));
+/// });
+/// ```
+pub fn chunks_timeout<S: Stream>(
+    capacity: usize,
+    duration: Duration,
+    stream: S,
+) -> ChunksTimeout<S> {
+    assert!(capacity > 0, "chunk capacity must be greater than zero");
+    ChunksTimeout {
+        stream,
+        capacity,
+        duration,
+        items: Vec::with_capacity(capacity),
+        sleep: sleep_until(now()),
+        done: false,
+    }
+}
+
+/// Stream returned by [`throttle`].
+///
+/// [`throttle`]: fn.throttle.html
+#[must_use = "streams do nothing unless polled"]
+#[derive(Debug)]
+pub struct Throttle<S> {
+    stream: S,
+    duration: Duration,
+    sleep: Sleep,
+    /// Set while `sleep` holds back the next item.
+    waiting: bool,
+}
+
+impl<S: Stream> Stream for Throttle<S> {
+    type Item = S::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<S::Item>> {
+        // Safety: `stream` is never moved out of `self`, and `Sleep` is
+        // `Unpin`.
+        let this = unsafe { self.get_unchecked_mut() };
+        let stream = unsafe { Pin::new_unchecked(&mut this.stream) };
+
+        if this.waiting {
+            ready!(Pin::new(&mut this.sleep).poll(cx));
+            this.waiting = false;
+        }
+
+        let item = ready!(stream.poll_next(cx));
+        if item.is_some() {
+            this.sleep.reset(now() + this.duration);
+            this.waiting = true;
+        }
+        Poll::Ready(item)
+    }
+}
+
+/// Stream returned by [`debounce`].
+///
+/// [`debounce`]: fn.debounce.html
+#[must_use = "streams do nothing unless polled"]
+pub struct Debounce<S: Stream> {
+    stream: S,
+    duration: Duration,
+    sleep: Sleep,
+    pending: Option<S::Item>,
+    done: bool,
+}
+
+impl<S: Stream> Stream for Debounce<S> {
+    type Item = S::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<S::Item>> {
+        // Safety: `stream` is never moved out of `self`, only `pending` is,
+        // and it isn't pinned.
+        let this = unsafe { self.get_unchecked_mut() };
+        let mut stream = unsafe { Pin::new_unchecked(&mut this.stream) };
+
+        while !this.done {
+            match stream.as_mut().poll_next(cx) {
+                Poll::Ready(Some(item)) => {
+                    this.pending = Some(item);
+                    this.sleep.reset(now() + this.duration);
+                }
+                Poll::Ready(None) => this.done = true,
+                Poll::Pending => break,
+            }
+        }
+
+        if this.done {
+            return Poll::Ready(this.pending.take());
+        }
+        if this.pending.is_some() {
+            ready!(Pin::new(&mut this.sleep).poll(cx));
+            return Poll::Ready(this.pending.take());
+        }
+        Poll::Pending
+    }
+}
+
+impl<S: Stream + fmt::Debug> fmt::Debug for Debounce<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Debounce")
+            .field("stream", &self.stream)
+            .field("duration", &self.duration)
+            .field("pending", &self.pending.is_some())
+            .finish()
+    }
+}
+
+/// Stream returned by [`chunks_timeout`].
+///
+/// [`chunks_timeout`]: fn.chunks_timeout.html
+#[must_use = "streams do nothing unless polled"]
+pub struct ChunksTimeout<S: Stream> {
+    stream: S,
+    capacity: usize,
+    duration: Duration,
+    items: Vec<S::Item>,
+    /// Deadline of the batch being filled.
+    sleep: Sleep,
+    done: bool,
+}
+
+impl<S: Stream> ChunksTimeout<S> {
+    fn take(&mut self) -> Vec<S::Item> {
+        mem::replace(&mut self.items, Vec::with_capacity(self.capacity))
+    }
+}
+
+impl<S: Stream> Stream for ChunksTimeout<S> {
+    type Item = Vec<S::Item>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Vec<S::Item>>> {
+        // Safety: `stream` is never moved out of `self`, only `items` is, and
+        // it isn't pinned.
+        let this = unsafe { self.get_unchecked_mut() };
+
+        if this.done {
+            return Poll::Ready(None);
+        }
+
+        loop {
+            let stream = unsafe { Pin::new_unchecked(&mut this.stream) };
+            match stream.poll_next(cx) {
+                Poll::Ready(Some(item)) => {
+                    if this.items.is_empty() {
+                        this.sleep.reset(now() + this.duration);
+                    }
+                    this.items.push(item);
+                    if this.items.len() >= this.capacity {
+                        return Poll::Ready(Some(this.take()));
+                    }
+                }
+                Poll::Ready(None) => {
+                    this.done = true;
+                    if this.items.is_empty() {
+                        return Poll::Ready(None);
+                    }
+                    return Poll::Ready(Some(this.take()));
+                }
+                Poll::Pending => break,
+            }
+        }
+
+        if this.items.is_empty() {
+            return Poll::Pending;
+        }
+        ready!(Pin::new(&mut this.sleep).poll(cx));
+        Poll::Ready(Some(this.take()))
+    }
+}
+
+impl<S: Stream + fmt::Debug> fmt::Debug for ChunksTimeout<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChunksTimeout")
+            .field("stream", &self.stream)
+            .field("capacity", &self.capacity)
+            .field("duration", &self.duration)
+            .field("buffered", &self.items.len())
+            .finish()
+    }
+}
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_chunks_flush_at_end_of_stream() {
+    use crate::runtime::{Builder, Runtime};
+    use futures_util::stream::{self, StreamExt};
+
+    let mut rt = Builder::new_current_thread()
+        .start_paused(true)
+        .build()
+        .unwrap();
+
+    let batches: Vec<_> = rt
+        .exec(chunks_timeout(2, Duration::from_secs(60), stream::iter(0..5)).collect());
+    assert_eq!(batches, [vec![0, 1], vec![2, 3], vec![4]]);
+}
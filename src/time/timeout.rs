@@ -0,0 +1,128 @@
+use futures_core::future::Future;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use super::{clock, sleep_until, Sleep};
+
+/// Requires `fut` to complete within `duration`.
+///
+/// The returned future yields the output of `fut`, or an [`Elapsed`] error
+/// if the time ran out first, in which case `fut` is dropped.
+///
+/// # Examples
+///
+/// ```
+/// use futures::future;
+/// use futures_net::runtime::{self, Runtime};
+/// use futures_net::time;
+/// use std::time::Duration;
+///
+/// let mut rt = runtime::default();
+///
+/// let res = rt.exec(time::timeout(Duration::from_millis(10), future::pending::<()>()));
+/// assert!(res.is_err());
+///
+/// let res = rt.exec(time::timeout(Duration::from_secs(10), async { 42 }));
+/// assert_eq!(res.unwrap(), 42);
+/// ```
+///
+/// [`Elapsed`]: struct.Elapsed.html
+pub fn timeout<F: Future>(duration: Duration, fut: F) -> Timeout<F> {
+    timeout_at(clock::now() + duration, fut)
+}
+
+/// Requires `fut` to complete before `deadline`.
+///
+/// See [`timeout`].
+///
+/// [`timeout`]: fn.timeout.html
+pub fn timeout_at<F: Future>(deadline: Instant, fut: F) -> Timeout<F> {
+    Timeout {
+        fut,
+        sleep: sleep_until(deadline),
+    }
+}
+
+/// Future returned by [`timeout`] and [`timeout_at`].
+///
+/// [`timeout`]: fn.timeout.html
+/// [`timeout_at`]: fn.timeout_at.html
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+#[derive(Debug)]
+pub struct Timeout<F> {
+    fut: F,
+    sleep: Sleep,
+}
+
+/// Error returned by [`Timeout`] when the time ran out.
+///
+/// [`Timeout`]: struct.Timeout.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Elapsed {
+    _priv: (),
+}
+
+impl Elapsed {
+    pub(super) fn new() -> Elapsed {
+        Elapsed { _priv: () }
+    }
+}
+
+impl<F> Timeout<F> {
+    /// Returns the instant at which the time runs out.
+    pub fn deadline(&self) -> Instant {
+        self.sleep.deadline()
+    }
+
+    /// Returns a reference to the wrapped future.
+    pub fn get_ref(&self) -> &F {
+        &self.fut
+    }
+
+    /// Returns a mutable reference to the wrapped future.
+    pub fn get_mut(&mut self) -> &mut F {
+        &mut self.fut
+    }
+
+    /// Consumes `self`, returning the wrapped future.
+    pub fn into_inner(self) -> F {
+        self.fut
+    }
+}
+
+impl<F: Future> Future for Timeout<F> {
+    type Output = Result<F::Output, Elapsed>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // Safety: `fut` is never moved out of `self`, and `Sleep` is `Unpin`.
+        let this = unsafe { self.get_unchecked_mut() };
+        let fut = unsafe { Pin::new_unchecked(&mut this.fut) };
+
+        // A future completing at its deadline still counts as completed.
+        if let Poll::Ready(out) = fut.poll(cx) {
+            return Poll::Ready(Ok(out));
+        }
+        match Pin::new(&mut this.sleep).poll(cx) {
+            Poll::Ready(()) => Poll::Ready(Err(Elapsed::new())),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl fmt::Display for Elapsed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("deadline has elapsed")
+    }
+}
+
+impl Error for Elapsed {}
+
+impl From<Elapsed> for io::Error {
+    fn from(err: Elapsed) -> io::Error {
+        io::Error::new(io::ErrorKind::TimedOut, err)
+    }
+}
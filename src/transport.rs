@@ -0,0 +1,488 @@
+//! Abstractions over the stream transports.
+//!
+//! [`Transport`] is implemented by the connected stream types and
+//! [`Listener`] by the listeners accepting them, so a server can be written
+//! once for TCP and Unix sockets alike. [`AnyStream`] and [`AnyListener`]
+//! hold either kind, for servers listening on both at the same time.
+//!
+//! Listeners of both kinds also report the connections they accept to a
+//! [`ConnectionObserver`], if one is set.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use futures::prelude::*;
+//! use futures_net::transport::{AnyListener, Listener};
+//! use futures_net::{TcpListener, UnixListener};
+//!
+//! async fn serve<L: Listener + Unpin>(mut listener: L) -> std::io::Result<()> {
+//!     loop {
+//!         let (mut stream, peer) = listener.accept().await?;
+//!         println!("connection from {:?}", peer);
+//!         stream.write_all(b"hello\n").await?;
+//!     }
+//! }
+//!
+//! # async fn run() -> std::io::Result<()> {
+//! let addr = "127.0.0.1:8080".parse().unwrap();
+//! let tcp = AnyListener::from(TcpListener::bind(&addr)?);
+//! let unix = AnyListener::from(UnixListener::bind("/tmp/server.sock")?);
+//! future::try_join(serve(tcp), serve(unix)).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`Transport`]: trait.Transport.html
+//! [`Listener`]: trait.Listener.html
+//! [`AnyStream`]: enum.AnyStream.html
+//! [`AnyListener`]: enum.AnyListener.html
+//! [`ConnectionObserver`]: trait.ConnectionObserver.html
+
+use async_ready::AsyncReady;
+use futures_core::Future;
+use futures_io::{AsyncRead, AsyncWrite};
+use futures_util::ready;
+use std::io;
+use std::net::SocketAddr;
+use std::os::unix::net;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering::Relaxed};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use crate::{TcpListener, TcpStream, UnixListener, UnixStream};
+
+/// The address of either end of a transport.
+#[derive(Debug, Clone)]
+pub enum Addr {
+    /// The address of a TCP socket.
+    Tcp(SocketAddr),
+    /// The address of a Unix socket, unnamed for most clients.
+    Unix(net::SocketAddr),
+}
+
+/// A connected byte stream.
+pub trait Transport: AsyncRead + AsyncWrite + Unpin {
+    /// Returns the address of the local end of the stream.
+    fn local_addr(&self) -> io::Result<Addr>;
+
+    /// Returns the address of the peer of the stream.
+    fn peer_addr(&self) -> io::Result<Addr>;
+}
+
+/// A listener accepting connections of a [`Transport`].
+///
+/// [`Transport`]: trait.Transport.html
+pub trait Listener {
+    /// The type of the accepted connections.
+    type Io: Transport;
+
+    /// Polls for the next connection, along with the address of its peer.
+    fn poll_accept(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<(Self::Io, Addr)>>;
+
+    /// Returns the address the listener is bound to.
+    fn local_addr(&self) -> io::Result<Addr>;
+
+    /// Accepts the next connection, along with the address of its peer.
+    fn accept(&mut self) -> Accept<'_, Self>
+    where
+        Self: Unpin,
+    {
+        Accept { listener: self }
+    }
+}
+
+/// Future returned by [`Listener::accept`].
+///
+/// [`Listener::accept`]: trait.Listener.html#method.accept
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+#[derive(Debug)]
+pub struct Accept<'a, L: ?Sized> {
+    listener: &'a mut L,
+}
+
+impl<L: Listener + Unpin + ?Sized> Future for Accept<'_, L> {
+    type Output = io::Result<(L::Io, Addr)>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut *self.get_mut().listener).poll_accept(cx)
+    }
+}
+
+/// Observes the connections accepted by a listener, so audit logging or
+/// connection tracking is attached to the listener once rather than to each
+/// handler.
+///
+/// Set with [`TcpListener::set_observer`] or [`UnixListener::set_observer`].
+/// The methods are called from the task accepting or dropping the stream,
+/// and shouldn't block. Both do nothing by default.
+///
+/// # Examples
+///
+/// ```
+/// use futures::StreamExt;
+/// use futures_net::runtime::{self, Runtime};
+/// use futures_net::transport::{Connection, ConnectionObserver};
+/// use futures_net::{TcpListener, TcpStream};
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use std::sync::Arc;
+///
+/// #[derive(Default)]
+/// struct Open(AtomicUsize);
+///
+/// impl ConnectionObserver for Open {
+///     fn on_accept(&self, conn: &Connection) {
+///         println!("#{} accepted from {:?}", conn.id, conn.peer);
+///         self.0.fetch_add(1, Ordering::Relaxed);
+///     }
+///
+///     fn on_close(&self, conn: &Connection) {
+///         println!("#{} closed", conn.id);
+///         self.0.fetch_sub(1, Ordering::Relaxed);
+///     }
+/// }
+///
+/// let open = Arc::new(Open::default());
+/// let mut rt = runtime::default();
+/// rt.exec(async {
+///     let mut listener = TcpListener::bind(&"127.0.0.1:0".parse().unwrap())?;
+///     listener.set_observer(Some(open.clone()));
+///
+///     let _client = TcpStream::connect(&listener.local_addr()?).await?;
+///     let stream = listener.incoming().next().await.unwrap()?;
+///     assert_eq!(open.0.load(Ordering::Relaxed), 1);
+///     drop(stream);
+///     assert_eq!(open.0.load(Ordering::Relaxed), 0);
+///     Ok::<_, std::io::Error>(())
+/// })?;
+/// # Ok::<_, std::io::Error>(())
+/// ```
+///
+/// [`TcpListener::set_observer`]: ../tcp/struct.TcpListener.html#method.set_observer
+/// [`UnixListener::set_observer`]: ../uds/struct.UnixListener.html#method.set_observer
+pub trait ConnectionObserver: Send + Sync {
+    /// Called when the listener yields a connection.
+    ///
+    /// Connections a `TcpListener` closes on its own, for going over the
+    /// limit of their address, are never reported.
+    fn on_accept(&self, conn: &Connection) {
+        let _ = conn;
+    }
+
+    /// Called when the stream of a connection reported by [`on_accept`] is
+    /// dropped or converted into a standard library stream.
+    ///
+    /// [`on_accept`]: #method.on_accept
+    fn on_close(&self, conn: &Connection) {
+        let _ = conn;
+    }
+}
+
+/// A connection reported to a [`ConnectionObserver`].
+///
+/// [`ConnectionObserver`]: trait.ConnectionObserver.html
+#[derive(Debug, Clone)]
+pub struct Connection {
+    /// An id of the connection, unique within the process, also returned by
+    /// the `connection_id` method of the stream.
+    pub id: u64,
+    /// The address of the peer.
+    pub peer: Addr,
+}
+
+/// Reports the closing of an accepted connection when dropped.
+pub(crate) struct CloseHook {
+    observer: Arc<dyn ConnectionObserver>,
+    conn: Connection,
+}
+
+impl CloseHook {
+    /// Reports a connection from `peer` to `observer` as accepted.
+    pub(crate) fn accepted(
+        observer: &Arc<dyn ConnectionObserver>,
+        peer: Addr,
+    ) -> CloseHook {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+        let conn = Connection {
+            id: NEXT_ID.fetch_add(1, Relaxed),
+            peer,
+        };
+        observer.on_accept(&conn);
+        CloseHook {
+            observer: observer.clone(),
+            conn,
+        }
+    }
+
+    pub(crate) fn id(&self) -> u64 {
+        self.conn.id
+    }
+}
+
+impl Drop for CloseHook {
+    fn drop(&mut self) {
+        self.observer.on_close(&self.conn);
+    }
+}
+
+impl Transport for TcpStream {
+    fn local_addr(&self) -> io::Result<Addr> {
+        TcpStream::local_addr(self).map(Addr::Tcp)
+    }
+
+    fn peer_addr(&self) -> io::Result<Addr> {
+        TcpStream::peer_addr(self).map(Addr::Tcp)
+    }
+}
+
+impl Transport for UnixStream {
+    fn local_addr(&self) -> io::Result<Addr> {
+        UnixStream::local_addr(self).map(Addr::Unix)
+    }
+
+    fn peer_addr(&self) -> io::Result<Addr> {
+        UnixStream::peer_addr(self).map(Addr::Unix)
+    }
+}
+
+impl Listener for TcpListener {
+    type Io = TcpStream;
+
+    fn poll_accept(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<(TcpStream, Addr)>> {
+        let (stream, addr) = ready!(self.poll_ready(cx))?;
+        Poll::Ready(Ok((stream, Addr::Tcp(addr))))
+    }
+
+    fn local_addr(&self) -> io::Result<Addr> {
+        TcpListener::local_addr(self).map(Addr::Tcp)
+    }
+}
+
+impl Listener for UnixListener {
+    type Io = UnixStream;
+
+    fn poll_accept(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<(UnixStream, Addr)>> {
+        let (stream, addr) = ready!(self.poll_ready(cx))?;
+        Poll::Ready(Ok((stream, Addr::Unix(addr))))
+    }
+
+    fn local_addr(&self) -> io::Result<Addr> {
+        UnixListener::local_addr(self).map(Addr::Unix)
+    }
+}
+
+/// A stream of either transport.
+#[derive(Debug)]
+pub enum AnyStream {
+    /// A TCP stream.
+    Tcp(TcpStream),
+    /// A Unix stream.
+    Unix(UnixStream),
+}
+
+impl From<TcpStream> for AnyStream {
+    fn from(stream: TcpStream) -> AnyStream {
+        AnyStream::Tcp(stream)
+    }
+}
+
+impl From<UnixStream> for AnyStream {
+    fn from(stream: UnixStream) -> AnyStream {
+        AnyStream::Unix(stream)
+    }
+}
+
+impl Transport for AnyStream {
+    fn local_addr(&self) -> io::Result<Addr> {
+        match self {
+            AnyStream::Tcp(stream) => Transport::local_addr(stream),
+            AnyStream::Unix(stream) => Transport::local_addr(stream),
+        }
+    }
+
+    fn peer_addr(&self) -> io::Result<Addr> {
+        match self {
+            AnyStream::Tcp(stream) => Transport::peer_addr(stream),
+            AnyStream::Unix(stream) => Transport::peer_addr(stream),
+        }
+    }
+}
+
+impl AsyncRead for AnyStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            AnyStream::Tcp(stream) => Pin::new(stream).poll_read(cx, buf),
+            AnyStream::Unix(stream) => Pin::new(stream).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for AnyStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            AnyStream::Tcp(stream) => Pin::new(stream).poll_write(cx, buf),
+            AnyStream::Unix(stream) => Pin::new(stream).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            AnyStream::Tcp(stream) => Pin::new(stream).poll_flush(cx),
+            AnyStream::Unix(stream) => Pin::new(stream).poll_flush(cx),
+        }
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            AnyStream::Tcp(stream) => Pin::new(stream).poll_close(cx),
+            AnyStream::Unix(stream) => Pin::new(stream).poll_close(cx),
+        }
+    }
+}
+
+/// A listener of either transport, accepting [`AnyStream`]s.
+///
+/// [`AnyStream`]: enum.AnyStream.html
+#[derive(Debug)]
+pub enum AnyListener {
+    /// A TCP listener.
+    Tcp(TcpListener),
+    /// A Unix listener.
+    Unix(UnixListener),
+}
+
+impl From<TcpListener> for AnyListener {
+    fn from(listener: TcpListener) -> AnyListener {
+        AnyListener::Tcp(listener)
+    }
+}
+
+impl From<UnixListener> for AnyListener {
+    fn from(listener: UnixListener) -> AnyListener {
+        AnyListener::Unix(listener)
+    }
+}
+
+impl Listener for AnyListener {
+    type Io = AnyStream;
+
+    fn poll_accept(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<(AnyStream, Addr)>> {
+        match self.get_mut() {
+            AnyListener::Tcp(listener) => {
+                let (stream, addr) = ready!(Pin::new(listener).poll_accept(cx))?;
+                Poll::Ready(Ok((stream.into(), addr)))
+            }
+            AnyListener::Unix(listener) => {
+                let (stream, addr) = ready!(Pin::new(listener).poll_accept(cx))?;
+                Poll::Ready(Ok((stream.into(), addr)))
+            }
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<Addr> {
+        match self {
+            AnyListener::Tcp(listener) => Listener::local_addr(listener),
+            AnyListener::Unix(listener) => Listener::local_addr(listener),
+        }
+    }
+}
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_one_server_for_both_transports() {
+    use crate::runtime::{self, Runtime};
+    use futures_util::io::{AsyncReadExt, AsyncWriteExt};
+
+    async fn greet<L: Listener + Unpin>(listener: &mut L) -> Addr {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        stream.write_all(b"hi").await.unwrap();
+        stream.close().await.unwrap();
+        stream.local_addr().unwrap()
+    }
+
+    let dir = tempdir::TempDir::new("transport").unwrap();
+    let path = dir.path().join("sock");
+    let any = "127.0.0.1:0".parse().unwrap();
+    let mut tcp = AnyListener::from(TcpListener::bind(&any).unwrap());
+    let mut unix = AnyListener::from(UnixListener::bind(&path).unwrap());
+    let tcp_addr = match Listener::local_addr(&tcp).unwrap() {
+        Addr::Tcp(addr) => addr,
+        addr => panic!("unexpected address {:?}", addr),
+    };
+
+    let mut rt = runtime::default();
+    rt.exec(async {
+        let mut client = TcpStream::connect(&tcp_addr).await.unwrap();
+        assert!(matches!(greet(&mut tcp).await, Addr::Tcp(addr) if addr == tcp_addr));
+        let mut buf = Vec::new();
+        client.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(buf, b"hi");
+
+        let mut client = UnixStream::connect(&path).await.unwrap();
+        match greet(&mut unix).await {
+            Addr::Unix(addr) => assert_eq!(addr.as_pathname(), Some(path.as_path())),
+            addr => panic!("unexpected address {:?}", addr),
+        }
+        let mut buf = Vec::new();
+        client.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(buf, b"hi");
+    });
+}
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_unix_connections_are_observed() {
+    use crate::runtime::{self, Runtime};
+    use parking_lot::Mutex;
+
+    #[derive(Default)]
+    struct Log(Mutex<Vec<(bool, u64)>>);
+
+    impl ConnectionObserver for Log {
+        fn on_accept(&self, conn: &Connection) {
+            self.0.lock().push((true, conn.id));
+        }
+
+        fn on_close(&self, conn: &Connection) {
+            self.0.lock().push((false, conn.id));
+        }
+    }
+
+    let log = Arc::new(Log::default());
+    let dir = tempdir::TempDir::new("observer").unwrap();
+    let path = dir.path().join("sock");
+    let mut rt = runtime::default();
+    rt.exec(async {
+        let mut listener = UnixListener::bind(&path).unwrap();
+        listener.set_observer(Some(log.clone()));
+        let _client = UnixStream::connect(&path).await.unwrap();
+        let (stream, peer) = listener.accept().await.unwrap();
+        let id = stream.connection_id().unwrap();
+        assert!(matches!(peer, Addr::Unix(_)));
+        assert_eq!(*log.0.lock(), [(true, id)]);
+
+        drop(stream);
+        assert_eq!(*log.0.lock(), [(true, id), (false, id)]);
+    });
+}
@@ -0,0 +1,218 @@
+//! Virtual network interfaces.
+//!
+//! A [`TunDevice`] is an interface whose IP packets are read and written by
+//! the process instead of a network card, and a [`TapDevice`] does the same
+//! with Ethernet frames. This is what VPNs and overlay networks are built
+//! on. Each read returns one packet and each write sends one, and
+//! [`into_packets`] turns a device into a `Stream` and `Sink` of packets.
+//!
+//! Creating an interface requires the `CAP_NET_ADMIN` capability.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use futures::prelude::*;
+//! use futures_net::tun::TunDevice;
+//!
+//! # async fn run() -> std::io::Result<()> {
+//! let tun = TunDevice::new("tun%d")?;
+//! tun.set_address("10.8.0.1".parse().unwrap())?;
+//! tun.set_netmask("255.255.255.0".parse().unwrap())?;
+//! tun.set_up(true)?;
+//!
+//! // Reflect every packet routed to 10.8.0.0/24 back to the kernel.
+//! let (sink, stream) = tun.into_packets().split();
+//! stream.forward(sink).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`TunDevice`]: struct.TunDevice.html
+//! [`TapDevice`]: struct.TapDevice.html
+//! [`into_packets`]: struct.TunDevice.html#method.into_packets
+
+use async_ready::{AsyncReadReady, AsyncWriteReady};
+use futures_io::{AsyncRead, AsyncWrite};
+use std::fmt;
+use std::io;
+use std::net::Ipv4Addr;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use crate::driver::sys;
+use crate::driver::PollEvented;
+
+mod packets;
+
+pub use self::packets::Packets;
+
+/// A TUN interface, carrying IP packets.
+pub struct TunDevice {
+    io: PollEvented<sys::net::TunDevice>,
+}
+
+/// A TAP interface, carrying Ethernet frames.
+pub struct TapDevice {
+    io: PollEvented<sys::net::TunDevice>,
+}
+
+impl TunDevice {
+    /// Creates the TUN interface called `name`, or attaches to it if it
+    /// exists and is persistent.
+    ///
+    /// A name containing `%d`, like `tun%d`, lets the kernel pick the first
+    /// free number, and [`name`] returns the result.
+    ///
+    /// [`name`]: #method.name
+    pub fn new(name: &str) -> io::Result<TunDevice> {
+        let device = sys::net::TunDevice::open(name, false, false)?;
+        Ok(TunDevice {
+            io: PollEvented::new(device),
+        })
+    }
+}
+
+impl TapDevice {
+    /// Creates the TAP interface called `name`, or attaches to it if it
+    /// exists and is persistent.
+    ///
+    /// A name containing `%d`, like `tap%d`, lets the kernel pick the first
+    /// free number, and [`name`] returns the result.
+    ///
+    /// [`name`]: #method.name
+    pub fn new(name: &str) -> io::Result<TapDevice> {
+        let device = sys::net::TunDevice::open(name, true, false)?;
+        Ok(TapDevice {
+            io: PollEvented::new(device),
+        })
+    }
+
+    /// Returns the MAC address of the interface.
+    pub fn hwaddr(&self) -> io::Result<[u8; 6]> {
+        self.io.get_ref().hwaddr()
+    }
+
+    /// Sets the MAC address of the interface.
+    pub fn set_hwaddr(&self, addr: [u8; 6]) -> io::Result<()> {
+        self.io.get_ref().set_hwaddr(addr)
+    }
+}
+
+macro_rules! device {
+    ($name:ident, $unit:expr) => {
+        impl $name {
+            /// Returns the name of the interface.
+            pub fn name(&self) -> &str {
+                self.io.get_ref().name()
+            }
+
+            /// Returns the MTU of the interface.
+            pub fn mtu(&self) -> io::Result<u32> {
+                self.io.get_ref().mtu()
+            }
+
+            /// Sets the MTU of the interface.
+            pub fn set_mtu(&self, mtu: u32) -> io::Result<()> {
+                self.io.get_ref().set_mtu(mtu)
+            }
+
+            /// Assigns the IPv4 address `addr` to the interface.
+            pub fn set_address(&self, addr: Ipv4Addr) -> io::Result<()> {
+                self.io.get_ref().set_address(addr)
+            }
+
+            /// Sets the netmask of the IPv4 address of the interface.
+            pub fn set_netmask(&self, mask: Ipv4Addr) -> io::Result<()> {
+                self.io.get_ref().set_netmask(mask)
+            }
+
+            /// Brings the interface up or down.
+            pub fn set_up(&self, up: bool) -> io::Result<()> {
+                self.io.get_ref().set_up(up)
+            }
+
+            #[doc = "Turns the device into a `Stream` and `Sink` of "]
+            #[doc = $unit]
+            #[doc = "."]
+            pub fn into_packets(self) -> Packets {
+                Packets::new(self.io)
+            }
+        }
+
+        impl AsyncRead for $name {
+            fn poll_read(
+                mut self: Pin<&mut Self>,
+                cx: &mut Context<'_>,
+                buf: &mut [u8],
+            ) -> Poll<io::Result<usize>> {
+                Pin::new(&mut self.io).poll_read(cx, buf)
+            }
+        }
+
+        impl AsyncWrite for $name {
+            fn poll_write(
+                mut self: Pin<&mut Self>,
+                cx: &mut Context<'_>,
+                buf: &[u8],
+            ) -> Poll<io::Result<usize>> {
+                Pin::new(&mut self.io).poll_write(cx, buf)
+            }
+
+            fn poll_flush(
+                mut self: Pin<&mut Self>,
+                cx: &mut Context<'_>,
+            ) -> Poll<io::Result<()>> {
+                Pin::new(&mut self.io).poll_flush(cx)
+            }
+
+            fn poll_close(
+                mut self: Pin<&mut Self>,
+                cx: &mut Context<'_>,
+            ) -> Poll<io::Result<()>> {
+                Pin::new(&mut self.io).poll_close(cx)
+            }
+        }
+
+        impl AsyncReadReady for $name {
+            type Ok = sys::event::Ready;
+            type Err = io::Error;
+
+            /// Poll the device's readiness for reading.
+            fn poll_read_ready(
+                mut self: Pin<&mut Self>,
+                cx: &mut Context<'_>,
+            ) -> Poll<Result<Self::Ok, Self::Err>> {
+                Pin::new(&mut self.io).poll_read_ready(cx)
+            }
+        }
+
+        impl AsyncWriteReady for $name {
+            type Ok = sys::event::Ready;
+            type Err = io::Error;
+
+            /// Poll the device's readiness for writing.
+            fn poll_write_ready(
+                self: Pin<&mut Self>,
+                cx: &mut Context<'_>,
+            ) -> Poll<Result<Self::Ok, Self::Err>> {
+                self.io.poll_write_ready(cx)
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.io.get_ref().fmt(f)
+            }
+        }
+
+        impl AsRawFd for $name {
+            fn as_raw_fd(&self) -> RawFd {
+                self.io.get_ref().as_raw_fd()
+            }
+        }
+    };
+}
+
+device!(TunDevice, "IP packets");
+device!(TapDevice, "Ethernet frames");
@@ -0,0 +1,141 @@
+use futures_core::stream::Stream;
+use futures_util::ready;
+use futures_util::sink::Sink;
+use std::fmt;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use crate::driver::sys;
+use crate::driver::PollEvented;
+use futures_io::{AsyncRead, AsyncWrite};
+
+/// Large enough for any IP packet, and any frame of a TAP device.
+const MAX_PACKET: usize = 65536;
+
+/// A `Stream` and `Sink` of the packets of a TUN or TAP device.
+///
+/// Created with `TunDevice::into_packets` or `TapDevice::into_packets`.
+/// The sink buffers a single packet, which is written on the next call to
+/// `poll_ready` or `poll_flush`.
+pub struct Packets {
+    io: PollEvented<sys::net::TunDevice>,
+    buf: Box<[u8]>,
+    pending: Option<Vec<u8>>,
+}
+
+impl Packets {
+    pub(super) fn new(io: PollEvented<sys::net::TunDevice>) -> Packets {
+        Packets {
+            io,
+            buf: vec![0; MAX_PACKET].into_boxed_slice(),
+            pending: None,
+        }
+    }
+
+    fn poll_write_pending(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if let Some(packet) = &self.pending {
+            let n = ready!(Pin::new(&mut self.io).poll_write(cx, packet))?;
+            // The device takes whole packets or nothing.
+            debug_assert_eq!(n, packet.len());
+            self.pending = None;
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Stream for Packets {
+    type Item = io::Result<Vec<u8>>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        let n = ready!(Pin::new(&mut this.io).poll_read(cx, &mut this.buf))?;
+        Poll::Ready(Some(Ok(this.buf[..n].to_vec())))
+    }
+}
+
+impl Sink<Vec<u8>> for Packets {
+    type Error = io::Error;
+
+    fn poll_ready(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        self.poll_write_pending(cx)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, packet: Vec<u8>) -> io::Result<()> {
+        debug_assert!(self.pending.is_none(), "`start_send` without `poll_ready`");
+        self.pending = Some(packet);
+        Ok(())
+    }
+
+    fn poll_flush(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        self.poll_write_pending(cx)
+    }
+
+    fn poll_close(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        self.poll_write_pending(cx)
+    }
+}
+
+impl fmt::Debug for Packets {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Packets")
+            .field("device", self.io.get_ref())
+            .field("pending", &self.pending.as_ref().map(Vec::len))
+            .finish()
+    }
+}
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_routed_packets_reach_the_stream() {
+    use crate::runtime::{self, Runtime};
+    use futures_util::StreamExt;
+    use std::net::UdpSocket;
+
+    let tun = match super::TunDevice::new("fnet%d") {
+        Ok(tun) => tun,
+        // Needs `CAP_NET_ADMIN` and the tun module.
+        Err(ref e)
+            if e.kind() == io::ErrorKind::PermissionDenied
+                || e.kind() == io::ErrorKind::NotFound =>
+        {
+            return
+        }
+        Err(e) => panic!("{}", e),
+    };
+    tun.set_mtu(1400).unwrap();
+    assert_eq!(tun.mtu().unwrap(), 1400);
+    tun.set_address("10.213.0.1".parse().unwrap()).unwrap();
+    tun.set_netmask("255.255.255.0".parse().unwrap()).unwrap();
+    tun.set_up(true).unwrap();
+
+    let socket = UdpSocket::bind("10.213.0.1:0").unwrap();
+    socket
+        .send_to(b"through the tunnel", "10.213.0.2:9")
+        .unwrap();
+
+    let mut rt = runtime::default();
+    let mut packets = tun.into_packets();
+    rt.exec(async {
+        loop {
+            let packet = packets.next().await.unwrap().unwrap();
+            // Skip IPv6 and anything else the kernel sends on its own.
+            if packet[0] >> 4 == 4 && packet[9] == libc::IPPROTO_UDP as u8 {
+                assert!(packet.ends_with(b"through the tunnel"));
+                break;
+            }
+        }
+    });
+}
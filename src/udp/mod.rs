@@ -15,18 +15,24 @@ use async_datagram::AsyncDatagram;
 use async_ready::{AsyncReadReady, AsyncWriteReady};
 use futures_core::Future;
 use futures_util::ready;
+use futures_util::StreamExt;
 use std::fmt;
-use std::io;
+use std::io::{self, IoSliceMut};
 use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
+use crate::dns::{Resolver, SystemResolver};
 use crate::driver::sys;
-use crate::driver::PollEvented;
+use crate::driver::{Handle, PollEvented};
+use crate::metrics::{SocketMetrics, SocketStats};
+
+pub use crate::driver::sys::net::{EcnCodepoint, RecvMeta, Transmit, BATCH_SIZE};
 
 /// A UDP socket.
 pub struct UdpSocket {
     io: PollEvented<sys::net::UdpSocket>,
+    metrics: SocketMetrics,
 }
 
 impl UdpSocket {
@@ -55,7 +61,47 @@ impl UdpSocket {
 
     fn new(socket: sys::net::UdpSocket) -> UdpSocket {
         let io = PollEvented::new(socket);
-        UdpSocket { io: io }
+        UdpSocket {
+            io,
+            metrics: SocketMetrics::socket(),
+        }
+    }
+
+    /// Creates a `UdpSocket` from a bound `std::net::UdpSocket`, moving it
+    /// into nonblocking mode.
+    ///
+    /// Like [`TcpStream::from_std`], this takes sockets configured
+    /// elsewhere, e.g. with `socket2`.
+    ///
+    /// [`TcpStream::from_std`]: ../tcp/struct.TcpStream.html#method.from_std
+    pub fn from_std(socket: std::net::UdpSocket) -> io::Result<UdpSocket> {
+        sys::net::UdpSocket::from_socket(socket).map(UdpSocket::new)
+    }
+
+    /// Deregisters the socket from its reactor and returns it as a
+    /// `std::net::UdpSocket`, still in nonblocking mode.
+    pub fn into_std(self) -> io::Result<std::net::UdpSocket> {
+        let io = self.io.into_inner()?;
+        Ok(unsafe { std::net::UdpSocket::from_raw_fd(io.into_raw_fd()) })
+    }
+
+    /// Registers the socket with the reactor of `handle`, e.g. the
+    /// [`high_priority`] one, instead of the reactor of the task which
+    /// polls it first.
+    ///
+    /// Fails with `AlreadyExists` if the socket was polled already.
+    ///
+    /// [`high_priority`]: ../driver/fn.high_priority.html
+    pub fn register_with(&self, handle: &Handle) -> io::Result<()> {
+        self.io.register_with(handle)
+    }
+
+    /// Returns the bytes received and sent by the socket and the errors it
+    /// returned, if it was opened while [`metrics`] were enabled.
+    ///
+    /// [`metrics`]: ../metrics/index.html
+    pub fn io_stats(&self) -> SocketStats {
+        self.metrics.stats()
     }
 
     /// Returns the local address that this listener is bound to.
@@ -116,6 +162,121 @@ impl UdpSocket {
         }
     }
 
+    /// Appends `buf` to the datagram pending for `target`, see
+    /// [`send_to_more`].
+    ///
+    /// [`send_to_more`]: #method.send_to_more
+    pub fn poll_send_to_more(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+        target: &SocketAddr,
+    ) -> Poll<io::Result<usize>> {
+        ready!(self.io.poll_write_ready(cx)?);
+
+        match self.io.get_ref().send_to_more(buf, target) {
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                self.io.clear_write_ready(cx)?;
+                Poll::Pending
+            }
+            r => self.metrics.written(Poll::Ready(r)),
+        }
+    }
+
+    /// Appends `buf` to the datagram pending for `target`, with `MSG_MORE`,
+    /// instead of sending it on its own. On success, returns the number of
+    /// bytes appended.
+    ///
+    /// The datagram leaves, with everything appended to it, on the next
+    /// [`send_to`] to the same target. This builds a datagram from several
+    /// pieces without copying them into one buffer first.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures_net::runtime::{self, Runtime};
+    /// use futures_net::udp::UdpSocket;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let addr = "127.0.0.1:0".parse().unwrap();
+    /// let mut a = UdpSocket::bind(&addr)?;
+    /// let mut b = UdpSocket::bind(&addr)?;
+    /// let target = b.local_addr()?;
+    ///
+    /// let mut rt = runtime::default();
+    /// let mut buf = [0; 64];
+    /// let (n, _) = rt.exec(async {
+    ///     a.send_to_more(b"header,", &target).await?;
+    ///     a.send_to(b"body", &target).await?;
+    ///     b.recv_from(&mut buf).await
+    /// })?;
+    /// assert_eq!(&buf[..n], b"header,body");
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`send_to`]: #method.send_to
+    pub async fn send_to_more(
+        &self,
+        buf: &[u8],
+        target: &SocketAddr,
+    ) -> io::Result<usize> {
+        futures_util::future::poll_fn(|cx| self.poll_send_to_more(cx, buf, target)).await
+    }
+
+    /// Sends data on the socket to `host`, a host name or an IP address
+    /// followed by a port. On success, returns the number of bytes written.
+    ///
+    /// The host is resolved with [`lookup_host`], and the data is sent to
+    /// the first of its addresses of the same family as the socket.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::error::Error;
+    /// use futures_net::udp::UdpSocket;
+    ///
+    /// # async fn send_data() -> Result<(), Box<dyn Error + 'static>> {
+    /// let addr = "127.0.0.1:0".parse()?;
+    /// let mut socket = UdpSocket::bind(&addr)?;
+    ///
+    /// socket.send_to_host(b"hits:1|c", "localhost:8125").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`lookup_host`]: ../fn.lookup_host.html
+    pub async fn send_to_host(&mut self, buf: &[u8], host: &str) -> io::Result<usize> {
+        self.send_to_host_with(buf, host, &SystemResolver).await
+    }
+
+    /// Sends data on the socket to `host` like [`send_to_host`], resolving
+    /// it with `resolver`.
+    ///
+    /// [`send_to_host`]: #method.send_to_host
+    pub async fn send_to_host_with<R>(
+        &mut self,
+        buf: &[u8],
+        host: &str,
+        resolver: &R,
+    ) -> io::Result<usize>
+    where
+        R: Resolver + ?Sized,
+    {
+        let ipv4 = self.local_addr()?.is_ipv4();
+        let mut addrs = resolver.lookup_host(host);
+        while let Some(addr) = addrs.next().await {
+            let addr = addr?;
+            if addr.is_ipv4() == ipv4 {
+                return self.send_to(buf, &addr).await;
+            }
+        }
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "host has no address of the family of the socket",
+        ))
+    }
+
     /// Receives data from the socket. On success, returns the number of bytes
     /// read and the address from whence the data came.
     ///
@@ -234,6 +395,27 @@ impl UdpSocket {
         self.io.get_ref().set_ttl(ttl)
     }
 
+    /// Gets the value of the `SO_BUSY_POLL` option on this socket.
+    pub fn busy_poll(&self) -> io::Result<u32> {
+        self.io.get_ref().busy_poll()
+    }
+
+    /// Sets the value of the `SO_BUSY_POLL` option on this socket, in
+    /// microseconds.
+    pub fn set_busy_poll(&self, micros: u32) -> io::Result<()> {
+        self.io.get_ref().set_busy_poll(micros)
+    }
+
+    /// Gets the value of the `SO_PREFER_BUSY_POLL` option on this socket.
+    pub fn prefer_busy_poll(&self) -> io::Result<bool> {
+        self.io.get_ref().prefer_busy_poll()
+    }
+
+    /// Sets the value of the `SO_PREFER_BUSY_POLL` option on this socket.
+    pub fn set_prefer_busy_poll(&self, prefer: bool) -> io::Result<()> {
+        self.io.get_ref().set_prefer_busy_poll(prefer)
+    }
+
     /// Executes an operation of the `IP_ADD_MEMBERSHIP` type.
     ///
     /// This function specifies a new multicast group for this socket to join.
@@ -317,6 +499,134 @@ impl UdpSocket {
     ) -> io::Result<()> {
         self.io.get_ref().leave_multicast_v6(multiaddr, interface)
     }
+
+    /// Turns on the reporting of the metadata returned by [`recv_msgs`]: the
+    /// ECN codepoint and the destination address of each datagram, and GRO
+    /// coalescing where the kernel supports it.
+    ///
+    /// [`recv_msgs`]: #method.recv_msgs
+    pub fn enable_recv_meta(&self) -> io::Result<()> {
+        self.io.get_ref().enable_recv_meta()
+    }
+
+    /// Returns the most datagrams a single [`send_msg`] can carry with
+    /// [`Transmit::segment_size`], 1 if the kernel doesn't support GSO.
+    ///
+    /// [`send_msg`]: #method.send_msg
+    /// [`Transmit::segment_size`]: struct.Transmit.html#structfield.segment_size
+    pub fn max_gso_segments(&self) -> usize {
+        self.io.get_ref().max_gso_segments()
+    }
+
+    /// Sends the datagrams of `transmit` with their metadata, see
+    /// [`send_msg`].
+    ///
+    /// [`send_msg`]: #method.send_msg
+    pub fn poll_send_msg(
+        &self,
+        cx: &mut Context<'_>,
+        transmit: &Transmit<'_>,
+    ) -> Poll<io::Result<usize>> {
+        ready!(self.io.poll_write_ready(cx)?);
+
+        match self.io.get_ref().send_msg(transmit) {
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                self.io.clear_write_ready(cx)?;
+                Poll::Pending
+            }
+            r => self.metrics.written(Poll::Ready(r)),
+        }
+    }
+
+    /// Sends the datagrams of `transmit`, marked with its ECN codepoint and
+    /// sent from its source address. With a segment size, the kernel splits
+    /// the contents into several datagrams (GSO). On success, returns the
+    /// number of bytes written.
+    ///
+    /// Unlike the other methods this one only needs a shared reference, so
+    /// a socket can send and receive from several tasks.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures_net::runtime::{self, Runtime};
+    /// use futures_net::udp::{EcnCodepoint, RecvMeta, Transmit, UdpSocket};
+    /// use std::io::IoSliceMut;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let addr = "127.0.0.1:0".parse().unwrap();
+    /// let a = UdpSocket::bind(&addr)?;
+    /// let b = UdpSocket::bind(&addr)?;
+    /// b.enable_recv_meta()?;
+    ///
+    /// let mut rt = runtime::default();
+    /// let mut buf = [0; 1500];
+    /// let mut meta = [RecvMeta::default()];
+    /// rt.exec(async {
+    ///     let transmit = Transmit {
+    ///         destination: b.local_addr()?,
+    ///         ecn: Some(EcnCodepoint::Ect0),
+    ///         contents: b"initial",
+    ///         segment_size: None,
+    ///         src_ip: None,
+    ///     };
+    ///     a.send_msg(&transmit).await?;
+    ///     b.recv_msgs(&mut [IoSliceMut::new(&mut buf)], &mut meta).await
+    /// })?;
+    /// assert_eq!(&buf[..meta[0].len], b"initial");
+    /// assert_eq!(meta[0].ecn, Some(EcnCodepoint::Ect0));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn send_msg(&self, transmit: &Transmit<'_>) -> io::Result<usize> {
+        futures_util::future::poll_fn(|cx| self.poll_send_msg(cx, transmit)).await
+    }
+
+    /// Receives a batch of datagrams with their metadata, see
+    /// [`recv_msgs`].
+    ///
+    /// [`recv_msgs`]: #method.recv_msgs
+    pub fn poll_recv_msgs(
+        &self,
+        cx: &mut Context<'_>,
+        bufs: &mut [IoSliceMut<'_>],
+        meta: &mut [RecvMeta],
+    ) -> Poll<io::Result<usize>> {
+        ready!(self.io.poll_read_ready(cx)?);
+
+        match self.io.get_ref().recv_msgs(bufs, meta) {
+            Ok(n) => {
+                let bytes = meta[..n].iter().map(|meta| meta.len).sum::<usize>();
+                let _ = self.metrics.read(Poll::Ready(Ok(bytes)));
+                Poll::Ready(Ok(n))
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                self.io.clear_read_ready(cx)?;
+                Poll::Pending
+            }
+            r => self.metrics.read(Poll::Ready(r)),
+        }
+    }
+
+    /// Receives up to [`BATCH_SIZE`] datagrams at once, one per buffer, and
+    /// fills `meta` with their metadata. On success, returns the number of
+    /// datagrams received.
+    ///
+    /// The ECN codepoint and the destination address are only reported
+    /// after [`enable_recv_meta`]. A buffer can then hold several datagrams
+    /// coalesced by the kernel (GRO), each [`RecvMeta::stride`] bytes long
+    /// but the last.
+    ///
+    /// [`BATCH_SIZE`]: constant.BATCH_SIZE.html
+    /// [`enable_recv_meta`]: #method.enable_recv_meta
+    /// [`RecvMeta::stride`]: struct.RecvMeta.html#structfield.stride
+    pub async fn recv_msgs(
+        &self,
+        bufs: &mut [IoSliceMut<'_>],
+        meta: &mut [RecvMeta],
+    ) -> io::Result<usize> {
+        futures_util::future::poll_fn(|cx| self.poll_recv_msgs(cx, bufs, meta)).await
+    }
 }
 
 impl AsyncDatagram for UdpSocket {
@@ -333,12 +643,11 @@ impl AsyncDatagram for UdpSocket {
         ready!(self.io.poll_write_ready(cx)?);
 
         match self.io.get_ref().send_to(buf, receiver) {
-            Ok(n) => Poll::Ready(Ok(n)),
             Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
                 Pin::new(&mut self.io).clear_write_ready(cx)?;
                 Poll::Pending
             }
-            Err(e) => Poll::Ready(Err(e)),
+            r => self.metrics.written(Poll::Ready(r)),
         }
     }
 
@@ -350,12 +659,11 @@ impl AsyncDatagram for UdpSocket {
         ready!(Pin::new(&mut self.io).poll_read_ready(cx)?);
 
         match self.io.get_ref().recv_from(buf) {
-            Ok(n) => Poll::Ready(Ok(n)),
             Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
                 Pin::new(&mut self.io).clear_read_ready(cx)?;
                 Poll::Pending
             }
-            Err(e) => Poll::Ready(Err(e)),
+            r => self.metrics.read(Poll::Ready(r)),
         }
     }
 }
@@ -401,6 +709,14 @@ impl AsyncWriteReady for UdpSocket {
     }
 }
 
+impl std::convert::TryFrom<std::net::UdpSocket> for UdpSocket {
+    type Error = io::Error;
+
+    fn try_from(socket: std::net::UdpSocket) -> Result<Self, Self::Error> {
+        UdpSocket::from_std(socket)
+    }
+}
+
 impl fmt::Debug for UdpSocket {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         self.io.get_ref().fmt(f)
@@ -451,3 +767,49 @@ impl AsRawFd for UdpSocket {
         self.io.get_ref().as_raw_fd()
     }
 }
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_gso_trains_come_back_whole_or_split() {
+    use crate::runtime::{self, Runtime};
+    use std::net::IpAddr;
+
+    let addr = "127.0.0.1:0".parse().unwrap();
+    let a = UdpSocket::bind(&addr).unwrap();
+    let b = UdpSocket::bind(&addr).unwrap();
+    b.enable_recv_meta().unwrap();
+    let segments = a.max_gso_segments().min(3);
+
+    let contents = vec![7; 100 * segments];
+    let transmit = Transmit {
+        destination: b.local_addr().unwrap(),
+        ecn: Some(EcnCodepoint::Ce),
+        contents: &contents,
+        segment_size: if segments > 1 { Some(100) } else { None },
+        src_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+    };
+
+    let mut rt = runtime::default();
+    rt.exec(async {
+        assert_eq!(a.send_msg(&transmit).await.unwrap(), contents.len());
+
+        // Whether the kernel coalesces the train again depends on its
+        // version, either way every datagram is 100 bytes long.
+        let mut bufs = vec![[0u8; 1500]; 3];
+        let mut received = 0;
+        while received < contents.len() {
+            let mut slices: Vec<_> =
+                bufs.iter_mut().map(|b| IoSliceMut::new(b)).collect();
+            let mut meta = [RecvMeta::default(); 3];
+            let n = b.recv_msgs(&mut slices, &mut meta).await.unwrap();
+            for meta in &meta[..n] {
+                assert_eq!(meta.addr, a.local_addr().unwrap());
+                assert_eq!(meta.ecn, Some(EcnCodepoint::Ce));
+                assert_eq!(meta.dst_ip, Some(IpAddr::V4(Ipv4Addr::LOCALHOST)));
+                assert_eq!(meta.stride, 100);
+                received += meta.len;
+            }
+        }
+        assert_eq!(received, contents.len());
+    });
+}
@@ -7,8 +7,8 @@ use std::fmt;
 use std::future::Future;
 use std::io;
 use std::net::Shutdown;
-use std::os::unix::io::{AsRawFd, RawFd};
-use std::os::unix::net::SocketAddr;
+use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, OwnedFd, RawFd};
+use std::os::unix::net::{self, SocketAddr};
 use std::path::{Path, PathBuf};
 use std::pin::Pin;
 use std::task::{Context, Poll};
@@ -98,6 +98,19 @@ impl UnixDatagram {
         Ok((a, b))
     }
 
+    /// Creates a `UnixDatagram` from a `std::os::unix::net::UnixDatagram`,
+    /// moving it into nonblocking mode.
+    pub fn from_std(socket: net::UnixDatagram) -> io::Result<UnixDatagram> {
+        sys::net::UnixDatagram::from_datagram(socket).map(UnixDatagram::new)
+    }
+
+    /// Deregisters the socket from its reactor and returns it as a
+    /// `std::os::unix::net::UnixDatagram`, still in nonblocking mode.
+    pub fn into_std(self) -> io::Result<net::UnixDatagram> {
+        let io = self.io.into_inner()?;
+        Ok(unsafe { net::UnixDatagram::from_raw_fd(io.into_raw_fd()) })
+    }
+
     fn new(socket: sys::net::UnixDatagram) -> UnixDatagram {
         let io = PollEvented::new(socket);
         UnixDatagram { io }
@@ -119,6 +132,27 @@ impl UnixDatagram {
         Ok(UnixDatagram::new(socket))
     }
 
+    /// Connects the socket to the socket bound at `path`, which becomes the
+    /// destination of [`send`] and the only source of [`recv`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use futures_net::uds::UnixDatagram;
+    ///
+    /// # async fn run() -> std::io::Result<()> {
+    /// let mut sock = UnixDatagram::unbound()?;
+    /// sock.connect("/dev/log")?;
+    /// sock.send(b"<14>hello").await?;
+    /// # Ok(()) }
+    /// ```
+    ///
+    /// [`send`]: #method.send
+    /// [`recv`]: #method.recv
+    pub fn connect(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.io.get_ref().connect(path)
+    }
+
     /// Returns the local address that this socket is bound to.
     /// # Examples
     ///
@@ -231,6 +265,172 @@ impl UnixDatagram {
     pub fn recv_from<'a, 'b>(&'a mut self, buf: &'b mut [u8]) -> RecvFrom<'a, 'b> {
         RecvFrom { buf, socket: self }
     }
+
+    /// Sends data to the socket's peer, see [`send`].
+    ///
+    /// [`send`]: #method.send
+    pub fn poll_send(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        ready!(self.io.poll_write_ready(cx)?);
+
+        let r = self.io.get_ref().send(buf);
+
+        if is_wouldblock(&r) {
+            Pin::new(&mut self.io).clear_write_ready(cx)?;
+            Poll::Pending
+        } else {
+            Poll::Ready(r)
+        }
+    }
+
+    /// Sends data to the socket's peer, set with [`connect`]. On success,
+    /// returns the number of bytes written.
+    ///
+    /// [`connect`]: #method.connect
+    pub async fn send(&mut self, buf: &[u8]) -> io::Result<usize> {
+        futures_util::future::poll_fn(|cx| self.poll_send(cx, buf)).await
+    }
+
+    /// Receives data from the socket's peer, see [`recv`].
+    ///
+    /// [`recv`]: #method.recv
+    pub fn poll_recv(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        ready!(Pin::new(&mut self.io).poll_read_ready(cx)?);
+
+        let r = self.io.get_ref().recv(buf);
+
+        if is_wouldblock(&r) {
+            Pin::new(&mut self.io).clear_read_ready(cx)?;
+            Poll::Pending
+        } else {
+            Poll::Ready(r)
+        }
+    }
+
+    /// Receives data from the socket's peer, set with [`connect`]. On
+    /// success, returns the number of bytes read.
+    ///
+    /// [`connect`]: #method.connect
+    pub async fn recv(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        futures_util::future::poll_fn(|cx| self.poll_recv(cx, buf)).await
+    }
+
+    /// Sends data along with file descriptors to the socket's peer, see
+    /// [`send_with_fds`].
+    ///
+    /// [`send_with_fds`]: #method.send_with_fds
+    pub fn poll_send_with_fds(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+        fds: &[RawFd],
+    ) -> Poll<io::Result<usize>> {
+        ready!(self.io.poll_write_ready(cx)?);
+
+        let r = self.io.get_ref().send_with_fds(buf, fds);
+
+        if is_wouldblock(&r) {
+            Pin::new(&mut self.io).clear_write_ready(cx)?;
+            Poll::Pending
+        } else {
+            Poll::Ready(r)
+        }
+    }
+
+    /// Sends data to the socket's peer, set with [`connect`], along with
+    /// copies of the file descriptors `fds`. On success, returns the number
+    /// of bytes written.
+    ///
+    /// At most 253 descriptors can be sent with a message.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures_net::runtime::{self, Runtime};
+    /// use futures_net::uds::UnixDatagram;
+    /// use std::fs::File;
+    /// use std::os::unix::io::AsRawFd;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut rt = runtime::default();
+    /// let (mut a, mut b) = UnixDatagram::pair()?;
+    /// let file = File::open("Cargo.toml")?;
+    ///
+    /// let (mut buf, mut fds) = ([0; 16], Vec::new());
+    /// let n = rt.exec(async {
+    ///     a.send_with_fds(b"config", &[file.as_raw_fd()]).await?;
+    ///     b.recv_with_fds(&mut buf, &mut fds).await
+    /// })?;
+    /// assert_eq!(&buf[..n], b"config");
+    /// assert_eq!(fds.len(), 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`connect`]: #method.connect
+    pub async fn send_with_fds(
+        &mut self,
+        buf: &[u8],
+        fds: &[RawFd],
+    ) -> io::Result<usize> {
+        futures_util::future::poll_fn(|cx| self.poll_send_with_fds(cx, buf, fds)).await
+    }
+
+    /// Receives data and file descriptors from the socket's peer, see
+    /// [`recv_with_fds`].
+    ///
+    /// [`recv_with_fds`]: #method.recv_with_fds
+    pub fn poll_recv_with_fds(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+        fds: &mut Vec<OwnedFd>,
+    ) -> Poll<io::Result<usize>> {
+        self.poll_recv_msg(cx, buf, fds).map_ok(|(n, _)| n)
+    }
+
+    /// Receives data from the socket's peer, set with [`connect`], pushing
+    /// the file descriptors sent along with it to `fds`. On success,
+    /// returns the number of bytes read.
+    ///
+    /// Like with [`recv`], the part of a message which does not fit in
+    /// `buf` is discarded. The descriptors are received with `CLOEXEC` set.
+    ///
+    /// [`connect`]: #method.connect
+    /// [`recv`]: #method.recv
+    pub async fn recv_with_fds(
+        &mut self,
+        buf: &mut [u8],
+        fds: &mut Vec<OwnedFd>,
+    ) -> io::Result<usize> {
+        futures_util::future::poll_fn(|cx| self.poll_recv_with_fds(cx, buf, fds)).await
+    }
+
+    /// Receives a message, returning its length and its `MSG_*` flags.
+    pub(super) fn poll_recv_msg(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+        fds: &mut Vec<OwnedFd>,
+    ) -> Poll<io::Result<(usize, libc::c_int)>> {
+        ready!(Pin::new(&mut self.io).poll_read_ready(cx)?);
+
+        let r = self.io.get_ref().recv_with_fds(buf, fds);
+
+        if is_wouldblock(&r) {
+            Pin::new(&mut self.io).clear_read_ready(cx)?;
+            Poll::Pending
+        } else {
+            Poll::Ready(r)
+        }
+    }
 }
 
 impl AsyncDatagram for UnixDatagram {
@@ -324,6 +524,14 @@ impl TakeError for UnixDatagram {
     }
 }
 
+impl std::convert::TryFrom<net::UnixDatagram> for UnixDatagram {
+    type Error = io::Error;
+
+    fn try_from(socket: net::UnixDatagram) -> Result<Self, Self::Error> {
+        UnixDatagram::from_std(socket)
+    }
+}
+
 impl fmt::Debug for UnixDatagram {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         self.io.get_ref().fmt(f)
@@ -0,0 +1,275 @@
+use bytes::BytesMut;
+use futures_core::stream::Stream;
+use futures_util::ready;
+use futures_util::sink::Sink;
+use std::fmt;
+use std::io;
+use std::os::unix::io::{AsRawFd, OwnedFd, RawFd};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use super::UnixDatagram;
+use crate::codec::{Decoder, Encoder};
+
+const INITIAL_RD_CAPACITY: usize = 64 * 1024;
+
+/// A `Stream` and `Sink` of messages over a connected [`UnixDatagram`].
+///
+/// Each datagram carries exactly one item: every datagram received is
+/// decoded with [`Decoder::decode_eof`] on its own, and every item sent is
+/// encoded into a datagram of its own, so message boundaries are kept
+/// whatever the codec. Along with each item go the file descriptors sent
+/// with its datagram; send an empty `Vec` for none.
+///
+/// Datagrams larger than the read buffer, 64 KiB unless created with
+/// [`with_capacity`], fail with an `InvalidData` error instead of being
+/// truncated. Datagram sockets have no end of stream, so the stream only
+/// ends when dropped.
+///
+/// # Examples
+///
+/// ```
+/// use futures::prelude::*;
+/// use futures_net::codec::{Bytes, BytesCodec};
+/// use futures_net::runtime::{self, Runtime};
+/// use futures_net::uds::{UnixDatagram, UnixDatagramFramed};
+///
+/// # fn main() -> std::io::Result<()> {
+/// let mut rt = runtime::default();
+/// let (a, b) = UnixDatagram::pair()?;
+/// let mut a = UnixDatagramFramed::new(a, BytesCodec::new());
+/// let mut b = UnixDatagramFramed::new(b, BytesCodec::new());
+///
+/// rt.exec(async {
+///     a.send((Bytes::from("hello"), Vec::new())).await?;
+///     a.send((Bytes::from("world"), Vec::new())).await?;
+///
+///     let (msg, _fds) = b.next().await.unwrap()?;
+///     assert_eq!(msg, "hello");
+///     let (msg, _fds) = b.next().await.unwrap()?;
+///     assert_eq!(msg, "world");
+///     Ok(())
+/// })
+/// # }
+/// ```
+///
+/// [`UnixDatagram`]: struct.UnixDatagram.html
+/// [`Decoder::decode_eof`]: ../codec/trait.Decoder.html#method.decode_eof
+/// [`with_capacity`]: #method.with_capacity
+pub struct UnixDatagramFramed<C> {
+    socket: UnixDatagram,
+    codec: C,
+    rd: BytesMut,
+    capacity: usize,
+    wr: BytesMut,
+    wr_fds: Vec<OwnedFd>,
+    /// Set while `wr` holds a datagram which wasn't sent yet; it may be
+    /// empty.
+    pending: bool,
+}
+
+impl<C> UnixDatagramFramed<C> {
+    /// Creates an adapter sending and receiving messages on `socket` with
+    /// `codec`.
+    pub fn new(socket: UnixDatagram, codec: C) -> UnixDatagramFramed<C> {
+        UnixDatagramFramed::with_capacity(socket, codec, INITIAL_RD_CAPACITY)
+    }
+
+    /// Creates an adapter receiving datagrams of up to `capacity` bytes.
+    pub fn with_capacity(
+        socket: UnixDatagram,
+        codec: C,
+        capacity: usize,
+    ) -> UnixDatagramFramed<C> {
+        UnixDatagramFramed {
+            socket,
+            codec,
+            rd: BytesMut::with_capacity(capacity),
+            capacity,
+            wr: BytesMut::new(),
+            wr_fds: Vec::new(),
+            pending: false,
+        }
+    }
+
+    /// Returns a reference to the socket.
+    pub fn get_ref(&self) -> &UnixDatagram {
+        &self.socket
+    }
+
+    /// Returns a mutable reference to the socket.
+    pub fn get_mut(&mut self) -> &mut UnixDatagram {
+        &mut self.socket
+    }
+
+    /// Consumes the adapter, returning the socket.
+    ///
+    /// An item which was not flushed yet is lost.
+    pub fn into_inner(self) -> UnixDatagram {
+        self.socket
+    }
+
+    /// Returns a reference to the codec.
+    pub fn codec(&self) -> &C {
+        &self.codec
+    }
+
+    /// Returns a mutable reference to the codec.
+    pub fn codec_mut(&mut self) -> &mut C {
+        &mut self.codec
+    }
+}
+
+// The fields are never pinned.
+impl<C> Unpin for UnixDatagramFramed<C> {}
+
+impl<C: Decoder> Stream for UnixDatagramFramed<C> {
+    type Item = Result<(C::Item, Vec<OwnedFd>), C::Error>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            this.rd.clear();
+            this.rd.resize(this.capacity, 0);
+            let mut fds = Vec::new();
+            let r = ready!(this.socket.poll_recv_msg(cx, &mut this.rd, &mut fds));
+            let n = match r {
+                Ok((_, flags)) if flags & libc::MSG_TRUNC != 0 => {
+                    return Poll::Ready(Some(Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "datagram larger than the read buffer",
+                    )
+                    .into())));
+                }
+                Ok((n, _)) => n,
+                Err(e) => return Poll::Ready(Some(Err(e.into()))),
+            };
+            this.rd.truncate(n);
+
+            // A datagram the codec makes nothing of, e.g. an empty one, is
+            // skipped.
+            match this.codec.decode_eof(&mut this.rd) {
+                Ok(Some(item)) => return Poll::Ready(Some(Ok((item, fds)))),
+                Ok(None) => continue,
+                Err(e) => return Poll::Ready(Some(Err(e))),
+            }
+        }
+    }
+}
+
+impl<I, C: Encoder<I>> Sink<(I, Vec<OwnedFd>)> for UnixDatagramFramed<C> {
+    type Error = C::Error;
+
+    fn poll_ready(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), C::Error>> {
+        if self.pending {
+            self.poll_flush(cx)
+        } else {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    fn start_send(
+        self: Pin<&mut Self>,
+        (item, fds): (I, Vec<OwnedFd>),
+    ) -> Result<(), C::Error> {
+        let this = self.get_mut();
+        this.wr.clear();
+        this.codec.encode(item, &mut this.wr)?;
+        this.wr_fds = fds;
+        this.pending = true;
+        Ok(())
+    }
+
+    fn poll_flush(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), C::Error>> {
+        let this = self.get_mut();
+        if !this.pending {
+            return Poll::Ready(Ok(()));
+        }
+
+        let fds: Vec<RawFd> = this.wr_fds.iter().map(AsRawFd::as_raw_fd).collect();
+        let r = ready!(this.socket.poll_send_with_fds(cx, &this.wr, &fds));
+        // The peer got its own copies of the descriptors, if sent at all.
+        this.pending = false;
+        this.wr_fds.clear();
+        match r {
+            Ok(n) if n == this.wr.len() => Poll::Ready(Ok(())),
+            Ok(_) => Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::WriteZero,
+                "failed to write entire datagram",
+            )
+            .into())),
+            Err(e) => Poll::Ready(Err(e.into())),
+        }
+    }
+
+    fn poll_close(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), C::Error>> {
+        self.poll_flush(cx)
+    }
+}
+
+impl<C: fmt::Debug> fmt::Debug for UnixDatagramFramed<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UnixDatagramFramed")
+            .field("socket", &self.socket)
+            .field("codec", &self.codec)
+            .finish()
+    }
+}
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_messages_keep_their_boundaries_and_fds() {
+    use crate::codec::{Bytes, LengthDelimitedCodec};
+    use crate::runtime::{self, Runtime};
+    use futures_util::{SinkExt, StreamExt};
+    use std::io::{Read, Write};
+    use std::os::unix::net::UnixStream;
+
+    let (a, b) = UnixDatagram::pair().unwrap();
+    let mut a = UnixDatagramFramed::new(a, LengthDelimitedCodec::new());
+    let mut b = UnixDatagramFramed::with_capacity(b, LengthDelimitedCodec::new(), 16);
+    let (ours, theirs) = UnixStream::pair().unwrap();
+
+    let mut rt = runtime::default();
+    rt.exec(async {
+        // Both messages are queued in the socket before the first is read,
+        // and still come out one at a time.
+        a.feed((Bytes::from("one"), vec![OwnedFd::from(theirs)]))
+            .await
+            .unwrap();
+        a.send((Bytes::from("two"), Vec::new())).await.unwrap();
+        a.send((Bytes::from("far too long for the buffer"), Vec::new()))
+            .await
+            .unwrap();
+
+        let (msg, mut fds) = b.next().await.unwrap().unwrap();
+        assert_eq!(msg, "one");
+        assert_eq!(fds.len(), 1);
+        let mut theirs = UnixStream::from(fds.pop().unwrap());
+        theirs.write_all(b"passed").unwrap();
+
+        let (msg, fds) = b.next().await.unwrap().unwrap();
+        assert_eq!(msg, "two");
+        assert!(fds.is_empty());
+
+        let err = b.next().await.unwrap().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    });
+
+    let mut buf = [0; 6];
+    (&ours).read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"passed");
+}
@@ -3,26 +3,60 @@ use futures_core::Stream;
 use futures_util::ready;
 use std::fmt;
 use std::io;
-use std::os::unix::io::{AsRawFd, RawFd};
+use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd};
 use std::os::unix::net::{self, SocketAddr};
 use std::path::Path;
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::{Context, Poll};
 
 use super::UnixStream;
+use crate::driver::fd_reserve::FdReserve;
 use crate::driver::sys;
 use crate::driver::PollEvented;
+use crate::error::{self, Operation};
+use crate::metrics::SocketMetrics;
+use crate::transport::{Addr, CloseHook, ConnectionObserver};
 
 /// A Unix socket cna accept connections from other Unix sockets.
+///
+/// See [`TcpListener`] for how to deal with running out of file descriptors.
+///
+/// [`TcpListener`]: ../tcp/struct.TcpListener.html
 pub struct UnixListener {
     io: PollEvented<sys::net::UnixListener>,
+    reserve: Option<FdReserve>,
+    metrics: SocketMetrics,
+    observer: Option<Arc<dyn ConnectionObserver>>,
 }
 
 impl UnixListener {
     pub fn bind(path: impl AsRef<Path>) -> io::Result<UnixListener> {
         let listener = sys::net::UnixListener::bind(path)?;
-        let io = PollEvented::new(listener);
-        Ok(UnixListener { io })
+        Ok(UnixListener::new(listener))
+    }
+
+    /// Creates a `UnixListener` from a listening
+    /// `std::os::unix::net::UnixListener`, moving it into nonblocking mode.
+    pub fn from_std(listener: net::UnixListener) -> io::Result<UnixListener> {
+        let listener = sys::net::UnixListener::from_listener(listener)?;
+        Ok(UnixListener::new(listener))
+    }
+
+    fn new(listener: sys::net::UnixListener) -> UnixListener {
+        UnixListener {
+            io: PollEvented::new(listener),
+            reserve: None,
+            metrics: SocketMetrics::socket(),
+            observer: None,
+        }
+    }
+
+    /// Deregisters the listener from its reactor and returns it as a
+    /// `std::os::unix::net::UnixListener`, still in nonblocking mode.
+    pub fn into_std(self) -> io::Result<net::UnixListener> {
+        let io = self.io.into_inner()?;
+        Ok(unsafe { net::UnixListener::from_raw_fd(io.into_raw_fd()) })
     }
 
     pub fn local_addr(&self) -> io::Result<SocketAddr> {
@@ -33,6 +67,35 @@ impl UnixListener {
         Incoming::new(self)
     }
 
+    /// Keeps a spare file descriptor around to recover from descriptor
+    /// exhaustion, see [`TcpListener::set_fd_reserve`].
+    ///
+    /// [`TcpListener::set_fd_reserve`]: ../tcp/struct.TcpListener.html#method.set_fd_reserve
+    pub fn set_fd_reserve(&mut self, reserve: bool) -> io::Result<()> {
+        match (reserve, self.reserve.is_some()) {
+            (true, false) => self.reserve = Some(FdReserve::new()?),
+            (false, true) => self.reserve = None,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Returns whether a spare file descriptor is kept for this listener.
+    pub fn fd_reserve(&self) -> bool {
+        self.reserve.is_some()
+    }
+
+    /// Reports the connections the listener yields, and the closing of
+    /// their streams, to `observer`, or stops reporting them.
+    ///
+    /// See [`ConnectionObserver`]. Only connections accepted while an
+    /// observer is set are reported.
+    ///
+    /// [`ConnectionObserver`]: ../transport/trait.ConnectionObserver.html
+    pub fn set_observer(&mut self, observer: Option<Arc<dyn ConnectionObserver>>) {
+        self.observer = observer;
+    }
+
     fn poll_accept_std(
         mut self: Pin<&mut Self>,
         cx: &mut Context<'_>,
@@ -49,7 +112,16 @@ impl UnixListener {
                 Pin::new(&mut self.io).clear_read_ready(cx)?;
                 Poll::Pending
             }
-            Err(err) => Poll::Ready(Err(err)),
+            Err(err) => {
+                if crate::error::is_fd_exhaustion(&err) {
+                    let this = &mut *self;
+                    if let Some(reserve) = this.reserve.as_mut() {
+                        let io = this.io.get_ref();
+                        reserve.shed(|| io.accept_std());
+                    }
+                }
+                Poll::Ready(Err(err))
+            }
         }
     }
 }
@@ -63,9 +135,18 @@ impl AsyncReady for UnixListener {
         self: Pin<&mut Self>,
         cx: &mut Context<'_>,
     ) -> Poll<Result<Self::Ok, Self::Err>> {
-        let (io, addr) = ready!(self.poll_accept_std(cx)?);
+        let this = self.get_mut();
+        let accepted = Pin::new(&mut *this).poll_accept_std(cx);
+        let accepted = this.metrics.accepted(accepted);
+        let (io, addr) = ready!(error::annotate(accepted, Operation::Accept, || {
+            (this.local_addr().ok().map(Addr::Unix), None)
+        }))?;
         let io = sys::net::UnixStream::from_stream(io)?;
-        Poll::Ready(Ok((UnixStream::new(io), addr)))
+        let mut io = UnixStream::new(io);
+        if let Some(observer) = &this.observer {
+            io.set_close_hook(CloseHook::accepted(observer, Addr::Unix(addr.clone())));
+        }
+        Poll::Ready(Ok((io, addr)))
     }
 }
 
@@ -78,6 +159,14 @@ impl TakeError for UnixListener {
     }
 }
 
+impl std::convert::TryFrom<net::UnixListener> for UnixListener {
+    type Error = io::Error;
+
+    fn try_from(listener: net::UnixListener) -> Result<Self, Self::Error> {
+        UnixListener::from_std(listener)
+    }
+}
+
 impl fmt::Debug for UnixListener {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         self.io.get_ref().fmt(f)
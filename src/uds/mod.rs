@@ -23,11 +23,14 @@
 //! ```
 
 mod datagram;
+mod framed;
 mod listener;
 mod stream;
+pub mod syslog;
 mod ucred;
 
 pub use self::datagram::UnixDatagram;
+pub use self::framed::UnixDatagramFramed;
 pub use self::listener::{Incoming, UnixListener};
 pub use self::stream::{ConnectFuture, UnixStream};
 pub use self::ucred::UCred;
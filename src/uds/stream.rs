@@ -5,17 +5,21 @@ use futures_core::Future;
 use futures_io::{AsyncRead, AsyncWrite};
 use futures_util::ready;
 use std::fmt;
-use std::io;
+use std::io::{self, IoSlice, IoSliceMut, Read, Write};
 use std::net::Shutdown;
-use std::os::unix::io::{AsRawFd, RawFd};
-use std::os::unix::net::SocketAddr;
+use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd};
+use std::os::unix::net::{self, SocketAddr};
 use std::path::Path;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
 use super::ucred::{self, UCred};
 use crate::driver::sys;
-use crate::driver::PollEvented;
+use crate::driver::{Handle, PollEvented};
+use crate::error::{self, Operation};
+use crate::io::{pending_on_wouldblock, wouldblock_on_pending};
+use crate::metrics::{SocketMetrics, SocketStats};
+use crate::transport::{Addr, CloseHook};
 
 /// A structure representing a connected Unix socket.
 ///
@@ -24,6 +28,9 @@ use crate::driver::PollEvented;
 /// anonymous Unix sockets can be created with `UnixStream::pair`.
 pub struct UnixStream {
     io: PollEvented<sys::net::UnixStream>,
+    metrics: SocketMetrics,
+    /// Set for streams accepted by a listener with an observer.
+    close_hook: Option<CloseHook>,
 }
 
 /// Future returned by `UnixStream::connect` which will resolve to a
@@ -59,11 +66,15 @@ impl UnixStream {
     /// # Ok(()) }
     /// ```
     pub fn connect(path: impl AsRef<Path>) -> ConnectFuture {
+        let path = path.as_ref();
         let res = sys::net::UnixStream::connect(path).map(UnixStream::new);
 
         let inner = match res {
             Ok(stream) => State::Waiting(stream),
-            Err(e) => State::Error(e),
+            Err(e) => State::Error(error::annotate_err(e, Operation::Connect, || {
+                let peer = SocketAddr::from_pathname(path).ok();
+                (None, peer.map(Addr::Unix))
+            })),
         };
 
         ConnectFuture { inner }
@@ -94,7 +105,56 @@ impl UnixStream {
 
     pub(crate) fn new(stream: sys::net::UnixStream) -> UnixStream {
         let io = PollEvented::new(stream);
-        UnixStream { io }
+        UnixStream {
+            io,
+            metrics: SocketMetrics::stream(),
+            close_hook: None,
+        }
+    }
+
+    /// Creates a `UnixStream` from a connected
+    /// `std::os::unix::net::UnixStream`, moving it into nonblocking mode.
+    pub fn from_std(stream: net::UnixStream) -> io::Result<UnixStream> {
+        sys::net::UnixStream::from_stream(stream).map(UnixStream::new)
+    }
+
+    /// Deregisters the stream from its reactor and returns it as a
+    /// `std::os::unix::net::UnixStream`, still in nonblocking mode.
+    pub fn into_std(self) -> io::Result<net::UnixStream> {
+        let io = self.io.into_inner()?;
+        Ok(unsafe { net::UnixStream::from_raw_fd(io.into_raw_fd()) })
+    }
+
+    /// Registers the stream with the reactor of `handle`, e.g. the
+    /// [`high_priority`] one, instead of the reactor of the task which
+    /// polls it first.
+    ///
+    /// Fails with `AlreadyExists` if the stream was polled already.
+    ///
+    /// [`high_priority`]: ../driver/fn.high_priority.html
+    pub fn register_with(&self, handle: &Handle) -> io::Result<()> {
+        self.io.register_with(handle)
+    }
+
+    /// Returns the bytes read and written by the stream and the errors it
+    /// returned, if it was opened while [`metrics`] were enabled.
+    ///
+    /// [`metrics`]: ../metrics/index.html
+    pub fn io_stats(&self) -> SocketStats {
+        self.metrics.stats()
+    }
+
+    /// Returns the id the stream was reported with to the
+    /// [`ConnectionObserver`] of its listener, if it was accepted by a
+    /// listener with one.
+    ///
+    /// [`ConnectionObserver`]: ../transport/trait.ConnectionObserver.html
+    pub fn connection_id(&self) -> Option<u64> {
+        self.close_hook.as_ref().map(CloseHook::id)
+    }
+
+    pub(crate) fn set_close_hook(&mut self, hook: CloseHook) {
+        self.close_hook = Some(hook);
     }
 
     /// Returns the socket address of the local half of this connection.
@@ -163,25 +223,111 @@ impl UnixStream {
     pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
         self.io.get_ref().shutdown(how)
     }
+
+    /// Writes all of `bufs` to the stream, with one `writev` per write the
+    /// socket accepts rather than one write per buffer.
+    ///
+    /// See [`io::write_all_vectored`] for how partial writes are handled.
+    ///
+    /// [`io::write_all_vectored`]: ../io/fn.write_all_vectored.html
+    pub async fn write_all_vectored(
+        &mut self,
+        bufs: &mut [IoSlice<'_>],
+    ) -> io::Result<()> {
+        crate::io::write_all_vectored(self, bufs).await
+    }
+
+    /// Tries to read into `buf` right away, without waiting.
+    ///
+    /// Fails with `WouldBlock` if there is nothing to read. No task is
+    /// registered for wakeup then, so this doesn't steal the wakeups of a
+    /// task reading the stream; wait with [`poll_read_ready`] to retry.
+    ///
+    /// [`poll_read_ready`]: #method.poll_read_ready
+    pub fn try_read(&self, buf: &mut [u8]) -> io::Result<usize> {
+        let res = self.io.try_read_io(|io| (&*io).read(buf));
+        let poll = self.metrics.read(pending_on_wouldblock(res));
+        wouldblock_on_pending(annotate(&self.io, Operation::Read, poll))
+    }
+
+    /// Tries to read into `bufs` right away, like [`try_read`].
+    ///
+    /// [`try_read`]: #method.try_read
+    pub fn try_read_vectored(&self, bufs: &mut [IoSliceMut<'_>]) -> io::Result<usize> {
+        let res = self.io.try_read_io(|io| (&*io).read_vectored(bufs));
+        let poll = self.metrics.read(pending_on_wouldblock(res));
+        wouldblock_on_pending(annotate(&self.io, Operation::Read, poll))
+    }
+
+    /// Tries to write `buf` right away, without waiting.
+    ///
+    /// Fails with `WouldBlock` if the socket's send buffer is full, without
+    /// registering a task for wakeup, see [`try_read`].
+    ///
+    /// [`try_read`]: #method.try_read
+    pub fn try_write(&self, buf: &[u8]) -> io::Result<usize> {
+        let res = self.io.try_write_io(|io| (&*io).write(buf));
+        let poll = self.metrics.written(pending_on_wouldblock(res));
+        wouldblock_on_pending(annotate(&self.io, Operation::Write, poll))
+    }
+
+    /// Shuts down the write half of the stream, so the peer reads the end
+    /// of the stream. A peer which already went away is not an error.
+    fn poll_close_priv(&self) -> Poll<io::Result<()>> {
+        match self.shutdown(Shutdown::Write) {
+            Err(ref e) if e.kind() == io::ErrorKind::NotConnected => Poll::Ready(Ok(())),
+            res => Poll::Ready(res),
+        }
+    }
+}
+
+/// Adds the context of the stream to the error of `poll`, see
+/// `error::set_context_enabled`.
+fn annotate<T>(
+    io: &PollEvented<sys::net::UnixStream>,
+    operation: Operation,
+    poll: Poll<io::Result<T>>,
+) -> Poll<io::Result<T>> {
+    error::annotate(poll, operation, || {
+        let local = io.get_ref().local_addr().ok();
+        let peer = io.get_ref().peer_addr().ok();
+        (local.map(Addr::Unix), peer.map(Addr::Unix))
+    })
 }
 
 impl AsyncRead for UnixStream {
     fn poll_read(
-        mut self: Pin<&mut Self>,
+        self: Pin<&mut Self>,
         cx: &mut Context<'_>,
         buf: &mut [u8],
     ) -> Poll<io::Result<usize>> {
-        Pin::new(&mut self.io).poll_read(cx, buf)
+        let this = self.get_mut();
+        let poll = this.metrics.read(Pin::new(&mut this.io).poll_read(cx, buf));
+        annotate(&this.io, Operation::Read, poll)
     }
 }
 
 impl AsyncWrite for UnixStream {
     fn poll_write(
-        mut self: Pin<&mut Self>,
+        self: Pin<&mut Self>,
         cx: &mut Context<'_>,
         buf: &[u8],
     ) -> Poll<io::Result<usize>> {
-        Pin::new(&mut self.io).poll_write(cx, buf)
+        let this = self.get_mut();
+        let poll = this
+            .metrics
+            .written(Pin::new(&mut this.io).poll_write(cx, buf));
+        annotate(&this.io, Operation::Write, poll)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let poll = Pin::new(&mut this.io).poll_write_vectored(cx, bufs);
+        annotate(&this.io, Operation::Write, this.metrics.written(poll))
     }
 
     fn poll_flush(
@@ -191,11 +337,60 @@ impl AsyncWrite for UnixStream {
         Pin::new(&mut self.io).poll_flush(cx)
     }
 
-    fn poll_close(
-        mut self: Pin<&mut Self>,
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_close_priv()
+    }
+}
+
+/// Reads through a shared reference, like `Read` for
+/// `&std::os::unix::net::UnixStream`.
+///
+/// One task can read while another writes, e.g. with the stream behind an
+/// `Arc`. Readiness is tracked per direction, so when several tasks read at
+/// the same time, only the last one to wait is woken up.
+impl AsyncRead for &UnixStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
         cx: &mut Context<'_>,
-    ) -> Poll<io::Result<()>> {
-        Pin::new(&mut self.io).poll_close(cx)
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let poll = self.metrics.read(self.io.poll_read_ref(cx, buf));
+        annotate(&self.io, Operation::Read, poll)
+    }
+}
+
+/// Writes through a shared reference, like `Write` for
+/// `&std::os::unix::net::UnixStream`.
+///
+/// When several tasks write at the same time, only the last one to wait is
+/// woken up.
+impl AsyncWrite for &UnixStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let poll = self.metrics.written(self.io.poll_write_ref(cx, buf));
+        annotate(&self.io, Operation::Write, poll)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        let poll = self
+            .metrics
+            .written(self.io.poll_write_vectored_ref(cx, bufs));
+        annotate(&self.io, Operation::Write, poll)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.io.poll_flush_ref(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_close_priv()
     }
 }
 
@@ -249,6 +444,14 @@ impl TakeError for UnixStream {
     }
 }
 
+impl std::convert::TryFrom<net::UnixStream> for UnixStream {
+    type Error = io::Error;
+
+    fn try_from(stream: net::UnixStream) -> Result<Self, Self::Error> {
+        UnixStream::from_std(stream)
+    }
+}
+
 impl fmt::Debug for UnixStream {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         self.io.get_ref().fmt(f)
@@ -272,10 +475,17 @@ impl Future for ConnectFuture {
 
         match self.inner {
             State::Waiting(ref mut stream) => {
-                ready!(stream.io.poll_write_ready(cx)?);
-
-                if let Some(e) = stream.io.get_ref().take_error()? {
-                    return Poll::Ready(Err(e));
+                let connected = ready!(stream.io.poll_write_ready(cx))
+                    .and_then(|_| stream.io.get_ref().take_error());
+                match connected {
+                    Ok(None) => {}
+                    Ok(Some(e)) | Err(e) => {
+                        return annotate(
+                            &stream.io,
+                            Operation::Connect,
+                            Poll::Ready(Err(e)),
+                        )
+                    }
                 }
             }
             State::Error(_) => {
@@ -295,3 +505,81 @@ impl Future for ConnectFuture {
         }
     }
 }
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_shared_reference_reads_while_writing() {
+    use crate::runtime::{self, Runtime};
+    use futures_util::future;
+    use futures_util::io::{AsyncReadExt, AsyncWriteExt};
+    use std::sync::Arc;
+
+    let mut rt = runtime::default();
+    rt.exec(async {
+        let (a, b) = UnixStream::pair().unwrap();
+        let a = Arc::new(a);
+        let mut b = b;
+
+        // One task reads through `&UnixStream` while another writes through
+        // a second reference to the same stream.
+        let reader = {
+            let a = a.clone();
+            async move {
+                let mut buf = [0; 4];
+                (&*a).read_exact(&mut buf).await.unwrap();
+                buf
+            }
+        };
+        let writer = async {
+            (&*a).write_all(b"ping").await.unwrap();
+            let mut buf = [0; 4];
+            b.read_exact(&mut buf).await.unwrap();
+            b.write_all(b"pong").await.unwrap();
+            buf
+        };
+        let (read, written) = future::join(reader, writer).await;
+        assert_eq!((&read, &written), (b"pong", b"ping"));
+    });
+}
+
+#[test]
+fn test_try_read_and_write_do_not_wait() {
+    let (a, b) = UnixStream::pair().unwrap();
+
+    let mut buf = [0; 4];
+    let err = b.try_read(&mut buf).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+
+    assert_eq!(a.try_write(b"pingpong").unwrap(), 8);
+    assert_eq!(b.try_read(&mut buf).unwrap(), 4);
+    assert_eq!(&buf, b"ping");
+    let (mut x, mut y) = ([0; 2], [0; 2]);
+    let mut bufs = [IoSliceMut::new(&mut x), IoSliceMut::new(&mut y)];
+    assert_eq!(b.try_read_vectored(&mut bufs).unwrap(), 4);
+    assert_eq!((&x, &y), (b"po", b"ng"));
+    assert_eq!(b.io_stats().errors, 0);
+}
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_close_shuts_down_the_write_half() {
+    use crate::runtime::{self, Runtime};
+    use futures_util::io::{AsyncReadExt, AsyncWriteExt};
+
+    let mut rt = runtime::default();
+    rt.exec(async {
+        let (mut a, mut b) = UnixStream::pair().unwrap();
+
+        a.write_all(b"ping").await.unwrap();
+        a.close().await.unwrap();
+        let mut buf = Vec::new();
+        b.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+
+        // The read half is still open.
+        b.write_all(b"pong").await.unwrap();
+        let mut buf = [0; 4];
+        a.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"pong");
+    });
+}
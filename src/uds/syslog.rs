@@ -0,0 +1,206 @@
+//! Logging to the local syslog daemon.
+//!
+//! [`Syslog`] sends messages to `/dev/log`, the datagram socket of
+//! `syslogd`, `rsyslog` or `journald`, in the BSD format those daemons all
+//! accept. The daemon can be restarted while the logger is in use: the
+//! socket is reconnected when sending fails because the old one went away.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use futures_net::uds::syslog::{Facility, Severity, Syslog};
+//!
+//! # async fn run() -> std::io::Result<()> {
+//! let mut log = Syslog::new("my-daemon").facility(Facility::Daemon);
+//! log.send(Severity::Info, "started").await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`Syslog`]: struct.Syslog.html
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use super::UnixDatagram;
+
+/// Messages longer than this are truncated by default.
+const DEFAULT_MAX_LEN: usize = 8192;
+
+/// The facility of a message, which tells the daemon where it comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[allow(missing_docs)]
+pub enum Facility {
+    Kern = 0,
+    User = 1,
+    Mail = 2,
+    Daemon = 3,
+    Auth = 4,
+    Syslog = 5,
+    Lpr = 6,
+    News = 7,
+    Uucp = 8,
+    Cron = 9,
+    Authpriv = 10,
+    Ftp = 11,
+    Local0 = 16,
+    Local1 = 17,
+    Local2 = 18,
+    Local3 = 19,
+    Local4 = 20,
+    Local5 = 21,
+    Local6 = 22,
+    Local7 = 23,
+}
+
+/// The severity of a message, from the most to the least severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[allow(missing_docs)]
+pub enum Severity {
+    Emergency = 0,
+    Alert = 1,
+    Critical = 2,
+    Error = 3,
+    Warning = 4,
+    Notice = 5,
+    Info = 6,
+    Debug = 7,
+}
+
+/// A logger sending to the local syslog daemon.
+///
+/// The socket is connected on the first message.
+#[derive(Debug)]
+pub struct Syslog {
+    path: PathBuf,
+    socket: Option<UnixDatagram>,
+    tag: String,
+    facility: Facility,
+    max_len: usize,
+    buf: Vec<u8>,
+}
+
+impl Syslog {
+    /// Creates a logger for `/dev/log`, tagging messages with `tag`, usually
+    /// the name of the program, and with the process id.
+    pub fn new(tag: impl Into<String>) -> Syslog {
+        Syslog::with_path("/dev/log", tag)
+    }
+
+    /// Creates a logger for the daemon listening at `path`.
+    pub fn with_path(path: impl AsRef<Path>, tag: impl Into<String>) -> Syslog {
+        Syslog {
+            path: path.as_ref().to_owned(),
+            socket: None,
+            tag: tag.into(),
+            facility: Facility::User,
+            max_len: DEFAULT_MAX_LEN,
+            buf: Vec::new(),
+        }
+    }
+
+    /// Sets the facility of the messages, `User` by default.
+    pub fn facility(mut self, facility: Facility) -> Syslog {
+        self.facility = facility;
+        self
+    }
+
+    /// Sets the size in bytes, header included, above which messages are
+    /// truncated. Defaults to 8192, which `rsyslog` and `journald` accept.
+    pub fn max_len(mut self, max_len: usize) -> Syslog {
+        self.max_len = max_len;
+        self
+    }
+
+    /// Sends `msg` with the given severity.
+    ///
+    /// If the daemon went away since the last message, the socket is
+    /// reconnected once before giving up.
+    pub async fn send(&mut self, severity: Severity, msg: &str) -> io::Result<()> {
+        self.format(severity, msg);
+
+        let mut reconnected = self.socket.is_none();
+        loop {
+            if self.socket.is_none() {
+                let socket = UnixDatagram::unbound()?;
+                socket.connect(&self.path)?;
+                self.socket = Some(socket);
+            }
+            let socket = self.socket.as_mut().unwrap();
+            match socket.send(&self.buf).await {
+                Ok(_) => return Ok(()),
+                Err(ref e) if !reconnected && is_disconnect(e) => {
+                    self.socket = None;
+                    reconnected = true;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn format(&mut self, severity: Severity, msg: &str) {
+        use std::io::Write;
+
+        let pri = (self.facility as u8) << 3 | severity as u8;
+        self.buf.clear();
+        // Writing to a `Vec` can't fail.
+        let _ = write!(self.buf, "<{}>{}[{}]: ", pri, self.tag, process::id());
+        self.buf
+            .extend_from_slice(msg.trim_end_matches('\n').as_bytes());
+
+        if self.buf.len() > self.max_len {
+            let mut len = self.max_len;
+            // Don't split a character, daemons may reject invalid UTF-8.
+            while len > 0 && self.buf[len] & 0xc0 == 0x80 {
+                len -= 1;
+            }
+            self.buf.truncate(len);
+        }
+    }
+}
+
+/// Returns `true` if the error means the daemon closed its socket, e.g.
+/// because it restarted.
+fn is_disconnect(err: &io::Error) -> bool {
+    match err.raw_os_error() {
+        Some(libc::ENOTCONN) | Some(libc::ECONNREFUSED) | Some(libc::ECONNRESET) => true,
+        _ => false,
+    }
+}
+
+#[cfg(feature = "runtime")]
+#[test]
+fn test_reconnects_after_daemon_restart() {
+    use crate::runtime::{self, Runtime};
+
+    let dir = tempdir::TempDir::new("syslog").unwrap();
+    let path = dir.path().join("log");
+    let prefix = format!("<30>test[{}]: ", process::id());
+
+    let mut rt = runtime::default();
+    rt.exec(async {
+        let mut daemon = UnixDatagram::bind(&path).unwrap();
+        let mut log = Syslog::with_path(&path, "test")
+            .facility(Facility::Daemon)
+            .max_len(prefix.len() + 4);
+        let mut buf = [0; 256];
+
+        log.send(Severity::Info, "hello\n").await.unwrap();
+        let (n, _) = daemon.recv_from(&mut buf).await.unwrap();
+        assert_eq!(&buf[..n], format!("{}hell", prefix).as_bytes());
+
+        // A multi-byte character over the limit is dropped whole.
+        log.send(Severity::Info, "abc\u{e9}").await.unwrap();
+        let (n, _) = daemon.recv_from(&mut buf).await.unwrap();
+        assert_eq!(&buf[..n], format!("{}abc", prefix).as_bytes());
+
+        drop(daemon);
+        std::fs::remove_file(&path).unwrap();
+        let mut daemon = UnixDatagram::bind(&path).unwrap();
+
+        log.send(Severity::Info, "back").await.unwrap();
+        let (n, _) = daemon.recv_from(&mut buf).await.unwrap();
+        assert_eq!(&buf[..n], format!("{}back", prefix).as_bytes());
+    });
+}
@@ -0,0 +1,315 @@
+//! Experimental `AF_XDP` sockets.
+//!
+//! An [`XdpSocket`] exchanges frames with a network interface queue through
+//! memory shared with the kernel, the UMEM, skipping most of the network
+//! stack. The UMEM is split into frames of a fixed size, identified by
+//! their offset, and ownership of the frames moves between the process and
+//! the kernel through four rings:
+//!
+//! * frames put on the fill ring with [`fill`] receive packets, which come
+//!   back as descriptors from [`recv`];
+//! * frames written and queued with [`send`] are transmitted, and their
+//!   offsets come back from [`completed`].
+//!
+//! The process keeps track of which frames it owns. Receiving also needs an
+//! XDP program on the interface redirecting packets to the socket, through
+//! an `XSKMAP`, which this module doesn't load.
+//!
+//! The API is experimental and may change in minor releases. Opening a
+//! socket requires the `CAP_NET_RAW` capability.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use futures_net::xdp::{XdpDesc, XdpMode, XdpSocket};
+//!
+//! # async fn run() -> std::io::Result<()> {
+//! let mut socket = XdpSocket::new(4096, 2048, 2048)?;
+//! socket.bind("eth0", 0, XdpMode::Auto)?;
+//!
+//! // Give the first half of the frames to the kernel for reception.
+//! let frames: Vec<u64> = (0..2048).map(|i| i * 2048).collect();
+//! socket.fill(&frames);
+//!
+//! let mut descs = [XdpDesc::default(); 64];
+//! loop {
+//!     let n = socket.recv(&mut descs).await?;
+//!     for desc in &descs[..n] {
+//!         println!("{} bytes", socket.frame(desc).len());
+//!     }
+//!     // Recycle the frames.
+//!     let addrs: Vec<u64> = descs[..n].iter().map(|d| d.addr() & !2047).collect();
+//!     socket.fill(&addrs);
+//! }
+//! # }
+//! ```
+//!
+//! [`XdpSocket`]: struct.XdpSocket.html
+//! [`fill`]: struct.XdpSocket.html#method.fill
+//! [`recv`]: struct.XdpSocket.html#method.recv
+//! [`send`]: struct.XdpSocket.html#method.send
+//! [`completed`]: struct.XdpSocket.html#method.completed
+
+use async_ready::{AsyncReadReady, TakeError};
+use futures_util::ready;
+use std::fmt;
+use std::io;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use crate::driver::sys;
+use crate::driver::PollEvented;
+
+/// How the kernel moves frames between the driver and the UMEM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum XdpMode {
+    /// Zero-copy if the driver supports it, copies otherwise.
+    Auto,
+    /// Always copy frames (`XDP_COPY`).
+    Copy,
+    /// The driver works on the UMEM directly, failing to bind if it can't
+    /// (`XDP_ZEROCOPY`).
+    ZeroCopy,
+}
+
+/// A frame of the UMEM, as found on the RX and TX rings.
+#[repr(transparent)]
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct XdpDesc {
+    raw: sys::net::XdpDesc,
+}
+
+/// An `AF_XDP` socket, with its UMEM and rings.
+pub struct XdpSocket {
+    io: PollEvented<sys::net::XdpSocket>,
+}
+
+impl XdpSocket {
+    /// Opens a socket with a UMEM of `frame_count` frames of `frame_size`
+    /// bytes each, and rings of `ring_size` entries.
+    ///
+    /// `frame_size` must be a power of two between 2048 and the page size,
+    /// and `ring_size` a power of two.
+    pub fn new(
+        frame_count: u32,
+        frame_size: u32,
+        ring_size: u32,
+    ) -> io::Result<XdpSocket> {
+        let socket = sys::net::XdpSocket::new(frame_count, frame_size, ring_size)?;
+        Ok(XdpSocket {
+            io: PollEvented::new(socket),
+        })
+    }
+
+    /// Attaches the socket to the queue `queue_id` of the interface called
+    /// `name`.
+    pub fn bind(&self, name: &str, queue_id: u32, mode: XdpMode) -> io::Result<()> {
+        let ifindex = sys::net::interface_index(name)?;
+        let flags = match mode {
+            XdpMode::Auto => 0,
+            XdpMode::Copy => sys::net::XDP_COPY,
+            XdpMode::ZeroCopy => sys::net::XDP_ZEROCOPY,
+        };
+        self.io
+            .get_ref()
+            .bind(ifindex, queue_id, flags | sys::net::XDP_USE_NEED_WAKEUP)
+    }
+
+    /// Returns the size of the frames of the UMEM.
+    pub fn frame_size(&self) -> u32 {
+        self.io.get_ref().frame_size()
+    }
+
+    /// Returns the bytes of a received frame.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `desc` lies outside of the UMEM.
+    pub fn frame(&self, desc: &XdpDesc) -> &[u8] {
+        let start = desc.raw.addr as usize;
+        &self.io.get_ref().umem()[start..start + desc.raw.len as usize]
+    }
+
+    /// Returns the frame of the UMEM starting at offset `addr`, to write a
+    /// packet into before sending it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `addr` lies outside of the UMEM.
+    pub fn frame_mut(&mut self, addr: u64) -> &mut [u8] {
+        let size = self.frame_size() as usize;
+        let start = addr as usize;
+        let umem = self.io.get_mut().umem_mut();
+        let end = umem.len().min(start - start % size + size);
+        &mut umem[start..end]
+    }
+
+    /// Hands the frames at offsets `addrs` to the kernel to receive packets
+    /// into, returning how many fit in the fill ring.
+    pub fn fill(&mut self, addrs: &[u64]) -> usize {
+        self.io.get_mut().fill(addrs)
+    }
+
+    /// Waits for received frames, and moves up to `descs.len()` of them
+    /// into `descs`.
+    pub fn poll_recv(
+        &mut self,
+        cx: &mut Context<'_>,
+        descs: &mut [XdpDesc],
+    ) -> Poll<io::Result<usize>> {
+        if descs.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        ready!(Pin::new(&mut self.io).poll_read_ready(cx)?);
+
+        // Safety: `XdpDesc` is a transparent wrapper.
+        let raw = unsafe {
+            std::slice::from_raw_parts_mut(
+                descs.as_mut_ptr() as *mut sys::net::XdpDesc,
+                descs.len(),
+            )
+        };
+        match self.io.get_mut().recv(raw) {
+            0 => {
+                Pin::new(&mut self.io).clear_read_ready(cx)?;
+                Poll::Pending
+            }
+            n => Poll::Ready(Ok(n)),
+        }
+    }
+
+    /// Waits for received frames, see [`poll_recv`].
+    ///
+    /// [`poll_recv`]: #method.poll_recv
+    pub async fn recv(&mut self, descs: &mut [XdpDesc]) -> io::Result<usize> {
+        futures_util::future::poll_fn(|cx| self.poll_recv(cx, descs)).await
+    }
+
+    /// Queues frames for transmission, returning how many fit in the TX
+    /// ring. The frames belong to the kernel until they are returned by
+    /// [`completed`].
+    ///
+    /// [`completed`]: #method.completed
+    pub fn send(&mut self, descs: &[XdpDesc]) -> io::Result<usize> {
+        // Safety: `XdpDesc` is a transparent wrapper.
+        let raw = unsafe {
+            std::slice::from_raw_parts(
+                descs.as_ptr() as *const sys::net::XdpDesc,
+                descs.len(),
+            )
+        };
+        self.io.get_mut().send(raw)
+    }
+
+    /// Moves the offsets of transmitted frames into `addrs`, returning
+    /// their number.
+    pub fn completed(&mut self, addrs: &mut [u64]) -> usize {
+        self.io.get_mut().complete(addrs)
+    }
+}
+
+impl AsyncReadReady for XdpSocket {
+    type Ok = sys::event::Ready;
+    type Err = io::Error;
+
+    /// Poll the socket's readiness for reading.
+    fn poll_read_ready(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Self::Ok, Self::Err>> {
+        Pin::new(&mut self.io).poll_read_ready(cx)
+    }
+}
+
+impl TakeError for XdpSocket {
+    type Ok = io::Error;
+    type Err = io::Error;
+
+    /// Returns the value of the `SO_ERROR` option.
+    fn take_error(&self) -> Result<Option<Self::Ok>, Self::Err> {
+        self.io.get_ref().take_error()
+    }
+}
+
+impl fmt::Debug for XdpSocket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.io.get_ref().fmt(f)
+    }
+}
+
+impl AsRawFd for XdpSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.io.get_ref().as_raw_fd()
+    }
+}
+
+impl XdpDesc {
+    /// Describes the `len` bytes at offset `addr` of the UMEM.
+    pub fn new(addr: u64, len: u32) -> XdpDesc {
+        XdpDesc {
+            raw: sys::net::XdpDesc {
+                addr,
+                len,
+                options: 0,
+            },
+        }
+    }
+
+    /// Returns the offset of the packet in the UMEM.
+    pub fn addr(&self) -> u64 {
+        self.raw.addr
+    }
+
+    /// Returns the length of the packet.
+    pub fn len(&self) -> u32 {
+        self.raw.len
+    }
+}
+
+impl fmt::Debug for XdpDesc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("XdpDesc")
+            .field("addr", &self.raw.addr)
+            .field("len", &self.raw.len)
+            .finish()
+    }
+}
+
+#[test]
+fn test_transmitted_frames_complete() {
+    let mut socket = match XdpSocket::new(64, 2048, 64) {
+        Ok(socket) => socket,
+        // Needs `CAP_NET_RAW` and a kernel built with `AF_XDP`.
+        Err(ref e)
+            if e.kind() == io::ErrorKind::PermissionDenied
+                || e.raw_os_error() == Some(libc::EAFNOSUPPORT) =>
+        {
+            return
+        }
+        Err(e) => panic!("{}", e),
+    };
+    socket.bind("lo", 0, XdpMode::Copy).unwrap();
+
+    // An Ethernet broadcast frame with an unassigned ethertype.
+    let frame = socket.frame_mut(2048);
+    frame[..12].copy_from_slice(&[0xff; 12]);
+    frame[12..14].copy_from_slice(&[0x88, 0xb5]);
+    assert_eq!(socket.send(&[XdpDesc::new(2048, 60)]).unwrap(), 1);
+
+    let mut addrs = [0; 4];
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+    loop {
+        match socket.completed(&mut addrs) {
+            0 if std::time::Instant::now() < deadline => {
+                // Kick the kernel again in case it was busy.
+                socket.send(&[]).unwrap();
+                std::thread::yield_now();
+            }
+            n => {
+                assert_eq!(&addrs[..n], &[2048]);
+                break;
+            }
+        }
+    }
+}